@@ -1,6 +1,7 @@
 use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
-use gnosis_vpn_lib::command::Command as LibCommand;
-use gnosis_vpn_lib::socket;
+use gnosis_vpn_lib::command::{Command as LibCommand, Secret};
+use gnosis_vpn_lib::{config, socket};
+use human_bandwidth::re::bandwidth::Bandwidth;
 use std::path::PathBuf;
 
 #[derive(Clone, Copy, Debug, ValueEnum)]
@@ -17,14 +18,27 @@ pub struct Cli {
     #[command(subcommand)]
     pub command: Command,
 
-    /// Specify socket path
+    /// Specify socket path, prefix with `@` for a Linux abstract socket
+    /// [default: `[socket] path` from the configuration file or /var/run/gnosisvpn.sock]
     #[arg(
         short,
         long,
         env = socket::root::ENV_VAR,
-        default_value = socket::root::DEFAULT_PATH
     )]
-    pub socket_path: PathBuf,
+    pub socket_path: Option<PathBuf>,
+
+    /// Service configuration file, consulted for the socket path
+    #[arg(
+        long,
+        env = config::ENV_VAR,
+        default_value = config::DEFAULT_PATH,
+    )]
+    pub config_path: PathBuf,
+
+    /// Control a service on another machine over TLS instead of the local socket, e.g. `vpn-box:7475`
+    /// Pair once with the `pair` command before sending other commands
+    #[arg(long, value_name = "HOST:PORT", env = socket::remote::ENV_VAR)]
+    pub remote: Option<String>,
 
     /// Output format applied to every command
     #[arg(short = 'o', long = "output", value_name = "FORMAT", value_enum)]
@@ -35,28 +49,76 @@ pub struct Cli {
 pub enum Command {
     /// Query current service status
     #[command()]
-    Status {},
+    Status {
+        /// Keep refreshing the status, every second unless an interval is given
+        #[arg(short, long, value_name = "INTERVAL", num_args = 0..=1, default_missing_value = "1s")]
+        watch: Option<humantime::Duration>,
+    },
 
     /// Connect to this exit location
     #[command()]
     Connect {
-        /// Endpoint node address
-        id: String,
+        /// Destination id or name of a destination group
+        #[arg(required_unless_present = "any", conflicts_with = "any")]
+        id: Option<String>,
+        /// Destination or group to fail over to once the previous ones failed, may be repeated
+        #[arg(long, value_name = "ID", requires = "id")]
+        fallback: Vec<String>,
+        /// Connect to whichever destination is ready, the one with the fastest exit wins
+        #[arg(long)]
+        any: bool,
+        /// Skip destinations that failed to connect within the last 15 minutes
+        #[arg(long, requires = "any")]
+        avoid_failed: bool,
+        /// Pick a new destination instead of returning to the one picked within `connection.affinity_ttl`
+        #[arg(long, requires = "any")]
+        rotate: bool,
+        /// Keep retrying for up to this long while the local service is not running, e.g. restarting after an upgrade;
+        /// not available with --remote
+        #[arg(long, value_name = "DURATION")]
+        wait: Option<humantime::Duration>,
     },
 
     /// Disconnect from current exit location
     #[command()]
-    Disconnect {},
+    Disconnect {
+        /// Keep retrying for up to this long while the local service is not running, e.g. restarting after an upgrade;
+        /// not available with --remote
+        #[arg(long, value_name = "DURATION")]
+        wait: Option<humantime::Duration>,
+    },
+
+    /// Abort a pending connection attempt without touching an established connection
+    #[command()]
+    CancelConnect {},
 
     /// Query balance information
     #[command()]
     Balance {},
 
+    /// Show balance snapshots over time, one line per snapshot taken every 15 minutes
+    #[command()]
+    BalanceHistory {
+        /// Number of days to show, snapshots are kept for 30 days
+        #[arg(long, default_value_t = 7)]
+        days: u32,
+    },
+
     /// Trigger a funding tool run to claim funds for your account during onboarding
     #[command()]
     FundingTool {
-        /// Your secret hash
-        secret: String,
+        /// Your secret hash, ends up in shell history - prefer --secret-file or --stdin
+        #[arg(
+            required_unless_present_any = ["secret_file", "stdin"],
+            conflicts_with_all = ["secret_file", "stdin"]
+        )]
+        secret: Option<String>,
+        /// Read the secret hash from this file
+        #[arg(long, value_name = "FILE", conflicts_with = "stdin")]
+        secret_file: Option<PathBuf>,
+        /// Read the secret hash from standard input
+        #[arg(long)]
+        stdin: bool,
     },
 
     /// Solicit a ping response ("pong") from the service and it's worker process to check if it is alive
@@ -67,6 +129,16 @@ pub enum Command {
     #[command()]
     Telemetry {},
 
+    /// Show the anonymous usage telemetry payload or opt in and out
+    ///
+    /// Without argument the current state and the exact payload of the next upload are shown.
+    /// The choice is persisted and applies unless `telemetry = false` is set in the configuration file.
+    #[command()]
+    UsageTelemetry {
+        #[arg(value_enum)]
+        toggle: Option<Toggle>,
+    },
+
     /// Query some nerd stats for connecting/connected destination
     #[command()]
     NerdStats {},
@@ -75,6 +147,75 @@ pub enum Command {
     #[command()]
     Info {},
 
+    /// List HOPR peers the node is connected to, with probe quality, channel and destination details
+    #[command()]
+    Peers {
+        /// Keep refreshing the list, every 5s unless an interval is given
+        #[arg(short, long, value_name = "INTERVAL", num_args = 0..=1, default_missing_value = "5s")]
+        watch: Option<humantime::Duration>,
+    },
+
+    /// List raw HOPR sessions open on the node, marking those backing the current tunnel
+    #[command()]
+    Sessions {},
+
+    /// List on-chain transactions the client sent, such as safe deployment or channel funding, with their outcome
+    #[command()]
+    Transactions {},
+
+    /// List connection outcome reports recently sent to exit operators through the tunnel, including their full content
+    #[command()]
+    ExitReports {},
+
+    /// Print recent connection attempts to a destination as JSON to share with its exit operator
+    ///
+    /// Holds phases, their timing and setback kinds only, no error messages, addresses or keys.
+    #[command()]
+    ReportIssue {
+        /// Destination id
+        destination: String,
+    },
+
+    /// Print recent log output of root and worker
+    #[command()]
+    Logs {
+        /// Keep streaming new lines until interrupted, only on the local socket
+        #[arg(short, long)]
+        follow: bool,
+        /// Number of recent lines to print
+        #[arg(short = 'n', long, default_value_t = 100)]
+        lines: u32,
+    },
+
+    /// Check tooling, socket, routing, identity, safe and chain endpoint, e.g. when connecting keeps failing
+    #[command()]
+    Doctor {},
+
+    /// Remove interface, routes, firewall rules, WireGuard config and socket file a crashed service left behind.
+    /// Needs root, refuses while the service is running
+    #[command()]
+    Cleanup {
+        /// Only list what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Full screen dashboard of destinations, connection, node startup and balances
+    #[command()]
+    Tui {
+        /// Refresh interval
+        #[arg(short, long, default_value = "2s")]
+        refresh: humantime::Duration,
+    },
+
+    /// Show local gateway mode and traffic per LAN client
+    #[command()]
+    Gateway {},
+
+    /// Inspect the routes the service installs for the tunnel
+    #[command(subcommand)]
+    Routing(Routing),
+
     /// Start worker process that runs main connection loop
     /// Needs a keep alive timeout to determine how long to wait for commands before stopping
     /// worker and returning to idle mode
@@ -90,6 +231,24 @@ pub enum Command {
     #[command()]
     StopClient {},
 
+    /// Snapshot node database, identity and safe configuration into a tarball
+    ///
+    /// A running worker is stopped for the snapshot and started again afterwards. The archive
+    /// holds the identity pass, it is created readable only by the calling user. Only accepted on
+    /// the local service socket. Restore it by starting the service with `--restore-backup <PATH>`.
+    #[command()]
+    Backup {
+        /// Archive to create, must not exist yet
+        path: PathBuf,
+    },
+
+    /// Compact the node databases and remove rotated service logs
+    ///
+    /// A running worker is stopped for the compaction and started again afterwards.
+    /// Requires `sqlite3` on the host.
+    #[command()]
+    Compact {},
+
     /// Fetch and display the latest available version from the update manifest
     ///
     /// Refuses to run unless the VPN is connected. Pass --force to bypass the connection check.
@@ -100,32 +259,188 @@ pub enum Command {
         force: bool,
     },
 
+    /// Pair with the service given by --remote
+    ///
+    /// The service logs a six digit code and its certificate fingerprint. Enter the code here
+    /// after checking the fingerprint matches.
+    #[command()]
+    Pair {
+        /// Name the service lists this client under
+        #[arg(long, default_value = "gnosis_vpn-ctl")]
+        name: String,
+    },
+
+    /// Revoke a paired remote client
+    ///
+    /// Every client paired under this name has to pair again before sending commands.
+    /// Only accepted on the local socket.
+    #[command()]
+    Unpair {
+        /// Name the client paired under
+        name: String,
+    },
+
+    /// Send a command given as JSON and print the service response as JSON
+    ///
+    /// The command is serialized as on the control socket, e.g. '"Status"' or '{"Connect":"Germany"}'.
+    /// Pass --schema instead to print the JSON schema of all commands and responses.
+    #[command()]
+    Raw {
+        /// Serialized command
+        #[arg(required_unless_present = "schema", conflicts_with = "schema")]
+        json: Option<String>,
+        /// Print the JSON schema of commands and responses without contacting the service
+        #[arg(long)]
+        schema: bool,
+    },
+
     /// Print shell completion script for the given shell to stdout
     #[command(hide = true)]
     Completions { shell: clap_complete::Shell },
 
-    /// List configured destination IDs, one per line
-    #[command(hide = true)]
-    Destinations {},
+    /// List configured destination IDs, one per line, or share destinations between machines
+    #[command()]
+    Destinations {
+        #[command(subcommand)]
+        action: Option<Destinations>,
+    },
+
+    /// Query or set the egress rate limit on the tunnel
+    ///
+    /// Without argument the current limit is shown. The limit stays until the service restarts or a
+    /// configuration reload changes `egress_rate_limit`, other configuration changes keep it.
+    #[command()]
+    RateLimit {
+        /// New limit, e.g. "2 Mb/s", or "off" to remove the limit
+        #[arg(value_parser = parse_rate_limit)]
+        limit: Option<RateLimit>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Routing {
+    /// List the routes and firewall tables a tunnel setup applies next to their current state
+    ///
+    /// Routes marked with `!` differ from what the service expects, e.g. leftovers of a
+    /// previous run while disconnected or routes missing while connected.
+    #[command()]
+    Explain {},
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Destinations {
+    /// Print all destinations with their options as configuration file tables
+    ///
+    /// Access tokens of private exits are included, e.g. `gnosis_vpn-ctl destinations export > dests.toml`.
+    #[command()]
+    Export {},
+
+    /// Add the destinations of an export to the configuration file
+    ///
+    /// Destinations already configured identically are skipped. The import is refused if one is
+    /// configured differently. The service reloads its configuration afterwards.
+    #[command()]
+    Import {
+        /// Export to read, standard input if omitted
+        file: Option<PathBuf>,
+        /// Content of `file`, read before socket dispatch
+        #[arg(skip)]
+        export: Option<String>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Toggle {
+    On,
+    Off,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum RateLimit {
+    Off,
+    Limit(Bandwidth),
+}
+
+fn parse_rate_limit(s: &str) -> Result<RateLimit, String> {
+    if s.eq_ignore_ascii_case("off") {
+        return Ok(RateLimit::Off);
+    }
+    human_bandwidth::parse_bandwidth(s)
+        .map(RateLimit::Limit)
+        .map_err(|e| e.to_string())
+}
+
+impl Command {
+    /// How long to wait for a service that is not running, see `--wait`.
+    pub fn wait(&self) -> Option<std::time::Duration> {
+        match self {
+            Command::Connect { wait, .. } | Command::Disconnect { wait } => wait.map(Into::into),
+            _ => None,
+        }
+    }
 }
 
 impl From<Command> for LibCommand {
     fn from(val: Command) -> Self {
         match val {
-            Command::Status {} => LibCommand::Status,
-            Command::Connect { id } => LibCommand::Connect(id),
-            Command::Disconnect {} => LibCommand::Disconnect,
+            Command::Status { .. } => LibCommand::Status,
+            Command::Connect {
+                id: Some(id), fallback, ..
+            } if !fallback.is_empty() => LibCommand::ConnectFailover(std::iter::once(id).chain(fallback).collect()),
+            Command::Connect { id: Some(id), .. } => LibCommand::Connect(id),
+            Command::Connect {
+                id: None,
+                avoid_failed,
+                rotate,
+                ..
+            } => LibCommand::ConnectAny { avoid_failed, rotate },
+            Command::Disconnect { .. } => LibCommand::Disconnect,
+            Command::CancelConnect {} => LibCommand::CancelConnect,
             Command::Balance {} => LibCommand::Balance,
-            Command::FundingTool { secret } => LibCommand::FundingTool(secret),
+            Command::BalanceHistory { days } => LibCommand::BalanceHistory { days },
+            Command::FundingTool { secret, .. } => LibCommand::FundingTool(Secret::new(
+                secret.expect("funding tool secret is read before socket dispatch"),
+            )),
             Command::Ping {} => LibCommand::Ping,
             Command::Telemetry {} => LibCommand::Telemetry,
+            Command::UsageTelemetry { toggle } => LibCommand::UsageTelemetry(toggle.map(|t| matches!(t, Toggle::On))),
             Command::NerdStats {} => LibCommand::NerdStats,
             Command::Info {} => LibCommand::Info,
+            Command::Peers { .. } => LibCommand::Peers,
+            Command::Sessions {} => LibCommand::Sessions,
+            Command::Transactions {} => LibCommand::Transactions,
+            Command::ExitReports {} => LibCommand::ExitReports,
+            Command::ReportIssue { destination } => LibCommand::ReportIssue { destination },
+            Command::Logs { follow, lines } => LibCommand::Logs { follow, lines },
+            Command::Doctor {} => LibCommand::Diagnose,
+            Command::Gateway {} => LibCommand::Gateway,
+            Command::Routing(Routing::Explain {}) => LibCommand::RoutingExplain,
             Command::StartClient { keep_alive } => LibCommand::StartClient(keep_alive.into()),
             Command::StopClient {} => LibCommand::StopClient,
-            Command::Destinations {} => LibCommand::Destinations,
+            // the service resolves paths relative to its own working directory
+            Command::Backup { path } => LibCommand::Backup(std::path::absolute(&path).unwrap_or(path)),
+            Command::Compact {} => LibCommand::Compact,
+            Command::Unpair { name } => LibCommand::Unpair(name),
+            Command::Destinations { action: None } => LibCommand::Destinations,
+            Command::Destinations {
+                action: Some(Destinations::Export {}),
+            } => LibCommand::ExportDestinations,
+            Command::Destinations {
+                action: Some(Destinations::Import { export, .. }),
+            } => LibCommand::ImportDestinations(export.expect("export is read before socket dispatch")),
+            Command::RateLimit { limit: None } => LibCommand::RateLimit,
+            Command::RateLimit {
+                limit: Some(RateLimit::Off),
+            } => LibCommand::SetRateLimit(None),
+            Command::RateLimit {
+                limit: Some(RateLimit::Limit(limit)),
+            } => LibCommand::SetRateLimit(Some(limit)),
             Command::CheckUpdate { .. } => unreachable!("CheckUpdate is handled before socket dispatch"),
             Command::Completions { .. } => unreachable!("Completions is handled before socket dispatch"),
+            Command::Pair { .. } => unreachable!("Pair is handled before socket dispatch"),
+            Command::Raw { .. } => unreachable!("Raw is handled before socket dispatch"),
+            Command::Tui { .. } => unreachable!("Tui is handled before socket dispatch"),
+            Command::Cleanup { .. } => unreachable!("Cleanup is handled before socket dispatch"),
         }
     }
 }
@@ -1,15 +1,30 @@
 use exitcode::{self, ExitCode};
 
+use std::env;
 use std::fmt;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use gnosis_vpn_lib::balance;
 use gnosis_vpn_lib::check_update;
+use gnosis_vpn_lib::cleanup;
 use gnosis_vpn_lib::command::{self, Command, Response};
+use gnosis_vpn_lib::config;
+use gnosis_vpn_lib::crash;
+use gnosis_vpn_lib::diagnostics;
+use gnosis_vpn_lib::dirs;
+use gnosis_vpn_lib::exit_reports;
+use gnosis_vpn_lib::i18n::{Locale, Message};
+use gnosis_vpn_lib::proxy;
 use gnosis_vpn_lib::socket;
+use gnosis_vpn_lib::socket::remote::CredentialStore;
+use gnosis_vpn_lib::tls;
+use gnosis_vpn_lib::transactions;
 
 mod cli;
+mod tui;
 
 use cli::OutputFormat;
 
@@ -21,8 +36,15 @@ static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
 #[tokio::main]
 async fn main() {
+    crash::install("ctl", None);
     let args = cli::parse();
     let format = args.output.unwrap_or(OutputFormat::Plain);
+    let configured_socket_path = match args.socket_path {
+        Some(_) => None,
+        None => config::read_socket_path(&args.config_path).await,
+    };
+    let socket_path = socket::root::resolve_path(args.socket_path.clone(), configured_socket_path);
+    let locale = Locale::resolve(config::read_locale(&args.config_path).await.as_deref());
 
     if let cli::Command::Completions { shell } = args.command {
         cli::generate_completions(shell);
@@ -30,12 +52,97 @@ async fn main() {
     }
 
     if let cli::Command::CheckUpdate { force } = args.command {
-        let exit = run_check_update(format, &args.socket_path, force).await;
+        let network = config::read_network(&args.config_path).await;
+        let exit = run_check_update(format, &socket_path, &network, force).await;
+        process::exit(exit);
+    }
+
+    if let cli::Command::Cleanup { dry_run } = args.command {
+        if args.remote.is_some() {
+            eprintln!("Cleanup only works on this machine, run it without --remote");
+            process::exit(exitcode::USAGE);
+        }
+        let exit = run_cleanup(&args.config_path, &socket_path, locale, dry_run).await;
+        process::exit(exit);
+    }
+
+    if let cli::Command::Pair { name } = args.command {
+        let exit = run_pair(args.remote.as_deref(), &name).await;
         process::exit(exit);
     }
 
-    let cmd: Command = args.command.into();
-    let resp = match socket::root::process_cmd(&args.socket_path, &cmd).await {
+    let target = match args.remote {
+        Some(addr) => Target::Remote(addr),
+        None => Target::Local(socket_path),
+    };
+
+    if let cli::Command::Raw { json, schema } = args.command {
+        let exit = run_raw(&target, json, schema).await;
+        process::exit(exit);
+    }
+
+    if let cli::Command::Peers { watch: Some(interval) } = args.command {
+        run_watch(format, locale, &target, Command::Peers, interval.into()).await;
+    }
+
+    if let cli::Command::Status { watch: Some(interval) } = args.command {
+        run_watch(format, locale, &target, Command::Status, interval.into()).await;
+    }
+
+    if let cli::Command::Tui { refresh } = args.command {
+        let exit = tui::run(&target, locale, refresh.into()).await;
+        process::exit(exit);
+    }
+
+    if let (cli::Command::Logs { follow: true, lines }, Target::Local(socket_path)) = (&args.command, &target) {
+        let exit = run_follow_logs(socket_path, *lines, locale).await;
+        process::exit(exit);
+    }
+
+    let command = match args.command {
+        cli::Command::FundingTool {
+            secret: None,
+            secret_file,
+            stdin: _,
+        } => match read_secret(secret_file.as_deref()) {
+            Ok(secret) => cli::Command::FundingTool {
+                secret: Some(secret),
+                secret_file: None,
+                stdin: false,
+            },
+            Err(e) => {
+                eprintln!("Unable to read funding tool secret: {e}");
+                process::exit(exitcode::NOINPUT);
+            }
+        },
+        cli::Command::Destinations {
+            action: Some(cli::Destinations::Import { file, export: None }),
+        } => match read_export(file.as_deref()) {
+            Ok(export) => cli::Command::Destinations {
+                action: Some(cli::Destinations::Import {
+                    file: None,
+                    export: Some(export),
+                }),
+            },
+            Err(e) => {
+                eprintln!("Unable to read destinations export: {e}");
+                process::exit(exitcode::NOINPUT);
+            }
+        },
+        command => command,
+    };
+
+    let wait = command.wait();
+    if wait.is_some() && matches!(target, Target::Remote(_)) {
+        eprintln!("--wait only applies to the local service and cannot be combined with --remote");
+        process::exit(exitcode::USAGE);
+    }
+    let cmd: Command = command.into();
+    let res = match wait {
+        Some(wait) => target.process_cmd_waiting(&cmd, wait).await,
+        None => target.process_cmd(&cmd).await,
+    };
+    let resp = match res {
         Ok(resp) => resp,
         Err(e) => {
             eprintln!("Error processing {cmd}: {e}");
@@ -43,18 +150,327 @@ async fn main() {
         }
     };
 
-    match format {
-        OutputFormat::Json => json_print(&resp),
-        OutputFormat::Yaml => yaml_print(&resp),
-        OutputFormat::Plain => pretty_print(&resp),
-    };
+    print_response(format, locale, &resp);
 
     let exit = determine_exitcode(&resp);
     process::exit(exit);
 }
 
-async fn run_check_update(format: OutputFormat, socket_path: &std::path::Path, force: bool) -> ExitCode {
-    let client = match reqwest::Client::builder().timeout(Duration::from_secs(30)).build() {
+/// Pause between connection attempts to a service that is not running.
+const WAIT_POLL: Duration = Duration::from_millis(500);
+
+/// Where commands are sent to.
+enum Target {
+    Local(PathBuf),
+    /// Paired service reached over TLS
+    Remote(String),
+}
+
+impl Target {
+    async fn process_cmd(&self, cmd: &Command) -> Result<Response, String> {
+        match self {
+            Target::Local(socket_path) => socket::root::process_cmd(socket_path, cmd)
+                .await
+                .map_err(|e| e.to_string()),
+            Target::Remote(addr) => {
+                let store = CredentialStore::load(credentials_path()?).map_err(|e| e.to_string())?;
+                let credentials = store
+                    .get(addr)
+                    .ok_or_else(|| socket::remote::Error::NotPaired(addr.clone()).to_string())?;
+                socket::remote::process_cmd(addr, credentials, cmd)
+                    .await
+                    .map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// Like [`Target::process_cmd`] but retries for up to `wait` while the local service is not running.
+    /// Waiting is rejected for a remote target before getting here.
+    async fn process_cmd_waiting(&self, cmd: &Command, wait: Duration) -> Result<Response, String> {
+        let Target::Local(socket_path) = self else {
+            return self.process_cmd(cmd).await;
+        };
+        let deadline = tokio::time::Instant::now() + wait;
+        let mut waiting = false;
+        loop {
+            match socket::root::process_cmd(socket_path, cmd).await {
+                Ok(resp) => {
+                    if waiting {
+                        eprintln!("Service is back, sent {cmd}");
+                    }
+                    return Ok(resp);
+                }
+                Err(e) if e.is_not_running() && tokio::time::Instant::now() < deadline => {
+                    if !waiting {
+                        eprintln!(
+                            "Service not running, retrying {cmd} for up to {}",
+                            humantime::format_duration(wait)
+                        );
+                        waiting = true;
+                    }
+                    tokio::time::sleep(WAIT_POLL).await;
+                }
+                Err(e) if waiting => {
+                    return Err(format!("{e} - gave up after {}", humantime::format_duration(wait)));
+                }
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+    }
+}
+
+fn credentials_path() -> Result<PathBuf, String> {
+    CredentialStore::default_path().ok_or_else(|| "unable to determine configuration directory".to_string())
+}
+
+/// Remove what a crashed service left behind, a running service still owns all of it.
+async fn run_cleanup(config_path: &Path, socket_path: &Path, locale: Locale, dry_run: bool) -> ExitCode {
+    if socket::root::process_cmd(socket_path, &Command::Ping).await.is_ok() {
+        eprintln!("{}", Message::ServiceRunningNoCleanup.text(locale));
+        return exitcode::TEMPFAIL;
+    }
+    let explicit = dirs::Config {
+        data: env::var_os(dirs::ENV_VAR_STATE_HOME).map(PathBuf::from),
+        cache: env::var_os(dirs::ENV_VAR_CACHE_HOME).map(PathBuf::from),
+    };
+    let configured = config::read_dirs(config_path).await;
+    let locations = dirs::Locations::resolve(explicit, &configured, |key| env::var(key).ok());
+    // a hanging service does not answer on the socket but still holds its lock
+    match cleanup::service_running(&dirs::root_home(&locations.state_home)) {
+        Ok(false) => (),
+        Ok(true) => {
+            eprintln!("{}", Message::ServiceRunningNoCleanup.text(locale));
+            return exitcode::TEMPFAIL;
+        }
+        Err(e) => {
+            eprintln!("Unable to check whether the service is running: {e}");
+            return exitcode::NOPERM;
+        }
+    }
+    let leftovers = cleanup::find(&locations.cache_home, socket_path).await;
+    if leftovers.is_empty() {
+        println!("{}", Message::NothingToCleanUp.text(locale));
+        return exitcode::OK;
+    }
+    let mut exit = exitcode::OK;
+    for leftover in &leftovers {
+        if dry_run {
+            println!("Would remove {leftover}");
+            continue;
+        }
+        match cleanup::remove(leftover).await {
+            Ok(()) => println!("Removed {leftover}"),
+            Err(e) => {
+                eprintln!("Failed to remove {leftover}: {e}");
+                exit = exitcode::OSERR;
+            }
+        }
+    }
+    exit
+}
+
+async fn run_pair(remote: Option<&str>, name: &str) -> ExitCode {
+    let Some(addr) = remote else {
+        eprintln!("Pairing needs the service address, pass --remote <HOST:PORT>");
+        return exitcode::USAGE;
+    };
+    let path = match credentials_path() {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("{e}");
+            return exitcode::CONFIG;
+        }
+    };
+    println!("Requested pairing with {addr} - the service logs a six digit code");
+    let read_code = |fingerprint: &str, denied: Option<&str>| -> io::Result<String> {
+        if let Some(reason) = denied {
+            eprintln!("{reason}");
+        }
+        print!("Service fingerprint {fingerprint}\nEnter code: ");
+        io::stdout().flush()?;
+        let mut code = String::new();
+        io::stdin().lock().read_line(&mut code)?;
+        Ok(code)
+    };
+    // refuse before pairing, an unreadable file would be overwritten with the new credentials only
+    let mut store = match CredentialStore::load(path.clone()) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("{e}");
+            return exitcode::DATAERR;
+        }
+    };
+    let credentials = match socket::remote::pair(addr, name, read_code).await {
+        Ok(credentials) => credentials,
+        Err(e) => {
+            eprintln!("Pairing failed: {e}");
+            return exitcode::NOPERM;
+        }
+    };
+    if let Err(e) = store.insert(addr.to_string(), credentials) {
+        eprintln!("Error storing credentials at {}: {e}", path.display());
+        return exitcode::CANTCREAT;
+    }
+    println!("Paired with {addr} - credentials stored at {}", path.display());
+    exitcode::OK
+}
+
+/// Reads the funding tool secret from `file` or, without one, from stdin.
+fn read_secret(file: Option<&Path>) -> io::Result<String> {
+    let content = match file {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => io::read_to_string(io::stdin())?,
+    };
+    let secret = content.trim();
+    if secret.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "secret is empty"));
+    }
+    Ok(secret.to_string())
+}
+
+/// Reads a destinations export from `file` or, without one, from stdin.
+fn read_export(file: Option<&Path>) -> io::Result<String> {
+    match file {
+        Some(path) => std::fs::read_to_string(path),
+        None => io::read_to_string(io::stdin()),
+    }
+}
+
+/// Send a command given as JSON and print the response the way it went over the socket.
+async fn run_raw(target: &Target, json: Option<String>, schema: bool) -> ExitCode {
+    if schema {
+        println!("{:#}", command::json_schema());
+        return exitcode::OK;
+    }
+    let Some(json) = json else {
+        eprintln!("Missing command, pass the serialized command or --schema");
+        return exitcode::USAGE;
+    };
+    let cmd: Command = match serde_json::from_str(&json) {
+        Ok(cmd) => cmd,
+        Err(e) => {
+            eprintln!("Invalid command JSON: {e}");
+            return exitcode::DATAERR;
+        }
+    };
+    let resp = match target.process_cmd(&cmd).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            eprintln!("Error processing {cmd}: {e}");
+            return exitcode::UNAVAILABLE;
+        }
+    };
+    match serde_json::to_string(&resp) {
+        Ok(s) => println!("{s}"),
+        Err(e) => {
+            eprintln!("Error serializing response to JSON: {e}");
+            return exitcode::SOFTWARE;
+        }
+    }
+    determine_exitcode(&resp)
+}
+
+/// Repeat `cmd` every `interval` until the service becomes unreachable or the user interrupts.
+///
+/// Status phase changes are highlighted as they happen, so short lived phases are not missed between refreshes.
+async fn run_watch(format: OutputFormat, locale: Locale, target: &Target, cmd: Command, interval: Duration) -> ! {
+    let mut phase: Option<String> = None;
+    loop {
+        match target.process_cmd(&cmd).await {
+            Ok(resp) => {
+                let now = SystemTime::now();
+                let transition = match &resp {
+                    Response::Status(status) => {
+                        let current = status_phase(status);
+                        phase
+                            .replace(current.clone())
+                            .filter(|p| *p != current)
+                            .map(|p| (p, current))
+                    }
+                    _ => None,
+                };
+                if matches!(format, OutputFormat::Plain) {
+                    println!("--- {}", humantime::format_rfc3339_seconds(now));
+                    if let Some((from, to)) = transition {
+                        let line = format!(">>> {from} -> {to}");
+                        if io::stdout().is_terminal() {
+                            println!("\x1b[1;7m{line}\x1b[0m");
+                        } else {
+                            println!("{line}");
+                        }
+                    }
+                }
+                print_response(format, locale, &resp);
+            }
+            Err(e) => {
+                eprintln!("Error processing {cmd}: {e}");
+                process::exit(exitcode::UNAVAILABLE);
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// One line summary of where the service currently is, compared between watch refreshes.
+fn status_phase(status: &command::StatusResponse) -> String {
+    if let Some(info) = &status.connected {
+        return format!("connected to {}", info.destination_id);
+    }
+    if let Some(info) = &status.reconnecting {
+        return format!("reconnecting to {}: {}", info.destination_id, info.phase);
+    }
+    if let Some(info) = &status.connecting {
+        return format!("connecting to {}: {}", info.destination_id, info.phase);
+    }
+    if !status.disconnecting.is_empty() {
+        return "disconnecting".to_string();
+    }
+    match status.run_mode {
+        command::RunMode::WaitingForNetwork { .. } => "waiting for network",
+        command::RunMode::Init { .. } => "initializing",
+        command::RunMode::PreparingSafe { .. } => "preparing safe",
+        command::RunMode::DeployingSafe { .. } => "deploying safe",
+        command::RunMode::Warmup { .. } => "warming up",
+        command::RunMode::Running { .. } => "running",
+        command::RunMode::Shutdown => "shutting down",
+        command::RunMode::Restarting => "restarting",
+        command::RunMode::NotRunning => "not running",
+    }
+    .to_string()
+}
+
+fn print_response(format: OutputFormat, locale: Locale, resp: &Response) {
+    match format {
+        OutputFormat::Json => json_print(resp),
+        OutputFormat::Yaml => yaml_print(resp),
+        OutputFormat::Plain => pretty_print(resp, locale),
+    };
+}
+
+/// Print log lines as the service streams them, returns once the service closes the stream.
+async fn run_follow_logs(socket_path: &Path, lines: u32, locale: Locale) -> ExitCode {
+    let cmd = Command::Logs { follow: true, lines };
+    let res = socket::root::process_stream(socket_path, &cmd, |resp| match resp {
+        Response::Logs(lines) => lines.iter().for_each(|line| println!("{line}")),
+        other => pretty_print(&other, locale),
+    })
+    .await;
+    match res {
+        Ok(()) => exitcode::OK,
+        Err(e) => {
+            eprintln!("Error processing {cmd}: {e}");
+            exitcode::UNAVAILABLE
+        }
+    }
+}
+
+async fn run_check_update(format: OutputFormat, socket_path: &Path, network: &proxy::Config, force: bool) -> ExitCode {
+    if let Err(e) = tls::install(&network.tls) {
+        return emit_check_update_error(format, CheckUpdateErrorKind::Internal, &e.to_string());
+    }
+    let client = match proxy::outbound(network.https_proxy.as_ref())
+        .and_then(|builder| builder.timeout(Duration::from_secs(30)).build())
+    {
         Ok(c) => c,
         Err(e) => return emit_check_update_error(format, CheckUpdateErrorKind::Internal, &e.to_string()),
     };
@@ -189,28 +605,70 @@ fn yaml_print(resp: &Response) {
     }
 }
 
-fn pretty_print(resp: &Response) {
+fn pretty_print(resp: &Response, locale: Locale) {
     match resp {
         Response::Connect(command::ConnectResponse::AlreadyConnected(dest)) => {
-            println!("Already connected to {dest}");
+            println!("{}", Message::AlreadyConnected { destination: dest }.text(locale));
         }
         Response::Connect(command::ConnectResponse::Connecting(dest)) => {
-            println!("Connecting to {dest}");
+            println!("{}", Message::Connecting { destination: dest }.text(locale));
         }
         Response::Connect(command::ConnectResponse::WaitingToConnect(dest, route_health)) => {
-            println!("Waiting to connect to {dest} once possible: {route_health}")
+            let msg = Message::WaitingToConnect {
+                destination: dest,
+                route_health,
+            };
+            println!("{}", msg.text(locale));
         }
         Response::Connect(command::ConnectResponse::UnableToConnect(dest, route_health)) => {
-            eprintln!("Unable to connect to {dest}: {route_health}");
+            let msg = Message::UnableToConnect {
+                destination: dest,
+                route_health,
+            };
+            eprintln!("{}", msg.text(locale));
         }
         Response::Connect(command::ConnectResponse::DestinationNotFound) => {
-            eprintln!("Destination not found");
+            eprintln!("{}", Message::DestinationNotFound.text(locale));
+        }
+        Response::Connect(command::ConnectResponse::BudgetExceeded(usage)) => {
+            eprintln!("{}", Message::RefusingToConnect { reason: usage }.text(locale));
+        }
+        Response::Connect(command::ConnectResponse::BlockedByPolicy(reason)) => {
+            eprintln!("{}", Message::RefusingToConnect { reason }.text(locale));
+        }
+        Response::Connect(command::ConnectResponse::Queued {
+            destination,
+            run_mode,
+            startup_percent,
+        }) => {
+            let msg = Message::Queued {
+                destination,
+                run_mode,
+                startup_percent: startup_percent.as_ref().map(|p| p as &dyn fmt::Display),
+            };
+            println!("{}", msg.text(locale));
+        }
+        Response::Connect(command::ConnectResponse::NoneReady) => {
+            eprintln!("{}", Message::NoneReady.text(locale));
+        }
+        Response::Connect(command::ConnectResponse::NotReady { reason, retry_in }) => {
+            let msg = Message::NotReady {
+                reason,
+                retry_in: &humantime::format_duration(*retry_in),
+            };
+            eprintln!("{}", msg.text(locale));
         }
         Response::Disconnect(command::DisconnectResponse::Disconnecting(dest)) => {
-            println!("Disconnecting from {dest}");
+            println!("{}", Message::Disconnecting { destination: dest }.text(locale));
         }
         Response::Disconnect(command::DisconnectResponse::NotConnected) => {
-            eprintln!("Currently not connected to any destination");
+            eprintln!("{}", Message::NotConnected.text(locale));
+        }
+        Response::CancelConnect(command::CancelConnectResponse::Cancelled(dest)) => {
+            println!("{}", Message::CancelledConnecting { destination: dest }.text(locale));
+        }
+        Response::CancelConnect(command::CancelConnectResponse::NotConnecting) => {
+            eprintln!("{}", Message::NothingToCancel.text(locale));
         }
         Response::Telemetry(Some(metrics)) => {
             println!("{metrics}");
@@ -226,8 +684,19 @@ fn pretty_print(resp: &Response) {
             reconnecting,
             connected,
             disconnecting,
+            budget,
+            manual_channel_funding: _,
+            retrying,
+            previous_crash,
+            disk_space,
+            conflicts: _,
+            kill_switch_error: _,
+            hints,
         }) => {
             let mut str_resp = format!("{run_mode}\n");
+            if let Some(crash) = previous_crash {
+                str_resp.push_str(&format!("---\n{crash}\n"));
+            }
             if let Some(id) = target_destination {
                 let is_active = connecting.as_ref().is_some_and(|c| c.destination_id == *id)
                     || reconnecting.as_ref().is_some_and(|c| c.destination_id == *id)
@@ -248,6 +717,18 @@ fn pretty_print(resp: &Response) {
             for info in disconnecting {
                 str_resp.push_str(&format!("---\n{info}\n"));
             }
+            if let Some(usage) = budget {
+                str_resp.push_str(&format!("---\n{usage}\n"));
+            }
+            for info in retrying {
+                str_resp.push_str(&format!("---\n{info}\n"));
+            }
+            if let Some(usage) = disk_space.filter(|u| u.is_low()) {
+                str_resp.push_str(&format!("---\n{usage}\n"));
+            }
+            for hint in hints {
+                str_resp.push_str(&format!("---\n{}\n", Message::Hint { hint }.text(locale)));
+            }
             for dest_state in destinations {
                 str_resp.push_str(&format!("---\n{}\n", dest_state.destination));
                 if let Some(rh) = &dest_state.route_health {
@@ -332,6 +813,27 @@ fn pretty_print(resp: &Response) {
                     .map(|f| format!("\nLog file: {}", f.display()))
                     .unwrap_or_default(),
             );
+            if let Some(config_file) = &info.config_file {
+                println!("Config file: {}", config_file.display());
+            }
+            if let Some(state_home) = &info.state_home {
+                println!("State directory: {}", state_home.display());
+            }
+            if let Some(cache_home) = &info.cache_home {
+                println!("Cache directory: {}", cache_home.display());
+            }
+            match &info.node {
+                Some(node) => {
+                    let unknown = "not available yet";
+                    println!("Node address: {}", node.node_address.to_checksum());
+                    println!("Peer ID: {}", node.peer_id.as_deref().unwrap_or(unknown));
+                    println!("Safe address: {}", node.safe_address.as_deref().unwrap_or(unknown));
+                    println!("Module address: {}", node.module_address.as_deref().unwrap_or(unknown));
+                    println!("Blokli: {}", node.blokli_url.as_deref().unwrap_or("default endpoint"));
+                    println!("Identity file: {}", node.identity_file.display());
+                }
+                None => println!("Node details unavailable - worker not running"),
+            }
         }
         Response::StartClient(command::StartClientResponse::Started) => {
             println!("Worker client started");
@@ -350,11 +852,332 @@ fn pretty_print(resp: &Response) {
                 println!("{id}");
             }
         }
+        Response::ExportDestinations(Ok(export)) => {
+            print!("{export}");
+        }
+        Response::ExportDestinations(Err(msg)) => {
+            eprintln!("Export error: {msg}");
+        }
+        Response::ImportDestinations(Ok(ids)) if ids.is_empty() => {
+            println!("No new destinations - all of them are configured already");
+        }
+        Response::ImportDestinations(Ok(ids)) => {
+            println!("Imported destinations: {}", ids.join(", "));
+        }
+        Response::ImportDestinations(Err(msg)) => {
+            eprintln!("Import error: {msg}");
+        }
+        Response::RateLimit(Ok(command::RateLimitResponse { limit: Some(limit) })) => {
+            println!("Egress rate limit: {}", human_bandwidth::format_bandwidth(*limit));
+        }
+        Response::RateLimit(Ok(command::RateLimitResponse { limit: None })) => {
+            println!("No egress rate limit");
+        }
+        Response::RateLimit(Err(msg)) => {
+            eprintln!("Rate limit error: {msg}");
+        }
+        Response::Gateway(Ok(command::GatewayResponse {
+            lan_interface: None, ..
+        })) => {
+            println!("Gateway mode disabled");
+        }
+        Response::Gateway(Ok(command::GatewayResponse {
+            lan_interface: Some(lan_interface),
+            active,
+            clients,
+        })) => {
+            let state = if *active { "active" } else { "inactive until connected" };
+            println!("Gateway on {lan_interface}: {state}, {} clients", clients.len());
+            for client in clients {
+                println!(
+                    "    {}: sent {} bytes ({} packets), received {} bytes ({} packets)",
+                    client.ip, client.tx_bytes, client.tx_packets, client.rx_bytes, client.rx_packets
+                );
+            }
+        }
+        Response::Gateway(Err(msg)) => {
+            eprintln!("Gateway error: {msg}");
+        }
+        Response::RoutingExplain(Ok(command::RoutingExplainResponse { managed: false, .. })) => {
+            println!("Delegated routing mode - routes into the tunnel are set up outside of the service");
+        }
+        Response::RoutingExplain(Ok(command::RoutingExplainResponse {
+            active,
+            wan_device,
+            routes,
+            tables,
+            ..
+        })) => {
+            let state = if *active { "up" } else { "down" };
+            let wan = wan_device.as_deref().unwrap_or("none");
+            println!("Tunnel {state}, WAN interface: {wan}");
+            println!("    {:<8} {:<8} route", "expected", "actual");
+            let expected = if *active { "present" } else { "absent" };
+            for route in routes {
+                let actual = match route.present {
+                    Some(true) => "present",
+                    Some(false) => "absent",
+                    None => "unknown",
+                };
+                let marker = if route.present.is_some() && actual != expected {
+                    "!"
+                } else {
+                    " "
+                };
+                let via = route.gateway.as_ref().map(|g| format!(" via {g}")).unwrap_or_default();
+                println!(
+                    "  {marker} {expected:<8} {actual:<8} {}{via} dev {} ({})",
+                    route.destination, route.device, route.purpose
+                );
+            }
+            if !tables.is_empty() {
+                println!("    {:<8} {:<8} firewall table", "expected", "actual");
+            }
+            for table in tables {
+                let expected = if table.expected { "present" } else { "absent" };
+                let actual = match table.present {
+                    Some(true) => "present",
+                    Some(false) => "absent",
+                    None => "unknown",
+                };
+                let marker = if table.present.is_some() && actual != expected {
+                    "!"
+                } else {
+                    " "
+                };
+                println!(
+                    "  {marker} {expected:<8} {actual:<8} {} ({})",
+                    table.table, table.purpose
+                );
+            }
+        }
+        Response::RoutingExplain(Err(msg)) => {
+            eprintln!("Routing error: {msg}");
+        }
+        Response::Backup(Ok(command::BackupResponse {
+            path,
+            size_bytes,
+            paused_worker,
+        })) => {
+            println!("Backup written to {} ({size_bytes} bytes)", path.display());
+            if *paused_worker {
+                println!("The worker was paused for the snapshot and is starting again");
+            }
+        }
+        Response::Backup(Err(msg)) => {
+            eprintln!("Backup error: {msg}");
+        }
+        Response::Compact(Ok(command::CompactResponse {
+            databases,
+            skipped_databases,
+            rotated_logs,
+            reclaimed_bytes,
+            paused_worker,
+        })) => {
+            println!(
+                "Compacted {databases} databases and removed {rotated_logs} rotated logs ({reclaimed_bytes} bytes reclaimed)"
+            );
+            if *skipped_databases > 0 {
+                println!("Skipped {skipped_databases} databases, there is not enough free space to rebuild them");
+            }
+            if *paused_worker {
+                println!("The worker was paused for the compaction and is starting again");
+            }
+        }
+        Response::Compact(Err(msg)) => {
+            eprintln!("Compaction error: {msg}");
+        }
+        Response::Unpair(0) => {
+            eprintln!("No remote client is paired under this name");
+        }
+        Response::Unpair(removed) => {
+            println!("Revoked {removed} paired remote client(s)");
+        }
+        Response::UsageTelemetry(command::UsageTelemetryResponse {
+            enabled,
+            locked,
+            payload,
+        }) => {
+            let state = if *enabled { "enabled" } else { "disabled" };
+            if *locked {
+                println!(
+                    "Usage telemetry is switched off by `telemetry = false` in the config, the toggle has no effect."
+                );
+            }
+            println!("Usage telemetry {state}, next upload sends:");
+            println!("{}", serde_json::to_string_pretty(payload).unwrap_or_default());
+        }
+        Response::Peers(command::PeersResponse { updated_at: None, .. }) => {
+            eprintln!("{}", Message::PeersNotAvailable.text(locale));
+        }
+        Response::Peers(command::PeersResponse {
+            updated_at: Some(updated_at),
+            peers,
+        }) => {
+            println!(
+                "{} connected peers, updated at {}",
+                peers.len(),
+                humantime::format_rfc3339_seconds(*updated_at)
+            );
+            for peer in peers {
+                let ips: Vec<String> = peer.ipv4_addrs.iter().map(|ip| ip.to_string()).collect();
+                let mut line = format!("{} [{}]", peer.address.to_checksum(), ips.join(", "));
+                if let Some(score) = peer.score {
+                    line.push_str(&format!(" score: {score:.2}"));
+                }
+                if let Some(latency) = peer.latency {
+                    line.push_str(&format!(" latency: {}ms", latency.as_millis()));
+                }
+                if let Some(channel) = &peer.channel {
+                    line.push_str(&format!(" channel: {}", channel.stake));
+                }
+                if !peer.destinations.is_empty() {
+                    line.push_str(&format!(" exit for: {}", peer.destinations.join(", ")));
+                }
+                println!("{line}");
+            }
+        }
+        Response::Sessions(None) => {
+            eprintln!("{}", Message::SessionsNotAvailable.text(locale));
+        }
+        Response::Sessions(Some(sessions)) if sessions.is_empty() => {
+            println!("No open sessions");
+        }
+        Response::Sessions(Some(sessions)) => {
+            for session in sessions {
+                let role = match session.role {
+                    Some(command::SessionRole::Bridge) => "bridge",
+                    Some(command::SessionRole::Ping) => "ping",
+                    Some(command::SessionRole::Main) => "main",
+                    None => "unknown",
+                };
+                let protocol = match session.protocol {
+                    command::SessionProtocol::Udp => "udp",
+                    command::SessionProtocol::Tcp => "tcp",
+                };
+                println!(
+                    "{} {} on {} -> {} via {} ({}-hop forward, {}-hop return)",
+                    role,
+                    protocol,
+                    session.bound_host,
+                    session.target,
+                    session.destination.to_checksum(),
+                    session.forward_path.hop_count(),
+                    session.return_path.hop_count()
+                );
+                let mut details = format!(
+                    "    clients: {}/{}, mtu: {}, surb: {}",
+                    session.active_clients.len(),
+                    session.max_client_sessions,
+                    session.hopr_mtu,
+                    session.surb_len
+                );
+                if let Some(bw) = session.max_surb_upstream {
+                    details.push_str(&format!(
+                        ", max surb upstream: {}",
+                        human_bandwidth::format_bandwidth(bw)
+                    ));
+                }
+                if let Some(buffer) = session.response_buffer {
+                    details.push_str(&format!(", response buffer: {buffer} bytes"));
+                }
+                if let Some(pool) = session.session_pool {
+                    details.push_str(&format!(", pool: {pool}"));
+                }
+                println!("{details}");
+                for client in &session.active_clients {
+                    println!("    client {client}");
+                }
+            }
+        }
+        Response::BalanceHistory(points) if points.is_empty() => {
+            println!("No balance history yet");
+        }
+        Response::BalanceHistory(points) => {
+            for point in points {
+                println!(
+                    "{} node: {} safe: {} channels out: {}",
+                    humantime::format_rfc3339_seconds(point.at),
+                    point.node_xdai,
+                    point.safe_wxhopr,
+                    point.channels_out_wxhopr
+                );
+            }
+        }
+        Response::Transactions(txs) if txs.is_empty() => {
+            println!("No transactions");
+        }
+        Response::Transactions(txs) => {
+            for tx in txs {
+                let kind = match tx.kind {
+                    transactions::Kind::SafeDeployment => "safe deployment",
+                    transactions::Kind::WxhoprWithdrawal => "wxHOPR withdrawal",
+                    transactions::Kind::ChannelFunding => "channel funding",
+                };
+                let status = match &tx.status {
+                    transactions::Status::Pending => "pending".to_string(),
+                    transactions::Status::Confirmed => "confirmed".to_string(),
+                    transactions::Status::Failed { reason } => format!("failed: {reason}"),
+                    transactions::Status::Interrupted => "interrupted - check the chain".to_string(),
+                };
+                let mut line = format!(
+                    "{} {} {}",
+                    humantime::format_rfc3339_seconds(tx.started_at),
+                    kind,
+                    status
+                );
+                if let Some(amount) = &tx.amount {
+                    line.push_str(&format!(" amount: {amount}"));
+                }
+                if let Some(target) = &tx.target {
+                    line.push_str(&format!(" to: {target}"));
+                }
+                if let Some(tx_hash) = &tx.tx_hash {
+                    line.push_str(&format!(" tx: {tx_hash}"));
+                }
+                println!("{line}");
+            }
+        }
+        Response::ExitReports(entries) if entries.is_empty() => {
+            println!("{}", Message::NoExitReports.text(locale));
+        }
+        Response::ExitReports(entries) => {
+            for entry in entries {
+                let delivery = match &entry.delivery {
+                    exit_reports::Delivery::Queued => "queued until a tunnel is up".to_string(),
+                    exit_reports::Delivery::Pending => "pending".to_string(),
+                    exit_reports::Delivery::Delivered => "delivered".to_string(),
+                    exit_reports::Delivery::Failed { reason } => format!("failed: {reason}"),
+                };
+                println!("{} to {} {}:", entry.destination, entry.url, delivery);
+                println!("{}", serde_json::to_string_pretty(&entry.report).unwrap_or_default());
+            }
+        }
+        Response::ReportIssue(Ok(report)) => match serde_json::to_string_pretty(report) {
+            Ok(s) => println!("{s}"),
+            Err(e) => eprintln!("Error serializing issue report: {e}"),
+        },
+        Response::ReportIssue(Err(msg)) => {
+            eprintln!("Unable to create issue report: {msg}");
+        }
+        Response::Logs(lines) => {
+            for line in lines {
+                println!("{line}");
+            }
+        }
+        Response::Diagnose(report) => {
+            for check in &report.checks {
+                println!("{check}");
+            }
+        }
         Response::WorkerOffline => {
-            eprintln!("Worker client is currently offline - use command `start-client` to start it");
+            eprintln!("{}", Message::WorkerOffline.text(locale));
         }
         Response::WorkerRestarting => {
-            eprintln!("Worker client is restarting - try again shortly");
+            eprintln!("{}", Message::WorkerRestarting.text(locale));
+        }
+        Response::Refused(msg) => {
+            eprintln!("{}", Message::CommandRefused { reason: msg }.text(locale));
         }
         // Internal response sent by the root process to itself when a WAN interface change
         // triggers a HOPR session reconnect. Never issued in response to a ctl command.
@@ -420,11 +1243,19 @@ fn determine_exitcode(resp: &Response) -> ExitCode {
         Response::Connect(command::ConnectResponse::DestinationNotFound) => exitcode::UNAVAILABLE,
         Response::Connect(command::ConnectResponse::WaitingToConnect(..)) => exitcode::OK,
         Response::Connect(command::ConnectResponse::UnableToConnect(..)) => exitcode::UNAVAILABLE,
+        Response::Connect(command::ConnectResponse::BudgetExceeded(..)) => exitcode::UNAVAILABLE,
+        Response::Connect(command::ConnectResponse::BlockedByPolicy(..)) => exitcode::NOPERM,
+        Response::Connect(command::ConnectResponse::Queued { .. }) => exitcode::OK,
+        Response::Connect(command::ConnectResponse::NoneReady) => exitcode::TEMPFAIL,
+        Response::Connect(command::ConnectResponse::NotReady { .. }) => exitcode::TEMPFAIL,
         Response::Disconnect(command::DisconnectResponse::Disconnecting(..)) => exitcode::OK,
         Response::Disconnect(command::DisconnectResponse::NotConnected) => exitcode::PROTOCOL,
+        Response::CancelConnect(command::CancelConnectResponse::Cancelled(..)) => exitcode::OK,
+        Response::CancelConnect(command::CancelConnectResponse::NotConnecting) => exitcode::PROTOCOL,
         Response::Status(..) => exitcode::OK,
         Response::Balance(Ok(..)) => exitcode::OK,
         Response::Balance(Err(..)) => exitcode::SOFTWARE,
+        Response::BalanceHistory(..) => exitcode::OK,
         Response::Pong => exitcode::OK,
         Response::Telemetry(Some(_)) => exitcode::OK,
         Response::Telemetry(None) => exitcode::UNAVAILABLE,
@@ -449,8 +1280,39 @@ fn determine_exitcode(resp: &Response) -> ExitCode {
         Response::StopClient(command::StopClientResponse::Stopped) => exitcode::OK,
         Response::StopClient(command::StopClientResponse::NotRunning) => exitcode::PROTOCOL,
         Response::Destinations(..) => exitcode::OK,
+        Response::ExportDestinations(Ok(..)) => exitcode::OK,
+        Response::ExportDestinations(Err(..)) => exitcode::SOFTWARE,
+        Response::ImportDestinations(Ok(..)) => exitcode::OK,
+        Response::ImportDestinations(Err(..)) => exitcode::DATAERR,
+        Response::RateLimit(Ok(..)) => exitcode::OK,
+        Response::RateLimit(Err(..)) => exitcode::SOFTWARE,
+        Response::Gateway(Ok(..)) => exitcode::OK,
+        Response::Gateway(Err(..)) => exitcode::SOFTWARE,
+        Response::RoutingExplain(Ok(..)) => exitcode::OK,
+        Response::RoutingExplain(Err(..)) => exitcode::SOFTWARE,
+        Response::UsageTelemetry(..) => exitcode::OK,
+        Response::Backup(Ok(..)) => exitcode::OK,
+        Response::Backup(Err(..)) => exitcode::CANTCREAT,
+        Response::Compact(Ok(..)) => exitcode::OK,
+        Response::Compact(Err(..)) => exitcode::IOERR,
+        Response::Unpair(0) => exitcode::NOUSER,
+        Response::Unpair(_) => exitcode::OK,
+        Response::Peers(command::PeersResponse { updated_at: None, .. }) => exitcode::UNAVAILABLE,
+        Response::Peers(..) => exitcode::OK,
+        Response::Sessions(None) => exitcode::UNAVAILABLE,
+        Response::Sessions(Some(_)) => exitcode::OK,
+        Response::Transactions(..) => exitcode::OK,
+        Response::ExitReports(..) => exitcode::OK,
+        Response::ReportIssue(Ok(..)) => exitcode::OK,
+        Response::ReportIssue(Err(..)) => exitcode::DATAERR,
+        Response::Logs(..) => exitcode::OK,
+        Response::Diagnose(report) => match report.outcome() {
+            diagnostics::Outcome::Fail => exitcode::UNAVAILABLE,
+            diagnostics::Outcome::Pass | diagnostics::Outcome::Warn => exitcode::OK,
+        },
         Response::WorkerOffline => exitcode::UNAVAILABLE,
         Response::WorkerRestarting => exitcode::TEMPFAIL,
+        Response::Refused(..) => exitcode::NOPERM,
         // Internal response — see pretty_print for explanation
         Response::ForceReconnectAcknowledged => exitcode::PROTOCOL,
     }
@@ -548,6 +1410,28 @@ fn print_connected_stats(stats: &command::ConnStats) {
     if let Some(ref wg_pubkey) = stats.wg_server_pubkey {
         str_resp.push_str(format!("---\nExit WireGuard Public Key: {}\n", wg_pubkey).as_str());
     }
+    if let Some(ref caps) = stats.exit_capabilities {
+        let mut parts = Vec::new();
+        if let Some(mtu) = caps.mtu {
+            parts.push(format!("MTU {mtu}"));
+        }
+        if let Some(ref dns) = caps.dns {
+            let servers: Vec<String> = dns.iter().map(|ip| ip.to_string()).collect();
+            parts.push(format!("DNS {}", servers.join(", ")));
+        }
+        parts.push(format!("IPv6 {}", if caps.ipv6 { "yes" } else { "no" }));
+        if let Some(bps) = caps.max_bandwidth_bps {
+            parts.push(format!(
+                "max {}",
+                human_bandwidth::format_bandwidth(human_bandwidth::re::bandwidth::Bandwidth::from_bps(bps))
+            ));
+        }
+        if let Some(expires_at) = caps.expires_at {
+            let at = SystemTime::UNIX_EPOCH + Duration::from_secs(expires_at);
+            parts.push(format!("expires {}", humantime::format_rfc3339_seconds(at)));
+        }
+        str_resp.push_str(format!("Exit Capabilities: {}\n", parts.join(", ")).as_str());
+    }
     println!("{str_resp}");
 }
 
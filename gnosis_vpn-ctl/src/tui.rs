@@ -0,0 +1,396 @@
+//! Full screen dashboard of `gnosis_vpn-ctl tui`.
+//!
+//! Polls status and balance over the socket like `status --watch` and renders destinations,
+//! connection phase, node startup progress and balances in one place. Keys connect to the
+//! selected destination or disconnect, the service answer shows up in the footer.
+use exitcode::{self, ExitCode};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use tokio::sync::mpsc;
+use tokio::time;
+
+use std::fmt::Display;
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use gnosis_vpn_lib::command::{self, BalanceResponse, Command, Response, StatusResponse};
+use gnosis_vpn_lib::i18n::{Locale, Message};
+
+use crate::Target;
+
+const HELP: &str = "↑/↓ select  enter connect  d disconnect  r refresh  q quit";
+
+#[derive(Default)]
+struct Dashboard {
+    status: Option<StatusResponse>,
+    balance: Option<Result<BalanceResponse, String>>,
+    selected: ListState,
+    /// Answer to the last key command or why the service is unreachable
+    message: Option<String>,
+}
+
+pub async fn run(target: &Target, locale: Locale, refresh: Duration) -> ExitCode {
+    let mut terminal = match ratatui::try_init() {
+        Ok(terminal) => terminal,
+        Err(e) => {
+            eprintln!("Dashboard needs an interactive terminal: {e}");
+            return exitcode::IOERR;
+        }
+    };
+    let (sender, mut events) = mpsc::channel(16);
+    // terminal reads block, keep them off the runtime
+    thread::spawn(move || read_events(sender));
+    let res = dashboard_loop(&mut terminal, &mut events, target, locale, refresh).await;
+    ratatui::restore();
+    match res {
+        Ok(()) => exitcode::OK,
+        Err(e) => {
+            eprintln!("Dashboard terminal error: {e}");
+            exitcode::IOERR
+        }
+    }
+}
+
+async fn dashboard_loop(
+    terminal: &mut DefaultTerminal,
+    events: &mut mpsc::Receiver<Event>,
+    target: &Target,
+    locale: Locale,
+    refresh: Duration,
+) -> Result<(), io::Error> {
+    let mut dashboard = Dashboard::default();
+    let mut ticker = time::interval(refresh);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => dashboard.refresh(target, locale).await,
+            event = events.recv() => match event {
+                Some(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                    if !dashboard.on_key(key, target, locale).await {
+                        return Ok(());
+                    }
+                }
+                // resizes and other events only need a redraw
+                Some(_) => (),
+                None => return Err(io::Error::other("terminal input closed")),
+            },
+        }
+        terminal.draw(|frame| dashboard.render(frame, locale))?;
+    }
+}
+
+fn read_events(sender: mpsc::Sender<Event>) {
+    while let Ok(event) = event::read() {
+        if sender.blocking_send(event).is_err() {
+            return;
+        }
+    }
+}
+
+impl Dashboard {
+    /// Handles a key press, `false` once the user quits.
+    async fn on_key(&mut self, key: KeyEvent, target: &Target, locale: Locale) -> bool {
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Up | KeyCode::Char('k') => self.selected.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.selected.select_next(),
+            KeyCode::Enter | KeyCode::Char('c') => {
+                if let Some(id) = self.selected_id() {
+                    self.send(target, locale, Command::Connect(id)).await;
+                }
+            }
+            KeyCode::Char('d') => self.send(target, locale, Command::Disconnect).await,
+            KeyCode::Char('r') => self.refresh(target, locale).await,
+            _ => (),
+        }
+        true
+    }
+
+    async fn refresh(&mut self, target: &Target, locale: Locale) {
+        match target.process_cmd(&Command::Status).await {
+            Ok(Response::Status(status)) => {
+                if self.selected.selected().is_none() && !status.destinations.is_empty() {
+                    self.selected.select(Some(0));
+                }
+                self.status = Some(status);
+            }
+            Ok(resp) => self.message = Some(describe(&resp, locale)),
+            Err(e) => {
+                self.status = None;
+                self.message = Some(e);
+            }
+        }
+        self.balance = match target.process_cmd(&Command::Balance).await {
+            Ok(Response::Balance(balance)) => Some(balance),
+            _ => None,
+        };
+    }
+
+    async fn send(&mut self, target: &Target, locale: Locale, cmd: Command) {
+        self.message = Some(match target.process_cmd(&cmd).await {
+            Ok(resp) => describe(&resp, locale),
+            Err(e) => e,
+        });
+        self.refresh(target, locale).await;
+    }
+
+    fn selected_id(&self) -> Option<String> {
+        let destinations = &self.status.as_ref()?.destinations;
+        let index = self.selected.selected()?.min(destinations.len().checked_sub(1)?);
+        destinations.get(index).map(|d| d.destination.id.clone())
+    }
+
+    fn render(&mut self, frame: &mut Frame, locale: Locale) {
+        let [header, progress, body, footer] = Layout::vertical([
+            Constraint::Length(7),
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(3),
+        ])
+        .areas(frame.area());
+        let [destinations, balance] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(body);
+
+        self.render_status(frame, header, locale);
+        self.render_progress(frame, progress);
+        self.render_destinations(frame, destinations);
+        self.render_balance(frame, balance);
+        let footer_block = Block::bordered().title_bottom(Line::from(HELP).right_aligned());
+        let footer_text = self.message.as_deref().unwrap_or_default();
+        frame.render_widget(Paragraph::new(footer_text).block(footer_block), footer);
+    }
+
+    fn render_status(&self, frame: &mut Frame, area: Rect, locale: Locale) {
+        let mut lines = Vec::new();
+        if let Some(status) = &self.status {
+            lines.push(Line::from(status.run_mode.to_string()));
+            let connection = status
+                .connected
+                .as_ref()
+                .map(|i| i.to_string())
+                .or_else(|| status.reconnecting.as_ref().map(|i| i.to_string()))
+                .or_else(|| status.connecting.as_ref().map(|i| i.to_string()))
+                .unwrap_or_else(|| Message::NotConnected.text(locale));
+            lines.extend(connection.lines().map(|l| Line::from(l.to_string())));
+            lines.extend(
+                status
+                    .hints
+                    .iter()
+                    .map(|hint| Line::from(Message::Hint { hint }.text(locale))),
+            );
+        }
+        let paragraph = Paragraph::new(lines)
+            .block(Block::bordered().title("Gnosis VPN"))
+            .wrap(Wrap { trim: true });
+        frame.render_widget(paragraph, area);
+    }
+
+    fn render_progress(&self, frame: &mut Frame, area: Rect) {
+        let Some(status) = &self.status else {
+            return;
+        };
+        let (title, percent) = match (&status.connecting, status.run_mode.startup_percent()) {
+            (Some(info), _) => (format!("Connecting to {}", info.destination_id), info.progress_percent),
+            (None, Some(percent)) => ("Node startup".to_string(), percent),
+            (None, None) if matches!(status.run_mode, command::RunMode::Running { .. }) => {
+                ("Node running".to_string(), 100)
+            }
+            (None, None) => ("Node idle".to_string(), 0),
+        };
+        let gauge = Gauge::default()
+            .block(Block::bordered().title(title))
+            .percent(u16::from(percent.min(100)));
+        frame.render_widget(gauge, area);
+    }
+
+    fn render_destinations(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .status
+            .iter()
+            .flat_map(|status| {
+                status.destinations.iter().map(|state| {
+                    let id = &state.destination.id;
+                    let marker = if status.connected.as_ref().is_some_and(|c| c.destination_id == *id) {
+                        "●"
+                    } else if status.target_destination.as_ref() == Some(id) {
+                        "○"
+                    } else {
+                        " "
+                    };
+                    let health = state
+                        .route_health
+                        .as_ref()
+                        .map(|rh| rh.state.to_string())
+                        .unwrap_or_default();
+                    ListItem::new(format!("{marker} {id}  {health}"))
+                })
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title("Destinations"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.selected);
+    }
+
+    fn render_balance(&self, frame: &mut Frame, area: Rect) {
+        let lines = match &self.balance {
+            Some(Ok(balance)) => {
+                let mut lines = vec![
+                    Line::from(format!("Node: {}", balance.node)),
+                    Line::from(format!("Safe: {}", balance.safe)),
+                    Line::from(format!("Outgoing channels: {}", balance.channels_out.len())),
+                ];
+                match balance.funding_issues.as_deref() {
+                    None => lines.push(Line::from("Waiting for funding calculations")),
+                    Some([]) => lines.push(Line::from("Well funded")),
+                    Some(issues) => lines.extend(issues.iter().map(|issue| Line::from(issue.to_string()))),
+                }
+                lines
+            }
+            Some(Err(e)) => vec![Line::from(e.clone())],
+            None => vec![Line::from("Not available while the node is not running")],
+        };
+        let paragraph = Paragraph::new(lines)
+            .block(Block::bordered().title("Balance"))
+            .wrap(Wrap { trim: true });
+        frame.render_widget(paragraph, area);
+    }
+}
+
+/// One line answer to a command sent from the dashboard.
+fn describe(resp: &Response, locale: Locale) -> String {
+    let msg = match resp {
+        Response::Connect(command::ConnectResponse::AlreadyConnected(dest)) => {
+            Message::AlreadyConnected { destination: dest }
+        }
+        Response::Connect(command::ConnectResponse::Connecting(dest)) => Message::Connecting { destination: dest },
+        Response::Connect(command::ConnectResponse::WaitingToConnect(dest, route_health)) => {
+            Message::WaitingToConnect {
+                destination: dest,
+                route_health,
+            }
+        }
+        Response::Connect(command::ConnectResponse::UnableToConnect(dest, route_health)) => Message::UnableToConnect {
+            destination: dest,
+            route_health,
+        },
+        Response::Connect(command::ConnectResponse::DestinationNotFound) => Message::DestinationNotFound,
+        Response::Connect(command::ConnectResponse::BudgetExceeded(usage)) => {
+            Message::RefusingToConnect { reason: usage }
+        }
+        Response::Connect(command::ConnectResponse::BlockedByPolicy(reason)) => Message::RefusingToConnect { reason },
+        Response::Connect(command::ConnectResponse::Queued {
+            destination,
+            run_mode,
+            startup_percent,
+        }) => Message::Queued {
+            destination,
+            run_mode,
+            startup_percent: startup_percent.as_ref().map(|p| p as &dyn Display),
+        },
+        Response::Connect(command::ConnectResponse::NoneReady) => Message::NoneReady,
+        Response::Connect(command::ConnectResponse::NotReady { reason, retry_in }) => {
+            let msg = Message::NotReady {
+                reason,
+                retry_in: &humantime::format_duration(*retry_in),
+            };
+            return msg.text(locale);
+        }
+        Response::Disconnect(command::DisconnectResponse::Disconnecting(dest)) => {
+            Message::Disconnecting { destination: dest }
+        }
+        Response::Disconnect(command::DisconnectResponse::NotConnected) => Message::NotConnected,
+        Response::WorkerOffline => Message::WorkerOffline,
+        Response::WorkerRestarting => Message::WorkerRestarting,
+        Response::Refused(reason) => Message::CommandRefused { reason },
+        other => return format!("Unexpected answer: {other:?}"),
+    };
+    msg.text(locale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use gnosis_vpn_lib::command::{DestinationState, RunMode};
+    use gnosis_vpn_lib::connection::destination::{Address, Destination, HopRouting};
+
+    fn dashboard(ids: &[&str]) -> Dashboard {
+        let destinations = ids
+            .iter()
+            .map(|id| DestinationState {
+                destination: Destination::new(
+                    id.to_string(),
+                    Address::from([1u8; 20]),
+                    HopRouting::try_from(1).expect("conversion cannot fail"),
+                    HashMap::new(),
+                ),
+                route_health: None,
+            })
+            .collect();
+        Dashboard {
+            status: Some(StatusResponse {
+                run_mode: RunMode::NotRunning,
+                destinations,
+                target_destination: None,
+                connecting: None,
+                reconnecting: None,
+                connected: None,
+                disconnecting: vec![],
+                budget: None,
+                manual_channel_funding: false,
+                retrying: vec![],
+                previous_crash: None,
+                disk_space: None,
+                conflicts: vec![],
+                kill_switch_error: None,
+                hints: vec![],
+            }),
+            ..Dashboard::default()
+        }
+    }
+
+    #[test]
+    fn selected_id_stays_within_the_destinations() {
+        assert_eq!(Dashboard::default().selected_id(), None);
+
+        let mut dashboard = dashboard(&["Germany", "Spain"]);
+        assert_eq!(dashboard.selected_id(), None);
+        dashboard.selected.select(Some(1));
+        assert_eq!(dashboard.selected_id().as_deref(), Some("Spain"));
+        // a refresh can shrink the list below the selection
+        dashboard.selected.select(Some(5));
+        assert_eq!(dashboard.selected_id().as_deref(), Some("Spain"));
+
+        let mut empty = dashboard(&[]);
+        empty.selected.select(Some(0));
+        assert_eq!(empty.selected_id(), None);
+    }
+
+    #[test]
+    fn describe_answers_commands_in_one_line() {
+        let not_found = Response::Connect(command::ConnectResponse::DestinationNotFound);
+        assert_eq!(describe(&not_found, Locale::En), "Destination not found");
+
+        let not_ready = Response::Connect(command::ConnectResponse::not_ready(
+            command::NotReadyReason::NoChannel,
+            Duration::from_secs(60),
+        ));
+        assert_eq!(
+            describe(&not_ready, Locale::En),
+            "Not ready to connect: no outgoing channel to a connected peer is open - retry in 1m"
+        );
+
+        let refused = Response::Refused("read only connection".to_string());
+        assert_eq!(describe(&refused, Locale::En), "Command refused: read only connection");
+
+        assert!(describe(&Response::Pong, Locale::En).starts_with("Unexpected answer"));
+    }
+}
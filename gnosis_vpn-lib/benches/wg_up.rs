@@ -0,0 +1,115 @@
+//! Round trip of the WireGuard setup request between worker and root.
+//!
+//! Compares the former newline delimited JSON with length-prefixed MessagePack, decoded into owned
+//! messages and into a view borrowing the keys from the frame. Run with `cargo bench --bench wg_up`.
+
+use serde::Deserialize;
+use serde_json::json;
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use gnosis_vpn_lib::event::WorkerToRoot;
+use gnosis_vpn_lib::socket::worker;
+
+const ROUNDS: u32 = 20_000;
+
+/// The fields root needs to configure the interface, borrowed from the frame.
+#[derive(Deserialize)]
+enum WorkerToRootView<'a> {
+    RequestToRoot(#[serde(borrow)] RequestView<'a>),
+}
+
+#[derive(Deserialize)]
+enum RequestView<'a> {
+    StaticWgRouting {
+        #[serde(borrow)]
+        wg_data: WireGuardDataView<'a>,
+    },
+}
+
+#[derive(Deserialize)]
+struct WireGuardDataView<'a> {
+    #[serde(borrow)]
+    peer_info: PeerInfoView<'a>,
+}
+
+#[derive(Deserialize)]
+struct PeerInfoView<'a> {
+    public_key: &'a str,
+    preshared_key: &'a str,
+    endpoint: &'a str,
+}
+
+fn wg_up() -> WorkerToRoot {
+    serde_json::from_value(json!({
+        "RequestToRoot": {
+            "StaticWgRouting": {
+                "request_id": 42,
+                "wg_data": {
+                    "wg": {
+                        "config": {
+                            "listen_port": 51820,
+                            "force_private_key": null,
+                            "allowed_ips": "0.0.0.0/0",
+                            "dns": "10.128.0.1"
+                        },
+                        "key_pair": {
+                            "priv_key": "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=",
+                            "public_key": "HIgo9xNzJMWLKASShiTqIybxZ0U3wGLiUeJ1PKf8ykw="
+                        }
+                    },
+                    "interface_info": { "address": "10.128.0.2/32", "mtu": 1420 },
+                    "peer_info": {
+                        "public_key": "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=",
+                        "preshared_key": "FpCyhws9cxwWoV4xELtfJvjJN+zQVRPISllRWgeopVE=",
+                        "endpoint": "192.0.2.10:51820"
+                    }
+                },
+                "peer_ips": ["192.0.2.10", "198.51.100.7", "203.0.113.21"]
+            }
+        }
+    }))
+    .expect("valid WgUp message")
+}
+
+fn measure(name: &str, mut round_trip: impl FnMut()) {
+    // warm up allocator and caches
+    for _ in 0..ROUNDS / 10 {
+        round_trip();
+    }
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        round_trip();
+    }
+    let per_round = start.elapsed() / ROUNDS;
+    println!("{name:<24} {:>8.2} µs", per_round.as_secs_f64() * 1e6);
+}
+
+fn main() {
+    let msg = wg_up();
+
+    measure("json lines", || {
+        let mut line = serde_json::to_string(black_box(&msg)).expect("encode");
+        line.push('\n');
+        let decoded: WorkerToRoot = serde_json::from_str(line.trim_end()).expect("decode");
+        black_box(decoded);
+    });
+
+    measure("msgpack frame, owned", || {
+        let frame = worker::encode(black_box(&msg)).expect("encode");
+        let decoded: WorkerToRoot = worker::decode(&frame[worker::PREFIX_LEN..]).expect("decode");
+        black_box(decoded);
+    });
+
+    measure("msgpack frame, borrowed", || {
+        let frame = worker::encode(black_box(&msg)).expect("encode");
+        let WorkerToRootView::RequestToRoot(RequestView::StaticWgRouting { wg_data }) =
+            worker::decode(&frame[worker::PREFIX_LEN..]).expect("decode");
+        black_box((
+            wg_data.peer_info.public_key,
+            wg_data.peer_info.preshared_key,
+            wg_data.peer_info.endpoint,
+        ));
+    });
+}
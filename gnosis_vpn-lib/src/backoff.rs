@@ -0,0 +1,248 @@
+//! Shared retry policy for the background runners.
+//!
+//! A runner retries a failing call with exponential backoff starting at `initial` and capped
+//! at `max_interval`. Once the accumulated delay would exceed `max_elapsed` the runner gives
+//! up and reports the error; core then reschedules the runner via [`Retries`], which applies
+//! the same exponential policy across reschedules and tracks how long a task keeps failing.
+use backon::ExponentialBuilder;
+use humantime::format_duration;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::time::{Duration, SystemTime};
+
+use crate::route_health::jitter;
+use crate::serde_utils;
+
+/// Consecutive failures after which a task is reported in status.
+const REPORT_AFTER_ATTEMPTS: u32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(with = "humantime_serde")]
+    pub initial: Duration,
+    #[serde(with = "humantime_serde")]
+    pub max_interval: Duration,
+    #[serde(with = "humantime_serde")]
+    pub max_elapsed: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        // 10s, 20s, 40s - three retries before reporting back to core
+        Self {
+            initial: Duration::from_secs(10),
+            max_interval: Duration::from_secs(60),
+            max_elapsed: Duration::from_secs(90),
+        }
+    }
+}
+
+impl Config {
+    /// Backoff bounded by `max_elapsed`.
+    pub fn exponential(&self) -> ExponentialBuilder {
+        self.unbounded().with_total_delay(Some(self.max_elapsed))
+    }
+
+    /// Backoff for runners that must never give up, `max_elapsed` is ignored.
+    pub fn unbounded(&self) -> ExponentialBuilder {
+        ExponentialBuilder::new()
+            .with_min_delay(self.initial)
+            .with_max_delay(self.max_interval)
+            .with_factor(2.0)
+            .with_jitter()
+            .without_max_times()
+    }
+}
+
+/// Background tasks core reschedules on failure.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Task {
+    NodeBalance,
+    QuerySafe,
+    Hopr,
+    Balances,
+    CapacityAllocations,
+    MinimumBalanceRecommendation,
+    IdealBalanceRecommendation,
+    AnnouncedPeers,
+    TicketStats,
+    ChainEndpoint,
+    ExternalSafe,
+}
+
+/// A task that keeps failing, reported in status.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RetryInfo {
+    pub task: Task,
+    pub attempts: u32,
+    pub last_error: String,
+    #[serde(with = "serde_utils::system_time")]
+    #[schemars(with = "u64")]
+    pub next_attempt: SystemTime,
+}
+
+/// Consecutive failure bookkeeping per [`Task`].
+#[derive(Debug)]
+pub struct Retries {
+    config: Config,
+    failing: HashMap<Task, RetryInfo>,
+    // all failures since start, exported as metrics
+    total_failures: HashMap<Task, u64>,
+}
+
+impl Retries {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            failing: HashMap::new(),
+            total_failures: HashMap::new(),
+        }
+    }
+
+    /// Record a failure of `task` and return the jittered delay until its next attempt.
+    pub fn failed(&mut self, task: Task, error: String, now: SystemTime) -> Duration {
+        let attempts = self.failing.get(&task).map(|r| r.attempts).unwrap_or(0) + 1;
+        let delay = jitter(self.delay(attempts));
+        *self.total_failures.entry(task).or_default() += 1;
+        self.failing.insert(
+            task,
+            RetryInfo {
+                task,
+                attempts,
+                last_error: error,
+                next_attempt: now + delay,
+            },
+        );
+        delay
+    }
+
+    pub fn succeeded(&mut self, task: Task) {
+        self.failing.remove(&task);
+    }
+
+    /// Tasks that failed often enough to be surfaced to the user.
+    pub fn reported(&self) -> Vec<RetryInfo> {
+        let mut res: Vec<RetryInfo> = self
+            .failing
+            .values()
+            .filter(|r| r.attempts >= REPORT_AFTER_ATTEMPTS)
+            .cloned()
+            .collect();
+        res.sort_by_key(|r| r.next_attempt);
+        res
+    }
+
+    pub fn total_failures(&self) -> impl Iterator<Item = (Task, u64)> + '_ {
+        self.total_failures.iter().map(|(task, count)| (*task, *count))
+    }
+
+    fn delay(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        self.config
+            .initial
+            .checked_mul(factor)
+            .unwrap_or(Duration::MAX)
+            .min(self.config.max_interval)
+    }
+}
+
+impl Task {
+    /// Name as serialized, used as metric label.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Task::NodeBalance => "node_balance",
+            Task::QuerySafe => "query_safe",
+            Task::Hopr => "hopr",
+            Task::Balances => "balances",
+            Task::CapacityAllocations => "capacity_allocations",
+            Task::MinimumBalanceRecommendation => "minimum_balance_recommendation",
+            Task::IdealBalanceRecommendation => "ideal_balance_recommendation",
+            Task::AnnouncedPeers => "announced_peers",
+            Task::TicketStats => "ticket_stats",
+            Task::ChainEndpoint => "chain_endpoint",
+            Task::ExternalSafe => "external_safe",
+        }
+    }
+}
+
+impl Display for Task {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Task::NodeBalance => "Node balance query",
+            Task::QuerySafe => "Safe query",
+            Task::Hopr => "Node startup",
+            Task::Balances => "Balances query",
+            Task::CapacityAllocations => "Capacity allocations query",
+            Task::MinimumBalanceRecommendation => "Minimum balance recommendation",
+            Task::IdealBalanceRecommendation => "Ideal balance recommendation",
+            Task::AnnouncedPeers => "Announced peers query",
+            Task::TicketStats => "Ticket price query",
+            Task::ChainEndpoint => "Chain endpoint check",
+            Task::ExternalSafe => "External safe module check",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl Display for RetryInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let remaining = self
+            .next_attempt
+            .duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO);
+        let remaining = Duration::from_secs(remaining.as_secs());
+        write!(
+            f,
+            "{} failing - retrying in {}, {} attempts so far: {}",
+            self.task,
+            format_duration(remaining),
+            self.attempts,
+            self.last_error
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn retries() -> Retries {
+        Retries::new(Config {
+            initial: Duration::from_secs(10),
+            max_interval: Duration::from_secs(300),
+            max_elapsed: Duration::from_secs(90),
+        })
+    }
+
+    #[test]
+    fn delay_doubles_up_to_max_interval() {
+        let r = retries();
+        assert_eq!(r.delay(1), Duration::from_secs(10));
+        assert_eq!(r.delay(2), Duration::from_secs(20));
+        assert_eq!(r.delay(5), Duration::from_secs(160));
+        assert_eq!(r.delay(6), Duration::from_secs(300));
+        assert_eq!(r.delay(64), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn failures_are_reported_until_success() {
+        let mut r = retries();
+        let now = SystemTime::now();
+        for _ in 0..REPORT_AFTER_ATTEMPTS - 1 {
+            r.failed(Task::Balances, "rpc unreachable".to_string(), now);
+        }
+        assert!(r.reported().is_empty());
+        r.failed(Task::Balances, "rpc unreachable".to_string(), now);
+        let reported = r.reported();
+        assert_eq!(reported.len(), 1);
+        assert_eq!(reported[0].attempts, REPORT_AFTER_ATTEMPTS);
+        r.succeeded(Task::Balances);
+        assert!(r.reported().is_empty());
+        let total: Vec<_> = r.total_failures().collect();
+        assert_eq!(total, vec![(Task::Balances, u64::from(REPORT_AFTER_ATTEMPTS))]);
+    }
+}
@@ -0,0 +1,273 @@
+//! Snapshots of the worker state directory: node database, identity and safe configuration.
+//!
+//! A backup is a gzipped tarball of the state home without its cache directory. It is created
+//! while the worker is stopped so the node database is not written to, and restored by the root
+//! service on startup before the worker is spawned.
+use thiserror::Error;
+use tokio::fs;
+use tokio::process::Command;
+
+use std::collections::BTreeSet;
+use std::os::unix::fs::{OpenOptionsExt, fchown, lchown};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use crate::dirs;
+use crate::shell_command_ext::{self, Logs, RunOptions, ShellCommandExt};
+
+pub const ENV_VAR_RESTORE: &str = "GNOSISVPN_RESTORE_BACKUP";
+
+/// Appended to a restored archive so it is not restored again on the next start.
+const RESTORED_SUFFIX: &str = "restored";
+/// Extraction target inside the state home, on the same file system for atomic renames.
+const STAGING_DIR: &str = ".restore";
+/// Compressing a grown node database takes a while.
+const TAR_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Backup path must be absolute: {0}")]
+    RelativePath(PathBuf),
+    #[error("Backup path must be outside the state directory: {0}")]
+    InsideStateHome(PathBuf),
+    #[error("Backup path already exists: {0}")]
+    AlreadyExists(PathBuf),
+    #[error("Archive entry leaves the state directory: {0}")]
+    UnsafeEntry(String),
+    #[error("Archive holds no node configuration")]
+    NoConfig,
+    #[error("tar error: {0}")]
+    Tar(#[from] shell_command_ext::Error),
+    #[error("IO error: {0}")]
+    IO(#[from] std::io::Error),
+}
+
+/// Archives the state home into `target`, owned by `owner`, and returns the archive size in bytes.
+/// The archive holds the identity pass, so it is created only readable by its owner and never
+/// follows or replaces an existing path: the requester picks the location but root writes it.
+pub async fn create(state_home: &Path, target: &Path, owner: u32) -> Result<u64, Error> {
+    if !target.is_absolute() {
+        return Err(Error::RelativePath(target.to_path_buf()));
+    }
+    if target.starts_with(state_home) {
+        return Err(Error::InsideStateHome(target.to_path_buf()));
+    }
+
+    let file = match std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(target)
+    {
+        Ok(file) => file,
+        Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+            return Err(Error::AlreadyExists(target.to_path_buf()));
+        }
+        Err(error) => return Err(error.into()),
+    };
+    let res = archive(state_home, &file, owner).await;
+    if res.is_err() {
+        let _ = fs::remove_file(target).await;
+    }
+    res
+}
+
+async fn archive(state_home: &Path, file: &std::fs::File, owner: u32) -> Result<u64, Error> {
+    fchown(file, Some(owner), None)?;
+    Command::new("tar")
+        .arg("-czf")
+        .arg("-")
+        .arg("-C")
+        .arg(state_home)
+        .arg(format!("--exclude=./{}", dirs::CACHE_DIRECTORY))
+        .arg(format!("--exclude=./{STAGING_DIR}"))
+        .arg(".")
+        .run_into(file.try_clone()?, RunOptions::new(Logs::Print).timeout(TAR_TIMEOUT))
+        .await?;
+    Ok(file.metadata()?.len())
+}
+
+/// Replaces everything the archive holds inside the state home, owned by the worker user.
+/// The archive is renamed afterwards so a service restart does not roll the node back again.
+/// Returns the new archive location.
+pub async fn restore(archive: &Path, state_home: &Path, uid: u32, gid: u32) -> Result<PathBuf, Error> {
+    let listing = Command::new("tar")
+        .arg("-tzf")
+        .arg(archive)
+        .run_stdout(RunOptions::new(Logs::Print).timeout(TAR_TIMEOUT))
+        .await?;
+    let entries = check_entries(&listing)?;
+
+    let staging = state_home.join(STAGING_DIR);
+    let _ = fs::remove_dir_all(&staging).await;
+    fs::create_dir(&staging).await?;
+    let res = swap_in(archive, state_home, &staging, &entries).await;
+    let _ = fs::remove_dir_all(&staging).await;
+    res?;
+
+    for entry in &entries {
+        lchown(state_home.join(entry), Some(uid), Some(gid))?;
+    }
+
+    let restored = with_suffix(archive, RESTORED_SUFFIX);
+    fs::rename(archive, &restored).await?;
+    Ok(restored)
+}
+
+/// Extracts into `staging` and moves every top level entry over its existing counterpart,
+/// so files of the replaced node database do not linger next to the restored ones.
+async fn swap_in(archive: &Path, state_home: &Path, staging: &Path, entries: &[PathBuf]) -> Result<(), Error> {
+    Command::new("tar")
+        .arg("-xzf")
+        .arg(archive)
+        .arg("-C")
+        .arg(staging)
+        .run(RunOptions::new(Logs::Print).timeout(TAR_TIMEOUT))
+        .await?;
+
+    for name in top_level(entries) {
+        let target = state_home.join(&name);
+        match fs::symlink_metadata(&target).await {
+            Ok(meta) if meta.is_dir() => fs::remove_dir_all(&target).await?,
+            Ok(_) => fs::remove_file(&target).await?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => (),
+            Err(error) => return Err(error.into()),
+        }
+        fs::rename(staging.join(&name), &target).await?;
+    }
+    Ok(())
+}
+
+/// Relative paths of a `tar -t` listing, refusing anything that could end up outside the state home.
+fn check_entries(listing: &str) -> Result<Vec<PathBuf>, Error> {
+    let mut entries = Vec::new();
+    for line in listing.lines().filter(|l| !l.is_empty()) {
+        let mut path = PathBuf::new();
+        for component in Path::new(line).components() {
+            match component {
+                Component::Normal(part) => path.push(part),
+                Component::CurDir => (),
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                    return Err(Error::UnsafeEntry(line.to_string()));
+                }
+            }
+        }
+        if !path.as_os_str().is_empty() {
+            entries.push(path);
+        }
+    }
+
+    let config = Path::new(dirs::CONFIG_DIRECTORY);
+    if !entries.iter().any(|e| e.starts_with(config) && e != config) {
+        return Err(Error::NoConfig);
+    }
+    Ok(entries)
+}
+
+fn top_level(entries: &[PathBuf]) -> BTreeSet<PathBuf> {
+    entries
+        .iter()
+        .filter_map(|e| e.components().next())
+        .map(|c| PathBuf::from(c.as_os_str()))
+        .collect()
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{suffix}"));
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::os::unix::fs::MetadataExt;
+
+    #[test]
+    fn accepts_state_home_listing() -> anyhow::Result<()> {
+        let listing =
+            "./\n./.config/\n./.config/gnosisvpn-hopr.id\n./.config/gnosisvpn-hopr.safe\n./db/\n./db/index.sqlite\n";
+        let entries = check_entries(listing)?;
+        assert_eq!(entries.len(), 5);
+        assert_eq!(
+            top_level(&entries).into_iter().collect::<Vec<_>>(),
+            vec![PathBuf::from(".config"), PathBuf::from("db")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_entries_leaving_the_state_home() {
+        for listing in [
+            "./.config/gnosisvpn-hopr.id\n../etc/passwd\n",
+            "./.config/gnosisvpn-hopr.id\n/etc/passwd\n",
+            "./.config/gnosisvpn-hopr.id\n./db/../../etc/passwd\n",
+        ] {
+            assert!(
+                matches!(check_entries(listing), Err(Error::UnsafeEntry(_))),
+                "{listing}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_archives_without_config() {
+        assert!(matches!(
+            check_entries("./\n./.config/\n./db/data\n"),
+            Err(Error::NoConfig)
+        ));
+    }
+
+    #[tokio::test]
+    async fn restores_what_was_backed_up() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let home = dir.path().join("home");
+        std::fs::create_dir_all(home.join(".config"))?;
+        std::fs::create_dir_all(home.join(".cache"))?;
+        std::fs::create_dir_all(home.join("db"))?;
+        std::fs::write(home.join(".config/gnosisvpn-hopr.id"), "identity")?;
+        std::fs::write(home.join(".cache/telemetry"), "cached")?;
+        std::fs::write(home.join("db/data"), "before")?;
+
+        let meta = std::fs::metadata(&home)?;
+        let archive = dir.path().join("backup.tar.gz");
+        assert!(create(&home, &archive, meta.uid()).await? > 0);
+        assert_eq!(std::fs::metadata(&archive)?.mode() & 0o777, 0o600);
+        assert!(matches!(
+            create(&home, &archive, meta.uid()).await,
+            Err(Error::AlreadyExists(_))
+        ));
+
+        std::fs::write(home.join("db/data"), "after")?;
+        std::fs::write(home.join("db/stale"), "stale")?;
+        let restored = restore(&archive, &home, meta.uid(), meta.gid()).await?;
+
+        assert_eq!(std::fs::read_to_string(home.join("db/data"))?, "before");
+        assert!(!home.join("db/stale").exists());
+        assert_eq!(std::fs::read_to_string(home.join(".cache/telemetry"))?, "cached");
+        assert!(!home.join(STAGING_DIR).exists());
+        assert!(!archive.exists() && restored.exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn does_not_follow_a_planted_symlink() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let home = dir.path().join("home");
+        std::fs::create_dir_all(home.join(".config"))?;
+        let victim = dir.path().join("victim");
+        std::fs::write(&victim, "untouched")?;
+        let archive = dir.path().join("backup.tar.gz");
+        std::os::unix::fs::symlink(&victim, &archive)?;
+
+        let uid = std::fs::metadata(&home)?.uid();
+        assert!(matches!(
+            create(&home, &archive, uid).await,
+            Err(Error::AlreadyExists(_))
+        ));
+        assert_eq!(std::fs::read_to_string(&victim)?, "untouched");
+        Ok(())
+    }
+}
@@ -1,10 +1,14 @@
+use edgli::hopr_lib::api::chain::DeployedSafe;
 pub use edgli::hopr_lib::api::types::primitive::prelude::{Address, Balance, WxHOPR, XDai};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::compat::SafeModule;
 use crate::serde_utils;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Display};
+use std::time::Duration;
 
 /// wxHOPR amounts (in whole tokens, i.e. the value returned by
 /// `Balance::amount_in_base_units` after the wei→token conversion) below this are
@@ -34,8 +38,9 @@ pub fn wxhopr_scientific(b: Balance<WxHOPR>) -> Option<String> {
 }
 
 // in order of priority
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum FundingIssue {
+    ModulePermissions,  // external safe module does not let the node operate, see the worker log
     Unfunded,           // node xdai zero and no funds in safe or channels - initial state
     ChannelsOutOfFunds, // less than 1 message available in all channels combined
     SafeOutOfFunds,     // less than 1 message available in safe
@@ -55,6 +60,7 @@ pub enum FundingTool {
 impl Display for FundingIssue {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let s = match self {
+            FundingIssue::ModulePermissions => "safe module lacks permissions for the node - ask the safe owners",
             FundingIssue::Unfunded => "unfunded - nothing will work",
             FundingIssue::ChannelsOutOfFunds => "channels are out of funds - connections will not work",
             FundingIssue::SafeOutOfFunds => "safe is out of funds - connections will stop working",
@@ -70,11 +76,15 @@ impl Display for FundingIssue {
 
 /// Which entity holds a wxHOPR stake: either an open outgoing channel to a peer,
 /// or the unallocated balance in the Safe contract.
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(tag = "type", content = "address", rename_all = "snake_case")]
 pub enum CapacityAllocator {
     Safe,
-    Peer(#[serde(with = "serde_utils::address")] Address),
+    Peer(
+        #[serde(with = "serde_utils::address")]
+        #[schemars(with = "String")]
+        Address,
+    ),
 }
 
 impl From<edgli::strategy::CapacityAllocator> for CapacityAllocator {
@@ -95,10 +105,22 @@ impl Display for CapacityAllocator {
     }
 }
 
+/// Peers we currently hold an outgoing channel to, according to the capacity allocations.
+pub(crate) fn channel_peers(allocations: &HashMap<CapacityAllocator, Capacity>) -> HashSet<Address> {
+    allocations
+        .keys()
+        .filter_map(|k| match k {
+            CapacityAllocator::Peer(addr) => Some(*addr),
+            CapacityAllocator::Safe => None,
+        })
+        .collect()
+}
+
 /// Data-throughput capacity for a wxHOPR stake at the current ticket price.
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Capacity {
     #[serde(with = "serde_utils::balance")]
+    #[schemars(with = "String")]
     pub stake: Balance<WxHOPR>,
     pub expected_messages: u64,
     pub min_guaranteed_messages: u64,
@@ -107,7 +129,7 @@ pub struct Capacity {
 
 /// A single capacity entry pairing an allocator with its capacity.
 /// Used in status responses instead of a HashMap so JSON keys remain strings.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct CapacityEntry {
     pub allocator: CapacityAllocator,
     pub capacity: Capacity,
@@ -126,11 +148,13 @@ impl From<edgli::strategy::Capacity> for Capacity {
 
 /// Minimum recommended wxHOPR and xDAI balance to open the target number of channels.
 /// Computed once during onboarding and surfaced in the PreparingSafe run mode.
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct BalanceRecommendation {
     #[serde(with = "serde_utils::balance")]
+    #[schemars(with = "String")]
     pub wxhopr: Balance<WxHOPR>,
     #[serde(with = "serde_utils::balance")]
+    #[schemars(with = "String")]
     pub xdai: Balance<XDai>,
 }
 
@@ -155,6 +179,89 @@ impl Display for PreSafe {
     }
 }
 
+/// Polling of the node balances once hopr is running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    /// Regular refresh interval. On-chain events of outgoing channels and finished disconnects
+    /// trigger an immediate refresh in between.
+    #[serde(with = "humantime_serde")]
+    pub refresh_interval: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            refresh_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Safe and module set up outside the client, e.g. by an organisation's treasury.
+///
+/// Onboarding is skipped and the client never moves funds: node wxHOPR stays on the node and
+/// channels are neither opened nor topped up. Funding needs are only reported.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalSafe {
+    #[serde(with = "serde_utils::address")]
+    pub safe_address: Address,
+    #[serde(with = "serde_utils::address")]
+    pub module_address: Address,
+}
+
+impl ExternalSafe {
+    /// Whether the safe module found for the node is the configured one.
+    pub(crate) fn matches(&self, safe_module: &SafeModule) -> bool {
+        safe_module.safe_address.parse::<Address>().ok() == Some(self.safe_address)
+            && safe_module.module_address.parse::<Address>().ok() == Some(self.module_address)
+    }
+
+    /// What keeps the configured module from serving `node_address`, given the safe as registered
+    /// on chain and the wxHOPR allowance it grants the channels contract.
+    pub(crate) fn module_issues(
+        &self,
+        node_address: Address,
+        safe: Option<&DeployedSafe>,
+        allowance: Balance<WxHOPR>,
+    ) -> Vec<ModuleIssue> {
+        let Some(safe) = safe else {
+            return vec![ModuleIssue::SafeNotFound];
+        };
+        let mut issues = Vec::new();
+        if safe.module != self.module_address {
+            issues.push(ModuleIssue::OtherModule(safe.module));
+        }
+        if !safe.registered_nodes.contains(&node_address) {
+            issues.push(ModuleIssue::NodeNotRegistered);
+        }
+        if allowance.is_zero() {
+            issues.push(ModuleIssue::NoAllowance);
+        }
+        issues
+    }
+}
+
+/// Missing permission of an external safe module, see [`ExternalSafe::module_issues`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ModuleIssue {
+    SafeNotFound,
+    /// The safe is set up with a different module
+    OtherModule(Address),
+    NodeNotRegistered,
+    /// Channels cannot be funded from the safe
+    NoAllowance,
+}
+
+impl Display for ModuleIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModuleIssue::SafeNotFound => write!(f, "safe not found on chain"),
+            ModuleIssue::OtherModule(module) => write!(f, "safe uses module {module} instead of the configured one"),
+            ModuleIssue::NodeNotRegistered => write!(f, "node is not registered with the safe"),
+            ModuleIssue::NoAllowance => write!(f, "safe grants the channels contract no wxHOPR allowance"),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Balances {
     pub node_xdai: Balance<XDai>,
@@ -400,4 +507,55 @@ mod tests {
     fn wxhopr_scientific_above_threshold_is_none() {
         assert_eq!(wxhopr_scientific(Balance::<WxHOPR>::from(SCI_THRESHOLD_WEI + 1)), None);
     }
+
+    #[test]
+    fn external_safe_matches_regardless_of_address_case() {
+        let external = ExternalSafe {
+            safe_address: Address::from([1u8; 20]),
+            module_address: Address::from([2u8; 20]),
+        };
+        let found = SafeModule {
+            safe_address: external.safe_address.to_checksum(),
+            module_address: external.module_address.to_string().to_lowercase(),
+        };
+        assert!(external.matches(&found));
+        let other = SafeModule {
+            module_address: Address::from([3u8; 20]).to_string(),
+            ..found
+        };
+        assert!(!external.matches(&other));
+    }
+
+    #[test]
+    fn external_safe_module_issues() {
+        let node = Address::from([9u8; 20]);
+        let external = ExternalSafe {
+            safe_address: Address::from([1u8; 20]),
+            module_address: Address::from([2u8; 20]),
+        };
+        let mut safe = DeployedSafe {
+            address: external.safe_address,
+            owners: vec![Address::from([4u8; 20])],
+            module: external.module_address,
+            registered_nodes: vec![node],
+            deployer: Address::from([4u8; 20]),
+        };
+        let allowance = Balance::<WxHOPR>::from(1u64);
+        assert!(external.module_issues(node, Some(&safe), allowance).is_empty());
+        assert_eq!(
+            external.module_issues(node, None, allowance),
+            vec![ModuleIssue::SafeNotFound]
+        );
+
+        safe.module = Address::from([3u8; 20]);
+        safe.registered_nodes.clear();
+        assert_eq!(
+            external.module_issues(node, Some(&safe), Balance::<WxHOPR>::zero()),
+            vec![
+                ModuleIssue::OtherModule(Address::from([3u8; 20])),
+                ModuleIssue::NodeNotRegistered,
+                ModuleIssue::NoAllowance
+            ]
+        );
+    }
 }
@@ -0,0 +1,164 @@
+//! Balance snapshots over time, listed by [`crate::command::Command::BalanceHistory`].
+//!
+//! Balances are refreshed every minute, a snapshot is kept at most every [`SNAPSHOT_INTERVAL`]
+//! for [`RETENTION`] so GUIs can plot spend without the file growing unbounded.
+use edgli::hopr_lib::api::types::primitive::prelude::{Balance, WxHOPR, XDai};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::balance::Balances;
+use crate::serde_utils;
+
+pub const BALANCE_HISTORY_FILE: &str = "balance-history.json";
+
+pub const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(15 * 60);
+pub const RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "BalancePoint")]
+pub struct Point {
+    #[serde(with = "serde_utils::system_time")]
+    #[schemars(with = "u64")]
+    pub at: SystemTime,
+    #[serde(with = "serde_utils::balance")]
+    #[schemars(with = "String")]
+    pub node_xdai: Balance<XDai>,
+    #[serde(with = "serde_utils::balance")]
+    #[schemars(with = "String")]
+    pub safe_wxhopr: Balance<WxHOPR>,
+    /// Combined stake of all outgoing channels
+    #[serde(with = "serde_utils::balance")]
+    #[schemars(with = "String")]
+    pub channels_out_wxhopr: Balance<WxHOPR>,
+}
+
+/// Persists balance snapshots across worker restarts.
+pub struct History {
+    path: PathBuf,
+    points: VecDeque<Point>,
+}
+
+impl History {
+    pub fn load(path: PathBuf) -> Self {
+        let points = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|error| {
+                tracing::warn!(?error, ?path, "discarding unreadable balance history file");
+                VecDeque::new()
+            }),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => VecDeque::new(),
+            Err(error) => {
+                tracing::warn!(?error, ?path, "unable to read balance history file");
+                VecDeque::new()
+            }
+        };
+        Self { path, points }
+    }
+
+    /// Keeps a snapshot of `balances` unless the latest one is more recent than [`SNAPSHOT_INTERVAL`].
+    pub fn record(&mut self, balances: &Balances, now: SystemTime) {
+        let due = self
+            .points
+            .back()
+            .is_none_or(|last| now.duration_since(last.at).unwrap_or_default() >= SNAPSHOT_INTERVAL);
+        if !due {
+            return;
+        }
+        self.points.push_back(Point {
+            at: now,
+            node_xdai: balances.node_xdai,
+            safe_wxhopr: balances.safe_wxhopr,
+            channels_out_wxhopr: balances.channels_out.values().copied().sum(),
+        });
+        while self
+            .points
+            .front()
+            .is_some_and(|first| now.duration_since(first.at).unwrap_or_default() > RETENTION)
+        {
+            self.points.pop_front();
+        }
+        self.persist();
+    }
+
+    /// Snapshots of the last `days`, oldest first.
+    pub fn since(&self, days: u32, now: SystemTime) -> Vec<Point> {
+        let from = now
+            .checked_sub(Duration::from_secs(u64::from(days) * SECS_PER_DAY))
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        self.points.iter().filter(|p| p.at >= from).cloned().collect()
+    }
+
+    fn persist(&self) {
+        let res = serde_json::to_string(&self.points)
+            .map_err(std::io::Error::other)
+            .and_then(|content| fs::write(&self.path, content));
+        if let Err(error) = res {
+            tracing::warn!(?error, path = ?self.path, "failed to persist balance history");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use edgli::hopr_lib::api::types::primitive::prelude::Address;
+    use std::collections::HashMap;
+    use tempfile::tempdir;
+
+    fn balances(safe: u64, channel: u64) -> Balances {
+        let mut channels_out = HashMap::new();
+        channels_out.insert(Address::from([1u8; 20]), Balance::<WxHOPR>::from(channel));
+        channels_out.insert(Address::from([2u8; 20]), Balance::<WxHOPR>::from(channel));
+        Balances {
+            node_xdai: Balance::<XDai>::from(1u64),
+            safe_wxhopr: Balance::<WxHOPR>::from(safe),
+            channels_out,
+        }
+    }
+
+    #[test]
+    fn snapshots_are_spaced_and_survive_reload() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(BALANCE_HISTORY_FILE);
+        let start = SystemTime::UNIX_EPOCH + RETENTION;
+        let mut history = History::load(path.clone());
+        history.record(&balances(100, 10), start);
+        history.record(&balances(90, 10), start + Duration::from_secs(60));
+        history.record(&balances(80, 5), start + SNAPSHOT_INTERVAL);
+
+        let points = History::load(path).since(1, start + SNAPSHOT_INTERVAL);
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].safe_wxhopr, Balance::<WxHOPR>::from(100u64));
+        assert_eq!(points[1].channels_out_wxhopr, Balance::<WxHOPR>::from(10u64));
+    }
+
+    #[test]
+    fn old_snapshots_are_dropped_and_filtered() {
+        let dir = tempdir().unwrap();
+        let start = SystemTime::UNIX_EPOCH + RETENTION;
+        let mut history = History::load(dir.path().join(BALANCE_HISTORY_FILE));
+        history.record(&balances(100, 10), start);
+        history.record(&balances(90, 10), start + Duration::from_secs(3 * SECS_PER_DAY));
+        let now = start + Duration::from_secs(3 * SECS_PER_DAY + 60);
+        assert_eq!(history.since(1, now).len(), 1);
+        assert_eq!(history.since(7, now).len(), 2);
+
+        history.record(&balances(80, 10), start + RETENTION + Duration::from_secs(60));
+        assert_eq!(history.since(u32::MAX, start + RETENTION).len(), 2);
+    }
+
+    #[test]
+    fn unreadable_file_starts_empty() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(BALANCE_HISTORY_FILE);
+        fs::write(&path, "not json").unwrap();
+        assert!(History::load(path).since(u32::MAX, SystemTime::now()).is_empty());
+    }
+}
@@ -0,0 +1,304 @@
+//! Daily wxHOPR spend budget.
+//!
+//! Spend is estimated from successive balance snapshots: any decrease of the combined
+//! safe and outgoing channel stake counts as spent, increases (funding, withdrawals from
+//! the node into the safe) are ignored. Moving funds from the safe into a channel does not
+//! change the combined stake and therefore does not count.
+//! Usage is persisted so a worker restart does not hand out a fresh budget.
+use edgli::hopr_lib::api::types::primitive::prelude::{Balance, WxHOPR};
+use human_bandwidth::re::bandwidth::Bandwidth;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::balance::Balances;
+use crate::serde_utils;
+
+pub const BUDGET_FILE: &str = "budget.json";
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// What to do once the daily budget is used up.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Only log and report the exceeded budget in status.
+    #[default]
+    Warn,
+    /// Lower the SURB upstream of the main session to reduce spending.
+    Throttle,
+    /// Tear down the active connection and refuse new ones until the budget resets.
+    Disconnect,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    /// Maximum wxHOPR spend per UTC day, `None` disables budgeting.
+    #[serde(with = "serde_utils::opt_balance")]
+    pub daily_limit: Option<Balance<WxHOPR>>,
+    pub action: Action,
+    /// SURB upstream applied to the main session when [`Action::Throttle`] kicks in.
+    pub throttle_max_surb_upstream: Bandwidth,
+}
+
+/// Snapshot of the current budget usage, reported in status.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "BudgetUsage")]
+pub struct Usage {
+    #[serde(with = "serde_utils::balance")]
+    #[schemars(with = "String")]
+    pub spent: Balance<WxHOPR>,
+    #[serde(with = "serde_utils::balance")]
+    #[schemars(with = "String")]
+    pub limit: Balance<WxHOPR>,
+    pub action: Action,
+    pub exceeded: bool,
+    #[serde(with = "serde_utils::system_time")]
+    #[schemars(with = "u64")]
+    pub resets_at: SystemTime,
+}
+
+#[derive(Clone, Debug)]
+pub struct Tracker {
+    config: Config,
+    path: PathBuf,
+    state: State,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct State {
+    day: u64,
+    #[serde(with = "serde_utils::balance")]
+    spent: Balance<WxHOPR>,
+    #[serde(with = "serde_utils::opt_balance")]
+    last_total: Option<Balance<WxHOPR>>,
+    exceeded: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            daily_limit: None,
+            action: Action::default(),
+            throttle_max_surb_upstream: Bandwidth::from_kbps(512),
+        }
+    }
+}
+
+impl Tracker {
+    pub fn load(path: PathBuf, config: Config) -> Self {
+        let fresh = || State {
+            day: day_index(SystemTime::now()),
+            spent: Balance::<WxHOPR>::zero(),
+            last_total: None,
+            exceeded: false,
+        };
+        let state = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|error| {
+                tracing::warn!(?error, ?path, "discarding unreadable budget file");
+                fresh()
+            }),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => fresh(),
+            Err(error) => {
+                tracing::warn!(?error, ?path, "unable to read budget file");
+                fresh()
+            }
+        };
+        Self { config, path, state }
+    }
+
+    pub fn action(&self) -> Action {
+        self.config.action
+    }
+
+    pub fn throttle_max_surb_upstream(&self) -> Bandwidth {
+        self.config.throttle_max_surb_upstream
+    }
+
+    /// True while the budget is exceeded and configured to block connections.
+    pub fn blocks_connections(&self) -> bool {
+        self.state.exceeded && self.config.action == Action::Disconnect
+    }
+
+    /// True while the budget is exceeded and new sessions should be throttled.
+    pub fn throttles(&self) -> bool {
+        self.state.exceeded && self.config.action == Action::Throttle
+    }
+
+    /// Feed a new balance snapshot into the tracker.
+    /// Returns true exactly once per day, when the budget was crossed by this snapshot.
+    pub fn record(&mut self, balances: &Balances, now: SystemTime) -> bool {
+        let Some(limit) = self.config.daily_limit else {
+            return false;
+        };
+        let before = self.state.clone();
+        let state = &mut self.state;
+        let today = day_index(now);
+        if today != state.day {
+            state.day = today;
+            state.spent = Balance::<WxHOPR>::zero();
+            state.exceeded = false;
+        }
+
+        let total = balances.safe_wxhopr + balances.channels_out.values().copied().sum::<Balance<WxHOPR>>();
+        if let Some(last) = state.last_total
+            && last > total
+        {
+            state.spent = state.spent + (last - total);
+        }
+        state.last_total = Some(total);
+
+        let crossed = !state.exceeded && state.spent >= limit;
+        state.exceeded |= crossed;
+        if self.state != before {
+            self.persist();
+        }
+        crossed
+    }
+
+    pub fn usage(&self) -> Option<Usage> {
+        self.config.daily_limit.map(|limit| Usage {
+            spent: self.state.spent,
+            limit,
+            action: self.config.action,
+            exceeded: self.state.exceeded,
+            resets_at: UNIX_EPOCH + Duration::from_secs((self.state.day + 1) * SECS_PER_DAY),
+        })
+    }
+
+    fn persist(&self) {
+        let res = serde_json::to_string(&self.state)
+            .map_err(std::io::Error::other)
+            .and_then(|content| fs::write(&self.path, content));
+        if let Err(error) = res {
+            tracing::warn!(?error, path = ?self.path, "failed to persist budget");
+        }
+    }
+}
+
+fn day_index(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO).as_secs() / SECS_PER_DAY
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Action::Warn => "warn",
+            Action::Throttle => "throttle",
+            Action::Disconnect => "disconnect",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Daily budget: {} of {} spent", self.spent, self.limit)?;
+        if self.exceeded {
+            write!(f, " - exceeded, action: {}", self.action)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use edgli::hopr_lib::api::types::primitive::prelude::{Address, XDai};
+    use tempfile::{TempDir, tempdir};
+
+    fn balances(safe: u64, channel: u64) -> Balances {
+        let mut channels_out = HashMap::new();
+        channels_out.insert(Address::from([1u8; 20]), Balance::<WxHOPR>::from(channel));
+        Balances {
+            node_xdai: Balance::<XDai>::zero(),
+            safe_wxhopr: Balance::<WxHOPR>::from(safe),
+            channels_out,
+        }
+    }
+
+    fn config(limit: u64, action: Action) -> Config {
+        Config {
+            daily_limit: Some(Balance::<WxHOPR>::from(limit)),
+            action,
+            ..Default::default()
+        }
+    }
+
+    fn tracker(limit: u64, action: Action) -> (TempDir, Tracker) {
+        let dir = tempdir().unwrap();
+        let t = Tracker::load(dir.path().join(BUDGET_FILE), config(limit, action));
+        (dir, t)
+    }
+
+    #[test]
+    fn disabled_budget_never_exceeds() {
+        let dir = tempdir().unwrap();
+        let mut t = Tracker::load(dir.path().join(BUDGET_FILE), Config::default());
+        let now = SystemTime::now();
+        assert!(!t.record(&balances(100, 100), now));
+        assert!(!t.record(&balances(0, 0), now));
+        assert!(t.usage().is_none());
+    }
+
+    #[test]
+    fn moving_funds_into_channels_is_not_spend() {
+        let (_dir, mut t) = tracker(10, Action::Warn);
+        let now = SystemTime::now();
+        t.record(&balances(100, 0), now);
+        t.record(&balances(50, 50), now);
+        assert_eq!(t.usage().unwrap().spent, Balance::<WxHOPR>::zero());
+    }
+
+    #[test]
+    fn funding_increase_is_ignored() {
+        let (_dir, mut t) = tracker(10, Action::Warn);
+        let now = SystemTime::now();
+        t.record(&balances(100, 50), now);
+        t.record(&balances(100, 45), now);
+        t.record(&balances(200, 45), now);
+        assert_eq!(t.usage().unwrap().spent, Balance::<WxHOPR>::from(5u64));
+    }
+
+    #[test]
+    fn exceeding_reports_once() {
+        let (_dir, mut t) = tracker(10, Action::Disconnect);
+        let now = SystemTime::now();
+        assert!(!t.record(&balances(100, 50), now));
+        assert!(t.record(&balances(100, 38), now));
+        assert!(!t.record(&balances(100, 30), now));
+        assert!(t.blocks_connections());
+    }
+
+    #[test]
+    fn usage_resets_on_new_day() {
+        let (_dir, mut t) = tracker(10, Action::Disconnect);
+        let now = SystemTime::now();
+        t.record(&balances(100, 50), now);
+        t.record(&balances(100, 30), now);
+        assert!(t.blocks_connections());
+        let tomorrow = now + Duration::from_secs(SECS_PER_DAY);
+        assert!(!t.record(&balances(100, 30), tomorrow));
+        assert!(!t.blocks_connections());
+        assert_eq!(t.usage().unwrap().spent, Balance::<WxHOPR>::zero());
+    }
+
+    #[test]
+    fn usage_survives_reload() {
+        let (dir, mut t) = tracker(10, Action::Disconnect);
+        let now = SystemTime::now();
+        t.record(&balances(100, 50), now);
+        t.record(&balances(100, 38), now);
+        let mut reloaded = Tracker::load(dir.path().join(BUDGET_FILE), config(10, Action::Disconnect));
+        assert!(reloaded.blocks_connections());
+        assert!(!reloaded.record(&balances(100, 35), now));
+        assert_eq!(reloaded.usage().unwrap().spent, Balance::<WxHOPR>::from(15u64));
+    }
+}
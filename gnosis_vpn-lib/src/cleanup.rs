@@ -0,0 +1,335 @@
+//! Leftovers of a crashed service, removed by `gnosis_vpn-ctl cleanup`.
+//!
+//! The service tears down interface, routes and firewall rules when it stops. A crash or a killed
+//! process leaves them behind and they get in the way of the next connection. Only what carries the
+//! names and markers the service uses is touched: the [`wireguard::WG_INTERFACE`] interface and its
+//! config file holding the tunnel private key, routes tagged with [`wireguard::ROUTE_PROTOCOL`],
+//! `gnosis_vpn_*` nftables tables or the killswitch pf anchor, the moved aside `/etc/resolv.conf`
+//! and the service socket file. Bypass routes on macOS carry no marker and are left to the next
+//! connection to replace.
+//!
+//! A running service holds the [`LOCK_FILE`] lock, which is released with the process even if it
+//! was killed, so a hanging service is not mistaken for a crashed one.
+use thiserror::Error;
+use tokio::fs;
+use tokio::process::Command;
+
+use std::fmt::{self, Display};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::dirs;
+use crate::killswitch;
+use crate::shell_command_ext::{self, Logs, ShellCommandExt};
+use crate::socket;
+use crate::wireguard;
+
+/// Locked by the running root process, in its [`dirs::root_home`].
+pub const LOCK_FILE: &str = "service.lock";
+
+/// Tables of killswitch, gateway and traffic marking share this prefix.
+#[cfg(target_os = "linux")]
+const NFT_TABLE_PREFIX: &str = "gnosis_vpn_";
+#[cfg(target_os = "linux")]
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+
+/// wg-quick keeps the utun name of an interface here, wireguard-go exits once its socket is gone.
+#[cfg(target_os = "macos")]
+const WG_RUN_DIR: &str = "/var/run/wireguard";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Command error: {0}")]
+    Command(#[from] shell_command_ext::Error),
+    #[error("IO error: {0}")]
+    IO(#[from] io::Error),
+    #[error("Killswitch error: {0}")]
+    Killswitch(#[from] killswitch::Error),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Leftover {
+    /// On macOS the utun device wg-quick mapped the interface to
+    Interface(String),
+    #[cfg(target_os = "linux")]
+    Routes {
+        family: IpFamily,
+        count: usize,
+    },
+    #[cfg(target_os = "linux")]
+    FirewallTable {
+        family: String,
+        name: String,
+    },
+    #[cfg(target_os = "macos")]
+    KillswitchAnchor,
+    /// The original `/etc/resolv.conf` moved aside by the `file` DNS strategy
+    #[cfg(target_os = "linux")]
+    ResolvConfBackup,
+    ConfigFile(PathBuf),
+    Socket(PathBuf),
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IpFamily {
+    V4,
+    V6,
+}
+
+#[cfg(target_os = "linux")]
+impl IpFamily {
+    fn flag(self) -> &'static str {
+        match self {
+            IpFamily::V4 => "-4",
+            IpFamily::V6 => "-6",
+        }
+    }
+}
+
+impl Display for Leftover {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Leftover::Interface(name) => write!(f, "WireGuard interface {name}"),
+            #[cfg(target_os = "linux")]
+            Leftover::Routes { family, count } => {
+                let family = match family {
+                    IpFamily::V4 => "IPv4",
+                    IpFamily::V6 => "IPv6",
+                };
+                write!(f, "{count} {family} routes tagged proto {}", wireguard::ROUTE_PROTOCOL)
+            }
+            #[cfg(target_os = "linux")]
+            Leftover::FirewallTable { family, name } => write!(f, "nftables table {family} {name}"),
+            #[cfg(target_os = "macos")]
+            Leftover::KillswitchAnchor => write!(f, "killswitch pf anchor {}", killswitch::ANCHOR_NAME),
+            #[cfg(target_os = "linux")]
+            Leftover::ResolvConfBackup => write!(
+                f,
+                "original resolv.conf moved aside to {}",
+                wireguard::RESOLV_CONF_BACKUP
+            ),
+            Leftover::ConfigFile(path) => write!(f, "WireGuard config with private key {}", path.display()),
+            Leftover::Socket(path) => write!(f, "socket file {}", path.display()),
+        }
+    }
+}
+
+/// Locks [`LOCK_FILE`] below `root_home` for as long as the returned file stays open, `None` if
+/// another service holds it.
+pub fn lock_service(root_home: &Path) -> io::Result<Option<File>> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .mode(0o600)
+        .open(root_home.join(LOCK_FILE))?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(error)) => Err(error),
+    }
+}
+
+/// Whether a service process holds [`LOCK_FILE`] below `root_home`, answering on the socket or not.
+pub fn service_running(root_home: &Path) -> io::Result<bool> {
+    let file = match File::open(root_home.join(LOCK_FILE)) {
+        Ok(file) => file,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(error) => return Err(error),
+    };
+    match file.try_lock_shared() {
+        Ok(()) => Ok(false),
+        Err(TryLockError::WouldBlock) => Ok(true),
+        Err(TryLockError::Error(error)) => Err(error),
+    }
+}
+
+/// Everything the service left behind, in removal order.
+///
+/// Only meaningful while no service is running, a running one owns all of it.
+pub async fn find(cache_home: &Path, socket_path: &Path) -> Vec<Leftover> {
+    let mut found = system().await;
+    let config_file = dirs::cache_dir(cache_home.to_path_buf(), wireguard::WG_CONFIG_FILE);
+    if fs::try_exists(&config_file).await.unwrap_or(false) {
+        found.push(Leftover::ConfigFile(config_file));
+    }
+    if socket::root::abstract_name(socket_path).is_none() && fs::try_exists(socket_path).await.unwrap_or(false) {
+        found.push(Leftover::Socket(socket_path.to_path_buf()));
+    }
+    found
+}
+
+pub async fn remove(leftover: &Leftover) -> Result<(), Error> {
+    match leftover {
+        #[cfg(target_os = "linux")]
+        Leftover::Interface(name) => Command::new("ip")
+            .args(["link", "delete", "dev", name])
+            .run(Logs::Suppress)
+            .await
+            .map_err(Error::from),
+        #[cfg(target_os = "macos")]
+        Leftover::Interface(utun) => {
+            let run_dir = Path::new(WG_RUN_DIR);
+            // a stale name file outlives wireguard-go and its socket
+            match fs::remove_file(run_dir.join(format!("{utun}.sock"))).await {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                _ => (),
+            }
+            fs::remove_file(run_dir.join(format!("{}.name", wireguard::WG_INTERFACE)))
+                .await
+                .map_err(Error::from)
+        }
+        #[cfg(target_os = "linux")]
+        Leftover::Routes { family, .. } => {
+            let proto = wireguard::ROUTE_PROTOCOL.to_string();
+            Command::new("ip")
+                .args([family.flag(), "route", "flush", "proto", proto.as_str()])
+                .run(Logs::Suppress)
+                .await
+                .map_err(Error::from)
+        }
+        #[cfg(target_os = "linux")]
+        Leftover::FirewallTable { family, name } => Command::new("nft")
+            .args(["delete", "table", family, name])
+            .run(Logs::Suppress)
+            .await
+            .map_err(Error::from),
+        #[cfg(target_os = "macos")]
+        Leftover::KillswitchAnchor => {
+            let mut firewall = killswitch::Firewall::new()?;
+            firewall.reset_policy().map_err(Error::from)
+        }
+        #[cfg(target_os = "linux")]
+        Leftover::ResolvConfBackup => fs::rename(wireguard::RESOLV_CONF_BACKUP, RESOLV_CONF)
+            .await
+            .map_err(Error::from),
+        Leftover::ConfigFile(path) | Leftover::Socket(path) => fs::remove_file(path).await.map_err(Error::from),
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn system() -> Vec<Leftover> {
+    let mut found = Vec::new();
+    let interface = Command::new("ip")
+        .args(["link", "show", "dev", wireguard::WG_INTERFACE])
+        .run(Logs::Suppress)
+        .await;
+    if interface.is_ok() {
+        found.push(Leftover::Interface(wireguard::WG_INTERFACE.to_string()));
+    }
+    let proto = wireguard::ROUTE_PROTOCOL.to_string();
+    for family in [IpFamily::V4, IpFamily::V6] {
+        let routes = Command::new("ip")
+            .args(["-j", family.flag(), "route", "show", "proto", proto.as_str()])
+            .run_stdout(Logs::Suppress)
+            .await;
+        let count = routes.map(|out| count_routes(&out)).unwrap_or_default();
+        if count > 0 {
+            found.push(Leftover::Routes { family, count });
+        }
+    }
+    // no nft binary means the service could not have created tables either
+    if let Ok(out) = Command::new("nft")
+        .args(["-j", "list", "tables"])
+        .run_stdout(Logs::Suppress)
+        .await
+    {
+        found.extend(
+            owned_tables(&out)
+                .into_iter()
+                .map(|(family, name)| Leftover::FirewallTable { family, name }),
+        );
+    }
+    if fs::symlink_metadata(wireguard::RESOLV_CONF_BACKUP).await.is_ok() {
+        found.push(Leftover::ResolvConfBackup);
+    }
+    found
+}
+
+#[cfg(target_os = "macos")]
+async fn system() -> Vec<Leftover> {
+    let mut found = Vec::new();
+    let name_file = Path::new(WG_RUN_DIR).join(format!("{}.name", wireguard::WG_INTERFACE));
+    if let Ok(utun) = fs::read_to_string(&name_file).await {
+        found.push(Leftover::Interface(utun.trim().to_string()));
+    }
+    let rules = Command::new("pfctl")
+        .args(["-a", killswitch::ANCHOR_NAME, "-s", "rules"])
+        .run_stdout(Logs::Suppress)
+        .await;
+    if rules.is_ok_and(|rules| !rules.is_empty()) {
+        found.push(Leftover::KillswitchAnchor);
+    }
+    found
+}
+
+/// Entries of `ip -j route show` output.
+#[cfg(target_os = "linux")]
+fn count_routes(json: &str) -> usize {
+    serde_json::from_str::<Vec<serde_json::Value>>(json)
+        .map(|r| r.len())
+        .unwrap_or_default()
+}
+
+/// Family and name of the service's tables in `nft -j list tables` output.
+#[cfg(target_os = "linux")]
+fn owned_tables(json: &str) -> Vec<(String, String)> {
+    let Ok(listing) = serde_json::from_str::<serde_json::Value>(json) else {
+        return Vec::new();
+    };
+    listing["nftables"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let table = entry.get("table")?;
+            let family = table["family"].as_str()?;
+            let name = table["name"].as_str()?;
+            name.starts_with(NFT_TABLE_PREFIX)
+                .then(|| (family.to_string(), name.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_service_tables_and_tagged_routes_are_found() {
+        let tables = r#"{"nftables": [{"metainfo": {"version": "1.0.9", "json_schema_version": 1}},
+            {"table": {"family": "inet", "name": "gnosis_vpn_ks", "handle": 3}},
+            {"table": {"family": "inet", "name": "filter", "handle": 4}},
+            {"table": {"family": "inet", "name": "gnosis_vpn_gateway", "handle": 5}}]}"#;
+        assert_eq!(
+            owned_tables(tables),
+            vec![
+                ("inet".to_string(), "gnosis_vpn_ks".to_string()),
+                ("inet".to_string(), "gnosis_vpn_gateway".to_string()),
+            ]
+        );
+        assert!(owned_tables("").is_empty());
+
+        let routes = r#"[{"dst":"0.0.0.0/1","dev":"wg0_gnosisvpn","protocol":"152","scope":"link","flags":[]},
+            {"dst":"1.1.1.1","gateway":"192.168.1.1","dev":"wlan0","protocol":"152","flags":[]}]"#;
+        assert_eq!(count_routes(routes), 2);
+        assert_eq!(count_routes("[]"), 0);
+    }
+
+    #[test]
+    fn service_lock_is_held_until_dropped() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        assert!(!service_running(dir.path())?);
+
+        let lock = lock_service(dir.path())?.expect("first lock");
+        assert!(service_running(dir.path())?);
+        assert!(lock_service(dir.path())?.is_none());
+
+        drop(lock);
+        assert!(!service_running(dir.path())?);
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use edgli::hopr_lib::api::types::primitive::prelude::{Address, Balance, WxHOPR, XDai};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
@@ -8,29 +9,33 @@ use std::{
 pub use crate::info::Info;
 use crate::{balance, connection::destination::Destination, serde_utils};
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct ChannelOut {
     #[serde(with = "serde_utils::address")]
+    #[schemars(with = "String")]
     pub address: Address,
     pub balance: ChannelBalance,
     pub matched_exit: Option<String>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(tag = "status", rename_all = "snake_case")]
 pub enum ChannelBalance {
     Unknown,
     Completed {
         #[serde(with = "serde_utils::balance")]
+        #[schemars(with = "String")]
         amount: Balance<WxHOPR>,
     },
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct BalanceResponse {
     #[serde(with = "serde_utils::balance")]
+    #[schemars(with = "String")]
     pub node: Balance<XDai>,
     #[serde(with = "serde_utils::balance")]
+    #[schemars(with = "String")]
     pub safe: Balance<WxHOPR>,
     pub channels_out: Vec<ChannelOut>,
     pub info: Info,
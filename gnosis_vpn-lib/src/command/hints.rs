@@ -0,0 +1,349 @@
+//! Troubleshooting hints derived from a status snapshot.
+//!
+//! Hints are generated by the root process once it filled in what only it knows, e.g. the last
+//! WireGuard handshake, so GUIs can show actionable guidance without interpreting the raw state.
+//! Texts come from the [`crate::i18n`] catalog in the locale the service resolved.
+use humantime::format_duration;
+
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::time::{Duration, SystemTime};
+
+use super::{RunMode, StatusResponse};
+use crate::balance::FundingIssue;
+use crate::conflicts::SuggestedMode;
+use crate::i18n::{Locale, Message};
+use crate::route_health::RouteHealthState;
+
+/// WireGuard renews handshakes every two minutes on an active tunnel.
+const STALE_HANDSHAKE: Duration = Duration::from_secs(3 * 60);
+/// Failed exit health checks in a row before the exit itself is suspected.
+const FAILING_HEALTH_CHECKS: u32 = 3;
+
+impl StatusResponse {
+    pub fn generate_hints(&self, now: SystemTime, locale: Locale) -> Vec<String> {
+        let mut hints = Vec::new();
+        match &self.run_mode {
+            RunMode::WaitingForNetwork { .. } => hints.push(Message::WaitingForNetwork.text(locale)),
+            RunMode::Init {
+                last_error: Some(error),
+            } => hints.push(Message::StartupFailing { error }.text(locale)),
+            RunMode::PreparingSafe { node_address, .. } => {
+                hints.push(Message::WaitingForFunds { node_address }.text(locale))
+            }
+            RunMode::Warmup {
+                last_error: Some(error),
+                ..
+            } => hints.push(Message::NodeStartupFailing { error }.text(locale)),
+            RunMode::Running {
+                funding_issues: Some(issues),
+                ..
+            } => {
+                let unique: BTreeSet<String> = issues
+                    .iter()
+                    .map(|issue| funding_hint(issue, self.manual_channel_funding).text(locale))
+                    .collect();
+                hints.extend(unique);
+            }
+            _ => (),
+        }
+        if !self.retrying.is_empty() {
+            let tasks: Vec<String> = self.retrying.iter().map(|r| r.task.to_string()).collect();
+            hints.push(
+                Message::ChainProviderUnreachable {
+                    tasks: &tasks.join(", "),
+                }
+                .text(locale),
+            );
+        }
+        if let Some(connected) = &self.connected {
+            let last_sign_of_life = connected.last_handshake.unwrap_or(connected.connected_since);
+            if let Ok(silent) = now.duration_since(last_sign_of_life)
+                && silent > STALE_HANDSHAKE
+            {
+                hints.push(
+                    Message::NoHandshake {
+                        silent: &format_duration(Duration::from_secs(silent.as_secs())),
+                    }
+                    .text(locale),
+                );
+            }
+        }
+        if let Some(usage) = self.disk_space.as_ref().filter(|u| u.is_low()) {
+            hints.push(
+                Message::LowDiskSpace {
+                    available: &usage.available,
+                }
+                .text(locale),
+            );
+        }
+        for conflict in &self.conflicts {
+            let msg = match conflict.suggested_mode {
+                SuggestedMode::Delegated => Message::VpnConflictRoutes { conflict },
+                SuggestedMode::Managed => Message::VpnConflictRunning { conflict },
+            };
+            hints.push(msg.text(locale));
+        }
+        if let Some(error) = &self.kill_switch_error {
+            hints.push(Message::KillSwitchFailed { error }.text(locale));
+        }
+        if let Some(usage) = self.budget.as_ref().filter(|u| u.exceeded) {
+            let resets_in = usage.resets_at.duration_since(now).unwrap_or_default();
+            hints.push(
+                Message::BudgetUsedUp {
+                    resets_in: &format_duration(Duration::from_secs(resets_in.as_secs())),
+                }
+                .text(locale),
+            );
+        }
+        let target = self
+            .destinations
+            .iter()
+            .find(|d| self.target_destination.as_ref() == Some(&d.destination.id));
+        if let Some(target) = target
+            && let Some(rh) = &target.route_health
+        {
+            let id = &target.destination.id;
+            match &rh.state {
+                RouteHealthState::Unrecoverable { reason } => {
+                    hints.push(Message::DestinationUnusable { id, reason }.text(locale))
+                }
+                RouteHealthState::NeedsPeering { .. } => hints.push(Message::ExitNotVisible { id }.text(locale)),
+                RouteHealthState::NeedsChannel if self.manual_channel_funding => {
+                    hints.push(Message::NoChannelManual { id }.text(locale))
+                }
+                RouteHealthState::NeedsChannel => hints.push(Message::WaitingForChannel { id }.text(locale)),
+                _ if rh.consecutive_failures >= FAILING_HEALTH_CHECKS => hints.push(
+                    Message::HealthChecksFailing {
+                        id,
+                        error: rh.last_error.as_ref().map(|e| e as &dyn Display),
+                    }
+                    .text(locale),
+                ),
+                _ => (),
+            }
+            if let Some(maintenance) = rh.state.maintenance()
+                && let Some(retires_at) = maintenance.retires_at()
+            {
+                let retires_in = retires_at.duration_since(now).unwrap_or_default();
+                // mirrors the migration of the worker, which only moves to a ready exit without a notice
+                let moving = self.destinations.iter().any(|d| {
+                    d.destination.id != *id
+                        && d.route_health.as_ref().is_some_and(|rh| {
+                            matches!(rh.state, RouteHealthState::ReadyToConnect { .. })
+                                && rh.state.maintenance().is_none()
+                        })
+                });
+                hints.push(
+                    Message::ExitMaintenance {
+                        id,
+                        retires_in: &format_duration(Duration::from_secs(retires_in.as_secs())),
+                        message: maintenance.message.as_ref().map(|m| m as &dyn Display),
+                        moving,
+                    }
+                    .text(locale),
+                );
+            }
+        }
+        hints
+    }
+}
+
+fn funding_hint(issue: &FundingIssue, manual_channel_funding: bool) -> Message<'static> {
+    match issue {
+        FundingIssue::ModulePermissions => Message::ModulePermissions,
+        FundingIssue::Unfunded => Message::NodeUnfunded,
+        FundingIssue::ChannelsOutOfFunds if manual_channel_funding => Message::ChannelsOutOfFundsManual,
+        FundingIssue::ChannelsOutOfFunds | FundingIssue::SafeOutOfFunds => Message::OutOfFunds,
+        FundingIssue::SafeLowOnFunds => Message::SafeLowOnFunds,
+        FundingIssue::NodeUnderfunded => Message::NodeUnderfunded,
+        FundingIssue::NodeLowOnFunds => Message::NodeLowOnFunds,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bytesize::ByteSize;
+
+    use crate::command::{ConnectedInfo, DestinationState, RouteHealthView};
+    use crate::connection::destination::{Address, Destination, HopRouting};
+    use crate::disk_space;
+    use crate::gvpn_client;
+    use crate::route_health::ExitHealth;
+    use std::collections::HashMap;
+
+    fn status(run_mode: RunMode) -> StatusResponse {
+        StatusResponse {
+            run_mode,
+            destinations: vec![],
+            target_destination: None,
+            connecting: None,
+            reconnecting: None,
+            connected: None,
+            disconnecting: vec![],
+            budget: None,
+            manual_channel_funding: false,
+            retrying: vec![],
+            previous_crash: None,
+            disk_space: None,
+            conflicts: vec![],
+            kill_switch_error: None,
+            hints: vec![],
+        }
+    }
+
+    #[test]
+    fn funding_issues_are_deduplicated() {
+        let status = status(RunMode::Running {
+            hopr_status: None,
+            funding_issues: Some(vec![FundingIssue::ChannelsOutOfFunds, FundingIssue::SafeOutOfFunds]),
+        });
+        let hints = status.generate_hints(SystemTime::now(), Locale::En);
+        assert_eq!(hints.len(), 1);
+        assert!(hints[0].starts_with("Out of funds"));
+    }
+
+    #[test]
+    fn manual_channel_funding_asks_for_channel_top_up() {
+        let mut status = status(RunMode::Running {
+            hopr_status: None,
+            funding_issues: Some(vec![FundingIssue::ChannelsOutOfFunds, FundingIssue::SafeOutOfFunds]),
+        });
+        status.manual_channel_funding = true;
+        let hints = status.generate_hints(SystemTime::now(), Locale::En);
+        assert_eq!(hints.len(), 2);
+        assert!(hints.iter().any(|h| h.starts_with("Channels are out of funds")));
+    }
+
+    #[test]
+    fn low_disk_space_is_reported() {
+        let mut status = status(RunMode::NotRunning);
+        status.disk_space = Some(disk_space::Usage {
+            available: ByteSize::gib(2),
+            total: ByteSize::gib(16),
+            min_free: ByteSize::gib(1),
+        });
+        assert!(status.generate_hints(SystemTime::now(), Locale::En).is_empty());
+
+        status.disk_space.as_mut().unwrap().available = ByteSize::mib(100);
+        let hints = status.generate_hints(SystemTime::now(), Locale::En);
+        assert_eq!(hints.len(), 1);
+        assert!(hints[0].starts_with("Only 100.0 MiB of disk space left"));
+    }
+
+    #[test]
+    fn kill_switch_failure_is_reported() {
+        let mut status = status(RunMode::NotRunning);
+        status.kill_switch_error = Some("netlink ACK error: Operation not permitted".to_string());
+        let hints = status.generate_hints(SystemTime::now(), Locale::En);
+        assert_eq!(hints.len(), 1);
+        assert!(hints[0].starts_with("Kill switch could not block traffic"));
+    }
+
+    #[test]
+    fn stale_handshake_is_reported() {
+        let now = SystemTime::now();
+        let mut status = status(RunMode::Running {
+            hopr_status: None,
+            funding_issues: None,
+        });
+        status.connected = Some(ConnectedInfo {
+            destination_id: "Germany".to_string(),
+            connected_since: now - Duration::from_secs(600),
+            last_handshake: Some(now - Duration::from_secs(60)),
+            duration: Duration::from_secs(600),
+            public_ip: None,
+        });
+        assert!(status.generate_hints(now, Locale::En).is_empty());
+
+        status.connected.as_mut().unwrap().last_handshake = Some(now - Duration::from_secs(300));
+        let hints = status.generate_hints(now, Locale::En);
+        assert_eq!(
+            hints,
+            vec!["No WireGuard handshake for 5m - outgoing UDP traffic may be blocked on this network"]
+        );
+    }
+
+    #[test]
+    fn exit_maintenance_is_reported() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let exit = ExitHealth {
+            checked_at: now,
+            versions: gvpn_client::Versions {
+                versions: vec!["v1".to_string()],
+                latest: "v1".to_string(),
+            },
+            ping_rtt: Duration::from_millis(100),
+            health: gvpn_client::Health {
+                slots: gvpn_client::Slots {
+                    available: 10,
+                    connected: 1,
+                },
+                load_avg: gvpn_client::LoadAvg {
+                    one: 0.1,
+                    five: 0.2,
+                    fifteen: 0.3,
+                    nproc: 4,
+                },
+                maintenance: Some(gvpn_client::Maintenance {
+                    retire_after: 1_700_000_000 + 2 * 60 * 60,
+                    message: Some("moving racks".to_string()),
+                }),
+            },
+        };
+        let mut status = status(RunMode::Running {
+            hopr_status: None,
+            funding_issues: None,
+        });
+        let ready = |id: &str, exit: ExitHealth| DestinationState {
+            destination: Destination::new(
+                id.to_string(),
+                Address::from([1u8; 20]),
+                HopRouting::try_from(1).expect("conversion cannot fail"),
+                HashMap::new(),
+            ),
+            route_health: Some(RouteHealthView {
+                state: RouteHealthState::ReadyToConnect { exit },
+                last_error: None,
+                checking_since: None,
+                consecutive_failures: 0,
+                cancelled_at: None,
+            }),
+        };
+        status.target_destination = Some("Germany".to_string());
+        status.destinations = vec![ready("Germany", exit.clone())];
+        assert_eq!(
+            status.generate_hints(now, Locale::En),
+            vec![
+                "Exit of Germany retires for maintenance in 2h (moving racks) - no other destination is ready to move to"
+            ]
+        );
+
+        // an exit announcing maintenance itself is no place to move to
+        status.destinations.push(ready("Spain", exit.clone()));
+        assert_eq!(
+            status.generate_hints(now, Locale::En),
+            vec![
+                "Exit of Germany retires for maintenance in 2h (moving racks) - no other destination is ready to move to"
+            ]
+        );
+
+        let mut healthy = exit.clone();
+        healthy.health.maintenance = None;
+        status.destinations.push(ready("France", healthy));
+        assert_eq!(
+            status.generate_hints(now, Locale::En),
+            vec!["Exit of Germany retires for maintenance in 2h (moving racks) - moving to another ready destination"]
+        );
+
+        // a notice beyond what the clock can represent is ignored
+        let mut invalid = exit;
+        if let Some(maintenance) = invalid.health.maintenance.as_mut() {
+            maintenance.retire_after = u64::MAX;
+        }
+        status.destinations = vec![ready("Germany", invalid)];
+        assert!(status.generate_hints(now, Locale::En).is_empty());
+    }
+}
@@ -1,27 +1,44 @@
 use edgli::EdgliInitState;
 use edgli::hopr_lib::api::node::HoprState;
 use edgli::hopr_lib::api::types::primitive::prelude::{Balance, WxHOPR, XDai};
+use human_bandwidth::re::bandwidth::Bandwidth;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use std::fmt::{self, Display};
-use std::net::SocketAddr;
+use std::net::{Ipv4Addr, SocketAddr};
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::{Duration, SystemTime};
 
+use crate::backoff;
 use crate::balance;
+use crate::balance_history;
+use crate::budget;
+use crate::conflicts;
 use crate::connection;
 use crate::connection::destination::{Address, Destination};
+use crate::crash;
+use crate::diagnostics;
+use crate::disk_space;
+use crate::exit_reports;
+use crate::gvpn_client;
+use crate::hopr::types::SessionClientMetadata;
+use crate::issue_report;
 use crate::log_output;
+use crate::public_ip::PublicIp;
 use crate::route_health::{RouteHealth, RouteHealthState};
 use crate::serde_utils;
+use crate::telemetry;
 pub use crate::ticket_stats::TicketStats;
+use crate::transactions::Transaction;
 
 mod balance_response;
+mod hints;
 pub use balance_response::{BalanceResponse, ChannelBalance, ChannelOut, Info};
 
 /// These commands are sent by the ctl app and forwarded to the core loop for answering
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum Command {
     /// Request general status about destinations and connected state
     Status,
@@ -29,13 +46,27 @@ pub enum Command {
     NerdStats,
     /// Connect to a destination, specified by its id
     Connect(String),
+    /// Connect to whichever destination is ready, `avoid_failed` skips those that recently failed to connect.
+    /// The pick is kept for the configured affinity TTL unless `rotate` asks for a new one.
+    ConnectAny {
+        avoid_failed: bool,
+        #[serde(default)]
+        rotate: bool,
+    },
+    /// Connect to the first usable of these destination ids or destination groups, in order.
+    /// The next one takes over once the target fails to connect or its route health becomes unrecoverable.
+    ConnectFailover(Vec<String>),
     /// Disconnect from a destination
     Disconnect,
+    /// Abort a pending or ongoing connection attempt, leaves established connections alone
+    CancelConnect,
     /// Show channel balance and funding status
     Balance,
+    /// Balance snapshots of the last days, oldest first
+    BalanceHistory { days: u32 },
     /// Trigger funding tool - only allowed at certain phases
-    FundingTool(String),
-    /// Return telemetry metrics of the underlying edge client, if running
+    FundingTool(Secret),
+    /// Return telemetry metrics of the underlying edge client, if running, and of the daemon itself
     Telemetry,
     /// Determine service liveness
     Ping,
@@ -47,6 +78,45 @@ pub enum Command {
     StopClient,
     /// List configured destination IDs
     Destinations,
+    /// Configured destinations as `[destinations.<id>]` tables of the configuration file
+    ExportDestinations,
+    /// Append the destinations of an export to the configuration file, existing ones are left alone
+    ImportDestinations(String),
+    /// Query the egress rate limit currently applied to the tunnel
+    RateLimit,
+    /// Set or clear (`None`) the egress rate limit on the tunnel until a config reload changes `egress_rate_limit`
+    SetRateLimit(
+        #[serde(default, with = "human_bandwidth::serde")]
+        #[schemars(with = "Option<String>")]
+        Option<Bandwidth>,
+    ),
+    /// List HOPR peers the node is currently connected to
+    Peers,
+    /// List raw HOPR sessions with their settings
+    Sessions,
+    /// Query local gateway mode and per client traffic
+    Gateway,
+    /// List the routes a tunnel setup applies next to whether they are present right now
+    RoutingExplain,
+    /// Show the anonymous usage telemetry payload, `Some` opts in or out first
+    UsageTelemetry(Option<bool>),
+    /// Snapshot node database, identity and safe configuration into a tarball at this absolute path,
+    /// a running worker is paused meanwhile
+    Backup(PathBuf),
+    /// Compact the node databases and remove rotated service logs, a running worker is paused meanwhile
+    Compact,
+    /// Revoke the remote clients paired under this name, they have to pair again
+    Unpair(String),
+    /// List on-chain transactions the client sent itself, newest first
+    Transactions,
+    /// List recent connection outcome reports sent to exit operators, newest first
+    ExitReports,
+    /// Recent connection attempts to a destination without sensitive details, to share with its exit operator
+    ReportIssue { destination: String },
+    /// Latest `lines` of service log output, `follow` keeps the connection open and streams new lines
+    Logs { follow: bool, lines: u32 },
+    /// Run the checks of `gnosis_vpn-ctl doctor` on root and core
+    Diagnose,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -54,22 +124,45 @@ pub enum WorkerCommand {
     Status,
     NerdStats,
     Connect(String),
+    ConnectAny {
+        avoid_failed: bool,
+        rotate: bool,
+    },
+    ConnectFailover(Vec<String>),
     Disconnect,
+    CancelConnect,
     Balance,
-    FundingTool(String),
+    BalanceHistory {
+        days: u32,
+    },
+    FundingTool(Secret),
     Telemetry,
+    /// Node identity, the root service completes the response with its own details
+    Info,
+    Peers,
+    Sessions,
+    UsageTelemetry(Option<bool>),
+    Transactions,
+    ExitReports,
+    ReportIssue {
+        destination: String,
+    },
+    /// Core checks, the root service adds its own
+    Diagnose,
     /// Reconnect the current HOPR session without clearing the target or disabling the killswitch.
     /// Used by the root process when a WAN interface change is detected.
     ForceReconnect,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub enum Response {
     Status(StatusResponse),
     NerdStats(NerdStatsResponse),
     Connect(ConnectResponse),
     Disconnect(DisconnectResponse),
+    CancelConnect(CancelConnectResponse),
     Balance(Result<BalanceResponse, String>),
+    BalanceHistory(Vec<balance_history::Point>),
     FundingTool(FundingToolResponse),
     Telemetry(Option<String>),
     /// Acknowledgment for [`WorkerCommand::ForceReconnect`]. Never sent in response to a ctl
@@ -80,11 +173,34 @@ pub enum Response {
     StartClient(StartClientResponse),
     StopClient(StopClientResponse),
     Destinations(Vec<String>),
+    ExportDestinations(Result<String, String>),
+    /// Ids of the destinations added to the configuration file
+    ImportDestinations(Result<Vec<String>, String>),
+    /// Currently applied egress rate limit, `None` when unshaped
+    RateLimit(Result<RateLimitResponse, String>),
+    Peers(PeersResponse),
+    /// Sessions open on the node, `None` until the node is running
+    Sessions(Option<Vec<SessionView>>),
+    Gateway(Result<GatewayResponse, String>),
+    RoutingExplain(Result<RoutingExplainResponse, String>),
+    UsageTelemetry(UsageTelemetryResponse),
+    Backup(Result<BackupResponse, String>),
+    Compact(Result<CompactResponse, String>),
+    /// Number of revoked remote clients
+    Unpair(usize),
+    Transactions(Vec<Transaction>),
+    ExitReports(Vec<exit_reports::Entry>),
+    ReportIssue(Result<issue_report::IssueReport, String>),
+    /// Log lines, oldest first. A followed stream sends one per batch and empty ones to keep the connection alive
+    Logs(Vec<String>),
+    Diagnose(diagnostics::Report),
+    /// Command not accepted on this socket, e.g. a mutating command sent to an observer
+    Refused(String),
     WorkerOffline,
     WorkerRestarting,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct StatusResponse {
     pub run_mode: RunMode,
     pub destinations: Vec<DestinationState>,
@@ -93,57 +209,211 @@ pub struct StatusResponse {
     pub reconnecting: Option<ReconnectingInfo>,
     pub connected: Option<ConnectedInfo>,
     pub disconnecting: Vec<DisconnectingInfo>,
+    /// Daily wxHOPR budget usage, if a budget is configured
+    pub budget: Option<budget::Usage>,
+    /// Channels are neither opened nor topped up automatically
+    #[serde(default)]
+    pub manual_channel_funding: bool,
+    /// Background tasks that keep failing and are retried with backoff
+    #[serde(default)]
+    pub retrying: Vec<backoff::RetryInfo>,
+    /// Latest crash of a previous run, filled in by the root process
+    #[serde(default)]
+    pub previous_crash: Option<crash::Summary>,
+    /// Free space of the data directory, filled in by the root process
+    #[serde(default)]
+    pub disk_space: Option<disk_space::Usage>,
+    /// Other VPN clients competing for the routing table, filled in by the root process
+    #[serde(default)]
+    pub conflicts: Vec<conflicts::Conflict>,
+    /// Why the kill switch block could not be engaged, filled in by the root process
+    #[serde(default)]
+    pub kill_switch_error: Option<String>,
+    /// Actionable troubleshooting advice derived from the fields above, filled in by the root process
+    #[serde(default)]
+    pub hints: Vec<String>,
+}
+
+/// Egress rate limit currently applied to the tunnel interface.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RateLimitResponse {
+    #[serde(default, with = "human_bandwidth::serde")]
+    #[schemars(with = "Option<String>")]
+    pub limit: Option<Bandwidth>,
+}
+
+/// Local gateway mode as applied by the root process.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct GatewayResponse {
+    /// Configured LAN interface, `None` when gateway mode is off
+    pub lan_interface: Option<String>,
+    /// Forwarding is only installed while the tunnel is up
+    pub active: bool,
+    pub clients: Vec<GatewayClient>,
+}
+
+/// Archive written by [`Command::Backup`].
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct BackupResponse {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    /// Whether the worker was stopped for the snapshot and restarted afterwards
+    pub paused_worker: bool,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Space freed by [`Command::Compact`].
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CompactResponse {
+    pub databases: usize,
+    /// Databases left alone for lack of free space to rebuild them
+    pub skipped_databases: usize,
+    pub rotated_logs: usize,
+    pub reclaimed_bytes: u64,
+    /// Whether the worker was stopped for the compaction and restarted afterwards
+    pub paused_worker: bool,
+}
+
+/// Routes and firewall tables the root process installs for a tunnel, compared against the live system.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RoutingExplainResponse {
+    /// `false` in delegated routing mode, where routes are owned by the surrounding setup
+    pub managed: bool,
+    /// Whether routing is currently set up for a tunnel
+    pub active: bool,
+    /// WAN interface used for bypass routes, `None` without a WAN default route
+    pub wan_device: Option<String>,
+    pub routes: Vec<RouteExplanation>,
+    #[serde(default)]
+    pub tables: Vec<TableExplanation>,
+}
+
+/// A single route of [`RoutingExplainResponse`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RouteExplanation {
+    pub destination: String,
+    pub gateway: Option<String>,
+    pub device: String,
+    /// What the route is for, e.g. `peer bypass`
+    pub purpose: String,
+    /// Whether the route is in the routing table, `None` if that could not be determined
+    pub present: Option<bool>,
+}
+
+/// A firewall table of [`RoutingExplainResponse`], e.g. the DSCP marking or the kill switch block.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TableExplanation {
+    pub table: String,
+    /// What the table is for, e.g. `local gateway`
+    pub purpose: String,
+    /// Whether the table should currently be installed
+    pub expected: bool,
+    /// Whether the table is installed, `None` if that could not be determined
+    pub present: Option<bool>,
+}
+
+/// Usage telemetry state and the exact payload the next upload would send.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct UsageTelemetryResponse {
+    pub enabled: bool,
+    /// Switched off in the config file, the toggle has no effect
+    #[serde(default)]
+    pub locked: bool,
+    pub payload: telemetry::Payload,
+}
+
+/// Traffic a LAN client sent into (`tx`) and received from (`rx`) the tunnel.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct GatewayClient {
+    pub ip: Ipv4Addr,
+    pub tx_bytes: u64,
+    pub tx_packets: u64,
+    pub rx_bytes: u64,
+    pub rx_packets: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ConnectingInfo {
     pub destination_id: String,
     #[serde(with = "serde_utils::system_time")]
+    #[schemars(with = "u64")]
     pub since: SystemTime,
     pub phase: connection::up::Phase,
+    /// Estimated overall progress from 0 to 100
+    #[serde(default)]
+    pub progress_percent: u8,
+    /// Estimated time until the connection is established
+    #[serde(default, with = "serde_utils::duration_ms")]
+    #[schemars(with = "f64")]
+    pub eta: Duration,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ReconnectingInfo {
     pub destination_id: String,
     /// When the WAN change that triggered the reconnect was detected.
     #[serde(with = "serde_utils::system_time")]
+    #[schemars(with = "u64")]
     pub since: SystemTime,
     pub phase: connection::up::Phase,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ConnectedInfo {
     pub destination_id: String,
-    #[serde(with = "serde_utils::system_time")]
-    pub since: SystemTime,
+    /// When the tunnel to this destination was established, kept as `since` on the wire
+    #[serde(rename = "since", with = "serde_utils::system_time")]
+    #[schemars(with = "u64")]
+    pub connected_since: SystemTime,
+    /// Most recent WireGuard handshake; filled in by the root process, which owns the interface
+    #[serde(default, with = "serde_utils::opt_system_time")]
+    #[schemars(with = "Option<u64>")]
+    pub last_handshake: Option<SystemTime>,
+    /// Time spent connected at the moment the status was taken
+    #[serde(default, with = "serde_utils::duration_ms")]
+    #[schemars(with = "f64")]
+    pub duration: Duration,
+    /// Exit address as seen by the internet, resolved through the tunnel after connecting
+    #[serde(default)]
+    pub public_ip: Option<PublicIp>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct DisconnectingInfo {
     pub destination_id: String,
     #[serde(with = "serde_utils::system_time")]
+    #[schemars(with = "u64")]
     pub since: SystemTime,
     pub phase: connection::down::Phase,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct DestinationState {
+    #[serde(with = "serde_utils::redacted_destination")]
+    #[schemars(with = "Destination")]
     pub destination: Destination,
     pub route_health: Option<RouteHealthView>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub enum RunMode {
+    /// Worker start is held back until the network is usable, e.g. right after boot
+    WaitingForNetwork {
+        #[serde(with = "serde_utils::system_time")]
+        #[schemars(with = "u64")]
+        since: SystemTime,
+    },
     /// Initial start
     Init { last_error: Option<String> },
     /// after creating safe this state will not be reached again
     PreparingSafe {
         #[serde(with = "serde_utils::address")]
+        #[schemars(with = "String")]
         node_address: Address,
         #[serde(with = "serde_utils::balance")]
+        #[schemars(with = "String")]
         node_xdai: Balance<XDai>,
         #[serde(with = "serde_utils::balance")]
+        #[schemars(with = "String")]
         node_wxhopr: Balance<WxHOPR>,
         funding_tool: Option<String>,
         error: Option<String>,
@@ -152,6 +422,7 @@ pub enum RunMode {
     /// Safe deployment ongoing
     DeployingSafe {
         #[serde(with = "serde_utils::address")]
+        #[schemars(with = "String")]
         node_address: Address,
     },
     /// Hopr started, determining ticket value for strategies
@@ -173,26 +444,141 @@ pub enum RunMode {
     NotRunning,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct InfoResponse {
     pub version: String,
     pub log_file: Option<PathBuf>,
     pub package_version: Option<String>,
+    #[serde(default)]
+    pub config_file: Option<PathBuf>,
+    /// Worker state directory holding identity and node database
+    #[serde(default)]
+    pub state_home: Option<PathBuf>,
+    #[serde(default)]
+    pub cache_home: Option<PathBuf>,
+    /// Only available while the worker is running
+    #[serde(default)]
+    pub node: Option<NodeIdentity>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct NodeIdentity {
+    #[serde(with = "serde_utils::address")]
+    #[schemars(with = "String")]
+    pub node_address: Address,
+    /// Known once the node is running
+    pub peer_id: Option<String>,
+    /// Known once the safe was found or deployed
+    pub safe_address: Option<String>,
+    pub module_address: Option<String>,
+    /// Blokli endpoint the node reads chain data from, `None` for the built-in default
+    pub blokli_url: Option<String>,
+    pub identity_file: PathBuf,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PeersResponse {
+    /// Last time the peer list was fetched from the node, `None` until the node is running
+    #[serde(with = "serde_utils::opt_system_time")]
+    #[schemars(with = "Option<u64>")]
+    pub updated_at: Option<SystemTime>,
+    pub peers: Vec<PeerView>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PeerView {
+    #[serde(with = "serde_utils::address")]
+    #[schemars(with = "String")]
+    pub address: Address,
+    pub ipv4_addrs: Vec<Ipv4Addr>,
+    /// Probe score from 0.0 to 1.0 the node measured towards this peer, `None` until probed
+    pub score: Option<f64>,
+    /// Average probe latency towards this peer
+    #[serde(default, with = "serde_utils::opt_duration_ms")]
+    #[schemars(with = "Option<f64>")]
+    pub latency: Option<Duration>,
+    /// Capacity of our outgoing channel to this peer
+    pub channel: Option<balance::Capacity>,
+    /// Configured destinations exiting at this peer
+    pub destinations: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionProtocol {
+    Udp,
+    Tcp,
+}
+
+/// Part a session plays for the current tunnel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionRole {
+    Bridge,
+    Ping,
+    Main,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SessionView {
+    /// Set if the session backs the current connection, `None` for sessions not known to core
+    pub role: Option<SessionRole>,
+    pub protocol: SessionProtocol,
+    pub bound_host: SocketAddr,
+    pub target: String,
+    #[serde(with = "serde_utils::address")]
+    #[schemars(with = "String")]
+    pub destination: Address,
+    #[schemars(with = "serde_json::Value")]
+    pub forward_path: connection::destination::HopRouting,
+    #[schemars(with = "serde_json::Value")]
+    pub return_path: connection::destination::HopRouting,
+    pub hopr_mtu: usize,
+    pub surb_len: usize,
+    pub active_clients: Vec<String>,
+    pub max_client_sessions: usize,
+    #[serde(default, with = "human_bandwidth::serde")]
+    #[schemars(with = "Option<String>")]
+    pub max_surb_upstream: Option<Bandwidth>,
+    /// Response buffer in bytes
+    pub response_buffer: Option<u64>,
+    pub session_pool: Option<usize>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+impl SessionView {
+    pub(crate) fn new(meta: SessionClientMetadata, protocol: SessionProtocol, role: Option<SessionRole>) -> Self {
+        SessionView {
+            role,
+            protocol,
+            bound_host: meta.bound_host,
+            target: meta.target,
+            destination: meta.destination,
+            forward_path: meta.forward_path,
+            return_path: meta.return_path,
+            hopr_mtu: meta.hopr_mtu,
+            surb_len: meta.surb_len,
+            active_clients: meta.active_clients,
+            max_client_sessions: meta.max_client_sessions,
+            max_surb_upstream: meta.max_surb_upstream,
+            response_buffer: meta.response_buffer.map(|b| b.as_u64()),
+            session_pool: meta.session_pool,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub enum StartClientResponse {
     Started,
     AlreadyRunning,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub enum StopClientResponse {
     Stopped,
     NotRunning,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum HoprStatus {
     Uninitialized,
     WaitingForFunds,
@@ -209,7 +595,7 @@ pub enum HoprStatus {
     Failed,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum HoprInitStatus {
     ValidatingConfig,
     IdentifyingNode,
@@ -219,22 +605,111 @@ pub enum HoprInitStatus {
     Ready,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub enum ConnectResponse {
-    AlreadyConnected(Destination),
-    Connecting(Destination),
-    WaitingToConnect(Destination, RouteHealthState),
-    UnableToConnect(Destination, RouteHealthState),
+    AlreadyConnected(
+        #[serde(with = "serde_utils::redacted_destination")]
+        #[schemars(with = "Destination")]
+        Destination,
+    ),
+    Connecting(
+        #[serde(with = "serde_utils::redacted_destination")]
+        #[schemars(with = "Destination")]
+        Destination,
+    ),
+    WaitingToConnect(
+        #[serde(with = "serde_utils::redacted_destination")]
+        #[schemars(with = "Destination")]
+        Destination,
+        RouteHealthState,
+    ),
+    UnableToConnect(
+        #[serde(with = "serde_utils::redacted_destination")]
+        #[schemars(with = "Destination")]
+        Destination,
+        RouteHealthState,
+    ),
     DestinationNotFound,
+    BudgetExceeded(budget::Usage),
+    /// Rejected by the management policy, see [`crate::management`]
+    BlockedByPolicy(String),
+    /// Node is still starting up, the connection is started once it runs
+    Queued {
+        #[serde(with = "serde_utils::redacted_destination")]
+        #[schemars(with = "Destination")]
+        destination: Destination,
+        run_mode: RunMode,
+        startup_percent: Option<u8>,
+    },
+    /// No destination is ready to connect, answer to [`Command::ConnectAny`]
+    NoneReady,
+    /// Route health looked fine but the reachability pre-check failed, nothing was started
+    NotReady {
+        reason: NotReadyReason,
+        #[serde(with = "serde_utils::duration_ms")]
+        #[schemars(with = "f64")]
+        retry_in: Duration,
+    },
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub enum NotReadyReason {
+    /// The exit is not among the peers known to the node
+    PeerNotSeen,
+    /// No outgoing channel is open to route through
+    NoChannel,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum DisconnectResponse {
-    Disconnecting(Destination),
+    Disconnecting(
+        #[serde(with = "serde_utils::redacted_destination")]
+        #[schemars(with = "Destination")]
+        Destination,
+    ),
     NotConnected,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub enum CancelConnectResponse {
+    Cancelled(
+        #[serde(with = "serde_utils::redacted_destination")]
+        #[schemars(with = "Destination")]
+        Destination,
+    ),
+    NotConnecting,
+}
+
+/// Funding tool secret, sent over the socket as is but redacted in logs and console output.
+#[derive(Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+const REDACTED: &str = "<redacted>";
+
+impl Secret {
+    pub fn new(secret: String) -> Self {
+        Self(secret)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Secret({REDACTED})")
+    }
+}
+
+impl Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum FundingToolResponse {
     WrongPhase,
     Started,
@@ -242,13 +717,18 @@ pub enum FundingToolResponse {
     Done,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct RouteHealthView {
     pub state: RouteHealthState,
     pub last_error: Option<String>,
     #[serde(with = "serde_utils::opt_system_time")]
+    #[schemars(with = "Option<u64>")]
     pub checking_since: Option<SystemTime>,
     pub consecutive_failures: u32,
+    /// Last connection attempt aborted by the user
+    #[serde(default, with = "serde_utils::opt_system_time")]
+    #[schemars(with = "Option<u64>")]
+    pub cancelled_at: Option<SystemTime>,
 }
 
 impl From<&RouteHealth> for RouteHealthView {
@@ -258,11 +738,12 @@ impl From<&RouteHealth> for RouteHealthView {
             last_error: rh.last_error().map(str::to_owned),
             checking_since: rh.checking_since(),
             consecutive_failures: rh.consecutive_failures(),
+            cancelled_at: rh.cancelled_at(),
         }
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub enum TicketStatsStatus {
     Available(TicketStats),
     /// incentive operations not yet initialized
@@ -270,14 +751,14 @@ pub enum TicketStatsStatus {
     Error(String),
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub enum NerdStatsResponse {
     NoInfo(TicketStatsStatus),
     Connecting(TicketStatsStatus, ConnStats),
     Connected(TicketStatsStatus, ConnStats),
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum ActiveSession {
     Bridge { bound_host: SocketAddr, id: String },
@@ -285,16 +766,22 @@ pub enum ActiveSession {
     Main { bound_host: SocketAddr, id: String },
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ConnStats {
     #[serde(with = "serde_utils::address")]
+    #[schemars(with = "String")]
     pub node_address: Address,
+    #[serde(with = "serde_utils::redacted_destination")]
+    #[schemars(with = "Destination")]
     pub destination: Destination,
     pub wg_pubkey: Option<String>,
     pub wg_server_pubkey: Option<String>,
     pub wg_ip: Option<String>,
     pub bridge_session: Option<ActiveSession>,
     pub main_session: Option<ActiveSession>,
+    /// Parameters the exit announced on registration
+    #[serde(default)]
+    pub exit_capabilities: Option<gvpn_client::Capabilities>,
 }
 
 impl ConnStats {
@@ -321,6 +808,7 @@ impl ConnStats {
             wg_ip: conn.registration.as_ref().map(|reg| reg.address().to_string()),
             bridge_session,
             main_session,
+            exit_capabilities: conn.registration.as_ref().map(|reg| reg.capabilities().clone()),
         }
     }
 }
@@ -365,6 +853,58 @@ impl RunMode {
             funding_issues,
         }
     }
+
+    /// Rough startup progress counting the init and node steps already passed.
+    /// `None` outside of startup and while waiting on the user, e.g. for funds.
+    pub fn startup_percent(&self) -> Option<u8> {
+        // init steps followed by node steps up to running
+        const STARTUP_STEPS: u16 = 16;
+        let step = match self {
+            RunMode::WaitingForNetwork { .. } | RunMode::Init { .. } => 0,
+            RunMode::Warmup {
+                hopr_status: Some(status),
+                ..
+            } => 6 + status.startup_step()?,
+            RunMode::Warmup {
+                hopr_init_status: Some(status),
+                ..
+            } => status.startup_step(),
+            RunMode::Warmup { .. } => 0,
+            _ => return None,
+        };
+        Some((u16::from(step) * 100 / STARTUP_STEPS) as u8)
+    }
+}
+
+impl HoprInitStatus {
+    fn startup_step(&self) -> u8 {
+        match self {
+            HoprInitStatus::ValidatingConfig => 0,
+            HoprInitStatus::IdentifyingNode => 1,
+            HoprInitStatus::ConnectingBlockchain => 2,
+            HoprInitStatus::CreatingNode => 3,
+            HoprInitStatus::StartingNode => 4,
+            HoprInitStatus::Ready => 5,
+        }
+    }
+}
+
+impl HoprStatus {
+    fn startup_step(&self) -> Option<u8> {
+        match self {
+            HoprStatus::Uninitialized => Some(0),
+            HoprStatus::WaitingForFunds => Some(1),
+            HoprStatus::CheckingBalance => Some(2),
+            HoprStatus::ValidatingNetworkConfig => Some(3),
+            HoprStatus::CheckingOnchainAddress => Some(4),
+            HoprStatus::RegisteringSafe => Some(5),
+            HoprStatus::AnnouncingNode => Some(6),
+            HoprStatus::AwaitingKeyBinding => Some(7),
+            HoprStatus::InitializingServices => Some(8),
+            HoprStatus::Running => Some(9),
+            HoprStatus::Terminated | HoprStatus::Degraded | HoprStatus::Failed => None,
+        }
+    }
 }
 
 impl ConnectResponse {
@@ -383,6 +923,25 @@ impl ConnectResponse {
     pub fn destination_not_found() -> Self {
         ConnectResponse::DestinationNotFound
     }
+    pub fn budget_exceeded(usage: budget::Usage) -> Self {
+        ConnectResponse::BudgetExceeded(usage)
+    }
+    pub fn blocked_by_policy(reason: String) -> Self {
+        ConnectResponse::BlockedByPolicy(reason)
+    }
+    pub fn queued(destination: Destination, run_mode: RunMode) -> Self {
+        ConnectResponse::Queued {
+            destination,
+            startup_percent: run_mode.startup_percent(),
+            run_mode,
+        }
+    }
+    pub fn not_ready(reason: NotReadyReason, retry_in: Duration) -> Self {
+        ConnectResponse::NotReady { reason, retry_in }
+    }
+    pub fn none_ready() -> Self {
+        ConnectResponse::NoneReady
+    }
 }
 
 impl DisconnectResponse {
@@ -395,6 +954,16 @@ impl DisconnectResponse {
     }
 }
 
+impl CancelConnectResponse {
+    pub fn cancelled(destination: Destination) -> Self {
+        CancelConnectResponse::Cancelled(destination)
+    }
+
+    pub fn not_connecting() -> Self {
+        CancelConnectResponse::NotConnecting
+    }
+}
+
 impl Response {
     pub fn connect(conn: ConnectResponse) -> Self {
         Response::Connect(conn)
@@ -404,6 +973,10 @@ impl Response {
         Response::Disconnect(disc)
     }
 
+    pub fn cancel_connect(cancel: CancelConnectResponse) -> Self {
+        Response::CancelConnect(cancel)
+    }
+
     pub fn nerd_stats(stats: NerdStatsResponse) -> Self {
         Response::NerdStats(stats)
     }
@@ -423,14 +996,28 @@ impl Response {
 
 impl Display for Command {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let s = log_output::serialize(self);
+        let s = match self {
+            Command::FundingTool(secret) => {
+                log_output::serialize(&serde_json::json!({ "FundingTool": secret.to_string() }))
+            }
+            // exports may carry access tokens of private exits
+            Command::ImportDestinations(export) => {
+                log_output::serialize(&serde_json::json!({ "ImportDestinations": format!("{} bytes", export.len()) }))
+            }
+            cmd => log_output::serialize(cmd),
+        };
         write!(f, "{s}")
     }
 }
 
 impl Display for WorkerCommand {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let s = log_output::serialize(self);
+        let s = match self {
+            WorkerCommand::FundingTool(secret) => {
+                log_output::serialize(&serde_json::json!({ "FundingTool": secret.to_string() }))
+            }
+            cmd => log_output::serialize(cmd),
+        };
         write!(f, "{s}")
     }
 }
@@ -479,6 +1066,9 @@ impl From<EdgliInitState> for HoprInitStatus {
 impl Display for RunMode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            RunMode::WaitingForNetwork { since } => {
+                write!(f, "Waiting for network (since {})", log_output::elapsed(since))
+            }
             RunMode::Init { last_error: None } => write!(f, "Initializing"),
             RunMode::Init { last_error: Some(err) } => write!(f, "Initializing (last error: {err})"),
             RunMode::PreparingSafe {
@@ -555,10 +1145,12 @@ impl Display for ConnectingInfo {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Connecting to {} (since {}, phase {})",
+            "Connecting to {} (since {}, phase {}, {}% - about {}s left)",
             self.destination_id,
             log_output::elapsed(&self.since),
-            self.phase
+            self.phase,
+            self.progress_percent,
+            self.eta.as_secs()
         )
     }
 }
@@ -579,10 +1171,18 @@ impl Display for ConnectedInfo {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Connected to {} (since {})",
+            "Connected to {} (since {}",
             self.destination_id,
-            log_output::elapsed(&self.since)
-        )
+            log_output::elapsed(&self.connected_since)
+        )?;
+        if let Some(handshake) = self.last_handshake {
+            write!(f, ", last handshake {} ago", log_output::elapsed(&handshake))?;
+        }
+        write!(f, ")")?;
+        if let Some(public_ip) = &self.public_ip {
+            write!(f, "\n{public_ip}")?;
+        }
+        Ok(())
     }
 }
 
@@ -598,6 +1198,15 @@ impl Display for DisconnectingInfo {
     }
 }
 
+impl Display for NotReadyReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NotReadyReason::PeerNotSeen => write!(f, "exit node is not seen by the node"),
+            NotReadyReason::NoChannel => write!(f, "no outgoing channel to a connected peer is open"),
+        }
+    }
+}
+
 impl Display for HoprStatus {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -639,18 +1248,108 @@ impl TryFrom<Command> for WorkerCommand {
             Command::Status => Ok(WorkerCommand::Status),
             Command::NerdStats => Ok(WorkerCommand::NerdStats),
             Command::Connect(dest) => Ok(WorkerCommand::Connect(dest)),
+            Command::ConnectAny { avoid_failed, rotate } => Ok(WorkerCommand::ConnectAny { avoid_failed, rotate }),
+            Command::ConnectFailover(ids) => Ok(WorkerCommand::ConnectFailover(ids)),
             Command::Disconnect => Ok(WorkerCommand::Disconnect),
+            Command::CancelConnect => Ok(WorkerCommand::CancelConnect),
             Command::Balance => Ok(WorkerCommand::Balance),
+            Command::BalanceHistory { days } => Ok(WorkerCommand::BalanceHistory { days }),
             Command::FundingTool(secret) => Ok(WorkerCommand::FundingTool(secret)),
             Command::Telemetry => Ok(WorkerCommand::Telemetry),
+            Command::Info => Ok(WorkerCommand::Info),
+            Command::Peers => Ok(WorkerCommand::Peers),
+            Command::Sessions => Ok(WorkerCommand::Sessions),
+            Command::UsageTelemetry(enable) => Ok(WorkerCommand::UsageTelemetry(enable)),
+            Command::Transactions => Ok(WorkerCommand::Transactions),
+            Command::ExitReports => Ok(WorkerCommand::ExitReports),
+            Command::ReportIssue { destination } => Ok(WorkerCommand::ReportIssue { destination }),
+            Command::Diagnose => Ok(WorkerCommand::Diagnose),
             // Commands that are not relevant for the worker
-            Command::Info | Command::Ping | Command::StartClient(_) | Command::StopClient | Command::Destinations => {
-                Err(())
-            }
+            Command::Ping
+            | Command::StartClient(_)
+            | Command::StopClient
+            | Command::Destinations
+            | Command::ExportDestinations
+            | Command::ImportDestinations(_)
+            | Command::RateLimit
+            | Command::SetRateLimit(_)
+            | Command::Gateway
+            | Command::RoutingExplain
+            | Command::Backup(_)
+            | Command::Compact
+            | Command::Unpair(_)
+            | Command::Logs { .. } => Err(()),
         }
     }
 }
 
+impl Command {
+    /// Queries that leave service and node untouched, the only commands an observer answers.
+    pub fn is_read_only(&self) -> bool {
+        match self {
+            Command::Status
+            | Command::NerdStats
+            | Command::Balance
+            | Command::BalanceHistory { .. }
+            | Command::Telemetry
+            | Command::Ping
+            | Command::Info
+            | Command::Destinations
+            | Command::RateLimit
+            | Command::Peers
+            | Command::Sessions
+            | Command::Gateway
+            | Command::RoutingExplain
+            | Command::UsageTelemetry(None)
+            | Command::Transactions
+            | Command::ExitReports
+            | Command::ReportIssue { .. }
+            | Command::Logs { .. }
+            | Command::Diagnose => true,
+            Command::Connect(_)
+            | Command::ConnectAny { .. }
+            | Command::ConnectFailover(_)
+            | Command::Disconnect
+            | Command::CancelConnect
+            | Command::FundingTool(_)
+            | Command::StartClient(_)
+            | Command::StopClient
+            | Command::SetRateLimit(_)
+            | Command::ImportDestinations(_)
+            | Command::UsageTelemetry(Some(_))
+            | Command::Backup(_)
+            | Command::Compact
+            | Command::Unpair(_) => false,
+            // carries destination credentials, see `is_local_only`
+            Command::ExportDestinations => false,
+        }
+    }
+
+    /// Commands handing out credentials or touching files on the service host, refused for remote clients.
+    pub fn is_local_only(&self) -> bool {
+        matches!(
+            self,
+            Command::Backup(_) | Command::ExportDestinations | Command::ImportDestinations(_) | Command::Unpair(_)
+        )
+    }
+
+    /// Queries answered with secrets or raw log output, kept off the observer socket.
+    pub fn is_private(&self) -> bool {
+        matches!(
+            self,
+            Command::ExportDestinations | Command::Logs { .. } | Command::Diagnose
+        )
+    }
+}
+
+/// JSON schema of the commands accepted on the control socket and of the responses sent back.
+pub fn json_schema() -> serde_json::Value {
+    serde_json::json!({
+        "command": schemars::schema_for!(Command),
+        "response": schemars::schema_for!(Response),
+    })
+}
+
 impl Display for RouteHealthView {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.state)?;
@@ -663,6 +1362,13 @@ impl Display for RouteHealthView {
         if let Some(err) = &self.last_error {
             write!(f, " (last error: {err})")?;
         }
+        if let Some(cancelled_at) = &self.cancelled_at {
+            write!(
+                f,
+                " (connect cancelled {} ago)",
+                crate::log_output::elapsed(cancelled_at)
+            )?;
+        }
         Ok(())
     }
 }
@@ -670,7 +1376,7 @@ impl Display for RouteHealthView {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::connection::destination::HopRouting;
+    use crate::connection::destination::{Auth, HopRouting};
     use crate::gvpn_client;
     use crate::route_health::ExitHealth;
     use std::collections::HashMap;
@@ -708,6 +1414,7 @@ mod tests {
                         fifteen: 0.3,
                         nproc: 4,
                     },
+                    maintenance: None,
                 },
             },
         }
@@ -739,6 +1446,27 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn startup_percent_follows_init_and_node_steps() -> anyhow::Result<()> {
+        let warmup = |hopr_init_status, hopr_status| RunMode::Warmup {
+            hopr_init_status,
+            hopr_status,
+            last_error: None,
+        };
+        assert_eq!(RunMode::Init { last_error: None }.startup_percent(), Some(0));
+        assert_eq!(
+            warmup(Some(HoprInitStatus::CreatingNode), None).startup_percent(),
+            Some(18)
+        );
+        assert_eq!(
+            warmup(Some(HoprInitStatus::Ready), Some(HoprStatus::AnnouncingNode)).startup_percent(),
+            Some(75)
+        );
+        assert_eq!(warmup(None, Some(HoprStatus::Failed)).startup_percent(), None);
+        assert_eq!(RunMode::Shutdown.startup_percent(), None);
+        Ok(())
+    }
+
     #[test]
     fn connect_response_helpers_cover_all_variants() -> anyhow::Result<()> {
         let dest = destination();
@@ -755,6 +1483,15 @@ mod tests {
             ConnectResponse::destination_not_found(),
             ConnectResponse::DestinationNotFound
         ));
+
+        let not_ready = ConnectResponse::not_ready(NotReadyReason::NoChannel, Duration::from_secs(60));
+        assert!(matches!(
+            not_ready,
+            ConnectResponse::NotReady {
+                reason: NotReadyReason::NoChannel,
+                ..
+            }
+        ));
         Ok(())
     }
 
@@ -836,4 +1573,125 @@ mod tests {
         let with_error: RunMode = serde_json::from_str(r#"{"Init":{"last_error":"connection refused"}}"#).unwrap();
         assert!(matches!(with_error, RunMode::Init { last_error: Some(ref e) } if e == "connection refused"));
     }
+
+    #[test]
+    fn connected_info_keeps_since_on_the_wire() -> anyhow::Result<()> {
+        let info: ConnectedInfo = serde_json::from_str(r#"{"destination_id":"Germany","since":1000}"#)?;
+        assert_eq!(info.connected_since, SystemTime::UNIX_EPOCH + Duration::from_secs(1));
+        assert_eq!(info.last_handshake, None);
+        assert_eq!(info.duration, Duration::ZERO);
+
+        let json = serde_json::to_value(&info)?;
+        assert_eq!(json["since"], 1000);
+        assert!(json.get("connected_since").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn only_queries_are_read_only() {
+        assert!(Command::Status.is_read_only());
+        assert!(Command::Telemetry.is_read_only());
+        assert!(Command::UsageTelemetry(None).is_read_only());
+        assert!(!Command::UsageTelemetry(Some(true)).is_read_only());
+        assert!(!Command::Connect("Germany".to_string()).is_read_only());
+        assert!(!Command::SetRateLimit(None).is_read_only());
+        assert!(!Command::Compact.is_read_only());
+        assert!(Command::Diagnose.is_read_only());
+        assert!(!Command::ExportDestinations.is_read_only());
+        assert!(!Command::ImportDestinations(String::new()).is_read_only());
+        assert!(
+            Command::ReportIssue {
+                destination: "Germany".to_string()
+            }
+            .is_read_only()
+        );
+        assert!(
+            Command::Logs {
+                follow: true,
+                lines: 100
+            }
+            .is_read_only()
+        );
+    }
+
+    #[test]
+    fn credentials_and_files_stay_local() {
+        assert!(Command::ExportDestinations.is_local_only());
+        assert!(Command::ImportDestinations(String::new()).is_local_only());
+        assert!(Command::Backup(std::path::PathBuf::from("/tmp/backup.tar.gz")).is_local_only());
+        assert!(Command::Unpair("laptop".to_string()).is_local_only());
+        assert!(!Command::Status.is_local_only());
+        assert!(!Command::Connect("Germany".to_string()).is_local_only());
+    }
+
+    #[test]
+    fn secrets_and_logs_are_private() {
+        assert!(Command::ExportDestinations.is_private());
+        assert!(Command::Diagnose.is_private());
+        assert!(
+            Command::Logs {
+                follow: false,
+                lines: 10
+            }
+            .is_private()
+        );
+        assert!(!Command::Status.is_private());
+        assert!(!Command::Destinations.is_private());
+    }
+
+    #[test]
+    fn funding_tool_secret_is_only_serialized() -> anyhow::Result<()> {
+        let cmd = Command::FundingTool(Secret::new("s3cr3t".to_string()));
+        assert_eq!(serde_json::to_string(&cmd)?, r#"{"FundingTool":"s3cr3t"}"#);
+        assert!(!cmd.to_string().contains("s3cr3t"));
+        assert!(!format!("{cmd:?}").contains("s3cr3t"));
+        let worker_cmd = WorkerCommand::try_from(cmd).expect("worker command");
+        assert!(!worker_cmd.to_string().contains("s3cr3t"));
+        assert!(!format!("{worker_cmd:?}").contains("s3cr3t"));
+        Ok(())
+    }
+
+    #[test]
+    fn destination_tokens_stay_out_of_responses() -> anyhow::Result<()> {
+        let dest = destination().with_auth(Some(Auth::Token("s3cr3t-token".to_string())));
+        let status = Response::status(StatusResponse {
+            run_mode: RunMode::NotRunning,
+            destinations: vec![DestinationState {
+                destination: dest.clone(),
+                route_health: None,
+            }],
+            target_destination: None,
+            connecting: None,
+            reconnecting: None,
+            connected: None,
+            disconnecting: vec![],
+            budget: None,
+            manual_channel_funding: false,
+            retrying: vec![],
+            previous_crash: None,
+            disk_space: None,
+            conflicts: vec![],
+            kill_switch_error: None,
+            hints: vec![],
+        });
+        let connect = Response::connect(ConnectResponse::connecting(dest.clone()));
+        for resp in [status, connect] {
+            let json = serde_json::to_string(&resp)?;
+            assert!(!json.contains("s3cr3t-token"), "{json}");
+            assert!(json.contains("token"), "{json}");
+        }
+        // the worker still receives the token within its configuration
+        assert!(serde_json::to_string(&dest)?.contains("s3cr3t-token"));
+        Ok(())
+    }
+
+    #[test]
+    fn json_schema_covers_commands_and_responses() {
+        let schema = json_schema();
+        let command = schema["command"].to_string();
+        assert!(command.contains("SetRateLimit") && command.contains("Compact"));
+        let response = schema["response"].to_string();
+        assert!(response.contains("StatusResponse") && response.contains("DiskSpaceUsage"));
+        assert!(response.contains("ConnectPhase") && response.contains("DisconnectPhase"));
+    }
 }
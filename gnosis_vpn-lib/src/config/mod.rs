@@ -2,13 +2,23 @@ use edgli::hopr_lib::api::types::primitive::errors::GeneralError;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::fs;
 
+use crate::backoff::Config as BackoffConfig;
+use crate::balance::{Config as BalancesConfig, ExternalSafe};
+use crate::budget::Config as BudgetConfig;
 use crate::connection::{destination::Destination, options::Options as ConnectionOptions};
+use crate::dirs::Config as DirsConfig;
+use crate::disk_space::Config as DiskSpaceConfig;
 use crate::hopr::blokli_config::BlokliConfig;
 use crate::hopr::strategy_config::StrategyConfig;
+use crate::management::Config as ManagementConfig;
+use crate::proxy::Config as NetworkConfig;
+use crate::socket::root::Config as SocketConfig;
+use crate::telemetry::Config as TelemetryConfig;
 use crate::wireguard::Config as WireGuardConfig;
 
 mod v3;
@@ -18,14 +28,36 @@ mod v6;
 
 pub const DEFAULT_PATH: &str = "/etc/gnosisvpn/config.toml";
 pub const ENV_VAR: &str = "GNOSISVPN_CONFIG_PATH";
+pub const DEFAULT_NETWORK_ONLINE_TIMEOUT: Duration = Duration::from_secs(90);
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Config {
     pub connection: ConnectionOptions,
     pub destinations: HashMap<String, Destination>,
+    /// Destination ids in order of preference, usable wherever a destination id is
+    pub destination_groups: BTreeMap<String, Vec<String>>,
     pub wireguard: WireGuardConfig,
     pub blokli: BlokliConfig,
     pub strategy: StrategyConfig,
+    pub budget: BudgetConfig,
+    pub backoff: BackoffConfig,
+    pub balances: BalancesConfig,
+    /// Safe managed outside the client, skips onboarding and all fund movements
+    pub external_safe: Option<ExternalSafe>,
+    pub socket: SocketConfig,
+    /// Managed mode, see [`crate::management`]
+    pub management: Option<ManagementConfig>,
+    /// Data and cache locations, only read on service start
+    pub dirs: DirsConfig,
+    pub disk_space: DiskSpaceConfig,
+    /// Anonymous usage telemetry and crash reports, see [`crate::telemetry`]
+    pub telemetry: TelemetryConfig,
+    /// Language of status hints and ctl output, see [`crate::i18n::Locale::resolve`]
+    pub locale: Option<String>,
+    /// How long the first worker start waits for a usable network, zero starts right away
+    pub network_online_timeout: Duration,
+    /// Egress of HTTP(S) requests, see [`crate::proxy`]
+    pub network: NetworkConfig,
 }
 
 #[derive(Debug, Error)]
@@ -38,16 +70,61 @@ pub enum Error {
     IO(#[from] std::io::Error),
     #[error("Deserialization error: {0}")]
     TomlDeserialization(#[from] toml::de::Error),
+    #[error("Serialization error: {0}")]
+    TomlSerialization(#[from] toml::ser::Error),
+    #[error("Destination {0} is already configured differently")]
+    DestinationConflict(String),
     #[error("Unsupported config version: {0}")]
     VersionMismatch(u8),
     #[error("No destinations")]
     NoDestinations,
+    #[error("Destination group {0} shares its name with a destination")]
+    GroupNameConflict(String),
+    #[error("Destination group {group} lists unknown destination {id}")]
+    UnknownGroupDestination { group: String, id: String },
     #[error("ping and main sessions must both have surb_balancing enabled or both disabled")]
     SurbBalancingMismatch,
+    #[error("backoff initial must be non-zero and not exceed max_interval")]
+    InvalidBackoff,
+    #[error("disk_space interval and compact_every must be non-zero")]
+    InvalidDiskSpaceInterval,
+    #[error("socket mode {0:#o} is not a valid permission mode")]
+    InvalidSocketMode(u32),
+    #[error("kill_switch is not supported on this platform")]
+    KillSwitchUnsupported,
     #[error("Error in hopr-lib: {0}")]
     HoprGeneral(#[from] GeneralError),
 }
 
+impl Config {
+    /// Channels are neither opened nor topped up by the client, see [`StrategyConfig::auto_fund_channels`].
+    pub fn manual_channel_funding(&self) -> bool {
+        self.external_safe.is_some() || !self.strategy.auto_fund_channels
+    }
+
+    /// Destinations to try in order for a list of destination ids and group names.
+    ///
+    /// Groups expand to their members, repeated and unknown entries are skipped as a management
+    /// policy may have removed destinations a group lists.
+    pub fn failover_candidates(&self, ids: &[String]) -> Vec<Destination> {
+        let mut candidates: Vec<Destination> = Vec::new();
+        for id in ids {
+            let members = self
+                .destination_groups
+                .get(id)
+                .map_or(std::slice::from_ref(id), Vec::as_slice);
+            for member in members {
+                if let Some(dest) = self.destinations.get(member)
+                    && !candidates.contains(dest)
+                {
+                    candidates.push(dest.clone());
+                }
+            }
+        }
+        candidates
+    }
+}
+
 pub async fn read(path: &Path) -> Result<Config, Error> {
     let content = fs::read_to_string(path).await.map_err(|e| {
         if e.kind() == std::io::ErrorKind::NotFound {
@@ -56,7 +133,10 @@ pub async fn read(path: &Path) -> Result<Config, Error> {
             Error::IO(e)
         }
     })?;
+    parse(&content)
+}
 
+fn parse(content: &str) -> Result<Config, Error> {
     let table = content.parse::<toml::Table>()?;
     let version = table
         .get("version")
@@ -65,7 +145,7 @@ pub async fn read(path: &Path) -> Result<Config, Error> {
 
     match version {
         3 => {
-            let res = toml::from_str::<v3::Config>(&content)?;
+            let res = toml::from_str::<v3::Config>(content)?;
             let wrong_keys = v3::wrong_keys(&table);
             for key in wrong_keys.iter() {
                 tracing::warn!(%key, "ignoring unsupported key in configuration file");
@@ -73,7 +153,7 @@ pub async fn read(path: &Path) -> Result<Config, Error> {
             res.try_into()
         }
         4 => {
-            let res = toml::from_str::<v4::Config>(&content)?;
+            let res = toml::from_str::<v4::Config>(content)?;
             let wrong_keys = v4::wrong_keys(&table);
             for key in wrong_keys.iter() {
                 tracing::warn!(%key, "ignoring unsupported key in configuration file");
@@ -81,7 +161,7 @@ pub async fn read(path: &Path) -> Result<Config, Error> {
             res.try_into()
         }
         5 => {
-            let res = toml::from_str::<v5::Config>(&content)?;
+            let res = toml::from_str::<v5::Config>(content)?;
             let wrong_keys = v5::wrong_keys(&table);
             for key in wrong_keys.iter() {
                 tracing::warn!(%key, "ignoring unsupported key in configuration file");
@@ -89,7 +169,7 @@ pub async fn read(path: &Path) -> Result<Config, Error> {
             res.try_into()
         }
         6 => {
-            let res = toml::from_str::<v6::Config>(&content)?;
+            let res = toml::from_str::<v6::Config>(content)?;
             let wrong_keys = v6::wrong_keys(&table);
             for key in wrong_keys.iter() {
                 tracing::warn!(%key, "ignoring unsupported key in configuration file");
@@ -99,3 +179,190 @@ pub async fn read(path: &Path) -> Result<Config, Error> {
         _ => Err(Error::VersionMismatch(version as u8)),
     }
 }
+
+/// Destinations as `[destinations.<id>]` tables, ready to be imported on another machine.
+pub fn export_destinations(destinations: &HashMap<String, Destination>) -> Result<String, Error> {
+    Ok(v6::export_destinations(destinations)?)
+}
+
+/// Appends the destinations of an export to the configuration file at `path`, returns the added ids.
+///
+/// Destinations configured identically already are skipped, one configured differently refuses the
+/// whole import. The file is replaced in one go so the config watcher only sees the final result.
+pub async fn import_destinations(path: &Path, export: &str) -> Result<Vec<String>, Error> {
+    let imported = v6::import_destinations(export)?;
+    let content = fs::read_to_string(path).await?;
+    let current = parse(&content)?;
+    let mut added = HashMap::new();
+    for (id, dest) in imported {
+        match current.destinations.get(&id) {
+            Some(existing) if *existing == dest => (),
+            Some(_) => return Err(Error::DestinationConflict(id)),
+            None => {
+                added.insert(id, dest);
+            }
+        }
+    }
+    if added.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let updated = format!("{}\n\n{}", content.trim_end(), v6::export_destinations(&added)?);
+    // older configuration versions might not accept the tables
+    parse(&updated)?;
+    let partial = path.with_extension("toml.partial");
+    fs::write(&partial, updated).await?;
+    fs::set_permissions(&partial, fs::metadata(path).await?.permissions()).await?;
+    fs::rename(&partial, path).await?;
+
+    let mut ids: Vec<String> = added.into_keys().collect();
+    ids.sort_unstable();
+    Ok(ids)
+}
+
+/// Socket path configured in `[socket]`, without validating the rest of the file.
+///
+/// Lets clients find the service socket even if they cannot make sense of the full configuration.
+pub async fn read_socket_path(path: &Path) -> Option<PathBuf> {
+    let content = fs::read_to_string(path).await.ok()?;
+    let table = content.parse::<toml::Table>().ok()?;
+    table
+        .get("socket")
+        .and_then(|s| s.get("path"))
+        .and_then(|p| p.as_str())
+        .map(PathBuf::from)
+}
+
+/// Top level `locale` key, without validating the rest of the file.
+pub async fn read_locale(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).await.ok()?;
+    let table = content.parse::<toml::Table>().ok()?;
+    table.get("locale").and_then(|l| l.as_str()).map(str::to_string)
+}
+
+/// Directories configured in `[dirs]`, without validating the rest of the file.
+///
+/// The service places its logs and state before it reads the full configuration.
+pub async fn read_dirs(path: &Path) -> DirsConfig {
+    let Ok(content) = fs::read_to_string(path).await else {
+        return DirsConfig::default();
+    };
+    let Ok(table) = content.parse::<toml::Table>() else {
+        return DirsConfig::default();
+    };
+    let dir = |key: &str| {
+        table
+            .get("dirs")
+            .and_then(|d| d.get(key))
+            .and_then(|p| p.as_str())
+            .map(PathBuf::from)
+    };
+    DirsConfig {
+        data: dir("data"),
+        cache: dir("cache"),
+    }
+}
+
+/// Egress settings of `[network]`, without validating the rest of the file.
+///
+/// ctl makes its own requests and leaves the host through the same proxy and trust as the service.
+pub async fn read_network(path: &Path) -> NetworkConfig {
+    let Ok(content) = fs::read_to_string(path).await else {
+        return NetworkConfig::default();
+    };
+    let Ok(table) = content.parse::<toml::Table>() else {
+        return NetworkConfig::default();
+    };
+    table
+        .get("network")
+        .cloned()
+        .and_then(|n| n.try_into::<v6::Network>().ok())
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#####"# hand written
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+"#####;
+
+    #[tokio::test]
+    async fn import_appends_new_destinations_only() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("config.toml");
+        fs::write(&path, CONFIG).await?;
+        let existing = read(&path).await?;
+
+        let mut export_config = existing.clone();
+        let mut usa = export_config.destinations["Germany"].clone();
+        usa.id = "USA".to_string();
+        usa.address = "0xa5Ca174Ef94403d6162a969341a61baeA48F57F8".parse()?;
+        export_config.destinations.insert("USA".to_string(), usa);
+        let export = export_destinations(&export_config.destinations)?;
+
+        assert_eq!(import_destinations(&path, &export).await?, vec!["USA".to_string()]);
+        let content = fs::read_to_string(&path).await?;
+        assert!(content.starts_with("# hand written"));
+        assert_eq!(read(&path).await?.destinations, export_config.destinations);
+        assert!(import_destinations(&path, &export).await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn import_refuses_differing_destination() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("config.toml");
+        fs::write(&path, CONFIG).await?;
+        let mut destinations = read(&path).await?.destinations;
+        if let Some(germany) = destinations.get_mut("Germany") {
+            germany.meta.insert("location".to_string(), "Berlin".to_string());
+        }
+
+        let res = import_destinations(&path, &export_destinations(&destinations)?).await;
+        assert!(matches!(res, Err(Error::DestinationConflict(id)) if id == "Germany"));
+        assert_eq!(fs::read_to_string(&path).await?, CONFIG);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failover_candidates_expand_groups_in_order() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("config.toml");
+        let content = format!(
+            r#####"{CONFIG}
+[destinations.USA]
+address = "0xa5Ca174Ef94403d6162a969341a61baeA48F57F8"
+
+[destination_groups]
+all = ["USA", "Germany"]
+"#####
+        );
+        fs::write(&path, content).await?;
+        let config = read(&path).await?;
+        let ids = |candidates: Vec<Destination>| candidates.into_iter().map(|d| d.id).collect::<Vec<_>>();
+
+        let candidates = config.failover_candidates(&["Germany".to_string(), "all".to_string()]);
+        assert_eq!(ids(candidates), vec!["Germany", "USA"]);
+        let candidates = config.failover_candidates(&["all".to_string(), "unknown".to_string()]);
+        assert_eq!(ids(candidates), vec!["USA", "Germany"]);
+
+        fs::write(
+            &path,
+            CONFIG.replace(
+                "[destinations.",
+                "[destination_groups]
+Germany = [\"Germany\"]
+
+[destinations.",
+            ),
+        )
+        .await?;
+        assert!(matches!(read(&path).await, Err(Error::GroupNameConflict(name)) if name == "Germany"));
+        Ok(())
+    }
+}
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 use serde_with::{DisplayFromStr, serde_as};
 
 use std::cmp::PartialEq;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::vec::Vec;
 
 use crate::config;
@@ -138,9 +138,22 @@ impl TryFrom<Config> for config::Config {
         Ok(config::Config {
             connection,
             destinations,
+            destination_groups: BTreeMap::new(),
             wireguard,
             blokli,
             strategy: Default::default(),
+            budget: Default::default(),
+            backoff: Default::default(),
+            balances: Default::default(),
+            external_safe: None,
+            socket: Default::default(),
+            dirs: Default::default(),
+            disk_space: Default::default(),
+            management: None,
+            telemetry: Default::default(),
+            locale: None,
+            network_online_timeout: config::DEFAULT_NETWORK_ONLINE_TIMEOUT,
+            network: Default::default(),
         })
     }
 }
@@ -7,7 +7,7 @@ use human_bandwidth::re::bandwidth::Bandwidth;
 use serde::{Deserialize, Serialize};
 use serde_with::{DisplayFromStr, serde_as};
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::net::SocketAddr;
 use std::time::Duration;
 use std::vec::Vec;
@@ -165,7 +165,10 @@ impl From<Option<Connection>> for options::Options {
             .and_then(|c| c.http_timeout)
             .unwrap_or(Connection::default_http_timeout());
 
-        let timeouts = options::Timeouts { http: http_timeout };
+        let timeouts = options::Timeouts {
+            http: http_timeout,
+            phases: Default::default(),
+        };
 
         let def_intervals = options::HealthCheckIntervals::default();
         let health_check_intervals = connection
@@ -188,9 +191,16 @@ impl From<Option<Connection>> for options::Options {
             timeouts,
             health_check_intervals,
             lan_lockdown: false,
+            kill_switch: false,
             // 1s effectively disables pseudonym caching; revert once hopr-lib supports PIX
             session_pseudonym_ttl: Duration::from_secs(1),
+            affinity_ttl: options::DEFAULT_AFFINITY_TTL,
             path_planner_min_ack_rate: options::DEFAULT_PATH_PLANNER_MIN_ACK_RATE,
+            egress_rate_limit: None,
+            dscp: None,
+            gateway: None,
+            public_ip_endpoint: options::default_public_ip_endpoint(),
+            reconnect: options::Reconnect::default(),
         }
     }
 }
@@ -367,9 +377,22 @@ impl TryFrom<Config> for config::Config {
         Ok(config::Config {
             connection,
             destinations,
+            destination_groups: BTreeMap::new(),
             wireguard,
             blokli,
             strategy: Default::default(),
+            budget: Default::default(),
+            backoff: Default::default(),
+            balances: Default::default(),
+            external_safe: None,
+            socket: Default::default(),
+            dirs: Default::default(),
+            disk_space: Default::default(),
+            management: None,
+            telemetry: Default::default(),
+            locale: None,
+            network_online_timeout: config::DEFAULT_NETWORK_ONLINE_TIMEOUT,
+            network: Default::default(),
         })
     }
 }
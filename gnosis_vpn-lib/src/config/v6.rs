@@ -5,25 +5,39 @@
 /// `path = { intermediates = [...] }` with `path = { hops = <count> }`.
 use bytesize::ByteSize;
 use edgli::hopr_lib::HopRouting;
-use edgli::hopr_lib::api::types::primitive::prelude::Address;
+use edgli::hopr_lib::api::types::primitive::prelude::{Address, Balance, WxHOPR};
 use edgli::hopr_lib::exports::network::types::types::{IpOrHost, SealedHost};
 use edgli::hopr_lib::exports::transport::{SessionCapabilities, SessionCapability, SessionTarget};
 use human_bandwidth::re::bandwidth::Bandwidth;
 use serde::{Deserialize, Deserializer, Serialize};
-use serde_with::{DisplayFromStr, serde_as};
+use serde_with::{DisplayFromStr, hex::Hex, serde_as};
+use url::Url;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::net::IpAddr;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::time::Duration;
 use std::vec::Vec;
 
+use crate::backoff;
+use crate::balance;
+use crate::budget;
 use crate::config;
-use crate::connection::{destination::Destination as ConnDestination, options};
+use crate::connection::destination::{Auth, Destination as ConnDestination};
+use crate::connection::options;
+use crate::dirs;
+use crate::disk_space;
 use crate::hopr::blokli_config::BlokliConfig as HoprBlokliConfig;
 use crate::hopr::strategy_config::StrategyConfig;
+use crate::management;
 use crate::ping;
-use crate::wireguard::Config as WireGuardConfig;
+use crate::proxy;
+use crate::serde_utils;
+use crate::socket;
+use crate::telemetry;
+use crate::tls;
+use crate::wireguard::{self, BlockIpv6, Config as WireGuardConfig, DnsStrategy, Tooling as WireGuardTooling};
 
 // Maximum supported hop count — used in both v5 and v6 conversion.
 pub(super) const MAX_HOPS: u8 = 3;
@@ -40,10 +54,53 @@ pub(super) struct Connection {
     pub(super) surb_balancing: Option<SurbBalancingConfig>,
     pub(super) health_check_intervals: Option<HealthCheckIntervalOptions>,
     pub(super) lan_lockdown: Option<bool>,
+    pub(super) kill_switch: Option<bool>,
     #[serde(default, with = "humantime_serde::option")]
     pub(super) session_pseudonym_ttl: Option<Duration>,
+    #[serde(default, with = "humantime_serde::option")]
+    pub(super) affinity_ttl: Option<Duration>,
     #[serde(default, deserialize_with = "validate_path_planner_min_ack_rate")]
     pub(super) path_planner_min_ack_rate: Option<f64>,
+    #[serde(default, with = "human_bandwidth::serde")]
+    pub(super) egress_rate_limit: Option<Bandwidth>,
+    #[serde(default, deserialize_with = "validate_dscp")]
+    pub(super) dscp: Option<u8>,
+    pub(super) phase_timeouts: Option<PhaseTimeoutOptions>,
+    pub(super) gateway: Option<GatewayOptions>,
+    /// Empty disables the public IP lookup
+    #[serde(default, deserialize_with = "validate_public_ip_endpoint")]
+    pub(super) public_ip_endpoint: Option<String>,
+    pub(super) reconnect: Option<ReconnectOptions>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct GatewayOptions {
+    pub(super) lan_interface: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct ReconnectOptions {
+    pub(super) max_retries: Option<u32>,
+    #[serde(default, with = "humantime_serde::option")]
+    pub(super) initial_delay: Option<Duration>,
+    #[serde(default, with = "humantime_serde::option")]
+    pub(super) max_delay: Option<Duration>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct PhaseTimeoutOptions {
+    #[serde(default, with = "humantime_serde::option")]
+    pub(super) open_bridge: Option<Duration>,
+    #[serde(default, with = "humantime_serde::option")]
+    pub(super) register_wg: Option<Duration>,
+    #[serde(default, with = "humantime_serde::option")]
+    pub(super) open_ping: Option<Duration>,
+    #[serde(default, with = "humantime_serde::option")]
+    pub(super) establish_wg_tunnel: Option<Duration>,
+    #[serde(default, with = "humantime_serde::option")]
+    pub(super) killswitch_lockdown: Option<Duration>,
+    #[serde(default, with = "humantime_serde::option")]
+    pub(super) verify_ping: Option<Duration>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -112,17 +169,29 @@ pub(super) struct WireGuard {
     pub(super) allowed_ips: Option<String>,
     pub(super) force_private_key: Option<String>,
     pub(super) dns: Option<WireGuardDNS>,
+    pub(super) wg_quick: Option<PathBuf>,
+    pub(super) hooks: Option<WireGuardHooks>,
+    pub(super) block_ipv6: Option<BlockIpv6>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct WireGuardHooks {
+    pub(super) pre_up: Option<String>,
+    pub(super) post_up: Option<String>,
+    pub(super) pre_down: Option<String>,
+    pub(super) post_down: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub(super) struct WireGuardDNS {
     pub overwrite: bool,
     pub servers: Option<String>,
+    pub strategy: Option<DnsStrategy>,
 }
 
 impl WireGuardDNS {
     fn default_server() -> String {
-        "1.1.1.1,8.8.8.8".to_string()
+        wireguard::DEFAULT_DNS.to_string()
     }
 }
 
@@ -148,6 +217,61 @@ where
     }
 }
 
+fn validate_dscp<'de, D>(deserializer: D) -> Result<Option<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<u8>::deserialize(deserializer)?;
+    match value {
+        Some(v) if v > 63 => Err(serde::de::Error::custom("dscp must be in the range [0, 63]")),
+        other => Ok(other),
+    }
+}
+
+fn validate_public_ip_endpoint<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+    match value {
+        Some(v) if !v.is_empty() && Url::parse(&v).is_err() => Err(serde::de::Error::custom(
+            "public_ip_endpoint must be a URL or empty to disable the lookup",
+        )),
+        other => Ok(other),
+    }
+}
+
+fn validate_refresh_interval<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Option<Duration> = humantime_serde::option::deserialize(deserializer)?;
+    match value {
+        Some(d) if d < Duration::from_secs(5) => {
+            Err(serde::de::Error::custom("refresh_interval must be at least 5 seconds"))
+        }
+        v => Ok(v),
+    }
+}
+
+fn validate_daily_limit<'de, D>(deserializer: D) -> Result<Option<Balance<WxHOPR>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+    match value {
+        None => Ok(None),
+        Some(s) => {
+            let limit = s.parse::<Balance<WxHOPR>>().map_err(serde::de::Error::custom)?;
+            if limit.is_zero() {
+                Err(serde::de::Error::custom("daily_limit must be greater than zero"))
+            } else {
+                Ok(Some(limit))
+            }
+        }
+    }
+}
+
 fn validate_n_pings<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
 where
     D: Deserializer<'de>,
@@ -302,7 +426,23 @@ impl From<Option<Connection>> for options::Options {
             .and_then(|c| c.http_timeout)
             .unwrap_or(Connection::default_http_timeout());
 
-        let timeouts = options::Timeouts { http: http_timeout };
+        let def_phases = options::PhaseTimeouts::default();
+        let phases = connection
+            .and_then(|c| c.phase_timeouts.as_ref())
+            .map(|p| options::PhaseTimeouts {
+                open_bridge: p.open_bridge.unwrap_or(def_phases.open_bridge),
+                register_wg: p.register_wg.unwrap_or(def_phases.register_wg),
+                open_ping: p.open_ping.unwrap_or(def_phases.open_ping),
+                establish_wg_tunnel: p.establish_wg_tunnel.unwrap_or(def_phases.establish_wg_tunnel),
+                killswitch_lockdown: p.killswitch_lockdown.unwrap_or(def_phases.killswitch_lockdown),
+                verify_ping: p.verify_ping.unwrap_or(def_phases.verify_ping),
+            })
+            .unwrap_or(def_phases);
+
+        let timeouts = options::Timeouts {
+            http: http_timeout,
+            phases,
+        };
 
         let def_intervals = options::HealthCheckIntervals::default();
         let health_check_intervals = connection
@@ -318,6 +458,16 @@ impl From<Option<Connection>> for options::Options {
             })
             .unwrap_or(def_intervals);
 
+        let def_reconnect = options::Reconnect::default();
+        let reconnect = connection
+            .and_then(|c| c.reconnect.as_ref())
+            .map(|r| options::Reconnect {
+                max_retries: r.max_retries.unwrap_or(def_reconnect.max_retries),
+                initial_delay: r.initial_delay.unwrap_or(def_reconnect.initial_delay),
+                max_delay: r.max_delay.unwrap_or(def_reconnect.max_delay),
+            })
+            .unwrap_or(def_reconnect);
+
         // 1s effectively disables pseudonym caching; revert once hopr-lib supports PIX
         let session_pseudonym_ttl = connection
             .and_then(|c| c.session_pseudonym_ttl)
@@ -330,10 +480,24 @@ impl From<Option<Connection>> for options::Options {
             timeouts,
             health_check_intervals,
             lan_lockdown: connection.and_then(|c| c.lan_lockdown).unwrap_or(false),
+            kill_switch: connection.and_then(|c| c.kill_switch).unwrap_or(false),
             session_pseudonym_ttl,
+            affinity_ttl: connection
+                .and_then(|c| c.affinity_ttl)
+                .unwrap_or(options::DEFAULT_AFFINITY_TTL),
             path_planner_min_ack_rate: connection
                 .and_then(|c| c.path_planner_min_ack_rate)
                 .unwrap_or(options::DEFAULT_PATH_PLANNER_MIN_ACK_RATE),
+            egress_rate_limit: connection.and_then(|c| c.egress_rate_limit),
+            dscp: connection.and_then(|c| c.dscp),
+            gateway: connection.and_then(|c| c.gateway.as_ref()).map(|g| options::Gateway {
+                lan_interface: g.lan_interface.clone(),
+            }),
+            public_ip_endpoint: match connection.and_then(|c| c.public_ip_endpoint.as_deref()) {
+                Some(endpoint) => Url::parse(endpoint).ok(),
+                None => options::default_public_ip_endpoint(),
+            },
+            reconnect,
         }
     }
 }
@@ -355,7 +519,29 @@ impl From<Option<WireGuard>> for WireGuardConfig {
                 })
             })
             .unwrap_or(Some(WireGuardDNS::default_server()));
-        WireGuardConfig::new(listen_port, allowed_ips, force_private_key, dns)
+        let dns_strategy = value
+            .as_ref()
+            .and_then(|wg| wg.dns.as_ref())
+            .and_then(|dns| dns.strategy)
+            .unwrap_or_default();
+        let hooks = value.as_ref().and_then(|wg| wg.hooks.clone());
+        let tooling = WireGuardTooling {
+            wg_quick: value.as_ref().and_then(|wg| wg.wg_quick.clone()),
+            pre_up: hooks.as_ref().and_then(|h| h.pre_up.clone()),
+            post_up: hooks.as_ref().and_then(|h| h.post_up.clone()),
+            pre_down: hooks.as_ref().and_then(|h| h.pre_down.clone()),
+            post_down: hooks.as_ref().and_then(|h| h.post_down.clone()),
+        };
+        let block_ipv6 = value.as_ref().and_then(|wg| wg.block_ipv6).unwrap_or_default();
+        WireGuardConfig::new(
+            listen_port,
+            allowed_ips,
+            force_private_key,
+            dns,
+            dns_strategy,
+            tooling,
+            block_ipv6,
+        )
     }
 }
 
@@ -381,19 +567,42 @@ impl From<Option<BlokliConfig>> for HoprBlokliConfig {
 pub fn wrong_keys(table: &toml::Table) -> Vec<String> {
     let mut wrong = Vec::new();
     for (key, value) in table.iter() {
-        if key == "version" {
+        if key == "version"
+            || key == "telemetry"
+            || key == "telemetry_url"
+            || key == "crash_report_url"
+            || key == "locale"
+            || key == "network_online_timeout"
+            || key == "destination_groups"
+        {
             continue;
         }
         if key == "wireguard" {
             if let Some(wg) = value.as_table() {
                 for (k, v) in wg.iter() {
-                    if k == "listen_port" || k == "allowed_ips" || k == "force_private_key" {
+                    if k == "listen_port"
+                        || k == "allowed_ips"
+                        || k == "force_private_key"
+                        || k == "wg_quick"
+                        || k == "block_ipv6"
+                    {
+                        continue;
+                    }
+                    if k == "hooks" {
+                        if let Some(hooks) = v.as_table() {
+                            for (k2, _) in hooks.iter() {
+                                if k2 == "pre_up" || k2 == "post_up" || k2 == "pre_down" || k2 == "post_down" {
+                                    continue;
+                                }
+                                wrong.push(format!("wireguard.hooks.{k2}"));
+                            }
+                        }
                         continue;
                     }
                     if k == "dns" {
                         if let Some(dns) = v.as_table() {
                             for (k2, _) in dns.iter() {
-                                if k2 == "overwrite" || k2 == "servers" {
+                                if k2 == "overwrite" || k2 == "servers" || k2 == "strategy" {
                                     continue;
                                 }
                                 wrong.push(format!("wireguard.dns.{k2}"));
@@ -423,8 +632,13 @@ pub fn wrong_keys(table: &toml::Table) -> Vec<String> {
                     if k == "http_timeout"
                         || k == "announced_peer_minimum_score"
                         || k == "lan_lockdown"
+                        || k == "kill_switch"
                         || k == "session_pseudonym_ttl"
+                        || k == "affinity_ttl"
                         || k == "path_planner_min_ack_rate"
+                        || k == "egress_rate_limit"
+                        || k == "dscp"
+                        || k == "public_ip_endpoint"
                     {
                         continue;
                     }
@@ -473,6 +687,45 @@ pub fn wrong_keys(table: &toml::Table) -> Vec<String> {
                         }
                         continue;
                     }
+                    if k == "gateway" {
+                        if let Some(gw) = v.as_table() {
+                            for (k2, _) in gw.iter() {
+                                if k2 == "lan_interface" {
+                                    continue;
+                                }
+                                wrong.push(format!("connection.gateway.{k2}"));
+                            }
+                        }
+                        continue;
+                    }
+                    if k == "phase_timeouts" {
+                        if let Some(pt) = v.as_table() {
+                            for (k2, _) in pt.iter() {
+                                if k2 == "open_bridge"
+                                    || k2 == "register_wg"
+                                    || k2 == "open_ping"
+                                    || k2 == "establish_wg_tunnel"
+                                    || k2 == "killswitch_lockdown"
+                                    || k2 == "verify_ping"
+                                {
+                                    continue;
+                                }
+                                wrong.push(format!("connection.phase_timeouts.{k2}"));
+                            }
+                        }
+                        continue;
+                    }
+                    if k == "reconnect" {
+                        if let Some(rc) = v.as_table() {
+                            for (k2, _) in rc.iter() {
+                                if k2 == "max_retries" || k2 == "initial_delay" || k2 == "max_delay" {
+                                    continue;
+                                }
+                                wrong.push(format!("connection.reconnect.{k2}"));
+                            }
+                        }
+                        continue;
+                    }
                     if k == "health_check_intervals" {
                         if let Some(hci) = v.as_table() {
                             for (k2, _) in hci.iter() {
@@ -494,12 +747,116 @@ pub fn wrong_keys(table: &toml::Table) -> Vec<String> {
             }
             continue;
         }
+        if key == "budget" {
+            if let Some(budget) = value.as_table() {
+                for (k, _) in budget.iter() {
+                    if k == "daily_limit" || k == "action" || k == "throttle_max_surb_upstream" {
+                        continue;
+                    }
+                    wrong.push(format!("budget.{k}"));
+                }
+            }
+            continue;
+        }
+        if key == "backoff" {
+            if let Some(backoff) = value.as_table() {
+                for (k, _) in backoff.iter() {
+                    if k == "initial" || k == "max_interval" || k == "max_elapsed" {
+                        continue;
+                    }
+                    wrong.push(format!("backoff.{k}"));
+                }
+            }
+            continue;
+        }
+        if key == "balances" {
+            if let Some(balances) = value.as_table() {
+                for (k, _) in balances.iter() {
+                    if k == "refresh_interval" {
+                        continue;
+                    }
+                    wrong.push(format!("balances.{k}"));
+                }
+            }
+            continue;
+        }
+        if key == "management" {
+            if let Some(management) = value.as_table() {
+                for (k, _) in management.iter() {
+                    if k == "url"
+                        || k == "public_key"
+                        || k == "interval"
+                        || k == "report"
+                        || k == "allow_unlisted_destinations"
+                    {
+                        continue;
+                    }
+                    wrong.push(format!("management.{k}"));
+                }
+            }
+            continue;
+        }
+        if key == "safe" {
+            if let Some(safe) = value.as_table() {
+                for (k, _) in safe.iter() {
+                    if k == "safe_address" || k == "module_address" {
+                        continue;
+                    }
+                    wrong.push(format!("safe.{k}"));
+                }
+            }
+            continue;
+        }
+        if key == "socket" {
+            if let Some(socket) = value.as_table() {
+                for (k, _) in socket.iter() {
+                    if k == "path" || k == "group" || k == "mode" || k == "remote_listen" {
+                        continue;
+                    }
+                    wrong.push(format!("socket.{k}"));
+                }
+            }
+            continue;
+        }
+        if key == "dirs" {
+            if let Some(dirs) = value.as_table() {
+                for (k, _) in dirs.iter() {
+                    if k == "data" || k == "cache" {
+                        continue;
+                    }
+                    wrong.push(format!("dirs.{k}"));
+                }
+            }
+            continue;
+        }
+        if key == "network" {
+            if let Some(network) = value.as_table() {
+                for (k, _) in network.iter() {
+                    if k == "https_proxy" || k == "ca_bundle" || k == "pinned_certificates" {
+                        continue;
+                    }
+                    wrong.push(format!("network.{k}"));
+                }
+            }
+            continue;
+        }
+        if key == "disk_space" {
+            if let Some(disk_space) = value.as_table() {
+                for (k, _) in disk_space.iter() {
+                    if k == "min_free" || k == "interval" || k == "compact" || k == "compact_every" {
+                        continue;
+                    }
+                    wrong.push(format!("disk_space.{k}"));
+                }
+            }
+            continue;
+        }
         if key == "destinations" {
             if let Some(destinations) = value.as_table() {
                 for (id, v) in destinations.iter() {
                     if let Some(dest) = v.as_table() {
                         for (k, _) in dest.iter() {
-                            if k == "address" || k == "meta" || k == "path" {
+                            if k == "address" || k == "meta" || k == "path" || k == "auth" || k == "report_url" {
                                 continue;
                             }
                             wrong.push(format!("destinations.{id}.{k}"));
@@ -514,7 +871,11 @@ pub fn wrong_keys(table: &toml::Table) -> Vec<String> {
         if key == "strategy" {
             if let Some(strategy) = value.as_table() {
                 for (k, v) in strategy.iter() {
-                    if k == "desired_message_count" || k == "min_open_channels" || k == "target_open_channels" {
+                    if k == "desired_message_count"
+                        || k == "min_open_channels"
+                        || k == "target_open_channels"
+                        || k == "auto_fund_channels"
+                    {
                         continue;
                     }
                     if k == "channel_allowlist" {
@@ -552,6 +913,7 @@ pub(super) struct Strategy {
     pub(super) min_open_channels: Option<usize>,
     pub(super) target_open_channels: Option<usize>,
     pub(super) channel_allowlist: Option<ChannelAllowlistConfig>,
+    pub(super) auto_fund_channels: Option<bool>,
 }
 
 impl From<Option<Strategy>> for StrategyConfig {
@@ -574,6 +936,218 @@ impl From<Option<Strategy>> for StrategyConfig {
                 .as_ref()
                 .and_then(|s| s.channel_allowlist.as_ref())
                 .and_then(|c| c.enabled.then(|| c.peers.iter().cloned().collect())),
+            auto_fund_channels: v
+                .as_ref()
+                .and_then(|s| s.auto_fund_channels)
+                .unwrap_or(def.auto_fund_channels),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct Budget {
+    #[serde(
+        default,
+        deserialize_with = "validate_daily_limit",
+        serialize_with = "serde_utils::opt_balance::serialize"
+    )]
+    pub(super) daily_limit: Option<Balance<WxHOPR>>,
+    pub(super) action: Option<budget::Action>,
+    #[serde(default, with = "human_bandwidth::serde")]
+    pub(super) throttle_max_surb_upstream: Option<Bandwidth>,
+}
+
+impl From<Option<Budget>> for budget::Config {
+    fn from(value: Option<Budget>) -> Self {
+        let def = budget::Config::default();
+        Self {
+            daily_limit: value.as_ref().and_then(|b| b.daily_limit),
+            action: value.as_ref().and_then(|b| b.action).unwrap_or(def.action),
+            throttle_max_surb_upstream: value
+                .as_ref()
+                .and_then(|b| b.throttle_max_surb_upstream)
+                .unwrap_or(def.throttle_max_surb_upstream),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct Backoff {
+    #[serde(default, with = "humantime_serde::option")]
+    pub(super) initial: Option<Duration>,
+    #[serde(default, with = "humantime_serde::option")]
+    pub(super) max_interval: Option<Duration>,
+    #[serde(default, with = "humantime_serde::option")]
+    pub(super) max_elapsed: Option<Duration>,
+}
+
+impl TryFrom<Option<Backoff>> for backoff::Config {
+    type Error = config::Error;
+
+    fn try_from(value: Option<Backoff>) -> Result<Self, Self::Error> {
+        let def = backoff::Config::default();
+        let res = Self {
+            initial: value.as_ref().and_then(|b| b.initial).unwrap_or(def.initial),
+            max_interval: value.as_ref().and_then(|b| b.max_interval).unwrap_or(def.max_interval),
+            max_elapsed: value.as_ref().and_then(|b| b.max_elapsed).unwrap_or(def.max_elapsed),
+        };
+        if res.initial.is_zero() || res.initial > res.max_interval {
+            return Err(config::Error::InvalidBackoff);
+        }
+        Ok(res)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct Balances {
+    #[serde(
+        default,
+        deserialize_with = "validate_refresh_interval",
+        serialize_with = "humantime_serde::option::serialize"
+    )]
+    pub(super) refresh_interval: Option<Duration>,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct Safe {
+    #[serde_as(as = "DisplayFromStr")]
+    pub(super) safe_address: Address,
+    #[serde_as(as = "DisplayFromStr")]
+    pub(super) module_address: Address,
+}
+
+impl From<Safe> for balance::ExternalSafe {
+    fn from(value: Safe) -> Self {
+        Self {
+            safe_address: value.safe_address,
+            module_address: value.module_address,
+        }
+    }
+}
+
+impl From<Option<Balances>> for balance::Config {
+    fn from(value: Option<Balances>) -> Self {
+        let def = balance::Config::default();
+        Self {
+            refresh_interval: value.and_then(|b| b.refresh_interval).unwrap_or(def.refresh_interval),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct Socket {
+    pub(super) path: Option<PathBuf>,
+    pub(super) group: Option<String>,
+    pub(super) mode: Option<u32>,
+    pub(super) remote_listen: Option<SocketAddr>,
+}
+
+impl TryFrom<Option<Socket>> for socket::root::Config {
+    type Error = config::Error;
+
+    fn try_from(value: Option<Socket>) -> Result<Self, Self::Error> {
+        let def = socket::root::Config::default();
+        let res = Self {
+            path: value.as_ref().and_then(|s| s.path.clone()),
+            group: value.as_ref().and_then(|s| s.group.clone()).unwrap_or(def.group),
+            mode: value.as_ref().and_then(|s| s.mode).unwrap_or(def.mode),
+            remote_listen: value.as_ref().and_then(|s| s.remote_listen),
+        };
+        if res.mode > 0o777 {
+            return Err(config::Error::InvalidSocketMode(res.mode));
+        }
+        Ok(res)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct Dirs {
+    pub(super) data: Option<PathBuf>,
+    pub(super) cache: Option<PathBuf>,
+}
+
+impl From<Option<Dirs>> for dirs::Config {
+    fn from(value: Option<Dirs>) -> Self {
+        value
+            .map(|d| dirs::Config {
+                data: d.data,
+                cache: d.cache,
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct DiskSpace {
+    pub(super) min_free: Option<ByteSize>,
+    #[serde(default, with = "humantime_serde::option")]
+    pub(super) interval: Option<Duration>,
+    pub(super) compact: Option<bool>,
+    #[serde(default, with = "humantime_serde::option")]
+    pub(super) compact_every: Option<Duration>,
+}
+
+impl TryFrom<Option<DiskSpace>> for disk_space::Config {
+    type Error = config::Error;
+
+    fn try_from(value: Option<DiskSpace>) -> Result<Self, Self::Error> {
+        let def = disk_space::Config::default();
+        let res = Self {
+            min_free: value.as_ref().and_then(|d| d.min_free).unwrap_or(def.min_free),
+            interval: value.as_ref().and_then(|d| d.interval).unwrap_or(def.interval),
+            compact: value.as_ref().and_then(|d| d.compact).unwrap_or(def.compact),
+            compact_every: value.as_ref().and_then(|d| d.compact_every).or(def.compact_every),
+        };
+        if res.interval.is_zero() || res.compact_every.is_some_and(|every| every.is_zero()) {
+            return Err(config::Error::InvalidDiskSpaceInterval);
+        }
+        Ok(res)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct Network {
+    pub(super) https_proxy: Option<Url>,
+    pub(super) ca_bundle: Option<PathBuf>,
+    pub(super) pinned_certificates: Option<BTreeMap<String, Vec<String>>>,
+}
+
+impl From<Option<Network>> for proxy::Config {
+    fn from(value: Option<Network>) -> Self {
+        let Some(network) = value else {
+            return Self::default();
+        };
+        Self {
+            https_proxy: network.https_proxy,
+            tls: tls::Config {
+                ca_bundle: network.ca_bundle,
+                pinned_certificates: network.pinned_certificates.unwrap_or_default(),
+            },
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct Management {
+    pub(super) url: Url,
+    #[serde_as(as = "Hex")]
+    pub(super) public_key: [u8; 32],
+    #[serde(default, with = "humantime_serde::option")]
+    pub(super) interval: Option<Duration>,
+    pub(super) report: Option<bool>,
+    pub(super) allow_unlisted_destinations: Option<bool>,
+}
+
+impl From<Management> for management::Config {
+    fn from(value: Management) -> Self {
+        Self {
+            url: value.url,
+            public_key: value.public_key,
+            interval: value.interval.unwrap_or(management::Config::DEFAULT_INTERVAL),
+            report: value.report.unwrap_or(true),
+            allow_unlisted_destinations: value.allow_unlisted_destinations.unwrap_or(false),
         }
     }
 }
@@ -583,10 +1157,26 @@ impl From<Option<Strategy>> for StrategyConfig {
 pub struct Config {
     pub version: u8,
     pub(super) destinations: Option<HashMap<String, Destination>>,
+    pub(super) destination_groups: Option<BTreeMap<String, Vec<String>>>,
     pub(super) connection: Option<Connection>,
     pub(super) wireguard: Option<WireGuard>,
     pub(super) blokli: Option<BlokliConfig>,
     pub(super) strategy: Option<Strategy>,
+    pub(super) budget: Option<Budget>,
+    pub(super) backoff: Option<Backoff>,
+    pub(super) balances: Option<Balances>,
+    pub(super) safe: Option<Safe>,
+    pub(super) socket: Option<Socket>,
+    pub(super) management: Option<Management>,
+    pub(super) dirs: Option<Dirs>,
+    pub(super) disk_space: Option<DiskSpace>,
+    pub(super) telemetry: Option<bool>,
+    pub(super) telemetry_url: Option<Url>,
+    pub(super) crash_report_url: Option<Url>,
+    pub(super) locale: Option<String>,
+    #[serde(default, with = "humantime_serde::option")]
+    pub(super) network_online_timeout: Option<Duration>,
+    pub(super) network: Option<Network>,
 }
 
 #[serde_as]
@@ -596,6 +1186,15 @@ pub(super) struct Destination {
     pub(super) address: Address,
     pub(super) meta: Option<HashMap<String, String>>,
     pub(super) path: Option<DestinationPath>,
+    pub(super) auth: Option<DestinationAuth>,
+    pub(super) report_url: Option<Url>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum DestinationAuth {
+    Token(String),
+    SignedChallenge,
 }
 
 /// Routing path for v6 — only hop-count routing is supported.
@@ -605,10 +1204,47 @@ pub(super) struct Destination {
 /// `path = { hops = <count> }`.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub(super) enum DestinationPath {
-    #[serde(alias = "hops", deserialize_with = "validate_hops")]
+    #[serde(rename(serialize = "hops"), alias = "hops", deserialize_with = "validate_hops")]
     Hops(u8),
 }
 
+impl From<&ConnDestination> for Destination {
+    fn from(value: &ConnDestination) -> Self {
+        Destination {
+            address: value.address,
+            meta: Some(value.meta.clone()).filter(|meta| !meta.is_empty()),
+            path: u8::try_from(value.routing.hop_count()).ok().map(DestinationPath::Hops),
+            auth: value.auth.clone().map(|auth| match auth {
+                Auth::Token(token) => DestinationAuth::Token(token),
+                Auth::SignedChallenge => DestinationAuth::SignedChallenge,
+            }),
+            report_url: value.report_url.clone(),
+        }
+    }
+}
+
+/// The `[destinations.<id>]` tables of a configuration file on their own, sorted by id.
+pub fn export_destinations(destinations: &HashMap<String, ConnDestination>) -> Result<String, toml::ser::Error> {
+    #[derive(Serialize)]
+    struct Export {
+        destinations: BTreeMap<String, Destination>,
+    }
+    let destinations = destinations
+        .iter()
+        .map(|(id, dest)| (id.clone(), dest.into()))
+        .collect();
+    toml::to_string(&Export { destinations })
+}
+
+/// Reads the `[destinations.<id>]` tables of an export or of a whole configuration file.
+pub fn import_destinations(content: &str) -> Result<HashMap<String, ConnDestination>, config::Error> {
+    #[derive(Deserialize)]
+    struct Import {
+        destinations: Option<HashMap<String, Destination>>,
+    }
+    convert_destinations(toml::from_str::<Import>(content)?.destinations)
+}
+
 impl TryFrom<Config> for config::Config {
     type Error = config::Error;
 
@@ -617,20 +1253,68 @@ impl TryFrom<Config> for config::Config {
         if connection.surb_balancing.ping.enabled != connection.surb_balancing.main.enabled {
             return Err(config::Error::SurbBalancingMismatch);
         }
+        // the macOS firewall has no kill switch block yet, refuse rather than leave traffic unguarded
+        if cfg!(target_os = "macos") && connection.kill_switch {
+            return Err(config::Error::KillSwitchUnsupported);
+        }
         let destinations = convert_destinations(value.destinations)?;
-        let wireguard = value.wireguard.into();
-        let blokli = value.blokli.into();
+        let destination_groups = value.destination_groups.unwrap_or_default();
+        for (group, ids) in destination_groups.iter() {
+            if destinations.contains_key(group) {
+                return Err(config::Error::GroupNameConflict(group.clone()));
+            }
+            if let Some(id) = ids.iter().find(|id| !destinations.contains_key(*id)) {
+                return Err(config::Error::UnknownGroupDestination {
+                    group: group.clone(),
+                    id: id.clone(),
+                });
+            }
+        }
+        let wireguard = value.wireguard.into();
+        let blokli = value.blokli.into();
         let strategy = value.strategy.into();
+        let budget = value.budget.into();
+        let backoff = value.backoff.try_into()?;
+        let balances = value.balances.into();
+        let external_safe = value.safe.map(Into::into);
+        let socket = value.socket.try_into()?;
+        let management = value.management.map(Into::into);
+        let dirs = value.dirs.into();
+        let disk_space = value.disk_space.try_into()?;
         Ok(config::Config {
             connection,
             destinations,
+            destination_groups,
             wireguard,
             blokli,
             strategy,
+            budget,
+            backoff,
+            balances,
+            external_safe,
+            socket,
+            management,
+            dirs,
+            disk_space,
+            telemetry: telemetry_config(value.telemetry, value.telemetry_url, value.crash_report_url),
+            locale: value.locale,
+            network_online_timeout: value
+                .network_online_timeout
+                .unwrap_or(config::DEFAULT_NETWORK_ONLINE_TIMEOUT),
+            network: value.network.into(),
         })
     }
 }
 
+fn telemetry_config(enabled: Option<bool>, usage_url: Option<Url>, crash_url: Option<Url>) -> telemetry::Config {
+    let default = telemetry::Config::default();
+    telemetry::Config {
+        enabled,
+        usage_url: usage_url.or(default.usage_url),
+        crash_url: crash_url.or(default.crash_url),
+    }
+}
+
 pub fn convert_destinations(
     value: Option<HashMap<String, Destination>>,
 ) -> Result<HashMap<String, ConnDestination>, config::Error> {
@@ -647,7 +1331,13 @@ pub fn convert_destinations(
         };
 
         let meta = dest.meta.clone().unwrap_or_default();
-        let dest = ConnDestination::new(id.to_string(), dest.address, path, meta);
+        let auth = dest.auth.clone().map(|auth| match auth {
+            DestinationAuth::Token(token) => Auth::Token(token),
+            DestinationAuth::SignedChallenge => Auth::SignedChallenge,
+        });
+        let dest = ConnDestination::new(id.to_string(), dest.address, path, meta)
+            .with_auth(auth)
+            .with_report_url(dest.report_url.clone());
         result.insert(id.to_string(), dest);
     }
     Ok(result)
@@ -655,10 +1345,14 @@ pub fn convert_destinations(
 
 #[cfg(test)]
 mod tests {
-    use super::{ChannelAllowlistConfig, Config, Strategy, convert_destinations};
+    use super::{Auth, ChannelAllowlistConfig, Config, Strategy, convert_destinations};
     use crate::hopr::strategy_config::StrategyConfig;
     use edgli::hopr_lib::HopRouting;
     use edgli::hopr_lib::api::types::primitive::prelude::Address;
+    use human_bandwidth::re::bandwidth::Bandwidth;
+
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
 
     fn parse(toml: &str) -> Config {
         toml::from_str(toml).expect("valid TOML")
@@ -707,6 +1401,48 @@ address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
         assert!(result.is_err());
     }
 
+    #[test]
+    fn convert_destinations_reads_auth() {
+        let cfg = parse(
+            r#####"
+version = 6
+
+[destinations.Private]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+auth = { token = "secret" }
+
+[destinations.Signed]
+address = "0xa5Ca174Ef94403d6162a969341a61baeA48F57F8"
+auth = "signed_challenge"
+"#####,
+        );
+        let result = convert_destinations(cfg.destinations).expect("should succeed");
+        assert_eq!(result["Private"].auth, Some(Auth::Token("secret".to_string())));
+        assert_eq!(result["Signed"].auth, Some(Auth::SignedChallenge));
+    }
+
+    #[test]
+    fn convert_destinations_reads_report_url() {
+        let content = r#####"
+version = 6
+
+[destinations.Reporting]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+report_url = "https://exit.example/report"
+
+[destinations.Silent]
+address = "0xa5Ca174Ef94403d6162a969341a61baeA48F57F8"
+"#####;
+        let table = content.parse::<toml::Table>().expect("valid TOML");
+        assert!(super::wrong_keys(&table).is_empty());
+        let result = convert_destinations(parse(content).destinations).expect("should succeed");
+        assert_eq!(
+            result["Reporting"].report_url.as_ref().map(|u| u.as_str()),
+            Some("https://exit.example/report")
+        );
+        assert_eq!(result["Silent"].report_url, None);
+    }
+
     #[test]
     fn intermediates_path_rejected_in_v6() {
         // v6 does not support the deprecated `intermediates` key — deserialization
@@ -790,6 +1526,244 @@ path_planner_min_ack_rate = {bad}
         }
     }
 
+    #[test]
+    fn egress_rate_limit_reads_from_connection() {
+        let cfg = parse(
+            r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[connection]
+egress_rate_limit = "2 Mb/s"
+"#####,
+        );
+        let result: crate::config::Config = cfg.try_into().expect("should succeed");
+        assert_eq!(result.connection.egress_rate_limit, Some(Bandwidth::from_mbps(2)));
+    }
+
+    #[test]
+    fn gateway_reads_lan_interface() {
+        let cfg = parse(
+            r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[connection.gateway]
+lan_interface = "eth0"
+"#####,
+        );
+        let result: crate::config::Config = cfg.try_into().expect("should succeed");
+        assert_eq!(
+            result.connection.gateway,
+            Some(crate::connection::options::Gateway {
+                lan_interface: "eth0".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn phase_timeouts_fall_back_to_defaults() {
+        let cfg = parse(
+            r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[connection.phase_timeouts]
+open_bridge = "2m"
+verify_ping = "30s"
+"#####,
+        );
+        let result: crate::config::Config = cfg.try_into().expect("should succeed");
+        let phases = result.connection.timeouts.phases;
+        let def = crate::connection::options::PhaseTimeouts::default();
+        assert_eq!(phases.open_bridge, std::time::Duration::from_secs(120));
+        assert_eq!(phases.verify_ping, std::time::Duration::from_secs(30));
+        assert_eq!(phases.register_wg, def.register_wg);
+    }
+
+    #[test]
+    fn reconnect_policy_falls_back_to_defaults() {
+        let cfg = parse(
+            r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[connection.reconnect]
+max_retries = 2
+max_delay = "5s"
+"#####,
+        );
+        let result: crate::config::Config = cfg.try_into().expect("should succeed");
+        let reconnect = result.connection.reconnect;
+        assert_eq!(reconnect.max_retries, 2);
+        assert_eq!(reconnect.initial_delay, std::time::Duration::from_secs(2));
+        assert_eq!(reconnect.delay(1), std::time::Duration::from_secs(2));
+        assert_eq!(reconnect.delay(2), std::time::Duration::from_secs(4));
+        assert_eq!(reconnect.delay(3), std::time::Duration::from_secs(5));
+        assert_eq!(reconnect.delay(40), std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn dscp_rejects_out_of_range() {
+        let result = toml::from_str::<Config>(
+            r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[connection]
+dscp = 64
+"#####,
+        );
+        assert!(result.is_err(), "dscp above 63 must be rejected");
+    }
+
+    #[test]
+    fn public_ip_endpoint_defaults_and_can_be_disabled() {
+        let endpoint = |connection: &str| -> Result<Option<String>, String> {
+            let cfg = toml::from_str::<Config>(&format!(
+                r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[connection]
+{connection}
+"#####
+            ))
+            .map_err(|e| e.to_string())?;
+            let result: crate::config::Config = cfg.try_into().expect("should succeed");
+            Ok(result.connection.public_ip_endpoint.map(|u| u.to_string()))
+        };
+        assert_eq!(endpoint(""), Ok(Some(crate::public_ip::DEFAULT_ENDPOINT.to_string())));
+        assert_eq!(endpoint(r#"public_ip_endpoint = """#), Ok(None));
+        assert_eq!(
+            endpoint(r#"public_ip_endpoint = "https://ipinfo.io/json""#),
+            Ok(Some("https://ipinfo.io/json".to_string()))
+        );
+        assert!(endpoint(r#"public_ip_endpoint = "not a url""#).is_err());
+    }
+
+    #[test]
+    fn budget_defaults_to_disabled() {
+        let cfg = parse(
+            r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+"#####,
+        );
+        let result: crate::config::Config = cfg.try_into().expect("should succeed");
+        assert!(result.budget.daily_limit.is_none());
+        assert_eq!(result.budget.action, crate::budget::Action::Warn);
+    }
+
+    #[test]
+    fn budget_reads_limit_and_action() {
+        let cfg = parse(
+            r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[budget]
+daily_limit = "5 wxHOPR"
+action = "disconnect"
+"#####,
+        );
+        let result: crate::config::Config = cfg.try_into().expect("should succeed");
+        assert!(result.budget.daily_limit.is_some());
+        assert_eq!(result.budget.action, crate::budget::Action::Disconnect);
+    }
+
+    #[test]
+    fn backoff_reads_partial_section() {
+        let cfg = parse(
+            r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[backoff]
+initial = "2s"
+max_elapsed = "10m"
+"#####,
+        );
+        let result: crate::config::Config = cfg.try_into().expect("should succeed");
+        assert_eq!(result.backoff.initial, std::time::Duration::from_secs(2));
+        assert_eq!(result.backoff.max_interval, std::time::Duration::from_secs(60));
+        assert_eq!(result.backoff.max_elapsed, std::time::Duration::from_secs(600));
+    }
+
+    #[test]
+    fn balances_refresh_interval_is_read() {
+        let cfg = parse(
+            r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[balances]
+refresh_interval = "5m"
+"#####,
+        );
+        let result: crate::config::Config = cfg.try_into().expect("should succeed");
+        assert_eq!(result.balances.refresh_interval, std::time::Duration::from_secs(300));
+    }
+
+    #[test]
+    fn backoff_rejects_initial_above_max_interval() {
+        let cfg = parse(
+            r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[backoff]
+initial = "2m"
+max_interval = "1m"
+"#####,
+        );
+        let result: Result<crate::config::Config, _> = cfg.try_into();
+        assert!(matches!(result, Err(crate::config::Error::InvalidBackoff)));
+    }
+
+    #[test]
+    fn kill_switch_is_refused_where_unsupported() {
+        let cfg = parse(
+            r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[connection]
+kill_switch = true
+"#####,
+        );
+        let result: Result<crate::config::Config, _> = cfg.try_into();
+        if cfg!(target_os = "macos") {
+            assert!(matches!(result, Err(crate::config::Error::KillSwitchUnsupported)));
+        } else {
+            assert!(result.expect("should succeed").connection.kill_switch);
+        }
+    }
+
     #[test]
     fn strategy_channel_allowlist_enabled_produces_some() {
         let addr: Address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739".parse().unwrap();
@@ -801,6 +1775,7 @@ path_planner_min_ack_rate = {bad}
                 enabled: true,
                 peers: vec![addr.clone()],
             }),
+            auto_fund_channels: None,
         });
         let cfg: StrategyConfig = strategy.into();
         assert_eq!(cfg.channel_allowlist, Some(std::collections::HashSet::from([addr])));
@@ -817,8 +1792,374 @@ path_planner_min_ack_rate = {bad}
                 enabled: false,
                 peers: vec![addr],
             }),
+            auto_fund_channels: None,
         });
         let cfg: StrategyConfig = strategy.into();
         assert!(cfg.channel_allowlist.is_none());
     }
+
+    #[test]
+    fn strategy_auto_fund_channels_opt_out() {
+        let content = r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[strategy]
+auto_fund_channels = false
+"#####;
+        let table = content.parse::<toml::Table>().expect("valid TOML");
+        assert!(super::wrong_keys(&table).is_empty());
+        let result: crate::config::Config = parse(content).try_into().expect("should succeed");
+        assert!(!result.strategy.auto_fund_channels);
+        assert!(StrategyConfig::default().auto_fund_channels);
+    }
+
+    #[test]
+    fn socket_section_overrides_group_and_mode() {
+        let cfg = parse(
+            r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[socket]
+path = "@gnosisvpn"
+group = "vpnusers"
+mode = 0o600
+remote_listen = "0.0.0.0:7475"
+"#####,
+        );
+        let result: crate::config::Config = cfg.try_into().expect("should succeed");
+        assert_eq!(result.socket.path, Some(PathBuf::from("@gnosisvpn")));
+        assert_eq!(result.socket.group, "vpnusers");
+        assert_eq!(result.socket.mode, 0o600);
+        assert_eq!(result.socket.remote_listen, Some("0.0.0.0:7475".parse().unwrap()));
+    }
+
+    #[test]
+    fn management_section_defaults() {
+        let cfg = parse(
+            r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[management]
+url = "https://fleet.example.com/policy.json"
+public_key = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"
+"#####,
+        );
+        let result: crate::config::Config = cfg.try_into().expect("should succeed");
+        let management = result.management.expect("management enabled");
+        assert_eq!(management.url.as_str(), "https://fleet.example.com/policy.json");
+        assert_eq!(management.public_key[0], 0xd7);
+        assert_eq!(management.interval, crate::management::Config::DEFAULT_INTERVAL);
+        assert!(management.report);
+        assert!(!management.allow_unlisted_destinations);
+        assert_eq!(result.telemetry, crate::telemetry::Config::default());
+    }
+
+    #[test]
+    fn wireguard_tooling_and_hooks() {
+        let content = r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[wireguard]
+wg_quick = "/usr/local/bin/wg-quick-resolvconf"
+
+[wireguard.hooks]
+post_up = "resolvectl dns $WG_INTERFACE 10.128.0.1"
+pre_down = "resolvectl revert $WG_INTERFACE"
+"#####;
+        let table = content.parse::<toml::Table>().expect("valid TOML");
+        assert!(super::wrong_keys(&table).is_empty());
+        let result: crate::config::Config = parse(content).try_into().expect("should succeed");
+        let tooling = result.wireguard.tooling;
+        assert_eq!(
+            tooling.wg_quick(),
+            std::path::Path::new("/usr/local/bin/wg-quick-resolvconf")
+        );
+        assert_eq!(tooling.pre_up, None);
+        assert_eq!(
+            tooling.post_up.as_deref(),
+            Some("resolvectl dns $WG_INTERFACE 10.128.0.1")
+        );
+        assert_eq!(tooling.pre_down.as_deref(), Some("resolvectl revert $WG_INTERFACE"));
+        assert_eq!(tooling.post_down, None);
+        assert_eq!(result.wireguard.block_ipv6, crate::wireguard::BlockIpv6::Auto);
+    }
+
+    #[test]
+    fn wireguard_block_ipv6() {
+        let content = r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[wireguard]
+block_ipv6 = "never"
+"#####;
+        let table = content.parse::<toml::Table>().expect("valid TOML");
+        assert!(super::wrong_keys(&table).is_empty());
+        let result: crate::config::Config = parse(content).try_into().expect("should succeed");
+        assert_eq!(result.wireguard.block_ipv6, crate::wireguard::BlockIpv6::Never);
+
+        let content = content.replace("never", "sometimes");
+        assert!(toml::from_str::<super::Config>(&content).is_err());
+    }
+
+    #[test]
+    fn wireguard_dns_strategy() {
+        let content = r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[wireguard]
+dns = { overwrite = true, strategy = "systemd-resolved" }
+"#####;
+        let table = content.parse::<toml::Table>().expect("valid TOML");
+        assert!(super::wrong_keys(&table).is_empty());
+        let result: crate::config::Config = parse(content).try_into().expect("should succeed");
+        assert_eq!(
+            result.wireguard.dns_strategy,
+            crate::wireguard::DnsStrategy::SystemdResolved
+        );
+        assert_eq!(result.wireguard.dns.as_deref(), Some("1.1.1.1,8.8.8.8"));
+        assert!(!result.wireguard.dns_strategy.uses_wg_quick());
+
+        let result: crate::config::Config = parse(&content.replace("systemd-resolved", "wg-quick"))
+            .try_into()
+            .expect("should succeed");
+        assert!(result.wireguard.dns_strategy.uses_wg_quick());
+    }
+
+    #[test]
+    fn dirs_locations() {
+        let content = r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[dirs]
+data = "/srv/gnosisvpn"
+cache = "/var/cache/gnosisvpn"
+"#####;
+        let table = content.parse::<toml::Table>().expect("valid TOML");
+        assert!(super::wrong_keys(&table).is_empty());
+        let result: crate::config::Config = parse(content).try_into().expect("should succeed");
+        assert_eq!(result.dirs.data, Some(std::path::PathBuf::from("/srv/gnosisvpn")));
+        assert_eq!(
+            result.dirs.cache,
+            Some(std::path::PathBuf::from("/var/cache/gnosisvpn"))
+        );
+    }
+
+    #[test]
+    fn disk_space_reads_partial_section() {
+        let content = r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[disk_space]
+min_free = "512 MiB"
+compact = true
+compact_every = "7d"
+"#####;
+        let table = content.parse::<toml::Table>().expect("valid TOML");
+        assert!(super::wrong_keys(&table).is_empty());
+        let result: crate::config::Config = parse(content).try_into().expect("should succeed");
+        assert_eq!(result.disk_space.min_free, bytesize::ByteSize::mib(512));
+        assert_eq!(result.disk_space.interval, std::time::Duration::from_secs(300));
+        assert!(result.disk_space.compact);
+        assert_eq!(
+            result.disk_space.compact_every,
+            Some(std::time::Duration::from_secs(7 * 86400))
+        );
+
+        let content = content.replace("compact = true", "interval = \"0s\"");
+        let result: Result<crate::config::Config, _> = parse(&content).try_into();
+        assert!(matches!(result, Err(crate::config::Error::InvalidDiskSpaceInterval)));
+    }
+
+    #[test]
+    fn external_safe_section() {
+        let content = r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[safe]
+safe_address = "0x0101010101010101010101010101010101010101"
+module_address = "0x0202020202020202020202020202020202020202"
+"#####;
+        let table = content.parse::<toml::Table>().expect("valid TOML");
+        assert!(super::wrong_keys(&table).is_empty());
+        let result: crate::config::Config = parse(content).try_into().expect("should succeed");
+        assert_eq!(
+            result.external_safe,
+            Some(crate::balance::ExternalSafe {
+                safe_address: Address::from([1u8; 20]),
+                module_address: Address::from([2u8; 20]),
+            })
+        );
+
+        let content = content.replace("module_address", "owner");
+        let table = content.parse::<toml::Table>().expect("valid TOML");
+        assert_eq!(super::wrong_keys(&table), vec!["safe.owner".to_string()]);
+    }
+
+    #[test]
+    fn telemetry_opt_in() {
+        let cfg = parse(
+            r#####"
+version = 6
+telemetry = true
+crash_report_url = "https://crashes.internal/v1/crash"
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+"#####,
+        );
+        let result: crate::config::Config = cfg.try_into().expect("should succeed");
+        assert_eq!(result.telemetry.enabled, Some(true));
+        assert_eq!(
+            result.telemetry.usage_url.map(|u| u.to_string()).as_deref(),
+            Some(crate::telemetry::DEFAULT_USAGE_URL)
+        );
+        assert_eq!(
+            result.telemetry.crash_url.map(|u| u.to_string()).as_deref(),
+            Some("https://crashes.internal/v1/crash")
+        );
+    }
+
+    #[test]
+    fn locale_is_a_known_top_level_key() {
+        let content = r#####"
+version = 6
+locale = "en_GB"
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+"#####;
+        let table = content.parse::<toml::Table>().expect("valid TOML");
+        assert!(super::wrong_keys(&table).is_empty());
+        let result: crate::config::Config = parse(content).try_into().expect("should succeed");
+        assert_eq!(result.locale.as_deref(), Some("en_GB"));
+    }
+
+    #[test]
+    fn network_online_timeout_defaults_and_can_be_disabled() {
+        let content = r#####"
+version = 6
+network_online_timeout = "0s"
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+"#####;
+        let table = content.parse::<toml::Table>().expect("valid TOML");
+        assert!(super::wrong_keys(&table).is_empty());
+        let result: crate::config::Config = parse(content).try_into().expect("should succeed");
+        assert_eq!(result.network_online_timeout, std::time::Duration::ZERO);
+
+        let result: crate::config::Config = parse(&content.replace("network_online_timeout = \"0s\"\n", ""))
+            .try_into()
+            .expect("should succeed");
+        assert_eq!(
+            result.network_online_timeout,
+            crate::config::DEFAULT_NETWORK_ONLINE_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn network_section_reads_proxy_and_tls_trust() {
+        let content = r#####"
+version = 6
+
+[network]
+https_proxy = "http://proxy.corp:3128"
+ca_bundle = "/etc/gnosisvpn/ca.pem"
+
+[network.pinned_certificates]
+"rpc.internal" = ["AB:CD"]
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+"#####;
+        let table = content.parse::<toml::Table>().expect("valid TOML");
+        assert!(super::wrong_keys(&table).is_empty());
+        let result: crate::config::Config = parse(content).try_into().expect("should succeed");
+        assert_eq!(
+            result.network.https_proxy.as_ref().map(|u| u.as_str()),
+            Some("http://proxy.corp:3128/")
+        );
+        assert_eq!(
+            result.network.tls.ca_bundle,
+            Some(PathBuf::from("/etc/gnosisvpn/ca.pem"))
+        );
+        assert_eq!(
+            result.network.tls.pinned_certificates,
+            BTreeMap::from([("rpc.internal".to_string(), vec!["AB:CD".to_string()])])
+        );
+    }
+
+    #[test]
+    fn socket_rejects_mode_with_special_bits() {
+        let cfg = parse(
+            r#####"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+
+[socket]
+mode = 0o4770
+"#####,
+        );
+        let result: Result<crate::config::Config, _> = cfg.try_into();
+        assert!(matches!(result, Err(crate::config::Error::InvalidSocketMode(0o4770))));
+    }
+
+    #[test]
+    fn exported_destinations_read_back_identically() {
+        let destinations = convert_destinations(
+            parse(
+                r#####"
+version = 6
+
+[destinations.Private]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+meta = { location = "Germany" }
+path = { hops = 2 }
+auth = { token = "secret" }
+report_url = "https://exit.example/report"
+
+[destinations.Signed]
+address = "0xa5Ca174Ef94403d6162a969341a61baeA48F57F8"
+auth = "signed_challenge"
+"#####,
+            )
+            .destinations,
+        )
+        .expect("should succeed");
+
+        let export = super::export_destinations(&destinations).expect("serializable");
+        assert!(export.starts_with("[destinations.Private]"));
+        assert!(export.contains("hops = 2"));
+        let imported = super::import_destinations(&export).expect("valid export");
+        assert_eq!(imported, destinations);
+    }
 }
@@ -0,0 +1,263 @@
+//! Other VPN clients competing with managed routing for the routing table.
+//!
+//! A client sending all traffic into its own tunnel makes route setup fail with errors like
+//! `File exists` that say nothing about the cause. The root process looks for them on startup and
+//! before every connection, reports them in status and names them when route setup fails.
+//! Clients are recognized by their process name or by a tunnel interface holding a default route.
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use std::fmt::{self, Display};
+
+use crate::shell_command_ext::{Logs, ShellCommandExt};
+use crate::wireguard;
+
+/// Process names of known clients.
+const PROCESSES: &[(&str, Service)] = &[("tailscaled", Service::Tailscale), ("openvpn", Service::OpenVpn)];
+
+/// Destinations covering all traffic: the default route and the split halves used by most VPN clients.
+#[cfg(target_os = "linux")]
+const DEFAULT_DESTINATIONS: &[&str] = &["default", "0.0.0.0/0", "0.0.0.0/1", "128.0.0.0/1"];
+#[cfg(target_os = "macos")]
+const DEFAULT_DESTINATIONS: &[&str] = &["default", "0/1", "128.0/1"];
+
+/// Name prefixes of tunnel interfaces, a default route via anything else is the regular uplink.
+const TUNNEL_PREFIXES: &[&str] = &["tun", "tap", "tailscale", "utun"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Service {
+    Tailscale,
+    OpenVpn,
+    WireGuard,
+    /// Tunnel interface of a client that could not be told apart
+    Unknown,
+}
+
+/// Routing mode of the root process that works alongside the conflicting client.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestedMode {
+    /// Split routes take precedence, the other client only needs watching
+    Managed,
+    /// Leave routing to the other client or stop it
+    Delegated,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Conflict {
+    pub service: Service,
+    /// Tunnel interface of the client, `None` if only its process was found
+    pub interface: Option<String>,
+    /// All traffic is routed into the other tunnel, setting up split routes fails or fights it
+    pub default_route: bool,
+    pub suggested_mode: SuggestedMode,
+}
+
+impl Display for Service {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Service::Tailscale => write!(f, "Tailscale"),
+            Service::OpenVpn => write!(f, "OpenVPN"),
+            Service::WireGuard => write!(f, "WireGuard"),
+            Service::Unknown => write!(f, "Another VPN client"),
+        }
+    }
+}
+
+impl Display for SuggestedMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SuggestedMode::Managed => write!(f, "managed"),
+            SuggestedMode::Delegated => write!(f, "delegated"),
+        }
+    }
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.interface, self.default_route) {
+            (Some(interface), true) => write!(f, "{} routes all traffic via {interface}", self.service),
+            (Some(interface), false) => write!(f, "{} is up on {interface}", self.service),
+            (None, _) => write!(f, "{} is running", self.service),
+        }
+    }
+}
+
+/// Other VPN clients found on this host, empty if none or if inspecting the system failed.
+pub async fn detect() -> Vec<Conflict> {
+    let mut running = Vec::new();
+    for (process, service) in PROCESSES {
+        let found = Command::new("pgrep").args(["-x", process]).run(Logs::Suppress).await;
+        if found.is_ok() {
+            running.push(*service);
+        }
+    }
+    let wg_interfaces = wireguard_interfaces().await;
+    let devices = default_route_devices(&wg_interfaces).await;
+    classify(&running, &wg_interfaces, &devices)
+}
+
+fn classify(running: &[Service], wg_interfaces: &[String], default_route_devices: &[String]) -> Vec<Conflict> {
+    let mut conflicts: Vec<Conflict> = Vec::new();
+    for device in default_route_devices {
+        if conflicts.iter().any(|c| c.interface.as_ref() == Some(device)) {
+            continue;
+        }
+        let service = if device.starts_with("tailscale") {
+            Service::Tailscale
+        } else if wg_interfaces.contains(device) {
+            Service::WireGuard
+        } else if let [service] = running {
+            *service
+        } else {
+            Service::Unknown
+        };
+        conflicts.push(Conflict {
+            service,
+            interface: Some(device.clone()),
+            default_route: true,
+            suggested_mode: SuggestedMode::Delegated,
+        });
+    }
+    for service in running {
+        if !conflicts.iter().any(|c| c.service == *service) {
+            conflicts.push(Conflict {
+                service: *service,
+                interface: None,
+                default_route: false,
+                suggested_mode: SuggestedMode::Managed,
+            });
+        }
+    }
+    conflicts
+}
+
+fn is_foreign_tunnel(device: &str, wg_interfaces: &[String], own: Option<&str>) -> bool {
+    own != Some(device)
+        && (wg_interfaces.iter().any(|w| w == device) || TUNNEL_PREFIXES.iter().any(|p| device.starts_with(p)))
+}
+
+/// WireGuard interfaces other than the one of this service.
+#[cfg(target_os = "linux")]
+async fn wireguard_interfaces() -> Vec<String> {
+    let Ok(out) = Command::new("ip")
+        .args(["-j", "link", "show", "type", "wireguard"])
+        .run_stdout(Logs::Suppress)
+        .await
+    else {
+        return Vec::new();
+    };
+    serde_json::from_str::<Vec<serde_json::Value>>(&out)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|link| link["ifname"].as_str())
+        .filter(|name| *name != wireguard::WG_INTERFACE)
+        .map(str::to_string)
+        .collect()
+}
+
+/// wg-quick on macOS maps interfaces to utun devices, those are matched by prefix.
+#[cfg(target_os = "macos")]
+async fn wireguard_interfaces() -> Vec<String> {
+    Vec::new()
+}
+
+#[cfg(target_os = "linux")]
+async fn default_route_devices(wg_interfaces: &[String]) -> Vec<String> {
+    // full tunnel setups like wg-quick and Tailscale exit nodes keep their default route in a separate table
+    let Ok(out) = Command::new("ip")
+        .args(["-j", "route", "show", "table", "all"])
+        .run_stdout(Logs::Suppress)
+        .await
+    else {
+        return Vec::new();
+    };
+    linux_default_route_devices(&out, wg_interfaces)
+}
+
+#[cfg(target_os = "macos")]
+async fn default_route_devices(wg_interfaces: &[String]) -> Vec<String> {
+    let Ok(out) = Command::new("netstat")
+        .args(["-rn", "-f", "inet"])
+        .run_stdout(Logs::Suppress)
+        .await
+    else {
+        return Vec::new();
+    };
+    let name_file = format!("/var/run/wireguard/{}.name", wireguard::WG_INTERFACE);
+    let own = tokio::fs::read_to_string(name_file).await.ok();
+    macos_default_route_devices(&out, wg_interfaces, own.as_deref().map(str::trim))
+}
+
+/// Tunnel devices of default routes in `ip -j route show` output.
+#[cfg(target_os = "linux")]
+fn linux_default_route_devices(json: &str, wg_interfaces: &[String]) -> Vec<String> {
+    serde_json::from_str::<Vec<serde_json::Value>>(json)
+        .unwrap_or_default()
+        .iter()
+        .filter(|route| {
+            route["dst"]
+                .as_str()
+                .is_some_and(|dst| DEFAULT_DESTINATIONS.contains(&dst))
+        })
+        .filter_map(|route| route["dev"].as_str())
+        .filter(|dev| is_foreign_tunnel(dev, wg_interfaces, Some(wireguard::WG_INTERFACE)))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Tunnel devices of default routes in `netstat -rn` output, `own` is the utun of this service.
+#[cfg(target_os = "macos")]
+fn macos_default_route_devices(netstat: &str, wg_interfaces: &[String], own: Option<&str>) -> Vec<String> {
+    netstat
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                [destination, _gateway, _flags, netif, ..] if DEFAULT_DESTINATIONS.contains(destination) => {
+                    Some(*netif)
+                }
+                _ => None,
+            }
+        })
+        .filter(|netif| is_foreign_tunnel(netif, wg_interfaces, own))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tunnels_holding_default_routes_suggest_delegated_routing() {
+        let routes = r#"[{"dst":"default","gateway":"192.168.1.1","dev":"eth0","protocol":"dhcp","flags":[]},
+            {"dst":"0.0.0.0/1","gateway":"10.8.0.1","dev":"tun0","flags":[]},
+            {"dst":"128.0.0.0/1","gateway":"10.8.0.1","dev":"tun0","flags":[]},
+            {"dst":"default","dev":"wg1","table":"51820","flags":[]},
+            {"dst":"0.0.0.0/1","dev":"wg0_gnosisvpn","protocol":"152","flags":[]},
+            {"dst":"100.64.0.0/10","dev":"tailscale0","table":"52","flags":[]}]"#;
+        let wg_interfaces = vec!["wg1".to_string()];
+        let devices = linux_default_route_devices(routes, &wg_interfaces);
+        assert_eq!(devices, vec!["tun0", "tun0", "wg1"]);
+
+        let conflicts = classify(&[Service::OpenVpn, Service::Tailscale], &wg_interfaces, &devices);
+        // two clients running, tun0 cannot be attributed to either
+        assert_eq!(conflicts.len(), 4);
+        assert_eq!(conflicts[0].service, Service::Unknown);
+        assert_eq!(conflicts[0].suggested_mode, SuggestedMode::Delegated);
+        assert_eq!(conflicts[1].service, Service::WireGuard);
+        assert_eq!(conflicts[2].service, Service::OpenVpn);
+        // Tailscale without exit node only adds its own range and leaves default traffic alone
+        assert_eq!(conflicts[3].service, Service::Tailscale);
+        assert_eq!(conflicts[3].suggested_mode, SuggestedMode::Managed);
+        assert!(!conflicts[3].default_route);
+
+        let conflicts = classify(&[Service::OpenVpn], &wg_interfaces, &devices[..1]);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].service, Service::OpenVpn);
+        assert_eq!(conflicts[0].to_string(), "OpenVPN routes all traffic via tun0");
+    }
+}
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::connection::destination::Destination;
+use crate::serde_utils;
+
+pub const AFFINITY_FILE: &str = "affinity.json";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Record {
+    destination_id: String,
+    #[serde(with = "serde_utils::system_time")]
+    picked_at: SystemTime,
+}
+
+/// Remembers the destination picked by `connect --any` for the configured TTL.
+///
+/// Repeated automatic connects land on the same exit as long as it stays ready, so the public IP
+/// seen by others does not change with every reconnect. The pick survives worker restarts.
+pub struct Affinity {
+    path: PathBuf,
+    ttl: Duration,
+    record: Option<Record>,
+}
+
+impl Affinity {
+    pub fn load(path: PathBuf, ttl: Duration) -> Self {
+        let record = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|error| {
+                tracing::warn!(?error, ?path, "discarding unreadable affinity file");
+                None
+            }),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => None,
+            Err(error) => {
+                tracing::warn!(?error, ?path, "unable to read affinity file");
+                None
+            }
+        };
+        Self { path, ttl, record }
+    }
+
+    /// Destination picked less than the TTL ago.
+    pub fn current(&self, now: SystemTime) -> Option<&str> {
+        self.record
+            .as_ref()
+            .filter(|r| now.duration_since(r.picked_at).is_ok_and(|age| age < self.ttl))
+            .map(|r| r.destination_id.as_str())
+    }
+
+    /// Picks from `candidates`, ordered by preference, sticking to the current destination unless `rotate` is set.
+    ///
+    /// Rotating prefers any other candidate, the current one is only kept if it is the last one ready.
+    pub fn choose<'a>(
+        &mut self,
+        candidates: &'a [Destination],
+        rotate: bool,
+        now: SystemTime,
+    ) -> Option<&'a Destination> {
+        let current = self.current(now);
+        let sticky = current
+            .filter(|_| !rotate)
+            .and_then(|id| candidates.iter().find(|d| d.id == id));
+        if let Some(dest) = sticky {
+            return Some(dest);
+        }
+        let dest = candidates
+            .iter()
+            .find(|d| Some(d.id.as_str()) != current)
+            .or(candidates.first())?;
+        self.record = Some(Record {
+            destination_id: dest.id.clone(),
+            picked_at: now,
+        });
+        self.persist();
+        Some(dest)
+    }
+
+    fn persist(&self) {
+        let res = serde_json::to_string(&self.record)
+            .map_err(std::io::Error::other)
+            .and_then(|content| fs::write(&self.path, content));
+        if let Err(error) = res {
+            tracing::warn!(?error, path = ?self.path, "failed to persist affinity");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use tempfile::tempdir;
+
+    use crate::connection::destination::{Address, HopRouting};
+
+    fn destinations(ids: &[&str]) -> Vec<Destination> {
+        ids.iter()
+            .enumerate()
+            .map(|(i, id)| {
+                Destination::new(
+                    id.to_string(),
+                    Address::from([i as u8 + 1; 20]),
+                    HopRouting::try_from(1).expect("conversion cannot fail"),
+                    HashMap::new(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pick_sticks_until_ttl_or_rotate() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(AFFINITY_FILE);
+        let now = SystemTime::now();
+        let ttl = Duration::from_secs(60 * 60);
+        let candidates = destinations(&["Germany", "Spain"]);
+
+        let mut affinity = Affinity::load(path.clone(), ttl);
+        assert_eq!(
+            affinity.choose(&candidates[1..], false, now).map(|d| d.id.as_str()),
+            Some("Spain")
+        );
+        // Germany is faster now but the pick sticks, also across reloads
+        let mut affinity = Affinity::load(path.clone(), ttl);
+        assert_eq!(
+            affinity.choose(&candidates, false, now).map(|d| d.id.as_str()),
+            Some("Spain")
+        );
+        assert_eq!(
+            affinity.choose(&candidates, true, now).map(|d| d.id.as_str()),
+            Some("Germany")
+        );
+        assert_eq!(
+            affinity.choose(&candidates[..1], true, now).map(|d| d.id.as_str()),
+            Some("Germany")
+        );
+
+        let later = now + ttl;
+        assert_eq!(affinity.current(later), None);
+        assert_eq!(
+            affinity
+                .choose(&destinations(&["Spain", "Germany"]), false, later)
+                .map(|d| d.id.as_str()),
+            Some("Spain")
+        );
+        assert_eq!(affinity.choose(&[], false, later), None);
+    }
+
+    #[test]
+    fn zero_ttl_never_sticks() {
+        let dir = tempdir().unwrap();
+        let mut affinity = Affinity::load(dir.path().join(AFFINITY_FILE), Duration::ZERO);
+        let now = SystemTime::now();
+        affinity.choose(&destinations(&["Spain"]), false, now);
+        assert_eq!(affinity.current(now), None);
+    }
+}
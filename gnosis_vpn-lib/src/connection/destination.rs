@@ -1,6 +1,8 @@
 pub use edgli::hopr_lib::HopRouting;
 pub use edgli::hopr_lib::api::types::primitive::prelude::Address;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use url::Url;
 
 use std::collections::HashMap;
 use std::fmt::{self, Display};
@@ -8,13 +10,34 @@ use std::fmt::{self, Display};
 use crate::log_output;
 use crate::serde_utils;
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Destination {
     pub id: String,
     pub meta: HashMap<String, String>,
     #[serde(with = "serde_utils::address")]
+    #[schemars(with = "String")]
     pub address: Address,
+    #[schemars(with = "serde_json::Value")]
     pub routing: HopRouting,
+    /// Credentials required by private exits, sent on registration
+    #[serde(default)]
+    pub auth: Option<Auth>,
+    /// Exit operator endpoint receiving anonymized connection outcome reports, see [`crate::exit_reports`]
+    #[serde(default)]
+    #[schemars(with = "Option<String>")]
+    pub report_url: Option<Url>,
+}
+
+const REDACTED_TOKEN: &str = "****";
+
+/// How to authenticate against an exit that does not accept anonymous registrations.
+#[derive(Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Auth {
+    /// Static access token handed out by the exit operator
+    Token(String),
+    /// Sign a challenge fetched from the exit with the node's packet key
+    SignedChallenge,
 }
 
 impl Destination {
@@ -24,9 +47,30 @@ impl Destination {
             address,
             routing,
             meta,
+            auth: None,
+            report_url: None,
         }
     }
 
+    pub fn with_auth(mut self, auth: Option<Auth>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    pub fn with_report_url(mut self, report_url: Option<Url>) -> Self {
+        self.report_url = report_url;
+        self
+    }
+
+    /// Copy safe to hand out in responses, a static token is replaced by a placeholder.
+    pub fn redacted(&self) -> Self {
+        let auth = self.auth.as_ref().map(|auth| match auth {
+            Auth::Token(_) => Auth::Token(REDACTED_TOKEN.to_string()),
+            Auth::SignedChallenge => Auth::SignedChallenge,
+        });
+        Self { auth, ..self.clone() }
+    }
+
     pub fn pretty_print_path(&self) -> String {
         let nr = self.routing.hop_count();
         let path = (0..nr).map(|_| "()").collect::<Vec<&str>>().join("->");
@@ -52,6 +96,15 @@ impl Destination {
     }
 }
 
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Auth::Token(_) => f.debug_tuple("Token").field(&"****").finish(),
+            Auth::SignedChallenge => write!(f, "SignedChallenge"),
+        }
+    }
+}
+
 impl Display for Destination {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let short_addr = log_output::address(&self.address);
@@ -1,3 +1,4 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -6,6 +7,7 @@ use std::time::SystemTime;
 
 use crate::connection::destination::Destination;
 use crate::connection::options::SurbConfigError;
+use crate::gvpn_client::ApiVersion;
 use crate::hopr::HoprError;
 use crate::{connection, gvpn_client, log_output, ping};
 
@@ -20,9 +22,11 @@ pub struct Down {
     pub destination: Destination,
     pub phase: (SystemTime, Phase),
     pub wg_public_key: String,
+    pub api_version: ApiVersion,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[schemars(rename = "DisconnectPhase")]
 pub enum Phase {
     Disconnecting,
     OpeningBridge,
@@ -40,6 +44,8 @@ pub(crate) enum Error {
     Ping(#[from] ping::Error),
     #[error("Surb config error: {0}")]
     SurbConfig(#[from] SurbConfigError),
+    #[error("Timed out during {0}")]
+    Timeout(Phase),
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -61,6 +67,7 @@ impl TryFrom<&connection::up::Up> for Down {
                 destination: value.destination.clone(),
                 phase: (SystemTime::now(), Phase::Disconnecting),
                 wg_public_key: wg.key_pair.public_key,
+                api_version: value.api_version,
             })
         } else {
             Err("Cannot convert Up to Down: missing WireGuard public key")
@@ -3,6 +3,7 @@
 //! This allows keeping the source of truth for data in `core` and avoiding structs duplication.
 use edgli::hopr_lib::HoprSessionClientConfig;
 use tokio::sync::mpsc;
+use tokio::time;
 
 use std::fmt::{self, Display};
 use std::sync::Arc;
@@ -11,9 +12,10 @@ use crate::connection;
 use crate::connection::options::Options;
 use crate::connection::options::{SurbParams, surb_config_for};
 use crate::core::runner::Results;
-use crate::gvpn_client;
+use crate::gvpn_client::{self, ApiVersion};
 use crate::hopr::types::SessionClientMetadata;
 use crate::hopr::{Hopr, HoprError};
+use crate::proxy;
 
 use super::{Error, Event};
 
@@ -49,7 +51,13 @@ impl Runner {
             })
             .await;
         let bridge_surb = surb_config_for(&self.options.surb_balancing.bridge)?;
-        let bridge_session = open_bridge_session(&self.hopr, &self.down, &self.options, bridge_surb).await?;
+        let timeouts = &self.options.timeouts.phases;
+        let bridge_session = time::timeout(
+            timeouts.open_bridge,
+            open_bridge_session(&self.hopr, &self.down, &self.options, bridge_surb),
+        )
+        .await
+        .map_err(|_| Error::Timeout(connection::down::Phase::OpeningBridge))??;
 
         // 2. unregister wg public key
         let _ = results_sender
@@ -58,14 +66,27 @@ impl Runner {
                 evt: Event::UnregisterWg,
             })
             .await;
-        match unregister(&self.options, &bridge_session, self.down.wg_public_key.clone()).await {
-            Ok(_) => (),
-            Err(gvpn_client::Error::RegistrationNotFound) => {
+        let unregistered = time::timeout(
+            timeouts.register_wg,
+            unregister(
+                &self.options,
+                self.down.api_version,
+                &bridge_session,
+                self.down.wg_public_key.clone(),
+            ),
+        )
+        .await;
+        match unregistered {
+            Ok(Ok(_)) => (),
+            Ok(Err(gvpn_client::Error::RegistrationNotFound)) => {
                 tracing::warn!(wg_public_key = %self.down.wg_public_key, "trying to unregister already removed registration");
             }
-            Err(error) => {
+            Ok(Err(error)) => {
                 tracing::error!(%error, "unregistering from gvpn server failed");
             }
+            Err(_) => {
+                tracing::error!(timeout = ?timeouts.register_wg, "unregistering from gvpn server timed out");
+            }
         }
 
         // 3. close bridge session
@@ -107,12 +128,13 @@ async fn open_bridge_session(
 
 async fn unregister(
     options: &Options,
+    api_version: ApiVersion,
     session_client_metadata: &SessionClientMetadata,
     public_key: String,
 ) -> Result<(), gvpn_client::Error> {
     let input = gvpn_client::Input::new(public_key, session_client_metadata.bound_host, options.timeouts.http);
-    let client = reqwest::Client::new();
-    gvpn_client::unregister(&client, &input).await
+    let client = proxy::direct();
+    api_version.api().unregister(&client, &input).await
 }
 
 async fn close_bridge_session(hopr: &Hopr, session_client_metadata: &SessionClientMetadata) -> Result<(), HoprError> {
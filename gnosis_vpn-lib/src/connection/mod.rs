@@ -1,7 +1,9 @@
+pub(crate) mod affinity;
 pub mod destination;
 pub(crate) mod down;
 pub(crate) mod options;
 pub(crate) mod pseudonym_cache;
+pub(crate) mod registrations;
 pub(crate) mod up;
 
 pub use down::Phase as DownPhase;
@@ -1,14 +1,16 @@
 pub const DEFAULT_PATH_PLANNER_MIN_ACK_RATE: f64 = 0.1;
+pub const DEFAULT_AFFINITY_TTL: Duration = Duration::from_secs(60 * 60);
 
 use bytesize::ByteSize;
 use edgli::hopr_lib::exports::transport::{SessionCapabilities, SessionTarget, SurbBalancerConfig};
 use human_bandwidth::re::bandwidth::Bandwidth;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use url::Url;
 
 use std::time::Duration;
 
-use crate::ping;
+use crate::{ping, public_ip};
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Options {
@@ -18,14 +20,39 @@ pub struct Options {
     pub surb_balancing: SurbBalancing,
     pub health_check_intervals: HealthCheckIntervals,
     pub lan_lockdown: bool,
+    /// Block all traffic but the worker's own and the tunnel's while the daemon runs without a tunnel.
+    pub kill_switch: bool,
     /// How long to keep a closed session's pseudonym cached for potential reuse on reconnect.
     /// Exit nodes retain session SURBs for ~30s, so reconnecting within this window
     /// avoids a cold-start SURB exchange. Currently set to 1s (effectively disabled)
     /// until hopr-lib supports PIX.
     pub session_pseudonym_ttl: Duration,
+    /// How long `connect --any` keeps returning to the destination it picked, zero picks anew every time.
+    pub affinity_ttl: Duration,
     /// Minimum acknowledgement rate [0.0, 1.0] a path must sustain to be considered by
     /// the latency path planner. Paths below this threshold are skipped.
     pub path_planner_min_ack_rate: f64,
+    /// Egress rate limit installed on the WireGuard interface by the root process.
+    /// `None` leaves the tunnel unshaped.
+    pub egress_rate_limit: Option<Bandwidth>,
+    /// DSCP codepoint (0-63) set on outgoing HOPR transport packets, `None` leaves them unmarked.
+    pub dscp: Option<u8>,
+    /// Share the tunnel with a LAN, `None` keeps it to this host.
+    pub gateway: Option<Gateway>,
+    /// Echo endpoint queried through the tunnel once connected, `None` skips the public IP lookup.
+    pub public_ip_endpoint: Option<Url>,
+    pub reconnect: Reconnect,
+}
+
+/// Fallback for configurations not naming an echo endpoint.
+pub fn default_public_ip_endpoint() -> Option<Url> {
+    Url::parse(public_ip::DEFAULT_ENDPOINT).ok()
+}
+
+/// Forward and NAT traffic arriving on a LAN interface into the tunnel.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gateway {
+    pub lan_interface: String,
 }
 
 /// Controls how often each tier of health check runs.
@@ -43,6 +70,16 @@ pub struct HealthCheckIntervals {
     pub tunnel_ping_max_failures: u32,
 }
 
+/// Re-running the connection after an established tunnel broke, keeping the target destination.
+/// Attempts are delayed exponentially from `initial_delay` up to `max_delay`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Reconnect {
+    /// Failed attempts retried before falling back to restarting the worker.
+    pub max_retries: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Sessions {
     pub bridge: SessionParameters,
@@ -52,6 +89,19 @@ pub struct Sessions {
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Timeouts {
     pub http: Duration,
+    pub phases: PhaseTimeouts,
+}
+
+/// Upper bound for each connection phase, retries included.
+/// A phase running longer fails the attempt instead of retrying indefinitely.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PhaseTimeouts {
+    pub open_bridge: Duration,
+    pub register_wg: Duration,
+    pub open_ping: Duration,
+    pub establish_wg_tunnel: Duration,
+    pub killswitch_lockdown: Duration,
+    pub verify_ping: Duration,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -94,6 +144,42 @@ impl SessionSurbOptions {
     }
 }
 
+impl Default for PhaseTimeouts {
+    fn default() -> Self {
+        // bridge and ping sessions need ~23s on 3 hops when hopr-lib exhausts its own retries
+        Self {
+            open_bridge: Duration::from_secs(90),
+            register_wg: Duration::from_secs(60),
+            open_ping: Duration::from_secs(90),
+            establish_wg_tunnel: Duration::from_secs(20),
+            killswitch_lockdown: Duration::from_secs(20),
+            verify_ping: Duration::from_secs(90),
+        }
+    }
+}
+
+impl Reconnect {
+    /// Delay before retrying after the `attempt`th failed reconnect, counting from 1.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay
+            .checked_mul(factor)
+            .unwrap_or(Duration::MAX)
+            .min(self.max_delay)
+    }
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        // 2s, 4s, 8s, 16s, 32s - about a minute of retries before restarting the worker
+        Self {
+            max_retries: 5,
+            initial_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
 impl Default for HealthCheckIntervals {
     fn default() -> Self {
         Self {
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::PathBuf;
+
+pub const REGISTRATIONS_FILE: &str = "registrations.json";
+
+/// A WireGuard public key registered at an exit node.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub destination_id: String,
+    pub public_key: String,
+}
+
+/// Persists exit registrations until they were unregistered again.
+///
+/// A record is written as soon as the exit confirmed the registration and removed once the
+/// disconnection runner finished. Records still present on startup belong to a run that
+/// ended before it could unregister and are cleaned up by core.
+pub struct RegistrationStore {
+    path: PathBuf,
+    records: Vec<Record>,
+}
+
+impl RegistrationStore {
+    pub fn load(path: PathBuf) -> Self {
+        let records = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|error| {
+                tracing::warn!(?error, ?path, "discarding unreadable registrations file");
+                Vec::new()
+            }),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => {
+                tracing::warn!(?error, ?path, "unable to read registrations file");
+                Vec::new()
+            }
+        };
+        Self { path, records }
+    }
+
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    pub fn insert(&mut self, destination_id: String, public_key: String) {
+        if self.records.iter().any(|r| r.public_key == public_key) {
+            return;
+        }
+        self.records.push(Record {
+            destination_id,
+            public_key,
+        });
+        self.persist();
+    }
+
+    pub fn remove(&mut self, public_key: &str) {
+        let before = self.records.len();
+        self.records.retain(|r| r.public_key != public_key);
+        if self.records.len() != before {
+            self.persist();
+        }
+    }
+
+    fn persist(&self) {
+        let res = serde_json::to_string(&self.records)
+            .map_err(std::io::Error::other)
+            .and_then(|content| fs::write(&self.path, content));
+        if let Err(error) = res {
+            tracing::warn!(?error, path = ?self.path, "failed to persist registrations");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::tempdir;
+
+    #[test]
+    fn records_survive_reload() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(REGISTRATIONS_FILE);
+
+        let mut store = RegistrationStore::load(path.clone());
+        store.insert("Germany".to_string(), "key-a".to_string());
+        store.insert("Spain".to_string(), "key-b".to_string());
+        store.insert("Spain".to_string(), "key-b".to_string());
+        store.remove("key-a");
+
+        let reloaded = RegistrationStore::load(path);
+        assert_eq!(
+            reloaded.records(),
+            &[Record {
+                destination_id: "Spain".to_string(),
+                public_key: "key-b".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn unreadable_file_starts_empty() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(REGISTRATIONS_FILE);
+        fs::write(&path, "not json").unwrap();
+        assert!(RegistrationStore::load(path).records().is_empty());
+    }
+}
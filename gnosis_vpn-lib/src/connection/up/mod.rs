@@ -1,3 +1,4 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -7,9 +8,10 @@ use std::time::{Duration, SystemTime};
 
 use crate::connection::destination::Destination;
 use crate::connection::options::SurbConfigError;
-use crate::gvpn_client::Registration;
+use crate::gvpn_client::{ApiVersion, Registration};
 use crate::hopr::HoprError;
 use crate::hopr::types::SessionClientMetadata;
+use crate::public_ip::PublicIp;
 use crate::wireguard::WireGuard;
 use crate::{gvpn_client, log_output, remote_data, wireguard};
 
@@ -49,6 +51,19 @@ pub enum Setback {
     RegisterWg(String),
     OpenPing(String),
     Ping(String),
+    Timeout(Phase, Remediation),
+}
+
+/// Suggested user action after a phase timed out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Remediation {
+    /// The exit or the route towards it is not reachable, another destination might work
+    TryOtherDestination,
+    /// Traffic through the tunnel does not arrive, a local firewall or VPN might interfere
+    CheckLocalFirewall,
+    /// The root service did not answer in time, restarting it usually helps
+    RestartService,
 }
 
 #[derive(Debug, Error)]
@@ -69,6 +84,10 @@ pub(crate) enum Error {
     WireGuard(#[from] wireguard::Error),
     #[error("Remote data error: {0}")]
     RemoteData(#[from] remote_data::Error),
+    #[error("Timed out during {0} - suggested remediation: {1}")]
+    Timeout(Phase, Remediation),
+    #[error("Exit authentication error: {0}")]
+    Auth(String),
 }
 
 /// Contains stateful data of establishing a VPN connection to a destination.
@@ -78,6 +97,8 @@ pub(crate) enum Error {
 #[derive(Clone, Debug)]
 pub struct Up {
     pub destination: Destination,
+    /// When this connection attempt started, `phase` only tracks the current step.
+    pub started: SystemTime,
     pub phase: (SystemTime, Phase),
     pub wireguard: Option<WireGuard>,
     pub registration: Option<Registration>,
@@ -85,9 +106,14 @@ pub struct Up {
     pub bridge_session: Option<SessionClientMetadata>,
     /// The ping session while connecting, promoted to Main once connected.
     pub ping_session: Option<(SessionKind, SessionClientMetadata)>,
+    /// Registration API negotiated with the exit.
+    pub api_version: ApiVersion,
+    /// Exit address resolved through the tunnel once connected.
+    pub public_ip: Option<PublicIp>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[schemars(rename = "ConnectPhase")]
 pub enum Phase {
     Init,
     ResolvingBlokliIps,
@@ -103,21 +129,123 @@ pub enum Phase {
     ConnectionEstablished,
 }
 
+/// Phases in the order a connection passes them, with their typical duration.
+/// Used to estimate progress, actual durations depend heavily on the chosen route.
+const EXPECTED_DURATIONS: [(Phase, Duration); 12] = [
+    (Phase::Init, Duration::ZERO),
+    (Phase::ResolvingBlokliIps, Duration::from_secs(1)),
+    (Phase::GeneratingWg, Duration::from_secs(1)),
+    (Phase::OpeningBridge, Duration::from_secs(10)),
+    (Phase::RegisterWg, Duration::from_secs(5)),
+    (Phase::OpeningPing, Duration::from_secs(10)),
+    (Phase::GatherPeerIps, Duration::from_secs(1)),
+    (Phase::KillswitchLockdown, Duration::from_secs(1)),
+    (Phase::EstablishWgTunnel, Duration::from_secs(2)),
+    (Phase::VerifyPing, Duration::from_secs(5)),
+    (Phase::AdjustToMain, Duration::from_secs(5)),
+    (Phase::ConnectionEstablished, Duration::ZERO),
+];
+
+/// Estimated progress of a connection attempt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Estimate {
+    pub percent: u8,
+    pub eta: Duration,
+}
+
+impl Phase {
+    /// Position of this phase in the order a connection passes them.
+    pub fn ordinal(&self) -> usize {
+        EXPECTED_DURATIONS
+            .iter()
+            .position(|(p, _)| p == self)
+            .unwrap_or_default()
+    }
+
+    /// Estimate overall progress given the time already spent in this phase.
+    /// A phase taking longer than expected never reaches the share of the next one.
+    pub fn estimate(&self, in_phase: Duration) -> Estimate {
+        let total: Duration = EXPECTED_DURATIONS.iter().map(|(_, d)| *d).sum();
+        let index = self.ordinal();
+        let done: Duration = EXPECTED_DURATIONS[..index].iter().map(|(_, d)| *d).sum();
+        let expected = EXPECTED_DURATIONS[index].1;
+        let current = in_phase.min(expected.mul_f64(0.95));
+        let following: Duration = EXPECTED_DURATIONS[index + 1..].iter().map(|(_, d)| *d).sum();
+        let percent = if *self == Phase::ConnectionEstablished {
+            100
+        } else {
+            ((done + current).as_secs_f64() / total.as_secs_f64() * 100.0) as u8
+        };
+        Estimate {
+            percent,
+            eta: expected.saturating_sub(in_phase) + following,
+        }
+    }
+}
+
+impl Remediation {
+    pub fn for_phase(phase: &Phase) -> Self {
+        match phase {
+            Phase::EstablishWgTunnel | Phase::KillswitchLockdown => Remediation::RestartService,
+            Phase::VerifyPing => Remediation::CheckLocalFirewall,
+            _ => Remediation::TryOtherDestination,
+        }
+    }
+}
+
+/// How core should react to a failed connection attempt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// Transient failure, trying again might succeed
+    Retryable,
+    /// Retrying is pointless until the user changed something, e.g. unblocked a port
+    NeedsUserAction,
+    /// The destination cannot be reached with the current configuration
+    Terminal,
+}
+
 impl Error {
     pub fn is_ping_error(&self) -> bool {
         matches!(self, Error::Ping(_))
     }
+
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Error::Hopr(_) | Error::Ping(_) | Error::Runtime(_) => ErrorCategory::Retryable,
+            Error::GvpnClient(
+                gvpn_client::Error::SocketConnect(_)
+                | gvpn_client::Error::AuthRejected(_)
+                | gvpn_client::Error::InvalidToken,
+            ) => ErrorCategory::NeedsUserAction,
+            Error::GvpnClient(gvpn_client::Error::Request(err))
+                if err.status().is_some_and(|s| s.is_client_error()) =>
+            {
+                ErrorCategory::NeedsUserAction
+            }
+            Error::GvpnClient(_) => ErrorCategory::Retryable,
+            Error::Routing(_) | Error::WireGuard(_) | Error::Auth(_) => ErrorCategory::NeedsUserAction,
+            Error::SurbConfig(_) => ErrorCategory::Terminal,
+            Error::RemoteData(remote_data::Error::IO(_)) => ErrorCategory::Retryable,
+            Error::RemoteData(_) => ErrorCategory::Terminal,
+            Error::Timeout(_, Remediation::TryOtherDestination) => ErrorCategory::Retryable,
+            Error::Timeout(..) => ErrorCategory::NeedsUserAction,
+        }
+    }
 }
 
 impl Up {
-    pub fn new(destination: Destination) -> Self {
+    pub fn new(destination: Destination, api_version: ApiVersion) -> Self {
+        let now = SystemTime::now();
         Self {
             destination,
-            phase: (SystemTime::now(), Phase::Init),
+            started: now,
+            phase: (now, Phase::Init),
             wireguard: None,
             registration: None,
             bridge_session: None,
             ping_session: None,
+            api_version,
+            public_ip: None,
         }
     }
 
@@ -229,6 +357,78 @@ impl Display for Setback {
             Setback::RegisterWg(err) => write!(f, "Failed to register WireGuard key: {err}"),
             Setback::OpenPing(err) => write!(f, "Failed to open main connection: {err}"),
             Setback::Ping(err) => write!(f, "Ping verification failed: {err}"),
+            Setback::Timeout(phase, remediation) => {
+                write!(f, "Timed out during {phase} - suggested remediation: {remediation}")
+            }
         }
     }
 }
+
+impl Display for Remediation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        let code = match self {
+            Remediation::TryOtherDestination => "try_other_destination",
+            Remediation::CheckLocalFirewall => "check_local_firewall",
+            Remediation::RestartService => "restart_service",
+        };
+        write!(f, "{code}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_are_categorized() {
+        assert_eq!(Error::Ping("lost".to_string()).category(), ErrorCategory::Retryable);
+        assert_eq!(
+            Error::SurbConfig(SurbConfigError::MaxSurbUpstreamCannotBeZero).category(),
+            ErrorCategory::Terminal
+        );
+        assert_eq!(
+            Error::RemoteData(remote_data::Error::NoHost).category(),
+            ErrorCategory::Terminal
+        );
+        assert_eq!(
+            Error::Timeout(Phase::OpeningBridge, Remediation::TryOtherDestination).category(),
+            ErrorCategory::Retryable
+        );
+        assert_eq!(
+            Error::Timeout(Phase::VerifyPing, Remediation::CheckLocalFirewall).category(),
+            ErrorCategory::NeedsUserAction
+        );
+        assert_eq!(
+            Error::Routing("permission denied".to_string()).category(),
+            ErrorCategory::NeedsUserAction
+        );
+    }
+
+    #[test]
+    fn estimate_grows_with_phases() {
+        let start = Phase::Init.estimate(Duration::ZERO);
+        assert_eq!(start.percent, 0);
+        assert_eq!(start.eta, Duration::from_secs(41));
+
+        let bridge = Phase::OpeningBridge.estimate(Duration::from_secs(4));
+        assert_eq!(bridge.eta, Duration::from_secs(35));
+        assert!(bridge.percent > start.percent);
+
+        let done = Phase::ConnectionEstablished.estimate(Duration::ZERO);
+        assert_eq!(
+            done,
+            Estimate {
+                percent: 100,
+                eta: Duration::ZERO
+            }
+        );
+    }
+
+    #[test]
+    fn slow_phase_stays_below_next() {
+        let stuck = Phase::OpeningBridge.estimate(Duration::from_secs(600));
+        let next = Phase::RegisterWg.estimate(Duration::ZERO);
+        assert!(stuck.percent < next.percent);
+        assert_eq!(stuck.eta, Duration::from_secs(29));
+    }
+}
@@ -2,26 +2,31 @@
 //! It handles state transitions up until wg tunnel initiation and forwards transition events though its channel.
 //! This allows keeping the source of truth for data in `core` and avoiding structs duplication.
 use backon::{FibonacciBuilder, Retryable};
-use edgli::hopr_lib::{HoprSessionClientConfig, api::types::internal::protocol::HoprPseudonym};
+use edgli::hopr_lib::api::types::crypto::prelude::OffchainSignature;
+use edgli::hopr_lib::api::types::primitive::traits::ToHex;
+use edgli::hopr_lib::builder::Keypair;
+use edgli::hopr_lib::{HoprKeys, HoprSessionClientConfig, api::types::internal::protocol::HoprPseudonym};
 use tokio::sync::{mpsc, oneshot};
 
 use std::fmt::{self, Display};
-use std::net::Ipv4Addr;
+use std::future::Future;
+use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::connection::destination::Destination;
+use crate::connection::destination::{Auth, Destination};
 use crate::connection::options::{Options, SurbParams, surb_config_for};
 use crate::core::runner::Results;
+use crate::core::tasks::{Subsystem, Tasks};
 use crate::event::{self, RunnerToRoot};
-use crate::gvpn_client::{self, Registration};
+use crate::gvpn_client::{self, ApiVersion, Registration};
 use crate::hopr::types::SessionClientMetadata;
 use crate::hopr::{self, Hopr, HoprError};
 use crate::wireguard::{self, WireGuard};
 use crate::worker_params::WorkerParams;
-use crate::{ping, remote_data};
+use crate::{backoff, log_output, ping, proxy, remote_data};
 
-use super::{Error, Event, Progress, Setback};
+use super::{Error, Event, Phase, Progress, Remediation, Setback};
 
 /// State carried over from a previous connection attempt.
 pub(crate) struct PreviousConnection {
@@ -35,29 +40,38 @@ pub(crate) struct PreviousConnection {
 
 pub(crate) struct Runner {
     destination: Destination,
+    api_version: ApiVersion,
     hopr: Arc<Hopr>,
     options: Options,
+    backoff: backoff::Config,
     wg_config: wireguard::Config,
     worker_params: WorkerParams,
     prev_conn: PreviousConnection,
+    tasks: Tasks,
 }
 
 impl Runner {
     pub(crate) fn new(
         destination: Destination,
+        api_version: ApiVersion,
         options: Options,
+        backoff: backoff::Config,
         wg_config: wireguard::Config,
         hopr: Arc<Hopr>,
         worker_params: WorkerParams,
         prev_conn: PreviousConnection,
+        tasks: Tasks,
     ) -> Self {
         Self {
             destination,
+            api_version,
             hopr,
             options,
+            backoff,
             wg_config,
             worker_params,
             prev_conn,
+            tasks,
         }
     }
 
@@ -67,11 +81,14 @@ impl Runner {
     }
 
     async fn run(&self, results_sender: mpsc::Sender<Results>) -> Result<SessionClientMetadata, Error> {
+        let timeouts = &self.options.timeouts.phases;
         // 1. resolve blokli ips — use cached IPs when killswitch is active (DNS unreachable)
         let _ = results_sender.send(progress(Progress::ResolveBlokliIps)).await;
         let blokli_url = hopr::blokli_url(self.worker_params.blokli_url());
+        // behind a proxy chain requests only ever reach the proxy
+        let chain_egress = proxy::from_env(&blokli_url).unwrap_or(blokli_url);
         let blokli_ips = if self.prev_conn.blokli_ips.is_empty() {
-            remote_data::resolve_ips(&blokli_url).await?
+            remote_data::resolve_ips(&chain_egress).await?
         } else {
             self.prev_conn.blokli_ips.clone()
         };
@@ -86,12 +103,19 @@ impl Runner {
         // 3. open bridge session
         let _ = results_sender.send(progress(Progress::OpenBridge(wg.clone()))).await;
         let bridge_surb = surb_config_for(&self.options.surb_balancing.bridge)?;
-        let bridge_session = open_bridge_session(
-            &self.hopr,
-            &self.destination,
-            &self.options,
-            bridge_surb,
+        let bridge_session = within(
+            timeouts.open_bridge,
+            Phase::OpeningBridge,
             &results_sender,
+            open_bridge_session(
+                &self.hopr,
+                &self.destination,
+                &self.options,
+                bridge_surb,
+                self.backoff,
+                &self.tasks,
+                &results_sender,
+            ),
         )
         .await?;
         let _ = results_sender
@@ -100,7 +124,32 @@ impl Runner {
 
         // 4. register wg public key
         let _ = results_sender.send(progress(Progress::RegisterWg)).await;
-        let registration = register(&self.options, &bridge_session, public_key, &results_sender).await?;
+        let keys = match self.destination.auth {
+            Some(Auth::SignedChallenge) => Some(
+                self.worker_params
+                    .calc_keys()
+                    .await
+                    .map_err(|err| Error::Auth(err.to_string()))?,
+            ),
+            _ => None,
+        };
+        let registration = within(
+            timeouts.register_wg,
+            Phase::RegisterWg,
+            &results_sender,
+            register(
+                &self.options,
+                &self.destination,
+                self.api_version,
+                keys.as_ref(),
+                &bridge_session,
+                public_key,
+                self.backoff,
+                &self.tasks,
+                &results_sender,
+            ),
+        )
+        .await?;
 
         // 5. signal ping phase (carries registration) and close bridge in background
         let _ = results_sender
@@ -110,19 +159,28 @@ impl Runner {
             self.hopr.clone(),
             bridge_session,
             self.options.clone(),
+            self.api_version,
             self.prev_conn.wg_public_key.clone(),
+            &self.tasks,
             results_sender.clone(),
         );
 
         // 6. open ping session
         let ping_surb = surb_config_for(&self.options.surb_balancing.ping)?;
-        let session = open_ping_session(
-            &self.hopr,
-            &self.destination,
-            &self.options,
-            ping_surb,
-            self.prev_conn.pseudonym,
+        let session = within(
+            timeouts.open_ping,
+            Phase::OpeningPing,
             &results_sender,
+            open_ping_session(
+                &self.hopr,
+                &self.destination,
+                &self.options,
+                ping_surb,
+                self.prev_conn.pseudonym,
+                self.backoff,
+                &self.tasks,
+                &results_sender,
+            ),
         )
         .await?;
 
@@ -137,16 +195,33 @@ impl Runner {
         let _ = results_sender
             .send(progress(Progress::StaticWgTunnel(session.clone())))
             .await;
-        let interface =
-            request_static_wg_tunnel(&wg, &registration, &session, peer_ips.clone(), &results_sender).await?;
+        let interface = within(
+            timeouts.establish_wg_tunnel,
+            Phase::EstablishWgTunnel,
+            &results_sender,
+            request_static_wg_tunnel(&wg, &registration, &session, peer_ips.clone(), &results_sender),
+        )
+        .await?;
 
         // 9. activate killswitch now that the interface name is known
         let _ = results_sender.send(progress(Progress::KillswitchLockdown)).await;
-        request_killswitch_lockdown(peer_ips, interface, &results_sender).await?;
+        within(
+            timeouts.killswitch_lockdown,
+            Phase::KillswitchLockdown,
+            &results_sender,
+            request_killswitch_lockdown(peer_ips, interface, &results_sender),
+        )
+        .await?;
 
         // 10. verify tunnel with ping — give it some leeway with 5 retries
         let _ = results_sender.send(progress(Progress::Ping)).await;
-        let round_trip_time = request_ping(&self.options.ping_options, 5, &results_sender).await?;
+        let round_trip_time = within(
+            timeouts.verify_ping,
+            Phase::VerifyPing,
+            &results_sender,
+            request_ping(&self.options.ping_options, 5, &self.tasks, &results_sender),
+        )
+        .await?;
 
         // 11. adjust to main session
         let _ = results_sender
@@ -174,7 +249,7 @@ impl Display for Runner {
 }
 
 #[tracing::instrument(
-    skip(hopr, options, destination, results_sender),
+    skip(hopr, options, backoff, destination, tasks, results_sender),
     fields(
         address = %destination.address,
         routing = ?destination.routing,
@@ -187,6 +262,8 @@ async fn open_bridge_session(
     destination: &Destination,
     options: &Options,
     surb: SurbParams,
+    backoff: backoff::Config,
+    tasks: &Tasks,
     results_sender: &mpsc::Sender<Results>,
 ) -> Result<SessionClientMetadata, HoprError> {
     let cfg = HoprSessionClientConfig {
@@ -202,6 +279,9 @@ async fn open_bridge_session(
     //   1-hop: ~2 s/attempt, ~15 s total
     //   2-hop: ~3 s/attempt, ~19 s total
     //   3-hop: ~4 s/attempt, ~23 s total
+    // A failure is likely structural by then, one extra attempt covers brief transients. More would
+    // delay the worker restart that is designed to fix deeper issues.
+    let mut sampler = log_output::Sampler::new();
     (|| async {
         tracing::debug!(%destination, "attempting to open bridge session");
         hopr.open_session(
@@ -213,42 +293,82 @@ async fn open_bridge_session(
         )
         .await
     })
-    .retry(remote_data::backoff_expo_short_delay_bridge())
+    .retry(backoff.exponential().with_max_times(1))
     .notify(|err: &HoprError, dur: Duration| {
-        tracing::warn!(error = ?err, "error opening bridge session - will retry after {:?}", dur);
-        let tx = results_sender.clone();
-        let payload = setback(Setback::OpenBridge(err.to_string()));
-        tokio::spawn(async move {
-            let _ = tx.send(payload).await;
-        });
+        if sampler.sample(&err.to_string()) {
+            tracing::warn!(error = ?err, "error opening bridge session - will retry after {:?}", dur);
+        }
+        report_setback(tasks, results_sender, Setback::OpenBridge(err.to_string()));
     })
     .await
 }
 
 async fn register(
     options: &Options,
+    destination: &Destination,
+    api_version: ApiVersion,
+    keys: Option<&HoprKeys>,
     session_client_metadata: &SessionClientMetadata,
     public_key: String,
+    backoff: backoff::Config,
+    tasks: &Tasks,
     results_sender: &mpsc::Sender<Results>,
 ) -> Result<Registration, gvpn_client::Error> {
     let input = gvpn_client::Input::new(public_key, session_client_metadata.bound_host, options.timeouts.http);
+    let mut sampler = log_output::Sampler::new();
     (|| async {
-        tracing::debug!(?input, "attempting to register gvpn client public key");
-        let client = reqwest::Client::new();
-        gvpn_client::register(&client, &input).await
+        let client = proxy::direct();
+        // challenges are single use - fetch a fresh one on every attempt
+        let credentials = credentials(
+            &client,
+            destination.auth.as_ref(),
+            keys,
+            input.socket_addr(),
+            input.timeout(),
+        )
+        .await?;
+        let input = input.clone().with_credentials(credentials);
+        tracing::debug!(?input, %api_version, "attempting to register gvpn client public key");
+        api_version.api().register(&client, &input).await
+    })
+    .retry(backoff.exponential())
+    .when(|err: &gvpn_client::Error| {
+        !matches!(
+            err,
+            gvpn_client::Error::AuthRejected(_) | gvpn_client::Error::InvalidToken
+        )
     })
-    .retry(remote_data::backoff_expo_short_delay())
     .notify(|err: &gvpn_client::Error, dur: Duration| {
-        tracing::warn!(error = ?err, "register wg pubkey failed - will retry after {:?}", dur);
-        let tx = results_sender.clone();
-        let payload = setback(Setback::RegisterWg(err.to_string()));
-        tokio::spawn(async move {
-            let _ = tx.send(payload).await;
-        });
+        if sampler.sample(&err.to_string()) {
+            tracing::warn!(error = ?err, "register wg pubkey failed - will retry after {:?}", dur);
+        }
+        report_setback(tasks, results_sender, Setback::RegisterWg(err.to_string()));
     })
     .await
 }
 
+async fn credentials(
+    client: &reqwest::Client,
+    auth: Option<&Auth>,
+    keys: Option<&HoprKeys>,
+    socket_addr: SocketAddr,
+    timeout: Duration,
+) -> Result<Option<gvpn_client::Credentials>, gvpn_client::Error> {
+    match (auth, keys) {
+        (Some(Auth::Token(token)), _) => Ok(Some(gvpn_client::Credentials::Token(token.clone()))),
+        (Some(Auth::SignedChallenge), Some(keys)) => {
+            let challenge = gvpn_client::challenge(client, socket_addr, timeout).await?;
+            let signature = OffchainSignature::sign_message(challenge.as_bytes(), &keys.packet_key);
+            Ok(Some(gvpn_client::Credentials::Signature {
+                challenge,
+                signature: signature.to_hex(),
+                public_key: keys.packet_key.public().to_hex(),
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
 async fn close_bridge_session(hopr: &Hopr, session_client_metadata: &SessionClientMetadata) -> Result<(), HoprError> {
     tracing::debug!(
         bound_host = ?session_client_metadata.bound_host,
@@ -273,6 +393,8 @@ async fn open_ping_session(
     options: &Options,
     surb: SurbParams,
     pseudonym: Option<HoprPseudonym>,
+    backoff: backoff::Config,
+    tasks: &Tasks,
     results_sender: &mpsc::Sender<Results>,
 ) -> Result<SessionClientMetadata, HoprError> {
     let cfg = HoprSessionClientConfig {
@@ -283,6 +405,7 @@ async fn open_ping_session(
         surb_management: surb.management,
         pseudonym,
     };
+    let mut sampler = log_output::Sampler::new();
     (|| async {
         tracing::debug!(%destination, "attempting to open ping session");
         hopr.open_session(
@@ -294,14 +417,12 @@ async fn open_ping_session(
         )
         .await
     })
-    .retry(remote_data::backoff_expo_short_delay())
+    .retry(backoff.exponential())
     .notify(|err: &HoprError, dur: Duration| {
-        tracing::warn!(error = ?err, "error opening ping session - will retry after {:?}", dur);
-        let tx = results_sender.clone();
-        let payload = setback(Setback::OpenPing(err.to_string()));
-        tokio::spawn(async move {
-            let _ = tx.send(payload).await;
-        });
+        if sampler.sample(&err.to_string()) {
+            tracing::warn!(error = ?err, "error opening ping session - will retry after {:?}", dur);
+        }
+        report_setback(tasks, results_sender, Setback::OpenPing(err.to_string()));
     })
     .await
 }
@@ -320,16 +441,11 @@ async fn request_killswitch_lockdown(
         }))
         .await;
 
-    tokio::select!(
-        res = rx => match res {
-            Ok(Ok(interface)) => Ok(interface),
-            Ok(Err(e)) => Err(Error::Routing(e)),
-            Err(reason) => Err(Error::Runtime(format!("Channel closed unexpectedly: {reason}"))),
-        },
-        _ = tokio::time::sleep(Duration::from_secs(20)) => {
-            Err(Error::Runtime("Timed out waiting for killswitch lockdown".to_string()))
-        }
-    )
+    match rx.await {
+        Ok(Ok(interface)) => Ok(interface),
+        Ok(Err(e)) => Err(Error::Routing(e)),
+        Err(reason) => Err(Error::Runtime(format!("Channel closed unexpectedly: {reason}"))),
+    }
 }
 
 async fn request_static_wg_tunnel(
@@ -340,9 +456,21 @@ async fn request_static_wg_tunnel(
     results_sender: &mpsc::Sender<Results>,
 ) -> Result<String, Error> {
     let (tx, rx) = oneshot::channel();
+    let capabilities = registration.capabilities();
     let interface_info = wireguard::InterfaceInfo {
         address: registration.address(),
+        mtu: capabilities.tunnel_mtu(),
     };
+    let mut wg = wg.clone();
+    // resolvers of the exit replace the defaults, configured ones and `overwrite = false` stay
+    if wg.config.dns.as_deref() == Some(wireguard::DEFAULT_DNS)
+        && let Some(servers) = capabilities.dns_servers()
+    {
+        wg.config.dns = Some(servers);
+    }
+    if capabilities.ipv6 {
+        tracing::debug!("exit routes IPv6 but the tunnel carries IPv4 only - IPv6 does not go through it");
+    }
     let peer_info = wireguard::PeerInfo {
         public_key: registration.server_public_key(),
         preshared_key: registration.preshared_key(),
@@ -353,7 +481,7 @@ async fn request_static_wg_tunnel(
         ),
     };
     let wg_data = event::WireGuardData {
-        wg: wg.clone(),
+        wg,
         peer_info,
         interface_info,
     };
@@ -365,16 +493,11 @@ async fn request_static_wg_tunnel(
         }))
         .await;
 
-    tokio::select!(
-        res = rx => match res {
-            Ok(Ok(interface)) => Ok(interface),
-            Ok(Err(e)) => Err(Error::Routing(e)),
-            Err(reason) => Err(Error::Runtime(format!("Channel closed unexpectedly: {}", reason))),
-        },
-        _ = tokio::time::sleep(Duration::from_secs(20)) => {
-            Err(Error::Runtime("Timed out waiting for response".to_string()))
-        }
-    )
+    match rx.await {
+        Ok(Ok(interface)) => Ok(interface),
+        Ok(Err(e)) => Err(Error::Routing(e)),
+        Err(reason) => Err(Error::Runtime(format!("Channel closed unexpectedly: {}", reason))),
+    }
 }
 
 async fn gather_peer_ips(hopr: &Hopr) -> Result<Vec<Ipv4Addr>, HoprError> {
@@ -388,8 +511,10 @@ async fn gather_peer_ips(hopr: &Hopr) -> Result<Vec<Ipv4Addr>, HoprError> {
 async fn request_ping(
     options: &ping::Options,
     max_backoff: usize,
+    tasks: &Tasks,
     results_sender: &mpsc::Sender<Results>,
 ) -> Result<Duration, Error> {
+    let mut sampler = log_output::Sampler::new();
     (|| async {
         let (tx, rx) = oneshot::channel();
         let _ = results_sender
@@ -412,12 +537,10 @@ async fn request_ping(
     .retry(FibonacciBuilder::new().with_jitter().with_max_times(max_backoff))
     .when(|err: &Error| err.is_ping_error())
     .notify(|err: &Error, dur: Duration| {
-        tracing::warn!(error = ?err, "ping request failed - will retry after {:?}", dur);
-        let tx = results_sender.clone();
-        let payload = setback(Setback::Ping(err.to_string()));
-        tokio::spawn(async move {
-            let _ = tx.send(payload).await;
-        });
+        if sampler.sample(&err.to_string()) {
+            tracing::warn!(error = ?err, "ping request failed - will retry after {:?}", dur);
+        }
+        report_setback(tasks, results_sender, Setback::Ping(err.to_string()));
     })
     .await
 }
@@ -426,14 +549,16 @@ fn spawn_background_bridge_cleanup(
     hopr: Arc<Hopr>,
     bridge_session: SessionClientMetadata,
     options: Options,
+    api_version: ApiVersion,
     prev_public_key: Option<String>,
+    tasks: &Tasks,
     results_sender: mpsc::Sender<Results>,
 ) {
-    tokio::spawn(async move {
+    tasks.spawn(Subsystem::Connection, async move {
         if let Some(old_key) = prev_public_key {
             let input = gvpn_client::Input::new(old_key, bridge_session.bound_host, options.timeouts.http);
-            let client = reqwest::Client::new();
-            match gvpn_client::unregister(&client, &input).await {
+            let client = proxy::direct();
+            match api_version.api().unregister(&client, &input).await {
                 Ok(()) => tracing::debug!("unregistered old wg public key"),
                 Err(gvpn_client::Error::RegistrationNotFound) => {
                     tracing::warn!(wg_public_key = %input.public_key(), "old wg key not found during unregister, possibly already removed");
@@ -450,6 +575,38 @@ fn spawn_background_bridge_cleanup(
     });
 }
 
+/// Bound `fut` by the timeout of `phase`, reporting a timeout as setback before failing.
+async fn within<T, E, F>(
+    timeout: Duration,
+    phase: Phase,
+    results_sender: &mpsc::Sender<Results>,
+    fut: F,
+) -> Result<T, Error>
+where
+    F: Future<Output = Result<T, E>>,
+    Error: From<E>,
+{
+    match tokio::time::timeout(timeout, fut).await {
+        Ok(res) => res.map_err(Error::from),
+        Err(_) => {
+            let remediation = Remediation::for_phase(&phase);
+            tracing::warn!(%phase, ?timeout, %remediation, "connection phase timed out");
+            let _ = results_sender
+                .send(setback(Setback::Timeout(phase.clone(), remediation)))
+                .await;
+            Err(Error::Timeout(phase, remediation))
+        }
+    }
+}
+
+/// Report a retry from a `notify` callback, which cannot await the send itself.
+fn report_setback(tasks: &Tasks, results_sender: &mpsc::Sender<Results>, payload: Setback) {
+    let tx = results_sender.clone();
+    tasks.spawn(Subsystem::Connection, async move {
+        let _ = tx.send(setback(payload)).await;
+    });
+}
+
 fn setback(setback: Setback) -> Results {
     Results::ConnectionEvent(Event::Setback(Box::new(setback)))
 }
@@ -2,50 +2,83 @@ use edgli::EdgliInitState;
 use edgli::blokli::IncentiveOperations;
 use edgli::hopr_lib::api::types::primitive::traits::ToHex;
 use edgli::hopr_lib::builder::Keypair;
+use edgli::hopr_lib::exports::network::types::types::IpProtocol;
 use edgli::hopr_lib::exports::transport::SessionId;
 use futures_util::future::AbortHandle;
 use thiserror::Error;
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{Semaphore, mpsc, oneshot};
 use tokio::time;
 use tokio_util::sync::CancellationToken;
 use tokio_util::task::TaskTracker;
 
 use std::collections::{HashMap, HashSet};
 use std::net;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
 
+use crate::backoff::{self, Task};
+use crate::balance_history;
 use crate::command::{self, Response, RunMode, WorkerCommand};
 use crate::compat::SafeModule;
 use crate::config::{self, Config};
 use crate::connection;
+use crate::connection::affinity::{self, Affinity};
 use crate::connection::destination::{Address, Destination};
 use crate::connection::pseudonym_cache::PseudonymCache;
+use crate::connection::registrations::{self, RegistrationStore};
 use crate::event::{CoreToWorker, RequestToRoot, ResponseFromRoot, RunnerToRoot, WorkerToCore};
 use crate::hopr::types::SessionClientMetadata;
 use crate::hopr::{self, Hopr, HoprError, config as hopr_config, identity};
 use crate::route_health::{self, RouteHealth};
+use crate::transactions::{self, Kind as TxKind};
 use crate::worker_params::{self, WorkerParams};
-use crate::{balance, log_output, ticket_stats, wireguard};
+use crate::{
+    balance, budget, crash, diagnostics, dirs, exit_reports, gvpn_client, issue_report, log_output, metrics, peer,
+    public_ip, telemetry, ticket_stats, watchdog,
+};
 
+pub mod preflight;
 pub(crate) mod runner;
+mod stats;
+pub(crate) mod tasks;
 
 use runner::Results;
+use stats::LoopStats;
+use tasks::{Subsystem, Tasks};
 
 enum Responder {
     Unit(oneshot::Sender<Result<(), String>>),
     Str(oneshot::Sender<Result<String, String>>),
     Duration(oneshot::Sender<Result<Duration, String>>),
+    PublicIp(oneshot::Sender<Result<public_ip::PublicIp, String>>),
 }
 
 const NODE_WXHOPR_WITHDRAW_INTERVAL: Duration = Duration::from_secs(45);
+// changes are picked up from the chain watcher, revalidation only covers missed events
+const TICKET_STATS_REVALIDATE_INTERVAL: Duration = Duration::from_mins(30);
+
+/// Upper bound for a graceful hopr shutdown. Core gives up waiting afterwards so a stalled
+/// node cannot keep the worker (and with it routing teardown in root) hanging.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+/// Runners end on cancellation, this only covers one-off command lookups still in flight.
+const TASKS_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+// peers are refreshed right away when a connect finds the exit missing
+const NOT_READY_PEER_RETRY: Duration = Duration::from_secs(10);
+// channels are opened by the funding strategy, which takes a few capacity polls
+const NOT_READY_CHANNEL_RETRY: Duration = Duration::from_secs(60);
+/// Time before an announced exit retirement to move to another destination.
+const MAINTENANCE_MIGRATION_LEAD: Duration = Duration::from_mins(5);
+/// Time before an exit drops the registration to reconnect with a fresh one.
+const REGISTRATION_RENEWAL_LEAD: Duration = Duration::from_mins(1);
+/// Clocks drift and get corrected while running, e.g. after a suspend, so the skew is measured again.
+const CLOCK_SKEW_RECHECK_INTERVAL: Duration = Duration::from_mins(30);
+/// The owners of an external safe can change its module at any time.
+const EXTERNAL_SAFE_RECHECK_INTERVAL: Duration = Duration::from_mins(10);
 
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Configuration error: {0}")]
     Config(#[from] config::Error),
-    #[error("WireGuard error: {0}")]
-    WireGuard(#[from] wireguard::Error),
     #[error("HOPR error: {0}")]
     Hopr(#[from] HoprError),
     #[error("Hopr config error: {0}")]
@@ -60,6 +93,33 @@ pub enum Error {
     HoprParams(#[from] worker_params::Error),
     #[error("IncentiveOperations creation error: {0}")]
     IncentiveOperationsCreation(String),
+    #[error("Preflight check failed: {0}")]
+    Preflight(#[from] preflight::Error),
+}
+
+/// Node of a core loop, held outside of it so the worker can still shut the node down after the
+/// watchdog aborted the loop. Otherwise the next core would open the same database while the
+/// previous node is still running on it.
+#[derive(Clone, Default)]
+pub struct NodeHandle(Arc<Mutex<Option<Arc<Hopr>>>>);
+
+impl NodeHandle {
+    fn set(&self, hopr: Arc<Hopr>) {
+        if let Ok(mut node) = self.0.lock() {
+            *node = Some(hopr);
+        }
+    }
+
+    /// Shut the node down if one was started, giving up after [`SHUTDOWN_TIMEOUT`].
+    pub async fn shutdown(&self) {
+        let Some(hopr) = self.0.lock().ok().and_then(|mut node| node.take()) else {
+            return;
+        };
+        tracing::debug!("shutting down hopr of aborted core");
+        if time::timeout(SHUTDOWN_TIMEOUT, hopr.shutdown()).await.is_err() {
+            tracing::warn!(timeout = ?SHUTDOWN_TIMEOUT, "hopr shutdown did not finish in time - force closing");
+        }
+    }
 }
 
 pub struct Core {
@@ -78,10 +138,15 @@ pub struct Core {
     cancel_on_shutdown: CancellationToken,
     cancel_presafe_queries: CancellationToken,
     cancel_balances: CancellationToken,
+    cancel_ticket_stats: CancellationToken,
     cancel_announced_peers: CancellationToken,
+    cancel_funding_calculations: CancellationToken,
+    cancel_maintenance_migration: CancellationToken,
 
     // user provided data
     target_destination: Option<Destination>,
+    // remaining candidates of a failover connect, in order
+    failover: Vec<Destination>,
 
     // runtime data
     phase: Phase,
@@ -91,6 +156,11 @@ pub struct Core {
     ideal_balance_recommendation: Option<balance::BalanceRecommendation>,
     capacity_allocations: Option<HashMap<balance::CapacityAllocator, balance::Capacity>>,
     balances: Option<balance::Balances>,
+    ticket_stats: Option<ticket_stats::TicketStats>,
+    safe_module: Option<SafeModule>,
+    // missing permissions of the configured external safe module
+    module_issues: Vec<balance::ModuleIssue>,
+    announced_peers: Option<(SystemTime, HashMap<Address, peer::Peer>)>,
     strategy_handle: Option<AbortHandle>,
     route_healths: HashMap<String, RouteHealth>,
     next_request_id: u64,
@@ -103,7 +173,47 @@ pub struct Core {
     ongoing_disconnections: Vec<connection::down::Down>,
     cached_resolved_blokli_ips: Vec<net::Ipv4Addr>,
     reconnecting_since: Option<SystemTime>,
+    // failed attempts to re-establish a broken tunnel, spacing out the next one
+    reconnect_attempts: u32,
+    // backoff is running before the next reconnect attempt
+    reconnect_scheduled: bool,
+    // target was set during startup, announced once the connection actually starts
+    connect_queued: bool,
     pseudonym_cache: PseudonymCache,
+    // destination picked by `connect --any`, survives restarts
+    affinity: Affinity,
+    // exit registrations not yet unregistered, survives restarts
+    registrations: RegistrationStore,
+    // on-chain transactions sent by the client, survives restarts
+    transactions: transactions::Tracker,
+    budget: budget::Tracker,
+    // periodic balance snapshots, persisted across worker restarts
+    balance_history: balance_history::History,
+    // opt-in usage counters, persisted across worker restarts
+    telemetry: telemetry::Recorder,
+    // connection outcome reports to exits opted in via `report_url`, kept in memory for ctl
+    exit_reports: exit_reports::Reporter,
+    // recent connection attempts per destination, shared on request via `report-issue`
+    issue_reports: issue_report::Recorder,
+    // consecutive failures of rescheduled runners
+    retries: backoff::Retries,
+    // connecting is deferred until leftover sessions from a previous run are closed
+    closing_stale_sessions: bool,
+    // beaten by the event loop, watched by the worker
+    heartbeat: watchdog::Heartbeat,
+    // started node, shut down by the worker if the watchdog aborts the loop
+    node: NodeHandle,
+    // every spawned runner, counted per subsystem
+    tasks: Tasks,
+    loop_stats: LoopStats,
+    // local clock offset against chain time at the last chain endpoint check,
+    // only kept when large enough to break sessions
+    clock_skew: Option<preflight::ClockSkew>,
+    // kill switch block last requested from root, root engages it whenever there is no tunnel
+    kill_switch_engaged: bool,
+    // runners recorded instead of spawned, lets tests play the node and connection runners
+    #[cfg(test)]
+    mocked_runners: Option<tests::MockedRunners>,
 }
 
 #[derive(Debug, Clone)]
@@ -142,6 +252,34 @@ enum Phase {
     ShuttingDown,
 }
 
+impl Phase {
+    fn name(&self) -> &'static str {
+        match self {
+            Phase::Initial { .. } => "Initial",
+            Phase::CheckingSafe { .. } => "CheckingSafe",
+            Phase::DeployingSafe { .. } => "DeployingSafe",
+            Phase::Starting { .. } => "Starting",
+            Phase::HoprSyncing => "HoprSyncing",
+            Phase::HoprRunning => "HoprRunning",
+            Phase::Connecting(_) => "Connecting",
+            Phase::Connected(_) => "Connected",
+            Phase::ShuttingDown => "ShuttingDown",
+        }
+    }
+
+    /// Edge client is not running yet, connections can only be queued
+    fn is_starting(&self) -> bool {
+        matches!(
+            self,
+            Phase::Initial { .. }
+                | Phase::CheckingSafe { .. }
+                | Phase::DeployingSafe { .. }
+                | Phase::Starting { .. }
+                | Phase::HoprSyncing
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
 enum Querying<T> {
     Init,
@@ -156,11 +294,29 @@ impl Core {
         target_dest_id: Option<String>,
         outgoing_sender: mpsc::Sender<CoreToWorker>,
     ) -> Result<(Core, mpsc::Sender<WorkerToCore>), Error> {
-        wireguard::available().await?;
-        wireguard::executable().await?;
-        let keys = worker_params.persist_identity_generation().await?;
+        // fail fast on problems runners would otherwise retry forever
+        let keys = preflight::identity(&worker_params).await?;
         let node_address = keys.chain_key.public().to_address();
+        Ok(Self::new(
+            config,
+            worker_params,
+            node_address,
+            target_dest_id,
+            outgoing_sender,
+        ))
+    }
+
+    /// Assemble core state without touching the system, prerequisites are checked in [`Core::init`].
+    fn new(
+        config: Config,
+        worker_params: WorkerParams,
+        node_address: Address,
+        target_dest_id: Option<String>,
+        outgoing_sender: mpsc::Sender<CoreToWorker>,
+    ) -> (Core, mpsc::Sender<WorkerToCore>) {
         let cancel_on_shutdown = CancellationToken::new();
+        let tasks = Tasks::new();
+        let probe_permits = Arc::new(Semaphore::new(route_health::MAX_PARALLEL_HEALTH_CHECKS));
         let mut route_healths = HashMap::new();
         for (id, dest) in config.destinations.clone() {
             route_healths.insert(
@@ -170,6 +326,8 @@ impl Core {
                     worker_params.allow_insecure(),
                     worker_params.allow_experimental(),
                     cancel_on_shutdown.clone(),
+                    probe_permits.clone(),
+                    tasks.clone(),
                 ),
             );
         }
@@ -179,6 +337,31 @@ impl Core {
         let (incoming_sender, incoming_receiver) = mpsc::channel(32);
         let cached_resolved_blokli_ips = worker_params.cached_blokli_ips().to_vec();
         let pseudonym_cache = PseudonymCache::new(config.connection.session_pseudonym_ttl);
+        let affinity = Affinity::load(
+            dirs::cache_dir(worker_params.cache_home(), affinity::AFFINITY_FILE),
+            config.connection.affinity_ttl,
+        );
+        let budget = budget::Tracker::load(
+            dirs::cache_dir(worker_params.cache_home(), budget::BUDGET_FILE),
+            config.budget.clone(),
+        );
+        let telemetry = telemetry::Recorder::load(
+            dirs::cache_dir(worker_params.cache_home(), telemetry::TELEMETRY_FILE),
+            config.telemetry.enabled,
+        );
+        let retries = backoff::Retries::new(config.backoff);
+        let registrations = RegistrationStore::load(dirs::cache_dir(
+            worker_params.cache_home(),
+            registrations::REGISTRATIONS_FILE,
+        ));
+        let balance_history = balance_history::History::load(dirs::cache_dir(
+            worker_params.cache_home(),
+            balance_history::BALANCE_HISTORY_FILE,
+        ));
+        let transactions = transactions::Tracker::load(dirs::cache_dir(
+            worker_params.cache_home(),
+            transactions::TRANSACTIONS_FILE,
+        ));
         let core = Core {
             // config data
             config,
@@ -195,10 +378,14 @@ impl Core {
             cancel_on_shutdown: cancel_on_shutdown.clone(),
             cancel_presafe_queries: cancel_on_shutdown.child_token(),
             cancel_balances: cancel_on_shutdown.child_token(),
+            cancel_ticket_stats: cancel_on_shutdown.child_token(),
             cancel_announced_peers: cancel_on_shutdown.child_token(),
+            cancel_funding_calculations: cancel_on_shutdown.child_token(),
+            cancel_maintenance_migration: cancel_on_shutdown.child_token(),
 
             // user provided data
             target_destination,
+            failover: Vec::new(),
 
             // runtime data
             phase: Phase::Initial { last_error: None },
@@ -208,6 +395,10 @@ impl Core {
             ideal_balance_recommendation: None,
             capacity_allocations: None,
             balances: None,
+            ticket_stats: None,
+            safe_module: None,
+            module_issues: Vec::new(),
+            announced_peers: None,
             strategy_handle: None,
             ongoing_disconnections: Vec::new(),
             route_healths,
@@ -216,9 +407,234 @@ impl Core {
             // needed to keep working during enabled killswitch
             cached_resolved_blokli_ips,
             pseudonym_cache,
+            affinity,
+            registrations,
+            transactions,
             reconnecting_since: None,
+            reconnect_attempts: 0,
+            reconnect_scheduled: false,
+            connect_queued: false,
+            budget,
+            balance_history,
+            telemetry,
+            exit_reports: exit_reports::Reporter::default(),
+            issue_reports: issue_report::Recorder::default(),
+            retries,
+            closing_stale_sessions: false,
+            heartbeat: watchdog::Heartbeat::new(),
+            node: NodeHandle::default(),
+            tasks,
+            loop_stats: LoopStats::default(),
+            clock_skew: None,
+            kill_switch_engaged: true,
+            #[cfg(test)]
+            mocked_runners: None,
+        };
+        (core, incoming_sender)
+    }
+
+    fn run_mode(&self) -> RunMode {
+        match self.phase.clone() {
+            Phase::Initial { last_error } => RunMode::Init { last_error },
+            Phase::CheckingSafe {
+                node_balance,
+                query_safe,
+                funding_tool,
+                deploy_safe_error,
+            } => {
+                let balance = match node_balance {
+                    Querying::Success(ref b) => Some(b.clone()),
+                    _ => None,
+                };
+                let mut errors = "".to_string();
+                if let Querying::Error(err) = node_balance {
+                    errors = err
+                };
+                if let Querying::Error(err) = query_safe {
+                    errors = format!("{} {}", errors, err);
+                }
+                if let Some(deploy_err) = deploy_safe_error {
+                    errors = format!("{} {}", errors, deploy_err);
+                }
+                let funding_tool = match funding_tool {
+                    balance::FundingTool::NotStarted => None,
+                    balance::FundingTool::InProgress => Some("Funding tool running".to_string()),
+                    balance::FundingTool::CompletedSuccess => Some("Funding tool ran successfully".to_string()),
+                    balance::FundingTool::CompletedError(error) => Some(format!("Funding tool error: {error}")),
+                };
+                let error = if errors.is_empty() { None } else { Some(errors) };
+                RunMode::preparing_safe(
+                    self.node_address,
+                    &balance,
+                    funding_tool,
+                    error,
+                    self.minimum_balance_recommendation,
+                )
+            }
+            Phase::DeployingSafe {
+                node_balance: _,
+                query_safe: _,
+            } => RunMode::deploying_safe(self.node_address),
+            Phase::Starting {
+                edgli_init_state,
+                last_error,
+            } => RunMode::warmup(edgli_init_state, None, last_error),
+            Phase::HoprSyncing => RunMode::warmup(None, self.hopr.as_ref().map(|h| h.status()), None),
+            Phase::HoprRunning | Phase::Connecting(_) | Phase::Connected(_) => {
+                RunMode::running(self.hopr.as_ref().map(|h| h.status()), self.funding_issues())
+            }
+            Phase::ShuttingDown => RunMode::Shutdown,
+        }
+    }
+
+    /// `None` until balances and funding calculations are known, missing permissions of an
+    /// external safe module are reported regardless.
+    fn funding_issues(&self) -> Option<Vec<balance::FundingIssue>> {
+        let mut issues = match (
+            &self.ideal_balance_recommendation,
+            &self.capacity_allocations,
+            &self.balances,
+        ) {
+            (Some(ideal), Some(allocs), Some(bals)) => Some(balance::to_funding_issues(*ideal, allocs, bals.node_xdai)),
+            _ => None,
+        };
+        if !self.module_issues.is_empty() {
+            issues
+                .get_or_insert_default()
+                .insert(0, balance::FundingIssue::ModulePermissions);
+        }
+        issues
+    }
+
+    fn has_outgoing_channel(&self) -> bool {
+        self.capacity_allocations
+            .as_ref()
+            .is_some_and(|map| map.keys().any(|k| matches!(k, balance::CapacityAllocator::Peer(_))))
+    }
+
+    /// Route health is updated on polls and may be minutes old. Checks the latest peer and channel
+    /// data before a connect spawns the connection runner, which would otherwise fail much later.
+    ///
+    /// A direct route needs the node connected to the exit. Routes with hops reach the exit through a
+    /// relay, so they need an open channel to a peer the node is connected to.
+    fn reachability(&self, dest: &Destination) -> Result<(), command::NotReadyReason> {
+        let connected = self.connected_peers();
+        if dest.routing.hop_count() == 0 {
+            if !connected.contains(&dest.address) {
+                return Err(command::NotReadyReason::PeerNotSeen);
+            }
+            return Ok(());
+        }
+        let exit_announced = self
+            .announced_peers
+            .as_ref()
+            .is_some_and(|(_, peers)| peers.contains_key(&dest.address));
+        if !exit_announced {
+            return Err(command::NotReadyReason::PeerNotSeen);
+        }
+        let channel_to_connected = self
+            .capacity_allocations
+            .as_ref()
+            .is_some_and(|allocs| !balance::channel_peers(allocs).is_disjoint(&connected));
+        if !channel_to_connected {
+            return Err(command::NotReadyReason::NoChannel);
+        }
+        Ok(())
+    }
+
+    /// Peers the node held a transport connection to at the last peer poll.
+    fn connected_peers(&self) -> HashSet<Address> {
+        self.announced_peers
+            .as_ref()
+            .map(|(_, peers)| peers.values().filter(|p| p.connected).map(|p| p.address).collect())
+            .unwrap_or_default()
+    }
+
+    /// Ready and reachable destination, the one picked last time within the affinity TTL or else the
+    /// one with the fastest exit ping, ties are broken by id.
+    fn any_ready_destination(&mut self, avoid_failed: bool, rotate: bool, now: SystemTime) -> Option<Destination> {
+        let mut candidates: Vec<(Duration, &Destination)> = self
+            .config
+            .destinations
+            .values()
+            .filter_map(|dest| {
+                let rh = self.route_healths.get(&dest.id)?;
+                if avoid_failed && rh.failed_recently(now) {
+                    return None;
+                }
+                let exit = rh.ready_to_connect()?;
+                self.reachability(dest).ok()?;
+                Some((exit.ping_rtt, dest))
+            })
+            .collect();
+        candidates.sort_by(|(a_rtt, a), (b_rtt, b)| a_rtt.cmp(b_rtt).then_with(|| a.id.cmp(&b.id)));
+        let candidates: Vec<Destination> = candidates.into_iter().map(|(_, dest)| dest.clone()).collect();
+        self.affinity.choose(&candidates, rotate, now).cloned()
+    }
+
+    fn safe_check(&self) -> diagnostics::Check {
+        const NAME: &str = "safe";
+        if let Some(safe) = &self.safe_module {
+            return diagnostics::Check::pass(
+                NAME,
+                format!("safe {} with module {}", safe.safe_address, safe.module_address),
+            );
+        }
+        match &self.phase {
+            Phase::Initial { last_error: Some(e) } => {
+                diagnostics::Check::fail(NAME, format!("unable to look up the safe: {e}"))
+            }
+            Phase::CheckingSafe {
+                deploy_safe_error: Some(e),
+                ..
+            } => diagnostics::Check::fail(NAME, format!("safe deployment failed: {e}")),
+            Phase::DeployingSafe { .. } => diagnostics::Check::warn(NAME, "safe deployment in progress"),
+            _ => diagnostics::Check::warn(
+                NAME,
+                "no safe deployed yet - fund the node, see `gnosis_vpn-ctl balance`",
+            ),
+        }
+    }
+
+    fn peers_response(&self) -> command::PeersResponse {
+        let Some((updated_at, peers)) = &self.announced_peers else {
+            return command::PeersResponse {
+                updated_at: None,
+                peers: Vec::new(),
+            };
         };
-        Ok((core, incoming_sender))
+        let mut peers: Vec<command::PeerView> = peers
+            .values()
+            .filter(|p| p.connected)
+            .map(|p| {
+                let mut destinations: Vec<String> = self
+                    .config
+                    .destinations
+                    .values()
+                    .filter(|d| d.address == p.address)
+                    .map(|d| d.id.clone())
+                    .collect();
+                destinations.sort();
+                command::PeerView {
+                    address: p.address,
+                    ipv4_addrs: p.ipv4_addrs.clone(),
+                    score: p.quality.map(|q| q.score),
+                    latency: p.quality.and_then(|q| q.latency),
+                    channel: self
+                        .capacity_allocations
+                        .as_ref()
+                        .and_then(|allocs| allocs.get(&balance::CapacityAllocator::Peer(p.address)))
+                        .copied(),
+                    destinations,
+                }
+            })
+            .collect();
+        // peers we have a channel to or exit at first
+        peers.sort_by_key(|p| (p.channel.is_none(), p.destinations.is_empty(), p.address.to_string()));
+        command::PeersResponse {
+            updated_at: Some(*updated_at),
+            peers,
+        }
     }
 
     fn next_request_id(&mut self) -> u64 {
@@ -227,32 +643,81 @@ impl Core {
         id
     }
 
+    /// Daemon metrics of the worker side, appended to the edge client metrics.
+    fn write_metrics(&self, out: &mut String) {
+        self.tasks.write_metrics(out);
+        self.loop_stats.write_metrics(out);
+        metrics::write_labeled(
+            out,
+            "gnosis_vpn_core_runner_failures_total",
+            "Failures of rescheduled runners",
+            metrics::Kind::Counter,
+            "task",
+            self.retries
+                .total_failures()
+                .map(|(task, count)| (task.name(), count as f64)),
+        );
+        metrics::ProcessUsage::sample().write_metrics(out, "worker");
+    }
+
+    /// Heartbeat of the event loop, see [`watchdog`].
+    pub fn heartbeat(&self) -> watchdog::Heartbeat {
+        self.heartbeat.clone()
+    }
+
+    /// Node of the event loop, see [`NodeHandle`].
+    pub fn node(&self) -> NodeHandle {
+        self.node.clone()
+    }
+
     pub async fn start(mut self) {
         let (results_sender, mut results_receiver) = mpsc::channel(32);
+        let mut heartbeat = time::interval(watchdog::HEARTBEAT_INTERVAL);
         self.spawn_initial_runner(&results_sender, Duration::ZERO);
+        self.spawn_chain_endpoint_runner(&results_sender, Duration::ZERO);
         loop {
+            crash::set_phase(self.phase.name());
             tokio::select! {
                 // React to an incoming worker events
-                Some(event) = self.incoming_receiver.recv() => {
-                    if self.on_event(event, &results_sender).await {
-                        continue;
-                    } else {
+                event = self.incoming_receiver.recv() => match event {
+                    Some(event) => {
+                        let started = time::Instant::now();
+                        let sustain = self.on_event(event, &results_sender).await;
+                        self.sync_kill_switch().await;
+                        self.loop_stats.handled(started.elapsed());
+                        if sustain {
+                            continue;
+                        } else {
+                            break;
+                        }
+                    }
+                    None => {
+                        tracing::warn!("event receiver closed");
                         break;
                     }
-                }
+                },
 
                 // React to internal results from spawned runner tasks
                 Some(results) = results_receiver.recv() => {
-                    if self.on_results(results, &results_sender).await {
+                    let started = time::Instant::now();
+                    let sustain = self.on_results(results, &results_sender).await;
+                    self.sync_kill_switch().await;
+                    self.loop_stats.handled(started.elapsed());
+                    if sustain {
                         continue;
                     } else {
                         break;
                     }
                 }
 
-                else => {
-                    tracing::warn!("event receiver closed");
-                    break;
+                scheduled = heartbeat.tick() => {
+                    self.heartbeat.beat();
+                    self.loop_stats.tick(
+                        scheduled.elapsed(),
+                        self.incoming_receiver.len(),
+                        results_receiver.len(),
+                        self.outgoing_sender.max_capacity() - self.outgoing_sender.capacity(),
+                    );
                 }
             }
         }
@@ -280,8 +745,18 @@ impl Core {
                         hopr.shutdown().await;
                     });
                     shutdown_tracker.close();
-                    shutdown_tracker.wait().await;
+                    if time::timeout(SHUTDOWN_TIMEOUT, shutdown_tracker.wait()).await.is_err() {
+                        tracing::warn!(
+                            timeout = ?SHUTDOWN_TIMEOUT,
+                            "hopr shutdown did not finish in time - force closing"
+                        );
+                    }
+                }
+                let leaked = self.tasks.shutdown(TASKS_SHUTDOWN_TIMEOUT).await;
+                if !leaked.is_empty() {
+                    tracing::error!(?leaked, "runner tasks still alive after shutdown");
                 }
+                debug_assert!(leaked.is_empty(), "leaked runner tasks on shutdown: {leaked:?}");
                 false
             }
 
@@ -327,6 +802,32 @@ impl Core {
                             );
                         }
                     }
+                    ResponseFromRoot::PublicIp { request_id, res } => {
+                        if let Some(Responder::PublicIp(tx)) = self.responders.remove(&request_id) {
+                            let _ = tx.send(res).map_err(|_| {
+                                tracing::warn!("responder channel closed for public ip response");
+                            });
+                        } else {
+                            tracing::debug!(
+                                request_id,
+                                ?res,
+                                "no responder for public ip response (evicted or duplicate)"
+                            );
+                        }
+                    }
+                    ResponseFromRoot::ExitReport { request_id, res } => {
+                        if let Some(Responder::Unit(tx)) = self.responders.remove(&request_id) {
+                            let _ = tx.send(res).map_err(|_| {
+                                tracing::warn!("responder channel closed for exit report response");
+                            });
+                        } else {
+                            tracing::debug!(
+                                request_id,
+                                ?res,
+                                "no responder for exit report response (evicted or duplicate)"
+                            );
+                        }
+                    }
                 };
 
                 true
@@ -339,7 +840,7 @@ impl Core {
                 } else {
                     tracing::debug!(%cmd, "incoming command");
                 }
-                match cmd {
+                match self.resolve_failover(cmd) {
                     WorkerCommand::NerdStats => {
                         tracing::debug!("incoming nerd stats request");
                         let Some(ops) = self.incentive_operations.clone() else {
@@ -348,8 +849,12 @@ impl Core {
                             )));
                             return true;
                         };
+                        if let Some(stats) = self.ticket_stats {
+                            let _ = resp.send(self.nerd_stats_response(command::TicketStatsStatus::Available(stats)));
+                            return true;
+                        }
                         let sender = results_sender.clone();
-                        tokio::spawn(async move {
+                        self.tasks.spawn(Subsystem::Commands, async move {
                             let ticket_stats_status = match ops.ticket_stats().await {
                                 Ok(ts) => command::TicketStatsStatus::Available(ticket_stats::TicketStats {
                                     ticket_price: ts.ticket_price,
@@ -367,71 +872,7 @@ impl Core {
                     }
 
                     WorkerCommand::Status => {
-                        let runmode = match self.phase.clone() {
-                            Phase::Initial { last_error } => RunMode::Init { last_error },
-                            Phase::CheckingSafe {
-                                node_balance,
-                                query_safe,
-                                funding_tool,
-                                deploy_safe_error,
-                            } => {
-                                let balance = match node_balance {
-                                    Querying::Success(ref b) => Some(b.clone()),
-                                    _ => None,
-                                };
-                                let mut errors = "".to_string();
-                                if let Querying::Error(err) = node_balance {
-                                    errors = err
-                                };
-                                if let Querying::Error(err) = query_safe {
-                                    errors = format!("{} {}", errors, err);
-                                }
-                                if let Some(deploy_err) = deploy_safe_error {
-                                    errors = format!("{} {}", errors, deploy_err);
-                                }
-                                let funding_tool = match funding_tool {
-                                    balance::FundingTool::NotStarted => None,
-                                    balance::FundingTool::InProgress => Some("Funding tool running".to_string()),
-                                    balance::FundingTool::CompletedSuccess => {
-                                        Some("Funding tool ran successfully".to_string())
-                                    }
-                                    balance::FundingTool::CompletedError(error) => {
-                                        Some(format!("Funding tool error: {error}"))
-                                    }
-                                };
-                                let error = if errors.is_empty() { None } else { Some(errors) };
-                                RunMode::preparing_safe(
-                                    self.node_address,
-                                    &balance,
-                                    funding_tool,
-                                    error,
-                                    self.minimum_balance_recommendation,
-                                )
-                            }
-                            Phase::DeployingSafe {
-                                node_balance: _,
-                                query_safe: _,
-                            } => RunMode::deploying_safe(self.node_address),
-                            Phase::Starting {
-                                edgli_init_state,
-                                last_error,
-                            } => RunMode::warmup(edgli_init_state, None, last_error),
-                            Phase::HoprSyncing => RunMode::warmup(None, self.hopr.as_ref().map(|h| h.status()), None),
-                            Phase::HoprRunning | Phase::Connecting(_) | Phase::Connected(_) => {
-                                let funding_issues = match (
-                                    &self.ideal_balance_recommendation,
-                                    &self.capacity_allocations,
-                                    &self.balances,
-                                ) {
-                                    (Some(ideal), Some(allocs), Some(bals)) => {
-                                        Some(balance::to_funding_issues(*ideal, allocs, bals.node_xdai))
-                                    }
-                                    _ => None,
-                                };
-                                RunMode::running(self.hopr.as_ref().map(|h| h.status()), funding_issues)
-                            }
-                            Phase::ShuttingDown => RunMode::Shutdown,
-                        };
+                        let runmode = self.run_mode();
 
                         let active_conn_phase = match &self.phase {
                             Phase::Connecting(conn) => {
@@ -451,16 +892,24 @@ impl Core {
                         let connecting = if reconnecting.is_some() {
                             None
                         } else {
-                            active_conn_phase.map(|(dest_id, since, phase)| command::ConnectingInfo {
-                                destination_id: dest_id,
-                                since,
-                                phase,
+                            active_conn_phase.map(|(dest_id, since, phase)| {
+                                let estimate = phase.estimate(since.elapsed().unwrap_or_default());
+                                command::ConnectingInfo {
+                                    destination_id: dest_id,
+                                    since,
+                                    phase,
+                                    progress_percent: estimate.percent,
+                                    eta: estimate.eta,
+                                }
                             })
                         };
                         let connected = match &self.phase {
                             Phase::Connected(conn) => Some(command::ConnectedInfo {
                                 destination_id: conn.destination.id.clone(),
-                                since: conn.phase.0,
+                                connected_since: conn.phase.0,
+                                last_handshake: None,
+                                duration: conn.phase.0.elapsed().unwrap_or_default(),
+                                public_ip: conn.public_ip.clone(),
                             }),
                             _ => None,
                         };
@@ -490,6 +939,14 @@ impl Core {
                             reconnecting,
                             connected,
                             disconnecting,
+                            budget: self.budget.usage(),
+                            manual_channel_funding: self.config.manual_channel_funding(),
+                            retrying: self.retries.reported(),
+                            previous_crash: None,
+                            disk_space: None,
+                            conflicts: vec![],
+                            kill_switch_error: None,
+                            hints: vec![],
                         });
                         let _ = resp.send(res);
                     }
@@ -497,6 +954,7 @@ impl Core {
                     WorkerCommand::Connect(id) => match self.config.destinations.clone().get(&id) {
                         Some(dest) => {
                             self.reconnecting_since = None;
+                            self.connect_queued = false;
                             let is_already_active = match &self.phase {
                                 Phase::Connected(conn) | Phase::Connecting(conn) => conn.destination == *dest,
                                 _ => false,
@@ -505,8 +963,37 @@ impl Core {
                                 let _ = resp.send(Response::connect(command::ConnectResponse::already_connected(
                                     dest.clone(),
                                 )));
+                            } else if self.budget.blocks_connections()
+                                && let Some(usage) = self.budget.usage()
+                            {
+                                tracing::warn!(%usage, "refusing connection - daily budget exceeded");
+                                let _ = resp.send(Response::connect(command::ConnectResponse::budget_exceeded(usage)));
+                            } else if self.phase.is_starting() {
+                                let run_mode = self.run_mode();
+                                tracing::info!(%id, phase = self.phase.name(), "queueing connection until node is running");
+                                self.target_destination = Some(dest.clone());
+                                self.connect_queued = true;
+                                let _ = resp.send(Response::connect(command::ConnectResponse::queued(
+                                    dest.clone(),
+                                    run_mode,
+                                )));
                             } else if let Some(rh) = self.route_healths.get(&dest.id) {
-                                if rh.is_ready_to_connect() {
+                                if rh.is_ready_to_connect()
+                                    && let Err(reason) = self.reachability(dest)
+                                {
+                                    tracing::warn!(%id, %reason, "refusing connection - destination not reachable");
+                                    let retry_in = match reason {
+                                        command::NotReadyReason::PeerNotSeen => {
+                                            self.cancel_announced_peers.cancel();
+                                            self.cancel_announced_peers = self.cancel_on_shutdown.child_token();
+                                            self.spawn_announced_peers(results_sender, Duration::ZERO);
+                                            NOT_READY_PEER_RETRY
+                                        }
+                                        command::NotReadyReason::NoChannel => NOT_READY_CHANNEL_RETRY,
+                                    };
+                                    let _ = resp
+                                        .send(Response::connect(command::ConnectResponse::not_ready(reason, retry_in)));
+                                } else if rh.is_ready_to_connect() {
                                     let _ = resp
                                         .send(Response::connect(command::ConnectResponse::connecting(dest.clone())));
                                     self.target_destination = Some(dest.clone());
@@ -534,9 +1021,33 @@ impl Core {
                         }
                     },
 
+                    WorkerCommand::ConnectAny { avoid_failed, rotate } => {
+                        if let Phase::Connected(conn) | Phase::Connecting(conn) = &self.phase {
+                            let _ = resp.send(Response::connect(command::ConnectResponse::already_connected(
+                                conn.destination.clone(),
+                            )));
+                        } else if self.budget.blocks_connections()
+                            && let Some(usage) = self.budget.usage()
+                        {
+                            tracing::warn!(%usage, "refusing connection - daily budget exceeded");
+                            let _ = resp.send(Response::connect(command::ConnectResponse::budget_exceeded(usage)));
+                        } else if let Some(dest) = self.any_ready_destination(avoid_failed, rotate, SystemTime::now()) {
+                            tracing::info!(destination = %dest.id, rotate, "picked destination ready to connect");
+                            self.reconnecting_since = None;
+                            self.connect_queued = false;
+                            let _ = resp.send(Response::connect(command::ConnectResponse::connecting(dest.clone())));
+                            self.target_destination = Some(dest);
+                            self.act_on_target(results_sender);
+                        } else {
+                            tracing::info!(avoid_failed, "cannot connect - no destination ready");
+                            let _ = resp.send(Response::connect(command::ConnectResponse::none_ready()));
+                        }
+                    }
+
                     WorkerCommand::Disconnect => {
                         self.target_destination = None;
                         self.reconnecting_since = None;
+                        self.connect_queued = false;
                         self.cached_resolved_blokli_ips = Vec::new();
                         match self.phase.clone() {
                             Phase::Connected(conn) | Phase::Connecting(conn) => {
@@ -553,38 +1064,169 @@ impl Core {
                         self.act_on_target(results_sender);
                     }
 
+                    WorkerCommand::CancelConnect => {
+                        // queued or waiting targets count as pending attempts, established connections do not
+                        let pending = match &self.phase {
+                            Phase::Connecting(conn) => Some(conn.destination.clone()),
+                            Phase::Connected(_) => None,
+                            _ => self.target_destination.clone(),
+                        };
+                        match pending {
+                            Some(dest) => {
+                                tracing::info!(destination = %dest, "cancelling connection attempt");
+                                self.target_destination = None;
+                                self.reconnecting_since = None;
+                                self.connect_queued = false;
+                                self.cached_resolved_blokli_ips = Vec::new();
+                                if let Some(rh) = self.route_healths.get_mut(&dest.id) {
+                                    rh.connect_cancelled();
+                                }
+                                let _ = resp.send(Response::cancel_connect(command::CancelConnectResponse::cancelled(
+                                    dest,
+                                )));
+                                self.act_on_target(results_sender);
+                            }
+                            None => {
+                                tracing::debug!("no connection attempt to cancel");
+                                let _ = resp.send(Response::cancel_connect(
+                                    command::CancelConnectResponse::not_connecting(),
+                                ));
+                            }
+                        }
+                    }
+
                     WorkerCommand::Balance => {
                         let result = match (&self.hopr, &self.balances) {
-                            (Some(hopr), Some(balances)) => {
-                                let funding_issues =
-                                    match (&self.ideal_balance_recommendation, &self.capacity_allocations) {
-                                        (Some(ideal), Some(allocs)) => {
-                                            Some(balance::to_funding_issues(*ideal, allocs, balances.node_xdai))
-                                        }
-                                        _ => None,
-                                    };
-                                Ok(command::BalanceResponse::build(
-                                    &hopr.info(),
-                                    balances,
-                                    &self.config.destinations.clone(),
-                                    self.capacity_allocations.as_ref(),
-                                    self.ideal_balance_recommendation,
-                                    funding_issues,
-                                ))
-                            }
+                            (Some(hopr), Some(balances)) => Ok(command::BalanceResponse::build(
+                                &hopr.info(),
+                                balances,
+                                &self.config.destinations.clone(),
+                                self.capacity_allocations.as_ref(),
+                                self.ideal_balance_recommendation,
+                                self.funding_issues(),
+                            )),
                             _ => Err("balance data not yet available".to_string()),
                         };
                         let _ = resp.send(Response::Balance(result));
                     }
 
+                    WorkerCommand::Info => {
+                        let node = command::NodeIdentity {
+                            node_address: self.node_address,
+                            peer_id: self.hopr.as_ref().map(|hopr| hopr.info().node_peer_id),
+                            safe_address: self.safe_module.as_ref().map(|s| s.safe_address.clone()),
+                            module_address: self.safe_module.as_ref().map(|s| s.module_address.clone()),
+                            blokli_url: self.worker_params.blokli_url().map(|url| url.to_string()),
+                            identity_file: self.worker_params.identity_file(),
+                        };
+                        let _ = resp.send(Response::info(command::InfoResponse {
+                            version: env!("CARGO_PKG_VERSION").to_string(),
+                            log_file: None,
+                            package_version: None,
+                            config_file: None,
+                            state_home: Some(self.worker_params.state_home()),
+                            cache_home: Some(self.worker_params.cache_home()),
+                            node: Some(node),
+                        }));
+                    }
+
+                    WorkerCommand::Peers => {
+                        let _ = resp.send(Response::Peers(self.peers_response()));
+                    }
+
+                    WorkerCommand::UsageTelemetry(enable) => {
+                        if let Some(enable) = enable {
+                            tracing::info!(enable, "usage telemetry toggled");
+                            self.telemetry.set_enabled(enable);
+                        }
+                        let _ = resp.send(Response::UsageTelemetry(command::UsageTelemetryResponse {
+                            enabled: self.telemetry.enabled(),
+                            locked: self.telemetry.locked(),
+                            payload: self.telemetry.payload(),
+                        }));
+                    }
+
+                    WorkerCommand::BalanceHistory { days } => {
+                        let points = self.balance_history.since(days, SystemTime::now());
+                        let _ = resp.send(Response::BalanceHistory(points));
+                    }
+
+                    WorkerCommand::Transactions => {
+                        let _ = resp.send(Response::Transactions(self.transactions.list()));
+                    }
+
+                    WorkerCommand::ExitReports => {
+                        let _ = resp.send(Response::ExitReports(self.exit_reports.list()));
+                    }
+
+                    WorkerCommand::ReportIssue { destination } => {
+                        let res = match self.config.destinations.get(&destination) {
+                            Some(dest) => Ok(self.issue_reports.report(dest, SystemTime::now())),
+                            None => Err(format!("unknown destination: {destination}")),
+                        };
+                        let _ = resp.send(Response::ReportIssue(res));
+                    }
+
+                    WorkerCommand::Diagnose => {
+                        let safe = self.safe_check();
+                        let worker_params = self.worker_params.clone();
+                        let node_address = self.node_address;
+                        let url = hopr::blokli_url(self.worker_params.blokli_url());
+                        let proxy = self.config.network.https_proxy.clone();
+                        self.tasks.spawn(Subsystem::Commands, async move {
+                            let mut checks =
+                                preflight::diagnose(&worker_params, node_address, &url, proxy.as_ref()).await;
+                            checks.push(safe);
+                            let _ = resp.send(Response::Diagnose(diagnostics::Report { checks }));
+                        });
+                    }
+
+                    WorkerCommand::Sessions => {
+                        let Some(hopr) = self.hopr.clone() else {
+                            let _ = resp.send(Response::Sessions(None));
+                            return true;
+                        };
+                        let (bridge, ping) = match &self.phase {
+                            Phase::Connected(conn) | Phase::Connecting(conn) => {
+                                (conn.bridge_session.clone(), conn.ping_session.clone())
+                            }
+                            _ => (None, None),
+                        };
+                        self.tasks.spawn(Subsystem::Commands, async move {
+                            let mut sessions = Vec::new();
+                            for (ip_protocol, protocol) in [
+                                (IpProtocol::UDP, command::SessionProtocol::Udp),
+                                (IpProtocol::TCP, command::SessionProtocol::Tcp),
+                            ] {
+                                for meta in hopr.list_sessions(ip_protocol).await {
+                                    let role = if bridge.as_ref() == Some(&meta) {
+                                        Some(command::SessionRole::Bridge)
+                                    } else {
+                                        match &ping {
+                                            Some((kind, m)) if *m == meta => Some(match kind {
+                                                connection::up::SessionKind::Ping => command::SessionRole::Ping,
+                                                connection::up::SessionKind::Main => command::SessionRole::Main,
+                                            }),
+                                            _ => None,
+                                        }
+                                    };
+                                    sessions.push(command::SessionView::new(meta, protocol, role));
+                                }
+                            }
+                            let _ = resp.send(Response::Sessions(Some(sessions)));
+                        });
+                    }
+
                     WorkerCommand::Telemetry => {
-                        let res = match hopr::telemetry() {
-                            Ok(t) => Some(t),
+                        let mut metrics = match hopr::telemetry() {
+                            Ok(t) => t,
                             Err(err) => {
                                 tracing::error!(?err, "failed to collect hopr telemetry");
-                                None
+                                String::new()
                             }
                         };
+                        self.write_metrics(&mut metrics);
+                        let res = Some(metrics);
                         let _ = resp.send(Response::Telemetry(res));
                     }
 
@@ -668,27 +1310,36 @@ impl Core {
             Results::MinimumBalanceRecommendation { res } => match res {
                 Ok(rec) => {
                     tracing::info!(?rec, "received minimum balance recommendation");
+                    self.retries.succeeded(Task::MinimumBalanceRecommendation);
                     self.minimum_balance_recommendation = Some(rec);
                 }
                 Err(err) => {
-                    tracing::error!(?err, "failed to fetch minimum balance recommendation - retrying");
-                    self.spawn_minimum_balance_recommendation_runner(results_sender, Duration::from_secs(10));
+                    let delay = self.retry_delay(Task::MinimumBalanceRecommendation, &err);
+                    tracing::error!(
+                        ?err,
+                        ?delay,
+                        "failed to fetch minimum balance recommendation - retrying"
+                    );
+                    self.spawn_minimum_balance_recommendation_runner(results_sender, delay);
                 }
             },
             Results::IdealBalanceRecommendation { res } => match res {
                 Ok(rec) => {
                     tracing::info!(?rec, "received ideal balance recommendation");
+                    self.retries.succeeded(Task::IdealBalanceRecommendation);
                     self.ideal_balance_recommendation = Some(rec);
                     self.spawn_ideal_balance_recommendation_runner(results_sender, Duration::from_secs(60));
                 }
                 Err(err) => {
-                    tracing::warn!(?err, "failed to fetch ideal balance recommendation - retrying");
-                    self.spawn_ideal_balance_recommendation_runner(results_sender, Duration::from_secs(10));
+                    let delay = self.retry_delay(Task::IdealBalanceRecommendation, &err);
+                    tracing::warn!(?err, ?delay, "failed to fetch ideal balance recommendation - retrying");
+                    self.spawn_ideal_balance_recommendation_runner(results_sender, delay);
                 }
             },
             Results::CapacityAllocations { res } => match res {
                 Ok(allocations) => {
                     tracing::info!(count = allocations.len(), "received capacity allocations");
+                    self.retries.succeeded(Task::CapacityAllocations);
                     let has_channels = allocations
                         .keys()
                         .any(|k| matches!(k, balance::CapacityAllocator::Peer(_)));
@@ -711,24 +1362,62 @@ impl Core {
                     self.spawn_capacity_allocations_runner(results_sender, delay);
                 }
                 Err(err) => {
-                    tracing::warn!(?err, "failed to fetch capacity allocations - retrying");
-                    self.spawn_capacity_allocations_runner(results_sender, Duration::from_secs(10));
+                    let delay = self.retry_delay(Task::CapacityAllocations, &err);
+                    tracing::warn!(?err, ?delay, "failed to fetch capacity allocations - retrying");
+                    self.spawn_capacity_allocations_runner(results_sender, delay);
                 }
             },
             Results::Balances { res } => match res {
                 Ok(balances) => {
                     tracing::info!(%balances, "received balances from hopr");
+                    self.retries.succeeded(Task::Balances);
+                    self.balance_history.record(&balances, SystemTime::now());
+                    if self.budget.record(&balances, SystemTime::now()) {
+                        self.on_budget_exceeded(results_sender);
+                    }
                     self.balances = Some(balances);
-                    self.spawn_balances_runner(results_sender, Duration::from_secs(60));
+                    self.spawn_balances_runner(results_sender, self.config.balances.refresh_interval);
+                }
+                Err(err) => {
+                    let delay = self.retry_delay(Task::Balances, &err);
+                    tracing::error!(?err, ?delay, "failed to fetch balances from hopr - retrying");
+                    self.spawn_balances_runner(results_sender, delay);
+                }
+            },
+
+            Results::TicketStats { res } => match res {
+                Ok(stats) => {
+                    self.retries.succeeded(Task::TicketStats);
+                    let changed = self.ticket_stats.is_some_and(|previous| previous != stats);
+                    self.ticket_stats = Some(stats);
+                    if changed {
+                        tracing::info!(
+                            ?stats,
+                            "ticket stats changed on-chain - recomputing funding calculations"
+                        );
+                        self.restart_funding_calculations(results_sender);
+                    }
+                    self.spawn_ticket_stats_runner(results_sender, TICKET_STATS_REVALIDATE_INTERVAL);
                 }
                 Err(err) => {
-                    tracing::error!(?err, "failed to fetch balances from hopr - retrying");
-                    self.spawn_balances_runner(results_sender, Duration::from_secs(10));
+                    let delay = self.retry_delay(Task::TicketStats, &err);
+                    tracing::warn!(?err, ?delay, "failed to fetch ticket stats - retrying");
+                    self.spawn_ticket_stats_runner(results_sender, delay);
                 }
             },
 
-            Results::NodeBalance { res } => self.on_results_node_balance(res, results_sender).await,
-            Results::QuerySafe { res } => self.on_results_query_safe(res, results_sender).await,
+            Results::NodeBalance { res } => {
+                if res.is_ok() {
+                    self.retries.succeeded(Task::NodeBalance);
+                }
+                self.on_results_node_balance(res, results_sender).await
+            }
+            Results::QuerySafe { res } => {
+                if res.is_ok() {
+                    self.retries.succeeded(Task::QuerySafe);
+                }
+                self.on_results_query_safe(res, results_sender).await
+            }
             Results::DeploySafe { res } => self.on_results_deploy_safe(res, results_sender).await,
             Results::FundingTool { res } => self.on_results_funding_tool(res),
 
@@ -738,29 +1427,44 @@ impl Core {
                 }
                 Err(err) => {
                     tracing::error!(?err, "failed to persist safe module - retrying");
-                    self.spawn_store_safe(safe_module, results_sender, Duration::from_secs(10));
+                    self.spawn_store_safe(safe_module, results_sender, self.config.backoff.initial);
                 }
             },
 
             Results::Hopr { res, safe_module } => match res {
                 Ok(hopr) => {
                     tracing::info!("hopr runner started successfully");
+                    self.retries.succeeded(Task::Hopr);
                     self.phase = Phase::HoprSyncing;
-                    self.hopr = Some(Arc::new(hopr));
+                    let hopr = Arc::new(hopr);
+                    self.node.set(hopr.clone());
+                    self.hopr = Some(hopr);
                     self.spawn_node_wxhopr_withdraw_runner(results_sender, Duration::ZERO);
                     self.try_start_reactor(results_sender).await;
                     self.spawn_wait_for_running(results_sender, Duration::from_secs(1));
                 }
                 Err(err) => {
-                    tracing::error!(?err, "hopr runner failed to start - trying again in 10 seconds");
-                    self.retry_hopr_runner(err.to_string(), safe_module, results_sender, Duration::from_secs(10));
+                    let delay = self.retry_delay(Task::Hopr, &err);
+                    tracing::error!(?err, ?delay, "hopr runner failed to start - trying again");
+                    self.retry_hopr_runner(err.to_string(), safe_module, results_sender, delay);
                 }
             },
 
             // The runner retries indefinitely so res is always Ok; log just in case.
             Results::NodeWxhoprWithdraw { res } => {
-                if let Err(err) = res {
-                    tracing::error!(?err, "failed to withdraw node wxHOPR to safe");
+                match res {
+                    Ok(Some(withdrawal)) => {
+                        let safe_address = self.hopr.as_ref().map(|hopr| hopr.info().safe_address.to_string());
+                        self.transactions.sent(
+                            TxKind::WxhoprWithdrawal,
+                            Some(withdrawal.amount.to_string()),
+                            safe_address,
+                            Some(withdrawal.tx_hash),
+                            SystemTime::now(),
+                        );
+                    }
+                    Ok(None) => (),
+                    Err(err) => tracing::error!(?err, "failed to withdraw node wxHOPR to safe"),
                 }
                 self.spawn_node_wxhopr_withdraw_runner(results_sender, NODE_WXHOPR_WITHDRAW_INTERVAL);
             }
@@ -769,28 +1473,39 @@ impl Core {
                 self.on_hopr_running(results_sender);
             }
 
+            Results::StaleSessionsClosed { closed } => {
+                if closed > 0 {
+                    tracing::info!(closed, "closed stale sessions left over from a previous run");
+                }
+                self.closing_stale_sessions = false;
+                self.act_on_target(results_sender);
+            }
+
             Results::AnnouncedPeers { res } => match res {
                 Ok(peers) => {
-                    tracing::info!(num_peers = %peers.len(), "fetched announced peers");
-                    let all_peers = HashSet::from_iter(peers.keys().copied());
+                    let connected_peers: HashSet<Address> =
+                        peers.values().filter(|p| p.connected).map(|p| p.address).collect();
+                    tracing::info!(
+                        num_peers = %peers.len(),
+                        num_connected = %connected_peers.len(),
+                        "fetched announced peers"
+                    );
+                    self.retries.succeeded(Task::AnnouncedPeers);
                     let dest_ids: Vec<String> = self.route_healths.keys().cloned().collect();
-                    let channels_already_available = self
-                        .capacity_allocations
-                        .as_ref()
-                        .is_some_and(|map| map.keys().any(|k| matches!(k, balance::CapacityAllocator::Peer(_))));
-                    for (idx, id) in dest_ids.into_iter().enumerate() {
+                    let channels_already_available = self.has_outgoing_channel();
+                    for id in dest_ids {
                         if let Some(dest) = self.config.destinations.get(&id).cloned()
                             && let Some(rh) = self.route_healths.get_mut(&id)
                             && let Some(hopr) = self.hopr.clone()
                         {
-                            let stagger = Duration::from_millis((idx as u64).saturating_mul(500));
+                            // concurrency is bounded by the shared probe permits
                             rh.peers(
-                                &all_peers,
+                                &connected_peers,
                                 &hopr,
                                 &dest,
                                 &self.config.connection,
                                 results_sender,
-                                stagger,
+                                Duration::ZERO,
                             );
                             // If peers just moved this route into NeedsChannel and capacity
                             // allocations already show open channels, complete the transition
@@ -807,6 +1522,7 @@ impl Core {
                         .outgoing_sender
                         .send(CoreToWorker::RequestToRoot(RequestToRoot::UpdatePeerIps { peer_ips }))
                         .await;
+                    self.announced_peers = Some((SystemTime::now(), peers));
 
                     let delay = if self.target_destination.is_some()
                         || route_health::any_needs_peers(self.route_healths.values())
@@ -818,8 +1534,26 @@ impl Core {
                     self.spawn_announced_peers(results_sender, delay);
                 }
                 Err(err) => {
-                    tracing::error!(?err, "failed to fetch announced peers");
-                    self.spawn_announced_peers(results_sender, Duration::from_secs(10));
+                    let delay = self.retry_delay(Task::AnnouncedPeers, &err);
+                    tracing::error!(?err, ?delay, "failed to fetch announced peers");
+                    self.spawn_announced_peers(results_sender, delay);
+                }
+            },
+
+            Results::ChainEndpoint { res } => match res {
+                Ok(skew) => {
+                    self.retries.succeeded(Task::ChainEndpoint);
+                    let skew = skew.filter(preflight::ClockSkew::exceeds_threshold);
+                    if skew != self.clock_skew {
+                        tracing::info!(previous = ?self.clock_skew, current = ?skew, "clock skew changed");
+                    }
+                    self.clock_skew = skew;
+                    self.spawn_chain_endpoint_runner(results_sender, CLOCK_SKEW_RECHECK_INTERVAL);
+                }
+                Err(err) => {
+                    let delay = self.retry_delay(Task::ChainEndpoint, &err);
+                    tracing::warn!(%err, ?delay, "chain endpoint unreachable - checking again later");
+                    self.spawn_chain_endpoint_runner(results_sender, delay);
                 }
             },
 
@@ -835,10 +1569,21 @@ impl Core {
                                 };
                                 let _ = self.outgoing_sender.send(CoreToWorker::RequestToRoot(request)).await;
                             }
+                            // the exit now holds our public key - remember it until unregistered
+                            if let connection::up::Progress::OpenPing(_) = e.as_ref()
+                                && let Some(wg) = conn.wireguard.as_ref()
+                            {
+                                self.registrations
+                                    .insert(conn.destination.id.clone(), wg.key_pair.public_key.clone());
+                            }
                             conn.connect_progress(e);
+                            self.issue_reports
+                                .phase(&conn.destination.id, &conn.phase.1, conn.phase.0);
                             self.phase = Phase::Connecting(conn);
                         }
                         connection::up::Event::Setback(e) => {
+                            self.issue_reports
+                                .setback(&conn.destination.id, &e, &conn.phase.1, SystemTime::now());
                             if let Some(rh) = self.route_healths.get_mut(&conn.destination.id) {
                                 rh.with_error(e.to_string());
                             }
@@ -872,7 +1617,17 @@ impl Core {
             Results::ConnectionResult { res } => match (res, self.phase.clone()) {
                 (Ok(session), Phase::Connecting(mut conn)) => {
                     tracing::info!(%conn, "connection established successfully");
+                    self.telemetry
+                        .record_success(conn.started.elapsed().unwrap_or_default());
+                    self.exit_reports
+                        .record(&conn.destination, exit_reports::Outcome::Success, SystemTime::now());
+                    self.issue_reports.finished(
+                        &conn.destination.id,
+                        exit_reports::Outcome::Success,
+                        SystemTime::now(),
+                    );
                     self.reconnecting_since = None;
+                    self.reconnect_attempts = 0;
                     conn.connected();
                     self.phase = Phase::Connected(conn.clone());
                     self.pseudonym_cache.remove(&conn.destination);
@@ -884,6 +1639,12 @@ impl Core {
                     log_output::print_session_established(route.as_str());
                     self.spawn_session_monitoring(session, results_sender);
                     self.spawn_tunnel_ping_probe(results_sender);
+                    self.spawn_public_ip_lookup(results_sender);
+                    self.spawn_exit_reports(results_sender);
+                    self.schedule_registration_renewal(&conn, results_sender);
+                    if self.budget.throttles() {
+                        self.throttle_main_session();
+                    }
                     self.cancel_announced_peers.cancel();
                     self.cancel_announced_peers = self.cancel_on_shutdown.child_token();
                     self.spawn_announced_peers(results_sender, Duration::from_secs(10));
@@ -892,16 +1653,57 @@ impl Core {
                     tracing::warn!(?phase, "unawaited connection established successfully");
                 }
                 (Err(err), Phase::Connecting(conn)) => {
-                    tracing::error!(?err, %conn, "connection failed");
-                    self.reconnecting_since = None;
+                    let category = err.category();
+                    tracing::error!(?err, ?category, %conn, "connection failed");
+                    self.telemetry.record_failure();
+                    let outcome = exit_reports::Outcome::Failure {
+                        phase: conn.phase.1.clone(),
+                    };
+                    self.issue_reports
+                        .finished(&conn.destination.id, outcome.clone(), SystemTime::now());
+                    // sent through the next tunnel that comes up
+                    self.exit_reports.record(&conn.destination, outcome, SystemTime::now());
+                    let reconnecting_since = self.reconnecting_since.take();
+                    let mut error = err.to_string();
+                    if let Some(skew) = self.clock_skew {
+                        tracing::warn!(%skew, "connection failed with a skewed local clock - check the system time");
+                        error = format!("{error} ({skew})");
+                    }
                     if let Some(rh) = self.route_healths.get_mut(&conn.destination.id) {
-                        rh.with_error(err.to_string());
+                        rh.connection_failed(error, SystemTime::now());
                     }
-                    if let Some(dest) = self.target_destination.clone()
-                        && dest == conn.destination
-                    {
-                        tracing::info!(%dest, "restarting connection worker process due to final connection error");
-                        return false;
+                    let targeted = self.target_destination.as_ref() == Some(&conn.destination);
+                    match category {
+                        connection::up::ErrorCategory::Retryable
+                            if targeted
+                                && reconnecting_since.is_some()
+                                && self.reconnect_attempts < self.config.connection.reconnect.max_retries =>
+                        {
+                            self.reconnect_attempts += 1;
+                            let delay =
+                                route_health::jitter(self.config.connection.reconnect.delay(self.reconnect_attempts));
+                            tracing::warn!(destination = %conn.destination, attempt = self.reconnect_attempts, ?delay, "reconnect failed - retrying after backoff");
+                            self.reconnecting_since = reconnecting_since;
+                            self.spawn_reconnect_timer(results_sender, delay);
+                            self.disconnect_from_connection(&conn, results_sender);
+                        }
+                        // moving the target away disconnects from the failed attempt
+                        _ if targeted && !self.failover.is_empty() => self.fail_over(results_sender),
+                        // retrying cannot fix these, status shows the error until the user connects again
+                        connection::up::ErrorCategory::Terminal | connection::up::ErrorCategory::NeedsUserAction => {
+                            if targeted {
+                                tracing::warn!(destination = %conn.destination, ?category, "clearing target after connection error");
+                                self.target_destination = None;
+                                let request = RequestToRoot::TargetCleared;
+                                let _ = self.outgoing_sender.send(CoreToWorker::RequestToRoot(request)).await;
+                            }
+                            self.disconnect_from_connection(&conn, results_sender);
+                        }
+                        connection::up::ErrorCategory::Retryable if targeted => {
+                            tracing::info!(destination = %conn.destination, "restarting connection worker process due to final connection error");
+                            return false;
+                        }
+                        _ => (),
                     }
                 }
                 (Err(err), phase) => {
@@ -913,6 +1715,9 @@ impl Core {
                 match res {
                     Ok(_) => {
                         tracing::info!(%wg_public_key, "disconnected successful");
+                        self.registrations.remove(&wg_public_key);
+                        // session traffic was paid for with tickets - pick up the new channel balances
+                        self.refresh_balances(results_sender);
                     }
                     Err(err) => {
                         tracing::error!(?err, %wg_public_key, "disconnection failed");
@@ -947,6 +1752,19 @@ impl Core {
                 }
             }
 
+            Results::PublicIp { res } => match (res, &mut self.phase) {
+                (Ok(public_ip), Phase::Connected(conn)) => {
+                    tracing::info!(%public_ip, destination = %conn.destination.id, "resolved public ip");
+                    conn.public_ip = Some(public_ip);
+                }
+                (Ok(_), phase) => {
+                    tracing::debug!(?phase, "discarding public ip resolved after the connection ended");
+                }
+                (Err(error), _) => {
+                    tracing::warn!(%error, "unable to resolve public ip through the tunnel");
+                }
+            },
+
             Results::ConnectionRequestToRoot(respondable_request) => match respondable_request {
                 RunnerToRoot::KillswitchLockdown {
                     peer_ips,
@@ -984,6 +1802,32 @@ impl Core {
                     let request = RequestToRoot::Ping { request_id, options };
                     let _ = self.outgoing_sender.send(CoreToWorker::RequestToRoot(request)).await;
                 }
+
+                RunnerToRoot::PublicIp {
+                    endpoint,
+                    timeout,
+                    resp,
+                } => {
+                    let request_id = self.next_request_id();
+                    self.responders.insert(request_id, Responder::PublicIp(resp));
+                    let request = RequestToRoot::PublicIp {
+                        request_id,
+                        endpoint,
+                        timeout,
+                    };
+                    let _ = self.outgoing_sender.send(CoreToWorker::RequestToRoot(request)).await;
+                }
+
+                RunnerToRoot::ExitReport { url, report, resp } => {
+                    let request_id = self.next_request_id();
+                    self.responders.insert(request_id, Responder::Unit(resp));
+                    let request = RequestToRoot::ExitReport {
+                        request_id,
+                        url,
+                        report,
+                    };
+                    let _ = self.outgoing_sender.send(CoreToWorker::RequestToRoot(request)).await;
+                }
             },
 
             Results::HealthCheck { id, outcome } => {
@@ -999,42 +1843,134 @@ impl Core {
                         &self.config.connection,
                         results_sender,
                     );
+                    let targeted = self.target_destination.as_ref().is_some_and(|t| t.id == id);
                     // Trigger connection if we just became ready
                     if !was_ready && rh.is_ready_to_connect() {
                         self.act_on_target(results_sender);
+                    } else if targeted && rh.is_unrecoverable() && !self.failover.is_empty() {
+                        self.fail_over(results_sender);
                     }
                 }
+                self.schedule_maintenance_migration(results_sender);
             }
 
-            Results::RetryReactor => {
-                self.try_start_reactor(results_sender).await;
+            Results::MaintenanceDue => {
+                self.migrate_from_retiring_exit(results_sender);
             }
 
-            Results::NerdStatsTicketStats {
-                res: ticket_stats_status,
-                resp,
-            } => match &self.phase {
-                Phase::Connecting(conn) => {
-                    let conn_stats = command::ConnStats::from_conn(conn, self.node_address);
-                    let _ = resp.send(Response::nerd_stats(command::NerdStatsResponse::Connecting(
-                        ticket_stats_status,
-                        conn_stats,
-                    )));
-                }
-                Phase::Connected(conn) => {
-                    let conn_stats = command::ConnStats::from_conn(conn, self.node_address);
-                    let _ = resp.send(Response::nerd_stats(command::NerdStatsResponse::Connected(
-                        ticket_stats_status,
-                        conn_stats,
-                    )));
+            Results::ReconnectDue => {
+                self.reconnect_scheduled = false;
+                self.act_on_target(results_sender);
+            }
+
+            Results::RegistrationExpiring { expires_at } => match self.phase.clone() {
+                Phase::Connected(conn)
+                    if conn
+                        .registration
+                        .as_ref()
+                        .and_then(|reg| reg.capabilities().expires_at())
+                        == Some(expires_at) =>
+                {
+                    tracing::info!(%conn, "exit registration expires - reconnecting");
+                    self.reconnecting_since = Some(SystemTime::now());
+                    self.disconnect_from_connection(&conn, results_sender);
                 }
-                _ => {
-                    let _ = resp.send(Response::nerd_stats(command::NerdStatsResponse::NoInfo(
-                        ticket_stats_status,
-                    )));
+                phase => {
+                    tracing::debug!(?phase, "expiring registration no longer in use");
                 }
             },
-        };
+
+            Results::RetryReactor => {
+                self.try_start_reactor(results_sender).await;
+            }
+
+            Results::TelemetryDue => {
+                if let Some(url) = self.config.telemetry.usage_url.clone()
+                    && self.telemetry.has_data()
+                {
+                    let payload = self.telemetry.payload();
+                    let proxy = self.config.network.https_proxy.clone();
+                    let results_sender = results_sender.clone();
+                    let cancel = self.cancel_on_shutdown.clone();
+                    self.tasks.spawn(Subsystem::Telemetry, async move {
+                        cancel
+                            .run_until_cancelled(runner::telemetry_upload(url, payload, proxy, results_sender))
+                            .await
+                    });
+                } else {
+                    self.spawn_telemetry_timer(results_sender, telemetry::UPLOAD_INTERVAL);
+                }
+            }
+
+            Results::TelemetryUploaded { res } => {
+                match res {
+                    Ok(payload) => self.telemetry.uploaded(&payload),
+                    Err(err) => tracing::debug!(?err, "failed to upload usage telemetry - keeping counters"),
+                }
+                self.spawn_telemetry_timer(results_sender, telemetry::UPLOAD_INTERVAL);
+            }
+
+            Results::ExitReportSent { id, res } => {
+                if let Err(err) = &res {
+                    tracing::debug!(?err, id, "failed to send exit report");
+                }
+                self.exit_reports.delivered(id, res);
+            }
+
+            Results::ChainChange(change) => match change {
+                runner::ChainChange::ChannelFunded { destination, amount } => {
+                    tracing::debug!(%destination, %amount, "outgoing channel funded on-chain - refreshing balances");
+                    self.transactions.sent(
+                        TxKind::ChannelFunding,
+                        Some(amount.to_string()),
+                        Some(destination.to_string()),
+                        None,
+                        SystemTime::now(),
+                    );
+                    self.refresh_balances(results_sender);
+                }
+                runner::ChainChange::OwnChannel => {
+                    tracing::debug!("outgoing channel changed on-chain - refreshing balances");
+                    self.refresh_balances(results_sender);
+                }
+                runner::ChainChange::TicketStats => {
+                    tracing::debug!("ticket parameters changed on-chain - refreshing ticket stats");
+                    self.refresh_ticket_stats(results_sender);
+                }
+            },
+
+            Results::ExternalSafeCheck { res } => match res {
+                Ok(issues) => {
+                    self.retries.succeeded(Task::ExternalSafe);
+                    if issues.is_empty() {
+                        tracing::info!("external safe module grants the node the needed permissions");
+                    } else {
+                        let issues_text = issues.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+                        tracing::error!(issues = %issues_text, "external safe module lacks permissions for the node");
+                    }
+                    self.module_issues = issues;
+                    self.spawn_external_safe_check(results_sender, EXTERNAL_SAFE_RECHECK_INTERVAL);
+                }
+                Err(err) => {
+                    let delay = self.retry_delay(Task::ExternalSafe, &err);
+                    tracing::warn!(?err, ?delay, "failed to check external safe module - retrying");
+                    self.spawn_external_safe_check(results_sender, delay);
+                }
+            },
+
+            Results::ChainWatcherStopped { error } => {
+                let delay = self.config.backoff.initial;
+                tracing::warn!(%error, ?delay, "chain watcher stopped - restarting");
+                self.spawn_chain_watcher(results_sender, delay);
+            }
+
+            Results::NerdStatsTicketStats {
+                res: ticket_stats_status,
+                resp,
+            } => {
+                let _ = resp.send(self.nerd_stats_response(ticket_stats_status));
+            }
+        };
         return true;
     }
 
@@ -1097,14 +2033,15 @@ impl Core {
                     funding_tool,
                 },
             ) => {
-                tracing::error!(?err, "failed to fetch presafe node balance - retrying");
+                let delay = self.retry_delay(Task::NodeBalance, &err);
+                tracing::error!(?err, ?delay, "failed to fetch presafe node balance - retrying");
                 self.phase = Phase::CheckingSafe {
                     node_balance: Querying::Error(err.to_string()),
                     query_safe,
                     deploy_safe_error,
                     funding_tool,
                 };
-                self.spawn_node_balance_runner(results_sender, Duration::from_secs(10));
+                self.spawn_node_balance_runner(results_sender, delay);
             }
             (res, phase) => {
                 tracing::warn!(?res, ?phase, "ignoring presafe node balance result in unexpected phase");
@@ -1118,6 +2055,34 @@ impl Core {
         results_sender: &mpsc::Sender<Results>,
     ) {
         match (res, self.phase.clone()) {
+            (
+                Ok(Some(safe_module)),
+                Phase::CheckingSafe {
+                    node_balance,
+                    query_safe: _,
+                    deploy_safe_error,
+                    funding_tool,
+                },
+            ) if self
+                .config
+                .external_safe
+                .as_ref()
+                .is_some_and(|external| !external.matches(&safe_module)) =>
+            {
+                let error = format!(
+                    "node belongs to safe {} with module {} instead of the configured one",
+                    safe_module.safe_address, safe_module.module_address
+                );
+                tracing::error!(%error, "external safe verification failed - retrying");
+                self.phase = Phase::CheckingSafe {
+                    node_balance,
+                    query_safe: Querying::Error(error.clone()),
+                    deploy_safe_error,
+                    funding_tool,
+                };
+                let delay = self.retry_delay(Task::QuerySafe, &error);
+                self.spawn_query_safe_runner(results_sender, delay);
+            }
             (Ok(Some(safe_module)), Phase::CheckingSafe { .. }) => {
                 tracing::info!(?safe_module, "found safe module");
                 self.cancel_presafe_queries.cancel();
@@ -1136,7 +2101,11 @@ impl Core {
                     funding_tool,
                 },
             ) => {
-                tracing::info!("found no deployed safe module");
+                if self.config.external_safe.is_some() {
+                    tracing::warn!("configured safe module does not include this node yet - waiting for its owners");
+                } else {
+                    tracing::info!("found no deployed safe module");
+                }
                 self.phase = Phase::CheckingSafe {
                     node_balance,
                     query_safe: Querying::Success(None),
@@ -1156,14 +2125,15 @@ impl Core {
                     funding_tool,
                 },
             ) => {
-                tracing::error!(?err, "failed to query safe module - retrying");
+                let delay = self.retry_delay(Task::QuerySafe, &err);
+                tracing::error!(?err, ?delay, "failed to query safe module - retrying");
                 self.phase = Phase::CheckingSafe {
                     node_balance,
                     query_safe: Querying::Error(err.to_string()),
                     deploy_safe_error,
                     funding_tool,
                 };
-                self.spawn_query_safe_runner(results_sender, Duration::from_secs(10));
+                self.spawn_query_safe_runner(results_sender, delay);
             }
             (res, phase) => {
                 tracing::warn!(?res, ?phase, "ignoring query safe result in unexpected phase");
@@ -1176,6 +2146,16 @@ impl Core {
         res: Result<SafeModule, runner::Error>,
         results_sender: &mpsc::Sender<Results>,
     ) {
+        match &res {
+            Ok(safe_module) => self.transactions.confirmed(
+                TxKind::SafeDeployment,
+                Some(safe_module.safe_address.clone()),
+                SystemTime::now(),
+            ),
+            Err(err) => self
+                .transactions
+                .failed(TxKind::SafeDeployment, err.to_string(), SystemTime::now()),
+        }
         match (res, self.phase.clone()) {
             (Ok(safe_module), Phase::DeployingSafe { .. }) => {
                 tracing::info!(?safe_module, "deployed safe module");
@@ -1198,8 +2178,8 @@ impl Core {
                     deploy_safe_error: Some(err.to_string()),
                     funding_tool: balance::FundingTool::NotStarted,
                 };
-                self.spawn_node_balance_runner(results_sender, Duration::from_secs(10));
-                self.spawn_query_safe_runner(results_sender, Duration::from_secs(10));
+                self.spawn_node_balance_runner(results_sender, self.config.backoff.initial);
+                self.spawn_query_safe_runner(results_sender, self.config.backoff.initial);
             }
             (res, phase) => {
                 tracing::warn!(?res, ?phase, "ignoring deploy safe result in unexpected phase");
@@ -1286,22 +2266,81 @@ impl Core {
         }
     }
 
+    fn nerd_stats_response(&self, ticket_stats_status: command::TicketStatsStatus) -> Response {
+        match &self.phase {
+            Phase::Connecting(conn) => {
+                let conn_stats = command::ConnStats::from_conn(conn, self.node_address);
+                Response::nerd_stats(command::NerdStatsResponse::Connecting(ticket_stats_status, conn_stats))
+            }
+            Phase::Connected(conn) => {
+                let conn_stats = command::ConnStats::from_conn(conn, self.node_address);
+                Response::nerd_stats(command::NerdStatsResponse::Connected(ticket_stats_status, conn_stats))
+            }
+            _ => Response::nerd_stats(command::NerdStatsResponse::NoInfo(ticket_stats_status)),
+        }
+    }
+
+    fn retry_delay(&mut self, task: Task, err: &impl std::fmt::Display) -> Duration {
+        self.retries.failed(task, err.to_string(), SystemTime::now())
+    }
+
     fn spawn_initial_runner(&mut self, results_sender: &mpsc::Sender<Results>, delay: Duration) {
         let cancel = self.cancel_on_shutdown.clone();
         let worker_params = self.worker_params.clone();
         let blokli_config = self.config.blokli.clone();
+        let backoff = self.config.backoff;
+        let results_sender = results_sender.clone();
+        let tasks = self.tasks.clone();
+        self.tasks.spawn(Subsystem::Onboarding, async move {
+            cancel
+                .run_until_cancelled(async move {
+                    time::sleep(delay).await;
+                    runner::create_incentive_operations(
+                        &worker_params,
+                        blokli_config.into(),
+                        backoff,
+                        &tasks,
+                        results_sender,
+                    )
+                    .await;
+                })
+                .await
+        });
+    }
+
+    fn spawn_chain_endpoint_runner(&self, results_sender: &mpsc::Sender<Results>, delay: Duration) {
+        let cancel = self.cancel_on_shutdown.clone();
+        let url = hopr::blokli_url(self.worker_params.blokli_url());
+        let proxy = self.config.network.https_proxy.clone();
+        let backoff = self.config.backoff;
         let results_sender = results_sender.clone();
-        tokio::spawn(async move {
+        self.tasks.spawn(Subsystem::Onboarding, async move {
             cancel
                 .run_until_cancelled(async move {
                     time::sleep(delay).await;
-                    runner::create_incentive_operations(&worker_params, blokli_config.into(), results_sender).await;
+                    runner::chain_endpoint(url, proxy, backoff, results_sender).await;
                 })
                 .await
         });
     }
 
     async fn determine_next_phase_from_safe_disk_query(&mut self, results_sender: &mpsc::Sender<Results>) {
+        if let Some(external) = &self.config.external_safe {
+            tracing::info!(
+                safe_address = %external.safe_address,
+                module_address = %external.module_address,
+                "verifying externally managed safe module"
+            );
+            // no node balance query, nothing is ever deployed for an external safe
+            self.phase = Phase::CheckingSafe {
+                node_balance: Querying::Init,
+                query_safe: Querying::Init,
+                deploy_safe_error: None,
+                funding_tool: balance::FundingTool::NotStarted,
+            };
+            self.spawn_query_safe_runner(results_sender, Duration::ZERO);
+            return;
+        }
         let res = hopr_config::read_safe(self.worker_params.state_home()).await;
         match res {
             Ok(safe_module) => {
@@ -1332,13 +2371,14 @@ impl Core {
 
     fn spawn_query_safe_runner(&mut self, results_sender: &mpsc::Sender<Results>, delay: Duration) {
         let cancel = self.cancel_presafe_queries.clone();
+        let backoff = self.config.backoff;
         let results_sender = results_sender.clone();
         if let Some(incentive_operations) = self.incentive_operations.clone() {
-            tokio::spawn(async move {
+            self.tasks.spawn(Subsystem::Onboarding, async move {
                 cancel
                     .run_until_cancelled(async move {
                         time::sleep(delay).await;
-                        runner::query_safe(incentive_operations, results_sender).await
+                        runner::query_safe(incentive_operations, backoff, results_sender).await
                     })
                     .await
             });
@@ -1347,39 +2387,50 @@ impl Core {
 
     fn spawn_node_balance_runner(&self, results_sender: &mpsc::Sender<Results>, delay: Duration) {
         let cancel = self.cancel_presafe_queries.clone();
+        let backoff = self.config.backoff;
         let results_sender = results_sender.clone();
         if let Some(incentive_operations) = self.incentive_operations.clone() {
-            tokio::spawn(async move {
+            self.tasks.spawn(Subsystem::Onboarding, async move {
                 cancel
                     .run_until_cancelled(async move {
                         time::sleep(delay).await;
-                        runner::node_balance(incentive_operations, results_sender).await
+                        runner::node_balance(incentive_operations, backoff, results_sender).await
                     })
                     .await
             });
         }
     }
 
-    fn spawn_funding_runner(&self, secret: String, results_sender: &mpsc::Sender<Results>) {
+    fn spawn_funding_runner(&self, secret: command::Secret, results_sender: &mpsc::Sender<Results>) {
         let cancel = self.cancel_on_shutdown.clone();
         let worker_params = self.worker_params.clone();
+        let backoff = self.config.backoff;
+        let proxy = self.config.network.https_proxy.clone();
         let results_sender = results_sender.clone();
-        tokio::spawn(async move {
+        self.tasks.spawn(Subsystem::Onboarding, async move {
             cancel
-                .run_until_cancelled(async move { runner::funding_tool(worker_params, secret, results_sender).await })
+                .run_until_cancelled(async move {
+                    runner::funding_tool(worker_params, secret, backoff, proxy, results_sender).await
+                })
                 .await;
         });
     }
 
-    fn spawn_safe_deployment_runner(&self, presafe: &balance::PreSafe, results_sender: &mpsc::Sender<Results>) {
+    fn spawn_safe_deployment_runner(&mut self, presafe: &balance::PreSafe, results_sender: &mpsc::Sender<Results>) {
         let cancel = self.cancel_on_shutdown.clone();
         let presafe = presafe.clone();
+        let backoff = self.config.backoff;
         let results_sender = results_sender.clone();
         if let Some(incentive_operations) = self.incentive_operations.clone() {
-            tokio::spawn(async move {
+            self.transactions.started(
+                TxKind::SafeDeployment,
+                Some(presafe.node_wxhopr.to_string()),
+                SystemTime::now(),
+            );
+            self.tasks.spawn(Subsystem::Onboarding, async move {
                 cancel
                     .run_until_cancelled(async move {
-                        runner::safe_deployment(incentive_operations, presafe, results_sender).await;
+                        runner::safe_deployment(incentive_operations, presafe, backoff, results_sender).await;
                     })
                     .await
             });
@@ -1390,7 +2441,7 @@ impl Core {
         let cancel = self.cancel_on_shutdown.clone();
         let state_home = self.worker_params.state_home();
         let results_sender = results_sender.clone();
-        tokio::spawn(async move {
+        self.tasks.spawn(Subsystem::Onboarding, async move {
             cancel
                 .run_until_cancelled(async move {
                     time::sleep(delay).await;
@@ -1401,12 +2452,17 @@ impl Core {
     }
 
     fn spawn_hopr_runner(&mut self, safe_module: SafeModule, results_sender: &mpsc::Sender<Results>, delay: Duration) {
+        #[cfg(test)]
+        if let Some(mocked) = self.mocked_runners.as_mut() {
+            mocked.spawned.push(tests::MockedRunner::Hopr);
+            return;
+        }
         let cancel = self.cancel_on_shutdown.clone();
         let worker_params = self.worker_params.clone();
         let blokli_config = self.config.blokli.clone();
         let path_planner_min_ack_rate = self.config.connection.path_planner_min_ack_rate;
         let results_sender = results_sender.clone();
-        tokio::spawn(async move {
+        self.tasks.spawn(Subsystem::Node, async move {
             cancel
                 .run_until_cancelled(async move {
                     time::sleep(delay).await;
@@ -1424,6 +2480,7 @@ impl Core {
     }
 
     fn start_hopr_runner(&mut self, safe_module: SafeModule, results_sender: &mpsc::Sender<Results>, delay: Duration) {
+        self.safe_module = Some(safe_module.clone());
         self.phase = Phase::Starting {
             edgli_init_state: None,
             last_error: None,
@@ -1449,12 +2506,14 @@ impl Core {
         let cancel = self.cancel_on_shutdown.clone();
         let results_sender = results_sender.clone();
         let cfg = self.config.strategy.clone().into();
+        let backoff = self.config.backoff;
         if let Some(incentive_operations) = self.incentive_operations.clone() {
-            tokio::spawn(async move {
+            self.tasks.spawn(Subsystem::Funding, async move {
                 cancel
                     .run_until_cancelled(async move {
                         time::sleep(delay).await;
-                        runner::minimum_balance_recommendation(incentive_operations, cfg, results_sender).await;
+                        runner::minimum_balance_recommendation(incentive_operations, cfg, backoff, results_sender)
+                            .await;
                     })
                     .await
             });
@@ -1463,10 +2522,10 @@ impl Core {
 
     fn spawn_ideal_balance_recommendation_runner(&self, results_sender: &mpsc::Sender<Results>, delay: Duration) {
         if let Some(hopr) = self.hopr.clone() {
-            let cancel = self.cancel_on_shutdown.clone();
+            let cancel = self.cancel_funding_calculations.clone();
             let cfg = self.config.strategy.clone().into();
             let results_sender = results_sender.clone();
-            tokio::spawn(async move {
+            self.tasks.spawn(Subsystem::Funding, async move {
                 cancel
                     .run_until_cancelled(async move {
                         time::sleep(delay).await;
@@ -1479,9 +2538,9 @@ impl Core {
 
     fn spawn_capacity_allocations_runner(&self, results_sender: &mpsc::Sender<Results>, delay: Duration) {
         if let Some(hopr) = self.hopr.clone() {
-            let cancel = self.cancel_on_shutdown.clone();
+            let cancel = self.cancel_funding_calculations.clone();
             let results_sender = results_sender.clone();
-            tokio::spawn(async move {
+            self.tasks.spawn(Subsystem::Funding, async move {
                 cancel
                     .run_until_cancelled(async move {
                         time::sleep(delay).await;
@@ -1492,11 +2551,56 @@ impl Core {
         }
     }
 
+    fn spawn_ticket_stats_runner(&self, results_sender: &mpsc::Sender<Results>, delay: Duration) {
+        if let Some(incentive_operations) = self.incentive_operations.clone() {
+            let cancel = self.cancel_ticket_stats.clone();
+            let results_sender = results_sender.clone();
+            self.tasks.spawn(Subsystem::Funding, async move {
+                cancel
+                    .run_until_cancelled(async move {
+                        time::sleep(delay).await;
+                        runner::ticket_stats(incentive_operations, results_sender).await;
+                    })
+                    .await
+            });
+        }
+    }
+
+    fn spawn_external_safe_check(&self, results_sender: &mpsc::Sender<Results>, delay: Duration) {
+        if let (Some(external), Some(hopr)) = (self.config.external_safe.clone(), self.hopr.clone()) {
+            let cancel = self.cancel_on_shutdown.clone();
+            let results_sender = results_sender.clone();
+            self.tasks.spawn(Subsystem::Funding, async move {
+                cancel
+                    .run_until_cancelled(async move {
+                        time::sleep(delay).await;
+                        runner::external_safe_check(hopr, external, results_sender).await;
+                    })
+                    .await
+            });
+        }
+    }
+
+    /// Fetch ticket stats right away instead of waiting for the next revalidation.
+    fn refresh_ticket_stats(&mut self, results_sender: &mpsc::Sender<Results>) {
+        self.cancel_ticket_stats.cancel();
+        self.cancel_ticket_stats = self.cancel_on_shutdown.child_token();
+        self.spawn_ticket_stats_runner(results_sender, Duration::ZERO);
+    }
+
+    /// Recompute funding recommendations and capacity right away, e.g. after the ticket price changed.
+    fn restart_funding_calculations(&mut self, results_sender: &mpsc::Sender<Results>) {
+        self.cancel_funding_calculations.cancel();
+        self.cancel_funding_calculations = self.cancel_on_shutdown.child_token();
+        self.spawn_ideal_balance_recommendation_runner(results_sender, Duration::ZERO);
+        self.spawn_capacity_allocations_runner(results_sender, Duration::ZERO);
+    }
+
     fn spawn_balances_runner(&self, results_sender: &mpsc::Sender<Results>, delay: Duration) {
         if let Some(hopr) = self.hopr.clone() {
             let cancel = self.cancel_balances.clone();
             let results_sender = results_sender.clone();
-            tokio::spawn(async move {
+            self.tasks.spawn(Subsystem::Funding, async move {
                 cancel
                     .run_until_cancelled(async move {
                         time::sleep(delay).await;
@@ -1507,16 +2611,45 @@ impl Core {
         }
     }
 
+    fn spawn_chain_watcher(&self, results_sender: &mpsc::Sender<Results>, delay: Duration) {
+        if let Some(hopr) = self.hopr.clone() {
+            let cancel = self.cancel_on_shutdown.clone();
+            let results_sender = results_sender.clone();
+            self.tasks.spawn(Subsystem::Funding, async move {
+                cancel
+                    .run_until_cancelled(async move {
+                        time::sleep(delay).await;
+                        runner::chain_watcher(hopr, results_sender).await;
+                    })
+                    .await
+            });
+        }
+    }
+
+    /// Fetch balances right away instead of waiting for the next scheduled refresh.
+    fn refresh_balances(&mut self, results_sender: &mpsc::Sender<Results>) {
+        if self.hopr.is_none() {
+            return;
+        }
+        self.cancel_balances.cancel();
+        self.cancel_balances = self.cancel_on_shutdown.child_token();
+        self.spawn_balances_runner(results_sender, Duration::ZERO);
+    }
+
     fn spawn_node_wxhopr_withdraw_runner(&self, results_sender: &mpsc::Sender<Results>, delay: Duration) {
+        // funds of an externally managed safe are left to its owners
+        if self.config.external_safe.is_some() {
+            return;
+        }
         if let (Some(ops), Some(hopr)) = (self.incentive_operations.clone(), self.hopr.clone()) {
-            let safe_address = hopr.info().safe_address;
             let cancel = self.cancel_node_wxhopr.clone();
+            let backoff = self.config.backoff;
             let results_sender = results_sender.clone();
-            tokio::spawn(async move {
+            self.tasks.spawn(Subsystem::Funding, async move {
                 cancel
                     .run_until_cancelled(async move {
                         time::sleep(delay).await;
-                        runner::node_wxhopr_withdraw(ops, safe_address, results_sender).await;
+                        runner::node_wxhopr_withdraw(ops, hopr, backoff, results_sender).await;
                     })
                     .await
             });
@@ -1527,7 +2660,7 @@ impl Core {
         if let Some(hopr) = self.hopr.clone() {
             let cancel = self.cancel_on_shutdown.clone();
             let results_sender = results_sender.clone();
-            tokio::spawn(async move {
+            self.tasks.spawn(Subsystem::Node, async move {
                 cancel
                     .run_until_cancelled(async move {
                         time::sleep(delay).await;
@@ -1538,11 +2671,25 @@ impl Core {
         }
     }
 
+    fn spawn_stale_sessions_runner(&mut self, results_sender: &mpsc::Sender<Results>) {
+        if let Some(hopr) = self.hopr.clone() {
+            self.closing_stale_sessions = true;
+            let destinations = self.config.destinations.values().map(|d| d.address).collect();
+            let cancel = self.cancel_on_shutdown.clone();
+            let results_sender = results_sender.clone();
+            self.tasks.spawn(Subsystem::Node, async move {
+                cancel
+                    .run_until_cancelled(runner::close_stale_sessions(hopr, destinations, results_sender))
+                    .await
+            });
+        }
+    }
+
     fn spawn_announced_peers(&self, results_sender: &mpsc::Sender<Results>, delay: Duration) {
         if let Some(hopr) = self.hopr.clone() {
             let cancel = self.cancel_announced_peers.clone();
             let results_sender = results_sender.clone();
-            tokio::spawn(async move {
+            self.tasks.spawn(Subsystem::Peers, async move {
                 cancel
                     .run_until_cancelled(async move {
                         time::sleep(delay).await;
@@ -1560,9 +2707,19 @@ impl Core {
         prev_public_key: Option<String>,
         results_sender: &mpsc::Sender<Results>,
     ) {
+        #[cfg(test)]
+        if let Some(mocked) = self.mocked_runners.as_mut()
+            && mocked.node_started
+        {
+            mocked
+                .spawned
+                .push(tests::MockedRunner::Connection(destination.id.clone()));
+            self.phase = Phase::Connecting(connection::up::Up::new(destination, exit.api_version()));
+            return;
+        }
         if let Some(hopr) = self.hopr.clone() {
             let cancel = self.cancel_connection.clone();
-            let conn = connection::up::Up::new(destination.clone());
+            let conn = connection::up::Up::new(destination.clone(), exit.api_version());
             let config_connection = self.config.connection.clone();
             let config_wireguard = self.config.wireguard.clone();
             let hopr = hopr.clone();
@@ -1578,11 +2735,14 @@ impl Core {
             };
             let runner = connection::up::runner::Runner::new(
                 conn.destination.clone(),
+                conn.api_version,
                 config_connection,
+                self.config.backoff,
                 config_wireguard,
                 hopr,
                 self.worker_params.clone(),
                 prev_conn,
+                self.tasks.clone(),
             );
             let results_sender = results_sender.clone();
             if let Some(rh) = self.route_healths.get_mut(&destination.id) {
@@ -1594,8 +2754,9 @@ impl Core {
                     &results_sender,
                 );
             }
+            self.issue_reports.started(&destination.id, conn.started);
             self.phase = Phase::Connecting(conn);
-            tokio::spawn(async move {
+            self.tasks.spawn(Subsystem::Connection, async move {
                 cancel
                     .run_until_cancelled(async move {
                         runner.start(results_sender).await;
@@ -1606,6 +2767,16 @@ impl Core {
     }
 
     fn spawn_disconnection_runner(&mut self, disconn: &connection::down::Down, results_sender: &mpsc::Sender<Results>) {
+        #[cfg(test)]
+        if let Some(mocked) = self.mocked_runners.as_mut()
+            && mocked.node_started
+        {
+            mocked
+                .spawned
+                .push(tests::MockedRunner::Disconnection(disconn.wg_public_key.clone()));
+            self.ongoing_disconnections.push(disconn.clone());
+            return;
+        }
         if let Some(hopr) = self.hopr.clone() {
             let cancel = self.cancel_on_shutdown.clone();
             let config_connection = self.config.connection.clone();
@@ -1614,7 +2785,7 @@ impl Core {
             let results_sender = results_sender.clone();
             self.ongoing_disconnections.push(disconn.clone());
             let outgoing_sender = self.outgoing_sender.clone();
-            tokio::spawn(async move {
+            self.tasks.spawn(Subsystem::Connection, async move {
                 // this is a oneshot command and we do not wait for any result
                 let _ = outgoing_sender
                     .send(CoreToWorker::RequestToRoot(RequestToRoot::TearDownWg))
@@ -1632,7 +2803,7 @@ impl Core {
         if let Some(hopr) = self.hopr.clone() {
             let cancel = self.cancel_connection.clone();
             let results_sender = results_sender.clone();
-            tokio::spawn(async move {
+            self.tasks.spawn(Subsystem::Connection, async move {
                 cancel
                     .run_until_cancelled(async move {
                         runner::monitor_session(hopr, &session, results_sender).await;
@@ -1646,7 +2817,7 @@ impl Core {
         let interval = self.config.connection.health_check_intervals.tunnel_ping;
         let cancel = self.cancel_connection.clone();
         let results_sender = results_sender.clone();
-        tokio::spawn(async move {
+        self.tasks.spawn(Subsystem::Connection, async move {
             cancel
                 .run_until_cancelled(async move {
                     runner::tunnel_ping_loop(interval, results_sender).await;
@@ -1655,16 +2826,43 @@ impl Core {
         });
     }
 
+    fn spawn_public_ip_lookup(&self, results_sender: &mpsc::Sender<Results>) {
+        let Some(endpoint) = self.config.connection.public_ip_endpoint.clone() else {
+            return;
+        };
+        let timeout = self.config.connection.timeouts.http;
+        let cancel = self.cancel_connection.clone();
+        let results_sender = results_sender.clone();
+        self.tasks.spawn(Subsystem::Connection, async move {
+            cancel
+                .run_until_cancelled(async move {
+                    runner::resolve_public_ip(endpoint, timeout, results_sender).await;
+                })
+                .await
+        });
+    }
+
     #[tracing::instrument(skip(self, results_sender), level = "debug", ret)]
     fn act_on_target(&mut self, results_sender: &mpsc::Sender<Results>) {
         tracing::debug!(target = ?self.target_destination, phase = ?self.phase, "acting on target destination");
         match (self.target_destination.clone(), self.phase.clone()) {
             // Connecting from ready
+            (Some(dest), Phase::HoprRunning) if self.closing_stale_sessions => {
+                tracing::debug!(destination = %dest, "deferring connection until stale sessions are closed");
+            }
+            (Some(dest), Phase::HoprRunning) if self.reconnect_scheduled && self.reconnecting_since.is_some() => {
+                tracing::debug!(destination = %dest, "deferring reconnect until backoff elapsed");
+            }
             (Some(dest), Phase::HoprRunning) => {
                 if let Some(rh) = self.route_healths.get(&dest.id) {
                     if let Some(exit) = rh.ready_to_connect() {
+                        if std::mem::take(&mut self.connect_queued) {
+                            log_output::print_queued_connection_started(&dest.id);
+                        }
                         tracing::info!(destination = %dest, "establishing connection to new destination");
                         self.spawn_connection_runner(dest.clone(), exit, None, results_sender);
+                    } else if rh.is_unrecoverable() && !self.failover.is_empty() {
+                        self.fail_over(results_sender);
                     } else if rh.is_unrecoverable() {
                         tracing::error!(destination = %dest,route_health = ?rh.state(),  "refusing connection because of route health");
                     } else {
@@ -1699,6 +2897,72 @@ impl Core {
         }
     }
 
+    /// Turns a failover connect, or a connect to a destination group, into a connect to its first
+    /// usable candidate and keeps the others. Any other connect or disconnect drops kept candidates.
+    fn resolve_failover(&mut self, cmd: WorkerCommand) -> WorkerCommand {
+        let ids = match cmd {
+            WorkerCommand::ConnectFailover(ids) => ids,
+            WorkerCommand::Connect(ref id)
+                if !self.config.destinations.contains_key(id) && self.config.destination_groups.contains_key(id) =>
+            {
+                vec![id.clone()]
+            }
+            WorkerCommand::Connect(_)
+            | WorkerCommand::ConnectAny { .. }
+            | WorkerCommand::Disconnect
+            | WorkerCommand::CancelConnect => {
+                self.failover.clear();
+                return cmd;
+            }
+            cmd => return cmd,
+        };
+        let mut candidates = self.config.failover_candidates(&ids);
+        // with every candidate unusable the first one answers why
+        if let Some(usable) = candidates
+            .iter()
+            .position(|dest| !self.route_healths.get(&dest.id).is_some_and(|rh| rh.is_unrecoverable()))
+        {
+            candidates.drain(..usable);
+        }
+        if candidates.is_empty() {
+            self.failover.clear();
+            return WorkerCommand::Connect(ids.into_iter().next().unwrap_or_default());
+        }
+        let first = candidates.remove(0);
+        tracing::info!(destination = %first, fallbacks = candidates.len(), "connecting with failover");
+        self.failover = candidates;
+        WorkerCommand::Connect(first.id)
+    }
+
+    /// Moves the target on to the next failover candidate.
+    fn fail_over(&mut self, results_sender: &mpsc::Sender<Results>) {
+        if self.failover.is_empty() {
+            return;
+        }
+        let next = self.failover.remove(0);
+        tracing::warn!(from = ?self.target_destination.as_ref().map(|d| &d.id), to = %next, remaining = self.failover.len(), "failing over to next destination");
+        self.target_destination = Some(next);
+        self.reconnecting_since = None;
+        self.reconnect_attempts = 0;
+        self.act_on_target(results_sender);
+    }
+
+    /// Lifts the kill switch block once connected and engages it again as soon as the tunnel is gone.
+    async fn sync_kill_switch(&mut self) {
+        if !self.config.connection.kill_switch {
+            return;
+        }
+        let engage = !matches!(self.phase, Phase::Connected(_));
+        if engage == self.kill_switch_engaged {
+            return;
+        }
+        self.kill_switch_engaged = engage;
+        let _ = self
+            .outgoing_sender
+            .send(CoreToWorker::RequestToRoot(RequestToRoot::KillSwitch { engage }))
+            .await;
+    }
+
     fn disconnect_from_connection(&mut self, conn: &connection::up::Up, results_sender: &mpsc::Sender<Results>) {
         // Cache the pseudonym so a reconnect within the TTL window can reuse exit node SURBs.
         if let Some((_, session)) = &conn.ping_session
@@ -1712,14 +2976,10 @@ impl Core {
         self.responders.clear();
         self.phase = Phase::HoprRunning;
         if let Some(dest) = self.config.destinations.get(&conn.destination.id).cloned()
+            && let Some(hopr) = self.hopr.as_ref()
             && let Some(rh) = self.route_healths.get_mut(&conn.destination.id)
         {
-            rh.disconnecting(
-                self.hopr.as_ref().unwrap(),
-                &dest,
-                &self.config.connection,
-                results_sender,
-            );
+            rh.disconnecting(hopr, &dest, &self.config.connection, results_sender);
         }
         if let Ok(disconn) = conn.try_into() {
             self.spawn_disconnection_runner(&disconn, results_sender);
@@ -1766,11 +3026,68 @@ impl Core {
         }
     }
 
+    fn on_budget_exceeded(&mut self, results_sender: &mpsc::Sender<Results>) {
+        let usage = self.budget.usage();
+        match self.budget.action() {
+            budget::Action::Warn => {
+                tracing::warn!(?usage, "daily budget exceeded");
+            }
+            budget::Action::Throttle => {
+                tracing::warn!(?usage, "daily budget exceeded - throttling main session");
+                self.throttle_main_session();
+            }
+            budget::Action::Disconnect => {
+                tracing::warn!(?usage, "daily budget exceeded - disconnecting");
+                self.target_destination = None;
+                self.failover.clear();
+                self.reconnecting_since = None;
+                self.act_on_target(results_sender);
+            }
+        }
+    }
+
+    fn throttle_main_session(&self) {
+        let Phase::Connected(conn) = &self.phase else {
+            return;
+        };
+        let (Some(hopr), Some((_, session))) = (self.hopr.clone(), conn.ping_session.as_ref()) else {
+            return;
+        };
+        let buffer = self.config.connection.surb_balancing.main.buffer;
+        let cfg = match connection::options::to_surb_balancer_config(buffer, self.budget.throttle_max_surb_upstream()) {
+            Ok(cfg) => cfg,
+            Err(err) => {
+                tracing::error!(?err, "invalid throttle surb configuration");
+                return;
+            }
+        };
+        let Some(client) = session.active_clients.first().cloned() else {
+            return;
+        };
+        let cancel = self.cancel_connection.clone();
+        self.tasks.spawn(Subsystem::Connection, async move {
+            cancel
+                .run_until_cancelled(async move {
+                    if let Err(err) = hopr.adjust_session(cfg, client).await {
+                        tracing::error!(?err, "failed to throttle main session");
+                    }
+                })
+                .await
+        });
+    }
+
     fn on_hopr_running(&mut self, results_sender: &mpsc::Sender<Results>) {
         self.phase = Phase::HoprRunning;
+        self.spawn_stale_sessions_runner(results_sender);
+        self.unregister_orphans(results_sender);
         self.spawn_ideal_balance_recommendation_runner(results_sender, Duration::ZERO);
         self.spawn_capacity_allocations_runner(results_sender, Duration::ZERO);
         self.spawn_balances_runner(results_sender, Duration::ZERO);
+        self.spawn_ticket_stats_runner(results_sender, Duration::ZERO);
+        self.spawn_telemetry_timer(results_sender, telemetry::UPLOAD_INTERVAL);
+        self.spawn_chain_watcher(results_sender, Duration::ZERO);
+        self.spawn_external_safe_check(results_sender, Duration::ZERO);
+        self.probe_destinations(results_sender);
         if route_health::any_needs_peers(self.route_healths.values()) {
             self.spawn_announced_peers(results_sender, Duration::ZERO);
         } else {
@@ -1778,10 +3095,66 @@ impl Core {
         }
     }
 
+    /// Checks every routable destination at once when the node comes up, instead of waiting for
+    /// each route's next health check. Config reloads restart the worker and end up here as well.
+    /// Routes still waiting for peers are probed from the immediate peers fetch, the shared probe
+    /// permits bound how many checks run in parallel.
+    fn probe_destinations(&mut self, results_sender: &mpsc::Sender<Results>) {
+        let Some(hopr) = self.hopr.clone() else {
+            return;
+        };
+        for (id, rh) in self.route_healths.iter_mut() {
+            if let Some(dest) = self.config.destinations.get(id) {
+                rh.probe(&hopr, dest, &self.config.connection, results_sender);
+            }
+        }
+    }
+
+    /// Unregister keys left behind at exits by a previous run that did not shut down cleanly.
+    /// Adopting them is not possible as the matching private keys are never persisted.
+    fn unregister_orphans(&mut self, results_sender: &mpsc::Sender<Results>) {
+        for record in self.registrations.records().to_vec() {
+            if self
+                .ongoing_disconnections
+                .iter()
+                .any(|d| d.wg_public_key == record.public_key)
+            {
+                continue;
+            }
+            match self.config.destinations.get(&record.destination_id).cloned() {
+                Some(destination) => {
+                    tracing::info!(destination = %record.destination_id, "unregistering orphaned exit registration");
+                    let api_version = self
+                        .route_healths
+                        .get(&record.destination_id)
+                        .and_then(|rh| rh.current_exit_health())
+                        .map(|exit| exit.api_version())
+                        .unwrap_or(gvpn_client::ApiVersion::V1);
+                    let disconn = connection::down::Down {
+                        destination,
+                        phase: (SystemTime::now(), connection::down::Phase::Disconnecting),
+                        wg_public_key: record.public_key,
+                        api_version,
+                    };
+                    self.spawn_disconnection_runner(&disconn, results_sender);
+                }
+                None => {
+                    tracing::warn!(destination = %record.destination_id, "dropping registration for unknown destination");
+                    self.registrations.remove(&record.public_key);
+                }
+            }
+        }
+    }
+
     async fn try_start_reactor(&mut self, results_sender: &mpsc::Sender<Results>) {
         if self.strategy_handle.is_some() {
             return;
         }
+        // the strategy opens and tops up channels, left to the user or the owners of an external safe
+        if self.config.manual_channel_funding() {
+            tracing::info!("channels are funded manually - not starting channel funding strategy");
+            return;
+        }
         let Some(edgli) = self.hopr.as_ref() else { return };
         match edgli.start_telemetry_reactor(self.config.strategy.clone().into()).await {
             Ok(strategy_process) => {
@@ -1789,16 +3162,32 @@ impl Core {
                 self.strategy_handle = Some(strategy_process);
             }
             Err(err) => {
-                tracing::error!(?err, "failed to start edge node telemetry reactor - retrying in 10s");
-                self.spawn_retry_reactor(results_sender, Duration::from_secs(10));
+                let delay = self.config.backoff.initial;
+                tracing::error!(?err, ?delay, "failed to start edge node telemetry reactor - retrying");
+                self.spawn_retry_reactor(results_sender, delay);
             }
         }
     }
 
+    /// Sets off the next reconnect attempt once `delay` elapsed, see [`connection::options::Reconnect`].
+    fn spawn_reconnect_timer(&mut self, results_sender: &mpsc::Sender<Results>, delay: Duration) {
+        self.reconnect_scheduled = true;
+        let cancel = self.cancel_on_shutdown.clone();
+        let results_sender = results_sender.clone();
+        self.tasks.spawn(Subsystem::Connection, async move {
+            cancel
+                .run_until_cancelled(async move {
+                    time::sleep(delay).await;
+                    let _ = results_sender.send(Results::ReconnectDue).await;
+                })
+                .await
+        });
+    }
+
     fn spawn_retry_reactor(&self, results_sender: &mpsc::Sender<Results>, delay: Duration) {
         let cancel = self.cancel_on_shutdown.clone();
         let results_sender = results_sender.clone();
-        tokio::spawn(async move {
+        self.tasks.spawn(Subsystem::Node, async move {
             cancel
                 .run_until_cancelled(async move {
                     time::sleep(delay).await;
@@ -1807,4 +3196,1086 @@ impl Core {
                 .await
         });
     }
+
+    /// Moves away from the target exit shortly before its announced retirement, rescheduled on every
+    /// health check as notices may appear, change or be withdrawn.
+    fn schedule_maintenance_migration(&mut self, results_sender: &mpsc::Sender<Results>) {
+        self.cancel_maintenance_migration.cancel();
+        self.cancel_maintenance_migration = self.cancel_on_shutdown.child_token();
+        let Some((maintenance, retires_at)) = self
+            .target_destination
+            .as_ref()
+            .and_then(|dest| self.route_healths.get(&dest.id))
+            .and_then(|rh| rh.state().maintenance())
+            .and_then(|m| Some((m, m.retires_at()?)))
+        else {
+            return;
+        };
+        let migrate_at = retires_at
+            .checked_sub(MAINTENANCE_MIGRATION_LEAD)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        match migrate_at.duration_since(SystemTime::now()) {
+            Ok(delay) => {
+                tracing::info!(%maintenance, ?delay, "target exit announced maintenance - scheduling migration");
+                let cancel = self.cancel_maintenance_migration.clone();
+                let results_sender = results_sender.clone();
+                self.tasks.spawn(Subsystem::Connection, async move {
+                    cancel
+                        .run_until_cancelled(async move {
+                            time::sleep(delay).await;
+                            let _ = results_sender.send(Results::MaintenanceDue).await;
+                        })
+                        .await
+                });
+            }
+            Err(_) => self.migrate_from_retiring_exit(results_sender),
+        }
+    }
+
+    /// Reconnects shortly before the exit drops the registration of `conn`, a registration
+    /// expiring within the lead is left to run out.
+    fn schedule_registration_renewal(&mut self, conn: &connection::up::Up, results_sender: &mpsc::Sender<Results>) {
+        let Some(expires_at) = conn
+            .registration
+            .as_ref()
+            .and_then(|reg| reg.capabilities().expires_at())
+        else {
+            return;
+        };
+        let renew_at = expires_at
+            .checked_sub(REGISTRATION_RENEWAL_LEAD)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let Ok(delay) = renew_at.duration_since(SystemTime::now()) else {
+            tracing::warn!(%conn, "exit registration expires right away - not renewing");
+            return;
+        };
+        tracing::debug!(%conn, ?delay, "scheduling renewal of the exit registration");
+        let cancel = self.cancel_on_shutdown.clone();
+        let results_sender = results_sender.clone();
+        self.tasks.spawn(Subsystem::Connection, async move {
+            cancel
+                .run_until_cancelled(async move {
+                    time::sleep(delay).await;
+                    let _ = results_sender.send(Results::RegistrationExpiring { expires_at }).await;
+                })
+                .await
+        });
+    }
+
+    /// Targets the ready destination with the fastest exit ping that has no maintenance notice itself.
+    fn migrate_from_retiring_exit(&mut self, results_sender: &mpsc::Sender<Results>) {
+        let Some(current) = self.target_destination.clone() else {
+            return;
+        };
+        let retiring = self
+            .route_healths
+            .get(&current.id)
+            .is_some_and(|rh| rh.state().maintenance().is_some());
+        if !retiring {
+            return;
+        }
+        let alternative = self
+            .config
+            .destinations
+            .values()
+            .filter(|dest| dest.id != current.id)
+            .filter_map(|dest| {
+                let rh = self.route_healths.get(&dest.id)?;
+                let exit = rh.ready_to_connect()?;
+                rh.state().maintenance().is_none().then_some((dest, exit.ping_rtt))
+            })
+            .min_by_key(|(_, rtt)| *rtt)
+            .map(|(dest, _)| dest.clone());
+        match alternative {
+            Some(dest) => {
+                tracing::warn!(from = %current, to = %dest, "migrating away from exit retiring for maintenance");
+                self.target_destination = Some(dest);
+                self.act_on_target(results_sender);
+            }
+            None => {
+                tracing::warn!(destination = %current, "exit retires for maintenance but no other destination is ready - staying");
+            }
+        }
+    }
+
+    /// Sends queued reports through the tunnel that just came up. Reports are not retried, a failed
+    /// delivery only shows in ctl.
+    fn spawn_exit_reports(&mut self, results_sender: &mpsc::Sender<Results>) {
+        for (id, url, report) in self.exit_reports.take_queued() {
+            let results_sender = results_sender.clone();
+            let cancel = self.cancel_on_shutdown.clone();
+            self.tasks.spawn(Subsystem::Telemetry, async move {
+                cancel
+                    .run_until_cancelled(runner::exit_report_upload(id, url, report, results_sender))
+                    .await
+            });
+        }
+    }
+
+    /// Checked even while telemetry is disabled, it can be enabled at any time via ctl.
+    fn spawn_telemetry_timer(&self, results_sender: &mpsc::Sender<Results>, delay: Duration) {
+        let cancel = self.cancel_on_shutdown.clone();
+        let results_sender = results_sender.clone();
+        self.tasks.spawn(Subsystem::Telemetry, async move {
+            cancel
+                .run_until_cancelled(async move {
+                    time::sleep(delay).await;
+                    let _ = results_sender.send(Results::TelemetryDue).await;
+                })
+                .await
+        });
+    }
+}
+
+impl Drop for Core {
+    // a core loop aborted by the watchdog must not leave runners or the strategy behind
+    fn drop(&mut self) {
+        self.cancel_on_shutdown.cancel();
+        if let Some(handle) = self.strategy_handle.take() {
+            handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::TempDir;
+
+    const CONFIG: &str = r#"
+version = 6
+
+[destinations.Germany]
+address = "0xD9c11f07BfBC1914877d7395459223aFF9Dc2739"
+"#;
+
+    /// Runners core asked for while mocked, tests play them by feeding their results.
+    #[derive(Debug, Default)]
+    pub(super) struct MockedRunners {
+        // the hopr runner was played successfully, stands in for the node handle
+        pub(super) node_started: bool,
+        pub(super) spawned: Vec<MockedRunner>,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub(super) enum MockedRunner {
+        Hopr,
+        Connection(String),
+        Disconnection(String),
+    }
+
+    /// Core wired to in-memory channels, no runner is ever spawned without a hopr or
+    /// incentive operations handle, so results can be fed in one by one.
+    struct Harness {
+        core: Core,
+        results_sender: mpsc::Sender<Results>,
+        results_receiver: mpsc::Receiver<Results>,
+        _outgoing_receiver: mpsc::Receiver<CoreToWorker>,
+        _state_home: TempDir,
+    }
+
+    impl Harness {
+        async fn new() -> Self {
+            Self::with_config(CONFIG).await
+        }
+
+        async fn with_config(content: &str) -> Self {
+            let state_home = tempfile::tempdir().unwrap();
+            let config_path = state_home.path().join("config.toml");
+            tokio::fs::write(&config_path, content).await.unwrap();
+            let config = config::read(&config_path).await.unwrap();
+            let worker_params = WorkerParams::new(
+                None,
+                None,
+                worker_params::ConfigFileMode::Manual(config_path),
+                false,
+                false,
+                None,
+                dirs::Locations {
+                    state_home: state_home.path().to_path_buf(),
+                    cache_home: dirs::default_cache_home(state_home.path()),
+                },
+            );
+            let (outgoing_sender, outgoing_receiver) = mpsc::channel(32);
+            let (core, _) = Core::new(config, worker_params, Address::from([1u8; 20]), None, outgoing_sender);
+            let (results_sender, results_receiver) = mpsc::channel(32);
+            Self {
+                core,
+                results_sender,
+                results_receiver,
+                _outgoing_receiver: outgoing_receiver,
+                _state_home: state_home,
+            }
+        }
+
+        async fn results(&mut self, results: Results) -> bool {
+            let sender = self.results_sender.clone();
+            self.core.on_results(results, &sender).await
+        }
+
+        async fn command(&mut self, cmd: WorkerCommand) -> Response {
+            let (resp, rx) = oneshot::channel();
+            let sender = self.results_sender.clone();
+            assert!(
+                self.core
+                    .on_event(WorkerToCore::WorkerCommand { cmd, resp }, &sender)
+                    .await
+            );
+            rx.await.expect("core answers every command")
+        }
+
+        fn checking_safe(&mut self) {
+            self.core.phase = Phase::CheckingSafe {
+                node_balance: Querying::Init,
+                query_safe: Querying::Init,
+                funding_tool: balance::FundingTool::NotStarted,
+                deploy_safe_error: None,
+            };
+        }
+
+        /// Track an unregistration as if a disconnection runner had been spawned for `wg_public_key`.
+        fn disconnecting(&mut self, wg_public_key: &str) {
+            let destination = self.core.config.destinations["Germany"].clone();
+            self.core
+                .registrations
+                .insert(destination.id.clone(), wg_public_key.to_string());
+            self.core.ongoing_disconnections.push(connection::down::Down {
+                destination,
+                phase: (SystemTime::now(), connection::down::Phase::UnregisterWg),
+                wg_public_key: wg_public_key.to_string(),
+                api_version: gvpn_client::ApiVersion::V1,
+            });
+        }
+    }
+
+    impl Harness {
+        /// Harness recording node and connection runners instead of spawning them, the public IP
+        /// lookup is turned off as it would leave the test.
+        async fn mocked() -> Self {
+            let mut h = Self::with_config(&format!("{CONFIG}\n[connection]\npublic_ip_endpoint = \"\"\n")).await;
+            h.core.mocked_runners = Some(MockedRunners::default());
+            h
+        }
+
+        fn spawned(&self) -> &[MockedRunner] {
+            &self.core.mocked_runners.as_ref().expect("runners are mocked").spawned
+        }
+
+        /// Play the hopr runner reporting a started node. The node handle itself needs a chain, so
+        /// only the phase it leads to is taken.
+        fn node_started(&mut self) {
+            let mocked = self.core.mocked_runners.as_mut().expect("runners are mocked");
+            assert_eq!(mocked.spawned.last(), Some(&MockedRunner::Hopr));
+            mocked.node_started = true;
+            self.core.phase = Phase::HoprSyncing;
+        }
+    }
+
+    /// Whether a node is running, be it a real or a played one.
+    fn node_running(core: &Core) -> bool {
+        core.hopr.is_some() || core.mocked_runners.as_ref().is_some_and(|m| m.node_started)
+    }
+
+    fn chain_error() -> runner::Error {
+        runner::Error::Chain("rpc unreachable".to_string())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn incentive_operations_retry_is_reported_in_status() {
+        let mut h = Harness::new().await;
+        assert!(
+            h.results(Results::IncentiveOperationsRetry {
+                error: "blokli down".to_string(),
+            })
+            .await
+        );
+        match h.command(WorkerCommand::Status).await {
+            Response::Status(status) => {
+                assert!(matches!(status.run_mode, RunMode::Init { last_error: Some(ref e) } if e == "blokli down"));
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn repeated_node_balance_failures_surface_in_status() {
+        let mut h = Harness::new().await;
+        h.checking_safe();
+        for _ in 0..3 {
+            h.results(Results::NodeBalance {
+                res: Err(chain_error()),
+            })
+            .await;
+        }
+        assert!(matches!(
+            h.core.phase,
+            Phase::CheckingSafe {
+                node_balance: Querying::Error(_),
+                ..
+            }
+        ));
+        match h.command(WorkerCommand::Status).await {
+            Response::Status(status) => {
+                assert_eq!(status.retrying.len(), 1);
+                assert_eq!(status.retrying[0].task, Task::NodeBalance);
+                assert_eq!(status.retrying[0].attempts, 3);
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unreachable_chain_endpoint_is_retried_until_it_answers() {
+        let mut h = Harness::new().await;
+        for _ in 0..3 {
+            let err = preflight::Error::ChainUnreachable {
+                url: "https://blokli.example".parse().expect("valid url"),
+                reason: "connection refused".to_string(),
+            };
+            assert!(h.results(Results::ChainEndpoint { res: Err(err) }).await);
+        }
+        match h.command(WorkerCommand::Status).await {
+            Response::Status(status) => {
+                assert_eq!(status.retrying.len(), 1);
+                assert_eq!(status.retrying[0].task, Task::ChainEndpoint);
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+
+        assert!(h.results(Results::ChainEndpoint { res: Ok(None) }).await);
+        match h.command(WorkerCommand::Status).await {
+            Response::Status(status) => assert!(status.retrying.is_empty()),
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn connect_before_hopr_runs_is_queued() {
+        let mut h = Harness::new().await;
+        let resp = h.command(WorkerCommand::Connect("Germany".to_string())).await;
+        assert!(matches!(
+            resp,
+            Response::Connect(command::ConnectResponse::Queued {
+                startup_percent: Some(0),
+                ..
+            })
+        ));
+        assert_eq!(
+            h.core.target_destination.as_ref().map(|d| d.id.as_str()),
+            Some("Germany")
+        );
+        assert!(h.core.connect_queued);
+
+        h.core.phase = Phase::HoprRunning;
+        let resp = h.command(WorkerCommand::Connect("Germany".to_string())).await;
+        assert!(matches!(
+            resp,
+            Response::Connect(command::ConnectResponse::WaitingToConnect(..))
+        ));
+        assert!(!h.core.connect_queued);
+        let resp = h.command(WorkerCommand::Connect("Nowhere".to_string())).await;
+        assert!(matches!(
+            resp,
+            Response::Connect(command::ConnectResponse::DestinationNotFound)
+        ));
+    }
+
+    fn announced(byte: u8, connected: bool) -> (Address, peer::Peer) {
+        let address = Address::from([byte; 20]);
+        let peer = peer::Peer::new(address, vec![net::Ipv4Addr::new(10, 0, 0, byte)]).with_connection(connected, None);
+        (address, peer)
+    }
+
+    fn channel_to(address: Address) -> (balance::CapacityAllocator, balance::Capacity) {
+        use edgli::hopr_lib::api::types::primitive::prelude::{Balance, WxHOPR};
+        let capacity = balance::Capacity {
+            stake: Balance::<WxHOPR>::from(100u64),
+            expected_messages: 1000,
+            min_guaranteed_messages: 1000,
+            byte_capacity: 0,
+        };
+        (balance::CapacityAllocator::Peer(address), capacity)
+    }
+
+    fn ready_exit() -> route_health::RouteHealthState {
+        route_health::RouteHealthState::ReadyToConnect {
+            exit: route_health::ExitHealth {
+                checked_at: SystemTime::now(),
+                versions: gvpn_client::Versions {
+                    versions: vec!["v1".to_string()],
+                    latest: "v1".to_string(),
+                },
+                ping_rtt: Duration::from_millis(100),
+                health: gvpn_client::Health {
+                    slots: gvpn_client::Slots {
+                        available: 10,
+                        connected: 1,
+                    },
+                    load_avg: gvpn_client::LoadAvg {
+                        one: 0.1,
+                        five: 0.2,
+                        fifteen: 0.3,
+                        nproc: 4,
+                    },
+                    maintenance: None,
+                },
+            },
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn connect_is_not_ready_without_a_channel_to_a_connected_peer() {
+        let mut h = Harness::new().await;
+        h.core.phase = Phase::HoprRunning;
+        h.core
+            .route_healths
+            .get_mut("Germany")
+            .expect("route health per destination")
+            .set_state(ready_exit());
+        let exit = h.core.config.destinations["Germany"].address;
+        let (relay, relay_peer) = announced(2, true);
+        let (offline, offline_peer) = announced(3, false);
+
+        // the exit is not announced
+        h.core.announced_peers = Some((SystemTime::now(), HashMap::from([(relay, relay_peer.clone())])));
+        let resp = h.command(WorkerCommand::Connect("Germany".to_string())).await;
+        assert!(matches!(
+            resp,
+            Response::Connect(command::ConnectResponse::NotReady {
+                reason: command::NotReadyReason::PeerNotSeen,
+                ..
+            })
+        ));
+
+        // the only channel leads to a peer the node is not connected to
+        h.core.announced_peers = Some((
+            SystemTime::now(),
+            HashMap::from([
+                (exit, peer::Peer::new(exit, vec![net::Ipv4Addr::new(10, 0, 0, 1)])),
+                (relay, relay_peer),
+                (offline, offline_peer),
+            ]),
+        ));
+        h.core.capacity_allocations = Some(HashMap::from([channel_to(offline)]));
+        let resp = h.command(WorkerCommand::Connect("Germany".to_string())).await;
+        assert!(matches!(
+            resp,
+            Response::Connect(command::ConnectResponse::NotReady {
+                reason: command::NotReadyReason::NoChannel,
+                ..
+            })
+        ));
+        assert_eq!(h.core.target_destination, None);
+
+        h.core.capacity_allocations = Some(HashMap::from([channel_to(relay)]));
+        let dest = h.core.config.destinations["Germany"].clone();
+        assert_eq!(h.core.reachability(&dest), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn connect_any_needs_a_ready_destination() {
+        let mut h = Harness::new().await;
+        h.core.phase = Phase::HoprRunning;
+        // route health still waits for peers
+        let resp = h
+            .command(WorkerCommand::ConnectAny {
+                avoid_failed: true,
+                rotate: false,
+            })
+            .await;
+        assert!(matches!(resp, Response::Connect(command::ConnectResponse::NoneReady)));
+        assert_eq!(h.core.target_destination, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn terminal_connection_error_clears_target() {
+        let mut h = Harness::new().await;
+        let destination = h.core.config.destinations["Germany"].clone();
+        h.core.target_destination = Some(destination.clone());
+        h.core.phase = Phase::Connecting(connection::up::Up::new(
+            destination.clone(),
+            gvpn_client::ApiVersion::V1,
+        ));
+        let err = connection::up::Error::RemoteData(crate::remote_data::Error::NoHost);
+        assert!(h.results(Results::ConnectionResult { res: Err(err) }).await);
+        assert!(h.core.target_destination.is_none());
+        assert!(matches!(h.core.phase, Phase::HoprRunning));
+
+        h.core.target_destination = Some(destination.clone());
+        h.core.phase = Phase::Connecting(connection::up::Up::new(destination, gvpn_client::ApiVersion::V1));
+        let err = connection::up::Error::Ping("timeout".to_string());
+        assert!(!h.results(Results::ConnectionResult { res: Err(err) }).await);
+        assert!(h.core.target_destination.is_some());
+    }
+
+    #[tokio::test]
+    async fn expiring_registration_reconnects_while_in_use() -> anyhow::Result<()> {
+        let mut h = Harness::new().await;
+        let destination = h.core.config.destinations["Germany"].clone();
+        h.core.target_destination = Some(destination.clone());
+        let mut conn = connection::up::Up::new(destination, gvpn_client::ApiVersion::V1);
+        conn.registration = Some(serde_json::from_str(
+            r#"{"public_key":"pk","ip":"10.128.0.5","newly_registered":true,"server_public_key":"spk","preshared_key":"psk",
+                "capabilities":{"expires_at":1800000000}}"#,
+        )?);
+        h.core.phase = Phase::Connected(conn);
+
+        let replaced = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert!(h.results(Results::RegistrationExpiring { expires_at: replaced }).await);
+        assert!(h.core.reconnecting_since.is_none());
+
+        let expires_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_800_000_000);
+        assert!(h.results(Results::RegistrationExpiring { expires_at }).await);
+        assert!(h.core.reconnecting_since.is_some());
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn broken_tunnel_reconnects_with_backoff_until_retries_run_out() {
+        let mut h = Harness::new().await;
+        let destination = h.core.config.destinations["Germany"].clone();
+        h.core.target_destination = Some(destination.clone());
+        h.core.phase = Phase::Connected(connection::up::Up::new(
+            destination.clone(),
+            gvpn_client::ApiVersion::V1,
+        ));
+        assert!(h.results(Results::SessionMonitorFailed).await);
+        assert!(h.core.reconnecting_since.is_some());
+
+        h.core.phase = Phase::Connecting(connection::up::Up::new(
+            destination.clone(),
+            gvpn_client::ApiVersion::V1,
+        ));
+        let err = connection::up::Error::Ping("timeout".to_string());
+        assert!(h.results(Results::ConnectionResult { res: Err(err) }).await);
+        assert_eq!(h.core.target_destination.as_ref(), Some(&destination));
+        assert!(h.core.reconnecting_since.is_some());
+        assert_eq!(h.core.reconnect_attempts, 1);
+        assert!(h.core.reconnect_scheduled);
+
+        // first backoff is 2s ±25 %
+        let due = time::timeout(Duration::from_secs(3), h.results_receiver.recv())
+            .await
+            .expect("reconnect due after backoff");
+        assert!(matches!(due, Some(Results::ReconnectDue)));
+        assert!(h.results(Results::ReconnectDue).await);
+        assert!(!h.core.reconnect_scheduled);
+
+        h.core.reconnect_attempts = h.core.config.connection.reconnect.max_retries;
+        h.core.phase = Phase::Connecting(connection::up::Up::new(destination, gvpn_client::ApiVersion::V1));
+        let err = connection::up::Error::Ping("timeout".to_string());
+        assert!(!h.results(Results::ConnectionResult { res: Err(err) }).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_target_fails_over_to_next_group_member() {
+        let mut h = Harness::with_config(&format!(
+            r#"{CONFIG}
+[destinations.USA]
+address = "0xa5Ca174Ef94403d6162a969341a61baeA48F57F8"
+
+[destination_groups]
+all = ["Germany", "USA"]
+"#
+        ))
+        .await;
+        h.core.phase = Phase::HoprRunning;
+        let germany = h.core.config.destinations["Germany"].clone();
+        let usa = h.core.config.destinations["USA"].clone();
+        // route health still waits for peers
+        let resp = h.command(WorkerCommand::Connect("all".to_string())).await;
+        assert!(matches!(
+            resp,
+            Response::Connect(command::ConnectResponse::WaitingToConnect(ref d, _)) if *d == germany
+        ));
+        assert_eq!(h.core.failover, vec![usa.clone()]);
+
+        h.core.phase = Phase::Connecting(connection::up::Up::new(germany, gvpn_client::ApiVersion::V1));
+        let err = connection::up::Error::RemoteData(crate::remote_data::Error::NoHost);
+        assert!(h.results(Results::ConnectionResult { res: Err(err) }).await);
+        assert_eq!(h.core.target_destination.as_ref(), Some(&usa));
+        assert!(h.core.failover.is_empty());
+        assert!(matches!(h.core.phase, Phase::HoprRunning));
+
+        h.command(WorkerCommand::ConnectFailover(vec![
+            "Nowhere".to_string(),
+            "all".to_string(),
+        ]))
+        .await;
+        assert_eq!(h.core.failover, vec![usa]);
+        h.command(WorkerCommand::Disconnect).await;
+        assert!(h.core.failover.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancel_connect_aborts_attempt_but_keeps_connection() {
+        let mut h = Harness::new().await;
+        let destination = h.core.config.destinations["Germany"].clone();
+        h.core.target_destination = Some(destination.clone());
+        h.core.phase = Phase::Connecting(connection::up::Up::new(
+            destination.clone(),
+            gvpn_client::ApiVersion::V1,
+        ));
+        let resp = h.command(WorkerCommand::CancelConnect).await;
+        assert!(matches!(
+            resp,
+            Response::CancelConnect(command::CancelConnectResponse::Cancelled(ref d)) if *d == destination
+        ));
+        assert!(h.core.target_destination.is_none());
+        assert!(matches!(h.core.phase, Phase::HoprRunning));
+        assert!(h.core.route_healths["Germany"].cancelled_at().is_some());
+
+        h.core.target_destination = Some(destination.clone());
+        h.core.phase = Phase::Connected(connection::up::Up::new(destination, gvpn_client::ApiVersion::V1));
+        let resp = h.command(WorkerCommand::CancelConnect).await;
+        assert!(matches!(
+            resp,
+            Response::CancelConnect(command::CancelConnectResponse::NotConnecting)
+        ));
+        assert!(h.core.target_destination.is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn public_ip_is_kept_with_the_connection() {
+        let mut h = Harness::new().await;
+        let destination = h.core.config.destinations["Germany"].clone();
+        let public_ip = public_ip::PublicIp {
+            ip: "185.12.64.3".parse().unwrap(),
+            country: Some("Germany".to_string()),
+            resolved_at: SystemTime::now(),
+        };
+        h.core.phase = Phase::Connected(connection::up::Up::new(destination, gvpn_client::ApiVersion::V1));
+        h.results(Results::PublicIp {
+            res: Ok(public_ip.clone()),
+        })
+        .await;
+        let Response::Status(status) = h.command(WorkerCommand::Status).await else {
+            panic!("expected status response");
+        };
+        assert_eq!(status.connected.and_then(|c| c.public_ip), Some(public_ip));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn disconnection_result_drains_bookkeeping() {
+        let mut h = Harness::new().await;
+        h.core.phase = Phase::HoprRunning;
+        h.disconnecting("wg-key");
+        h.results(Results::DisconnectionResult {
+            wg_public_key: "wg-key".to_string(),
+            res: Ok(()),
+        })
+        .await;
+        assert!(h.core.ongoing_disconnections.is_empty());
+        assert!(h.core.registrations.records().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shutdown_stops_the_loop() {
+        let mut h = Harness::new().await;
+        let sender = h.results_sender.clone();
+        assert!(!h.core.on_event(WorkerToCore::Shutdown, &sender).await);
+        assert!(matches!(h.core.phase, Phase::ShuttingDown));
+        assert!(h.core.cancel_on_shutdown.is_cancelled());
+    }
+
+    fn session(destination: &Destination) -> SessionClientMetadata {
+        SessionClientMetadata {
+            target: "wireguard".to_string(),
+            destination: destination.address,
+            forward_path: destination.routing,
+            return_path: destination.routing,
+            protocol: IpProtocol::UDP,
+            bound_host: net::SocketAddr::from(([127, 0, 0, 1], 51820)),
+            hopr_mtu: 1002,
+            surb_len: 400,
+            active_clients: Vec::new(),
+            max_client_sessions: 1,
+            max_surb_upstream: None,
+            response_buffer: None,
+            session_pool: None,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn mocked_runners_drive_onboarding_connect_and_disconnect() -> anyhow::Result<()> {
+        let mut h = Harness::mocked().await;
+        let destination = h.core.config.destinations["Germany"].clone();
+
+        // onboarding: the safe shows up on chain and the node is started with it
+        h.checking_safe();
+        let safe_module = SafeModule {
+            safe_address: "0x0000000000000000000000000000000000000005".to_string(),
+            module_address: "0x0000000000000000000000000000000000000006".to_string(),
+        };
+        h.results(Results::QuerySafe {
+            res: Ok(Some(safe_module.clone())),
+        })
+        .await;
+        assert!(matches!(h.core.phase, Phase::Starting { .. }));
+        assert_eq!(h.core.safe_module, Some(safe_module));
+        assert_eq!(h.spawned(), [MockedRunner::Hopr]);
+        h.node_started();
+        assert!(h.results(Results::HoprRunning).await);
+        assert!(matches!(h.core.phase, Phase::HoprRunning));
+
+        // peers, channels and the exit probe come in from their runners
+        let exit = destination.address;
+        let (relay, relay_peer) = announced(2, true);
+        h.results(Results::AnnouncedPeers {
+            res: Ok(HashMap::from([
+                (exit, peer::Peer::new(exit, vec![net::Ipv4Addr::new(10, 0, 0, 1)])),
+                (relay, relay_peer),
+            ])),
+        })
+        .await;
+        h.results(Results::CapacityAllocations {
+            res: Ok(HashMap::from([channel_to(relay)])),
+        })
+        .await;
+        h.core
+            .route_healths
+            .get_mut("Germany")
+            .expect("route health per destination")
+            .set_state(ready_exit());
+
+        let resp = h.command(WorkerCommand::Connect("Germany".to_string())).await;
+        assert!(matches!(resp, Response::Connect(_)));
+        assert!(matches!(h.core.phase, Phase::Connecting(_)));
+        assert_eq!(
+            h.spawned().last(),
+            Some(&MockedRunner::Connection("Germany".to_string()))
+        );
+
+        // the connection runner reports its progress and the established session
+        let wg = crate::wireguard::WireGuard::from_config(h.core.config.wireguard.clone()).await?;
+        let wg_public_key = wg.key_pair.public_key.clone();
+        h.results(Results::ConnectionEvent(connection::up::Event::Progress(Box::new(
+            connection::up::Progress::OpenBridge(wg),
+        ))))
+        .await;
+        let registration = serde_json::from_str(
+            r#"{"public_key":"pk","ip":"10.128.0.5","newly_registered":true,"server_public_key":"spk","preshared_key":"psk"}"#,
+        )?;
+        h.results(Results::ConnectionEvent(connection::up::Event::Progress(Box::new(
+            connection::up::Progress::OpenPing(registration),
+        ))))
+        .await;
+        assert_eq!(h.core.registrations.records().len(), 1);
+        h.results(Results::ConnectionResult {
+            res: Ok(session(&destination)),
+        })
+        .await;
+        assert!(matches!(h.core.phase, Phase::Connected(ref conn) if conn.destination == destination));
+
+        // disconnecting hands the key to a disconnection runner until it reports back
+        let resp = h.command(WorkerCommand::Disconnect).await;
+        assert!(matches!(resp, Response::Disconnect(_)));
+        assert!(matches!(h.core.phase, Phase::HoprRunning));
+        assert_eq!(
+            h.spawned().last(),
+            Some(&MockedRunner::Disconnection(wg_public_key.clone()))
+        );
+        assert_eq!(h.core.ongoing_disconnections.len(), 1);
+        h.results(Results::DisconnectionResult {
+            wg_public_key,
+            res: Ok(()),
+        })
+        .await;
+        assert!(h.core.ongoing_disconnections.is_empty());
+        assert!(h.core.registrations.records().is_empty());
+        assert!(matches!(h.core.phase, Phase::HoprRunning));
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tunnel_ping_probe_follows_virtual_time() {
+        let mut h = Harness::new().await;
+        let interval = Duration::from_secs(60);
+        let sender = h.results_sender.clone();
+        h.core
+            .tasks
+            .spawn(Subsystem::Connection, runner::tunnel_ping_loop(interval, sender));
+
+        // jitter keeps the first probe within ±25 % of the interval
+        assert!(
+            time::timeout(Duration::from_secs(44), h.results_receiver.recv())
+                .await
+                .is_err()
+        );
+        let Some(Results::ConnectionRequestToRoot(RunnerToRoot::Ping { resp, .. })) =
+            time::timeout(Duration::from_secs(32), h.results_receiver.recv())
+                .await
+                .expect("probe within jittered interval")
+        else {
+            panic!("expected tunnel ping request");
+        };
+        resp.send(Ok(Duration::from_millis(42))).unwrap();
+        assert!(matches!(
+            h.results_receiver.recv().await,
+            Some(Results::TunnelPingResult { rtt: Ok(_) })
+        ));
+    }
+
+    mod phase_transitions {
+        use super::*;
+
+        use proptest::prelude::*;
+
+        const WG_KEYS: [&str; 3] = ["wg-a", "wg-b", "wg-c"];
+
+        /// Inputs core can receive, runner results are played by the test.
+        #[derive(Clone, Debug)]
+        enum Step {
+            NodeStarted,
+            RouteReady,
+            BridgeOpened(usize),
+            Established,
+            ConnectFailed,
+            IncentiveOperationsRetry,
+            NodeBalanceFailed,
+            QuerySafeFailed,
+            DisconnectionEvent(usize),
+            Disconnected(usize),
+            SessionMonitorFailed,
+            TunnelPing(bool),
+            Connect(bool),
+            Disconnect,
+            Status,
+        }
+
+        fn step() -> impl Strategy<Value = Step> {
+            prop_oneof![
+                Just(Step::NodeStarted),
+                3 => Just(Step::RouteReady),
+                3 => (0..WG_KEYS.len()).prop_map(Step::BridgeOpened),
+                3 => Just(Step::Established),
+                Just(Step::ConnectFailed),
+                Just(Step::IncentiveOperationsRetry),
+                Just(Step::NodeBalanceFailed),
+                Just(Step::QuerySafeFailed),
+                (0..WG_KEYS.len()).prop_map(Step::DisconnectionEvent),
+                2 => (0..WG_KEYS.len()).prop_map(Step::Disconnected),
+                Just(Step::SessionMonitorFailed),
+                any::<bool>().prop_map(Step::TunnelPing),
+                3 => any::<bool>().prop_map(Step::Connect),
+                Just(Step::Disconnect),
+                Just(Step::Status),
+            ]
+        }
+
+        fn initial_phase() -> impl Strategy<Value = u8> {
+            0u8..4
+        }
+
+        async fn apply(h: &mut Harness, step: Step) {
+            let destination = h.core.config.destinations["Germany"].clone();
+            match step {
+                // the hopr runner only reports back while the node is starting
+                Step::NodeStarted => {
+                    if matches!(h.core.phase, Phase::Starting { .. }) {
+                        h.node_started();
+                        h.results(Results::HoprRunning).await;
+                    }
+                }
+                Step::RouteReady => {
+                    let exit = destination.address;
+                    let (relay, relay_peer) = announced(2, true);
+                    h.results(Results::AnnouncedPeers {
+                        res: Ok(HashMap::from([
+                            (exit, peer::Peer::new(exit, vec![net::Ipv4Addr::new(10, 0, 0, 1)])),
+                            (relay, relay_peer),
+                        ])),
+                    })
+                    .await;
+                    h.results(Results::CapacityAllocations {
+                        res: Ok(HashMap::from([channel_to(relay)])),
+                    })
+                    .await;
+                    if let Some(rh) = h.core.route_healths.get_mut("Germany") {
+                        rh.set_state(ready_exit());
+                    }
+                }
+                Step::BridgeOpened(i) => {
+                    // generated keys never repeat, a key still being unregistered is not handed out again
+                    if h.core
+                        .ongoing_disconnections
+                        .iter()
+                        .any(|d| d.wg_public_key == WG_KEYS[i])
+                    {
+                        return;
+                    }
+                    let key_pair = crate::wireguard::KeyPair {
+                        priv_key: format!("{}-private", WG_KEYS[i]),
+                        public_key: WG_KEYS[i].to_string(),
+                    };
+                    let wg = crate::wireguard::WireGuard::new(h.core.config.wireguard.clone(), key_pair);
+                    h.results(Results::ConnectionEvent(connection::up::Event::Progress(Box::new(
+                        connection::up::Progress::OpenBridge(wg),
+                    ))))
+                    .await;
+                }
+                Step::Established => {
+                    h.results(Results::ConnectionResult {
+                        res: Ok(session(&destination)),
+                    })
+                    .await;
+                }
+                Step::ConnectFailed => {
+                    let err = connection::up::Error::RemoteData(crate::remote_data::Error::NoHost);
+                    h.results(Results::ConnectionResult { res: Err(err) }).await;
+                }
+                Step::IncentiveOperationsRetry => {
+                    h.results(Results::IncentiveOperationsRetry {
+                        error: "blokli down".to_string(),
+                    })
+                    .await;
+                }
+                Step::NodeBalanceFailed => {
+                    h.results(Results::NodeBalance {
+                        res: Err(chain_error()),
+                    })
+                    .await;
+                }
+                Step::QuerySafeFailed => {
+                    h.results(Results::QuerySafe {
+                        res: Err(chain_error()),
+                    })
+                    .await;
+                }
+                Step::DisconnectionEvent(i) => {
+                    h.results(Results::DisconnectionEvent {
+                        wg_public_key: WG_KEYS[i].to_string(),
+                        evt: connection::down::Event::CloseBridge,
+                    })
+                    .await;
+                }
+                Step::Disconnected(i) => {
+                    h.results(Results::DisconnectionResult {
+                        wg_public_key: WG_KEYS[i].to_string(),
+                        res: Ok(()),
+                    })
+                    .await;
+                }
+                Step::SessionMonitorFailed => {
+                    h.results(Results::SessionMonitorFailed).await;
+                }
+                Step::TunnelPing(ok) => {
+                    let rtt = if ok {
+                        Ok(Duration::from_millis(42))
+                    } else {
+                        Err("timeout".to_string())
+                    };
+                    h.results(Results::TunnelPingResult { rtt }).await;
+                }
+                Step::Connect(known) => {
+                    let id = if known { "Germany" } else { "Nowhere" };
+                    h.command(WorkerCommand::Connect(id.to_string())).await;
+                }
+                Step::Disconnect => {
+                    h.command(WorkerCommand::Disconnect).await;
+                }
+                Step::Status => {
+                    h.command(WorkerCommand::Status).await;
+                }
+            }
+        }
+
+        fn active_connection(core: &Core) -> bool {
+            matches!(core.phase, Phase::Connected(_) | Phase::Connecting(_))
+        }
+
+        fn connection_runners(core: &Core) -> usize {
+            core.mocked_runners.as_ref().map_or(0, |m| {
+                m.spawned
+                    .iter()
+                    .filter(|r| matches!(r, MockedRunner::Connection(_)))
+                    .count()
+            })
+        }
+
+        fn assert_invariants(core: &Core) {
+            // a tunnel can only exist on top of a running node
+            if !node_running(core) {
+                assert!(
+                    !active_connection(core),
+                    "connection phase without hopr: {:?}",
+                    core.phase
+                );
+            }
+            // every key is unregistered by exactly one disconnection runner
+            let keys: HashSet<&str> = core
+                .ongoing_disconnections
+                .iter()
+                .map(|d| d.wg_public_key.as_str())
+                .collect();
+            assert_eq!(keys.len(), core.ongoing_disconnections.len());
+            // the active connection is never torn down at the same time
+            if let Phase::Connected(conn) | Phase::Connecting(conn) = &core.phase
+                && let Some(wg) = &conn.wireguard
+            {
+                assert!(!keys.contains(wg.key_pair.public_key.as_str()));
+            }
+        }
+
+        proptest! {
+            #[test]
+            fn random_inputs_keep_phase_consistent(
+                initial in initial_phase(),
+                disconnecting in proptest::sample::subsequence(WG_KEYS.to_vec(), 0..=WG_KEYS.len()),
+                steps in proptest::collection::vec(step(), 0..40),
+            ) {
+                let rt = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .start_paused(true)
+                    .build()
+                    .unwrap();
+                rt.block_on(async {
+                    let mut h = Harness::mocked().await;
+                    match initial {
+                        0 => {}
+                        1 => h.checking_safe(),
+                        // running phase without a node, nothing may ever connect
+                        2 => h.core.phase = Phase::HoprRunning,
+                        _ => {
+                            h.checking_safe();
+                            h.results(Results::QuerySafe {
+                                res: Ok(Some(SafeModule {
+                                    safe_address: "0x0000000000000000000000000000000000000005".to_string(),
+                                    module_address: "0x0000000000000000000000000000000000000006".to_string(),
+                                })),
+                            })
+                            .await;
+                        }
+                    }
+                    for key in disconnecting.iter() {
+                        h.disconnecting(key);
+                    }
+                    assert_invariants(&h.core);
+
+                    for step in steps {
+                        let was_active = active_connection(&h.core);
+                        let runners = connection_runners(&h.core);
+                        apply(&mut h, step).await;
+                        assert_invariants(&h.core);
+                        // a second connection never starts while one is still up or coming up
+                        let started = connection_runners(&h.core) - runners;
+                        assert!(started <= 1, "{started} connection runners started at once");
+                        assert!(started == 0 || !was_active, "connection runner started next to an active one");
+                    }
+
+                    // every disconnection still in flight drains once its runner reports back
+                    for key in WG_KEYS {
+                        h.results(Results::DisconnectionResult {
+                            wg_public_key: key.to_string(),
+                            res: Ok(()),
+                        })
+                        .await;
+                    }
+                    assert!(h.core.ongoing_disconnections.is_empty());
+                    assert!(h.core.registrations.records().is_empty());
+                });
+            }
+        }
+    }
 }
@@ -0,0 +1,262 @@
+//! Checks of the node's prerequisites before its runners start.
+//!
+//! A wrong identity pass otherwise only shows up much later as generic errors of runners retrying
+//! forever, so [`super::Core::init`] fails on it and the worker exits with a configuration error.
+//! An unreachable chain endpoint may well be temporary: core warns and checks it again through
+//! [`crate::backoff::Retries`] like any other failing task.
+//!
+//! The edge client reads chain state through its Blokli indexer instead of an RPC provider and
+//! the network is fixed by the indexer, so reachability of that endpoint is all there is to check.
+//!
+//! Its answer also serves as time reference. Tickets and exit registrations are checked against
+//! chain time, a skewed local clock otherwise only surfaces as opaque session failures.
+use backon::Retryable;
+use chrono::DateTime;
+use edgli::hopr_lib::HoprKeys;
+use edgli::hopr_lib::builder::Keypair;
+use reqwest::header::DATE;
+use thiserror::Error;
+use url::Url;
+
+use std::fmt::{self, Display};
+use std::time::{Duration, SystemTime};
+
+use crate::backoff;
+use crate::connection::destination::Address;
+use crate::diagnostics::Check;
+use crate::worker_params::{self, WorkerParams};
+use crate::{log_output, proxy};
+
+/// Per request, the whole check is bounded by the backoff configuration.
+const CHAIN_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Larger offsets of the local clock against chain time are worth a warning.
+const CLOCK_SKEW_THRESHOLD: Duration = Duration::from_secs(30);
+/// `doctor` should answer within seconds, not after the retries of a starting worker.
+const DIAGNOSE_BACKOFF: backoff::Config = backoff::Config {
+    initial: Duration::from_secs(1),
+    max_interval: Duration::from_secs(2),
+    max_elapsed: Duration::from_secs(5),
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Unable to load HOPR identity, check identity file and pass: {0}")]
+    Identity(#[source] worker_params::Error),
+    #[error("Chain endpoint {url} did not answer: {reason}")]
+    ChainUnreachable { url: Url, reason: String },
+}
+
+/// Decrypts the identity, generating identity and pass on first start.
+pub(super) async fn identity(worker_params: &WorkerParams) -> Result<HoprKeys, Error> {
+    worker_params
+        .persist_identity_generation()
+        .await
+        .map_err(Error::Identity)
+}
+
+/// Offset of the local clock against the chain endpoint, positive when the local clock is ahead.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ClockSkew {
+    secs: i64,
+}
+
+impl ClockSkew {
+    /// NTP-style estimate, the remote clock is read in the middle of the round trip.
+    fn estimate(sent: SystemTime, received: SystemTime, remote: SystemTime) -> Self {
+        let round_trip = received.duration_since(sent).unwrap_or_default();
+        let local = sent + round_trip / 2;
+        let secs = match local.duration_since(remote) {
+            Ok(ahead) => ahead.as_secs() as i64,
+            Err(behind) => -(behind.duration().as_secs() as i64),
+        };
+        Self { secs }
+    }
+
+    pub(super) fn exceeds_threshold(&self) -> bool {
+        self.secs.unsigned_abs() > CLOCK_SKEW_THRESHOLD.as_secs()
+    }
+}
+
+impl Display for ClockSkew {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = if self.secs < 0 { "behind" } else { "ahead of" };
+        let offset = humantime::format_duration(Duration::from_secs(self.secs.unsigned_abs()));
+        write!(f, "local clock is {offset} {direction} chain time")
+    }
+}
+
+/// Any HTTP answer counts, the indexer rejects plain requests without a query.
+///
+/// Returns the clock skew if the answer carried a `Date` header.
+pub(super) async fn chain_endpoint(
+    url: &Url,
+    proxy: Option<&Url>,
+    backoff: backoff::Config,
+) -> Result<Option<ClockSkew>, Error> {
+    let unreachable = |reason: String| Error::ChainUnreachable {
+        url: url.clone(),
+        reason,
+    };
+    let client = proxy::outbound(proxy)
+        .and_then(|builder| builder.timeout(CHAIN_REQUEST_TIMEOUT).build())
+        .map_err(|e| unreachable(e.to_string()))?;
+    let mut sampler = log_output::Sampler::new();
+    let (sent, resp) = (|| async {
+        let sent = SystemTime::now();
+        client.get(url.clone()).send().await.map(|resp| (sent, resp))
+    })
+    .retry(backoff.exponential())
+    .notify(|err, delay| {
+        if sampler.sample(&err.to_string()) {
+            tracing::warn!(?err, ?delay, %url, "chain endpoint did not answer, retrying...");
+        }
+    })
+    .await
+    .map_err(|e| unreachable(e.to_string()))?;
+    let received = SystemTime::now();
+    tracing::debug!(%url, status = %resp.status(), "chain endpoint answered");
+    let skew = resp
+        .headers()
+        .get(DATE)
+        .and_then(|date| date.to_str().ok())
+        .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+        .map(|remote| ClockSkew::estimate(sent, received, remote.into()));
+    match skew {
+        Some(skew) if skew.exceeds_threshold() => {
+            tracing::warn!(%skew, %url, "local clock is off, tickets and exit registrations may be rejected")
+        }
+        Some(skew) => tracing::debug!(%skew, "local clock is in sync with chain time"),
+        None => tracing::debug!(%url, "chain endpoint sent no date, clock skew unknown"),
+    }
+    Ok(skew)
+}
+
+/// Repeats the system checks of [`super::Core::init`] for `gnosis_vpn-ctl doctor`.
+pub(super) async fn diagnose(
+    worker_params: &WorkerParams,
+    node_address: Address,
+    url: &Url,
+    proxy: Option<&Url>,
+) -> Vec<Check> {
+    let identity = check_identity(worker_params, node_address).await;
+    let (chain, clock) = match chain_endpoint(url, proxy, DIAGNOSE_BACKOFF).await {
+        Ok(skew) => {
+            let clock = skew.map(|skew| {
+                if skew.exceeds_threshold() {
+                    Check::warn(
+                        "clock",
+                        format!("{skew} - tickets and exit registrations may be rejected"),
+                    )
+                } else {
+                    Check::pass("clock", skew.to_string())
+                }
+            });
+            (Check::pass("chain endpoint", format!("{url} answered")), clock)
+        }
+        Err(e) => (Check::fail("chain endpoint", e.to_string()), None),
+    };
+    [Some(identity), Some(chain), clock].into_iter().flatten().collect()
+}
+
+/// Decrypts the identity file again, it may have been replaced or its pass changed since startup.
+async fn check_identity(worker_params: &WorkerParams, node_address: Address) -> Check {
+    let file = worker_params.identity_file();
+    match worker_params.calc_keys().await {
+        Ok(keys) if keys.chain_key.public().to_address() == node_address => Check::pass(
+            "identity",
+            format!("node {node_address} loaded from {}", file.display()),
+        ),
+        Ok(keys) => Check::fail(
+            "identity",
+            format!(
+                "{} holds node {} instead of the running node {node_address} - restart the client",
+                file.display(),
+                keys.chain_key.public().to_address()
+            ),
+        ),
+        Err(e) => Check::fail("identity", format!("unable to load {}: {e}", file.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn quick_backoff() -> backoff::Config {
+        backoff::Config {
+            initial: Duration::from_millis(10),
+            max_interval: Duration::from_millis(10),
+            max_elapsed: Duration::from_millis(30),
+        }
+    }
+
+    #[tokio::test]
+    async fn any_http_answer_passes() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url: Url = format!("http://{}/", listener.local_addr()?).parse()?;
+        let server = tokio::spawn(async move {
+            if let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream
+                    .write_all(b"HTTP/1.1 405 Method Not Allowed\r\ncontent-length: 0\r\n\r\n")
+                    .await;
+            }
+        });
+
+        assert_eq!(chain_endpoint(&url, None, quick_backoff()).await?, None);
+        server.await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn answer_date_reveals_clock_skew() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url: Url = format!("http://{}/", listener.local_addr()?).parse()?;
+        let server = tokio::spawn(async move {
+            if let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ndate: Sun, 06 Nov 1994 08:49:37 GMT\r\ncontent-length: 0\r\n\r\n")
+                    .await;
+            }
+        });
+
+        let skew = chain_endpoint(&url, None, quick_backoff()).await?.expect("date header");
+        assert!(skew.exceeds_threshold());
+        assert!(skew.to_string().ends_with("ahead of chain time"));
+        server.await?;
+        Ok(())
+    }
+
+    #[test]
+    fn clock_skew_is_taken_at_half_round_trip() {
+        let sent = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let received = sent + Duration::from_secs(4);
+        let in_sync = ClockSkew::estimate(sent, received, sent + Duration::from_secs(2));
+        assert_eq!(in_sync, ClockSkew { secs: 0 });
+
+        let behind = ClockSkew::estimate(sent, received, sent + Duration::from_secs(62));
+        assert_eq!(behind, ClockSkew { secs: -60 });
+        assert!(behind.exceeds_threshold());
+        assert_eq!(behind.to_string(), "local clock is 1m behind chain time");
+        assert!(!ClockSkew { secs: 30 }.exceeds_threshold());
+    }
+
+    #[tokio::test]
+    async fn closed_port_is_unreachable() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url: Url = format!("http://{}/", listener.local_addr()?).parse()?;
+        drop(listener);
+
+        assert!(matches!(
+            chain_endpoint(&url, None, quick_backoff()).await,
+            Err(Error::ChainUnreachable { .. })
+        ));
+        Ok(())
+    }
+}
@@ -1,13 +1,15 @@
 //! Various runner tasks that might get extracted into their own modules once applicable.
 //! These function expect to be spawn and will deliver their result or progress via channels.
 
-use backon::{ExponentialBuilder, Retryable};
+use backon::Retryable;
 use edgli::blokli::{IncentiveOperations, make_incentive_operations};
+use edgli::hopr_lib::api::chain::ChainEvent;
 use edgli::hopr_lib::api::node::HoprState;
-use edgli::hopr_lib::api::types::primitive::prelude::Address;
+use edgli::hopr_lib::api::types::primitive::prelude::{Address, Balance, WxHOPR};
 use edgli::hopr_lib::builder::Keypair;
 use edgli::hopr_lib::exports::network::types::types::IpProtocol;
 use edgli::{BlockchainConnectorConfig, EdgliInitState};
+use futures_util::StreamExt;
 use rand::prelude::*;
 use serde::Deserialize;
 use serde_json::json;
@@ -20,16 +22,21 @@ use std::collections::HashMap;
 use std::fmt::{self, Display};
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use crate::command::{self, Response};
 use crate::compat::SafeModule;
+use crate::core::preflight;
+use crate::core::tasks::{Subsystem, Tasks};
 use crate::hopr::blokli_config::BlokliConfig;
 use crate::hopr::types::SessionClientMetadata;
 use crate::hopr::{Hopr, HoprError, config as hopr_config};
 use crate::route_health::{self, HealthCheckOutcome};
 use crate::worker_params::{self, WorkerParams};
-use crate::{balance, connection, event, peer, ping, remote_data};
+use crate::{
+    backoff, balance, connection, event, exit_reports, log_output, peer, ping, proxy, public_ip, remote_data,
+    telemetry, ticket_stats,
+};
 
 /// Results indicate events that arise from concurrent runners.
 /// These runners are usually spawned and want to report data or progress back to the core application loop.
@@ -72,14 +79,25 @@ pub(crate) enum Results {
     IncentiveOperationsRetry {
         error: String,
     },
+    /// `None` when there was nothing to withdraw
     NodeWxhoprWithdraw {
-        res: Result<(), Error>,
+        res: Result<Option<Withdrawal>, Error>,
     },
     AnnouncedPeers {
         res: Result<HashMap<Address, peer::Peer>, Error>,
     },
+    TicketStats {
+        res: Result<ticket_stats::TicketStats, Error>,
+    },
+    /// Clock skew against chain time if the endpoint sent its date
+    ChainEndpoint {
+        res: Result<Option<preflight::ClockSkew>, preflight::Error>,
+    },
     HoprConstruction(EdgliInitState),
     HoprRunning,
+    StaleSessionsClosed {
+        closed: usize,
+    },
     ConnectionEvent(connection::up::Event),
     ConnectionRequestToRoot(event::RunnerToRoot),
     ConnectionResult {
@@ -97,15 +115,64 @@ pub(crate) enum Results {
     TunnelPingResult {
         rtt: Result<Duration, String>,
     },
+    PublicIp {
+        res: Result<public_ip::PublicIp, String>,
+    },
     HealthCheck {
         id: String,
         outcome: HealthCheckOutcome,
     },
     RetryReactor,
+    /// The target exit retires soon, time to move elsewhere
+    MaintenanceDue,
+    /// Backoff after a failed reconnect attempt elapsed
+    ReconnectDue,
+    /// The exit drops the registration expiring at `expires_at` soon, time to register again
+    RegistrationExpiring {
+        expires_at: SystemTime,
+    },
+    TelemetryDue,
+    TelemetryUploaded {
+        res: Result<telemetry::Payload, telemetry::Error>,
+    },
+    ExitReportSent {
+        id: u64,
+        res: Result<(), String>,
+    },
     NerdStatsTicketStats {
         res: command::TicketStatsStatus,
         resp: oneshot::Sender<Response>,
     },
+    /// Missing permissions of the configured external safe module, empty when it serves the node
+    ExternalSafeCheck {
+        res: Result<Vec<balance::ModuleIssue>, Error>,
+    },
+    ChainChange(ChainChange),
+    /// The chain event stream could not be opened or ended
+    ChainWatcherStopped {
+        error: String,
+    },
+}
+
+/// Node wxHOPR moved to the safe.
+#[derive(Debug)]
+pub(crate) struct Withdrawal {
+    pub amount: Balance<WxHOPR>,
+    pub tx_hash: String,
+}
+
+/// On-chain changes core reacts to, picked from the node's chain events.
+#[derive(Debug)]
+pub(crate) enum ChainChange {
+    /// An outgoing channel was opened or topped up
+    ChannelFunded {
+        destination: Address,
+        amount: Balance<WxHOPR>,
+    },
+    /// An outgoing channel was closed, drained or had tickets redeemed against it
+    OwnChannel,
+    /// Ticket price or minimum winning probability was changed
+    TicketStats,
 }
 
 #[derive(Debug, Error)]
@@ -136,9 +203,10 @@ struct UnauthorizedError {
 pub(crate) async fn minimum_balance_recommendation(
     incentive_operations: Arc<dyn IncentiveOperations>,
     cfg: edgli::strategy::IncentiveConfiguration,
+    backoff: backoff::Config,
     results_sender: mpsc::Sender<Results>,
 ) {
-    let res = run_minimum_balance_recommendation(incentive_operations, cfg).await;
+    let res = run_minimum_balance_recommendation(incentive_operations, cfg, backoff).await;
     let _ = results_sender.send(Results::MinimumBalanceRecommendation { res }).await;
 }
 
@@ -168,33 +236,88 @@ pub(crate) async fn balances(hopr: Arc<Hopr>, results_sender: mpsc::Sender<Resul
     let _ = results_sender.send(Results::Balances { res }).await;
 }
 
+pub(crate) async fn telemetry_upload(
+    url: Url,
+    payload: telemetry::Payload,
+    proxy: Option<Url>,
+    results_sender: mpsc::Sender<Results>,
+) {
+    tracing::debug!(?payload, %url, "uploading usage telemetry");
+    let res = telemetry::upload(url, &payload, proxy.as_ref()).await.map(|_| payload);
+    let _ = results_sender.send(Results::TelemetryUploaded { res }).await;
+}
+
+/// Ask root to send the report, it has to leave through the tunnel.
+pub(crate) async fn exit_report_upload(
+    id: u64,
+    url: Url,
+    report: exit_reports::Report,
+    results_sender: mpsc::Sender<Results>,
+) {
+    tracing::debug!(?report, %url, "sending exit report");
+    let (tx, rx) = oneshot::channel();
+    let request = Results::ConnectionRequestToRoot(event::RunnerToRoot::ExitReport { url, report, resp: tx });
+    if results_sender.send(request).await.is_err() {
+        return;
+    }
+    let res = rx
+        .await
+        .unwrap_or_else(|_| Err("exit report response channel closed".to_string()));
+    let _ = results_sender.send(Results::ExitReportSent { id, res }).await;
+}
+
+pub(crate) async fn ticket_stats(
+    incentive_operations: Arc<dyn IncentiveOperations>,
+    results_sender: mpsc::Sender<Results>,
+) {
+    tracing::debug!("starting ticket stats runner");
+    let res = incentive_operations
+        .ticket_stats()
+        .await
+        .map(|ts| ticket_stats::TicketStats {
+            ticket_price: ts.ticket_price,
+            winning_probability: ts.winning_probability.into(),
+        })
+        .map_err(|e| Error::Chain(e.to_string()));
+    let _ = results_sender.send(Results::TicketStats { res }).await;
+}
+
 pub(crate) async fn node_balance(
     incentive_operations: Arc<dyn IncentiveOperations>,
+    backoff: backoff::Config,
     results_sender: mpsc::Sender<Results>,
 ) {
-    let res = run_node_balance(incentive_operations).await;
+    let res = run_node_balance(incentive_operations, backoff).await;
     let _ = results_sender.send(Results::NodeBalance { res }).await;
 }
 
 pub(crate) async fn query_safe(
     incentive_operations: Arc<dyn IncentiveOperations>,
+    backoff: backoff::Config,
     results_sender: mpsc::Sender<Results>,
 ) {
-    let res = run_query_safe(incentive_operations).await;
+    let res = run_query_safe(incentive_operations, backoff).await;
     let _ = results_sender.send(Results::QuerySafe { res }).await;
 }
 
-pub(crate) async fn funding_tool(worker_params: WorkerParams, code: String, results_sender: mpsc::Sender<Results>) {
-    let res = run_funding_tool(worker_params, code).await;
+pub(crate) async fn funding_tool(
+    worker_params: WorkerParams,
+    code: command::Secret,
+    backoff: backoff::Config,
+    proxy: Option<Url>,
+    results_sender: mpsc::Sender<Results>,
+) {
+    let res = run_funding_tool(worker_params, code, backoff, proxy).await;
     let _ = results_sender.send(Results::FundingTool { res }).await;
 }
 
 pub(crate) async fn safe_deployment(
     incentive_operations: Arc<dyn IncentiveOperations>,
     presafe: balance::PreSafe,
+    backoff: backoff::Config,
     results_sender: mpsc::Sender<Results>,
 ) {
-    let res = run_safe_deployment(incentive_operations, presafe).await;
+    let res = run_safe_deployment(incentive_operations, presafe, backoff).await;
     let _ = results_sender.send(Results::DeploySafe { res }).await;
 }
 
@@ -234,10 +357,11 @@ pub(crate) async fn hopr(
 
 pub(crate) async fn node_wxhopr_withdraw(
     incentive_operations: Arc<dyn IncentiveOperations>,
-    safe_address: Address,
+    hopr: Arc<Hopr>,
+    backoff: backoff::Config,
     results_sender: mpsc::Sender<Results>,
 ) {
-    let res = run_node_wxhopr_withdraw(incentive_operations, safe_address).await;
+    let res = run_node_wxhopr_withdraw(incentive_operations, hopr, backoff).await;
     let _ = results_sender.send(Results::NodeWxhoprWithdraw { res }).await;
 }
 
@@ -248,12 +372,114 @@ pub(crate) async fn wait_for_running(hopr: Arc<Hopr>, results_sender: mpsc::Send
     let _ = results_sender.send(Results::HoprRunning).await;
 }
 
+pub(crate) async fn chain_endpoint(
+    url: Url,
+    proxy: Option<Url>,
+    backoff: backoff::Config,
+    results_sender: mpsc::Sender<Results>,
+) {
+    tracing::debug!(%url, "starting chain endpoint runner");
+    let res = preflight::chain_endpoint(&url, proxy.as_ref(), backoff).await;
+    let _ = results_sender.send(Results::ChainEndpoint { res }).await;
+}
+
 pub(crate) async fn announced_peers(hopr: Arc<Hopr>, results_sender: mpsc::Sender<Results>) {
     tracing::debug!("starting announced peers runner");
     let res = hopr.announced_peers().await.map_err(Error::from);
     let _ = results_sender.send(Results::AnnouncedPeers { res }).await;
 }
 
+pub(crate) async fn external_safe_check(
+    hopr: Arc<Hopr>,
+    external: balance::ExternalSafe,
+    results_sender: mpsc::Sender<Results>,
+) {
+    tracing::debug!("starting external safe check runner");
+    let node_address = hopr.info().node_address;
+    let res = hopr
+        .safe_permissions(external.safe_address)
+        .await
+        .map(|(safe, allowance)| external.module_issues(node_address, safe.as_ref(), allowance))
+        .map_err(Error::from);
+    let _ = results_sender.send(Results::ExternalSafeCheck { res }).await;
+}
+
+/// Forwards chain events concerning the node until the event stream ends.
+pub(crate) async fn chain_watcher(hopr: Arc<Hopr>, results_sender: mpsc::Sender<Results>) {
+    tracing::debug!("starting chain watcher");
+    let node_address = hopr.info().node_address;
+    let mut events = match hopr.chain_events() {
+        Ok(events) => events,
+        Err(err) => {
+            let error = err.to_string();
+            let _ = results_sender.send(Results::ChainWatcherStopped { error }).await;
+            return;
+        }
+    };
+    while let Some(event) = events.next().await {
+        if let Some(change) = chain_change(&event, node_address) {
+            tracing::debug!(%event, "chain event concerning the node");
+            let _ = results_sender.send(Results::ChainChange(change)).await;
+        }
+    }
+    let error = "chain event stream ended".to_string();
+    let _ = results_sender.send(Results::ChainWatcherStopped { error }).await;
+}
+
+fn chain_change(event: &ChainEvent, node_address: Address) -> Option<ChainChange> {
+    match event {
+        ChainEvent::ChannelOpened(channel) if channel.source == node_address => Some(ChainChange::ChannelFunded {
+            destination: channel.destination,
+            amount: channel.balance,
+        }),
+        ChainEvent::ChannelBalanceIncreased(channel, amount) if channel.source == node_address => {
+            Some(ChainChange::ChannelFunded {
+                destination: channel.destination,
+                amount: *amount,
+            })
+        }
+        ChainEvent::ChannelClosureInitiated(channel)
+        | ChainEvent::ChannelClosed(channel)
+        | ChainEvent::ChannelBalanceDecreased(channel, _)
+        | ChainEvent::TicketRedeemed(channel, _)
+            if channel.source == node_address =>
+        {
+            Some(ChainChange::OwnChannel)
+        }
+        ChainEvent::TicketPriceChanged(_)
+        | ChainEvent::WinningProbabilityIncreased(_)
+        | ChainEvent::WinningProbabilityDecreased(_) => Some(ChainChange::TicketStats),
+        _ => None,
+    }
+}
+
+/// Close sessions towards any of `destinations` that are still registered on the node,
+/// e.g. left behind by a connection attempt that never got to clean up after itself.
+///
+/// Such sessions are never adopted: the node lives inside the worker process, so a session
+/// found here was not opened by a previous run, and the WireGuard key it would carry traffic
+/// for is gone together with the routing root tears down on worker exit.
+pub(crate) async fn close_stale_sessions(
+    hopr: Arc<Hopr>,
+    destinations: Vec<Address>,
+    results_sender: mpsc::Sender<Results>,
+) {
+    tracing::debug!("starting stale sessions runner");
+    let mut closed = 0;
+    for protocol in [IpProtocol::UDP, IpProtocol::TCP] {
+        for session in hopr.list_sessions(protocol).await {
+            if !destinations.contains(&session.destination) {
+                continue;
+            }
+            match hopr.close_session(session.bound_host, protocol).await {
+                Ok(()) => closed += 1,
+                Err(err) => tracing::warn!(?err, %session, "failed to close stale session"),
+            }
+        }
+    }
+    let _ = results_sender.send(Results::StaleSessionsClosed { closed }).await;
+}
+
 pub(crate) async fn monitor_session(
     hopr: Arc<Hopr>,
     session: &SessionClientMetadata,
@@ -297,52 +523,78 @@ pub(crate) async fn tunnel_ping_loop(interval: Duration, sender: mpsc::Sender<Re
     }
 }
 
+/// Ask root to resolve the exit address, the lookup has to leave through the tunnel.
+pub(crate) async fn resolve_public_ip(endpoint: Url, timeout: Duration, sender: mpsc::Sender<Results>) {
+    let (tx, rx) = oneshot::channel();
+    let request = Results::ConnectionRequestToRoot(event::RunnerToRoot::PublicIp {
+        endpoint,
+        timeout,
+        resp: tx,
+    });
+    if sender.send(request).await.is_err() {
+        return;
+    }
+    let res = match time::timeout(timeout * 2, rx).await {
+        Ok(Ok(res)) => res,
+        Ok(Err(_)) => Err("public ip response channel closed".to_string()),
+        Err(_) => Err("public ip response timed out".to_string()),
+    };
+    let _ = sender.send(Results::PublicIp { res }).await;
+}
+
 pub(crate) async fn create_incentive_operations(
     worker_params: &WorkerParams,
     blokli_config: BlockchainConnectorConfig,
+    backoff: backoff::Config,
+    tasks: &Tasks,
     results_sender: mpsc::Sender<Results>,
 ) {
-    let res = run_create_incentive_operations(worker_params, blokli_config, results_sender.clone()).await;
+    let res =
+        run_create_incentive_operations(worker_params, blokli_config, backoff, tasks, results_sender.clone()).await;
     let _ = results_sender.send(Results::IncentiveOperations { res }).await;
 }
 
 async fn run_node_wxhopr_withdraw(
     incentive_operations: Arc<dyn IncentiveOperations>,
-    safe_address: Address,
-) -> Result<(), Error> {
+    hopr: Arc<Hopr>,
+    backoff: backoff::Config,
+) -> Result<Option<Withdrawal>, Error> {
+    let safe_address = hopr.info().safe_address;
+    let mut sampler = log_output::Sampler::new();
     (|| {
         let incentive_operations = incentive_operations.clone();
+        let hopr = hopr.clone();
         async move {
             let (wxhopr, _xdai) = incentive_operations
                 .balances()
                 .await
                 .map_err(|e| Error::Chain(e.to_string()))?;
-            if !wxhopr.is_zero() {
-                tracing::info!(%wxhopr, %safe_address, "withdrawing node wxHOPR to safe");
-                incentive_operations
-                    .withdraw_wxhopr(safe_address, wxhopr)
-                    .await
-                    .map_err(|e| Error::Chain(e.to_string()))?;
+            if wxhopr.is_zero() {
+                return Ok(None);
             }
-            Ok(())
+            tracing::info!(%wxhopr, %safe_address, "withdrawing node wxHOPR to safe");
+            let tx_hash = hopr.withdraw_wxhopr_to_safe(wxhopr).await?;
+            Ok(Some(Withdrawal {
+                amount: wxhopr,
+                tx_hash,
+            }))
         }
     })
-    .retry(
-        ExponentialBuilder::new()
-            .with_min_delay(Duration::from_secs(10))
-            .with_max_delay(Duration::from_secs(60))
-            .with_factor(2.0)
-            .with_jitter()
-            .without_max_times(),
-    )
+    .retry(backoff.unbounded())
     .notify(|err, delay| {
-        tracing::warn!(?err, ?delay, "wxHOPR withdrawal attempt failed, retrying...");
+        if sampler.sample(&err.to_string()) {
+            tracing::warn!(?err, ?delay, "wxHOPR withdrawal attempt failed, retrying...");
+        }
     })
     .await
 }
 
-async fn run_query_safe(incentive_operations: Arc<dyn IncentiveOperations>) -> Result<Option<SafeModule>, Error> {
+async fn run_query_safe(
+    incentive_operations: Arc<dyn IncentiveOperations>,
+    backoff: backoff::Config,
+) -> Result<Option<SafeModule>, Error> {
     tracing::debug!("starting query safe runner");
+    let mut sampler = log_output::Sampler::new();
     (|| {
         let ops = incentive_operations.clone();
         async move {
@@ -352,15 +604,21 @@ async fn run_query_safe(incentive_operations: Arc<dyn IncentiveOperations>) -> R
                 .map(|b| b.map(SafeModule::from))
         }
     })
-    .retry(remote_data::backoff_expo_long_delay())
+    .retry(backoff.exponential())
     .notify(|err, delay| {
-        tracing::warn!(?err, ?delay, "Safe query attempt failed, retrying...");
+        if sampler.sample(&err.to_string()) {
+            tracing::warn!(?err, ?delay, "Safe query attempt failed, retrying...");
+        }
     })
     .await
 }
 
-async fn run_node_balance(incentive_operations: Arc<dyn IncentiveOperations>) -> Result<balance::PreSafe, Error> {
+async fn run_node_balance(
+    incentive_operations: Arc<dyn IncentiveOperations>,
+    backoff: backoff::Config,
+) -> Result<balance::PreSafe, Error> {
     tracing::debug!("starting node balance runner");
+    let mut sampler = log_output::Sampler::new();
     (|| {
         let ops = incentive_operations.clone();
         async move {
@@ -371,9 +629,11 @@ async fn run_node_balance(incentive_operations: Arc<dyn IncentiveOperations>) ->
             })
         }
     })
-    .retry(remote_data::backoff_expo_long_delay())
+    .retry(backoff.exponential())
     .notify(|err, delay| {
-        tracing::warn!(?err, ?delay, "PreSafe attempt failed, retrying...");
+        if sampler.sample(&err.to_string()) {
+            tracing::warn!(?err, ?delay, "PreSafe attempt failed, retrying...");
+        }
     })
     .await
 }
@@ -381,8 +641,10 @@ async fn run_node_balance(incentive_operations: Arc<dyn IncentiveOperations>) ->
 async fn run_minimum_balance_recommendation(
     incentive_operations: Arc<dyn IncentiveOperations>,
     cfg: edgli::strategy::IncentiveConfiguration,
+    backoff: backoff::Config,
 ) -> Result<balance::BalanceRecommendation, Error> {
     tracing::debug!("starting minimum balance recommendation runner");
+    let mut sampler = log_output::Sampler::new();
     (|| {
         let ops = incentive_operations.clone();
         let cfg = cfg.clone();
@@ -396,13 +658,15 @@ async fn run_minimum_balance_recommendation(
             })
         }
     })
-    .retry(remote_data::backoff_expo_long_delay())
+    .retry(backoff.exponential())
     .notify(|err, delay| {
-        tracing::warn!(
-            ?err,
-            ?delay,
-            "Minimum balance recommendation attempt failed, retrying..."
-        );
+        if sampler.sample(&err.to_string()) {
+            tracing::warn!(
+                ?err,
+                ?delay,
+                "Minimum balance recommendation attempt failed, retrying..."
+            );
+        }
     })
     .await
 }
@@ -410,8 +674,10 @@ async fn run_minimum_balance_recommendation(
 async fn run_safe_deployment(
     incentive_operations: Arc<dyn IncentiveOperations>,
     presafe: balance::PreSafe,
+    backoff: backoff::Config,
 ) -> Result<SafeModule, Error> {
     tracing::debug!("starting safe deployment runner");
+    let mut sampler = log_output::Sampler::new();
     (|| {
         let ops = incentive_operations.clone();
         async move {
@@ -421,23 +687,31 @@ async fn run_safe_deployment(
                 .map(SafeModule::from)
         }
     })
-    .retry(remote_data::backoff_expo_long_delay())
+    .retry(backoff.exponential())
     .notify(|err, delay| {
-        tracing::warn!(?err, ?delay, "Safe deployment attempt failed, retrying...");
+        if sampler.sample(&err.to_string()) {
+            tracing::warn!(?err, ?delay, "Safe deployment attempt failed, retrying...");
+        }
     })
     .await
 }
 
 // Posts to the HOPR funding tool API to request an airdrop using the provided code.
 // Returns final errors in ok branch to break exponential backoff retries.
-async fn run_funding_tool(worker_params: WorkerParams, code: String) -> Result<Option<String>, Error> {
+async fn run_funding_tool(
+    worker_params: WorkerParams,
+    code: command::Secret,
+    backoff: backoff::Config,
+    proxy: Option<Url>,
+) -> Result<Option<String>, Error> {
     let keys = worker_params.calc_keys().await?;
     let node_address = keys.chain_key.public().to_address();
     let url = Url::parse("https://cfp-funding-api-656686060169.europe-west1.run.app/api/cfp-funding-tool/airdrop")?;
-    let client = reqwest::Client::new();
+    let client = proxy::outbound(proxy.as_ref())?.build()?;
     let headers = remote_data::json_headers();
-    let body = json!({ "address": node_address.to_string(), "code": code, });
-    tracing::debug!(%url, ?headers, %body, "Posting funding tool");
+    let body = json!({ "address": node_address.to_string(), "code": code.expose(), });
+    tracing::debug!(%url, ?headers, %node_address, "Posting funding tool");
+    let mut sampler = log_output::Sampler::new();
     (|| async {
         let res = client
             .post(url.clone())
@@ -480,9 +754,11 @@ async fn run_funding_tool(worker_params: WorkerParams, code: String) -> Result<O
         let res = result?;
         Ok(res)
     })
-    .retry(remote_data::backoff_expo_long_delay())
+    .retry(backoff.exponential())
     .notify(|err, delay| {
-        tracing::warn!(?err, ?delay, "Funding tool attempt failed, retrying...");
+        if sampler.sample(&err.to_string()) {
+            tracing::warn!(?err, ?delay, "Funding tool attempt failed, retrying...");
+        }
     })
     .await
 }
@@ -528,22 +804,27 @@ async fn run_monitor_session(hopr: Arc<Hopr>, session: &SessionClientMetadata) {
 async fn run_create_incentive_operations(
     worker_params: &WorkerParams,
     blokli_config: BlockchainConnectorConfig,
+    backoff: backoff::Config,
+    tasks: &Tasks,
     results_sender: mpsc::Sender<Results>,
 ) -> Result<Arc<dyn IncentiveOperations>, Error> {
     let blokli_provider = worker_params.blokli_url();
     let chain_key = worker_params.calc_keys().await?.chain_key;
+    let mut sampler = log_output::Sampler::new();
     (|| async {
         let ops = make_incentive_operations(blokli_provider.clone(), &chain_key, Some(blokli_config))
             .await
             .map_err(|e| Error::IncentiveOperationsCreation(e.to_string()))?;
         Ok(Arc::from(ops))
     })
-    .retry(remote_data::backoff_expo_long_delay())
+    .retry(backoff.exponential())
     .notify(move |err: &Error, delay| {
-        tracing::warn!(?err, ?delay, "IncentiveOperations creation attempt failed, retrying...");
+        if sampler.sample(&err.to_string()) {
+            tracing::warn!(?err, ?delay, "IncentiveOperations creation attempt failed, retrying...");
+        }
         let sender = results_sender.clone();
         let error = err.to_string();
-        tokio::spawn(async move {
+        tasks.spawn(Subsystem::Onboarding, async move {
             let _ = sender.send(Results::IncentiveOperationsRetry { error }).await;
         });
     })
@@ -582,6 +863,14 @@ impl Display for Results {
                 Ok(balances) => write!(f, "Balances: {}", balances),
                 Err(err) => write!(f, "Balances: Error({})", err),
             },
+            Results::TicketStats { res } => match res {
+                Ok(ts) => write!(
+                    f,
+                    "TicketStats: price {}, winning probability {}",
+                    ts.ticket_price, ts.winning_probability
+                ),
+                Err(err) => write!(f, "TicketStats: Error({})", err),
+            },
             Results::DeploySafe { res } => match res {
                 Ok(deployment) => write!(f, "DeploySafe: {:?}", deployment),
                 Err(err) => write!(f, "DeploySafe: Error({})", err),
@@ -600,13 +889,21 @@ impl Display for Results {
                 Err(err) => write!(f, "Hopr: Error({})", err),
             },
             Results::NodeWxhoprWithdraw { res } => match res {
-                Ok(()) => write!(f, "NodeWxhoprWithdraw: Success"),
+                Ok(Some(withdrawal)) => {
+                    write!(f, "NodeWxhoprWithdraw: {} in {}", withdrawal.amount, withdrawal.tx_hash)
+                }
+                Ok(None) => write!(f, "NodeWxhoprWithdraw: Nothing to withdraw"),
                 Err(err) => write!(f, "NodeWxhoprWithdraw: Error({})", err),
             },
             Results::AnnouncedPeers { res } => match res {
                 Ok(peers) => write!(f, "AnnouncedPeers: {} peers", peers.len()),
                 Err(err) => write!(f, "AnnouncedPeers: Error({})", err),
             },
+            Results::ChainEndpoint { res } => match res {
+                Ok(Some(skew)) => write!(f, "ChainEndpoint: Reachable, {}", skew),
+                Ok(None) => write!(f, "ChainEndpoint: Reachable"),
+                Err(err) => write!(f, "ChainEndpoint: Error({})", err),
+            },
             Results::IncentiveOperations { res } => match res {
                 Ok(_) => write!(f, "IncentiveOperations: Created Successfully"),
                 Err(err) => write!(f, "IncentiveOperations: Error({})", err),
@@ -615,6 +912,7 @@ impl Display for Results {
                 write!(f, "IncentiveOperationsRetry: Error({})", error)
             }
             Results::HoprRunning => write!(f, "HoprRunning: Node is running"),
+            Results::StaleSessionsClosed { closed } => write!(f, "StaleSessionsClosed: {}", closed),
             Results::ConnectionEvent(evt) => {
                 write!(f, "ConnectionEvent: {}", evt)
             }
@@ -637,6 +935,10 @@ impl Display for Results {
                 Ok(d) => write!(f, "TunnelPingResult: {:.1}ms", d.as_secs_f64() * 1000.0),
                 Err(err) => write!(f, "TunnelPingResult: Error({})", err),
             },
+            Results::PublicIp { res } => match res {
+                Ok(public_ip) => write!(f, "PublicIp: {}", public_ip.ip),
+                Err(err) => write!(f, "PublicIp: Error({})", err),
+            },
             Results::QuerySafe { res } => match res {
                 Ok(Some(_)) => write!(f, "QuerySafe: Safe found"),
                 Ok(None) => write!(f, "QuerySafe: No safe found"),
@@ -644,7 +946,23 @@ impl Display for Results {
             },
             Results::HealthCheck { id, outcome } => write!(f, "HealthCheck ({}): {:?}", id, outcome),
             Results::RetryReactor => write!(f, "RetryReactor"),
+            Results::MaintenanceDue => write!(f, "MaintenanceDue"),
+            Results::TelemetryDue => write!(f, "TelemetryDue"),
+            Results::TelemetryUploaded { res } => match res {
+                Ok(_) => write!(f, "TelemetryUploaded: Success"),
+                Err(err) => write!(f, "TelemetryUploaded: Error({})", err),
+            },
+            Results::ExitReportSent { id, res } => match res {
+                Ok(_) => write!(f, "ExitReportSent ({}): Success", id),
+                Err(err) => write!(f, "ExitReportSent ({}): Error({})", id, err),
+            },
             Results::NerdStatsTicketStats { .. } => write!(f, "NerdStatsTicketStats"),
+            Results::ExternalSafeCheck { res } => match res {
+                Ok(issues) => write!(f, "ExternalSafeCheck: {} issues", issues.len()),
+                Err(err) => write!(f, "ExternalSafeCheck: Error({})", err),
+            },
+            Results::ChainChange(change) => write!(f, "ChainChange: {:?}", change),
+            Results::ChainWatcherStopped { error } => write!(f, "ChainWatcherStopped: {}", error),
         }
     }
 }
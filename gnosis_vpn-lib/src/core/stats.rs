@@ -0,0 +1,76 @@
+//! Event loop statistics of the core, exported next to the edge client metrics.
+use std::time::Duration;
+
+use crate::metrics::{self, Kind};
+
+#[derive(Debug, Default)]
+pub(crate) struct LoopStats {
+    // how late the last heartbeat tick fired, a busy loop shows up here first
+    tick_delay: Duration,
+    max_tick_delay: Duration,
+    events: u64,
+    busy: Duration,
+    // sampled on every heartbeat
+    incoming_depth: usize,
+    results_depth: usize,
+    outgoing_depth: usize,
+}
+
+impl LoopStats {
+    pub(crate) fn tick(&mut self, delay: Duration, incoming_depth: usize, results_depth: usize, outgoing_depth: usize) {
+        self.tick_delay = delay;
+        self.max_tick_delay = self.max_tick_delay.max(delay);
+        self.incoming_depth = incoming_depth;
+        self.results_depth = results_depth;
+        self.outgoing_depth = outgoing_depth;
+    }
+
+    /// An event or runner result was handled in `elapsed`.
+    pub(crate) fn handled(&mut self, elapsed: Duration) {
+        self.events = self.events.saturating_add(1);
+        self.busy = self.busy.saturating_add(elapsed);
+    }
+
+    pub(crate) fn write_metrics(&self, out: &mut String) {
+        metrics::write(
+            out,
+            "gnosis_vpn_core_loop_tick_delay_seconds",
+            "Delay of the last core loop heartbeat tick",
+            Kind::Gauge,
+            self.tick_delay.as_secs_f64(),
+        );
+        metrics::write(
+            out,
+            "gnosis_vpn_core_loop_tick_delay_max_seconds",
+            "Largest core loop heartbeat tick delay since start",
+            Kind::Gauge,
+            self.max_tick_delay.as_secs_f64(),
+        );
+        metrics::write(
+            out,
+            "gnosis_vpn_core_loop_events_total",
+            "Events and runner results handled by the core loop",
+            Kind::Counter,
+            self.events as f64,
+        );
+        metrics::write(
+            out,
+            "gnosis_vpn_core_loop_busy_seconds_total",
+            "Time the core loop spent handling events and runner results",
+            Kind::Counter,
+            self.busy.as_secs_f64(),
+        );
+        metrics::write_labeled(
+            out,
+            "gnosis_vpn_core_channel_depth",
+            "Queued messages per core channel",
+            Kind::Gauge,
+            "channel",
+            [
+                ("incoming", self.incoming_depth as f64),
+                ("results", self.results_depth as f64),
+                ("outgoing", self.outgoing_depth as f64),
+            ],
+        );
+    }
+}
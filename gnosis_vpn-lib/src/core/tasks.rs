@@ -0,0 +1,158 @@
+//! Runner tasks of the core loop, tracked per subsystem.
+//!
+//! Runners are only tied to cancellation tokens, so a retry loop that is rescheduled without its
+//! predecessor ending accumulates silently. Spawning through [`Tasks`] keeps a live count per
+//! subsystem, reported next to the edge client metrics, and lets shutdown verify that nothing
+//! outlives the core. Clones share the counts, runners get one to track what they spawn themselves.
+use tokio::time;
+use tokio_util::task::TaskTracker;
+
+use std::time::Duration;
+
+use crate::metrics;
+
+/// Live tasks of a single subsystem above which runners are assumed to leak.
+pub(crate) const MAX_TASKS_PER_SUBSYSTEM: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Subsystem {
+    /// incentive operations, safe lookup and deployment
+    Onboarding,
+    /// edge client startup and supervision
+    Node,
+    /// balances, recommendations and channel funding
+    Funding,
+    Peers,
+    /// route health checks and closing their sessions
+    Health,
+    Connection,
+    /// one-off lookups answering ctl commands
+    Commands,
+    Telemetry,
+}
+
+#[derive(Clone)]
+pub(crate) struct Tasks {
+    trackers: [TaskTracker; Subsystem::ALL.len()],
+}
+
+impl Subsystem {
+    pub(crate) const ALL: [Subsystem; 8] = [
+        Subsystem::Onboarding,
+        Subsystem::Node,
+        Subsystem::Funding,
+        Subsystem::Peers,
+        Subsystem::Health,
+        Subsystem::Connection,
+        Subsystem::Commands,
+        Subsystem::Telemetry,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Subsystem::Onboarding => "onboarding",
+            Subsystem::Node => "node",
+            Subsystem::Funding => "funding",
+            Subsystem::Peers => "peers",
+            Subsystem::Health => "health",
+            Subsystem::Connection => "connection",
+            Subsystem::Commands => "commands",
+            Subsystem::Telemetry => "telemetry",
+        }
+    }
+}
+
+impl Tasks {
+    pub(crate) fn new() -> Self {
+        Self {
+            trackers: std::array::from_fn(|_| TaskTracker::new()),
+        }
+    }
+
+    pub(crate) fn spawn<F>(&self, subsystem: Subsystem, future: F)
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let tracker = self.tracker(subsystem);
+        if tracker.len() >= MAX_TASKS_PER_SUBSYSTEM {
+            tracing::warn!(
+                subsystem = subsystem.name(),
+                live = tracker.len(),
+                "unusually many live tasks - runners are likely leaking"
+            );
+        }
+        tracker.spawn(future);
+    }
+
+    pub(crate) fn count(&self, subsystem: Subsystem) -> usize {
+        self.tracker(subsystem).len()
+    }
+
+    pub(crate) fn write_metrics(&self, out: &mut String) {
+        metrics::write_labeled(
+            out,
+            "gnosis_vpn_core_tasks",
+            "Live runner tasks of the core loop per subsystem",
+            metrics::Kind::Gauge,
+            "subsystem",
+            Subsystem::ALL.map(|s| (s.name(), self.count(s) as f64)),
+        );
+    }
+
+    /// Stop accepting tasks and wait for the running ones, returns the subsystems still having
+    /// tasks after `timeout`.
+    pub(crate) async fn shutdown(&self, timeout: Duration) -> Vec<(Subsystem, usize)> {
+        for tracker in &self.trackers {
+            tracker.close();
+        }
+        let wait_all = async {
+            for tracker in &self.trackers {
+                tracker.wait().await;
+            }
+        };
+        let _ = time::timeout(timeout, wait_all).await;
+        Subsystem::ALL
+            .into_iter()
+            .map(|s| (s, self.count(s)))
+            .filter(|(_, count)| *count > 0)
+            .collect()
+    }
+
+    fn tracker(&self, subsystem: Subsystem) -> &TaskTracker {
+        &self.trackers[subsystem as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio_util::sync::CancellationToken;
+
+    #[tokio::test]
+    async fn counts_per_subsystem_and_finishes_on_shutdown() {
+        let tasks = Tasks::new();
+        let cancel = CancellationToken::new();
+        for _ in 0..3 {
+            let cancel = cancel.clone();
+            tasks.spawn(Subsystem::Funding, async move { cancel.cancelled().await });
+        }
+        assert_eq!(tasks.count(Subsystem::Funding), 3);
+        assert_eq!(tasks.count(Subsystem::Connection), 0);
+        let mut out = String::new();
+        tasks.write_metrics(&mut out);
+        assert!(out.contains("gnosis_vpn_core_tasks{subsystem=\"funding\"} 3\n"));
+
+        cancel.cancel();
+        assert!(tasks.shutdown(Duration::from_secs(1)).await.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shutdown_reports_leaked_tasks() {
+        let tasks = Tasks::new();
+        tasks.spawn(Subsystem::Node, std::future::pending::<()>());
+        let leaked = tasks.shutdown(Duration::from_secs(5)).await;
+        assert_eq!(leaked, vec![(Subsystem::Node, 1)]);
+    }
+}
@@ -0,0 +1,360 @@
+//! Crash reports written from a panic hook.
+//!
+//! Every binary installs [`install`] early on. On panic a report with the message, a backtrace,
+//! the current core phase and the last [`RECENT_LINES`] log lines is written to
+//! `crash-<binary>.json` in the crash directory, next to the default panic output. Addresses,
+//! keys, peer ids and IPs are redacted from message and log lines before they are stored.
+//!
+//! Root writes its reports to a directory below its root only home, the worker to one in its
+//! cache home. Root picks up reports of both on its next start with [`take_reports`], surfaces
+//! the latest one in status and uploads them to the configured endpoint when usage telemetry is
+//! enabled.
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::panic::{self, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use crate::{log_output, proxy, serde_utils, telemetry};
+
+pub const RECENT_LINES: usize = 100;
+/// Directory below the root home for root and below the cache home for the worker.
+pub const DIR_NAME: &str = "crashes";
+const FILE_PREFIX: &str = "crash-";
+const REPORTED_SUFFIX: &str = ".reported";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Base64 of a 32 byte WireGuard key, padding included.
+const WG_KEY_LEN: usize = 44;
+/// Ed25519 and secp256k1 libp2p peer ids.
+const PEER_ID_PREFIXES: [&str; 2] = ["12D3KooW", "16Uiu2HA"];
+const MIN_PEER_ID_LEN: usize = 40;
+
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+static PHASE: Mutex<Option<&'static str>> = Mutex::new(None);
+static DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub binary: String,
+    pub version: String,
+    #[serde(with = "serde_utils::system_time")]
+    pub time: SystemTime,
+    pub phase: Option<String>,
+    pub message: String,
+    pub location: Option<String>,
+    pub backtrace: String,
+    pub log_lines: Vec<String>,
+}
+
+/// Crash of a previous run, reported in status.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Summary {
+    pub binary: String,
+    #[serde(with = "serde_utils::system_time")]
+    #[schemars(with = "u64")]
+    pub time: SystemTime,
+    pub message: String,
+    pub file: PathBuf,
+}
+
+/// Install the panic hook, reports go to `dir` or the temp dir until [`set_dir`] is called.
+pub fn install(binary: &'static str, dir: Option<PathBuf>) {
+    if let Some(dir) = dir {
+        set_dir(dir);
+    }
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        let report = Report::new(binary, info);
+        match report.write() {
+            Ok(path) => eprintln!("crash report written to {}", path.display()),
+            Err(err) => eprintln!("failed to write crash report: {err}"),
+        }
+    }));
+}
+
+pub fn set_dir(dir: PathBuf) {
+    if let Ok(mut guard) = DIR.lock() {
+        *guard = Some(dir);
+    }
+}
+
+/// Phase included in a crash report, set by the core loop.
+pub fn set_phase(phase: &'static str) {
+    if let Ok(mut guard) = PHASE.lock() {
+        *guard = Some(phase);
+    }
+}
+
+/// Tracing layer keeping the last [`RECENT_LINES`] formatted log lines for crash reports.
+pub fn recent_lines_layer<S>() -> impl tracing_subscriber::Layer<S>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .with_writer(|| RecentLinesWriter)
+}
+
+struct RecentLinesWriter;
+
+impl io::Write for RecentLinesWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Ok(mut recent) = RECENT.lock() {
+            for line in String::from_utf8_lossy(buf).lines() {
+                if recent.len() == RECENT_LINES {
+                    recent.pop_front();
+                }
+                recent.push_back(line.to_string());
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Report {
+    fn new(binary: &str, info: &PanicHookInfo) -> Self {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic payload".to_string());
+        // a poisoned lock means another thread panicked while logging, the lines are still usable
+        let log_lines = RECENT
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .map(|line| sanitize(line))
+            .collect();
+        let phase = PHASE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .map(str::to_string);
+        Self {
+            binary: binary.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            time: SystemTime::now(),
+            phase,
+            message: sanitize(&message),
+            location: info.location().map(|l| l.to_string()),
+            backtrace: Backtrace::force_capture().to_string(),
+            log_lines,
+        }
+    }
+
+    fn write(&self) -> io::Result<PathBuf> {
+        let dir = DIR
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+            .unwrap_or_else(std::env::temp_dir);
+        let path = dir.join(format!("{FILE_PREFIX}{}.json", self.binary));
+        let content = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+
+    pub fn summary(&self, file: PathBuf) -> Summary {
+        Summary {
+            binary: self.binary.clone(),
+            time: self.time,
+            message: self.message.clone(),
+            file,
+        }
+    }
+}
+
+/// Collect unreported crash reports from `dir`, each is renamed so it is only reported once.
+pub fn take_reports(dir: &Path) -> Vec<(PathBuf, Report)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut reports = Vec::new();
+    for path in entries.flatten().map(|e| e.path()) {
+        let is_report = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with(FILE_PREFIX) && n.ends_with(".json"));
+        // the worker directory is writable by the worker, never follow links placed there
+        let is_file = fs::symlink_metadata(&path).is_ok_and(|meta| meta.is_file());
+        if !is_report || !is_file {
+            continue;
+        }
+        let report = match fs::read_to_string(&path).map(|c| serde_json::from_str::<Report>(&c)) {
+            Ok(Ok(report)) => report,
+            Ok(Err(error)) => {
+                tracing::warn!(?error, ?path, "discarding unreadable crash report");
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+            Err(error) => {
+                tracing::warn!(?error, ?path, "unable to read crash report");
+                continue;
+            }
+        };
+        let mut reported = path.clone().into_os_string();
+        reported.push(REPORTED_SUFFIX);
+        let reported = PathBuf::from(reported);
+        if let Err(error) = fs::rename(&path, &reported) {
+            tracing::warn!(?error, ?path, "unable to mark crash report as reported");
+        }
+        reports.push((reported, report));
+    }
+    reports.sort_by_key(|(_, r)| r.time);
+    reports
+}
+
+pub async fn upload(url: Url, report: &Report, proxy: Option<&Url>) -> Result<(), telemetry::Error> {
+    proxy::outbound(proxy)?
+        .timeout(REQUEST_TIMEOUT)
+        .build()?
+        .post(url)
+        .json(report)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())?;
+    Ok(())
+}
+
+/// Redact hex strings (addresses, keys, hashes), WireGuard keys, peer ids and IP addresses.
+fn sanitize(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    let mut prev: Option<char> = None;
+    while let Some(c) = rest.chars().next() {
+        let at_boundary = !prev.is_some_and(|p| p.is_ascii_alphanumeric() || p == '.' || p == ':');
+        if at_boundary && let Some((len, replacement)) = sensitive_prefix(rest) {
+            out.push_str(replacement);
+            rest = &rest[len..];
+            prev = replacement.chars().last();
+            continue;
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+        prev = Some(c);
+    }
+    out
+}
+
+/// Length and replacement of a sensitive token `s` starts with.
+fn sensitive_prefix(s: &str) -> Option<(usize, &'static str)> {
+    let hex = s
+        .strip_prefix("0x")
+        .map(|h| h.chars().take_while(|c| c.is_ascii_hexdigit()).count())
+        .unwrap_or(0);
+    if hex >= 8 {
+        return Some((2 + hex, "0x<redacted>"));
+    }
+    let base64 = s
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '+' || *c == '/')
+        .count();
+    if base64 == WG_KEY_LEN - 1 && s[base64..].starts_with('=') {
+        return Some((WG_KEY_LEN, "<key>"));
+    }
+    let alphanumeric = s.chars().take_while(|c| c.is_ascii_alphanumeric()).count();
+    if alphanumeric >= MIN_PEER_ID_LEN && PEER_ID_PREFIXES.iter().any(|prefix| s.starts_with(prefix)) {
+        return Some((alphanumeric, "<peer>"));
+    }
+    let ipv4 = s.chars().take_while(|c| c.is_ascii_digit() || *c == '.').count();
+    if s[..ipv4].parse::<Ipv4Addr>().is_ok() {
+        return Some((ipv4, "<ip>"));
+    }
+    let ipv6 = s
+        .chars()
+        .take_while(|c| c.is_ascii_hexdigit() || *c == ':' || *c == '.')
+        .count();
+    // a trailing `:port` is not part of the address
+    let without_port = s[..ipv6].rfind(':');
+    [Some(ipv6), without_port]
+        .into_iter()
+        .flatten()
+        .find(|len| s[..*len].matches(':').count() >= 2 && s[..*len].parse::<Ipv6Addr>().is_ok())
+        .map(|len| (len, "<ip>"))
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Previous run of {} crashed {} ago: {} (report: {})",
+            self.binary,
+            log_output::elapsed(&self.time),
+            self.message,
+            self.file.display()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::tempdir;
+
+    #[test]
+    fn sanitize_redacts_addresses_and_ips() {
+        assert_eq!(
+            sanitize("peer 0xD9c11f07BfBC1914877d7395459223aFF9Dc2739 at 192.168.1.20:1422 failed"),
+            "peer 0x<redacted> at <ip>:1422 failed"
+        );
+        assert_eq!(sanitize("version 0.93.0, 0x1f"), "version 0.93.0, 0x1f");
+    }
+
+    #[test]
+    fn sanitize_redacts_keys_peer_ids_and_ipv6() {
+        assert_eq!(
+            sanitize("wg key 2T8MtVj2mmwl0KtYCUBUfY3f+zNhMXC3n5hOxu9BHEk= configured"),
+            "wg key <key> configured"
+        );
+        assert_eq!(
+            sanitize("peer 12D3KooWEqDWRMVfUGGgDoVmyY7WVbxcm1ZjgMtQXVUg6qhGqJ9V unreachable"),
+            "peer <peer> unreachable"
+        );
+        assert_eq!(
+            sanitize("endpoint [fe80::1]:51820, gateway 2001:db8::5"),
+            "endpoint [<ip>]:51820, gateway <ip>"
+        );
+        assert_eq!(
+            sanitize("2026-10-16T15:28:20Z gnosis_vpn_lib::core: ready"),
+            "2026-10-16T15:28:20Z gnosis_vpn_lib::core: ready"
+        );
+    }
+
+    #[test]
+    fn reports_are_taken_once() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let report = Report {
+            binary: "worker".to_string(),
+            version: "0.0.0".to_string(),
+            time: SystemTime::now(),
+            phase: Some("HoprRunning".to_string()),
+            message: "boom".to_string(),
+            location: None,
+            backtrace: String::new(),
+            log_lines: vec![],
+        };
+        set_dir(dir.path().to_path_buf());
+        report.write()?;
+
+        let taken = take_reports(dir.path());
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].1, report);
+        assert!(take_reports(dir.path()).is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+//! Checks behind `gnosis_vpn-ctl doctor`, collected into one report to paste into a support request.
+//!
+//! Root checks what only it can see: WireGuard tooling, the control socket and the routing table.
+//! The core adds the worker's WireGuard tools, identity, safe and reachability of the chain endpoint.
+//! Without a running worker the report carries a failed `worker` check instead of the core checks.
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use std::fmt::{self, Display};
+
+use crate::command::RoutingExplainResponse;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Pass,
+    /// Works for now but is likely to cause trouble
+    Warn,
+    Fail,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Check {
+    pub name: String,
+    pub outcome: Outcome,
+    pub detail: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Check {
+    pub fn pass<S: Into<String>>(name: &str, detail: S) -> Self {
+        Self::new(name, Outcome::Pass, detail)
+    }
+
+    pub fn warn<S: Into<String>>(name: &str, detail: S) -> Self {
+        Self::new(name, Outcome::Warn, detail)
+    }
+
+    pub fn fail<S: Into<String>>(name: &str, detail: S) -> Self {
+        Self::new(name, Outcome::Fail, detail)
+    }
+
+    fn new<S: Into<String>>(name: &str, outcome: Outcome, detail: S) -> Self {
+        Self {
+            name: name.to_string(),
+            outcome,
+            detail: detail.into(),
+        }
+    }
+}
+
+impl Report {
+    /// Worst outcome of all checks, passing if there are none.
+    pub fn outcome(&self) -> Outcome {
+        self.checks.iter().map(|c| c.outcome).max().unwrap_or(Outcome::Pass)
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Pass => write!(f, "pass"),
+            Outcome::Warn => write!(f, "warn"),
+            Outcome::Fail => write!(f, "fail"),
+        }
+    }
+}
+
+impl Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.outcome, self.name, self.detail)
+    }
+}
+
+/// Routing table sanity from what `routing explain` reports.
+pub fn routing(explain: &Result<RoutingExplainResponse, String>) -> Check {
+    const NAME: &str = "routing";
+    let explain = match explain {
+        Ok(explain) => explain,
+        Err(e) => return Check::fail(NAME, format!("unable to inspect routing: {e}")),
+    };
+    if !explain.managed {
+        return Check::pass(NAME, "routing is delegated to the surrounding setup");
+    }
+    let Some(wan) = &explain.wan_device else {
+        return Check::fail(NAME, "no WAN default route - check the network connection");
+    };
+    let missing: Vec<&str> = explain
+        .routes
+        .iter()
+        .filter(|route| route.present == Some(false))
+        .map(|route| route.destination.as_str())
+        .collect();
+    if !missing.is_empty() {
+        return Check::fail(NAME, format!("routes missing from the table: {}", missing.join(", ")));
+    }
+    let missing: Vec<&str> = explain
+        .tables
+        .iter()
+        .filter(|table| table.expected && table.present == Some(false))
+        .map(|table| table.table.as_str())
+        .collect();
+    if !missing.is_empty() {
+        return Check::fail(NAME, format!("firewall tables missing: {}", missing.join(", ")));
+    }
+    if explain.active {
+        Check::pass(
+            NAME,
+            format!("{} tunnel routes in place, WAN via {wan}", explain.routes.len()),
+        )
+    } else {
+        Check::pass(NAME, format!("no tunnel routes set up, WAN via {wan}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::command::{RouteExplanation, TableExplanation};
+
+    fn explain(wan_device: Option<&str>, present: Option<bool>) -> Result<RoutingExplainResponse, String> {
+        Ok(RoutingExplainResponse {
+            managed: true,
+            active: true,
+            wan_device: wan_device.map(str::to_string),
+            routes: vec![RouteExplanation {
+                destination: "10.128.0.1/32".to_string(),
+                gateway: None,
+                device: "wg0_gnosisvpn".to_string(),
+                purpose: "tunnel".to_string(),
+                present,
+            }],
+            tables: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn routing_fails_on_missing_routes_or_wan() {
+        assert_eq!(routing(&explain(Some("eth0"), Some(true))).outcome, Outcome::Pass);
+        assert_eq!(routing(&explain(Some("eth0"), None)).outcome, Outcome::Pass);
+        assert_eq!(routing(&explain(Some("eth0"), Some(false))).outcome, Outcome::Fail);
+        assert_eq!(routing(&explain(None, Some(true))).outcome, Outcome::Fail);
+        assert_eq!(routing(&Err("actor gone".to_string())).outcome, Outcome::Fail);
+
+        let mut with_block = explain(Some("eth0"), Some(true));
+        if let Ok(explain) = &mut with_block {
+            explain.tables.push(TableExplanation {
+                table: "gnosis_vpn_block".to_string(),
+                purpose: "kill switch block".to_string(),
+                expected: true,
+                present: Some(false),
+            });
+        }
+        assert_eq!(routing(&with_block).outcome, Outcome::Fail);
+
+        let report = Report {
+            checks: vec![Check::warn("a", ""), Check::pass("b", "")],
+        };
+        assert_eq!(report.outcome(), Outcome::Warn);
+        assert_eq!(Report::default().outcome(), Outcome::Pass);
+    }
+}
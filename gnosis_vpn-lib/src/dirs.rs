@@ -1,18 +1,27 @@
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use std::fs::DirBuilder;
-use std::os::unix::fs::{self as unix_fs, DirBuilderExt};
-use std::path::PathBuf;
+use std::fs::{self, DirBuilder};
+use std::io;
+use std::os::unix::fs::{self as unix_fs, DirBuilderExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
 
 pub const ENV_VAR_STATE_HOME: &str = "GNOSISVPN_HOME";
+pub const ENV_VAR_CACHE_HOME: &str = "GNOSISVPN_CACHE_HOME";
+pub const ENV_VAR_XDG_DATA_HOME: &str = "XDG_DATA_HOME";
+pub const ENV_VAR_XDG_CACHE_HOME: &str = "XDG_CACHE_HOME";
 
 #[cfg(target_os = "linux")]
 pub const DEFAULT_STATE_HOME: &str = "/var/lib/gnosisvpn";
 #[cfg(target_os = "macos")]
 pub const DEFAULT_STATE_HOME: &str = "/Library/Application Support/GnosisVPN";
 
-const CONFIG_DIRECTORY: &str = ".config";
-const CACHE_DIRECTORY: &str = ".cache";
+pub(crate) const CONFIG_DIRECTORY: &str = ".config";
+pub(crate) const CACHE_DIRECTORY: &str = ".cache";
+/// Directory below the XDG base directories.
+const XDG_DIRECTORY: &str = "gnosisvpn";
+/// Appended to the state home name for the directory only root writes to.
+const ROOT_HOME_SUFFIX: &str = "-root";
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -32,19 +41,86 @@ pub enum DirError {
     Ownership(String),
 }
 
+/// Data and cache locations from the configuration file, `None` falls back to the defaults.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    pub data: Option<PathBuf>,
+    pub cache: Option<PathBuf>,
+}
+
+/// Resolved state home (node database, identity, safe configuration) and cache home.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Locations {
+    pub state_home: PathBuf,
+    pub cache_home: PathBuf,
+}
+
+impl Locations {
+    /// Directory precedence: explicit argument or environment, then configuration, then the XDG
+    /// base directories, then [`DEFAULT_STATE_HOME`] with the cache inside the state home.
+    pub fn resolve<F>(explicit: Config, configured: &Config, env: F) -> Self
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        // relative XDG paths are invalid by specification and must be ignored
+        let xdg = |var: &str| {
+            env(var)
+                .map(PathBuf::from)
+                .filter(|p| p.is_absolute())
+                .map(|p| p.join(XDG_DIRECTORY))
+        };
+        let state_home = explicit
+            .data
+            .or_else(|| configured.data.clone())
+            .or_else(|| xdg(ENV_VAR_XDG_DATA_HOME))
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_STATE_HOME));
+        let cache_home = explicit
+            .cache
+            .or_else(|| configured.cache.clone())
+            .or_else(|| xdg(ENV_VAR_XDG_CACHE_HOME))
+            .unwrap_or_else(|| default_cache_home(&state_home));
+        Self { state_home, cache_home }
+    }
+}
+
 // Sets up the required directories for the worker, ensuring they are owned by the worker user
 // tracing is not yet enabled so we cannot use it
-pub fn setup_home(home: PathBuf, uid: u32, gid: u32) -> Result<(), Error> {
+pub fn setup_home(home: PathBuf, cache_home: PathBuf, uid: u32, gid: u32) -> Result<(), Error> {
     ensure_dir(home.clone(), 0o755, uid, gid).map_err(Error::HomeFolder)?;
-    let cache_path = home.join(CACHE_DIRECTORY);
-    ensure_dir(cache_path, 0o700, uid, gid).map_err(Error::CacheFolder)?;
+    ensure_dir(cache_home, 0o700, uid, gid).map_err(Error::CacheFolder)?;
     let config_path = home.join(CONFIG_DIRECTORY);
     ensure_dir(config_path, 0o700, uid, gid).map_err(Error::ConfigFolder)?;
     Ok(())
 }
 
-pub fn cache_dir(home: PathBuf, file: &str) -> PathBuf {
-    home.join(CACHE_DIRECTORY).join(file)
+/// Cache home used when none is configured.
+pub fn default_cache_home(state_home: &Path) -> PathBuf {
+    state_home.join(CACHE_DIRECTORY)
+}
+
+/// Next to the state home, for files root must not pick up from the worker owned state home,
+/// e.g. `/var/lib/gnosisvpn-root` for the remote listener identity and its paired clients.
+pub fn root_home(state_home: &Path) -> PathBuf {
+    let mut name = state_home.file_name().unwrap_or_default().to_os_string();
+    name.push(ROOT_HOME_SUFFIX);
+    state_home.with_file_name(name)
+}
+
+/// Creates `path` accessible only to the current user, refusing one owned by anybody else.
+pub fn ensure_private_dir(path: &Path) -> io::Result<()> {
+    DirBuilder::new().recursive(true).mode(0o700).create(path)?;
+    let meta = fs::symlink_metadata(path)?;
+    if !meta.is_dir() || meta.uid() != uzers::get_effective_uid() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a directory owned by this user", path.display()),
+        ));
+    }
+    fs::set_permissions(path, fs::Permissions::from_mode(0o700))
+}
+
+pub fn cache_dir(cache_home: PathBuf, file: &str) -> PathBuf {
+    cache_home.join(file)
 }
 
 pub fn config_dir(home: PathBuf, file: &str) -> PathBuf {
@@ -68,3 +144,146 @@ pub fn ensure_dir(path: PathBuf, mode: u32, uid: u32, gid: u32) -> Result<(), Di
     })?;
     Ok(())
 }
+
+/// Moves everything from `from` into `to`, merging directories and keeping entries that already
+/// exist in `to`. Entries copied across file systems are handed to `uid`/`gid`, the emptied source
+/// directories are removed where possible. Returns the number of moved entries.
+pub fn migrate(from: &Path, to: &Path, uid: u32, gid: u32) -> io::Result<usize> {
+    let mut moved = 0;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let source = entry.path();
+        let target = to.join(entry.file_name());
+        match fs::symlink_metadata(&target) {
+            Ok(meta) if meta.is_dir() && entry.file_type()?.is_dir() => {
+                moved += migrate(&source, &target, uid, gid)?;
+            }
+            Ok(_) => (),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                move_entry(&source, &target, uid, gid)?;
+                moved += 1;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    // fails while skipped entries remain, which is what keeps them safe
+    let _ = fs::remove_dir(from);
+    Ok(moved)
+}
+
+fn move_entry(source: &Path, target: &Path, uid: u32, gid: u32) -> io::Result<()> {
+    if fs::rename(source, target).is_ok() {
+        return Ok(());
+    }
+    // renaming fails across file systems, e.g. when moving off a small /var/lib
+    copy_tree(source, target, uid, gid)?;
+    let res = if fs::symlink_metadata(source)?.is_dir() {
+        fs::remove_dir_all(source)
+    } else {
+        fs::remove_file(source)
+    };
+    // a read-only source keeps its copy, the state lives on in the target
+    if let Err(error) = res {
+        tracing::warn!(?error, source = %source.display(), "unable to remove migrated entry");
+    }
+    Ok(())
+}
+
+fn copy_tree(source: &Path, target: &Path, uid: u32, gid: u32) -> io::Result<()> {
+    let meta = fs::symlink_metadata(source)?;
+    if meta.is_dir() {
+        fs::create_dir(target)?;
+        fs::set_permissions(target, meta.permissions())?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_tree(&entry.path(), &target.join(entry.file_name()), uid, gid)?;
+        }
+    } else if meta.file_type().is_symlink() {
+        unix_fs::symlink(fs::read_link(source)?, target)?;
+    } else {
+        fs::copy(source, target)?;
+    }
+    unix_fs::lchown(target, Some(uid), Some(gid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn locations_default_to_state_home() {
+        let locations = Locations::resolve(Config::default(), &Config::default(), env(&[]));
+        assert_eq!(locations.state_home, PathBuf::from(DEFAULT_STATE_HOME));
+        assert_eq!(locations.cache_home, PathBuf::from(DEFAULT_STATE_HOME).join(".cache"));
+    }
+
+    #[test]
+    fn locations_follow_precedence() {
+        let xdg = env(&[("XDG_DATA_HOME", "/xdg/data"), ("XDG_CACHE_HOME", "relative/cache")]);
+        let configured = Config {
+            data: None,
+            cache: Some(PathBuf::from("/srv/cache")),
+        };
+        let locations = Locations::resolve(Config::default(), &configured, xdg);
+        assert_eq!(locations.state_home, PathBuf::from("/xdg/data/gnosisvpn"));
+        assert_eq!(locations.cache_home, PathBuf::from("/srv/cache"));
+
+        let explicit = Config {
+            data: Some(PathBuf::from("/data")),
+            cache: None,
+        };
+        let locations = Locations::resolve(explicit, &Config::default(), env(&[("XDG_CACHE_HOME", "relative")]));
+        assert_eq!(locations.state_home, PathBuf::from("/data"));
+        assert_eq!(locations.cache_home, PathBuf::from("/data/.cache"));
+    }
+
+    #[test]
+    fn migrate_merges_and_keeps_existing_entries() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (from, to) = (dir.path().join("old"), dir.path().join("new"));
+        fs::create_dir_all(from.join(".config"))?;
+        fs::create_dir_all(to.join(".config"))?;
+        fs::write(from.join(".config/id"), "old id")?;
+        fs::write(from.join(".config/safe"), "old safe")?;
+        fs::write(to.join(".config/safe"), "new safe")?;
+        fs::write(from.join("db"), "old db")?;
+
+        let meta = fs::metadata(dir.path())?;
+        assert_eq!(migrate(&from, &to, meta.uid(), meta.gid())?, 2);
+
+        assert_eq!(fs::read_to_string(to.join(".config/id"))?, "old id");
+        assert_eq!(fs::read_to_string(to.join(".config/safe"))?, "new safe");
+        assert_eq!(fs::read_to_string(to.join("db"))?, "old db");
+        // the conflicting entry stays behind with its directories
+        assert!(from.join(".config/safe").exists());
+        assert!(!from.join("db").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn root_home_sits_next_to_the_state_home() -> anyhow::Result<()> {
+        assert_eq!(
+            root_home(Path::new(DEFAULT_STATE_HOME)),
+            PathBuf::from(format!("{DEFAULT_STATE_HOME}-root"))
+        );
+
+        let dir = tempfile::tempdir()?;
+        let private = dir.path().join("private");
+        fs::create_dir(&private)?;
+        fs::set_permissions(&private, fs::Permissions::from_mode(0o755))?;
+        ensure_private_dir(&private)?;
+        assert_eq!(fs::metadata(&private)?.mode() & 0o777, 0o700);
+
+        let link = dir.path().join("link");
+        unix_fs::symlink(&private, &link)?;
+        assert!(ensure_private_dir(&link).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,319 @@
+//! Free space monitoring of the data directory holding the node database.
+//!
+//! The node database grows while syncing and fails without much noise once the file system is
+//! full, so the root process samples the free space periodically and reports it in status when it
+//! drops below [`Config::min_free`]. [`compact`] rebuilds the node databases and drops rotated
+//! logs, on request, on a schedule or once space runs low. A rebuild writes a full copy of the
+//! database first, databases without room for it are left alone.
+use bytesize::ByteSize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::fs;
+use tokio::process::Command;
+
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::dirs;
+use crate::shell_command_ext::{self, Logs, RunOptions, ShellCommandExt};
+
+/// Marker in the cache home, its modification time is the last compaction.
+pub const LAST_COMPACTION_FILE: &str = "last-compaction";
+
+/// Rebuilding a grown node database takes a while.
+const COMPACT_TIMEOUT: Duration = Duration::from_secs(600);
+const DF_TIMEOUT: Duration = Duration::from_secs(10);
+/// File extensions of the SQLite databases below the state home.
+const DATABASE_EXTENSIONS: &[&str] = &["db", "sqlite", "sqlite3"];
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    /// Free space below which status reports a warning
+    pub min_free: ByteSize,
+    #[serde(with = "humantime_serde")]
+    pub interval: Duration,
+    /// Pause the worker to compact the node databases once free space runs low
+    pub compact: bool,
+    /// Compact regularly while disconnected, `None` only compacts on request or low space
+    #[serde(default, with = "humantime_serde::option")]
+    pub compact_every: Option<Duration>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            min_free: ByteSize::gib(1),
+            interval: Duration::from_secs(5 * 60),
+            compact: false,
+            compact_every: None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Unexpected df output: {0}")]
+    Unparsable(String),
+    #[error("Command error: {0}")]
+    Command(#[from] shell_command_ext::Error),
+    #[error("IO error: {0}")]
+    IO(#[from] std::io::Error),
+}
+
+/// Free space of the file system holding the data directory, reported in status.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "DiskSpaceUsage")]
+pub struct Usage {
+    #[schemars(with = "String")]
+    pub available: ByteSize,
+    #[schemars(with = "String")]
+    pub total: ByteSize,
+    #[schemars(with = "String")]
+    pub min_free: ByteSize,
+}
+
+impl Usage {
+    pub fn is_low(&self) -> bool {
+        self.available < self.min_free
+    }
+}
+
+impl Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Disk space: {} of {} available (warning below {})",
+            self.available, self.total, self.min_free
+        )
+    }
+}
+
+/// Space freed by [`compact`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reclaimed {
+    pub databases: usize,
+    /// Databases left alone, the file system had no room for their rebuilt copy
+    pub skipped: usize,
+    pub rotated_logs: usize,
+    pub bytes: u64,
+}
+
+/// Samples the file system holding `path`.
+pub async fn sample(path: &Path, min_free: ByteSize) -> Result<Usage, Error> {
+    let (total, available) = df(path).await?;
+    Ok(Usage {
+        available: ByteSize::kib(available),
+        total: ByteSize::kib(total),
+        min_free,
+    })
+}
+
+/// Total and available KiB of the file system holding `path`.
+async fn df(path: &Path) -> Result<(u64, u64), Error> {
+    // POSIX output is identical on Linux and macOS, sizes in KiB
+    let output = Command::new("df")
+        .arg("-Pk")
+        .arg(path)
+        .run_stdout(RunOptions::new(Logs::Suppress).timeout(DF_TIMEOUT))
+        .await?;
+    parse_df(&output).ok_or(Error::Unparsable(output))
+}
+
+/// Removes the rotated copies of `log_file` and runs `VACUUM` on every node database below
+/// `state_home` with room for its rebuilt copy, the worker must not be running.
+pub async fn compact(state_home: &Path, log_file: Option<&Path>) -> Result<Reclaimed, Error> {
+    let mut reclaimed = Reclaimed::default();
+    // frees space for the rebuilds
+    if let Some(log_file) = log_file {
+        for rotated in rotated_logs(log_file).await? {
+            reclaimed.bytes += fs::metadata(&rotated).await?.len();
+            fs::remove_file(&rotated).await?;
+            reclaimed.rotated_logs += 1;
+        }
+    }
+    for database in databases(state_home).await? {
+        let before = database_size(&database).await?;
+        let (_, available) = df(&database).await?;
+        if !has_room_for_rebuild(before, available) {
+            tracing::warn!(
+                database = %database.display(),
+                size = %ByteSize::b(before),
+                available = %ByteSize::kib(available),
+                "not enough free space to compact database"
+            );
+            reclaimed.skipped += 1;
+            continue;
+        }
+        // folds the write-ahead log back in before rebuilding
+        Command::new("sqlite3")
+            .arg(&database)
+            .arg("PRAGMA wal_checkpoint(TRUNCATE); VACUUM;")
+            .run(RunOptions::new(Logs::Print).timeout(COMPACT_TIMEOUT))
+            .await?;
+        let after = database_size(&database).await?;
+        tracing::debug!(database = %database.display(), before, after, "compacted database");
+        reclaimed.databases += 1;
+        reclaimed.bytes += before.saturating_sub(after);
+    }
+    Ok(reclaimed)
+}
+
+/// `VACUUM` writes the rebuilt database next to the original, which may take as much space again.
+fn has_room_for_rebuild(size_bytes: u64, available_kib: u64) -> bool {
+    available_kib.saturating_mul(1024) > size_bytes
+}
+
+/// Size of a database including its write-ahead log.
+async fn database_size(database: &Path) -> Result<u64, Error> {
+    let mut wal = database.as_os_str().to_owned();
+    wal.push("-wal");
+    let wal_size = fs::metadata(PathBuf::from(wal)).await.map(|m| m.len()).unwrap_or(0);
+    Ok(fs::metadata(database).await?.len() + wal_size)
+}
+
+/// Copies left behind by logrotate or newsyslog next to `log_file`, e.g. `gnosisvpn.log.1.gz`.
+async fn rotated_logs(log_file: &Path) -> Result<Vec<PathBuf>, Error> {
+    let (Some(dir), Some(name)) = (log_file.parent(), log_file.file_name().and_then(|n| n.to_str())) else {
+        return Ok(Vec::new());
+    };
+    let prefix = format!("{name}.");
+    let mut found = Vec::new();
+    let mut entries = fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let rotated = entry.file_name().to_str().is_some_and(|n| n.starts_with(&prefix));
+        if rotated && entry.file_type().await?.is_file() {
+            found.push(entry.path());
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// SQLite databases below `state_home`, leaving out the cache.
+async fn databases(state_home: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut found = Vec::new();
+    let mut pending = vec![state_home.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let file_type = entry.file_type().await?;
+            if file_type.is_dir() && entry.file_name() != dirs::CACHE_DIRECTORY {
+                pending.push(path);
+            } else if file_type.is_file() && is_database(&path) {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+fn is_database(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| DATABASE_EXTENSIONS.contains(&e))
+}
+
+/// Total and available KiB from `df -Pk` output.
+fn parse_df(output: &str) -> Option<(u64, u64)> {
+    let line = output.lines().nth(1)?;
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    // file system and mount point may contain spaces, the capacity column is the anchor
+    let capacity = fields.iter().position(|f| f.ends_with('%'))?;
+    let total = fields.get(capacity.checked_sub(3)?)?.parse().ok()?;
+    let available = fields.get(capacity - 1)?.parse().ok()?;
+    Some((total, available))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_df_output() {
+        let linux = "Filesystem     1024-blocks     Used Available Capacity Mounted on\n\
+                     /dev/sda1         51474912 20000000  29000000      41% /var/lib\n";
+        assert_eq!(parse_df(linux), Some((51474912, 29000000)));
+
+        let macos = "Filesystem    1024-blocks      Used Available Capacity  Mounted on\n\
+                     map auto_home           0         0         0   100%    /System/Volumes/Data/home\n";
+        assert_eq!(parse_df(macos), Some((0, 0)));
+
+        assert_eq!(parse_df("df: /nonexistent: No such file or directory\n"), None);
+    }
+
+    #[test]
+    fn low_below_min_free() {
+        let usage = Usage {
+            available: ByteSize::mib(512),
+            total: ByteSize::gib(8),
+            min_free: ByteSize::gib(1),
+        };
+        assert!(usage.is_low());
+        assert!(
+            !Usage {
+                available: ByteSize::gib(2),
+                ..usage
+            }
+            .is_low()
+        );
+    }
+
+    #[test]
+    fn rebuild_needs_room_for_a_full_copy() {
+        assert!(has_room_for_rebuild(
+            ByteSize::mib(100).as_u64(),
+            ByteSize::mib(101).as_u64() / 1024
+        ));
+        assert!(!has_room_for_rebuild(
+            ByteSize::mib(100).as_u64(),
+            ByteSize::mib(100).as_u64() / 1024
+        ));
+        assert!(!has_room_for_rebuild(1, 0));
+    }
+
+    #[tokio::test]
+    async fn finds_databases_outside_the_cache() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let home = dir.path();
+        std::fs::create_dir_all(home.join("db"))?;
+        std::fs::create_dir_all(home.join(dirs::CACHE_DIRECTORY))?;
+        std::fs::write(home.join("db/index.sqlite"), "")?;
+        std::fs::write(home.join("db/index.sqlite-wal"), "")?;
+        std::fs::write(home.join("peers.db"), "")?;
+        std::fs::write(home.join(dirs::CACHE_DIRECTORY).join("other.db"), "")?;
+
+        assert_eq!(
+            databases(home).await?,
+            vec![home.join("db/index.sqlite"), home.join("peers.db")]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn finds_rotated_logs_only() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let log_file = dir.path().join("gnosisvpn.log");
+        for name in [
+            "gnosisvpn.log",
+            "gnosisvpn.log.0",
+            "gnosisvpn.log.1.gz",
+            "gnosisvpn.logs",
+            "other.log.0",
+        ] {
+            std::fs::write(dir.path().join(name), "")?;
+        }
+
+        assert_eq!(
+            rotated_logs(&log_file).await?,
+            vec![
+                dir.path().join("gnosisvpn.log.0"),
+                dir.path().join("gnosisvpn.log.1.gz")
+            ]
+        );
+        Ok(())
+    }
+}
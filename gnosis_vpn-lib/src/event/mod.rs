@@ -2,13 +2,16 @@
 
 use serde::{Deserialize, Serialize};
 use tokio::sync::oneshot;
+use url::Url;
 
 use std::net::Ipv4Addr;
 use std::time::Duration;
 
 use crate::command::{Response, WorkerCommand};
 use crate::config::Config;
+use crate::exit_reports;
 use crate::ping;
+use crate::public_ip::PublicIp;
 use crate::wireguard::{self, WireGuard};
 use crate::worker_params::WorkerParams;
 
@@ -60,6 +63,10 @@ pub enum WorkerToRoot {
     Response { resp: Response, id: u64 },
     /// Request to root execution
     RequestToRoot(RequestToRoot),
+    /// Core loop was aborted by the watchdog and awaits fresh startup params
+    CoreStalled { stalled_for: Duration },
+    /// Latest worker log lines for the root log buffer
+    LogLines(Vec<String>),
 }
 
 /// Runner requesting root command and usually waiting for response
@@ -79,6 +86,16 @@ pub(crate) enum RunnerToRoot {
         options: ping::Options,
         resp: oneshot::Sender<Result<Duration, String>>,
     },
+    PublicIp {
+        endpoint: Url,
+        timeout: Duration,
+        resp: oneshot::Sender<Result<PublicIp, String>>,
+    },
+    ExitReport {
+        url: Url,
+        report: exit_reports::Report,
+        resp: oneshot::Sender<Result<(), String>>,
+    },
 }
 
 /// Data required for WireGuard operations
@@ -114,6 +131,18 @@ pub enum RequestToRoot {
         request_id: u64,
         options: ping::Options,
     },
+    /// Query the echo endpoint through the tunnel for the exit address.
+    PublicIp {
+        request_id: u64,
+        endpoint: Url,
+        timeout: Duration,
+    },
+    /// Send a connection outcome report through the tunnel, the operator must not learn the host address.
+    ExitReport {
+        request_id: u64,
+        url: Url,
+        report: exit_reports::Report,
+    },
     /// Fire-and-forget: ask root to hold resolved IPs so they survive a worker restart.
     CacheBlokliIps {
         ips: Vec<Ipv4Addr>,
@@ -122,6 +151,12 @@ pub enum RequestToRoot {
     UpdatePeerIps {
         peer_ips: Vec<Ipv4Addr>,
     },
+    /// Fire-and-forget: with `kill_switch` enabled, block non-tunnel traffic while the tunnel is down.
+    KillSwitch {
+        engage: bool,
+    },
+    /// Fire-and-forget: core gave up on the target destination, a worker restart must not reconnect to it.
+    TargetCleared,
 }
 
 /// Root execution response from root process.
@@ -141,4 +176,12 @@ pub enum ResponseFromRoot {
         request_id: u64,
         res: Result<Duration, String>,
     },
+    PublicIp {
+        request_id: u64,
+        res: Result<PublicIp, String>,
+    },
+    ExitReport {
+        request_id: u64,
+        res: Result<(), String>,
+    },
 }
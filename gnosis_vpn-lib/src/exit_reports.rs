@@ -0,0 +1,257 @@
+//! Connection outcome reports for exit operators, opt-in per destination via `report_url`.
+//!
+//! Operators only see reports of destinations pointing at their endpoint. A report carries the
+//! exit address, hop count, client version, the hour of the attempt and for failures the phase it
+//! failed in. Node address, error messages and connection durations stay on the machine.
+//! Everything sent recently is listed by `gnosis_vpn-ctl exit-reports` with its delivery status.
+//!
+//! Reports are queued and sent by the root process through the tunnel once one is up, so the
+//! operator sees an exit address and not the address of the host. At most [`MAX_PER_HOUR`]
+//! reports are recorded per destination and hour.
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use url::Url;
+
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+use crate::connection::destination::{Address, Destination};
+use crate::connection::up::Phase;
+use crate::{proxy, serde_utils};
+
+/// Reports per destination and hour, a flapping connection must not flood the operator.
+pub const MAX_PER_HOUR: usize = 6;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Reports are kept in memory for ctl only, older entries are dropped.
+const MAX_ENTRIES: usize = 50;
+const SECS_PER_HOUR: u64 = 60 * 60;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    Failure { phase: Phase },
+}
+
+/// Everything that is sent, nothing more.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "ExitReport")]
+pub struct Report {
+    #[serde(with = "serde_utils::address")]
+    #[schemars(with = "String")]
+    pub exit: Address,
+    pub hops: usize,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+    /// Start of the hour the attempt finished in
+    #[serde(with = "serde_utils::system_time")]
+    #[schemars(with = "u64")]
+    pub hour: SystemTime,
+    pub version: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "delivery", rename_all = "snake_case")]
+pub enum Delivery {
+    /// Waiting for a tunnel to send it through
+    Queued,
+    Pending,
+    Delivered,
+    Failed {
+        reason: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "ExitReportEntry")]
+pub struct Entry {
+    pub destination: String,
+    #[schemars(with = "String")]
+    pub url: Url,
+    pub report: Report,
+    #[serde(flatten)]
+    pub delivery: Delivery,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+}
+
+#[derive(Default)]
+pub struct Reporter {
+    next_id: u64,
+    entries: VecDeque<(u64, Entry)>,
+}
+
+impl Reporter {
+    /// Queues a report if `destination` opted in and has not used up its reports of this hour.
+    pub fn record(&mut self, destination: &Destination, outcome: Outcome, now: SystemTime) -> bool {
+        let Some(url) = destination.report_url.clone() else {
+            return false;
+        };
+        let secs = now.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs();
+        let hour = SystemTime::UNIX_EPOCH + Duration::from_secs(secs - secs % SECS_PER_HOUR);
+        let this_hour = self
+            .entries
+            .iter()
+            .filter(|(_, e)| e.destination == destination.id && e.report.hour == hour)
+            .count();
+        if this_hour >= MAX_PER_HOUR {
+            tracing::debug!(destination = %destination.id, "exit report limit reached for this hour");
+            return false;
+        }
+        let report = Report {
+            exit: destination.address,
+            hops: destination.routing.hop_count(),
+            outcome,
+            hour,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        };
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entries.push_back((
+            id,
+            Entry {
+                destination: destination.id.clone(),
+                url,
+                report,
+                delivery: Delivery::Queued,
+            },
+        ));
+        while self.entries.len() > MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        true
+    }
+
+    /// Queued reports to send through the tunnel now, they stay pending until [`Reporter::delivered`].
+    pub fn take_queued(&mut self) -> Vec<(u64, Url, Report)> {
+        self.entries
+            .iter_mut()
+            .filter(|(_, e)| e.delivery == Delivery::Queued)
+            .map(|(id, entry)| {
+                entry.delivery = Delivery::Pending;
+                (*id, entry.url.clone(), entry.report.clone())
+            })
+            .collect()
+    }
+
+    pub fn delivered(&mut self, id: u64, res: Result<(), String>) {
+        let Some((_, entry)) = self.entries.iter_mut().find(|(i, _)| *i == id) else {
+            return;
+        };
+        entry.delivery = match res {
+            Ok(()) => Delivery::Delivered,
+            Err(reason) => Delivery::Failed { reason },
+        };
+    }
+
+    /// Newest first.
+    pub fn list(&self) -> Vec<Entry> {
+        self.entries.iter().rev().map(|(_, entry)| entry.clone()).collect()
+    }
+}
+
+/// Sends `report`, only to be called while the tunnel is up so it leaves through the exit.
+pub async fn upload(url: Url, report: &Report) -> Result<(), Error> {
+    proxy::direct_builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?
+        .post(url)
+        .json(report)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::connection::destination::HopRouting;
+    use std::collections::HashMap;
+
+    fn destination(report_url: Option<&str>) -> Destination {
+        Destination::new(
+            "exit".to_string(),
+            Address::from([1u8; 20]),
+            HopRouting::try_from(1).expect("conversion cannot fail"),
+            HashMap::new(),
+        )
+        .with_report_url(report_url.map(|u| u.parse().expect("valid url")))
+    }
+
+    #[test]
+    fn nothing_is_reported_without_opt_in() {
+        let mut reporter = Reporter::default();
+        assert!(!reporter.record(&destination(None), Outcome::Success, SystemTime::now()));
+        assert!(reporter.list().is_empty());
+    }
+
+    #[test]
+    fn reports_are_coarse_and_track_delivery() {
+        let mut reporter = Reporter::default();
+        let dest = destination(Some("https://exit.example/report"));
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(10 * SECS_PER_HOUR + 1234);
+        assert!(reporter.record(&dest, Outcome::Success, now));
+        let sent = reporter.take_queued();
+        let (first, _, report) = sent.first().expect("queued");
+        assert_eq!(
+            report.hour,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(10 * SECS_PER_HOUR)
+        );
+        assert_eq!(report.hops, 1);
+
+        let failure = Outcome::Failure {
+            phase: Phase::OpeningPing,
+        };
+        assert!(reporter.record(&dest, failure.clone(), now));
+        reporter.delivered(*first, Ok(()));
+
+        let list = reporter.list();
+        assert_eq!(list[0].report.outcome, failure);
+        assert_eq!(list[0].delivery, Delivery::Queued);
+        assert_eq!(list[1].delivery, Delivery::Delivered);
+
+        // sent once only
+        let (second, _, _) = reporter.take_queued().pop().expect("queued");
+        assert_ne!(*first, second);
+        assert!(reporter.take_queued().is_empty());
+        assert_eq!(reporter.list()[0].delivery, Delivery::Pending);
+    }
+
+    #[test]
+    fn reports_are_limited_per_destination_and_hour() {
+        let mut reporter = Reporter::default();
+        let dest = destination(Some("https://exit.example/report"));
+        let mut other = dest.clone();
+        other.id = "other".to_string();
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(10 * SECS_PER_HOUR);
+        for _ in 0..MAX_PER_HOUR {
+            assert!(reporter.record(&dest, Outcome::Success, now));
+        }
+        assert!(!reporter.record(&dest, Outcome::Success, now));
+        assert!(reporter.record(&other, Outcome::Success, now));
+        assert!(reporter.record(&dest, Outcome::Success, now + Duration::from_secs(SECS_PER_HOUR)));
+    }
+
+    #[test]
+    fn payload_has_no_identifiers() -> anyhow::Result<()> {
+        let mut reporter = Reporter::default();
+        assert!(reporter.record(
+            &destination(Some("https://exit.example/report")),
+            Outcome::Success,
+            SystemTime::now(),
+        ));
+        let (_, _, report) = reporter.take_queued().pop().expect("queued");
+        let json = serde_json::to_value(&report)?;
+        let mut keys: Vec<&String> = json.as_object().expect("object").keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["exit", "hops", "hour", "outcome", "version"]);
+        Ok(())
+    }
+}
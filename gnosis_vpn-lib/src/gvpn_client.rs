@@ -1,14 +1,18 @@
-use reqwest::Client;
+use async_trait::async_trait;
+use reqwest::header::{self, HeaderValue};
+use reqwest::{Client, StatusCode};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use thiserror::Error;
 use url::Url;
 
 use std::fmt::{self, Display};
-use std::net::{Ipv4Addr, SocketAddr};
-use std::time::Duration;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::{Duration, SystemTime};
 
 use crate::remote_data;
+use crate::wireguard;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Registration {
@@ -17,6 +21,43 @@ pub struct Registration {
     newly_registered: bool,
     server_public_key: String,
     preshared_key: String,
+    #[serde(default)]
+    capabilities: Capabilities,
+}
+
+/// Interface parameters an exit announces on registration, exits predating this send none.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Capabilities {
+    /// Largest tunnel MTU the exit handles
+    pub mtu: Option<u16>,
+    /// Resolvers reachable through the exit, used instead of the default DNS servers
+    pub dns: Option<Vec<IpAddr>>,
+    /// Whether the exit routes IPv6, only logged as the tunnel itself carries IPv4 only
+    #[serde(default)]
+    pub ipv6: bool,
+    pub max_bandwidth_bps: Option<u64>,
+    /// Unix timestamp in seconds after which the exit drops the registration, the connection is
+    /// re-established shortly before
+    pub expires_at: Option<u64>,
+}
+
+impl Capabilities {
+    /// Announced MTU within what a WireGuard tunnel can use, IPv6 peers need at least 1280.
+    pub fn tunnel_mtu(&self) -> Option<u32> {
+        self.mtu
+            .map(|mtu| u32::from(mtu).clamp(wireguard::MIN_WG_MTU, wireguard::WG_MTU))
+    }
+
+    /// Announced resolvers in the comma separated form of the `dns` setting.
+    pub fn dns_servers(&self) -> Option<String> {
+        let servers: Vec<String> = self.dns.as_ref()?.iter().map(|ip| ip.to_string()).collect();
+        (!servers.is_empty()).then(|| servers.join(", "))
+    }
+
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.expires_at
+            .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -24,6 +65,24 @@ pub struct Input {
     public_key: String,
     socket_addr: SocketAddr,
     timeout: Duration,
+    credentials: Option<Credentials>,
+}
+
+/// Credentials presented to exits requiring authentication.
+#[derive(Clone)]
+pub enum Credentials {
+    Token(String),
+    /// Challenge fetched from the exit, signed with the node's packet key
+    Signature {
+        challenge: String,
+        signature: String,
+        public_key: String,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Challenge {
+    challenge: String,
 }
 
 #[derive(Error, Debug)]
@@ -38,6 +97,10 @@ pub enum Error {
     ConnectionReset(reqwest::Error),
     #[error("Registration not found")]
     RegistrationNotFound,
+    #[error("Exit rejected authentication (HTTP {0})")]
+    AuthRejected(u16),
+    #[error("Access token contains characters not allowed in a header")]
+    InvalidToken,
 }
 
 impl Input {
@@ -46,9 +109,23 @@ impl Input {
             public_key,
             socket_addr,
             timeout,
+            credentials: None,
         }
     }
 
+    pub fn with_credentials(mut self, credentials: Option<Credentials>) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    pub fn socket_addr(&self) -> SocketAddr {
+        self.socket_addr
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
     pub fn public_key(&self) -> &str {
         &self.public_key
     }
@@ -66,27 +143,44 @@ impl Registration {
     pub fn preshared_key(&self) -> String {
         self.preshared_key.clone()
     }
+
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 pub struct Versions {
     pub versions: Vec<String>,
     pub latest: String,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 pub struct Health {
     pub slots: Slots,
     pub load_avg: LoadAvg,
+    /// Announced by exits scheduled to go offline, exits predating this send none
+    #[serde(default)]
+    pub maintenance: Option<Maintenance>,
+}
+
+/// Maintenance notice of an exit, clients should move elsewhere before `retire_after`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct Maintenance {
+    /// Unix timestamp in seconds after which the exit stops serving tunnels
+    pub retire_after: u64,
+    /// Operator provided reason, e.g. a hardware migration
+    #[serde(default)]
+    pub message: Option<String>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 pub struct Slots {
     pub available: u32,
     pub connected: u32,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 pub struct LoadAvg {
     pub one: f32,
     pub five: f32,
@@ -166,50 +260,181 @@ pub async fn health(client: &Client, socket_addr: SocketAddr, timeout: Duration)
     Ok(resp)
 }
 
-pub async fn register(client: &Client, input: &Input) -> Result<Registration, Error> {
+/// Fetch a one time challenge to sign for exits using [`Credentials::Signature`].
+pub async fn challenge(client: &Client, socket_addr: SocketAddr, timeout: Duration) -> Result<String, Error> {
     let headers = remote_data::json_headers();
     let url = Url::parse(
         format!(
-            "http://{host}:{port}/api/v1/clients/register",
-            host = input.socket_addr.ip(),
-            port = input.socket_addr.port()
+            "http://{host}:{port}/api/v1/clients/challenge",
+            host = socket_addr.ip(),
+            port = socket_addr.port()
         )
         .as_str(),
     )?;
-    let json = json!({
-        "public_key": input.public_key,
-    });
-    tracing::debug!(?headers, body = ?json, ?url, "post register client");
+    tracing::debug!(?headers, ?url, "get registration challenge");
     let resp = client
-        .post(url)
-        .json(&json)
-        .timeout(input.timeout)
+        .get(url)
+        .timeout(timeout)
         .headers(headers)
         .send()
         .await
-        // connection error checks happen before response
         .map_err(connect_errors)?
-        .error_for_status()?
-        .json::<Registration>()
+        .error_for_status()
+        .map_err(auth_errors)?
+        .json::<Challenge>()
         .await?;
 
-    Ok(resp)
+    Ok(resp.challenge)
 }
 
-pub async fn unregister(client: &Client, input: &Input) -> Result<(), Error> {
-    let headers = remote_data::json_headers();
+/// Exit registration API versions this client speaks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiVersion {
+    V1,
+    /// Announces capabilities and expiry, unregisters via `DELETE`
+    V2,
+}
+
+/// Registration protocol of a single exit API version.
+#[async_trait]
+pub trait ExitApi: Send + Sync {
+    fn version(&self) -> ApiVersion;
+    async fn register(&self, client: &Client, input: &Input) -> Result<Registration, Error>;
+    async fn unregister(&self, client: &Client, input: &Input) -> Result<(), Error>;
+}
+
+struct V1;
+struct V2;
+
+impl ApiVersion {
+    /// Supported versions, preferred first.
+    pub const SUPPORTED: [ApiVersion; 2] = [ApiVersion::V2, ApiVersion::V1];
+
+    /// Pick the preferred version the exit offers as well.
+    pub fn negotiate(server_versions: &[String]) -> Option<ApiVersion> {
+        Self::SUPPORTED
+            .into_iter()
+            .find(|v| server_versions.iter().any(|sv| sv == v.as_str()))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "v1",
+            ApiVersion::V2 => "v2",
+        }
+    }
+
+    pub fn api(&self) -> &'static dyn ExitApi {
+        match self {
+            ApiVersion::V1 => &V1,
+            ApiVersion::V2 => &V2,
+        }
+    }
+}
+
+#[async_trait]
+impl ExitApi for V1 {
+    fn version(&self) -> ApiVersion {
+        ApiVersion::V1
+    }
+
+    async fn register(&self, client: &Client, input: &Input) -> Result<Registration, Error> {
+        register_at(client, input, "api/v1/clients/register").await
+    }
+
+    async fn unregister(&self, client: &Client, input: &Input) -> Result<(), Error> {
+        let headers = remote_data::json_headers();
+        let url = exit_url(input.socket_addr, "api/v1/clients/unregister")?;
+        let mut json = serde_json::Map::new();
+        json.insert("public_key".to_string(), json!(input.public_key));
+        tracing::debug!(?headers, body = ?json, ?url, "post unregister client");
+        client
+            .post(url)
+            .json(&json)
+            .timeout(input.timeout)
+            .headers(headers)
+            .send()
+            .await
+            // connection error checks happen before response
+            .map_err(connect_errors)?
+            .error_for_status()
+            // response error checks happen after response
+            .map_err(response_errors)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ExitApi for V2 {
+    fn version(&self) -> ApiVersion {
+        ApiVersion::V2
+    }
+
+    async fn register(&self, client: &Client, input: &Input) -> Result<Registration, Error> {
+        register_at(client, input, "api/v2/clients").await
+    }
+
+    async fn unregister(&self, client: &Client, input: &Input) -> Result<(), Error> {
+        let headers = remote_data::json_headers();
+        let mut url = exit_url(input.socket_addr, "api/v2/clients")?;
+        url.path_segments_mut()
+            .map_err(|_| Error::Url(url::ParseError::RelativeUrlWithCannotBeABaseBase))?
+            .push(&input.public_key);
+        tracing::debug!(?headers, ?url, "delete client");
+        client
+            .delete(url)
+            .timeout(input.timeout)
+            .headers(headers)
+            .send()
+            .await
+            .map_err(connect_errors)?
+            .error_for_status()
+            .map_err(response_errors)?;
+
+        Ok(())
+    }
+}
+
+fn exit_url(socket_addr: SocketAddr, path: &str) -> Result<Url, Error> {
     let url = Url::parse(
         format!(
-            "http://{host}:{port}/api/v1/clients/unregister",
-            host = input.socket_addr.ip(),
-            port = input.socket_addr.port()
+            "http://{host}:{port}/{path}",
+            host = socket_addr.ip(),
+            port = socket_addr.port()
         )
         .as_str(),
     )?;
-    let mut json = serde_json::Map::new();
-    json.insert("public_key".to_string(), json!(input.public_key));
-    tracing::debug!(?headers, body = ?json, ?url, "post unregister client");
-    client
+    Ok(url)
+}
+
+async fn register_at(client: &Client, input: &Input, path: &str) -> Result<Registration, Error> {
+    let mut headers = remote_data::json_headers();
+    let url = exit_url(input.socket_addr, path)?;
+    let mut json = json!({
+        "public_key": input.public_key,
+    });
+    tracing::debug!(?headers, body = ?json, ?url, "post register client");
+    match &input.credentials {
+        Some(Credentials::Token(token)) => {
+            let value = HeaderValue::from_str(&format!("Bearer {token}")).map_err(|_| Error::InvalidToken)?;
+            headers.insert(header::AUTHORIZATION, value);
+        }
+        Some(Credentials::Signature {
+            challenge,
+            signature,
+            public_key,
+        }) => {
+            json["auth"] = json!({
+                "challenge": challenge,
+                "signature": signature,
+                "public_key": public_key,
+            });
+        }
+        None => (),
+    }
+    let resp = client
         .post(url)
         .json(&json)
         .timeout(input.timeout)
@@ -219,10 +444,11 @@ pub async fn unregister(client: &Client, input: &Input) -> Result<(), Error> {
         // connection error checks happen before response
         .map_err(connect_errors)?
         .error_for_status()
-        // response error checks happen after response
-        .map_err(response_errors)?;
+        .map_err(auth_errors)?
+        .json::<Registration>()
+        .await?;
 
-    Ok(())
+    Ok(resp)
 }
 
 fn connect_errors(err: reqwest::Error) -> Error {
@@ -235,6 +461,15 @@ fn connect_errors(err: reqwest::Error) -> Error {
     }
 }
 
+fn auth_errors(err: reqwest::Error) -> Error {
+    match err.status() {
+        Some(status) if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN => {
+            Error::AuthRejected(status.as_u16())
+        }
+        _ => err.into(),
+    }
+}
+
 fn response_errors(err: reqwest::Error) -> Error {
     if err.status() == Some(reqwest::StatusCode::NOT_FOUND) {
         Error::RegistrationNotFound
@@ -243,6 +478,24 @@ fn response_errors(err: reqwest::Error) -> Error {
     }
 }
 
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Credentials::Token(_) => f.debug_tuple("Token").field(&"****").finish(),
+            Credentials::Signature { public_key, .. } => f
+                .debug_struct("Signature")
+                .field("public_key", public_key)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+impl Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
 impl Display for Registration {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "WgRegistration {{ ip: {} }}", self.ip)
@@ -279,8 +532,110 @@ impl Display for Versions {
     }
 }
 
+impl Maintenance {
+    /// `None` for a timestamp the system clock cannot represent, such a notice is invalid.
+    pub fn retires_at(&self) -> Option<SystemTime> {
+        SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(self.retire_after))
+    }
+}
+
+impl Display for Maintenance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.retires_at() {
+            Some(at) => write!(f, "retiring at {}", humantime::format_rfc3339_seconds(at))?,
+            None => write!(f, "retiring at invalid timestamp {}", self.retire_after)?,
+        }
+        if let Some(message) = &self.message {
+            write!(f, " ({message})")?;
+        }
+        Ok(())
+    }
+}
+
 impl Display for Health {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}, {}", self.load_avg, self.slots)
+        write!(f, "{}, {}", self.load_avg, self.slots)?;
+        if let Some(maintenance) = &self.maintenance {
+            write!(f, ", {maintenance}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registration_without_capabilities_uses_defaults() {
+        let reg: Registration = serde_json::from_str(
+            r#"{"public_key":"pk","ip":"10.128.0.5","newly_registered":true,"server_public_key":"spk","preshared_key":"psk"}"#,
+        )
+        .unwrap();
+        assert_eq!(reg.capabilities(), &Capabilities::default());
+    }
+
+    #[test]
+    fn registration_reads_capabilities() {
+        let reg: Registration = serde_json::from_str(
+            r#"{"public_key":"pk","ip":"10.128.0.5","newly_registered":true,"server_public_key":"spk","preshared_key":"psk",
+                "capabilities":{"mtu":1280,"dns":["10.128.0.1"],"ipv6":false,"max_bandwidth_bps":50000000}}"#,
+        )
+        .unwrap();
+        let caps = reg.capabilities();
+        assert_eq!(caps.mtu, Some(1280));
+        assert_eq!(caps.dns, Some(vec!["10.128.0.1".parse().unwrap()]));
+        assert_eq!(caps.max_bandwidth_bps, Some(50_000_000));
+        assert_eq!(caps.expires_at, None);
+    }
+
+    #[test]
+    fn capabilities_pick_interface_parameters() {
+        let caps: Capabilities =
+            serde_json::from_str(r#"{"mtu":1380,"dns":["10.128.0.1","10.128.0.2"],"expires_at":1800000000}"#).unwrap();
+        assert_eq!(caps.tunnel_mtu(), Some(1380));
+        assert_eq!(caps.dns_servers().as_deref(), Some("10.128.0.1, 10.128.0.2"));
+        assert_eq!(
+            caps.expires_at(),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_800_000_000))
+        );
+
+        let caps: Capabilities = serde_json::from_str(r#"{"mtu":576,"dns":[]}"#).unwrap();
+        assert_eq!(caps.tunnel_mtu(), Some(wireguard::MIN_WG_MTU));
+        assert_eq!(caps.dns_servers(), None);
+
+        let caps: Capabilities = serde_json::from_str(r#"{"mtu":9000}"#).unwrap();
+        assert_eq!(caps.tunnel_mtu(), Some(wireguard::WG_MTU));
+    }
+
+    #[test]
+    fn health_reads_maintenance_notice() {
+        let load = r#""slots":{"available":9,"connected":1},"load_avg":{"one":0.1,"five":0.2,"fifteen":0.3,"nproc":4}"#;
+        let health: Health = serde_json::from_str(&format!("{{{load}}}")).unwrap();
+        assert_eq!(health.maintenance, None);
+
+        let health: Health = serde_json::from_str(&format!(
+            r#"{{{load},"maintenance":{{"retire_after":1700000000,"message":"moving racks"}}}}"#
+        ))
+        .unwrap();
+        let maintenance = health.maintenance.expect("notice parsed");
+        assert_eq!(
+            maintenance.retires_at(),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(maintenance.message.as_deref(), Some("moving racks"));
+    }
+
+    #[test]
+    fn maintenance_beyond_the_clock_is_invalid() {
+        let maintenance = Maintenance {
+            retire_after: u64::MAX,
+            message: None,
+        };
+        assert_eq!(maintenance.retires_at(), None);
+        assert_eq!(
+            maintenance.to_string(),
+            format!("retiring at invalid timestamp {}", u64::MAX)
+        );
     }
 }
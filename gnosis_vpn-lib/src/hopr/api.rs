@@ -5,11 +5,21 @@ use edgli::{
     hopr_lib::{
         HoprSessionClientConfig,
         api::{
-            chain::{AccountSelector, ChainReadAccountOperations},
-            node::HasChainApi,
+            PeerId,
+            chain::{
+                AccountSelector, ChainEvent, ChainEvents, ChainReadAccountOperations, ChainReadSafeOperations,
+                ChainWriteAccountOperations, DeployedSafe, SafeSelector,
+            },
+            graph::{EdgeLinkObservable, EdgeObservableRead, NetworkGraphView},
+            network::NetworkView,
+            node::{HasChainApi, HasGraphView, HasNetworkView},
             types::{
+                crypto::prelude::ChainKeypair,
                 internal::channels::ChannelStatus,
-                primitive::{prelude::Address, traits::ToHex},
+                primitive::{
+                    prelude::{Address, Balance, WxHOPR},
+                    traits::ToHex,
+                },
             },
         },
         errors::HoprLibError,
@@ -19,7 +29,7 @@ use edgli::{
         },
     },
 };
-use futures_util::{StreamExt, future::AbortHandle};
+use futures_util::{Stream, StreamExt, TryFutureExt, future::AbortHandle};
 use hopr_utils_session::{
     HopSessionFactory, ListenerId, ListenerJoinHandles, SessionTargetSpec, create_tcp_client_binding,
     create_udp_client_binding,
@@ -28,12 +38,13 @@ use multiaddr::Protocol;
 use tracing::instrument;
 
 use std::collections::{BTreeSet, HashMap};
+use std::convert::identity;
 use std::{
     net::{Ipv4Addr, SocketAddr},
     sync::Arc,
 };
 
-use crate::peer::Peer;
+use crate::peer::{self, Peer};
 use crate::{
     balance::{self, Balances},
     hopr::{HoprError, types::SessionClientMetadata},
@@ -43,6 +54,7 @@ use crate::{
 pub struct Hopr {
     edgli: Arc<edgli::Edgli>,
     open_listeners: Arc<ListenerJoinHandles>,
+    chain_key: ChainKeypair,
 }
 
 impl Hopr {
@@ -55,6 +67,7 @@ impl Hopr {
         init_visitor: impl Fn(EdgliInitState) + Send + 'static,
     ) -> Result<Self, HoprError> {
         tracing::debug!("running hopr edge node");
+        let chain_key = keys.chain_key.clone();
         let edge_node = Edgli::new(
             cfg,
             keys,
@@ -71,6 +84,7 @@ impl Hopr {
         Ok(Self {
             edgli: Arc::new(edge_node),
             open_listeners: Default::default(),
+            chain_key,
         })
     }
 
@@ -334,9 +348,12 @@ impl Hopr {
             .map_err(|e| HoprError::TelemetryReactorStart(e.to_string()))
     }
 
+    /// Announced peers, marked with whether the node is connected to them and how well they answer probes.
     #[tracing::instrument(skip(self), level = "debug", ret)]
     pub async fn announced_peers(&self) -> Result<HashMap<Address, Peer>, HoprError> {
         tracing::debug!("query hopr announced peers");
+        let connected = self.edgli.network_view().connected_peers();
+        let graph = self.edgli.graph();
         let selector = AccountSelector::default().with_public_only(true);
         let mut stream = self
             .edgli
@@ -348,12 +365,22 @@ impl Hopr {
         while let Some(entry) = stream.next().await {
             let ipv4_addrs = extract_ipv4_addrs(entry.get_multiaddrs());
             if !ipv4_addrs.is_empty() {
-                peers.insert(entry.chain_addr, Peer::new(entry.chain_addr, ipv4_addrs));
+                let quality = graph.edge(graph.identity(), &entry.public_key).and_then(|edge| {
+                    edge.immediate_qos().map(|qos| peer::Quality {
+                        score: qos.score(),
+                        latency: qos.average_latency(),
+                    })
+                });
+                let is_connected = connected.contains(&PeerId::from(&entry.public_key));
+                peers.insert(
+                    entry.chain_addr,
+                    Peer::new(entry.chain_addr, ipv4_addrs).with_connection(is_connected, quality),
+                );
             }
         }
         tracing::debug!(
             peers = %peers.iter()
-                .map(|(addr, p)| format!("{addr}:{:?}", p.ipv4_addrs))
+                .map(|(addr, p)| format!("{addr}:{:?}:{}", p.ipv4_addrs, if p.connected { "connected" } else { "-" }))
                 .collect::<Vec<_>>()
                 .join(" "),
             "announced peers"
@@ -361,6 +388,46 @@ impl Hopr {
         Ok(peers)
     }
 
+    /// On-chain events from now on, the stream ends when the node shuts down.
+    pub fn chain_events(&self) -> Result<impl Stream<Item = ChainEvent> + Send + 'static, HoprError> {
+        self.edgli
+            .chain_api()
+            .subscribe()
+            .map_err(|e| HoprError::HoprLib(HoprLibError::GeneralError(e.to_string())))
+    }
+
+    /// Moves `amount` of the node's own wxHOPR into its safe, resolves with the transaction hash once it is on chain.
+    #[tracing::instrument(skip(self), level = "debug", ret, err)]
+    pub async fn withdraw_wxhopr_to_safe(&self, amount: Balance<WxHOPR>) -> Result<String, HoprError> {
+        let safe_address = self.edgli.safe_address();
+        let tx_hash = self
+            .edgli
+            .chain_api()
+            .withdraw_from_signer(&self.chain_key, amount, &safe_address)
+            .and_then(identity)
+            .await
+            .map_err(|e| HoprError::HoprLib(HoprLibError::GeneralError(e.to_string())))?;
+        Ok(tx_hash.to_string())
+    }
+
+    /// The safe as registered on chain and the wxHOPR allowance it grants the channels contract.
+    #[tracing::instrument(skip(self), level = "debug", ret, err)]
+    pub async fn safe_permissions(
+        &self,
+        safe_address: Address,
+    ) -> Result<(Option<DeployedSafe>, Balance<WxHOPR>), HoprError> {
+        let chain_api = self.edgli.chain_api();
+        let safe = chain_api
+            .safe_info(SafeSelector::Address(safe_address))
+            .await
+            .map_err(|e| HoprError::HoprLib(HoprLibError::GeneralError(e.to_string())))?;
+        let allowance = chain_api
+            .safe_allowance::<WxHOPR, _>(safe_address)
+            .await
+            .map_err(|e| HoprError::HoprLib(HoprLibError::GeneralError(e.to_string())))?;
+        Ok((safe, allowance))
+    }
+
     #[tracing::instrument(skip(self), level = "debug", ret, err)]
     pub async fn ideal_balance_recommendation(
         &self,
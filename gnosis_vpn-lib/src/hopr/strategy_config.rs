@@ -23,6 +23,10 @@ pub struct StrategyConfig {
 
     /// When `Some`, channels are opened exclusively to these peers; `None` uses quality-score selection.
     pub channel_allowlist: Option<HashSet<Address>>,
+
+    /// When `false` the reactor is not started at all and channels are opened and funded manually.
+    /// Not part of the upstream configuration.
+    pub auto_fund_channels: bool,
 }
 
 impl Default for StrategyConfig {
@@ -33,6 +37,7 @@ impl Default for StrategyConfig {
             min_open_channels: def.min_open_channels,
             target_open_channels: def.target_open_channels,
             channel_allowlist: def.channel_allowlist,
+            auto_fund_channels: true,
         }
     }
 }
@@ -0,0 +1,126 @@
+use super::Message;
+
+pub(super) fn text(msg: &Message) -> String {
+    match msg {
+        Message::WaitingForNetwork => {
+            "Waiting for a default route and working DNS before starting - check the network connection".to_string()
+        }
+        Message::StartupFailing { error } => format!(
+            "Startup keeps failing ({error}) - check the internet connection and the configured blokli endpoint"
+        ),
+        Message::WaitingForFunds { node_address } => format!(
+            "Waiting for funds - send xDai and wxHOPR to the node address {node_address} or redeem a funding code"
+        ),
+        Message::NodeStartupFailing { error } => format!("Node startup keeps failing ({error})"),
+        Message::ModulePermissions => {
+            "Safe module lacks permissions for the node - the worker log lists what to fix".to_string()
+        }
+        Message::NodeUnfunded => {
+            "Node is not funded yet - see `gnosis_vpn-ctl balance` for the addresses to fund".to_string()
+        }
+        Message::ChannelsOutOfFundsManual => {
+            "Channels are out of funds, connections will fail - channels are funded manually, top them up".to_string()
+        }
+        Message::OutOfFunds => {
+            "Out of funds, connections will fail - top up the safe, see `gnosis_vpn-ctl balance`".to_string()
+        }
+        Message::SafeLowOnFunds => "Safe is running low on funds - top up soon".to_string(),
+        Message::NodeUnderfunded => {
+            "Node xDai cannot cover transaction fees - send xDai to the node address".to_string()
+        }
+        Message::NodeLowOnFunds => "Node xDai is running low".to_string(),
+        Message::ChainProviderUnreachable { tasks } => format!(
+            "Chain data provider unreachable ({tasks} failing) - check the internet connection and the configured blokli endpoint"
+        ),
+        Message::NoHandshake { silent } => {
+            format!("No WireGuard handshake for {silent} - outgoing UDP traffic may be blocked on this network")
+        }
+        Message::LowDiskSpace { available } => format!(
+            "Only {available} of disk space left in the data directory - free up space, the node database cannot sync on a full disk"
+        ),
+        Message::BudgetUsedUp { resets_in } => format!("Daily wxHOPR budget is used up - it resets in {resets_in}"),
+        Message::DestinationUnusable { id, reason } => {
+            format!("{id} cannot be used ({reason}) - choose another destination")
+        }
+        Message::ExitNotVisible { id } => {
+            format!("Exit of {id} is not visible in the network - it may be offline, try another destination")
+        }
+        Message::NoChannelManual { id } => {
+            format!("No funded channel towards {id} - channels are funded manually, open and fund one")
+        }
+        Message::WaitingForChannel { id } => format!("Waiting for a funded channel towards {id}"),
+        Message::HealthChecksFailing { id, error } => format!(
+            "Health checks of {id} keep failing{} - try another destination",
+            error.map(|e| format!(" ({e})")).unwrap_or_default()
+        ),
+        Message::ExitMaintenance {
+            id,
+            retires_in,
+            message,
+            moving,
+        } => format!(
+            "Exit of {id} retires for maintenance in {retires_in}{} - {}",
+            message.map(|m| format!(" ({m})")).unwrap_or_default(),
+            if *moving {
+                "moving to another ready destination"
+            } else {
+                "no other destination is ready to move to"
+            }
+        ),
+        Message::VpnConflictRoutes { conflict } => format!(
+            "{conflict} - connecting fails or leaks traffic, stop it or run the service with `--routing-mode delegated`"
+        ),
+        Message::VpnConflictRunning { conflict } => {
+            format!("{conflict} - routing stays managed, disconnect it if connections fail")
+        }
+        Message::KillSwitchFailed { error } => {
+            format!(
+                "Kill switch could not block traffic outside the tunnel ({error}) - traffic may leak while disconnected"
+            )
+        }
+        Message::Hint { hint } => format!("Hint: {hint}"),
+        Message::AlreadyConnected { destination } => format!("Already connected to {destination}"),
+        Message::Connecting { destination } => format!("Connecting to {destination}"),
+        Message::WaitingToConnect {
+            destination,
+            route_health,
+        } => format!("Waiting to connect to {destination} once possible: {route_health}"),
+        Message::UnableToConnect {
+            destination,
+            route_health,
+        } => format!("Unable to connect to {destination}: {route_health}"),
+        Message::DestinationNotFound => "Destination not found".to_string(),
+        Message::RefusingToConnect { reason } => format!("Refusing to connect: {reason}"),
+        Message::Queued {
+            destination,
+            run_mode,
+            startup_percent: Some(percent),
+        } => format!("Queued connection to {destination} - node starting ({percent}%): {run_mode}"),
+        Message::Queued {
+            destination,
+            run_mode,
+            startup_percent: None,
+        } => format!("Queued connection to {destination} - {run_mode}"),
+        Message::NoneReady => {
+            "No destination is ready to connect - check `gnosis_vpn-ctl status` for their route health".to_string()
+        }
+        Message::NotReady { reason, retry_in } => format!("Not ready to connect: {reason} - retry in {retry_in}"),
+        Message::Disconnecting { destination } => format!("Disconnecting from {destination}"),
+        Message::NotConnected => "Currently not connected to any destination".to_string(),
+        Message::CancelledConnecting { destination } => format!("Cancelled connecting to {destination}"),
+        Message::NothingToCancel => "No connection attempt to cancel".to_string(),
+        Message::PeersNotAvailable => "Peers not available yet - the node is still starting".to_string(),
+        Message::SessionsNotAvailable => "Sessions not available yet - the node is still starting".to_string(),
+        Message::NoExitReports => "No exit reports - set `report_url` on a destination to opt in".to_string(),
+        Message::WorkerOffline => {
+            "Worker client is currently offline - use command `start-client` to start it".to_string()
+        }
+        Message::WorkerRestarting => "Worker client is restarting - try again shortly".to_string(),
+        Message::CommandRefused { reason } => format!("Command refused: {reason}"),
+        Message::ServiceRunningNoCleanup => {
+            "Service is running and owns its interface and routes - use `disconnect` or stop the service first"
+                .to_string()
+        }
+        Message::NothingToCleanUp => "Nothing left behind to clean up".to_string(),
+    }
+}
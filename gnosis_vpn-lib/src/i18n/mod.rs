@@ -0,0 +1,197 @@
+//! Message catalog for user facing guidance: status hints and `gnosis_vpn-ctl` output.
+//!
+//! Texts are looked up by [`Message`] in the catalog of the selected [`Locale`], formatting code
+//! only ever deals with message keys and their arguments. Translating means adding a [`Locale`]
+//! variant, its tags in [`Locale::parse`] and a catalog module next to `en.rs` covering every message.
+//! Command output that is data rather than guidance, e.g. addresses and balances, is not part of it.
+use std::env;
+use std::fmt::Display;
+
+mod en;
+
+/// Overrides the configured locale and the POSIX locale variables.
+pub const ENV_VAR: &str = "GNOSISVPN_LOCALE";
+
+/// POSIX locale variables in order of precedence.
+const POSIX_VARS: [&str; 3] = ["LC_ALL", "LC_MESSAGES", "LANG"];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+}
+
+impl Locale {
+    /// Locale from [`ENV_VAR`], then the `locale` configuration key, then `LC_ALL`, `LC_MESSAGES` and `LANG`.
+    ///
+    /// The first one set decides, a language without catalog falls back to English.
+    pub fn resolve(configured: Option<&str>) -> Self {
+        Self::resolve_with(configured, |key| env::var(key).ok())
+    }
+
+    fn resolve_with<F: Fn(&str) -> Option<String>>(configured: Option<&str>, var: F) -> Self {
+        let explicit = var(ENV_VAR).or_else(|| configured.map(str::to_string));
+        explicit
+            .into_iter()
+            .chain(POSIX_VARS.iter().filter_map(|key| var(key)))
+            .find(|tag| !tag.is_empty())
+            .and_then(|tag| Self::parse(&tag))
+            .unwrap_or_default()
+    }
+
+    /// Language of a tag such as `en`, `en-GB` or `en_US.UTF-8`, `None` if there is no catalog for it.
+    pub fn parse(tag: &str) -> Option<Self> {
+        let language = tag.split(['_', '-', '.', '@']).next().unwrap_or_default();
+        match language.to_ascii_lowercase().as_str() {
+            "en" | "c" | "posix" => Some(Locale::En),
+            _ => None,
+        }
+    }
+}
+
+/// Message argument, formatted as is.
+pub type Arg<'a> = &'a dyn Display;
+
+/// Catalog keys with their arguments.
+pub enum Message<'a> {
+    // status hints
+    WaitingForNetwork,
+    StartupFailing {
+        error: Arg<'a>,
+    },
+    WaitingForFunds {
+        node_address: Arg<'a>,
+    },
+    NodeStartupFailing {
+        error: Arg<'a>,
+    },
+    ModulePermissions,
+    NodeUnfunded,
+    ChannelsOutOfFundsManual,
+    OutOfFunds,
+    SafeLowOnFunds,
+    NodeUnderfunded,
+    NodeLowOnFunds,
+    ChainProviderUnreachable {
+        tasks: Arg<'a>,
+    },
+    NoHandshake {
+        silent: Arg<'a>,
+    },
+    LowDiskSpace {
+        available: Arg<'a>,
+    },
+    BudgetUsedUp {
+        resets_in: Arg<'a>,
+    },
+    DestinationUnusable {
+        id: Arg<'a>,
+        reason: Arg<'a>,
+    },
+    ExitNotVisible {
+        id: Arg<'a>,
+    },
+    NoChannelManual {
+        id: Arg<'a>,
+    },
+    WaitingForChannel {
+        id: Arg<'a>,
+    },
+    HealthChecksFailing {
+        id: Arg<'a>,
+        error: Option<Arg<'a>>,
+    },
+    ExitMaintenance {
+        id: Arg<'a>,
+        retires_in: Arg<'a>,
+        message: Option<Arg<'a>>,
+        /// Whether another ready destination without a notice is there to move to
+        moving: bool,
+    },
+    VpnConflictRoutes {
+        conflict: Arg<'a>,
+    },
+    VpnConflictRunning {
+        conflict: Arg<'a>,
+    },
+    KillSwitchFailed {
+        error: Arg<'a>,
+    },
+    // ctl output
+    Hint {
+        hint: Arg<'a>,
+    },
+    AlreadyConnected {
+        destination: Arg<'a>,
+    },
+    Connecting {
+        destination: Arg<'a>,
+    },
+    WaitingToConnect {
+        destination: Arg<'a>,
+        route_health: Arg<'a>,
+    },
+    UnableToConnect {
+        destination: Arg<'a>,
+        route_health: Arg<'a>,
+    },
+    DestinationNotFound,
+    RefusingToConnect {
+        reason: Arg<'a>,
+    },
+    Queued {
+        destination: Arg<'a>,
+        run_mode: Arg<'a>,
+        startup_percent: Option<Arg<'a>>,
+    },
+    NoneReady,
+    NotReady {
+        reason: Arg<'a>,
+        retry_in: Arg<'a>,
+    },
+    Disconnecting {
+        destination: Arg<'a>,
+    },
+    NotConnected,
+    CancelledConnecting {
+        destination: Arg<'a>,
+    },
+    NothingToCancel,
+    PeersNotAvailable,
+    SessionsNotAvailable,
+    NoExitReports,
+    WorkerOffline,
+    WorkerRestarting,
+    CommandRefused {
+        reason: Arg<'a>,
+    },
+    ServiceRunningNoCleanup,
+    NothingToCleanUp,
+}
+
+impl Message<'_> {
+    pub fn text(&self, locale: Locale) -> String {
+        match locale {
+            Locale::En => en::text(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    #[test]
+    fn explicit_locale_wins_and_unknown_falls_back() {
+        let vars = HashMap::from([("LANG", "en_US.UTF-8"), ("LC_ALL", "")]);
+        let var = |key: &str| vars.get(key).map(|v| v.to_string());
+        assert_eq!(Locale::resolve_with(None, var), Locale::En);
+        assert_eq!(Locale::resolve_with(Some("de"), var), Locale::default());
+
+        assert_eq!(Locale::parse("en-GB"), Some(Locale::En));
+        assert_eq!(Locale::parse("C.UTF-8"), Some(Locale::En));
+        assert_eq!(Locale::parse("de_DE@euro"), None);
+    }
+}
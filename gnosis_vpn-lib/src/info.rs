@@ -1,16 +1,19 @@
 use edgli::hopr_lib::api::types::primitive::prelude::Address;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use std::fmt::{self, Display};
 
 use crate::serde_utils;
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct Info {
     #[serde(with = "serde_utils::address")]
+    #[schemars(with = "String")]
     pub node_address: Address,
     pub node_peer_id: String,
     #[serde(with = "serde_utils::address")]
+    #[schemars(with = "String")]
     pub safe_address: Address,
 }
 
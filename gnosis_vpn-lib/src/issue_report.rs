@@ -0,0 +1,222 @@
+//! Diagnostics of recent connection attempts to one destination, shared with its exit operator on request.
+//!
+//! `gnosis_vpn-ctl report-issue <destination>` prints the report as JSON for the user to hand over
+//! when an exit keeps failing. It carries what exit reports disclose plus the timing of every phase
+//! and the kind of each setback. Error messages, node address, IPs and keys stay on the machine.
+//! Every report gets a fresh random token: passing it on is the user's consent, the operator quotes
+//! it when following up and it cannot be linked to the node.
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime};
+
+use crate::connection::destination::{Address, Destination};
+use crate::connection::up::{Phase, Setback};
+use crate::exit_reports::Outcome;
+use crate::serde_utils;
+
+/// Attempts kept per destination, older ones are dropped.
+const MAX_ATTEMPTS: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SetbackKind {
+    OpenBridge,
+    RegisterWg,
+    OpenPing,
+    Ping,
+    Timeout,
+}
+
+impl From<&Setback> for SetbackKind {
+    fn from(setback: &Setback) -> Self {
+        match setback {
+            Setback::OpenBridge(_) => SetbackKind::OpenBridge,
+            Setback::RegisterWg(_) => SetbackKind::RegisterWg,
+            Setback::OpenPing(_) => SetbackKind::OpenPing,
+            Setback::Ping(_) => SetbackKind::Ping,
+            Setback::Timeout(..) => SetbackKind::Timeout,
+        }
+    }
+}
+
+/// A phase the attempt entered, `after` the attempt started.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PhaseEntry {
+    pub phase: Phase,
+    #[serde(with = "serde_utils::duration_ms")]
+    #[schemars(with = "f64")]
+    pub after: Duration,
+}
+
+/// A retried failure within `phase`, `after` the attempt started.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SetbackEntry {
+    pub kind: SetbackKind,
+    pub phase: Phase,
+    #[serde(with = "serde_utils::duration_ms")]
+    #[schemars(with = "f64")]
+    pub after: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Attempt {
+    #[serde(with = "serde_utils::system_time")]
+    #[schemars(with = "u64")]
+    pub started: SystemTime,
+    pub phases: Vec<PhaseEntry>,
+    pub setbacks: Vec<SetbackEntry>,
+    /// `None` while the attempt is running or when it was cancelled
+    pub outcome: Option<Outcome>,
+    #[serde(default, with = "serde_utils::opt_duration_ms")]
+    #[schemars(with = "Option<f64>")]
+    pub duration: Option<Duration>,
+}
+
+/// Everything that is shared, nothing more.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct IssueReport {
+    pub token: String,
+    #[serde(with = "serde_utils::system_time")]
+    #[schemars(with = "u64")]
+    pub generated_at: SystemTime,
+    pub version: String,
+    pub destination: String,
+    #[serde(with = "serde_utils::address")]
+    #[schemars(with = "String")]
+    pub exit: Address,
+    pub hops: usize,
+    /// Oldest first
+    pub attempts: Vec<Attempt>,
+}
+
+#[derive(Default)]
+pub struct Recorder {
+    attempts: HashMap<String, VecDeque<Attempt>>,
+}
+
+impl Recorder {
+    pub fn started(&mut self, destination: &str, now: SystemTime) {
+        let attempts = self.attempts.entry(destination.to_string()).or_default();
+        attempts.push_back(Attempt {
+            started: now,
+            phases: Vec::new(),
+            setbacks: Vec::new(),
+            outcome: None,
+            duration: None,
+        });
+        while attempts.len() > MAX_ATTEMPTS {
+            attempts.pop_front();
+        }
+    }
+
+    /// Records `phase` unless the running attempt is already in it.
+    pub fn phase(&mut self, destination: &str, phase: &Phase, now: SystemTime) {
+        if let Some(attempt) = self.running(destination)
+            && attempt.phases.last().is_none_or(|p| p.phase != *phase)
+        {
+            let after = now.duration_since(attempt.started).unwrap_or_default();
+            attempt.phases.push(PhaseEntry {
+                phase: phase.clone(),
+                after,
+            });
+        }
+    }
+
+    pub fn setback(&mut self, destination: &str, setback: &Setback, phase: &Phase, now: SystemTime) {
+        if let Some(attempt) = self.running(destination) {
+            let after = now.duration_since(attempt.started).unwrap_or_default();
+            attempt.setbacks.push(SetbackEntry {
+                kind: setback.into(),
+                phase: phase.clone(),
+                after,
+            });
+        }
+    }
+
+    pub fn finished(&mut self, destination: &str, outcome: Outcome, now: SystemTime) {
+        if let Some(attempt) = self.running(destination) {
+            attempt.duration = Some(now.duration_since(attempt.started).unwrap_or_default());
+            attempt.outcome = Some(outcome);
+        }
+    }
+
+    pub fn report(&self, destination: &Destination, now: SystemTime) -> IssueReport {
+        IssueReport {
+            token: format!("{:032x}", rand::random::<u128>()),
+            generated_at: now,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            destination: destination.id.clone(),
+            exit: destination.address,
+            hops: destination.routing.hop_count(),
+            attempts: self
+                .attempts
+                .get(&destination.id)
+                .map(|a| a.iter().cloned().collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Latest attempt if it has not finished yet.
+    fn running(&mut self, destination: &str) -> Option<&mut Attempt> {
+        self.attempts
+            .get_mut(destination)
+            .and_then(|a| a.back_mut())
+            .filter(|a| a.outcome.is_none())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::connection::destination::HopRouting;
+    use crate::connection::up::Remediation;
+
+    #[test]
+    fn report_carries_timings_but_no_error_messages() {
+        let destination = Destination::new(
+            "Germany".to_string(),
+            Address::from([1; 20]),
+            HopRouting::try_from(1).expect("conversion cannot fail"),
+            HashMap::new(),
+        );
+        let start = SystemTime::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut recorder = Recorder::default();
+
+        recorder.started("Germany", start);
+        recorder.phase("Germany", &Phase::OpeningBridge, at(1));
+        recorder.phase("Germany", &Phase::OpeningBridge, at(2));
+        let setback = Setback::OpenBridge("no route to 10.0.0.7".to_string());
+        recorder.setback("Germany", &setback, &Phase::OpeningBridge, at(3));
+        let timeout = Setback::Timeout(Phase::OpeningBridge, Remediation::TryOtherDestination);
+        recorder.setback("Germany", &timeout, &Phase::OpeningBridge, at(30));
+        let failure = Outcome::Failure {
+            phase: Phase::OpeningBridge,
+        };
+        recorder.finished("Germany", failure.clone(), at(30));
+        // late events of a finished attempt are ignored
+        recorder.phase("Germany", &Phase::RegisterWg, at(31));
+        recorder.started("Germany", at(40));
+
+        let report = recorder.report(&destination, at(50));
+        assert_eq!(report.token.len(), 32);
+        assert_eq!(report.attempts.len(), 2);
+        let attempt = &report.attempts[0];
+        assert_eq!(attempt.phases.len(), 1);
+        assert_eq!(attempt.phases[0].after, Duration::from_secs(1));
+        assert_eq!(
+            attempt.setbacks.iter().map(|s| s.kind).collect::<Vec<_>>(),
+            vec![SetbackKind::OpenBridge, SetbackKind::Timeout]
+        );
+        assert_eq!(attempt.outcome, Some(failure));
+        assert_eq!(attempt.duration, Some(Duration::from_secs(30)));
+        assert_eq!(report.attempts[1].outcome, None);
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(!json.contains("10.0.0.7"));
+        assert_ne!(recorder.report(&destination, at(50)).token, report.token);
+    }
+}
@@ -8,6 +8,10 @@
 //! - WireGuard tunnel interface — name-based matching (`meta oifname`/`iifname`)
 //!   so the rule safely no-ops when `wg0_gnosisvpn` doesn't yet exist
 //! - Outbound to each explicitly listed IP; inbound from each IP if ESTABLISHED
+//!
+//! Kill switch mode covers the time without a connection with a second table
+//! (`gnosis_vpn_block`) sharing the same allowlist, except that listed users instead
+//! of listed IPs pass, so the worker node can bring the tunnel back.
 
 use std::ffi::CString;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
@@ -15,6 +19,7 @@ use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use ipnetwork::{IpNetwork, Ipv6Network};
 
 use super::{LAN_MULTICAST_NETS, LAN_NETS};
+use crate::nftables;
 use nftnl::{
     Batch, Chain, FinalizedBatch, Hook, MsgType, Policy, ProtoFamily, Rule, Table,
     expr::{self, Payload, Verdict},
@@ -28,7 +33,10 @@ pub enum Error {
     NfTables(String),
 }
 
-const TABLE_NAME: &std::ffi::CStr = c"gnosis_vpn_ks";
+/// Table of the connection killswitch.
+pub const TABLE_NAME: &std::ffi::CStr = c"gnosis_vpn_ks";
+/// Table of the block while no connection is up.
+pub const BLOCK_TABLE_NAME: &std::ffi::CStr = c"gnosis_vpn_block";
 const IN_CHAIN_NAME: &std::ffi::CStr = c"input";
 const OUT_CHAIN_NAME: &std::ffi::CStr = c"output";
 const FORWARD_CHAIN_NAME: &std::ffi::CStr = c"forward";
@@ -94,12 +102,21 @@ impl Firewall {
 
     /// Remove the killswitch table, restoring normal networking.
     pub fn reset_policy(&mut self) -> Result<(), Error> {
-        let table = Table::new(TABLE_NAME, ProtoFamily::Inet);
-        let mut batch = Batch::new();
-        // Add-then-Del avoids ENOENT if the table was never created.
-        batch.add(&table, MsgType::Add);
-        batch.add(&table, MsgType::Del);
-        send_batch(&batch.finalize())
+        nftables::delete_table(TABLE_NAME).map_err(|e| Error::NfTables(e.to_string()))
+    }
+
+    /// Apply the kill switch block: block everything except infrastructure and the traffic of
+    /// the users `uids`. Re-applying replaces a previous block atomically, so changed
+    /// settings never leave a gap.
+    pub fn apply_block(&mut self, interface: &str, uids: &[u32], lan_lockdown: bool) -> Result<(), Error> {
+        let table = Table::new(BLOCK_TABLE_NAME, ProtoFamily::Inet);
+        let batch = PolicyBatch::new(&table).finalize_block(interface, uids, lan_lockdown);
+        send_batch(&batch)
+    }
+
+    /// Remove the kill switch block table.
+    pub fn reset_block(&mut self) -> Result<(), Error> {
+        nftables::delete_table(BLOCK_TABLE_NAME).map_err(|e| Error::NfTables(e.to_string()))
     }
 }
 
@@ -156,6 +173,18 @@ impl<'a> PolicyBatch<'a> {
         self.batch.finalize()
     }
 
+    fn finalize_block(mut self, interface: &str, uids: &[u32], lan_lockdown: bool) -> FinalizedBatch {
+        self.add_loopback_rules();
+        self.add_dhcp_client_rules();
+        self.add_ndp_rules();
+        self.add_tunnel_rules(interface);
+        self.add_user_rules(uids);
+        if !lan_lockdown {
+            self.add_lan_rules();
+        }
+        self.batch.finalize()
+    }
+
     fn add_loopback_rules(&mut self) {
         let mut out_rule = Rule::new(&self.out_chain);
         check_iface_by_name(&mut out_rule, Direction::Out, "lo");
@@ -334,6 +363,26 @@ impl<'a> PolicyBatch<'a> {
         }
     }
 
+    fn add_user_rules(&mut self, uids: &[u32]) {
+        // Allow all outgoing traffic of the users
+        for &uid in uids {
+            let mut out_rule = Rule::new(&self.out_chain);
+            out_rule.add_expr(&nft_expr!(meta skuid));
+            out_rule.add_expr(&nft_expr!(cmp == uid));
+            out_rule.add_expr(&Verdict::Accept);
+            self.batch.add(&out_rule, MsgType::Add);
+        }
+
+        // Incoming packets carry no socket owner, let replies in if ESTABLISHED or RELATED
+        let state_bits = (nftnl::expr::ct::States::ESTABLISHED | nftnl::expr::ct::States::RELATED).bits();
+        let mut in_rule = Rule::new(&self.in_chain);
+        in_rule.add_expr(&nft_expr!(ct state));
+        in_rule.add_expr(&nft_expr!(bitwise mask state_bits, xor 0u32));
+        in_rule.add_expr(&nft_expr!(cmp != 0u32));
+        in_rule.add_expr(&Verdict::Accept);
+        self.batch.add(&in_rule, MsgType::Add);
+    }
+
     fn add_allowed_ip_rules(&mut self, ip: IpAddr) {
         // Allow all outgoing traffic to this IP
         let mut out_rule = Rule::new(&self.out_chain);
@@ -434,30 +483,69 @@ fn check_l4proto(rule: &mut Rule<'_>, protocol: Protocol) {
 }
 
 fn send_batch(batch: &FinalizedBatch) -> Result<(), Error> {
-    let socket = mnl::Socket::new(mnl::Bus::Netfilter)
-        .map_err(|e| Error::NfTables(format!("failed to open netlink socket: {e}")))?;
-    let portid = socket.portid();
-
-    socket
-        .send_all(batch)
-        .map_err(|e| Error::NfTables(format!("failed to send batch: {e}")))?;
-
-    let mut buffer = vec![0; nftnl::nft_nlmsg_maxsize() as usize];
-    let mut expected_seqs = batch.sequence_numbers();
-
-    while !expected_seqs.is_empty() {
-        let messages = socket
-            .recv(&mut buffer[..])
-            .map_err(|e| Error::NfTables(format!("failed to receive netlink response: {e}")))?;
-        for message in messages {
-            let message = message.map_err(|e| Error::NfTables(format!("netlink message error: {e}")))?;
-            let expected_seq = expected_seqs
-                .next()
-                .ok_or_else(|| Error::NfTables("unexpected ACK from netfilter".into()))?;
-            mnl::cb_run(message, expected_seq, portid)
-                .map_err(|e| Error::NfTables(format!("netlink ACK error: {e}")))?;
+    nftables::send_batch(batch).map_err(|e| Error::NfTables(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERFACE: &str = "wg0_gnosisvpn";
+    const WORKER_UID: u32 = 0x7a1c_e001;
+    const RESOLVER_UID: u32 = 0x7a1c_e002;
+
+    /// Payloads of the rule messages in `batch`.
+    fn rules(batch: &FinalizedBatch) -> Vec<Vec<u8>> {
+        let rule_type = ((libc::NFNL_SUBSYS_NFTABLES << 8) | libc::NFT_MSG_NEWRULE) as u16;
+        let mut rules = Vec::new();
+        for page in batch {
+            let mut offset = 0;
+            while offset + 16 <= page.len() {
+                let len = u32::from_ne_bytes(page[offset..offset + 4].try_into().unwrap()) as usize;
+                let msg_type = u16::from_ne_bytes(page[offset + 4..offset + 6].try_into().unwrap());
+                if msg_type == rule_type {
+                    rules.push(page[offset + 16..offset + len].to_vec());
+                }
+                offset += len.next_multiple_of(4);
+            }
         }
+        rules
+    }
+
+    fn matching(rules: &[Vec<u8>], needle: &[u8]) -> usize {
+        rules
+            .iter()
+            .filter(|rule| rule.windows(needle.len()).any(|w| w == needle))
+            .count()
     }
 
-    Ok(())
+    #[test]
+    fn block_lets_listed_users_and_their_replies_through() {
+        let policy_table = Table::new(TABLE_NAME, ProtoFamily::Inet);
+        let policy = rules(&PolicyBatch::new(&policy_table).finalize(INTERFACE, &[], false));
+        let block_table = Table::new(BLOCK_TABLE_NAME, ProtoFamily::Inet);
+        let block =
+            rules(&PolicyBatch::new(&block_table).finalize_block(INTERFACE, &[WORKER_UID, RESOLVER_UID], false));
+
+        // one outgoing rule per user and one for replies
+        assert_eq!(block.len(), policy.len() + 3);
+        assert_eq!(matching(&block, &WORKER_UID.to_ne_bytes()), 1);
+        assert_eq!(matching(&block, &RESOLVER_UID.to_ne_bytes()), 1);
+        assert_eq!(
+            matching(&block, INTERFACE.as_bytes()),
+            matching(&policy, INTERFACE.as_bytes())
+        );
+    }
+
+    #[test]
+    fn block_under_lan_lockdown_leaves_out_the_local_network() {
+        let table = Table::new(BLOCK_TABLE_NAME, ProtoFamily::Inet);
+        let open = rules(&PolicyBatch::new(&table).finalize_block(INTERFACE, &[WORKER_UID], false));
+        let locked = rules(&PolicyBatch::new(&table).finalize_block(INTERFACE, &[WORKER_UID], true));
+
+        // outgoing and forwarded to each net, incoming from each net
+        let lan_rules = 3 * (LAN_NETS.len() + LAN_MULTICAST_NETS.len());
+        assert_eq!(open.len(), locked.len() + lan_rules);
+        assert_eq!(matching(&locked, &WORKER_UID.to_ne_bytes()), 1);
+    }
 }
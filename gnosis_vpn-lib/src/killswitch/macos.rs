@@ -12,7 +12,7 @@ pub enum Error {
     PacketFilter(#[from] pfctl::Error),
 }
 
-const ANCHOR_NAME: &str = "gnosis_vpn_ks";
+pub const ANCHOR_NAME: &str = "gnosis_vpn_ks";
 
 const DHCPV4_SERVER_PORT: u16 = 67;
 const DHCPV4_CLIENT_PORT: u16 = 68;
@@ -30,9 +30,9 @@ pub(super) const LAN_MULTICAST_NETS: [IpNetwork; 8] = [
 cfg_if::cfg_if! {
     if #[cfg(target_os = "linux")] {
         mod linux;
-        pub use linux::{Error, Firewall};
+        pub use linux::{BLOCK_TABLE_NAME, Error, Firewall, TABLE_NAME};
     } else if #[cfg(target_os = "macos")] {
         mod macos;
-        pub use macos::{Error, Firewall};
+        pub use macos::{ANCHOR_NAME, Error, Firewall};
     }
 }
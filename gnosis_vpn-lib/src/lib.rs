@@ -1,20 +1,45 @@
 pub mod killswitch;
 
 pub mod app_nap;
+pub mod backoff;
+pub mod backup;
 pub mod balance;
+pub mod balance_history;
+pub mod budget;
 pub mod check_update;
+pub mod cleanup;
 pub mod command;
 pub mod config;
+pub mod conflicts;
 pub mod connection;
 pub mod core;
+pub mod crash;
+pub mod diagnostics;
 pub mod dirs;
+pub mod disk_space;
 pub mod event;
+pub mod exit_reports;
 pub mod hopr;
+pub mod i18n;
+pub mod issue_report;
+pub mod log_buffer;
 pub mod logging;
+pub mod management;
+pub mod metrics;
+#[cfg(target_os = "linux")]
+pub mod nftables;
 pub mod ping;
+pub mod proxy;
+pub mod public_ip;
 pub mod route_health;
 pub mod shell_command_ext;
 pub mod socket;
+pub mod telemetry;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+pub mod tls;
+pub mod transactions;
+pub mod watchdog;
 pub mod wireguard;
 pub mod worker;
 pub mod worker_params;
@@ -0,0 +1,94 @@
+//! Recent log output of the service, served by `gnosis_vpn-ctl logs`.
+//!
+//! Root and worker both keep their latest formatted lines in memory. The worker forwards its
+//! lines to root, which merges them into its own buffer so one socket command shows the whole
+//! service without reading the log file. Followers receive every line appended afterwards.
+use tokio::sync::broadcast;
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{LazyLock, Mutex};
+
+/// Lines kept for `logs`, older ones are dropped.
+pub const CAPACITY: usize = 1000;
+
+/// Lines a follower may lag behind before it misses some.
+const FOLLOW_BACKLOG: usize = 1024;
+
+static LINES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+static FOLLOWERS: LazyLock<broadcast::Sender<String>> = LazyLock::new(|| broadcast::channel(FOLLOW_BACKLOG).0);
+
+/// Tracing layer feeding the buffer.
+pub fn layer<S>() -> impl tracing_subscriber::Layer<S>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .with_writer(|| BufferWriter)
+}
+
+/// Add lines that were not logged by this process, e.g. forwarded by the worker.
+pub fn append<I: IntoIterator<Item = String>>(lines: I) {
+    let Ok(mut buffer) = LINES.lock() else {
+        return;
+    };
+    for line in lines {
+        if buffer.len() == CAPACITY {
+            buffer.pop_front();
+        }
+        buffer.push_back(line.clone());
+        // no followers is fine
+        let _ = FOLLOWERS.send(line);
+    }
+}
+
+/// Latest `count` lines, oldest first.
+pub fn recent(count: usize) -> Vec<String> {
+    match LINES.lock() {
+        Ok(buffer) => buffer
+            .iter()
+            .skip(buffer.len().saturating_sub(count))
+            .cloned()
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Receive every line appended from now on.
+pub fn subscribe() -> broadcast::Receiver<String> {
+    FOLLOWERS.subscribe()
+}
+
+struct BufferWriter;
+
+impl io::Write for BufferWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        append(String::from_utf8_lossy(buf).lines().map(str::to_string));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_latest_lines_and_feeds_followers() {
+        let mut follower = subscribe();
+        append((0..CAPACITY + 5).map(|i| format!("line {i}")));
+
+        let all = recent(CAPACITY + 10);
+        assert_eq!(all.len(), CAPACITY);
+        assert_eq!(all.first().map(String::as_str), Some("line 5"));
+        assert_eq!(
+            recent(2),
+            vec![format!("line {}", CAPACITY + 3), format!("line {}", CAPACITY + 4)]
+        );
+        assert_eq!(follower.try_recv().ok().as_deref(), Some("line 0"));
+    }
+}
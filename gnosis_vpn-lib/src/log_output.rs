@@ -1,8 +1,15 @@
 use edgli::hopr_lib::api::types::primitive::prelude::Address;
 use humantime::format_duration;
 use serde::ser::Serialize;
+use tokio::runtime::Handle;
+use tokio::task::AbortHandle;
+use tokio::time;
 
-use std::time::SystemTime;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Identical messages within this window are logged once, see [`Sampler`].
+pub const REPEAT_WINDOW: Duration = Duration::from_mins(5);
 
 pub fn serialize<T>(v: &T) -> String
 where
@@ -26,6 +33,96 @@ pub fn address(address: &Address) -> String {
     format!("{}..{}", &str[..6], &str[38..])
 }
 
+/// Rate limits a repeating log message, e.g. the same RPC error on every retry.
+///
+/// A message is let through when it differs from the previous one or [`REPEAT_WINDOW`] passed
+/// since it was last logged. Suppressed repeats are summarized as "message repeated N times" once
+/// the window ends, before a different message is let through and when the sampler is dropped.
+#[derive(Debug, Default)]
+pub struct Sampler {
+    state: Arc<Mutex<SamplerState>>,
+    flush_timer: Option<AbortHandle>,
+}
+
+#[derive(Debug, Default)]
+struct SamplerState {
+    last: Option<(String, Instant)>,
+    suppressed: u32,
+}
+
+impl Sampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `message` should be logged.
+    pub fn sample(&mut self, message: &str) -> bool {
+        self.sample_at(message, Instant::now())
+    }
+
+    fn sample_at(&mut self, message: &str, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let repeat_since = match &state.last {
+            Some((last, at)) if last == message => Some(now.duration_since(*at)),
+            _ => None,
+        };
+        if let Some(elapsed) = repeat_since
+            && elapsed < REPEAT_WINDOW
+        {
+            state.suppressed = state.suppressed.saturating_add(1);
+            if state.suppressed == 1 {
+                let remaining = REPEAT_WINDOW - elapsed;
+                drop(state);
+                self.start_flush_timer(remaining);
+            }
+            return false;
+        }
+        state.flush();
+        state.last = Some((message.to_string(), now));
+        drop(state);
+        if let Some(timer) = self.flush_timer.take() {
+            timer.abort();
+        }
+        true
+    }
+
+    /// Summarize suppressed repeats once the window ends, without waiting for the next message.
+    fn start_flush_timer(&mut self, delay: Duration) {
+        // outside of a runtime the summary is only written by the next message or on drop
+        let Ok(runtime) = Handle::try_current() else {
+            return;
+        };
+        let state = self.state.clone();
+        let timer = runtime.spawn(async move {
+            time::sleep(delay).await;
+            state.lock().unwrap_or_else(|e| e.into_inner()).flush();
+        });
+        if let Some(previous) = self.flush_timer.replace(timer.abort_handle()) {
+            previous.abort();
+        }
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        if let Some(timer) = self.flush_timer.take() {
+            timer.abort();
+        }
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).flush();
+    }
+}
+
+impl SamplerState {
+    fn flush(&mut self) {
+        let repeated = std::mem::take(&mut self.suppressed);
+        if let Some((message, _)) = &self.last
+            && repeated > 0
+        {
+            tracing::warn!(repeated, "message repeated {repeated} times: {message}");
+        }
+    }
+}
+
 fn truncate_after_second_space(s: &str) -> &str {
     let spaces = s.match_indices(' ').take(2);
     if let Some((index, _)) = spaces.last() {
@@ -35,6 +132,20 @@ fn truncate_after_second_space(s: &str) -> &str {
     }
 }
 
+pub fn print_queued_connection_started(destination_id: &str) {
+    tracing::info!(
+        r#"
+
+            /---==========================---\
+            |   STARTING QUEUED CONNECTION   |
+            \---==========================---/
+
+            destination: {}
+        "#,
+        destination_id
+    );
+}
+
 pub fn print_session_established(path: &str) {
     tracing::info!(
         r#"
@@ -48,3 +159,39 @@ pub fn print_session_established(path: &str) {
         path
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suppressed(sampler: &Sampler) -> u32 {
+        sampler.state.lock().map(|state| state.suppressed).unwrap_or_default()
+    }
+
+    #[test]
+    fn sampler_suppresses_repeats_within_window() {
+        let mut sampler = Sampler::new();
+        let start = Instant::now();
+        assert!(sampler.sample_at("rpc unreachable", start));
+        assert!(!sampler.sample_at("rpc unreachable", start + Duration::from_secs(10)));
+        assert!(!sampler.sample_at("rpc unreachable", start + Duration::from_secs(20)));
+        assert_eq!(suppressed(&sampler), 2);
+        assert!(sampler.sample_at("rpc unreachable", start + REPEAT_WINDOW));
+        assert_eq!(suppressed(&sampler), 0);
+        assert!(!sampler.sample_at("rpc unreachable", start + REPEAT_WINDOW));
+        // the repeat is summarized for the message it belongs to before the new one goes through
+        assert!(sampler.sample_at("rpc timeout", start + REPEAT_WINDOW));
+        assert_eq!(suppressed(&sampler), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sampler_summarizes_repeats_once_the_window_ends() {
+        let mut sampler = Sampler::new();
+        assert!(sampler.sample("rpc unreachable"));
+        assert!(!sampler.sample("rpc unreachable"));
+        assert_eq!(suppressed(&sampler), 1);
+        time::sleep(REPEAT_WINDOW).await;
+        tokio::task::yield_now().await;
+        assert_eq!(suppressed(&sampler), 0);
+    }
+}
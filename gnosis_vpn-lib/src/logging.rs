@@ -5,6 +5,8 @@ use std::fs::OpenOptions;
 use std::os::unix;
 use std::os::unix::fs::OpenOptionsExt;
 
+use crate::crash;
+use crate::log_buffer;
 use crate::worker::Worker;
 
 pub type FileFmtLayer =
@@ -12,7 +14,9 @@ pub type FileFmtLayer =
 
 pub type LogReloadHandle = reload::Handle<FileFmtLayer, tracing_subscriber::Registry>;
 
-const DEFAULT_LOG_FILTER: &str = "info";
+/// `netlink-packet-route` warns about every attribute of newer kernels it does not know (e.g.
+/// `IFLA_INET6_CONF` size mismatches), the root checks whether netlink is usable at startup instead.
+const DEFAULT_LOG_FILTER: &str = "info,netlink_packet_route=error";
 pub const ENV_VAR_LOG_FILE: &str = "GNOSISVPN_LOG_FILE";
 
 #[cfg(target_os = "linux")]
@@ -68,13 +72,15 @@ pub fn use_file_fmt_layer(log_path: &str) -> Result<FileFmtLayer, std::io::Error
 
 /// Initializes the global `tracing` subscriber with a reloadable file logging layer.
 ///
-/// Sets up a [`tracing_subscriber::Registry`] with two layers:
+/// Sets up a [`tracing_subscriber::Registry`] with four layers:
 ///
 /// 1. A **reloadable file layer** — created via [`make_file_fmt_layer` or `use_file_fmt_layer`] — that
 ///    writes structured logs to the file at `log_path`.
-/// 2. An **[`EnvFilter`]** that controls log verbosity. The filter is read from
+/// 2. The [`crash::recent_lines_layer`] keeping the latest lines for crash reports.
+/// 3. The [`log_buffer::layer`] serving the latest lines to `gnosis_vpn-ctl logs`.
+/// 4. An **[`EnvFilter`]** that controls log verbosity. The filter is read from
 ///    the `RUST_LOG` environment variable; if that is unset or invalid, it
-///    defaults to `"info"`.
+///    defaults to `"info"` without netlink parser warnings.
 ///
 /// The returned [`LogReloadHandle`] allows the file layer to be swapped at
 /// runtime without restarting the process. This is essential for log rotation:
@@ -101,19 +107,26 @@ pub fn setup_log_file(file_fmt_layer: FileFmtLayer) -> Result<LogReloadHandle, s
         reload::Layer<FileFmtLayer, tracing_subscriber::Registry>,
         LogReloadHandle,
     ) = reload::Layer::new(file_fmt_layer);
-    tracing_subscriber::registry().with(reload_layer).with(filter).init();
+    tracing_subscriber::registry()
+        .with(reload_layer)
+        .with(crash::recent_lines_layer())
+        .with(log_buffer::layer())
+        .with(filter)
+        .init();
     Ok(reload_handle)
 }
 
 /// Initializes the global `tracing` subscriber with stdout/stderr logging.
 ///
-/// Sets up a [`tracing_subscriber::Registry`] with two layers:
+/// Sets up a [`tracing_subscriber::Registry`] with four layers:
 ///
 /// 1. A **formatting layer** that writes structured logs to stdout with
 ///    ANSI colors enabled (suitable for terminal output).
-/// 2. An **[`EnvFilter`]** that controls log verbosity. The filter is read from
+/// 2. The [`crash::recent_lines_layer`] keeping the latest lines for crash reports.
+/// 3. The [`log_buffer::layer`] serving the latest lines to `gnosis_vpn-ctl logs`.
+/// 4. An **[`EnvFilter`]** that controls log verbosity. The filter is read from
 ///    the `RUST_LOG` environment variable; if that is unset or invalid, it
-///    defaults to `"info"`.
+///    defaults to `"info"` without netlink parser warnings.
 ///
 /// This setup does not support log rotation since it writes directly to
 /// stdout/stderr.
@@ -121,6 +134,8 @@ pub fn setup_stdout() {
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
     tracing_subscriber::registry()
         .with(fmt::layer().with_ansi(true))
+        .with(crash::recent_lines_layer())
+        .with(log_buffer::layer())
         .with(filter)
         .init();
 }
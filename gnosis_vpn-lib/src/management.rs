@@ -0,0 +1,396 @@
+//! Managed mode: a central management server hands out the connection policy.
+//!
+//! The policy document is fetched from `url`, its hex encoded Ed25519 signature from the same
+//! URL with `.sig` appended. Only policies signed by the configured key are applied, the last
+//! one is cached and keeps applying while the server is unreachable.
+//!
+//! A policy can only tighten the local configuration:
+//! - `allowed_destinations` drops every configured destination whose exit address is not
+//!   listed, unless the local config sets `allow_unlisted_destinations`
+//! - `require_lan_lockdown` forces `lan_lockdown`, a locally enabled lockdown always stays
+//! - `schedules` limit when connections may be established (UTC), an active connection is
+//!   closed on the first refresh outside of all windows
+//!
+//! With `report` enabled every refresh posts an anonymized [`Report`] to `report` next to
+//! the policy URL. It carries a random installation id but no addresses or destinations.
+//!
+//! Root owns the policy: it fetches it with [`watch`], caches it in its root only home and
+//! hands the restricted configuration to the worker, which stays unaware of managed mode.
+use chrono::{DateTime, Datelike, NaiveTime, Utc, Weekday};
+use edgli::hopr_lib::api::types::primitive::prelude::Address;
+use rand::prelude::*;
+use reqwest::Client;
+use ring::signature::{ED25519, UnparsedPublicKey};
+use serde::{Deserialize, Serialize};
+use serde_with::{DisplayFromStr, hex::Hex, serde_as};
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio::time;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::proxy;
+
+pub const CACHE_FILE: &str = "management.json";
+const SIGNATURE_SUFFIX: &str = ".sig";
+const REPORT_PATH: &str = "report";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[serde_as]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    /// Location of the policy document
+    pub url: Url,
+    /// Ed25519 key the policy must be signed with
+    #[serde_as(as = "Hex")]
+    pub public_key: [u8; 32],
+    #[serde(with = "humantime_serde")]
+    pub interval: Duration,
+    pub report: bool,
+    /// Keep destinations the policy does not list
+    pub allow_unlisted_destinations: bool,
+}
+
+impl Config {
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(15 * 60);
+}
+
+#[serde_as]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Policy {
+    /// Increased by the server on every change
+    pub serial: u64,
+    #[serde_as(as = "Option<Vec<DisplayFromStr>>")]
+    #[serde(default)]
+    pub allowed_destinations: Option<Vec<Address>>,
+    #[serde(default)]
+    pub require_lan_lockdown: bool,
+    /// Connection windows, empty allows connecting at any time
+    #[serde(default)]
+    pub schedules: Vec<Schedule>,
+}
+
+/// Time window in UTC, `end` before `start` spans midnight.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    /// Days the window opens on, empty means every day
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+/// Anonymized state posted back to the management server.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Report {
+    pub client_id: String,
+    pub version: String,
+    pub os: String,
+    pub policy_serial: Option<u64>,
+    pub worker_running: bool,
+    /// A destination was requested, not necessarily connected yet
+    pub connection_requested: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("IO error: {0}")]
+    IO(#[from] io::Error),
+    #[error("invalid policy document: {0}")]
+    Deserialization(#[from] serde_json::Error),
+    #[error("policy signature is not valid hex")]
+    SignatureEncoding,
+    #[error("policy signature does not match the configured key")]
+    Signature,
+    #[error("invalid URL: {0}")]
+    Url(#[from] url::ParseError),
+}
+
+/// A policy document as published, kept to re-verify the cached copy.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Signed {
+    pub body: String,
+    pub signature: String,
+}
+
+/// Persisted installation id and last verified policy.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Cache {
+    pub client_id: String,
+    pub policy: Option<Signed>,
+}
+
+impl Schedule {
+    fn contains(&self, now: DateTime<Utc>) -> bool {
+        let time = now.time();
+        let (day, inside) = if self.start <= self.end {
+            (now.weekday(), self.start <= time && time < self.end)
+        } else if time >= self.start {
+            (now.weekday(), true)
+        } else {
+            // early morning part of a window opened the day before
+            (now.weekday().pred(), time < self.end)
+        };
+        inside && (self.days.is_empty() || self.days.contains(&day))
+    }
+}
+
+impl Policy {
+    /// Restrict `config` to what this policy allows.
+    pub fn apply(&self, config: &mut crate::config::Config) {
+        let allow_unlisted = config
+            .management
+            .as_ref()
+            .map(|m| m.allow_unlisted_destinations)
+            .unwrap_or(false);
+        if let Some(allowed) = &self.allowed_destinations
+            && !allow_unlisted
+        {
+            config.destinations.retain(|id, dest| {
+                let keep = allowed.contains(&dest.address);
+                if !keep {
+                    tracing::info!(%id, "destination not allowed by management policy");
+                }
+                keep
+            });
+        }
+        if self.require_lan_lockdown {
+            config.connection.lan_lockdown = true;
+        }
+    }
+
+    /// Whether this policy may replace `current`, a replayed or rolled back document is refused.
+    pub fn supersedes(&self, current: Option<&Policy>) -> bool {
+        current.is_none_or(|current| self.serial > current.serial)
+    }
+
+    /// Whether a connection may be established at `now`, the reason if not.
+    pub fn connection_allowed(&self, now: DateTime<Utc>) -> Result<(), String> {
+        if self.schedules.is_empty() || self.schedules.iter().any(|s| s.contains(now)) {
+            return Ok(());
+        }
+        Err("outside of the connection schedule set by the management policy".to_string())
+    }
+}
+
+/// Parse `body` if `signature` is a valid signature by `public_key`.
+pub fn verify(public_key: &[u8; 32], signed: &Signed) -> Result<Policy, Error> {
+    let signature = decode_hex(signed.signature.trim()).ok_or(Error::SignatureEncoding)?;
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(signed.body.as_bytes(), &signature)
+        .map_err(|_| Error::Signature)?;
+    Ok(serde_json::from_str(&signed.body)?)
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Download and verify the current policy.
+pub async fn fetch(client: &Client, config: &Config) -> Result<(Signed, Policy), Error> {
+    let sig_url = Url::parse(&format!("{}{SIGNATURE_SUFFIX}", config.url))?;
+    tracing::debug!(url = %config.url, "fetching management policy");
+    let body = client
+        .get(config.url.clone())
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())?
+        .text()
+        .await?;
+    let signature = client
+        .get(sig_url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())?
+        .text()
+        .await?;
+    let signed = Signed { body, signature };
+    let policy = verify(&config.public_key, &signed)?;
+    Ok((signed, policy))
+}
+
+pub async fn report(config: &Config, proxy: Option<&Url>, report: &Report) -> Result<(), Error> {
+    let url = config.url.join(REPORT_PATH)?;
+    proxy::outbound(proxy)?
+        .timeout(REQUEST_TIMEOUT)
+        .build()?
+        .post(url)
+        .json(report)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())?;
+    Ok(())
+}
+
+impl Cache {
+    /// Load the cache or start a new one with a fresh installation id.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_else(|| Self {
+                client_id: new_client_id(),
+                policy: None,
+            })
+    }
+
+    /// The cached policy if it still verifies against `public_key`.
+    pub fn verified_policy(&self, public_key: &[u8; 32]) -> Option<Policy> {
+        let signed = self.policy.as_ref()?;
+        verify(public_key, signed)
+            .map_err(|err| tracing::warn!(?err, "ignoring cached management policy"))
+            .ok()
+    }
+
+    pub fn store(&self, path: &Path) -> Result<(), Error> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Fetch the policy every `interval` until cancelled, each outcome is sent to `sender`. The
+/// watcher runs on `tasks`.
+pub fn watch(
+    config: Config,
+    proxy: Option<Url>,
+    sender: mpsc::Sender<Result<(Signed, Policy), Error>>,
+    tasks: &mut JoinSet<()>,
+) -> CancellationToken {
+    let cancel = CancellationToken::new();
+    let owned_cancel = cancel.clone();
+    tasks.spawn(async move {
+        let client = match proxy::outbound(proxy.as_ref()).and_then(|b| b.timeout(REQUEST_TIMEOUT).build()) {
+            Ok(client) => client,
+            Err(err) => {
+                let _ = sender.send(Err(err.into())).await;
+                return;
+            }
+        };
+        cancel
+            .run_until_cancelled(async move {
+                loop {
+                    let res = fetch(&client, &config).await;
+                    if sender.send(res).await.is_err() {
+                        tracing::debug!("management policy receiver dropped");
+                        return;
+                    }
+                    time::sleep(config.interval).await;
+                }
+            })
+            .await
+    });
+    owned_cancel
+}
+
+fn new_client_id() -> String {
+    let bytes: [u8; 16] = rand::rng().random();
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    fn sign(body: &str) -> ([u8; 32], Signed) {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let signature = pair.sign(body.as_bytes());
+        let signature = signature.as_ref().iter().map(|b| format!("{b:02x}")).collect();
+        let public_key = pair.public_key().as_ref().try_into().unwrap();
+        let signed = Signed {
+            body: body.to_string(),
+            signature,
+        };
+        (public_key, signed)
+    }
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn only_signed_policies_are_accepted() -> anyhow::Result<()> {
+        let (key, signed) = sign(r#"{"serial": 3, "require_lan_lockdown": true}"#);
+        let policy = verify(&key, &signed)?;
+        assert_eq!(policy.serial, 3);
+        assert!(policy.require_lan_lockdown);
+
+        let tampered = Signed {
+            body: r#"{"serial": 3}"#.to_string(),
+            ..signed
+        };
+        assert!(matches!(verify(&key, &tampered), Err(Error::Signature)));
+        Ok(())
+    }
+
+    #[test]
+    fn older_serials_are_refused() {
+        let current = Policy {
+            serial: 5,
+            ..Default::default()
+        };
+        let older = Policy {
+            serial: 4,
+            require_lan_lockdown: true,
+            ..Default::default()
+        };
+        let replayed = Policy {
+            serial: 5,
+            require_lan_lockdown: true,
+            ..Default::default()
+        };
+        assert!(!older.supersedes(Some(&current)));
+        assert!(!replayed.supersedes(Some(&current)));
+        assert!(older.supersedes(None));
+        assert!(current.supersedes(Some(&older)));
+    }
+
+    #[test]
+    fn schedules_limit_connections() {
+        let policy = Policy {
+            schedules: vec![
+                Schedule {
+                    days: vec![Weekday::Mon],
+                    start: "08:00:00".parse().unwrap(),
+                    end: "18:00:00".parse().unwrap(),
+                },
+                Schedule {
+                    days: vec![Weekday::Fri],
+                    start: "22:00:00".parse().unwrap(),
+                    end: "02:00:00".parse().unwrap(),
+                },
+            ],
+            ..Default::default()
+        };
+        // 2026-10-12 is a Monday
+        assert!(policy.connection_allowed(at("2026-10-12T09:30:00Z")).is_ok());
+        assert!(policy.connection_allowed(at("2026-10-12T18:00:00Z")).is_err());
+        assert!(policy.connection_allowed(at("2026-10-13T09:30:00Z")).is_err());
+        // overnight window from Friday into Saturday
+        assert!(policy.connection_allowed(at("2026-10-16T23:00:00Z")).is_ok());
+        assert!(policy.connection_allowed(at("2026-10-17T01:00:00Z")).is_ok());
+        assert!(policy.connection_allowed(at("2026-10-18T01:00:00Z")).is_err());
+        assert!(Policy::default().connection_allowed(at("2026-10-13T03:00:00Z")).is_ok());
+    }
+}
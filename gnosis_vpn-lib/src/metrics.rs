@@ -0,0 +1,188 @@
+//! Prometheus text exposition of the daemon's own metrics.
+//!
+//! Worker and root append their families to the edge client metrics returned by the telemetry
+//! command, so one scrape covers node and daemon.
+use std::fmt::Write;
+use std::fs;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Kind {
+    Gauge,
+    Counter,
+}
+
+impl Kind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Kind::Gauge => "gauge",
+            Kind::Counter => "counter",
+        }
+    }
+}
+
+/// Append a metric family with a single unlabeled sample.
+pub fn write(out: &mut String, name: &str, help: &str, kind: Kind, value: f64) {
+    header(out, name, help, kind);
+    let _ = writeln!(out, "{name} {value}");
+}
+
+/// Append a metric family with one sample per value of `label`.
+pub fn write_labeled<'a, I>(out: &mut String, name: &str, help: &str, kind: Kind, label: &str, samples: I)
+where
+    I: IntoIterator<Item = (&'a str, f64)>,
+{
+    header(out, name, help, kind);
+    for (value_of_label, value) in samples {
+        let _ = writeln!(out, "{name}{{{label}=\"{value_of_label}\"}} {value}");
+    }
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: Kind) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {}", kind.as_str());
+}
+
+/// Resource usage of the current process, `None` where the platform does not expose it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProcessUsage {
+    pub resident_bytes: Option<u64>,
+    pub threads: Option<u64>,
+    pub open_fds: Option<u64>,
+    pub open_sockets: Option<u64>,
+    pub alive_tasks: Option<u64>,
+}
+
+impl ProcessUsage {
+    /// Samples the current process and, when called from within a runtime, its tokio tasks.
+    pub fn sample() -> Self {
+        let alive_tasks = tokio::runtime::Handle::try_current()
+            .ok()
+            .map(|handle| handle.metrics().num_alive_tasks() as u64);
+        let (resident_bytes, threads) = fs::read_to_string("/proc/self/status")
+            .map(|status| parse_status(&status))
+            .unwrap_or_default();
+        let (open_fds, open_sockets) = count_fds();
+        Self {
+            resident_bytes,
+            threads,
+            open_fds,
+            open_sockets,
+            alive_tasks,
+        }
+    }
+
+    /// Append one family per available value, prefixed with the `process` name so root and worker
+    /// families stay distinct in the combined output.
+    pub fn write_metrics(&self, out: &mut String, process: &str) {
+        let families = [
+            ("resident_memory_bytes", "Resident memory", self.resident_bytes),
+            ("threads", "Operating system threads", self.threads),
+            ("open_fds", "Open file descriptors", self.open_fds),
+            ("open_sockets", "Open sockets", self.open_sockets),
+            ("alive_tasks", "Tokio tasks alive in the runtime", self.alive_tasks),
+        ];
+        for (name, help, value) in families {
+            if let Some(value) = value {
+                write(
+                    out,
+                    &format!("gnosis_vpn_{process}_{name}"),
+                    &format!("{help} of the {process} process"),
+                    Kind::Gauge,
+                    value as f64,
+                );
+            }
+        }
+    }
+}
+
+/// Resident bytes and thread count from the contents of `/proc/<pid>/status`.
+fn parse_status(status: &str) -> (Option<u64>, Option<u64>) {
+    let field = |key: &str| {
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix(key))
+            .and_then(|value| value.split_whitespace().next())
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+    (field("VmRSS:").map(|kib| kib * 1024), field("Threads:"))
+}
+
+/// Open descriptors and how many of them are sockets, the latter is only known on Linux.
+fn count_fds() -> (Option<u64>, Option<u64>) {
+    if let Ok(entries) = fs::read_dir("/proc/self/fd") {
+        let (mut fds, mut sockets) = (0, 0);
+        for entry in entries.flatten() {
+            fds += 1;
+            if fs::read_link(entry.path()).is_ok_and(|target| target.to_string_lossy().starts_with("socket:")) {
+                sockets += 1;
+            }
+        }
+        return (Some(fds), Some(sockets));
+    }
+    // macOS exposes the descriptor table without link targets
+    let fds = fs::read_dir("/dev/fd").ok().map(|entries| entries.count() as u64);
+    (fds, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_prometheus_text() {
+        let mut out = String::new();
+        write(&mut out, "gnosis_vpn_requests_total", "Requests", Kind::Counter, 3.0);
+        write_labeled(
+            &mut out,
+            "gnosis_vpn_depth",
+            "Depth",
+            Kind::Gauge,
+            "channel",
+            [("incoming", 1.0), ("results", 0.5)],
+        );
+        assert_eq!(
+            out,
+            "# HELP gnosis_vpn_requests_total Requests\n\
+             # TYPE gnosis_vpn_requests_total counter\n\
+             gnosis_vpn_requests_total 3\n\
+             # HELP gnosis_vpn_depth Depth\n\
+             # TYPE gnosis_vpn_depth gauge\n\
+             gnosis_vpn_depth{channel=\"incoming\"} 1\n\
+             gnosis_vpn_depth{channel=\"results\"} 0.5\n"
+        );
+    }
+
+    #[test]
+    fn parses_proc_status() {
+        let status = "Name:\tgnosis_vpn-root\nVmPeak:\t  80000 kB\nVmRSS:\t   2048 kB\nThreads:\t7\n";
+        assert_eq!(parse_status(status), (Some(2048 * 1024), Some(7)));
+        assert_eq!(parse_status("Name:\tother\n"), (None, None));
+    }
+
+    #[test]
+    fn writes_only_available_process_usage() {
+        let usage = ProcessUsage {
+            open_fds: Some(12),
+            alive_tasks: Some(3),
+            ..Default::default()
+        };
+        let mut out = String::new();
+        usage.write_metrics(&mut out, "worker");
+        assert_eq!(
+            out,
+            "# HELP gnosis_vpn_worker_open_fds Open file descriptors of the worker process\n\
+             # TYPE gnosis_vpn_worker_open_fds gauge\n\
+             gnosis_vpn_worker_open_fds 12\n\
+             # HELP gnosis_vpn_worker_alive_tasks Tokio tasks alive in the runtime of the worker process\n\
+             # TYPE gnosis_vpn_worker_alive_tasks gauge\n\
+             gnosis_vpn_worker_alive_tasks 3\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn samples_the_current_process() {
+        let usage = ProcessUsage::sample();
+        assert!(usage.alive_tasks.is_some());
+        assert!(usage.open_fds.is_some_and(|fds| fds > 0));
+    }
+}
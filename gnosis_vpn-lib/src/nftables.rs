@@ -0,0 +1,165 @@
+//! Shared nftables plumbing for the tables gnosis_vpn installs on Linux.
+//!
+//! Every table is built as one nftnl batch and sent in a single netlink transaction, so it is
+//! either installed completely or not at all. Next to [`send_batch`] this module provides the
+//! expressions nftnl lacks: rewriting packet headers ([`PayloadLoad`], [`PayloadWrite`]) and
+//! counting into a dynamic set ([`DynsetUpdate`]).
+
+use std::ffi::{CStr, c_void};
+use std::ptr::NonNull;
+
+use nftnl::expr::{Counter, Expression};
+use nftnl::nftnl_sys::{self as sys, libc};
+use nftnl::{Batch, FinalizedBatch, MsgType, ProtoFamily, Rule, Table};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Netlink(String),
+}
+
+/// Send `batch` as one transaction and wait for the kernel to acknowledge every message.
+pub fn send_batch(batch: &FinalizedBatch) -> Result<(), Error> {
+    let socket = mnl::Socket::new(mnl::Bus::Netfilter)
+        .map_err(|e| Error::Netlink(format!("failed to open netlink socket: {e}")))?;
+    let portid = socket.portid();
+
+    socket
+        .send_all(batch)
+        .map_err(|e| Error::Netlink(format!("failed to send batch: {e}")))?;
+
+    let mut buffer = vec![0; nftnl::nft_nlmsg_maxsize() as usize];
+    let mut expected_seqs = batch.sequence_numbers();
+
+    while !expected_seqs.is_empty() {
+        let messages = socket
+            .recv(&mut buffer[..])
+            .map_err(|e| Error::Netlink(format!("failed to receive netlink response: {e}")))?;
+        for message in messages {
+            let message = message.map_err(|e| Error::Netlink(format!("netlink message error: {e}")))?;
+            let expected_seq = expected_seqs
+                .next()
+                .ok_or_else(|| Error::Netlink("unexpected ACK from netfilter".into()))?;
+            mnl::cb_run(message, expected_seq, portid)
+                .map_err(|e| Error::Netlink(format!("netlink ACK error: {e}")))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove the `inet` table `name` with everything in it, a missing table is not an error.
+pub fn delete_table(name: &CStr) -> Result<(), Error> {
+    let table = Table::new(name, ProtoFamily::Inet);
+    let mut batch = Batch::new();
+    // Add-then-Del avoids ENOENT if the table was never created.
+    batch.add(&table, MsgType::Add);
+    batch.add(&table, MsgType::Del);
+    send_batch(&batch.finalize())
+}
+
+/// Load `len` bytes of the network header at `offset` into register 1, for fields nftnl has no name for.
+pub struct PayloadLoad {
+    pub offset: u32,
+    pub len: u32,
+}
+
+impl Expression for PayloadLoad {
+    fn to_expr(&self, _rule: &Rule) -> NonNull<sys::nftnl_expr> {
+        let expr = alloc(c"payload");
+        unsafe {
+            let expr = expr.as_ptr();
+            sys::nftnl_expr_set_u32(
+                expr,
+                sys::NFTNL_EXPR_PAYLOAD_BASE as u16,
+                libc::NFT_PAYLOAD_NETWORK_HEADER as u32,
+            );
+            sys::nftnl_expr_set_u32(expr, sys::NFTNL_EXPR_PAYLOAD_OFFSET as u16, self.offset);
+            sys::nftnl_expr_set_u32(expr, sys::NFTNL_EXPR_PAYLOAD_LEN as u16, self.len);
+            sys::nftnl_expr_set_u32(expr, sys::NFTNL_EXPR_PAYLOAD_DREG as u16, libc::NFT_REG_1 as u32);
+        }
+        expr
+    }
+}
+
+/// Write register 1 into the network header at `offset`, e.g. after a `bitwise` rewrote a field.
+pub struct PayloadWrite {
+    pub offset: u32,
+    pub len: u32,
+    /// Offset of the IPv4 header checksum to fix up, `None` for headers without one
+    pub checksum_offset: Option<u32>,
+}
+
+impl Expression for PayloadWrite {
+    fn to_expr(&self, _rule: &Rule) -> NonNull<sys::nftnl_expr> {
+        let expr = alloc(c"payload");
+        unsafe {
+            let expr = expr.as_ptr();
+            sys::nftnl_expr_set_u32(
+                expr,
+                sys::NFTNL_EXPR_PAYLOAD_BASE as u16,
+                libc::NFT_PAYLOAD_NETWORK_HEADER as u32,
+            );
+            sys::nftnl_expr_set_u32(expr, sys::NFTNL_EXPR_PAYLOAD_OFFSET as u16, self.offset);
+            sys::nftnl_expr_set_u32(expr, sys::NFTNL_EXPR_PAYLOAD_LEN as u16, self.len);
+            sys::nftnl_expr_set_u32(expr, sys::NFTNL_EXPR_PAYLOAD_SREG as u16, libc::NFT_REG_1 as u32);
+            if let Some(checksum_offset) = self.checksum_offset {
+                sys::nftnl_expr_set_u32(
+                    expr,
+                    sys::NFTNL_EXPR_PAYLOAD_CSUM_TYPE as u16,
+                    libc::NFT_PAYLOAD_CSUM_INET as u32,
+                );
+                sys::nftnl_expr_set_u32(expr, sys::NFTNL_EXPR_PAYLOAD_CSUM_OFFSET as u16, checksum_offset);
+            }
+        }
+        expr
+    }
+}
+
+/// Add the key in register 1 to the dynamic set `set` and count the packet on its element.
+pub struct DynsetUpdate<'a> {
+    pub set: &'a CStr,
+    pub set_id: u32,
+}
+
+impl Expression for DynsetUpdate<'_> {
+    fn to_expr(&self, rule: &Rule) -> NonNull<sys::nftnl_expr> {
+        let expr = alloc(c"dynset");
+        unsafe {
+            let expr = expr.as_ptr();
+            sys::nftnl_expr_set_u32(expr, sys::NFTNL_EXPR_DYNSET_SREG_KEY as u16, libc::NFT_REG_1 as u32);
+            sys::nftnl_expr_set_u32(
+                expr,
+                sys::NFTNL_EXPR_DYNSET_OP as u16,
+                libc::NFT_DYNSET_OP_UPDATE as u32,
+            );
+            sys::nftnl_expr_set_str(expr, sys::NFTNL_EXPR_DYNSET_SET_NAME as u16, self.set.as_ptr());
+            sys::nftnl_expr_set_u32(expr, sys::NFTNL_EXPR_DYNSET_SET_ID as u16, self.set_id);
+            // the dynset owns the counter from here on and frees it along with itself
+            let counter = Counter.to_expr(rule);
+            sys::nftnl_expr_set(
+                expr,
+                sys::NFTNL_EXPR_DYNSET_EXPR as u16,
+                counter.as_ptr() as *const c_void,
+                0,
+            );
+        }
+        expr
+    }
+}
+
+/// Mark a set built by [`nftnl::set::Set::new`] as dynamic, so rules can add elements to it.
+pub fn make_dynamic<K>(set: &nftnl::set::Set<'_, K>) {
+    unsafe {
+        sys::nftnl_set_set_u32(
+            set.as_ptr().as_ptr(),
+            sys::NFTNL_SET_FLAGS as u16,
+            libc::NFT_SET_EVAL as u32,
+        );
+    }
+}
+
+fn alloc(name: &CStr) -> NonNull<sys::nftnl_expr> {
+    NonNull::new(unsafe { sys::nftnl_expr_alloc(name.as_ptr()) }).expect("nftnl expression allocation failed")
+}
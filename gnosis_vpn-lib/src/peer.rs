@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 use std::net::Ipv4Addr;
+use std::time::Duration;
 
 use crate::connection::destination::Address;
 
@@ -8,10 +9,32 @@ use crate::connection::destination::Address;
 pub struct Peer {
     pub address: Address,
     pub ipv4_addrs: Vec<Ipv4Addr>,
+    /// Whether the node currently holds a transport connection to this peer
+    pub connected: bool,
+    /// Link quality the node measured towards this peer, `None` until probed
+    pub quality: Option<Quality>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Quality {
+    /// Score from 0.0 to 1.0, higher is better
+    pub score: f64,
+    pub latency: Option<Duration>,
 }
 
 impl Peer {
     pub fn new(address: Address, ipv4_addrs: Vec<Ipv4Addr>) -> Self {
-        Self { address, ipv4_addrs }
+        Self {
+            address,
+            ipv4_addrs,
+            connected: false,
+            quality: None,
+        }
+    }
+
+    pub fn with_connection(mut self, connected: bool, quality: Option<Quality>) -> Self {
+        self.connected = connected;
+        self.quality = quality;
+        self
     }
 }
@@ -0,0 +1,111 @@
+//! HTTP(S) proxy for requests leaving the host.
+//!
+//! Networks without direct egress only let requests out through a proxy. `[network] https_proxy`
+//! takes precedence over the standard `HTTPS_PROXY`, `HTTP_PROXY` and `ALL_PROXY` variables,
+//! `NO_PROXY` exempts hosts from either. Root passes the configured proxy on to the worker in
+//! [`ENV_VARS`], so chain requests made by hopr-lib and every client built in the worker use it.
+//! Requests into local HOPR sessions and through the tunnel must never take a proxy, see [`direct`].
+//! Clients of either kind verify TLS against the trust installed by [`tls::install`].
+use reqwest::{Client, ClientBuilder, NoProxy, Proxy};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use std::env;
+
+use crate::tls;
+
+/// Variables the worker receives the configured proxy in.
+pub const ENV_VARS: [&str; 2] = ["HTTPS_PROXY", "https_proxy"];
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    /// Proxy for outgoing HTTPS requests, `None` falls back to the proxy variables
+    pub https_proxy: Option<Url>,
+    /// CA bundle and pinned certificates replacing the system trust store
+    pub tls: tls::Config,
+}
+
+/// Client builder for requests leaving the host, through `configured` or the proxy variables.
+pub fn outbound(configured: Option<&Url>) -> Result<ClientBuilder, reqwest::Error> {
+    let builder = tls::apply(Client::builder());
+    match configured {
+        // an explicit proxy replaces the one reqwest picks from the variables
+        Some(url) => Ok(builder.proxy(Proxy::https(url.as_str())?.no_proxy(NoProxy::from_env()))),
+        None => Ok(builder),
+    }
+}
+
+/// Client builder for requests into local HOPR sessions or through the tunnel.
+pub fn direct_builder() -> ClientBuilder {
+    tls::apply(Client::builder()).no_proxy()
+}
+
+/// [`direct_builder`] with defaults, panics like [`Client::new`] if the TLS backend cannot be initialized.
+pub fn direct() -> Client {
+    direct_builder().build().expect("TLS backend cannot be initialized")
+}
+
+/// Proxy requests to `url` go through according to the proxy variables, `None` if they go direct.
+///
+/// Behind a proxy it is the proxy, not the host of `url`, that has to stay reachable outside the tunnel.
+pub fn from_env(url: &Url) -> Option<Url> {
+    from_env_with(url, |key| env::var(key).ok())
+}
+
+fn from_env_with<F: Fn(&str) -> Option<String>>(url: &Url, var: F) -> Option<Url> {
+    let lookup = |key: &str| {
+        var(key)
+            .or_else(|| var(&key.to_ascii_lowercase()))
+            .filter(|value| !value.is_empty())
+    };
+    let host = url.host_str()?;
+    if lookup("NO_PROXY").is_some_and(|no_proxy| exempts(&no_proxy, host)) {
+        return None;
+    }
+    let scheme_var = match url.scheme() {
+        "https" => "HTTPS_PROXY",
+        "http" => "HTTP_PROXY",
+        _ => return None,
+    };
+    lookup(scheme_var)
+        .or_else(|| lookup("ALL_PROXY"))
+        .and_then(|proxy| Url::parse(&proxy).ok())
+}
+
+/// Host matches an entry of a `NO_PROXY` list, entries match their subdomains as well.
+fn exempts(no_proxy: &str, host: &str) -> bool {
+    no_proxy.split(',').map(str::trim).any(|entry| {
+        let entry = entry.trim_start_matches('.');
+        entry == "*" || (!entry.is_empty() && (host == entry || host.ends_with(&format!(".{entry}"))))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    #[test]
+    fn variables_pick_proxy_by_scheme_and_honor_no_proxy() -> anyhow::Result<()> {
+        let vars = HashMap::from([
+            ("https_proxy", "http://proxy.corp:3128"),
+            ("ALL_PROXY", "socks5://fallback.corp:1080"),
+            ("NO_PROXY", "localhost, .internal.corp"),
+        ]);
+        let var = |key: &str| vars.get(key).map(|v| v.to_string());
+
+        let blokli = Url::parse("https://blokli.example.com/graphql")?;
+        assert_eq!(from_env_with(&blokli, var), Some(Url::parse("http://proxy.corp:3128")?));
+        let plain = Url::parse("http://blokli.example.com")?;
+        assert_eq!(
+            from_env_with(&plain, var),
+            Some(Url::parse("socks5://fallback.corp:1080")?)
+        );
+
+        let exempt = Url::parse("https://chain.internal.corp")?;
+        assert_eq!(from_env_with(&exempt, var), None);
+        assert_eq!(from_env_with(&blokli, |_| None), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+//! Public IP of the tunnel as seen by the internet.
+//!
+//! Once a connection is verified the worker asks the root process to query an echo endpoint, the
+//! request leaves through the tunnel so the answer is the exit address. The result is kept with
+//! the connection and shown in status, users no longer need to curl an echo service themselves.
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use url::Url;
+
+use std::fmt::{self, Display};
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime};
+
+use crate::{proxy, serde_utils};
+
+/// Answers with the caller address and its country, either as JSON or plain text.
+pub const DEFAULT_ENDPOINT: &str = "https://ifconfig.co/json";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Unexpected answer: {0}")]
+    Unparsable(String),
+}
+
+/// Exit address of the current connection, reported in status.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct PublicIp {
+    #[schemars(with = "String")]
+    pub ip: IpAddr,
+    /// Country as named by the echo endpoint, not every endpoint provides it
+    pub country: Option<String>,
+    #[serde(with = "serde_utils::system_time")]
+    #[schemars(with = "u64")]
+    pub resolved_at: SystemTime,
+}
+
+impl Display for PublicIp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.country {
+            Some(country) => write!(f, "Public IP: {} ({country})", self.ip),
+            None => write!(f, "Public IP: {}", self.ip),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct EchoAnswer {
+    ip: IpAddr,
+    country: Option<String>,
+}
+
+/// Query `endpoint` for the address the request originated from.
+pub async fn lookup(endpoint: &Url, timeout: Duration) -> Result<PublicIp, Error> {
+    // a proxy would answer with its own address instead of the exit's
+    let body = proxy::direct_builder()
+        .timeout(timeout)
+        .build()?
+        .get(endpoint.clone())
+        .header(reqwest::header::ACCEPT, "application/json, text/plain")
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let (ip, country) = parse(&body)?;
+    Ok(PublicIp {
+        ip,
+        country,
+        resolved_at: SystemTime::now(),
+    })
+}
+
+/// Echo services either answer with a JSON object holding `ip` and `country` or just the bare address.
+fn parse(body: &str) -> Result<(IpAddr, Option<String>), Error> {
+    let body = body.trim();
+    if let Ok(ip) = body.parse() {
+        return Ok((ip, None));
+    }
+    let answer: EchoAnswer = serde_json::from_str(body).map_err(|_| {
+        let excerpt: String = body.chars().take(80).collect();
+        Error::Unparsable(excerpt)
+    })?;
+    Ok((answer.ip, answer.country.filter(|c| !c.is_empty())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn parses_json_and_plain_answers() {
+        let json = r#"{"ip":"185.12.64.3","ip_decimal":3104587779,"country":"Germany","country_iso":"DE"}"#;
+        assert_eq!(
+            parse(json).unwrap(),
+            (IpAddr::V4(Ipv4Addr::new(185, 12, 64, 3)), Some("Germany".to_string()))
+        );
+        assert_eq!(
+            parse(r#"{"ip":"2001:db8::1","country":""}"#).unwrap(),
+            (IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)), None)
+        );
+        assert_eq!(
+            parse("185.12.64.3\n").unwrap(),
+            (IpAddr::V4(Ipv4Addr::new(185, 12, 64, 3)), None)
+        );
+        assert!(matches!(parse("<html>blocked</html>"), Err(Error::Unparsable(_))));
+    }
+}
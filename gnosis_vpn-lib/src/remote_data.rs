@@ -1,4 +1,3 @@
-use backon::ExponentialBuilder;
 use reqwest::header::{self, HeaderMap, HeaderValue};
 use thiserror::Error;
 use tokio::net;
@@ -23,37 +22,6 @@ pub fn json_headers() -> HeaderMap {
     headers
 }
 
-/// Creates a backoff strategy with exponential backoff and jitter, suitable for retrying remote
-/// data fetches with long delays.
-pub fn backoff_expo_long_delay() -> ExponentialBuilder {
-    ExponentialBuilder::new()
-        .with_min_delay(std::time::Duration::from_secs(10))
-        .with_max_delay(std::time::Duration::from_secs(60))
-        .with_factor(2.0)
-        .with_jitter()
-}
-
-/// Creates a backoff strategy with exponential backoff and jitter, suitable for retrying remote
-/// data fetches with short delays.
-pub fn backoff_expo_short_delay() -> ExponentialBuilder {
-    ExponentialBuilder::new()
-        .with_min_delay(std::time::Duration::from_secs(1))
-        .with_max_delay(std::time::Duration::from_secs(10))
-        .with_factor(2.0)
-        .with_jitter()
-}
-
-/// Like [`backoff_expo_short_delay`] but limited to 1 retry (2 total attempts).
-///
-/// hopr-lib already retries new_session 3× internally (~15 s total for 1-hop) before surfacing
-/// an error, so by the time we get a failure it is likely structural. One extra attempt covers
-/// brief transients; more would delay the worker restart that is designed to fix deeper issues.
-/// With only one retry the backoff growth pattern is irrelevant — both calls use the ~1 s
-/// first-step delay.
-pub fn backoff_expo_short_delay_bridge() -> ExponentialBuilder {
-    backoff_expo_short_delay().with_max_times(1)
-}
-
 /// Resolves the IPv4 addresses for the host and port specified in the provided URL.
 pub async fn resolve_ips(url: &url::Url) -> Result<Vec<Ipv4Addr>, Error> {
     let host = url.host_str().ok_or(Error::NoHost)?;
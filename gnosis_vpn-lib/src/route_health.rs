@@ -20,10 +20,15 @@
 //!
 //! Core owns one `RouteHealth` per configured destination and uses the
 //! aggregate view (via `any_needs_peers`) to decide when to poll peers.
+//!
+//! All trackers share one [`Semaphore`] so that destinations becoming routable
+//! at the same time (typically right after startup) are probed in parallel
+//! without opening an unbounded number of bridge sessions at once.
 use edgli::hopr_lib::HoprSessionClientConfig;
 use rand::prelude::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use tokio::sync::mpsc;
+use tokio::sync::{Semaphore, mpsc};
 use tokio::time;
 use tokio_util::sync::CancellationToken;
 
@@ -36,12 +41,14 @@ use crate::connection::destination::{Address, Destination, HopRouting};
 use crate::connection::options::Options;
 use crate::connection::options::surb_config_for;
 use crate::core::runner::Results;
+use crate::core::tasks::{Subsystem, Tasks};
+use crate::gvpn_client::ApiVersion;
 use crate::hopr::types::SessionClientMetadata;
 use crate::hopr::{Hopr, HoprError};
 use crate::serde_utils;
-use crate::{gvpn_client, log_output};
+use crate::{gvpn_client, log_output, proxy};
 
-pub use crate::gvpn_client::{Health, LoadAvg, Slots, Versions};
+pub use crate::gvpn_client::{Health, LoadAvg, Maintenance, Slots, Versions};
 
 const MAX_INTERVAL_BETWEEN_FAILURES: Duration = Duration::from_mins(5);
 const FAILURE_INTERVAL: Duration = Duration::from_secs(30);
@@ -60,6 +67,11 @@ const FAILURE_INTERVAL: Duration = Duration::from_secs(30);
 ///     session establishment succeeds.
 const GRAPH_WARMUP_RETRY_INTERVAL: Duration = Duration::from_secs(90);
 const GRAPH_WARMUP_RETRY_COUNT: u32 = 3;
+/// Destinations failing a connection attempt within this window are skipped by `connect --any`
+/// when asked to avoid failed ones.
+const RECENT_CONNECTION_FAILURE: Duration = Duration::from_mins(15);
+/// Upper bound of health checks running at the same time across all destinations.
+pub(crate) const MAX_PARALLEL_HEALTH_CHECKS: usize = 4;
 
 /// Add ±25 % random jitter to `base`. Zero durations (immediate triggers)
 /// are returned unchanged so initial spawns are not delayed.
@@ -78,18 +90,13 @@ pub(crate) fn jitter(base: Duration) -> Duration {
     }
 }
 
-/// Returns the first supported API version found in `server_versions`, or `None`
+/// Returns the preferred API version supported by both sides, or `None`
 /// if there is no compatible version.
 ///
-/// This is the single place that maps API version strings to gvpn_client modules.
-/// Currently only "v1" is supported — all gvpn_client functions use the /api/v1/ prefix.
-/// Add new versions here when introducing a new API module.
-fn select_api_version(server_versions: &[String]) -> Option<&'static str> {
-    const SUPPORTED: &[&str] = &["v1"]; // v1 → gvpn_client
-    SUPPORTED
-        .iter()
-        .copied()
-        .find(|&v| server_versions.iter().any(|sv| sv == v))
+/// Negotiated per exit, so exits and clients can be upgraded independently.
+/// New versions are added as [`gvpn_client::ExitApi`] implementations.
+fn select_api_version(server_versions: &[String]) -> Option<ApiVersion> {
+    ApiVersion::negotiate(server_versions)
 }
 
 // ---------------------------------------------------------------------------
@@ -112,7 +119,7 @@ pub enum StaticNeed {
 /// Terminal failure modes that cannot be recovered from without a config
 /// change or an exit-server upgrade. Once a route enters
 /// [`RouteHealthState::Unrecoverable`] it stays there.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum UnrecoverableReason {
     /// Direct (0-hop) peering is configured but insecure peering is disabled.
     NotAllowed,
@@ -127,21 +134,30 @@ pub enum UnrecoverableReason {
 /// Not every check cycle fetches every field; when a
 /// field is skipped it is carried forward from the previous successful
 /// snapshot so the state always exposes a full picture.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ExitHealth {
     #[serde(with = "serde_utils::system_time")]
+    #[schemars(with = "u64")]
     pub checked_at: SystemTime,
     pub versions: gvpn_client::Versions,
     #[serde(with = "serde_utils::duration_ms")]
+    #[schemars(with = "f64")]
     pub ping_rtt: Duration,
     pub health: gvpn_client::Health,
 }
 
+impl ExitHealth {
+    /// Registration API to use with this exit, exits predating version negotiation speak v1.
+    pub fn api_version(&self) -> ApiVersion {
+        select_api_version(&self.versions.versions).unwrap_or(ApiVersion::V1)
+    }
+}
+
 /// Combined route state: network reachability plus exit-node health.
 ///
 /// Also the wire-format shown to the CLI via the command API, so variant
 /// names and payloads are part of the user-visible surface.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "state")]
 pub enum RouteHealthState {
     Unrecoverable {
@@ -167,10 +183,23 @@ pub enum RouteHealthState {
     Connecting {
         exit: ExitHealth,
         #[serde(with = "serde_utils::opt_duration_ms")]
+        #[schemars(with = "Option<f64>")]
         tunnel_ping_rtt: Option<Duration>,
     },
 }
 
+impl RouteHealthState {
+    /// Maintenance notice from the last exit health check, if the exit announced a valid one.
+    pub fn maintenance(&self) -> Option<&Maintenance> {
+        match self {
+            RouteHealthState::ReadyToConnect { exit } | RouteHealthState::Connecting { exit, .. } => {
+                exit.health.maintenance.as_ref().filter(|m| m.retires_at().is_some())
+            }
+            _ => None,
+        }
+    }
+}
+
 /// Message a health-check runner task sends back to the main loop, consumed
 /// by `RouteHealth::health_check_result`.
 ///
@@ -210,12 +239,16 @@ pub(crate) struct RouteHealth {
     state: RouteHealthState,
     health_check_cancel: CancellationToken,
     cancel_on_shutdown: CancellationToken,
+    probe_permits: Arc<Semaphore>,
+    tasks: Tasks,
     check_cycle: u32,
     checking_since: Option<SystemTime>,
     exit_failures: u32,
     exit_last_error: Option<String>,
     tunnel_ping_failures: u32,
     tunnel_ping_last_error: Option<String>,
+    cancelled_at: Option<SystemTime>,
+    connection_failed_at: Option<SystemTime>,
 }
 
 // ---------------------------------------------------------------------------
@@ -225,13 +258,16 @@ pub(crate) struct RouteHealth {
 impl RouteHealth {
     /// Build an initial tracker for `dest`. `cancel_on_shutdown` is inherited
     /// by every background task this tracker spawns so that they all stop
-    /// when the core shuts down. `allow_insecure` gates 0-hop routes;
-    /// `allow_experimental` gates 2+ hop routes.
+    /// when the core shuts down. `probe_permits` is shared by all trackers and
+    /// bounds how many health checks run concurrently, `tasks` tracks them. `allow_insecure` gates
+    /// 0-hop routes; `allow_experimental` gates 2+ hop routes.
     pub(crate) fn new(
         dest: &Destination,
         allow_insecure: bool,
         allow_experimental: bool,
         cancel_on_shutdown: CancellationToken,
+        probe_permits: Arc<Semaphore>,
+        tasks: Tasks,
     ) -> Self {
         let static_need = derive_static_need(&dest.routing, dest.address);
         let state = derive_initial_state(&dest.routing, allow_insecure, allow_experimental);
@@ -242,12 +278,16 @@ impl RouteHealth {
             state,
             health_check_cancel,
             cancel_on_shutdown,
+            probe_permits,
+            tasks,
             check_cycle: 0,
             checking_since: None,
             exit_failures: 0,
             exit_last_error: None,
             tunnel_ping_failures: 0,
             tunnel_ping_last_error: None,
+            cancelled_at: None,
+            connection_failed_at: None,
         }
     }
 }
@@ -292,6 +332,11 @@ impl RouteHealth {
         &self.state
     }
 
+    #[cfg(test)]
+    pub(crate) fn set_state(&mut self, state: RouteHealthState) {
+        self.state = state;
+    }
+
     pub(crate) fn last_error(&self) -> Option<&str> {
         self.exit_last_error.as_deref()
     }
@@ -304,6 +349,10 @@ impl RouteHealth {
         self.exit_failures
     }
 
+    pub(crate) fn cancelled_at(&self) -> Option<SystemTime> {
+        self.cancelled_at
+    }
+
     pub(crate) fn needs_peer(&self) -> bool {
         matches!(self.state, RouteHealthState::NeedsPeering { .. })
     }
@@ -336,6 +385,12 @@ impl RouteHealth {
     pub fn is_unrecoverable(&self) -> bool {
         matches!(self.state, RouteHealthState::Unrecoverable { .. })
     }
+
+    pub(crate) fn failed_recently(&self, now: SystemTime) -> bool {
+        self.connection_failed_at
+            .and_then(|at| now.duration_since(at).ok())
+            .is_some_and(|ago| ago < RECENT_CONNECTION_FAILURE)
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -556,6 +611,29 @@ impl RouteHealth {
         }
     }
 
+    /// Run a full check cycle right away, used when Core probes all
+    /// destinations at once.
+    ///
+    /// Only `Routable` and `ReadyToConnect` routes are probed. Routes still
+    /// waiting for peers or a channel get their first check from those
+    /// transitions, `Connecting` keeps its reduced cadence.
+    pub(crate) fn probe(
+        &mut self,
+        hopr: &Arc<Hopr>,
+        dest: &Destination,
+        options: &Options,
+        sender: &mpsc::Sender<Results>,
+    ) {
+        if !matches!(
+            self.state,
+            RouteHealthState::Routable | RouteHealthState::ReadyToConnect { .. }
+        ) {
+            return;
+        }
+        self.check_cycle = 0;
+        self.spawn_health_check(Duration::ZERO, hopr, dest, options, sender);
+    }
+
     /// Transition `ReadyToConnect` → `Connecting` when Core starts bringing
     /// up the tunnel.
     ///
@@ -612,6 +690,13 @@ impl RouteHealth {
         }
     }
 
+    /// Remember that the user aborted a connection attempt to this route. The state itself is
+    /// left to `disconnecting`, a cancelled attempt says nothing about the exit.
+    pub(crate) fn connect_cancelled(&mut self) {
+        tracing::debug!(destination = %self.id, "connection attempt cancelled");
+        self.cancelled_at = Some(SystemTime::now());
+    }
+
     /// Update exit health from a tunnel ping result. Returns the tunnel ping
     /// failure count after applying this result. On success the `ping_rtt` is
     /// refreshed with the new measurement. On failure the exit data is
@@ -647,6 +732,12 @@ impl RouteHealth {
         }
         self.exit_last_error = Some(err);
     }
+
+    /// A connection attempt failed for good, unlike the setbacks reported via [`Self::with_error`].
+    pub(crate) fn connection_failed(&mut self, err: String, now: SystemTime) {
+        self.with_error(err);
+        self.connection_failed_at = Some(now);
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -697,17 +788,24 @@ impl RouteHealth {
             }
         };
 
+        // the destination we are connecting to must not queue behind background probes
+        let permits = (!is_connecting).then(|| self.probe_permits.clone());
         let token = self.health_check_cancel.clone();
         let hopr = hopr.clone();
         let dest = dest.clone();
         let options = options.clone();
         let sender = sender.clone();
+        let tasks = self.tasks.clone();
 
-        tokio::spawn(async move {
+        self.tasks.spawn(Subsystem::Health, async move {
             token
                 .run_until_cancelled(async {
                     time::sleep(jitter(delay)).await;
-                    run_health_check(hopr, &dest, &options, &scope, &sender).await;
+                    let _permit = match permits {
+                        Some(permits) => permits.acquire_owned().await.ok(),
+                        None => None,
+                    };
+                    run_health_check(hopr, &dest, &options, &scope, &sender, tasks).await;
                 })
                 .await;
         });
@@ -739,6 +837,7 @@ async fn run_health_check(
     options: &Options,
     scope: &CheckScope,
     sender: &mpsc::Sender<Results>,
+    tasks: Tasks,
 ) {
     let id = destination.id.clone();
     let checked_at = SystemTime::now();
@@ -750,7 +849,7 @@ async fn run_health_check(
         })
         .await;
 
-    let res_session = HealthSession::open(hopr, destination, options).await;
+    let res_session = HealthSession::open(hopr, destination, options, tasks).await;
     let session = match res_session {
         Ok(session) => session,
         Err(err) => {
@@ -773,7 +872,7 @@ async fn run_health_check(
     // future is cancelled via `tokio::select!`.
     let socket_addr = session.meta.bound_host;
     let timeout = options.timeouts.http;
-    let client = reqwest::Client::new();
+    let client = proxy::direct();
     let mut versions = None;
     if scope.version {
         let res_versions = gvpn_client::versions(&client, socket_addr, timeout).await;
@@ -888,6 +987,7 @@ async fn run_health_check(
 struct HealthSession {
     hopr: Arc<Hopr>,
     meta: SessionClientMetadata,
+    tasks: Tasks,
     closed: bool,
 }
 
@@ -896,7 +996,12 @@ impl HealthSession {
     ///
     /// Uses the configured bridge capabilities/target and applies the health-check SURB settings —
     /// the session is short-lived and not used for user traffic.
-    async fn open(hopr: Arc<Hopr>, destination: &Destination, options: &Options) -> Result<Self, HoprError> {
+    async fn open(
+        hopr: Arc<Hopr>,
+        destination: &Destination,
+        options: &Options,
+        tasks: Tasks,
+    ) -> Result<Self, HoprError> {
         let health_surb =
             surb_config_for(&options.surb_balancing.health_check).map_err(|e| HoprError::Session(e.to_string()))?;
         let cfg = HoprSessionClientConfig {
@@ -920,6 +1025,7 @@ impl HealthSession {
         Ok(Self {
             hopr,
             meta,
+            tasks,
             closed: false,
         })
     }
@@ -936,13 +1042,13 @@ impl Drop for HealthSession {
         if self.closed {
             return;
         }
-        tracing::debug!("health session dropped without explicit close, spawning detached close task");
-        // Explicit `close()` never ran — detach a close task so the exit
-        // port is not leaked. Fire and forget; errors are logged inside
+        tracing::debug!("health session dropped without explicit close, spawning close task");
+        // Explicit `close()` never ran — spawn a close task so the exit
+        // port is not leaked. Nothing waits for it; errors are logged inside
         // `close_health_session`.
         let hopr = self.hopr.clone();
         let meta = self.meta.clone();
-        tokio::spawn(async move {
+        self.tasks.spawn(Subsystem::Health, async move {
             close_health_session(&hopr, &meta).await;
         });
     }
@@ -1200,11 +1306,46 @@ mod tests {
             HopRouting::try_from(1).unwrap(),
             Default::default(),
         );
-        let mut rh = RouteHealth::new(&dest, false, false, CancellationToken::new());
+        let mut rh = RouteHealth::new(
+            &dest,
+            false,
+            false,
+            CancellationToken::new(),
+            Arc::new(Semaphore::new(MAX_PARALLEL_HEALTH_CHECKS)),
+            Tasks::new(),
+        );
         rh.exit_failures = failures;
         rh.failure_backoff()
     }
 
+    #[test]
+    fn connection_failures_are_only_recent_for_a_while() {
+        use crate::connection::destination::{Destination, HopRouting};
+        use tokio_util::sync::CancellationToken;
+        let dest = Destination::new(
+            "test".to_string(),
+            addr(1),
+            HopRouting::try_from(1).unwrap(),
+            Default::default(),
+        );
+        let mut rh = RouteHealth::new(
+            &dest,
+            false,
+            false,
+            CancellationToken::new(),
+            Arc::new(Semaphore::new(MAX_PARALLEL_HEALTH_CHECKS)),
+            Tasks::new(),
+        );
+        let now = SystemTime::now();
+        assert!(!rh.failed_recently(now));
+        rh.with_error("setback".to_string());
+        assert!(!rh.failed_recently(now));
+        rh.connection_failed("session failed".to_string(), now);
+        assert!(rh.failed_recently(now + Duration::from_mins(1)));
+        assert!(!rh.failed_recently(now + RECENT_CONNECTION_FAILURE));
+        assert_eq!(rh.last_error(), Some("session failed"));
+    }
+
     #[test]
     fn failure_backoff_uses_warmup_interval_for_first_failures() {
         for n in 1..=GRAPH_WARMUP_RETRY_COUNT {
@@ -1245,7 +1386,13 @@ mod tests {
     #[test]
     fn select_api_version_finds_v1() {
         let versions = vec!["v1".to_string()];
-        assert_eq!(select_api_version(&versions), Some("v1"));
+        assert_eq!(select_api_version(&versions), Some(ApiVersion::V1));
+    }
+
+    #[test]
+    fn select_api_version_prefers_v2() {
+        let versions = vec!["v1".to_string(), "v2".to_string()];
+        assert_eq!(select_api_version(&versions), Some(ApiVersion::V2));
     }
 
     #[test]
@@ -1255,7 +1402,7 @@ mod tests {
 
     #[test]
     fn select_api_version_returns_none_when_no_match() {
-        let versions = vec!["v2".to_string(), "v99".to_string()];
+        let versions = vec!["v0".to_string(), "v99".to_string()];
         assert_eq!(select_api_version(&versions), None);
     }
 
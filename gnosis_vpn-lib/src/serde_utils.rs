@@ -16,6 +16,21 @@ pub mod address {
     }
 }
 
+/// Destinations in responses, credentials only travel to the worker within its configuration.
+pub mod redacted_destination {
+    use super::*;
+    use crate::connection::destination::Destination;
+    use serde::Serialize;
+
+    pub fn serialize<S: Serializer>(dest: &Destination, s: S) -> Result<S::Ok, S::Error> {
+        dest.redacted().serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Destination, D::Error> {
+        Destination::deserialize(d)
+    }
+}
+
 pub mod balance {
     use super::*;
 
@@ -104,3 +119,20 @@ pub mod opt_duration_ms {
         .transpose()
     }
 }
+
+pub mod opt_balance {
+    use super::*;
+
+    pub fn serialize<C: Currency, S: Serializer>(bal: &Option<Balance<C>>, s: S) -> Result<S::Ok, S::Error> {
+        match bal {
+            None => s.serialize_none(),
+            Some(b) => s.serialize_some(&b.to_string()),
+        }
+    }
+
+    pub fn deserialize<'de, C: Currency, D: Deserializer<'de>>(d: D) -> Result<Option<Balance<C>>, D::Error> {
+        let s = Option::<String>::deserialize(d)?;
+        s.map(|s| s.parse::<Balance<C>>().map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
@@ -1,94 +1,288 @@
 use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio::process::Command;
+use tokio::time;
 
 use std::future::Future;
 use std::io;
-use std::process::Output;
+use std::process::{ExitStatus, Output, Stdio};
+use std::time::Duration;
+
+/// Commands not finished after this are killed, a hung helper must never stall the caller.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Bytes kept per output stream, anything beyond is drained and dropped.
+pub const MAX_CAPTURE: usize = 64 * 1024;
 
 #[derive(Debug, Error)]
 pub enum Error {
-    #[error("Command execution failed")]
-    CommandFailed,
+    #[error("Command exited with code {0}")]
+    ExitCode(i32),
+    #[error("Command terminated by signal")]
+    Signaled,
+    #[error("Command timed out after {0:?}")]
+    TimedOut(Duration),
     #[error("IO error: {0}")]
     IO(#[from] io::Error),
 }
 
+impl Error {
+    /// Failures worth another attempt, a missing binary will not appear by retrying.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Error::ExitCode(_) | Error::Signaled | Error::TimedOut(_))
+    }
+}
+
 /// log errors and warnings or suppress them
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub enum Logs {
     Print,
     Suppress,
 }
 
+/// Retry transient failures up to `attempts` runs in total, waiting `delay` in between.
+#[derive(Clone, Copy, Debug)]
+pub struct Retry {
+    pub attempts: u32,
+    pub delay: Duration,
+}
+
+impl Retry {
+    pub const NEVER: Retry = Retry {
+        attempts: 1,
+        delay: Duration::ZERO,
+    };
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RunOptions {
+    pub logs: Logs,
+    pub timeout: Duration,
+    pub retry: Retry,
+}
+
+impl RunOptions {
+    pub const fn new(logs: Logs) -> Self {
+        RunOptions {
+            logs,
+            timeout: DEFAULT_TIMEOUT,
+            retry: Retry::NEVER,
+        }
+    }
+
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub const fn retry(mut self, attempts: u32, delay: Duration) -> Self {
+        self.retry = Retry { attempts, delay };
+        self
+    }
+}
+
+impl From<Logs> for RunOptions {
+    fn from(logs: Logs) -> Self {
+        RunOptions::new(logs)
+    }
+}
+
 pub trait ShellCommandExt {
-    fn run(&mut self, logs: Logs) -> impl Future<Output = Result<(), Error>> + Send;
-    fn run_stdout(&mut self, logs: Logs) -> impl Future<Output = Result<String, Error>> + Send;
+    fn run(&mut self, opts: impl Into<RunOptions> + Send) -> impl Future<Output = Result<(), Error>> + Send;
+    fn run_stdout(&mut self, opts: impl Into<RunOptions> + Send) -> impl Future<Output = Result<String, Error>> + Send;
+    fn run_into(
+        &mut self,
+        out: std::fs::File,
+        opts: impl Into<RunOptions> + Send,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
     fn spawn_no_capture(&mut self) -> impl Future<Output = Result<(), Error>> + Send;
 }
 
 impl ShellCommandExt for Command {
     /// Run the command and print stderr with a warning on success.
-    /// Unconditionally captures stdout and stderr regardless of command settings.
-    /// See tokio's output behaviour: <https://docs.rs/tokio/latest/tokio/process/struct.Command.html#method.output>
-    async fn run(&mut self, logs: Logs) -> Result<(), Error> {
-        let output = self.output().await?;
-        let stderrempty = output.stderr.is_empty();
-        match (stderrempty, output.status) {
-            (true, status) if status.success() => Ok(()),
-            (false, status) if status.success() => {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                if matches!(logs, Logs::Print) {
-                    tracing::warn!(%stderr, cmd = ?self, "Non empty stderr on successful command");
-                }
-                Ok(())
-            }
-            (_, status) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                if matches!(logs, Logs::Print) {
-                    tracing::error!(status_code = ?status.code(), %stdout, %stderr, cmd = ?self, "Error executing command");
+    /// Unconditionally captures stdout and stderr regardless of command settings, bounded to [`MAX_CAPTURE`].
+    async fn run(&mut self, opts: impl Into<RunOptions> + Send) -> Result<(), Error> {
+        execute(self, opts.into()).await.map(|_| ())
+    }
+
+    async fn run_stdout(&mut self, opts: impl Into<RunOptions> + Send) -> Result<String, Error> {
+        let output = execute(self, opts.into()).await?;
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Run the command once with stdout written to `out`, a partially written file is not worth retrying.
+    async fn run_into(&mut self, out: std::fs::File, opts: impl Into<RunOptions> + Send) -> Result<(), Error> {
+        let opts = opts.into();
+        match capture(self, Stdio::from(out), opts.timeout).await {
+            Ok(output) => check_output(format!("{self:?}"), output, opts.logs).map(|_| ()),
+            Err(error) => {
+                if matches!(error, Error::TimedOut(_)) && matches!(opts.logs, Logs::Print) {
+                    tracing::error!(%error, cmd = ?self, "Error executing command");
                 }
-                Err(Error::CommandFailed)
+                Err(error)
             }
         }
     }
 
-    async fn run_stdout(&mut self, logs: Logs) -> Result<String, Error> {
-        let output = self.output().await?;
-        let cmd_debug = format!("{:?}", self);
-        stdout_from_output(cmd_debug, output, logs)
-    }
-
     async fn spawn_no_capture(&mut self) -> Result<(), Error> {
-        let mut cmd = self
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
+        let mut child = self
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
             .spawn()?;
-        match cmd.wait().await {
-            Ok(status) if status.success() => Ok(()),
-            _ => Err(Error::CommandFailed),
-        }
+        let status = match time::timeout(DEFAULT_TIMEOUT, child.wait()).await {
+            Ok(status) => status?,
+            Err(_) => {
+                let _ = child.kill().await;
+                return Err(Error::TimedOut(DEFAULT_TIMEOUT));
+            }
+        };
+        exit_result(status)
     }
 }
 
 pub fn stdout_from_output(cmd: String, output: Output, logs: Logs) -> Result<String, Error> {
-    let stderrempty = output.stderr.is_empty();
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    match (stderrempty, output.status) {
-        (true, status) if status.success() => Ok(stdout.trim().to_string()),
-        (false, status) if status.success() => {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            if matches!(logs, Logs::Print) {
+    let output = check_output(cmd, output, logs)?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+async fn execute(cmd: &mut Command, opts: RunOptions) -> Result<Output, Error> {
+    let mut attempt = 1;
+    loop {
+        let last = attempt >= opts.retry.attempts;
+        // only the final attempt reports, earlier failures are expected to be retried
+        let logs = if last { opts.logs } else { Logs::Suppress };
+        let result = match capture(cmd, Stdio::piped(), opts.timeout).await {
+            Ok(output) => check_output(format!("{cmd:?}"), output, logs),
+            Err(error) => {
+                if matches!(error, Error::TimedOut(_)) && matches!(logs, Logs::Print) {
+                    tracing::error!(%error, ?cmd, "Error executing command");
+                }
+                Err(error)
+            }
+        };
+        match result {
+            Err(error) if !last && error.is_transient() => {
+                tracing::debug!(%error, attempt, ?cmd, "Retrying command");
+                time::sleep(opts.retry.delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Spawn the command and collect its bounded output, killing it once `timeout` elapsed.
+/// Stdout is only collected when `stdout` is a pipe.
+async fn capture(cmd: &mut Command, stdout: Stdio, timeout: Duration) -> Result<Output, Error> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(stdout)
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let collect = async {
+        let (stdout, stderr, status) = tokio::try_join!(read_bounded(stdout), read_bounded(stderr), child.wait())?;
+        Ok::<_, io::Error>(Output { status, stdout, stderr })
+    };
+    match time::timeout(timeout, collect).await {
+        Ok(output) => Ok(output?),
+        Err(_) => {
+            let _ = child.kill().await;
+            Err(Error::TimedOut(timeout))
+        }
+    }
+}
+
+async fn read_bounded<R: AsyncRead + Unpin>(reader: Option<R>) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    let Some(mut reader) = reader else {
+        return Ok(buf);
+    };
+    (&mut reader).take(MAX_CAPTURE as u64).read_to_end(&mut buf).await?;
+    // keep draining so the command never blocks on a full pipe
+    tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+    Ok(buf)
+}
+
+fn check_output(cmd: String, output: Output, logs: Logs) -> Result<Output, Error> {
+    match exit_result(output.status) {
+        Ok(()) => {
+            if !output.stderr.is_empty() && matches!(logs, Logs::Print) {
+                let stderr = String::from_utf8_lossy(&output.stderr);
                 tracing::warn!(%stderr, cmd, "Non empty stderr on successful command");
             }
-            Ok(stdout.trim().to_string())
+            Ok(output)
         }
-        (_, status) => {
-            let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(error) => {
             if matches!(logs, Logs::Print) {
-                tracing::error!(status_code = ?status.code(), %stdout, %stderr, cmd, "Error executing command");
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                tracing::error!(status_code = ?output.status.code(), %stdout, %stderr, cmd, "Error executing command");
             }
-            Err(Error::CommandFailed)
+            Err(error)
         }
     }
 }
+
+fn exit_result(status: ExitStatus) -> Result<(), Error> {
+    match status.code() {
+        _ if status.success() => Ok(()),
+        Some(code) => Err(Error::ExitCode(code)),
+        None => Err(Error::Signaled),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn hung_command_times_out() {
+        let res = Command::new("sleep")
+            .arg("5")
+            .run(RunOptions::new(Logs::Suppress).timeout(Duration::from_millis(100)))
+            .await;
+        assert!(matches!(res, Err(Error::TimedOut(_))));
+    }
+
+    #[tokio::test]
+    async fn output_is_bounded() -> anyhow::Result<()> {
+        let out = Command::new("sh")
+            .args(["-c", "head -c 200000 /dev/zero | tr '\\0' a"])
+            .run_stdout(Logs::Suppress)
+            .await?;
+        assert_eq!(out.len(), MAX_CAPTURE);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn exit_code_is_classified_and_retried() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let marker = dir.path().join("attempts");
+        let script = format!("echo x >> {0}; [ $(wc -l < {0}) -ge 3 ] || exit 7", marker.display());
+
+        let res = Command::new("sh").args(["-c", &script]).run(Logs::Suppress).await;
+        assert!(matches!(res, Err(Error::ExitCode(7))));
+
+        std::fs::remove_file(&marker)?;
+        Command::new("sh")
+            .args(["-c", &script])
+            .run(RunOptions::new(Logs::Suppress).retry(3, Duration::ZERO))
+            .await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stdout_goes_into_the_file() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("out");
+        Command::new("echo")
+            .arg("streamed")
+            .run_into(std::fs::File::create(&path)?, Logs::Suppress)
+            .await?;
+        assert_eq!(std::fs::read_to_string(&path)?, "streamed\n");
+        Ok(())
+    }
+}
@@ -1,2 +1,3 @@
+pub mod remote;
 pub mod root;
 pub mod worker;
@@ -0,0 +1,502 @@
+/// Module for controlling the Gnosis VPN root service from another machine over TLS.
+///
+/// The service presents a self-signed certificate. A client pairs once: it asks for pairing,
+/// the service logs a short code together with its certificate fingerprint and the admin
+/// enters that code on the client. The client then pins the fingerprint and authenticates
+/// every command with the token handed out on pairing.
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::io::{AsyncBufRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use tokio_rustls::rustls::crypto::{self, CryptoProvider};
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{self, ClientConfig, DigitallySignedStruct, ServerConfig, SignatureScheme};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::command::{Command, Response};
+use crate::socket::root::{self, Limits};
+use crate::{serde_utils, tls};
+
+pub const ENV_VAR: &str = "GNOSISVPN_REMOTE";
+pub const DEFAULT_PORT: u16 = 7475;
+/// Directory in the state home holding the service identity and the paired clients.
+pub const DIR_NAME: &str = "remote";
+pub const CERT_FILE: &str = "remote_cert.der";
+pub const KEY_FILE: &str = "remote_key.der";
+pub const CLIENTS_FILE: &str = "remote_clients.json";
+/// Wrong codes tolerated per pairing attempt.
+pub const MAX_CODE_ATTEMPTS: u8 = 3;
+
+/// Name the certificate is issued for, clients pin the fingerprint instead of checking it.
+const SERVER_NAME: &str = "gnosisvpn";
+
+/// Pairing waits for a human typing the code.
+pub const PAIRING_LIMITS: Limits = Limits {
+    max_size: 1024,
+    idle_timeout: Duration::from_secs(120),
+    read_timeout: Duration::from_secs(5),
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Request {
+    /// Start pairing, the service logs a code for `name`
+    Pair {
+        name: String,
+    },
+    Confirm {
+        code: String,
+    },
+    Command {
+        token: String,
+        command: Command,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Reply {
+    PairingStarted,
+    Paired { token: String },
+    Response(Response),
+    Denied(String),
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("IO error: {0}")]
+    IO(#[from] io::Error),
+    #[error("TLS error: {0}")]
+    Tls(#[from] rustls::Error),
+    #[error("certificate generation failed: {0}")]
+    Certificate(#[from] rcgen::Error),
+    #[error(transparent)]
+    Message(#[from] root::Error),
+    #[error("failed serializing message: {0}")]
+    Serialization(serde_json::Error),
+    #[error("failed deserializing message: {0}")]
+    Deserialization(serde_json::Error),
+    #[error("service closed the connection")]
+    Closed,
+    #[error("unexpected reply from service")]
+    UnexpectedReply,
+    #[error("denied by service: {0}")]
+    Denied(String),
+    #[error("not paired with {0} - run `gnosis_vpn-ctl --remote {0} pair` first")]
+    NotPaired(String),
+    #[error("unreadable credentials file {} - fix or remove it: {1}", .0.display())]
+    CorruptCredentials(PathBuf, serde_json::Error),
+}
+
+/// Certificate and key the service presents to remote clients.
+pub struct Identity {
+    cert: Vec<u8>,
+    key: Vec<u8>,
+}
+
+impl Identity {
+    /// Load the identity from `dir` or create and persist a new one.
+    pub fn load_or_create(dir: &Path) -> Result<Self, Error> {
+        let cert_path = dir.join(CERT_FILE);
+        let key_path = dir.join(KEY_FILE);
+        if let (Ok(cert), Ok(key)) = (fs::read(&cert_path), fs::read(&key_path)) {
+            return Ok(Self { cert, key });
+        }
+        let certified = rcgen::generate_simple_self_signed(vec![SERVER_NAME.to_string()])?;
+        let identity = Self {
+            cert: certified.cert.der().to_vec(),
+            key: certified.key_pair.serialize_der(),
+        };
+        fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+        write_private(&key_path, &identity.key)?;
+        fs::write(&cert_path, &identity.cert)?;
+        Ok(identity)
+    }
+
+    pub fn fingerprint(&self) -> String {
+        tls::fingerprint(&self.cert)
+    }
+
+    pub fn acceptor(&self) -> Result<TlsAcceptor, Error> {
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(self.key.clone()));
+        let config = ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(vec![CertificateDer::from(self.cert.clone())], key)?;
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(crypto::ring::default_provider())
+}
+
+fn write_private(path: &Path, content: &[u8]) -> Result<(), io::Error> {
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    io::Write::write_all(&mut file, content)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+}
+
+/// Random six digit pairing code.
+pub fn pairing_code() -> String {
+    format!("{:06}", rand::rng().random_range(0..1_000_000))
+}
+
+fn new_token() -> String {
+    let bytes: [u8; 32] = rand::rng().random();
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// A client paired with the service, only the token hash is kept.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PairedClient {
+    pub name: String,
+    pub token_hash: String,
+    #[serde(with = "serde_utils::system_time")]
+    pub paired_at: SystemTime,
+}
+
+/// Clients allowed to send commands, persisted next to the service identity.
+pub struct PairedClients {
+    path: PathBuf,
+    clients: Vec<PairedClient>,
+}
+
+impl PairedClients {
+    pub fn load(path: PathBuf) -> Self {
+        let clients = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|error| {
+                tracing::warn!(?error, ?path, "discarding unreadable paired clients file");
+                Vec::new()
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => {
+                tracing::warn!(?error, ?path, "unable to read paired clients file");
+                Vec::new()
+            }
+        };
+        Self { path, clients }
+    }
+
+    /// Pair `name` and return its new token.
+    pub fn pair(&mut self, name: String) -> String {
+        let token = new_token();
+        self.clients.push(PairedClient {
+            name,
+            token_hash: hash_token(&token),
+            paired_at: SystemTime::now(),
+        });
+        self.store();
+        token
+    }
+
+    /// Revoke every client paired as `name` and return how many were removed.
+    pub fn unpair(&mut self, name: &str) -> usize {
+        let before = self.clients.len();
+        self.clients.retain(|c| c.name != name);
+        let removed = before - self.clients.len();
+        if removed > 0 {
+            self.store();
+        }
+        removed
+    }
+
+    fn store(&self) {
+        let res = serde_json::to_string(&self.clients)
+            .map_err(io::Error::other)
+            .and_then(|content| write_private(&self.path, content.as_bytes()));
+        if let Err(error) = res {
+            tracing::warn!(?error, path = ?self.path, "failed to persist paired clients");
+        }
+    }
+
+    /// Name of the client owning `token`.
+    pub fn verify(&self, token: &str) -> Option<&str> {
+        let hash = hash_token(token);
+        self.clients
+            .iter()
+            .find(|c| c.token_hash == hash)
+            .map(|c| c.name.as_str())
+    }
+}
+
+/// What a client needs to reach a paired service.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Credentials {
+    pub fingerprint: String,
+    pub token: String,
+}
+
+/// Client side credentials per service address, e.g. `~/.config/gnosisvpn/remote.json`.
+pub struct CredentialStore {
+    path: PathBuf,
+    entries: HashMap<String, Credentials>,
+}
+
+impl CredentialStore {
+    pub fn default_path() -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(base.join("gnosisvpn").join("remote.json"))
+    }
+
+    /// Load the stored credentials, a missing file is empty. An unreadable one is an error
+    /// rather than empty, storing new credentials would overwrite every other pairing.
+    pub fn load(path: PathBuf) -> Result<Self, Error> {
+        let entries = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| Error::CorruptCredentials(path.clone(), e))?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => return Err(error.into()),
+        };
+        Ok(Self { path, entries })
+    }
+
+    pub fn get(&self, addr: &str) -> Option<&Credentials> {
+        self.entries.get(addr)
+    }
+
+    pub fn insert(&mut self, addr: String, credentials: Credentials) -> Result<(), io::Error> {
+        self.entries.insert(addr, credentials);
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string_pretty(&self.entries).map_err(io::Error::other)?;
+        write_private(&self.path, content.as_bytes())
+    }
+}
+
+/// Accepts exactly the pinned certificate, or records the presented one while pairing.
+#[derive(Debug)]
+struct PinnedCert {
+    expected: Option<String>,
+    seen: Arc<Mutex<Option<String>>>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedCert {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let presented = tls::fingerprint(end_entity);
+        if let Ok(mut seen) = self.seen.lock() {
+            *seen = Some(presented.clone());
+        }
+        match &self.expected {
+            Some(expected) if *expected != presented => Err(rustls::Error::InvalidCertificate(
+                rustls::CertificateError::ApplicationVerificationFailure,
+            )),
+            _ => Ok(ServerCertVerified::assertion()),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+type ClientStream = BufReader<tokio_rustls::client::TlsStream<TcpStream>>;
+
+async fn connect(addr: &str, expected: Option<String>) -> Result<(ClientStream, Option<String>), Error> {
+    let provider = provider();
+    let seen = Arc::new(Mutex::new(None));
+    let verifier = PinnedCert {
+        expected,
+        seen: seen.clone(),
+        provider: provider.clone(),
+    };
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    let tcp = TcpStream::connect(addr).await?;
+    let server_name = ServerName::try_from(SERVER_NAME).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let tls = TlsConnector::from(Arc::new(config)).connect(server_name, tcp).await?;
+    let seen = seen.lock().ok().and_then(|s| s.clone());
+    Ok((BufReader::new(tls), seen))
+}
+
+/// Write one newline terminated message.
+pub async fn send<W: AsyncWrite + Unpin, T: Serialize>(writer: &mut W, msg: &T) -> Result<(), Error> {
+    let mut json = serde_json::to_string(msg).map_err(Error::Serialization)?;
+    json.push('\n');
+    writer.write_all(json.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Read one message, `None` if the peer closed the connection.
+pub async fn receive<R: AsyncBufRead + Unpin, T: for<'de> Deserialize<'de>>(
+    reader: &mut R,
+    limits: Limits,
+) -> Result<Option<T>, Error> {
+    let line = root::read_message(reader, limits).await?;
+    if line.is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str(&line).map(Some).map_err(Error::Deserialization)
+}
+
+/// Pair with the service at `addr`, `read_code` is asked for the code shown by the service
+/// together with the fingerprint it has to match and, on a retry, why the last code was denied.
+pub async fn pair(
+    addr: &str,
+    name: &str,
+    mut read_code: impl FnMut(&str, Option<&str>) -> Result<String, io::Error>,
+) -> Result<Credentials, Error> {
+    let (mut stream, fingerprint) = connect(addr, None).await?;
+    let fingerprint = fingerprint.ok_or(Error::UnexpectedReply)?;
+    send(&mut stream, &Request::Pair { name: name.to_string() }).await?;
+    match receive(&mut stream, Limits::RESPONSE).await? {
+        Some(Reply::PairingStarted) => (),
+        Some(Reply::Denied(reason)) => return Err(Error::Denied(reason)),
+        Some(_) => return Err(Error::UnexpectedReply),
+        None => return Err(Error::Closed),
+    }
+    let mut last_denial = None;
+    for _ in 0..MAX_CODE_ATTEMPTS {
+        let code = read_code(&fingerprint, last_denial.as_deref())?;
+        send(
+            &mut stream,
+            &Request::Confirm {
+                code: code.trim().to_string(),
+            },
+        )
+        .await?;
+        match receive(&mut stream, PAIRING_LIMITS).await? {
+            Some(Reply::Paired { token }) => return Ok(Credentials { fingerprint, token }),
+            Some(Reply::Denied(reason)) => last_denial = Some(reason),
+            Some(_) => return Err(Error::UnexpectedReply),
+            None => return Err(Error::Closed),
+        }
+    }
+    Err(Error::Denied(last_denial.unwrap_or_default()))
+}
+
+pub async fn process_cmd(addr: &str, credentials: &Credentials, cmd: &Command) -> Result<Response, Error> {
+    let (mut stream, _) = connect(addr, Some(credentials.fingerprint.clone())).await?;
+    let request = Request::Command {
+        token: credentials.token.clone(),
+        command: cmd.clone(),
+    };
+    send(&mut stream, &request).await?;
+    match receive(&mut stream, Limits::RESPONSE).await? {
+        Some(Reply::Response(resp)) => Ok(resp),
+        Some(Reply::Denied(reason)) => Err(Error::Denied(reason)),
+        Some(_) => Err(Error::UnexpectedReply),
+        None => Err(Error::Closed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::tempdir;
+
+    #[test]
+    fn paired_tokens_survive_reload() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(CLIENTS_FILE);
+
+        let mut clients = PairedClients::load(path.clone());
+        let token = clients.pair("laptop".to_string());
+
+        let reloaded = PairedClients::load(path);
+        assert_eq!(reloaded.verify(&token), Some("laptop"));
+        assert_eq!(reloaded.verify("not-a-token"), None);
+        assert!(!reloaded.clients[0].token_hash.contains(&token));
+    }
+
+    #[test]
+    fn unpaired_tokens_are_revoked_after_reload() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(CLIENTS_FILE);
+
+        let mut clients = PairedClients::load(path.clone());
+        let laptop = clients.pair("laptop".to_string());
+        let phone = clients.pair("phone".to_string());
+        assert_eq!(clients.unpair("laptop"), 1);
+        assert_eq!(clients.unpair("laptop"), 0);
+
+        let reloaded = PairedClients::load(path);
+        assert_eq!(reloaded.verify(&laptop), None);
+        assert_eq!(reloaded.verify(&phone), Some("phone"));
+    }
+
+    #[test]
+    fn corrupt_credentials_are_not_overwritten() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("remote.json");
+        fs::write(&path, "{ not json").unwrap();
+
+        assert!(matches!(
+            CredentialStore::load(path.clone()),
+            Err(Error::CorruptCredentials(..))
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{ not json");
+
+        let mut store = CredentialStore::load(dir.path().join("missing.json")).unwrap();
+        let credentials = Credentials {
+            fingerprint: "AB:CD".to_string(),
+            token: "token".to_string(),
+        };
+        store.insert("host:7475".to_string(), credentials.clone()).unwrap();
+        let reloaded = CredentialStore::load(dir.path().join("missing.json")).unwrap();
+        assert_eq!(reloaded.get("host:7475"), Some(&credentials));
+    }
+
+    #[test]
+    fn pairing_code_has_six_digits() {
+        for _ in 0..100 {
+            let code = pairing_code();
+            assert_eq!(code.len(), 6);
+            assert!(code.chars().all(|c| c.is_ascii_digit()));
+        }
+    }
+}
@@ -1,32 +1,112 @@
 /// Module for communicating with the Gnosis VPN root service over a Unix domain socket.
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::UnixStream;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::time::{self, Instant};
 
 use std::io;
-use std::path::Path;
+use std::net::SocketAddr;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use crate::command::{Command, Response};
+use crate::diagnostics;
+use crate::worker;
 
 pub const DEFAULT_PATH: &str = "/var/run/gnosisvpn.sock";
 pub const ENV_VAR: &str = "GNOSISVPN_SOCKET_PATH";
+pub const DEFAULT_MODE: u32 = 0o660;
+/// Leading character of a Linux abstract namespace socket, e.g. `@gnosisvpn`.
+pub const ABSTRACT_PREFIX: char = '@';
+
+/// Location, ownership and permissions of the socket file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    /// Overrides [`DEFAULT_PATH`], command line and environment take precedence
+    pub path: Option<PathBuf>,
+    /// Group owning the socket, members may talk to the service
+    pub group: String,
+    pub mode: u32,
+    /// Address of the optional TLS listener for paired remote clients, see [`super::remote`]
+    pub remote_listen: Option<SocketAddr>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            path: None,
+            group: worker::GROUPNAME.to_string(),
+            mode: DEFAULT_MODE,
+            remote_listen: None,
+        }
+    }
+}
+
+/// Bounds for reading a single message from the socket.
+///
+/// `idle_timeout` caps the wait for the first byte, `read_timeout` the gap between later reads.
+/// The whole message must arrive within `idle_timeout + read_timeout` so a peer trickling
+/// bytes cannot hold the connection open forever.
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    pub max_size: usize,
+    pub idle_timeout: Duration,
+    pub read_timeout: Duration,
+}
+
+impl Limits {
+    /// Commands sent by clients to the root service.
+    pub const REQUEST: Limits = Limits {
+        max_size: 64 * 1024,
+        idle_timeout: Duration::from_secs(10),
+        read_timeout: Duration::from_secs(5),
+    };
+
+    /// Responses read by clients, the service might need a while to answer.
+    pub const RESPONSE: Limits = Limits {
+        max_size: 16 * 1024 * 1024,
+        idle_timeout: Duration::from_secs(120),
+        read_timeout: Duration::from_secs(10),
+    };
+}
 
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("service not running")]
     ServiceNotRunning,
+    #[error(
+        "permission denied - the service socket is restricted to the {group} group since it is no longer world writable, add your user with `sudo usermod -aG {group} $USER` and log in again",
+        group = worker::GROUPNAME
+    )]
+    PermissionDenied,
     #[error("failed serializing command: {0}")]
     Serialization(serde_json::Error),
     #[error("failed deserializing response: {0}")]
     Deserialization(serde_json::Error),
+    #[error("message exceeds {0} bytes")]
+    MessageTooLarge(usize),
+    #[error("timed out reading message")]
+    Timeout,
     #[error("IO error: {0}")]
     IO(#[from] io::Error),
 }
 
-pub async fn process_cmd(socket_path: &Path, cmd: &Command) -> Result<Response, Error> {
-    check_path(socket_path)?;
+impl Error {
+    /// Nothing listens on the socket, e.g. while the service restarts after an upgrade.
+    pub fn is_not_running(&self) -> bool {
+        match self {
+            Error::ServiceNotRunning => true,
+            // a socket file left behind by the stopped service
+            Error::IO(err) => matches!(err.kind(), io::ErrorKind::ConnectionRefused | io::ErrorKind::NotFound),
+            _ => false,
+        }
+    }
+}
 
-    let mut stream = UnixStream::connect(socket_path).await?;
+pub async fn process_cmd(socket_path: &Path, cmd: &Command) -> Result<Response, Error> {
+    let mut stream = connect(socket_path).await?;
 
     let json_cmd = serde_json::to_string(cmd).map_err(Error::Serialization)?;
     push_command(&mut stream, &json_cmd).await?;
@@ -34,6 +114,98 @@ pub async fn process_cmd(socket_path: &Path, cmd: &Command) -> Result<Response,
     serde_json::from_str::<Response>(&str_resp).map_err(Error::Deserialization)
 }
 
+/// Like [`process_cmd`] but hands every response to `on_response` until the service closes the connection.
+///
+/// Used for streamed answers such as `Command::Logs { follow: true, .. }`.
+pub async fn process_stream<F: FnMut(Response)>(
+    socket_path: &Path,
+    cmd: &Command,
+    mut on_response: F,
+) -> Result<(), Error> {
+    let mut stream = connect(socket_path).await?;
+
+    let json_cmd = serde_json::to_string(cmd).map_err(Error::Serialization)?;
+    push_command(&mut stream, &json_cmd).await?;
+    let mut reader = BufReader::new(stream);
+    loop {
+        let str_resp = read_message(&mut reader, Limits::RESPONSE).await?;
+        if str_resp.is_empty() {
+            return Ok(());
+        }
+        on_response(serde_json::from_str::<Response>(&str_resp).map_err(Error::Deserialization)?);
+    }
+}
+
+/// Socket path precedence shared by service and clients: explicit argument or environment,
+/// then configuration, then [`DEFAULT_PATH`].
+pub fn resolve_path(explicit: Option<PathBuf>, configured: Option<PathBuf>) -> PathBuf {
+    explicit.or(configured).unwrap_or_else(|| PathBuf::from(DEFAULT_PATH))
+}
+
+/// Name of the abstract namespace socket if `socket_path` denotes one.
+pub fn abstract_name(socket_path: &Path) -> Option<&str> {
+    socket_path.to_str().and_then(|s| s.strip_prefix(ABSTRACT_PREFIX))
+}
+
+pub fn bind(socket_path: &Path) -> Result<UnixListener, io::Error> {
+    match abstract_name(socket_path) {
+        Some(name) => bind_abstract(name),
+        None => UnixListener::bind(socket_path),
+    }
+}
+
+async fn connect(socket_path: &Path) -> Result<UnixStream, Error> {
+    let res = match abstract_name(socket_path) {
+        Some(name) => connect_abstract(name),
+        None => {
+            check_path(socket_path)?;
+            UnixStream::connect(socket_path).await
+        }
+    };
+    res.map_err(|err| match err.kind() {
+        io::ErrorKind::PermissionDenied => Error::PermissionDenied,
+        // abstract sockets vanish with the service, there is no file to check beforehand
+        io::ErrorKind::ConnectionRefused if abstract_name(socket_path).is_some() => Error::ServiceNotRunning,
+        _ => Error::IO(err),
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn bind_abstract(name: &str) -> Result<UnixListener, io::Error> {
+    use std::os::linux::net::SocketAddrExt;
+    let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+    let listener = std::os::unix::net::UnixListener::bind_addr(&addr)?;
+    listener.set_nonblocking(true)?;
+    UnixListener::from_std(listener)
+}
+
+#[cfg(target_os = "linux")]
+fn connect_abstract(name: &str) -> Result<UnixStream, io::Error> {
+    use std::os::linux::net::SocketAddrExt;
+    let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+    let stream = std::os::unix::net::UnixStream::connect_addr(&addr)?;
+    stream.set_nonblocking(true)?;
+    UnixStream::from_std(stream)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn bind_abstract(_name: &str) -> Result<UnixListener, io::Error> {
+    Err(abstract_unsupported())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn connect_abstract(_name: &str) -> Result<UnixStream, io::Error> {
+    Err(abstract_unsupported())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn abstract_unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "abstract sockets are only available on Linux",
+    )
+}
+
 fn check_path(socket_path: &Path) -> Result<(), Error> {
     match socket_path.try_exists() {
         Ok(true) => Ok(()),
@@ -42,6 +214,57 @@ fn check_path(socket_path: &Path) -> Result<(), Error> {
     }
 }
 
+/// Hand the socket file to the configured group and apply its mode.
+///
+/// A missing group only logs a warning so the service stays reachable for root.
+pub fn restrict(socket_path: &Path, config: &Config) -> Result<(), io::Error> {
+    match uzers::get_group_by_name(&config.group) {
+        Some(group) => std::os::unix::fs::chown(socket_path, None, Some(group.gid()))?,
+        None => tracing::warn!(group = %config.group, "socket group not found - only root can access the socket"),
+    }
+    std::fs::set_permissions(socket_path, std::fs::Permissions::from_mode(config.mode))
+}
+
+/// Whether the socket still carries the group and mode [`restrict`] applied.
+pub fn check_permissions(socket_path: &Path, config: &Config) -> diagnostics::Check {
+    const NAME: &str = "control socket";
+    if let Some(name) = abstract_name(socket_path) {
+        return diagnostics::Check::warn(
+            NAME,
+            format!(
+                "abstract socket {name} ignores group and mode - every process in this network namespace can control the service"
+            ),
+        );
+    }
+    let metadata = match std::fs::metadata(socket_path) {
+        Ok(metadata) => metadata,
+        Err(e) => return diagnostics::Check::fail(NAME, format!("{}: {e}", socket_path.display())),
+    };
+    let mode = metadata.permissions().mode() & 0o777;
+    if mode != config.mode {
+        return diagnostics::Check::warn(
+            NAME,
+            format!("mode is {mode:o} instead of the configured {:o}", config.mode),
+        );
+    }
+    match uzers::get_group_by_name(&config.group) {
+        Some(group) if group.gid() == metadata.gid() => {
+            diagnostics::Check::pass(NAME, format!("owned by group {} with mode {mode:o}", config.group))
+        }
+        Some(_) => diagnostics::Check::warn(
+            NAME,
+            format!(
+                "not owned by group {} - its members cannot control the service",
+                config.group
+            ),
+        ),
+        None => diagnostics::Check::warn(
+            NAME,
+            format!("group {} not found - only root can access the socket", config.group),
+        ),
+    }
+}
+
 async fn push_command(socket: &mut UnixStream, json_cmd: &str) -> Result<(), Error> {
     // flush is not enough to push the command
     // we need to shutdown the write channel to signal the other side that all data was transferred
@@ -51,12 +274,38 @@ async fn push_command(socket: &mut UnixStream, json_cmd: &str) -> Result<(), Err
 }
 
 async fn pull_response(socket: &mut UnixStream) -> Result<String, Error> {
-    let mut response = String::new();
-    socket
-        .read_to_string(&mut response)
-        .await
-        .map(|_size| response)
-        .map_err(Error::from)
+    read_message(&mut BufReader::new(socket), Limits::RESPONSE).await
+}
+
+/// Read one newline or EOF terminated message within `limits`.
+///
+/// Returns an empty string if the peer closed the connection without sending anything.
+pub async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R, limits: Limits) -> Result<String, Error> {
+    let deadline = Instant::now() + limits.idle_timeout + limits.read_timeout;
+    let mut read_by = Instant::now() + limits.idle_timeout;
+    let mut message = Vec::new();
+    loop {
+        let chunk = time::timeout_at(read_by.min(deadline), reader.fill_buf())
+            .await
+            .map_err(|_| Error::Timeout)??;
+        if chunk.is_empty() {
+            break;
+        }
+        let (len, complete) = match chunk.iter().position(|b| *b == b'\n') {
+            Some(pos) => (pos + 1, true),
+            None => (chunk.len(), false),
+        };
+        if message.len() + len > limits.max_size {
+            return Err(Error::MessageTooLarge(limits.max_size));
+        }
+        message.extend_from_slice(&chunk[..len]);
+        reader.consume(len);
+        if complete {
+            break;
+        }
+        read_by = Instant::now() + limits.read_timeout;
+    }
+    String::from_utf8(message).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err).into())
 }
 
 #[cfg(test)]
@@ -64,6 +313,7 @@ mod tests {
     use super::*;
     use serde_json;
     use tempfile::tempdir;
+    use tokio::io::AsyncReadExt;
 
     fn sample_command() -> Command {
         Command::Ping
@@ -80,6 +330,70 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn stale_socket_counts_as_not_running() -> anyhow::Result<()> {
+        let tmp = tempdir()?;
+        let path = tmp.path().join("stale.sock");
+        drop(std::os::unix::net::UnixListener::bind(&path)?);
+        let err = process_cmd(&path, &sample_command()).await.expect_err("nobody listens");
+        assert!(err.is_not_running());
+        assert!(!Error::PermissionDenied.is_not_running());
+        Ok(())
+    }
+
+    #[test]
+    fn explicit_socket_path_wins_over_config() {
+        let explicit = Some(PathBuf::from("/tmp/explicit.sock"));
+        let configured = Some(PathBuf::from("@gnosisvpn"));
+        assert_eq!(
+            resolve_path(explicit, configured.clone()),
+            PathBuf::from("/tmp/explicit.sock")
+        );
+        assert_eq!(resolve_path(None, configured), PathBuf::from("@gnosisvpn"));
+        assert_eq!(resolve_path(None, None), PathBuf::from(DEFAULT_PATH));
+    }
+
+    #[test]
+    fn abstract_name_requires_prefix() {
+        assert_eq!(abstract_name(Path::new("@gnosisvpn")), Some("gnosisvpn"));
+        assert_eq!(abstract_name(Path::new("/run/gnosisvpn.sock")), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn process_cmd_over_abstract_socket() -> anyhow::Result<()> {
+        let path = PathBuf::from(format!("@gnosisvpn-test-{}", std::process::id()));
+        let listener = bind(&path)?;
+        let server = tokio::spawn(async move {
+            if let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = String::new();
+                stream.read_to_string(&mut buf).await.expect("read");
+                let json = serde_json::to_string(&Response::Pong).expect("json");
+                stream.write_all(json.as_bytes()).await.expect("write response");
+            }
+        });
+
+        let resp = process_cmd(&path, &sample_command()).await.expect("response");
+        assert!(matches!(resp, Response::Pong));
+        server.await.expect("listener task");
+        Ok(())
+    }
+
+    #[test]
+    fn restrict_applies_mode_without_known_group() -> anyhow::Result<()> {
+        let tmp = tempdir()?;
+        let path = tmp.path().join("socket");
+        std::fs::write(&path, "")?;
+        let config = Config {
+            group: "gnosisvpn-test-missing-group".to_string(),
+            mode: 0o640,
+            ..Config::default()
+        };
+        restrict(&path, &config)?;
+        assert_eq!(std::fs::metadata(&path)?.permissions().mode() & 0o777, 0o640);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn push_and_pull_round_trip_command_frames() -> anyhow::Result<()> {
         let (mut server, mut client) = UnixStream::pair().expect("pair");
@@ -90,6 +404,45 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn read_message_stops_at_newline() -> anyhow::Result<()> {
+        let mut reader = BufReader::new(&b"{\"Ping\":null}\ntrailing"[..]);
+        let msg = read_message(&mut reader, Limits::REQUEST).await?;
+        assert_eq!(msg, "{\"Ping\":null}\n");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn read_message_rejects_oversized_message() -> anyhow::Result<()> {
+        let limits = Limits {
+            max_size: 8,
+            ..Limits::REQUEST
+        };
+        let mut reader = BufReader::new(&b"0123456789"[..]);
+        let err = read_message(&mut reader, limits).await.expect_err("too large");
+        assert!(matches!(err, Error::MessageTooLarge(8)));
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn read_message_times_out_on_trickling_peer() -> anyhow::Result<()> {
+        let (mut client, server) = tokio::io::duplex(64);
+        let writer = tokio::spawn(async move {
+            for _ in 0..100 {
+                if client.write_all(b"x").await.is_err() {
+                    break;
+                }
+                time::sleep(Duration::from_secs(4)).await;
+            }
+        });
+        let err = read_message(&mut BufReader::new(server), Limits::REQUEST)
+            .await
+            .expect_err("trickling peer should time out");
+        assert!(matches!(err, Error::Timeout));
+        writer.abort();
+        Ok(())
+    }
+
     #[tokio::test]
     async fn process_cmd_returns_deserialization_error_on_invalid_response() -> anyhow::Result<()> {
         let tmp = tempdir().expect("tempdir");
@@ -1,2 +1,180 @@
 /// Module for IPC communcation between root service and worker process.
+///
+/// Messages are framed as a big-endian `u32` length followed by the MessagePack encoded message.
+/// Compared to newline delimited JSON this avoids scanning for line breaks, UTF-8 validation and
+/// the intermediate `String` copies, which adds up for full WireGuard configs and metric dumps.
+/// Structs are encoded with field names so serde defaults and tagged enums behave as with JSON.
+/// Decoding is borrow friendly: message types with `&str` or `&[u8]` fields point into the frame
+/// buffer instead of copying, see [`decode`] and [`FrameReader::next`].
+///
+/// `cargo bench --bench wg_up` compares WireGuard setup round trips against the former JSON lines.
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use std::io;
+
 pub const ENV_VAR: &str = "INTERNAL_WORKER_FD";
+/// Upper bound for a single frame, guards against allocating on a corrupted length prefix.
+pub const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+pub const PREFIX_LEN: usize = 4;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("IO error: {0}")]
+    IO(#[from] io::Error),
+    #[error("Encoding error: {0}")]
+    Encode(#[from] rmp_serde::encode::Error),
+    #[error("Decoding error: {0}")]
+    Decode(#[from] rmp_serde::decode::Error),
+    #[error("Frame of {0} bytes exceeds limit")]
+    FrameTooLarge(usize),
+}
+
+/// Encode a message into a complete frame, length prefix included.
+pub fn encode<T: Serialize>(msg: &T) -> Result<Vec<u8>, Error> {
+    let mut frame = vec![0u8; PREFIX_LEN];
+    rmp_serde::encode::write_named(&mut frame, msg)?;
+    let len = frame.len() - PREFIX_LEN;
+    if len > MAX_FRAME_LEN {
+        return Err(Error::FrameTooLarge(len));
+    }
+    frame[..PREFIX_LEN].copy_from_slice(&(len as u32).to_be_bytes());
+    Ok(frame)
+}
+
+/// Decode the payload of a frame, length prefix excluded; borrowed fields point into `payload`.
+pub fn decode<'a, T: Deserialize<'a>>(payload: &'a [u8]) -> Result<T, Error> {
+    Ok(rmp_serde::from_slice(payload)?)
+}
+
+pub async fn write_frame<W, T>(writer: &mut W, msg: &T) -> Result<(), Error>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let frame = encode(msg)?;
+    writer.write_all(&frame).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Reads frames into a buffer reused across messages.
+pub struct FrameReader<R> {
+    reader: R,
+    buf: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> FrameReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Next message, `None` once the other side closed the socket.
+    /// Borrowed fields point into the reader buffer and live until the next frame is read.
+    /// Not cancel safe, a partially read frame is lost when the future is dropped.
+    pub async fn next<'a, T: Deserialize<'a>>(&'a mut self) -> Result<Option<T>, Error> {
+        let mut prefix = [0u8; PREFIX_LEN];
+        match self.reader.read_exact(&mut prefix).await {
+            Ok(_) => (),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+        let len = u32::from_be_bytes(prefix) as usize;
+        if len > MAX_FRAME_LEN {
+            return Err(Error::FrameTooLarge(len));
+        }
+        self.buf.resize(len, 0);
+        self.reader.read_exact(&mut self.buf).await?;
+        decode(&self.buf).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::command::{Response, WorkerCommand};
+    use crate::event::{RequestToRoot, RootToWorker, WorkerToRoot};
+    use crate::route_health::RouteHealthState;
+
+    #[tokio::test]
+    async fn frames_roundtrip_over_a_stream() -> anyhow::Result<()> {
+        let (mut client, server) = tokio::io::duplex(64);
+        let writer = tokio::spawn(async move {
+            write_frame(
+                &mut client,
+                &RootToWorker::WorkerCommand {
+                    cmd: WorkerCommand::Telemetry,
+                    id: 7,
+                },
+            )
+            .await?;
+            write_frame(&mut client, &WorkerToRoot::RequestToRoot(RequestToRoot::TearDownWg)).await?;
+            write_frame(
+                &mut client,
+                &WorkerToRoot::Response {
+                    resp: Response::Telemetry(Some("x".repeat(1024))),
+                    id: 7,
+                },
+            )
+            .await?;
+            Ok::<_, Error>(())
+        });
+
+        let mut reader = FrameReader::new(server);
+        let first: RootToWorker = reader.next().await?.expect("first frame");
+        assert!(matches!(first, RootToWorker::WorkerCommand { id: 7, .. }));
+        let second: WorkerToRoot = reader.next().await?.expect("second frame");
+        assert!(matches!(second, WorkerToRoot::RequestToRoot(RequestToRoot::TearDownWg)));
+        let third: WorkerToRoot = reader.next().await?.expect("third frame");
+        assert!(
+            matches!(third, WorkerToRoot::Response { resp: Response::Telemetry(Some(ref t)), .. } if t.len() == 1024)
+        );
+        writer.await??;
+        assert!(reader.next::<WorkerToRoot>().await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn internally_tagged_enums_survive_encoding() -> anyhow::Result<()> {
+        let frame = encode(&RouteHealthState::NeedsPeering { has_channel: true })?;
+        let mut reader = FrameReader::new(&frame[..]);
+        let decoded: RouteHealthState = reader.next().await?.expect("frame");
+        assert!(matches!(decoded, RouteHealthState::NeedsPeering { has_channel: true }));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn borrowed_fields_point_into_the_frame() -> anyhow::Result<()> {
+        #[derive(Serialize, Deserialize)]
+        struct Peer<'a> {
+            public_key: &'a str,
+            endpoint: &'a str,
+        }
+
+        let frame = encode(&Peer {
+            public_key: "aGVsbG8gd29ybGQgaGVsbG8gd29ybGQgaGVsbG8gd28=",
+            endpoint: "10.0.0.1:51820",
+        })?;
+        let mut reader = FrameReader::new(&frame[..]);
+        let peer: Peer = reader.next().await?.expect("frame");
+        assert_eq!(peer.endpoint, "10.0.0.1:51820");
+
+        let payload = &frame[PREFIX_LEN..];
+        let borrowed: Peer = decode(payload)?;
+        assert!(payload.as_ptr_range().contains(&borrowed.public_key.as_ptr()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn oversized_length_prefix_is_rejected() {
+        let frame = ((MAX_FRAME_LEN + 1) as u32).to_be_bytes();
+        let mut reader = FrameReader::new(&frame[..]);
+        let res = reader.next::<WorkerToRoot>().await;
+        assert!(matches!(res, Err(Error::FrameTooLarge(_))));
+    }
+}
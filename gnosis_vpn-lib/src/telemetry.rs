@@ -0,0 +1,285 @@
+//! Anonymous usage telemetry, strictly opt-in.
+//!
+//! Only coarse counters are collected: connection attempts, successes and how long successful
+//! connections took in a few buckets, next to client version and OS. No addresses, destinations
+//! or identifiers leave the machine, the exact payload is shown by `gnosis_vpn-ctl usage-telemetry`.
+//!
+//! Nothing is recorded or sent unless `telemetry = true` is set in the config or telemetry was
+//! enabled via ctl. The ctl toggle is persisted next to the counters and decides while the config
+//! file leaves `telemetry` unset or enables it, an explicit `telemetry = false` cannot be turned
+//! back on via ctl. Counters survive worker restarts and are reset once uploaded.
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use url::Url;
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::proxy;
+
+pub const TELEMETRY_FILE: &str = "telemetry.json";
+pub const UPLOAD_INTERVAL: Duration = Duration::from_hours(24);
+pub const DEFAULT_USAGE_URL: &str = "https://telemetry.gnosisvpn.io/v1/usage";
+pub const DEFAULT_CRASH_URL: &str = "https://telemetry.gnosisvpn.io/v1/crash";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Telemetry settings of the config file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    /// `telemetry` of the config file, `None` leaves the decision to the ctl toggle
+    pub enabled: Option<bool>,
+    /// Receives the usage counters, `None` keeps them local
+    pub usage_url: Option<Url>,
+    /// Receives crash reports, `None` keeps them local
+    pub crash_url: Option<Url>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: None,
+            usage_url: Url::parse(DEFAULT_USAGE_URL).ok(),
+            crash_url: Url::parse(DEFAULT_CRASH_URL).ok(),
+        }
+    }
+}
+
+/// Successful connections by time from connect request to established tunnel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct DurationBuckets {
+    pub under_5s: u32,
+    pub under_15s: u32,
+    pub under_30s: u32,
+    pub under_60s: u32,
+    pub over_60s: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Counters {
+    pub connect_attempts: u32,
+    pub connect_successes: u32,
+    pub connect_durations: DurationBuckets,
+}
+
+/// Everything that is sent, nothing more.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Payload {
+    pub version: String,
+    pub os: String,
+    #[serde(flatten)]
+    pub counters: Counters,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Persisted {
+    opt_in: Option<bool>,
+    counters: Counters,
+}
+
+pub struct Recorder {
+    path: PathBuf,
+    config_enabled: Option<bool>,
+    opt_in: Option<bool>,
+    counters: Counters,
+}
+
+impl DurationBuckets {
+    fn record(&mut self, duration: Duration) {
+        let bucket = match duration.as_secs() {
+            0..5 => &mut self.under_5s,
+            5..15 => &mut self.under_15s,
+            15..30 => &mut self.under_30s,
+            30..60 => &mut self.under_60s,
+            _ => &mut self.over_60s,
+        };
+        *bucket = bucket.saturating_add(1);
+    }
+
+    fn subtract(&mut self, other: &DurationBuckets) {
+        self.under_5s = self.under_5s.saturating_sub(other.under_5s);
+        self.under_15s = self.under_15s.saturating_sub(other.under_15s);
+        self.under_30s = self.under_30s.saturating_sub(other.under_30s);
+        self.under_60s = self.under_60s.saturating_sub(other.under_60s);
+        self.over_60s = self.over_60s.saturating_sub(other.over_60s);
+    }
+}
+
+impl Recorder {
+    pub fn load(path: PathBuf, config_enabled: Option<bool>) -> Self {
+        let persisted = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|error| {
+                tracing::warn!(?error, ?path, "discarding unreadable telemetry file");
+                Persisted::default()
+            }),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Persisted::default(),
+            Err(error) => {
+                tracing::warn!(?error, ?path, "unable to read telemetry file");
+                Persisted::default()
+            }
+        };
+        Self {
+            path,
+            config_enabled,
+            opt_in: persisted.opt_in,
+            counters: persisted.counters,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        match self.config_enabled {
+            Some(false) => false,
+            Some(true) => self.opt_in.unwrap_or(true),
+            None => self.opt_in.unwrap_or(false),
+        }
+    }
+
+    /// Switched off in the config file, the ctl toggle has no effect.
+    pub fn locked(&self) -> bool {
+        self.config_enabled == Some(false)
+    }
+
+    /// Opt in or out, opting out drops everything collected so far. Opting in is ignored while
+    /// [`Recorder::locked`].
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if enabled && self.locked() {
+            return;
+        }
+        self.opt_in = Some(enabled);
+        if !enabled {
+            self.counters = Counters::default();
+        }
+        self.persist();
+    }
+
+    pub fn record_success(&mut self, duration: Duration) {
+        if !self.enabled() {
+            return;
+        }
+        self.counters.connect_attempts = self.counters.connect_attempts.saturating_add(1);
+        self.counters.connect_successes = self.counters.connect_successes.saturating_add(1);
+        self.counters.connect_durations.record(duration);
+        self.persist();
+    }
+
+    pub fn record_failure(&mut self) {
+        if !self.enabled() {
+            return;
+        }
+        self.counters.connect_attempts = self.counters.connect_attempts.saturating_add(1);
+        self.persist();
+    }
+
+    pub fn payload(&self) -> Payload {
+        Payload {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            counters: self.counters,
+        }
+    }
+
+    /// Whether there is anything worth uploading.
+    pub fn has_data(&self) -> bool {
+        self.enabled() && self.counters.connect_attempts > 0
+    }
+
+    /// Drop the counters contained in an uploaded payload, keeping what was recorded meanwhile.
+    pub fn uploaded(&mut self, payload: &Payload) {
+        let sent = &payload.counters;
+        self.counters.connect_attempts = self.counters.connect_attempts.saturating_sub(sent.connect_attempts);
+        self.counters.connect_successes = self.counters.connect_successes.saturating_sub(sent.connect_successes);
+        self.counters.connect_durations.subtract(&sent.connect_durations);
+        self.persist();
+    }
+
+    fn persist(&self) {
+        let persisted = Persisted {
+            opt_in: self.opt_in,
+            counters: self.counters,
+        };
+        let res = serde_json::to_string(&persisted)
+            .map_err(std::io::Error::other)
+            .and_then(|content| fs::write(&self.path, content));
+        if let Err(error) = res {
+            tracing::warn!(?error, path = ?self.path, "failed to persist telemetry");
+        }
+    }
+}
+
+pub async fn upload(url: Url, payload: &Payload, proxy: Option<&Url>) -> Result<(), Error> {
+    proxy::outbound(proxy)?
+        .timeout(REQUEST_TIMEOUT)
+        .build()?
+        .post(url)
+        .json(payload)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::tempdir;
+
+    #[test]
+    fn nothing_is_recorded_without_opt_in() {
+        let dir = tempdir().unwrap();
+        let mut recorder = Recorder::load(dir.path().join(TELEMETRY_FILE), None);
+        recorder.record_success(Duration::from_secs(3));
+        recorder.record_failure();
+        assert_eq!(recorder.payload().counters, Counters::default());
+        assert!(!recorder.has_data());
+    }
+
+    #[test]
+    fn toggle_overrides_config_and_survives_reload() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(TELEMETRY_FILE);
+        let mut recorder = Recorder::load(path.clone(), None);
+        recorder.set_enabled(true);
+        recorder.record_success(Duration::from_secs(20));
+        recorder.record_failure();
+
+        let mut reloaded = Recorder::load(path.clone(), None);
+        assert!(reloaded.enabled());
+        let payload = reloaded.payload();
+        assert_eq!(payload.counters.connect_attempts, 2);
+        assert_eq!(payload.counters.connect_successes, 1);
+        assert_eq!(payload.counters.connect_durations.under_30s, 1);
+
+        reloaded.record_success(Duration::from_secs(90));
+        reloaded.uploaded(&payload);
+        assert_eq!(reloaded.payload().counters.connect_attempts, 1);
+        assert_eq!(reloaded.payload().counters.connect_durations.over_60s, 1);
+
+        reloaded.set_enabled(false);
+        let disabled = Recorder::load(path, Some(true));
+        assert!(!disabled.enabled());
+        assert_eq!(disabled.payload().counters, Counters::default());
+    }
+
+    #[test]
+    fn toggle_cannot_override_disabled_config() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(TELEMETRY_FILE);
+        let mut recorder = Recorder::load(path.clone(), Some(false));
+        assert!(recorder.locked());
+        recorder.set_enabled(true);
+        recorder.record_success(Duration::from_secs(3));
+        assert!(!recorder.enabled());
+        assert!(!recorder.has_data());
+
+        // an opt-in while forbidden is not remembered for later
+        assert!(!Recorder::load(path, None).enabled());
+    }
+}
@@ -1,43 +1,92 @@
-use gnosis_vpn_lib::command::{
+use rand::seq::SliceRandom;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::command::{
     BalanceResponse, Command, ConnectResponse, DestinationState, DisconnectResponse, Response, RunMode,
     StartClientResponse, StatusResponse, StopClientResponse,
 };
-use gnosis_vpn_lib::connection::destination::Destination;
-use gnosis_vpn_lib::route_health::RouteHealthState;
-use gnosis_vpn_lib::socket::root::{Error as SocketError, process_cmd};
-use rand::seq::SliceRandom;
-use std::{path::PathBuf, time::Duration};
-use tracing::{debug, error, info, warn};
+use crate::connection::destination::Destination;
+use crate::connection::up::Phase;
+use crate::route_health::RouteHealthState;
+use crate::socket::root::{Error as SocketError, process_cmd};
 
-use crate::fixtures::lib::{self, ConditionCheck};
+use super::wait::{ConditionCheck, wait_for_condition};
 
-/// Thin wrapper around the gnosis_vpn control socket used during system tests.
+/// How often each wait polls the service.
+#[derive(Clone, Copy, Debug)]
+pub struct Intervals {
+    /// Until the control socket answers pings
+    pub service: Duration,
+    /// Until the safe is created or hopr started
+    pub run_mode: Duration,
+    /// Until the node is running
+    pub node_running: Duration,
+    /// Until node and safe are funded
+    pub funding: Duration,
+    /// Until destinations are ready to connect
+    pub destinations: Duration,
+    /// Until a connection reached a phase or is established
+    pub connection: Duration,
+    /// Until the connection is closed
+    pub disconnection: Duration,
+    /// Until a troubleshooting hint is reported
+    pub hints: Duration,
+}
+
+impl Default for Intervals {
+    fn default() -> Self {
+        Intervals {
+            service: Duration::from_secs(2),
+            run_mode: Duration::from_secs(5),
+            node_running: Duration::from_secs(10),
+            funding: Duration::from_secs(5),
+            destinations: Duration::from_secs(10),
+            connection: Duration::from_secs(5),
+            disconnection: Duration::from_secs(2),
+            hints: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Thin wrapper around the control socket with typed waits for every phase of the service.
 pub struct ControlClient {
     socket_path: PathBuf,
+    intervals: Intervals,
 }
 
 impl ControlClient {
     /// Creates a new client bound to a Unix domain socket path.
     pub fn new(socket_path: PathBuf) -> Self {
-        Self { socket_path }
+        Self {
+            socket_path,
+            intervals: Intervals::default(),
+        }
+    }
+
+    /// Replaces the poll intervals used by the waits.
+    pub fn with_intervals(mut self, intervals: Intervals) -> Self {
+        self.intervals = intervals;
+        self
     }
 
-    /// Sends a raw command to the control socket and returns the daemon response.
+    /// Sends a raw command to the control socket and returns the service response.
     pub async fn send(&self, cmd: &Command) -> anyhow::Result<Response> {
         match process_cmd(self.socket_path.as_path(), cmd).await {
             Ok(resp) => Ok(resp),
             Err(SocketError::ServiceNotRunning) => {
-                error!(?cmd, "service not running when sending command");
+                tracing::error!(?cmd, "service not running when sending command");
                 Err(SocketError::ServiceNotRunning.into())
             }
             Err(error) => {
-                error!(%error, ?cmd, "error while sending command");
+                tracing::error!(%error, ?cmd, "error while sending command");
                 Err(error.into())
             }
         }
     }
 
-    /// Verifies the daemon responds to ping requests.
+    /// Verifies the service responds to ping requests.
     pub async fn ping(&self) -> anyhow::Result<()> {
         self.send(&Command::Ping).await.and_then(|result| {
             matches!(result, Response::Pong)
@@ -46,7 +95,7 @@ impl ControlClient {
         })
     }
 
-    /// Fetches current daemon status information.
+    /// Fetches current service status information.
     pub async fn status(&self) -> anyhow::Result<Option<StatusResponse>> {
         match self.send(&Command::Status).await {
             Ok(Response::Status(status)) => Ok(Some(status)),
@@ -65,6 +114,29 @@ impl ControlClient {
         }
     }
 
+    /// Fetches the Prometheus metrics of node, worker and root.
+    pub async fn telemetry(&self) -> anyhow::Result<String> {
+        match self.send(&Command::Telemetry).await {
+            Ok(Response::Telemetry(Some(metrics))) => Ok(metrics),
+            Ok(resp) => Err(anyhow::anyhow!("unexpected telemetry response {resp:?}")),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sums the tokio tasks alive in the root and worker runtimes, as reported in their metrics.
+    pub async fn alive_tasks(&self) -> anyhow::Result<u64> {
+        let metrics = self.telemetry().await?;
+        let samples = metrics
+            .lines()
+            .filter(|line| line.starts_with("gnosis_vpn_"))
+            .filter_map(|line| line.split_once(' '))
+            .filter(|(name, _)| name.ends_with("_alive_tasks"))
+            .filter_map(|(_, value)| value.trim().parse::<f64>().ok())
+            .collect::<Vec<_>>();
+        anyhow::ensure!(!samples.is_empty(), "no alive task metrics reported");
+        Ok(samples.into_iter().sum::<f64>() as u64)
+    }
+
     /// Initiates a VPN connection to the provided destination.
     pub async fn connect(&self, destination: String) -> anyhow::Result<ConnectResponse> {
         match self.send(&Command::Connect(destination)).await {
@@ -103,10 +175,10 @@ impl ControlClient {
 
     /// Waits until the control API responds to ping requests.
     pub async fn wait_for_service_running(&self, timeout: Duration) -> anyhow::Result<()> {
-        lib::wait_for_condition("service running", timeout, Duration::from_secs(2), || async {
+        wait_for_condition("service running", timeout, self.intervals.service, || async {
             match self.ping().await {
                 Ok(_) => {
-                    info!("gnosis_vpn service is pingable");
+                    tracing::info!("gnosis_vpn service is pingable");
                     Ok(ConditionCheck::Ready(()))
                 }
                 Err(_) => Ok(ConditionCheck::Pending),
@@ -116,47 +188,60 @@ impl ControlClient {
         Ok(())
     }
 
-    /// Waits until a safe is created and available.
-    pub async fn wait_for_safe_created(&self, timeout: Duration) -> anyhow::Result<()> {
-        lib::wait_for_condition("safe created", timeout, Duration::from_secs(5), || async {
+    /// Waits until the reported run mode satisfies `reached`, polling at the run mode interval.
+    pub async fn wait_for_run_mode<F>(&self, label: &str, timeout: Duration, reached: F) -> anyhow::Result<RunMode>
+    where
+        F: Fn(&RunMode) -> bool,
+    {
+        self.wait_for_run_mode_every(label, timeout, self.intervals.run_mode, reached)
+            .await
+    }
+
+    async fn wait_for_run_mode_every<F>(
+        &self,
+        label: &str,
+        timeout: Duration,
+        interval: Duration,
+        reached: F,
+    ) -> anyhow::Result<RunMode>
+    where
+        F: Fn(&RunMode) -> bool,
+    {
+        wait_for_condition(label, timeout, interval, || async {
             match self.status().await {
-                Ok(Some(status)) => match status.run_mode {
-                    RunMode::Init { .. } | RunMode::NotRunning | RunMode::Restarting => Ok(ConditionCheck::Pending),
-                    RunMode::PreparingSafe { .. } => {
-                        warn!("safe being prepared");
-                        Ok(ConditionCheck::Pending)
-                    }
-                    RunMode::DeployingSafe { .. }
-                    | RunMode::Warmup { .. }
-                    | RunMode::Running { .. }
-                    | RunMode::Shutdown => {
-                        info!("safe is created and ready");
-                        Ok(ConditionCheck::Ready(()))
+                Ok(Some(status)) if reached(&status.run_mode) => {
+                    tracing::info!(%label, "run mode reached");
+                    Ok(ConditionCheck::Ready(status.run_mode))
+                }
+                Ok(Some(status)) => {
+                    if let RunMode::PreparingSafe { .. } = status.run_mode {
+                        tracing::warn!("safe being prepared");
                     }
-                },
-                Ok(None) => Ok(ConditionCheck::Pending),
-                Err(_) => Ok(ConditionCheck::Pending),
+                    tracing::debug!(%label, run_mode = ?status.run_mode, "run mode not reached yet");
+                    Ok(ConditionCheck::Pending)
+                }
+                Ok(None) | Err(_) => Ok(ConditionCheck::Pending),
             }
         })
+        .await
+    }
+
+    /// Waits until a safe is created and available.
+    pub async fn wait_for_safe_created(&self, timeout: Duration) -> anyhow::Result<()> {
+        self.wait_for_run_mode("safe created", timeout, |run_mode| {
+            matches!(
+                run_mode,
+                RunMode::DeployingSafe { .. } | RunMode::Warmup { .. } | RunMode::Running { .. } | RunMode::Shutdown
+            )
+        })
         .await?;
         Ok(())
     }
 
-    /// Ensures both on-chain accounts have funds before the test proceeds.
-    pub async fn wait_for_node_funding(&self, timeout: Duration) -> anyhow::Result<()> {
-        lib::wait_for_condition("node funds", timeout, Duration::from_secs(5), || async {
-            match self.balance().await {
-                Ok(BalanceResponse { node, safe, .. }) => {
-                    if node.is_zero() || safe.is_zero() {
-                        debug!("node or safe have zero funds");
-                        Ok(ConditionCheck::Pending)
-                    } else {
-                        info!("node and safe have funds");
-                        Ok(ConditionCheck::Ready(()))
-                    }
-                }
-                Err(_) => Ok(ConditionCheck::Pending),
-            }
+    /// Waits until hopr started and is warming up, or already running.
+    pub async fn wait_for_warmup(&self, timeout: Duration) -> anyhow::Result<()> {
+        self.wait_for_run_mode("node warmup", timeout, |run_mode| {
+            matches!(run_mode, RunMode::Warmup { .. } | RunMode::Running { .. })
         })
         .await?;
         Ok(())
@@ -164,18 +249,26 @@ impl ControlClient {
 
     /// Waits until the node reports a running state.
     pub async fn wait_for_node_running(&self, timeout: Duration) -> anyhow::Result<()> {
-        lib::wait_for_condition("node running", timeout, Duration::from_secs(10), || async {
-            match self.status().await {
-                Ok(Some(status)) => {
-                    if matches!(status.run_mode, RunMode::Running { .. }) {
-                        info!("node is in Running state");
-                        Ok(ConditionCheck::Ready(()))
-                    } else {
-                        debug!("node not in Running state yet");
+        self.wait_for_run_mode_every("node running", timeout, self.intervals.node_running, |run_mode| {
+            matches!(run_mode, RunMode::Running { .. })
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Ensures both on-chain accounts have funds before the test proceeds.
+    pub async fn wait_for_node_funding(&self, timeout: Duration) -> anyhow::Result<()> {
+        wait_for_condition("node funds", timeout, self.intervals.funding, || async {
+            match self.balance().await {
+                Ok(BalanceResponse { node, safe, .. }) => {
+                    if node.is_zero() || safe.is_zero() {
+                        tracing::debug!("node or safe have zero funds");
                         Ok(ConditionCheck::Pending)
+                    } else {
+                        tracing::info!("node and safe have funds");
+                        Ok(ConditionCheck::Ready(()))
                     }
                 }
-                Ok(None) => Ok(ConditionCheck::Pending),
                 Err(_) => Ok(ConditionCheck::Pending),
             }
         })
@@ -184,28 +277,29 @@ impl ControlClient {
     }
 
     /// Aggregates ready and not-ready destinations.
+    /// On timeout the last partial readiness is returned if at least one destination was ready.
     pub async fn wait_for_ready_destinations(&self, timeout: Duration) -> anyhow::Result<DestinationReadiness> {
-        lib::wait_for_condition(
+        wait_for_condition(
             "node ready to reach all destinations",
             timeout,
-            Duration::from_secs(10),
+            self.intervals.destinations,
             || async {
                 match self.status().await {
                     Ok(Some(status)) => {
                         let mut readiness = DestinationReadiness::from_states(status.destinations);
 
                         if readiness.ready().is_empty() {
-                            warn!("no ready destinations yet");
+                            tracing::warn!("no ready destinations yet");
                             return Ok(ConditionCheck::PendingWithValue(readiness));
                         }
 
                         if readiness.not_ready().is_empty() {
-                            info!("all destinations are ready");
+                            tracing::info!("all destinations are ready");
                             readiness.shuffle_ready();
                             return Ok(ConditionCheck::Ready(readiness));
                         }
 
-                        warn!(
+                        tracing::warn!(
                             ready = readiness.ready().len(),
                             not_ready = readiness.not_ready().len(),
                             "waiting for all destinations to be ready"
@@ -220,13 +314,47 @@ impl ControlClient {
         .await
     }
 
+    /// Waits until the connection to `destination` passed `phase`, an established connection counts as well.
+    pub async fn wait_for_connecting_phase(
+        &self,
+        destination: &Destination,
+        phase: Phase,
+        timeout: Duration,
+    ) -> anyhow::Result<()> {
+        let label = format!("connecting phase {phase}");
+        wait_for_condition(&label, timeout, self.intervals.connection, || async {
+            match self.status().await {
+                Ok(Some(status)) => {
+                    let is_connected = status
+                        .connected
+                        .as_ref()
+                        .is_some_and(|c| c.destination_id == destination.id);
+                    let current = status
+                        .connecting
+                        .as_ref()
+                        .filter(|c| c.destination_id == destination.id)
+                        .map(|c| &c.phase);
+                    if is_connected || current.is_some_and(|current| current.ordinal() >= phase.ordinal()) {
+                        tracing::info!(%phase, dest = %destination, "connecting phase reached");
+                        Ok(ConditionCheck::Ready(()))
+                    } else {
+                        tracing::debug!(?current, dest = %destination, "connecting phase not reached yet");
+                        Ok(ConditionCheck::Pending)
+                    }
+                }
+                Ok(None) | Err(_) => Ok(ConditionCheck::Pending),
+            }
+        })
+        .await
+    }
+
     /// Ensures a specific destination reaches the Connected state.
     pub async fn wait_for_connection_established(
         &self,
         destination: &Destination,
         timeout: Duration,
     ) -> anyhow::Result<()> {
-        lib::wait_for_condition("connection settlement", timeout, Duration::from_secs(5), || async {
+        wait_for_condition("connection settlement", timeout, self.intervals.connection, || async {
             match self.status().await {
                 Ok(Some(status)) => {
                     let location = status
@@ -247,13 +375,13 @@ impl ControlClient {
                         .map(|c| &c.phase);
 
                     if is_connected {
-                        info!(?location, "connection established successfully");
+                        tracing::info!(?location, "connection established successfully");
                         Ok(ConditionCheck::Ready(()))
                     } else if let Some(phase) = connecting_phase {
-                        warn!(?phase, ?location, "connection is being established");
+                        tracing::warn!(?phase, ?location, "connection is being established");
                         Ok(ConditionCheck::Pending)
                     } else {
-                        warn!(?location, "connection state is unknown");
+                        tracing::warn!(?location, "connection state is unknown");
                         Ok(ConditionCheck::Pending)
                     }
                 }
@@ -265,17 +393,40 @@ impl ControlClient {
         Ok(())
     }
 
-    /// Ensures there is no active VPN connection.
+    /// Waits until a reported hint contains one of `markers` and returns it.
+    pub async fn wait_for_hint(&self, markers: &[&str], timeout: Duration) -> anyhow::Result<String> {
+        wait_for_condition("troubleshooting hint", timeout, self.intervals.hints, || async {
+            match self.status().await {
+                Ok(Some(status)) => {
+                    let found = status
+                        .hints
+                        .into_iter()
+                        .find(|hint| markers.iter().any(|marker| hint.contains(marker)));
+                    match found {
+                        Some(hint) => {
+                            tracing::info!(%hint, "expected hint reported");
+                            Ok(ConditionCheck::Ready(hint))
+                        }
+                        None => Ok(ConditionCheck::Pending),
+                    }
+                }
+                Ok(None) | Err(_) => Ok(ConditionCheck::Pending),
+            }
+        })
+        .await
+    }
+
+    /// Requests disconnection until there is no active VPN connection.
     pub async fn wait_for_disconnection(&self, timeout: Duration) -> anyhow::Result<()> {
-        lib::wait_for_condition("disconnection", timeout, Duration::from_secs(2), || async {
+        wait_for_condition("disconnection", timeout, self.intervals.disconnection, || async {
             match self.disconnect().await {
                 Ok(response) => match response {
                     DisconnectResponse::Disconnecting(address) => {
-                        info!("disconnecting from destination {address}");
+                        tracing::info!("disconnecting from destination {address}");
                         Ok(ConditionCheck::Pending)
                     }
                     DisconnectResponse::NotConnected => {
-                        info!("successfully disconnected");
+                        tracing::info!("successfully disconnected");
                         Ok(ConditionCheck::Ready(()))
                     }
                 },
@@ -0,0 +1,10 @@
+//! Helpers to drive a running service through its control socket from integration tests.
+//!
+//! Used by the system tests and meant for packagers verifying their builds in CI.
+//! Only available with the `test-util` feature.
+
+pub mod control_client;
+pub mod wait;
+
+pub use control_client::{ControlClient, DestinationReadiness, Intervals};
+pub use wait::{ConditionCheck, wait_for_condition};
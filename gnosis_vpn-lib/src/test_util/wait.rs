@@ -0,0 +1,80 @@
+use std::fmt::Debug;
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Result of a single condition evaluation.
+#[derive(Debug)]
+pub enum ConditionCheck<T> {
+    /// Condition not satisfied yet and no partial result available.
+    Pending,
+    /// Condition not satisfied yet but we captured a partial result.
+    PendingWithValue(T),
+    /// Condition satisfied with the provided value.
+    Ready(T),
+}
+
+/// Repeatedly evaluates `check` until it yields a value or the timeout expires.
+/// On timeout the last partial result is returned if there is one.
+pub async fn wait_for_condition<T, F, Fut>(
+    label: &str,
+    timeout: Duration,
+    interval: Duration,
+    mut check: F,
+) -> anyhow::Result<T>
+where
+    T: Debug,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<ConditionCheck<T>>>,
+{
+    let start = Instant::now();
+    let mut last_progress: Option<T> = None;
+
+    loop {
+        tracing::debug!("checking condition for {label}");
+        match check().await? {
+            ConditionCheck::Ready(result) => return Ok(result),
+            ConditionCheck::Pending => {}
+            ConditionCheck::PendingWithValue(result) => {
+                last_progress = Some(result);
+            }
+        }
+
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            if let Some(result) = last_progress {
+                tracing::warn!(%label, "timeout expired, returning last known progress");
+                return Ok(result);
+            }
+            return Err(anyhow::anyhow!("timeout on {label}"));
+        }
+
+        let sleep_duration = interval.min(timeout.saturating_sub(elapsed));
+        tokio::time::sleep(sleep_duration).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn returns_last_progress_on_timeout() -> anyhow::Result<()> {
+        let mut calls = 0;
+        let res = wait_for_condition("progress", Duration::from_secs(3), Duration::from_secs(1), || {
+            calls += 1;
+            let calls = calls;
+            async move { Ok(ConditionCheck::PendingWithValue(calls)) }
+        })
+        .await?;
+        assert!(res >= 4);
+
+        let res = wait_for_condition("nothing", Duration::from_secs(3), Duration::from_secs(1), || async {
+            Ok(ConditionCheck::<()>::Pending)
+        })
+        .await;
+        assert!(res.is_err());
+        Ok(())
+    }
+}
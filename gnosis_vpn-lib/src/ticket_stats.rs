@@ -1,11 +1,13 @@
 use edgli::hopr_lib::api::types::primitive::prelude::{Balance, WxHOPR};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::serde_utils;
 
-#[derive(Copy, Debug, Clone, Serialize, Deserialize)]
+#[derive(Copy, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct TicketStats {
     #[serde(with = "serde_utils::balance")]
+    #[schemars(with = "String")]
     pub ticket_price: Balance<WxHOPR>,
     pub winning_probability: f64,
 }
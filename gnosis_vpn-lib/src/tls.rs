@@ -0,0 +1,386 @@
+//! Trust for TLS connections of outgoing HTTPS requests.
+//!
+//! Deployments running their own PKI, e.g. exits behind a private CA, replace the system trust
+//! store with `[network] ca_bundle`. `pinned_certificates` additionally requires the server
+//! certificate of a host to be one of the SHA-256 fingerprints listed for it. Pinned hosts are
+//! still checked for name and validity period: against the CA bundle if there is one, otherwise
+//! the pinned certificate is its own trust anchor. Hosts without pins are verified as usual.
+//!
+//! The trust is installed once per process with [`install`] and applied to every client built by
+//! [`crate::proxy`]. Root passes the bundle on to the worker in [`ENV_VAR`] for the chain client of
+//! hopr-lib, which only honors the bundle: pins for its host are refused, see [`Config::refuse_pins_for`].
+use reqwest::ClientBuilder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio_rustls::rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use tokio_rustls::rustls::client::{VerifierBuilderError, WebPkiServerVerifier};
+use tokio_rustls::rustls::crypto::{self, CryptoProvider};
+use tokio_rustls::rustls::pki_types::pem::{self, PemObject};
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{self, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Variable the worker receives the CA bundle in.
+pub const ENV_VAR: &str = "SSL_CERT_FILE";
+
+static TRUST: Mutex<Option<ClientConfig>> = Mutex::new(None);
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    /// PEM file with the only CAs accepted, `None` keeps the system trust store
+    pub ca_bundle: Option<PathBuf>,
+    /// SHA-256 fingerprints, hex with optional colons, per host name: its server certificate must be one of them
+    pub pinned_certificates: BTreeMap<String, Vec<String>>,
+}
+
+impl Config {
+    /// Pins only apply to clients built by [`crate::proxy`], refuse them for a host connected to otherwise.
+    pub fn refuse_pins_for(&self, host: &str) -> Result<(), Error> {
+        if self.pinned_certificates.keys().any(|h| h.eq_ignore_ascii_case(host)) {
+            Err(Error::UnpinnableHost(host.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("unable to load CA bundle: {0}")]
+    Pem(#[from] pem::Error),
+    #[error("CA bundle {0} holds no certificates")]
+    EmptyBundle(PathBuf),
+    #[error("invalid certificate fingerprint: {0}")]
+    InvalidPin(String),
+    #[error("certificates of {0} cannot be pinned, the HOPR node connects to it on its own")]
+    UnpinnableHost(String),
+    #[error("TLS error: {0}")]
+    Tls(#[from] rustls::Error),
+    #[error("unusable CA bundle: {0}")]
+    Verifier(#[from] VerifierBuilderError),
+}
+
+/// Replace the trust of clients built from now on, the default config restores the system trust store.
+pub fn install(config: &Config) -> Result<(), Error> {
+    let trust = if *config == Config::default() {
+        None
+    } else {
+        Some(client_config(config)?)
+    };
+    if let Ok(mut current) = TRUST.lock() {
+        *current = trust;
+    }
+    Ok(())
+}
+
+/// Colon separated SHA-256 of a DER certificate, the form pins are configured and shown in.
+pub fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|b| format!("{b:02X}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Apply the installed trust to `builder`.
+pub fn apply(builder: ClientBuilder) -> ClientBuilder {
+    match TRUST.lock().ok().and_then(|trust| trust.clone()) {
+        Some(tls) => builder.use_preconfigured_tls(tls),
+        None => builder,
+    }
+}
+
+fn client_config(config: &Config) -> Result<ClientConfig, Error> {
+    let provider = Arc::new(crypto::ring::default_provider());
+    let verifier = verifier(config, provider.clone())?;
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    Ok(config)
+}
+
+fn verifier(config: &Config, provider: Arc<CryptoProvider>) -> Result<Verifier, Error> {
+    let mut pins = BTreeMap::new();
+    for (host, fingerprints) in &config.pinned_certificates {
+        let fingerprints = fingerprints.iter().map(|f| pin(f)).collect::<Result<Vec<_>, _>>()?;
+        pins.insert(host.to_ascii_lowercase(), fingerprints);
+    }
+    let chain: Arc<dyn ServerCertVerifier> = match &config.ca_bundle {
+        Some(path) => WebPkiServerVerifier::builder_with_provider(Arc::new(roots(path)?), provider.clone()).build()?,
+        None => Arc::new(rustls_platform_verifier::Verifier::new(provider.clone())?),
+    };
+    Ok(Verifier {
+        chain,
+        bundle: config.ca_bundle.is_some(),
+        pins,
+        provider,
+    })
+}
+
+fn roots(path: &Path) -> Result<RootCertStore, Error> {
+    let mut roots = RootCertStore::empty();
+    for cert in CertificateDer::pem_file_iter(path)? {
+        roots.add(cert?)?;
+    }
+    if roots.is_empty() {
+        return Err(Error::EmptyBundle(path.to_path_buf()));
+    }
+    Ok(roots)
+}
+
+/// Normalized fingerprint: upper case hex without separators.
+fn pin(fingerprint: &str) -> Result<String, Error> {
+    let hex = fingerprint.replace(':', "").to_ascii_uppercase();
+    if hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(hex)
+    } else {
+        Err(Error::InvalidPin(fingerprint.to_string()))
+    }
+}
+
+/// Validates against the CA bundle or the platform trust, pinned hosts only with their pinned certificate.
+#[derive(Debug)]
+struct Verifier {
+    chain: Arc<dyn ServerCertVerifier>,
+    bundle: bool,
+    /// Normalized fingerprints per lower case host name
+    pins: BTreeMap<String, Vec<String>>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl Verifier {
+    /// Name and validity period of a pinned certificate nothing else vouches for, it is its own trust anchor.
+    fn verify_self_anchored(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let mut roots = RootCertStore::empty();
+        roots.add(end_entity.clone().into_owned())?;
+        WebPkiServerVerifier::builder_with_provider(Arc::new(roots), self.provider.clone())
+            .build()
+            .map_err(|error| rustls::Error::General(error.to_string()))?
+            .verify_server_cert(end_entity, &[], server_name, ocsp_response, now)
+    }
+}
+
+impl ServerCertVerifier for Verifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let Some(pins) = self.pins.get(&server_name.to_str().to_ascii_lowercase()) else {
+            return self
+                .chain
+                .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now);
+        };
+        // only the server certificate counts, an intermediate may vouch for any number of hosts
+        if !pins.contains(&fingerprint(end_entity).replace(':', "")) {
+            return Err(rustls::Error::InvalidCertificate(
+                rustls::CertificateError::ApplicationVerificationFailure,
+            ));
+        }
+        if self.bundle {
+            return self
+                .chain
+                .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now);
+        }
+        // a pinned certificate issued by a public CA is validated like any other
+        self.verify_self_anchored(end_entity, server_name, ocsp_response, now)
+            .or_else(|_| {
+                self.chain
+                    .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+            })
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+    use tokio_rustls::rustls::ServerConfig;
+    use tokio_rustls::rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+
+    #[test]
+    fn fingerprint_is_colon_separated_sha256() {
+        let fp = fingerprint(b"certificate");
+        assert_eq!(fp.len(), 32 * 3 - 1);
+        assert_eq!(fp.split(':').count(), 32);
+    }
+
+    #[tokio::test]
+    async fn endpoint_without_pinned_certificate_is_refused() -> anyhow::Result<()> {
+        let served = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])?;
+        let other = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])?;
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(served.key_pair.serialize_der()));
+        let server = ServerConfig::builder_with_provider(Arc::new(crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(vec![served.cert.der().clone()], key)?;
+        let acceptor = TlsAcceptor::from(Arc::new(server));
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("https://localhost:{}/", listener.local_addr()?.port());
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                if let Ok(mut stream) = acceptor.accept(stream).await {
+                    let mut buf = [0u8; 1024];
+                    let _ = stream.read(&mut buf).await;
+                    let _ = stream
+                        .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                        .await;
+                }
+            }
+        });
+
+        // the trust `apply` hands to every client, without installing it for the whole test process
+        let client = |pinned: &rcgen::CertifiedKey| -> anyhow::Result<reqwest::Client> {
+            let config = Config {
+                ca_bundle: None,
+                pinned_certificates: BTreeMap::from([("localhost".to_string(), vec![fingerprint(pinned.cert.der())])]),
+            };
+            Ok(ClientBuilder::new()
+                .no_proxy()
+                .use_preconfigured_tls(client_config(&config)?)
+                .build()?)
+        };
+        assert!(client(&other)?.get(&url).send().await.is_err());
+        assert!(client(&served)?.get(&url).send().await?.status().is_success());
+        server.abort();
+        Ok(())
+    }
+
+    fn pinned(host: &str, cert: &rcgen::CertifiedKey) -> anyhow::Result<Verifier> {
+        let config = Config {
+            ca_bundle: None,
+            pinned_certificates: BTreeMap::from([(
+                host.to_string(),
+                vec![fingerprint(cert.cert.der()).to_lowercase()],
+            )]),
+        };
+        Ok(verifier(&config, Arc::new(crypto::ring::default_provider()))?)
+    }
+
+    #[test]
+    fn pins_without_bundle_only_accept_the_pinned_server_certificate() -> anyhow::Result<()> {
+        let pinned_cert = rcgen::generate_simple_self_signed(vec!["rpc.internal".to_string()])?;
+        let other = rcgen::generate_simple_self_signed(vec!["rpc.internal".to_string()])?;
+        let verifier = pinned("RPC.internal", &pinned_cert)?;
+        let name = ServerName::try_from("rpc.internal")?;
+        let now = UnixTime::now();
+
+        assert!(
+            verifier
+                .verify_server_cert(pinned_cert.cert.der(), &[], &name, &[], now)
+                .is_ok()
+        );
+        let smuggled = [pinned_cert.cert.der().clone()];
+        assert!(
+            verifier
+                .verify_server_cert(other.cert.der(), &smuggled, &name, &[], now)
+                .is_err()
+        );
+        assert!(pin("AB:CD").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn pinned_certificate_is_still_checked_for_name_and_validity() -> anyhow::Result<()> {
+        let mut params = rcgen::CertificateParams::new(vec!["rpc.internal".to_string()])?;
+        params.not_before = rcgen::date_time_ymd(2000, 1, 1);
+        params.not_after = rcgen::date_time_ymd(2001, 1, 1);
+        let key_pair = rcgen::KeyPair::generate()?;
+        let expired = rcgen::CertifiedKey {
+            cert: params.self_signed(&key_pair)?,
+            key_pair,
+        };
+        let now = UnixTime::now();
+
+        let verifier = pinned("rpc.internal", &expired)?;
+        let name = ServerName::try_from("rpc.internal")?;
+        assert!(
+            verifier
+                .verify_server_cert(expired.cert.der(), &[], &name, &[], now)
+                .is_err()
+        );
+
+        let misnamed = rcgen::generate_simple_self_signed(vec!["other.internal".to_string()])?;
+        let verifier = pinned("rpc.internal", &misnamed)?;
+        assert!(
+            verifier
+                .verify_server_cert(misnamed.cert.der(), &[], &name, &[], now)
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pins_only_apply_to_their_host() -> anyhow::Result<()> {
+        let pinned_cert = rcgen::generate_simple_self_signed(vec!["rpc.internal".to_string()])?;
+        let other = rcgen::generate_simple_self_signed(vec!["exit.internal".to_string()])?;
+        let verifier = pinned("rpc.internal", &pinned_cert)?;
+        let other_name = ServerName::try_from("exit.internal")?;
+
+        // left to the platform trust, which knows nothing of the pinned certificate
+        assert!(
+            verifier
+                .verify_server_cert(pinned_cert.cert.der(), &[], &other_name, &[], UnixTime::now())
+                .is_err()
+        );
+        assert!(
+            verifier
+                .verify_server_cert(other.cert.der(), &[], &other_name, &[], UnixTime::now())
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pins_are_refused_for_hosts_outside_the_proxy_clients() {
+        let config = Config {
+            ca_bundle: None,
+            pinned_certificates: BTreeMap::from([("RPC.internal".to_string(), vec!["AB:CD".to_string()])]),
+        };
+        assert!(matches!(
+            config.refuse_pins_for("rpc.internal"),
+            Err(Error::UnpinnableHost(_))
+        ));
+        assert!(config.refuse_pins_for("exit.internal").is_ok());
+    }
+}
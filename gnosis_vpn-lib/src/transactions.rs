@@ -0,0 +1,235 @@
+//! On-chain transactions the client sent itself, listed by [`crate::command::Command::Transactions`].
+//!
+//! The withdrawal of node wxHOPR into the safe is sent through the node's chain API, which resolves
+//! with the transaction hash once the transaction is on chain. Channel funding is done by the node
+//! strategy and recorded from the chain events of outgoing channels, which only arrive after the
+//! funding went through but do not name the transaction. Safe deployment goes through the Blokli
+//! client which only reports the outcome. The chain API reports no confirmation depth, `confirmed`
+//! means the transaction was included.
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::serde_utils;
+
+pub const TRANSACTIONS_FILE: &str = "transactions.json";
+
+/// Withdrawals and channel funding repeat for as long as the node runs, older entries are dropped.
+const MAX_ENTRIES: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    SafeDeployment,
+    /// Node wxHOPR moved into the safe
+    WxhoprWithdrawal,
+    /// Outgoing channel opened or topped up
+    ChannelFunding,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Status {
+    Pending,
+    Confirmed,
+    Failed {
+        reason: String,
+    },
+    /// The worker stopped before the outcome was known, check the chain
+    Interrupted,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Transaction {
+    pub kind: Kind,
+    pub status: Status,
+    /// Amount moved, e.g. `1.5 wxHOPR`
+    pub amount: Option<String>,
+    /// Contract created or funds sent to, e.g. the deployed safe
+    pub target: Option<String>,
+    /// Transaction hash, `None` where the sender does not report it
+    #[serde(default)]
+    pub tx_hash: Option<String>,
+    #[serde(with = "serde_utils::system_time")]
+    #[schemars(with = "u64")]
+    pub started_at: SystemTime,
+    #[serde(default, with = "serde_utils::opt_system_time")]
+    #[schemars(with = "Option<u64>")]
+    pub finished_at: Option<SystemTime>,
+}
+
+/// Persists the latest transactions across worker restarts.
+pub struct Tracker {
+    path: PathBuf,
+    entries: VecDeque<Transaction>,
+}
+
+impl Tracker {
+    pub fn load(path: PathBuf) -> Self {
+        let mut entries: VecDeque<Transaction> = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|error| {
+                tracing::warn!(?error, ?path, "discarding unreadable transactions file");
+                VecDeque::new()
+            }),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => VecDeque::new(),
+            Err(error) => {
+                tracing::warn!(?error, ?path, "unable to read transactions file");
+                VecDeque::new()
+            }
+        };
+        // whoever waited for these is gone
+        for tx in entries.iter_mut().filter(|tx| tx.status == Status::Pending) {
+            tx.status = Status::Interrupted;
+        }
+        Self { path, entries }
+    }
+
+    /// Newest first.
+    pub fn list(&self) -> Vec<Transaction> {
+        self.entries.iter().rev().cloned().collect()
+    }
+
+    pub fn started(&mut self, kind: Kind, amount: Option<String>, now: SystemTime) {
+        self.push(Transaction {
+            kind,
+            status: Status::Pending,
+            amount,
+            target: None,
+            tx_hash: None,
+            started_at: now,
+            finished_at: None,
+        });
+    }
+
+    /// Completes the pending transaction of `kind`, `target` is filled in if given.
+    pub fn confirmed(&mut self, kind: Kind, target: Option<String>, now: SystemTime) {
+        self.finish(kind, Status::Confirmed, target, now);
+    }
+
+    pub fn failed(&mut self, kind: Kind, reason: String, now: SystemTime) {
+        self.finish(kind, Status::Failed { reason }, None, now);
+    }
+
+    /// Records a transaction of a runner that only reports back once it went through.
+    pub fn sent(
+        &mut self,
+        kind: Kind,
+        amount: Option<String>,
+        target: Option<String>,
+        tx_hash: Option<String>,
+        now: SystemTime,
+    ) {
+        self.push(Transaction {
+            kind,
+            status: Status::Confirmed,
+            amount,
+            target,
+            tx_hash,
+            started_at: now,
+            finished_at: Some(now),
+        });
+    }
+
+    fn finish(&mut self, kind: Kind, status: Status, target: Option<String>, now: SystemTime) {
+        let pending = self
+            .entries
+            .iter_mut()
+            .rev()
+            .find(|tx| tx.kind == kind && tx.status == Status::Pending);
+        let Some(tx) = pending else {
+            tracing::warn!(?kind, ?status, "no pending transaction to complete");
+            return;
+        };
+        tx.status = status;
+        tx.target = target.or(tx.target.take());
+        tx.finished_at = Some(now);
+        self.persist();
+    }
+
+    fn push(&mut self, tx: Transaction) {
+        self.entries.push_back(tx);
+        while self.entries.len() > MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.persist();
+    }
+
+    fn persist(&self) {
+        let res = serde_json::to_string(&self.entries)
+            .map_err(std::io::Error::other)
+            .and_then(|content| fs::write(&self.path, content));
+        if let Err(error) = res {
+            tracing::warn!(?error, path = ?self.path, "failed to persist transactions");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[test]
+    fn completes_the_pending_transaction() {
+        let dir = tempdir().unwrap();
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let mut tracker = Tracker::load(dir.path().join(TRANSACTIONS_FILE));
+
+        tracker.started(Kind::SafeDeployment, Some("10 wxHOPR".to_string()), now);
+        tracker.failed(Kind::SafeDeployment, "out of gas".to_string(), now);
+        tracker.started(Kind::SafeDeployment, Some("10 wxHOPR".to_string()), now);
+        tracker.confirmed(Kind::SafeDeployment, Some("0xsafe".to_string()), now);
+
+        let list = tracker.list();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].status, Status::Confirmed);
+        assert_eq!(list[0].target.as_deref(), Some("0xsafe"));
+        assert_eq!(
+            list[1].status,
+            Status::Failed {
+                reason: "out of gas".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn pending_transactions_are_interrupted_on_reload() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(TRANSACTIONS_FILE);
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let mut tracker = Tracker::load(path.clone());
+        tracker.sent(
+            Kind::WxhoprWithdrawal,
+            Some("1 wxHOPR".to_string()),
+            None,
+            Some("0xhash".to_string()),
+            now,
+        );
+        tracker.started(Kind::SafeDeployment, None, now);
+
+        let reloaded = Tracker::load(path);
+        let list = reloaded.list();
+        let statuses: Vec<Status> = list.iter().map(|tx| tx.status.clone()).collect();
+        assert_eq!(statuses, vec![Status::Interrupted, Status::Confirmed]);
+        assert_eq!(list[1].tx_hash.as_deref(), Some("0xhash"));
+    }
+
+    #[test]
+    fn keeps_the_latest_entries() {
+        let dir = tempdir().unwrap();
+        let now = SystemTime::UNIX_EPOCH;
+        let mut tracker = Tracker::load(dir.path().join(TRANSACTIONS_FILE));
+        for i in 0..MAX_ENTRIES + 5 {
+            tracker.sent(Kind::ChannelFunding, Some(i.to_string()), None, None, now);
+        }
+        let list = tracker.list();
+        assert_eq!(list.len(), MAX_ENTRIES);
+        assert_eq!(list[0].amount, Some((MAX_ENTRIES + 4).to_string()));
+    }
+}
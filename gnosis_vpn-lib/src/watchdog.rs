@@ -0,0 +1,79 @@
+//! Detection of a stalled core event loop.
+//!
+//! The core loop beats a [`Heartbeat`] every [`HEARTBEAT_INTERVAL`] as long as it gets to poll its
+//! event sources. A handler that never returns stops the beats and [`stalled`] resolves once no beat
+//! was seen for [`STALL_TIMEOUT`], so the worker can abort and re-initialize the core instead of
+//! requiring a service restart.
+use tokio::time::{self, Instant};
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+/// Well above anything a single handler may legitimately block the loop, shutdown included.
+pub const STALL_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Clone, Debug)]
+pub struct Heartbeat(Arc<Mutex<Instant>>);
+
+impl Heartbeat {
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(Instant::now())))
+    }
+
+    pub fn beat(&self) {
+        if let Ok(mut last) = self.0.lock() {
+            *last = Instant::now();
+        }
+    }
+
+    pub fn since_last_beat(&self) -> Duration {
+        self.0.lock().map(|last| last.elapsed()).unwrap_or_default()
+    }
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Resolves with the time since the last beat once the heartbeat stalled.
+pub async fn stalled(heartbeat: &Heartbeat) -> Duration {
+    let mut check = time::interval(HEARTBEAT_INTERVAL);
+    loop {
+        check.tick().await;
+        let since = heartbeat.since_last_beat();
+        if since >= STALL_TIMEOUT {
+            return since;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn beating_heartbeat_never_stalls() {
+        let heartbeat = Heartbeat::new();
+        let beating = heartbeat.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(HEARTBEAT_INTERVAL);
+            loop {
+                interval.tick().await;
+                beating.beat();
+            }
+        });
+        let res = time::timeout(STALL_TIMEOUT * 5, stalled(&heartbeat)).await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn missing_beats_are_detected() {
+        let heartbeat = Heartbeat::new();
+        let since = stalled(&heartbeat).await;
+        assert!(since >= STALL_TIMEOUT);
+        assert!(since < STALL_TIMEOUT + HEARTBEAT_INTERVAL);
+    }
+}
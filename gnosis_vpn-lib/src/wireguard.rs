@@ -1,17 +1,25 @@
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tokio::io::AsyncWriteExt;
-use tokio::process::Command;
+use wireguard_control::Key;
 
 use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
 use std::{io, string};
 
 use crate::dirs;
-use crate::shell_command_ext::{self, Logs, ShellCommandExt};
+use crate::shell_command_ext;
 
 pub const WG_INTERFACE: &str = "wg0_gnosisvpn";
 pub const WG_CONFIG_FILE: &str = "wg0_gnosisvpn.conf";
 pub const WG_MTU: u32 = 1420;
+/// Smallest MTU taken from an exit, the IPv6 minimum.
+pub const MIN_WG_MTU: u32 = 1280;
+/// Tunnel resolvers unless configured otherwise or announced by the exit.
+pub const DEFAULT_DNS: &str = "1.1.1.1,8.8.8.8";
+/// Routing protocol id marking routes owned by gnosisvpn, unassigned in `/etc/iproute2/rt_protos`.
+pub const ROUTE_PROTOCOL: u8 = 152;
+/// The original `/etc/resolv.conf`, file or symlink, is moved here while the `file` DNS strategy is applied.
+pub const RESOLV_CONF_BACKUP: &str = "/etc/resolv.conf.gnosisvpn";
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -21,8 +29,8 @@ pub enum Error {
     FromUtf8Error(#[from] string::FromUtf8Error),
     #[error("TOML serialization error: {0}")]
     Toml(#[from] toml::ser::Error),
-    #[error("error generating wg key")]
-    WgGenKey,
+    #[error("invalid wg private key")]
+    InvalidKey,
     #[error("Dirs error: {0}")]
     Dirs(#[from] dirs::Error),
     #[error("Shell command error: {0}")]
@@ -38,6 +46,9 @@ pub struct WireGuard {
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct InterfaceInfo {
     pub address: String,
+    /// Tunnel MTU, [`WG_MTU`] unless the exit announced a smaller one of at least [`MIN_WG_MTU`]
+    #[serde(default)]
+    pub mtu: Option<u32>,
 }
 
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
@@ -68,6 +79,69 @@ pub struct Config {
     pub force_private_key: Option<String>,
     pub allowed_ips: Option<String>,
     pub dns: Option<String>,
+    #[serde(default)]
+    pub dns_strategy: DnsStrategy,
+    #[serde(default)]
+    pub tooling: Tooling,
+    #[serde(default)]
+    pub block_ipv6: BlockIpv6,
+}
+
+/// IPv6 leak prevention while the tunnel is up, the tunnel itself only carries IPv4.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockIpv6 {
+    /// Blackhole IPv6 if the host has it enabled, failing to do so only warns
+    #[default]
+    Auto,
+    /// Blackhole IPv6 and fail the connection if that is not possible
+    Always,
+    /// Leave IPv6 untouched, it bypasses the tunnel
+    Never,
+}
+
+/// Who points the system resolver at the tunnel DNS servers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DnsStrategy {
+    /// systemd-resolved if it manages `/etc/resolv.conf`, resolvconf if installed, the file otherwise;
+    /// wg-quick on macOS
+    #[default]
+    Auto,
+    /// `DNS =` line for wg-quick, which needs `resolvconf` on Linux
+    WgQuick,
+    /// Register the servers for the tunnel interface with `resolvconf`
+    Resolvconf,
+    /// Per-link DNS and routing domain of the tunnel interface via the systemd-resolved D-Bus API
+    SystemdResolved,
+    /// Replace `/etc/resolv.conf` while connected and restore it afterwards
+    File,
+    /// Leave the system resolver untouched
+    None,
+}
+
+impl DnsStrategy {
+    /// Whether wg-quick is left to configure DNS, the root process applies every other strategy itself.
+    pub fn uses_wg_quick(self) -> bool {
+        match self {
+            DnsStrategy::WgQuick => true,
+            DnsStrategy::Auto => cfg!(target_os = "macos"),
+            DnsStrategy::Resolvconf | DnsStrategy::SystemdResolved | DnsStrategy::File | DnsStrategy::None => false,
+        }
+    }
+}
+
+/// Interface tool and hook commands, only ever executed by the root process.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Tooling {
+    /// Replacement for `wg-quick`, must understand `up <file>` and `down <file>`. Setting it keeps
+    /// Linux on wg-quick instead of netlink.
+    pub wg_quick: Option<PathBuf>,
+    /// Shell commands run before and after the interface goes up or down
+    pub pre_up: Option<String>,
+    pub post_up: Option<String>,
+    pub pre_down: Option<String>,
+    pub post_down: Option<String>,
 }
 
 impl Config {
@@ -76,56 +150,31 @@ impl Config {
         allowed_ips: Option<String>,
         force_private_key: Option<String>,
         dns: Option<String>,
+        dns_strategy: DnsStrategy,
+        tooling: Tooling,
+        block_ipv6: BlockIpv6,
     ) -> Self {
         Config {
             listen_port,
             allowed_ips,
             force_private_key,
             dns,
+            dns_strategy,
+            tooling,
+            block_ipv6,
         }
     }
 }
 
-pub async fn available() -> Result<(), Error> {
-    let out = Command::new("which")
-        .arg("wg")
-        .run_stdout(Logs::Print)
-        .await
-        .map_err(Error::from)?;
-    tracing::debug!(at = %out, "wg command available");
-    Ok(())
-}
-
-pub async fn executable() -> Result<(), Error> {
-    Command::new("wg")
-        .arg("--version")
-        .spawn_no_capture()
-        .await
-        .map_err(Error::from)
-}
-
-async fn generate_key() -> Result<String, Error> {
-    Command::new("wg")
-        .arg("genkey")
-        .run_stdout(Logs::Print)
-        .await
-        .map_err(|_| Error::WgGenKey)
-}
-
-async fn public_key(priv_key: &str) -> Result<String, Error> {
-    let mut command = Command::new("wg")
-        .arg("pubkey")
-        .stdin(std::process::Stdio::piped()) // Enable piping to stdin
-        .stdout(std::process::Stdio::piped()) // Capture stdout
-        .spawn()?;
-
-    if let Some(stdin) = command.stdin.as_mut() {
-        stdin.write_all(priv_key.as_bytes()).await?
+impl Tooling {
+    pub fn wg_quick(&self) -> &Path {
+        self.wg_quick.as_deref().unwrap_or(Path::new("wg-quick"))
     }
+}
 
-    let cmd_debug = format!("{:?}", command);
-    let output = command.wait_with_output().await?;
-    shell_command_ext::stdout_from_output(cmd_debug, output, Logs::Print).map_err(|_| Error::WgGenKey)
+fn public_key(priv_key: &str) -> Result<String, Error> {
+    let key = Key::from_base64(priv_key.trim()).map_err(|_| Error::InvalidKey)?;
+    Ok(key.get_public().to_base64())
 }
 
 impl WireGuard {
@@ -136,9 +185,9 @@ impl WireGuard {
     pub async fn from_config(config: Config) -> Result<Self, Error> {
         let priv_key = match config.force_private_key.clone() {
             Some(key) => key,
-            None => generate_key().await?,
+            None => Key::generate_private().to_base64(),
         };
-        let public_key = public_key(&priv_key).await?;
+        let public_key = public_key(&priv_key)?;
         let key_pair = KeyPair { priv_key, public_key };
         Ok(WireGuard { config, key_pair })
     }
@@ -156,8 +205,10 @@ impl WireGuard {
         lines.push("[Interface]".to_string());
         lines.push(format!("PrivateKey = {}", self.key_pair.priv_key));
         lines.push(format!("Address = {}", interface.address));
-        lines.push(format!("MTU = {WG_MTU}"));
-        if let Some(dns) = &self.config.dns {
+        lines.push(format!("MTU = {}", interface.mtu.unwrap_or(WG_MTU)));
+        if let Some(dns) = &self.config.dns
+            && self.config.dns_strategy.uses_wg_quick()
+        {
             lines.push(format!("DNS = {dns}"));
         }
         if let Some(listen_port) = self.config.listen_port {
@@ -165,29 +216,6 @@ impl WireGuard {
         }
         lines.extend(extra_interface_lines);
 
-        // Blackhold Ipv6 traffic for now.
-        // Contrary to routing exceptions this happens in preup and postdown
-        // To avoid leakage and because those are global rules
-        #[cfg(target_os = "linux")]
-        {
-            // we cannot handle IPv6 yet, so blackhole it for now, make it idempotent to avoid wg-quick stopping because of errors
-            lines.push("PreUp = ip -6 route del blackhole ::/1 || true".to_string());
-            lines.push("PreUp = ip -6 route del blackhole 8000::/1 || true".to_string());
-            lines.push("PreUp = ip -6 route add blackhole ::/1".to_string());
-            lines.push("PreUp = ip -6 route add blackhole 8000::/1".to_string());
-            lines.push("PostDown = ip -6 route del blackhole ::/1 || true".to_string());
-            lines.push("PostDown = ip -6 route del blackhole 8000::/1 || true".to_string());
-        }
-        #[cfg(target_os = "macos")]
-        {
-            // on macos to avoid fighting router specific rules we split the range in two
-            // this way the routes are more specific and take precedence over other rules
-            lines.push("PreUp = route -n add -blackhole -inet6 ::/1 ::1".to_string());
-            lines.push("PreUp = route -n add -blackhole -inet6 8000::/1 ::1".to_string());
-            lines.push("PostDown = route -n delete -blackhole -inet6 ::/1 ::1".to_string());
-            lines.push("PostDown = route -n delete -blackhole -inet6 8000::/1 ::1".to_string());
-        }
-
         lines.push("".to_string()); // Empty line for spacing
 
         // [Peer] section
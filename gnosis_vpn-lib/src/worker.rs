@@ -33,6 +33,7 @@ pub struct Input {
     binary: PathBuf,
     version: String,
     state_home: PathBuf,
+    cache_home: PathBuf,
 }
 
 pub const USERNAME: &str = "gnosisvpn";
@@ -54,12 +55,13 @@ pub struct Worker {
 }
 
 impl Input {
-    pub fn new(user: String, binary: PathBuf, version: &str, state_home: PathBuf) -> Self {
+    pub fn new(user: String, binary: PathBuf, version: &str, state_home: PathBuf, cache_home: PathBuf) -> Self {
         Self {
             user,
             binary,
             version: version.to_string(),
             state_home,
+            cache_home,
         }
     }
 }
@@ -106,7 +108,7 @@ impl Worker {
         let version = version_output.split_whitespace().nth(1).unwrap_or_default();
         if version == input.version {
             // set up application state directory
-            dirs::setup_home(input.state_home.clone(), uid, gid).map_err(|error| {
+            dirs::setup_home(input.state_home.clone(), input.cache_home, uid, gid).map_err(|error| {
                 eprintln!("Error setting up home directory for {worker_user:?}: {error:?}");
                 Error::InvalidHomeDir
             })?;
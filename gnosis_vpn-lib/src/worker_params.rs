@@ -12,6 +12,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::compat::SafeModule;
+use crate::dirs;
 use crate::hopr::blokli_config::BlokliConfig;
 use crate::hopr::{config, identity};
 
@@ -38,6 +39,7 @@ pub struct WorkerParams {
     allow_experimental: bool,
     blokli_url: Option<Url>,
     state_home: PathBuf,
+    cache_home: PathBuf,
     cached_blokli_ips: Vec<Ipv4Addr>,
 }
 
@@ -55,7 +57,7 @@ impl WorkerParams {
         allow_insecure: bool,
         allow_experimental: bool,
         blokli_url: Option<Url>,
-        state_home: PathBuf,
+        locations: dirs::Locations,
     ) -> Self {
         Self {
             identity_file,
@@ -64,7 +66,8 @@ impl WorkerParams {
             allow_insecure,
             allow_experimental,
             blokli_url,
-            state_home,
+            state_home: locations.state_home,
+            cache_home: locations.cache_home,
             cached_blokli_ips: Vec::new(),
         }
     }
@@ -77,6 +80,13 @@ impl WorkerParams {
         &self.cached_blokli_ips
     }
 
+    /// Identity file in use, either provided or generated inside the state directory.
+    pub fn identity_file(&self) -> PathBuf {
+        self.identity_file
+            .clone()
+            .unwrap_or_else(|| identity::file(self.state_home()))
+    }
+
     pub async fn persist_identity_generation(&self) -> Result<HoprKeys, Error> {
         let identity_file = match &self.identity_file {
             Some(path) => {
@@ -205,6 +215,10 @@ impl WorkerParams {
     pub fn state_home(&self) -> PathBuf {
         self.state_home.clone()
     }
+
+    pub fn cache_home(&self) -> PathBuf {
+        self.cache_home.clone()
+    }
 }
 
 fn log_path_diagnostics(path: &std::path::Path) {
@@ -19,6 +19,7 @@ pub fn create_test_wg_data(interface_addr: &str, peer_endpoint: &str) -> WireGua
         wg,
         interface_info: InterfaceInfo {
             address: interface_addr.to_string(),
+            mtu: None,
         },
         peer_info: PeerInfo {
             public_key: "peer_key".to_string(),
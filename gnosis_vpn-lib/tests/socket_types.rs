@@ -3,12 +3,16 @@
 //! so any type that can't be named here isn't properly exported.
 //! Compilation failure == missing export.
 
+use gnosis_vpn_lib::backoff::{RetryInfo, Task as RetryTask};
 use gnosis_vpn_lib::balance::{BalanceRecommendation, Capacity, CapacityAllocator, CapacityEntry, FundingIssue};
+use gnosis_vpn_lib::budget::{Action as BudgetAction, Usage as BudgetUsage};
 use gnosis_vpn_lib::command::{
     ActiveSession, BalanceResponse, ChannelBalance, ChannelOut, Command, ConnStats, ConnectResponse, ConnectedInfo,
-    ConnectingInfo, DestinationState, DisconnectResponse, DisconnectingInfo, FundingToolResponse, HoprInitStatus,
-    HoprStatus, Info, InfoResponse, NerdStatsResponse, ReconnectingInfo, Response, RouteHealthView, RunMode,
-    StartClientResponse, StatusResponse, StopClientResponse, TicketStats, TicketStatsStatus, WorkerCommand,
+    ConnectingInfo, DestinationState, DisconnectResponse, DisconnectingInfo, FundingToolResponse, GatewayClient,
+    GatewayResponse, HoprInitStatus, HoprStatus, Info, InfoResponse, NerdStatsResponse, NodeIdentity, PeerView,
+    PeersResponse, RateLimitResponse, ReconnectingInfo, Response, RouteHealthView, RunMode, SessionProtocol,
+    SessionRole, SessionView, StartClientResponse, StatusResponse, StopClientResponse, TicketStats, TicketStatsStatus,
+    WorkerCommand,
 };
 use gnosis_vpn_lib::connection::destination::{Address, Destination, HopRouting};
 use gnosis_vpn_lib::connection::{DownPhase, UpPhase};
@@ -33,6 +37,12 @@ fn assert_types_are_accessible() {
     let _: HoprStatus;
     let _: HoprInitStatus;
     let _: InfoResponse;
+    let _: NodeIdentity;
+    let _: PeersResponse;
+    let _: SessionView;
+    let _: SessionRole;
+    let _: SessionProtocol;
+    let _: PeerView;
     let _: StartClientResponse;
     let _: StopClientResponse;
     let _: ConnectResponse;
@@ -45,6 +55,9 @@ fn assert_types_are_accessible() {
     let _: ConnStats;
     let _: ActiveSession;
     let _: BalanceResponse;
+    let _: RateLimitResponse;
+    let _: GatewayResponse;
+    let _: GatewayClient;
     let _: ChannelOut;
     let _: ChannelBalance;
     let _: Info;
@@ -65,6 +78,10 @@ fn assert_types_are_accessible() {
     let _: CapacityEntry;
     let _: CapacityAllocator;
     let _: Capacity;
+    let _: BudgetUsage;
+    let _: BudgetAction;
+    let _: RetryInfo;
+    let _: RetryTask;
 }
 
 #[test]
@@ -5,22 +5,22 @@ use std::path::PathBuf;
 use std::time::Duration;
 
 use gnosis_vpn_lib::worker_params::{self, WorkerParams};
-use gnosis_vpn_lib::{config, dirs, hopr, logging, socket};
+use gnosis_vpn_lib::{backup, config, dirs, hopr, logging, socket};
 
-use crate::{ENV_VAR_PID_FILE, worker};
+use crate::{ENV_VAR_PID_FILE, ENV_VAR_ROUTING_BACKEND, ENV_VAR_ROUTING_MODE, observer, routing, worker};
 
 /// Gnosis VPN system service - client application for Gnosis VPN connections
 #[derive(Clone, Debug, Parser)]
 #[command(version)]
 pub struct Cli {
-    /// Socket path for communication with this service
+    /// Socket path for communication with this service, prefix with `@` for a Linux abstract socket
+    /// [default: `[socket] path` from the configuration file or /var/run/gnosisvpn.sock]
     #[arg(
         short,
         long,
         env = socket::root::ENV_VAR,
-        default_value = socket::root::DEFAULT_PATH
     )]
-    pub socket_path: PathBuf,
+    pub socket_path: Option<PathBuf>,
 
     /// General configuration file
     #[arg(
@@ -32,12 +32,21 @@ pub struct Cli {
     pub config_path: PathBuf,
 
     /// Service state directory - practically identical with home directory of the worker user
+    /// [default: `[dirs] data` from the configuration file, $XDG_DATA_HOME/gnosisvpn or /var/lib/gnosisvpn]
     #[arg(
         long,
+        visible_alias = "data-dir",
         env = dirs::ENV_VAR_STATE_HOME,
-        default_value = dirs::DEFAULT_STATE_HOME,
     )]
-    pub state_home: PathBuf,
+    pub state_home: Option<PathBuf>,
+
+    /// Cache directory for disposable service state
+    /// [default: `[dirs] cache` from the configuration file, $XDG_CACHE_HOME/gnosisvpn or `.cache` in the state directory]
+    #[arg(
+        long,
+        env = dirs::ENV_VAR_CACHE_HOME,
+    )]
+    pub cache_dir: Option<PathBuf>,
 
     /// Log file path - provide if you want the service to manage the log file
     #[arg(
@@ -91,30 +100,57 @@ pub struct Cli {
                 value_parser = humantime::parse_duration
         )]
     pub client_autostart: Option<Duration>,
+
+    /// Route traffic into the tunnel (managed) or only bring up the tunnel interface and leave routing to the
+    /// environment (delegated), e.g. a container sidecar that sets up the pod network itself
+    #[arg(long, env = ENV_VAR_ROUTING_MODE, value_enum, default_value_t = routing::Mode::Managed)]
+    pub routing_mode: routing::Mode,
+
+    /// How managed routing changes the routing table. `auto` uses netlink on Linux unless a startup self-test finds
+    /// its replies unreliable on the running kernel and falls back to iproute2, force either one if that guess is wrong
+    #[arg(long, env = ENV_VAR_ROUTING_BACKEND, value_enum, default_value_t = routing::Backend::Auto)]
+    pub routing_backend: routing::Backend,
+
+    /// Restore node database, identity and safe configuration from a `gnosis_vpn-ctl backup` archive on startup.
+    /// The archive is renamed to `<path>.restored` afterwards so later restarts keep the node state.
+    #[arg(long, env = backup::ENV_VAR_RESTORE, value_name = "PATH")]
+    pub restore_backup: Option<PathBuf>,
+
+    /// Run as read-only observer listening on this socket instead of starting the service.
+    /// Queries are forwarded to the service socket, commands changing state and queries revealing
+    /// secrets or logs are refused.
+    /// Meant for monitoring agents running as a separate unit with membership in the socket group only.
+    #[arg(long, env = observer::ENV_VAR, value_name = "SOCKET")]
+    pub observer: Option<PathBuf>,
 }
 
 pub fn parse() -> Cli {
     Cli::parse()
 }
 
-impl From<&Cli> for WorkerParams {
-    fn from(cli: &Cli) -> Self {
-        let config_mode = match cli.hopr_config_path.clone() {
+impl Cli {
+    /// Directories given explicitly, taking precedence over the configuration file.
+    pub fn dirs(&self) -> dirs::Config {
+        dirs::Config {
+            data: self.state_home.clone(),
+            cache: self.cache_dir.clone(),
+        }
+    }
+
+    pub fn worker_params(&self, locations: dirs::Locations) -> WorkerParams {
+        let config_mode = match self.hopr_config_path.clone() {
             Some(path) => worker_params::ConfigFileMode::Manual(path),
             None => worker_params::ConfigFileMode::Generated,
         };
-        let allow_insecure = cli.allow_insecure;
-        let allow_experimental = cli.allow_experimental;
-        let state_home = cli.state_home.clone();
 
         WorkerParams::new(
-            cli.hopr_identity_file.clone(),
-            cli.hopr_identity_pass.clone(),
+            self.hopr_identity_file.clone(),
+            self.hopr_identity_pass.clone(),
             config_mode,
-            allow_insecure,
-            allow_experimental,
-            cli.hopr_blokli_url.clone(),
-            state_home,
+            self.allow_insecure,
+            self.allow_experimental,
+            self.hopr_blokli_url.clone(),
+            locations,
         )
     }
 }
@@ -137,6 +173,31 @@ mod tests {
     fn parses_cli_with_minimum_arguments() -> anyhow::Result<()> {
         let args = Cli::try_parse_from(base_args())?;
         assert!(args.hopr_config_path.is_none());
+        assert!(args.state_home.is_none());
+        assert_eq!(args.routing_mode, routing::Mode::Managed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parses_delegated_routing_mode() -> anyhow::Result<()> {
+        let mut args = base_args();
+        args.extend(["--routing-mode", "delegated"]);
+        let args = Cli::try_parse_from(args)?;
+        assert_eq!(args.routing_mode, routing::Mode::Delegated);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parses_routing_backend() -> anyhow::Result<()> {
+        assert_eq!(
+            Cli::try_parse_from(base_args())?.routing_backend,
+            routing::Backend::Auto
+        );
+        let mut args = base_args();
+        args.extend(["--routing-backend", "iproute2"]);
+        assert_eq!(Cli::try_parse_from(args)?.routing_backend, routing::Backend::Iproute2);
 
         Ok(())
     }
@@ -1,35 +1,42 @@
 use gnosis_vpn_lib::logging::LogReloadHandle;
+use human_bandwidth::re::bandwidth::Bandwidth;
 use notify::{Event, EventKind, RecursiveMode, Watcher};
 use tokio::fs;
-use tokio::io::{self, AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter, WriteHalf};
+use tokio::io::{self, AsyncWriteExt, BufReader, BufWriter, WriteHalf};
 use tokio::net::unix::OwnedWriteHalf;
 use tokio::net::{UnixListener as TokioUnixListener, UnixStream as TokioUnixStream};
 use tokio::process::Command as TokioCommand;
 use tokio::signal::unix::{SignalKind, signal};
-use tokio::sync::{mpsc, oneshot};
-use tokio::task::{JoinHandle, JoinSet};
+use tokio::sync::{Mutex, broadcast, mpsc, oneshot};
+use tokio::task::{Id as TaskId, JoinError, JoinHandle, JoinSet};
 use tokio::time;
 use tokio_util::sync::CancellationToken;
 
 use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr};
-use std::os::unix::fs::PermissionsExt;
 use std::os::unix::io::{AsRawFd, IntoRawFd};
 use std::os::unix::net::UnixStream;
 use std::path::{Path, PathBuf};
 use std::process::{self};
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use gnosis_vpn_lib::command::{self, Command as LibCommand, Response, WorkerCommand};
 use gnosis_vpn_lib::config::{self, Config};
 use gnosis_vpn_lib::connection::destination::Destination;
 use gnosis_vpn_lib::event::{self, RequestToRoot, ResponseFromRoot, RootToWorker, WorkerToRoot};
 use gnosis_vpn_lib::worker_params::WorkerParams;
-use gnosis_vpn_lib::{dirs, logging, ping, socket, worker};
+use gnosis_vpn_lib::{
+    backup, cleanup, conflicts, crash, diagnostics, dirs, disk_space, exit_reports, hopr, i18n, log_buffer, logging,
+    management, metrics, ping, proxy, public_ip, socket, telemetry, tls, wireguard, worker,
+};
 
 mod cli;
 mod device_monitor;
+mod network_gate;
 mod network_info;
+mod observer;
+mod remote;
 mod routing;
 mod routing_actor;
 mod wg_tooling;
@@ -41,25 +48,43 @@ mod wg_tooling;
 static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
 pub const ENV_VAR_PID_FILE: &str = "GNOSISVPN_PID_FILE";
+pub const ENV_VAR_ROUTING_MODE: &str = "GNOSISVPN_ROUTING_MODE";
+pub const ENV_VAR_ROUTING_BACKEND: &str = "GNOSISVPN_ROUTING_BACKEND";
+
+/// How long root waits for the worker to exit on service shutdown before giving up on it.
+/// Leaves the worker enough room to hit its own hopr shutdown deadline first.
+const WORKER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(gnosis_vpn_lib::core::SHUTDOWN_TIMEOUT.as_secs() + 5);
+
+/// Gap between empty batches on an idle `logs --follow` stream, well below the client idle timeout.
+const LOG_FOLLOW_KEEPALIVE: Duration = Duration::from_secs(30);
 
 struct DaemonState {
     worker_user: worker::Worker,
+    // configuration handed to the worker - the local one restricted by the management policy
     config: Config,
+    // configuration as read from the config file
+    local_config: Config,
     config_path: PathBuf,
+    // control socket as bound on startup, inspected by `doctor`
+    socket_path: PathBuf,
     log_file: Option<PathBuf>,
     worker_params: WorkerParams,
     reload_handle: Option<LogReloadHandle>,
     shutdown_ongoing: Shutdown,
+    // set once service shutdown waits on the worker - root exits with teardown when it passes
+    shutdown_deadline: Option<time::Instant>,
     // keep track of the current target for restore/restart/reload logic
     target_dest_id: Option<String>,
     // used to forward messages incoming on unix socket to worker process
-    incoming_worker_channel: (mpsc::Sender<String>, mpsc::Receiver<String>),
+    incoming_worker_channel: (mpsc::Sender<WorkerToRoot>, mpsc::Receiver<WorkerToRoot>),
     // optional worker paramters set after construction
     worker_child: Option<WorkerChild>,
     // status code channel for when the worker process exits
     worker_exit_channel: (mpsc::Sender<process::ExitStatus>, mpsc::Receiver<process::ExitStatus>),
     // keep track of longer running root tasks
     ping_tasks: JoinSet<(u64, Result<Duration, String>)>,
+    public_ip_tasks: JoinSet<(u64, Result<public_ip::PublicIp, String>)>,
+    exit_report_tasks: JoinSet<(u64, Result<(), String>)>,
     // External socket commands need an internal mapping:
     // root process will keep track of worker requests and map their responses
     // so that the requesting stream on the socket receives it's answer
@@ -68,6 +93,65 @@ struct DaemonState {
     // keepalive instructions from service to timer loop
     keep_alive_instruction_sender: mpsc::Sender<KeepAliveInstruction>,
     routing_actor_sender: mpsc::Sender<routing_actor::Msg>,
+    // egress rate limit on the tunnel, initialized from config and overridable via socket command
+    rate_limit: Option<Bandwidth>,
+    // latest crash report found on startup
+    previous_crash: Option<crash::Summary>,
+    // managed mode, only set when configured
+    management: Option<Management>,
+    // fetched management policies, the sender is handed to every policy watcher
+    management_channel: (mpsc::Sender<PolicyResult>, mpsc::Receiver<PolicyResult>),
+    // exported alongside the worker metrics
+    socket_requests: u64,
+    core_stalls: u64,
+    routing_stats: Arc<routing::Stats>,
+    // backup waiting for the worker to exit, with the uid owning the archive
+    pending_backup: Option<(PathBuf, u32, oneshot::Sender<Response>)>,
+    // backups and compactions running off the main loop, a paused worker is restarted once they finished
+    maintenance_tasks: JoinSet<()>,
+    // management policy watch, report and crash uploads, nothing waits for their outcome
+    background_tasks: JoinSet<()>,
+    // the running compaction among the maintenance tasks, only one rebuilds the databases at a time
+    compaction_task: Option<TaskId>,
+    // allowlist of the remote listener, shared so clients can be unpaired from the local socket
+    paired_clients: Arc<Mutex<socket::remote::PairedClients>>,
+    // latest free space sample of the data directory, sampled on every tick
+    disk_space: Option<disk_space::Usage>,
+    disk_space_check: time::Interval,
+    disk_space_tasks: JoinSet<Result<disk_space::Usage, disk_space::Error>>,
+    // compaction waiting for the worker to exit
+    pending_compaction: Option<Compaction>,
+    // automatic compaction is only attempted once per low space episode
+    compacted: bool,
+    // who owns the routes into the tunnel, other VPN clients only get in the way of managed routing
+    routing_mode: routing::Mode,
+    // other VPN clients found on startup or before the latest route setup
+    conflicts: Vec<conflicts::Conflict>,
+    // set once the network gate passed or timed out, the worker is only held back on its first start
+    network_online: bool,
+    // start of the running network gate
+    waiting_for_network: Option<SystemTime>,
+    // worker start requested while the network gate is still running
+    worker_start_pending: bool,
+    network_gate_channel: (
+        mpsc::Sender<network_gate::Outcome>,
+        mpsc::Receiver<network_gate::Outcome>,
+    ),
+}
+
+type PolicyResult = Result<(management::Signed, management::Policy), management::Error>;
+
+struct Management {
+    cache: management::Cache,
+    // last verified policy, fetched or loaded from cache
+    policy: Option<management::Policy>,
+    cancel: CancellationToken,
+}
+
+enum Compaction {
+    Requested(oneshot::Sender<Response>),
+    LowDiskSpace,
+    Scheduled,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -85,12 +169,16 @@ enum SignalMessage {
 
 struct SocketCmd {
     cmd: LibCommand,
+    // uid of a client on the local socket, remote clients have none
+    peer_uid: Option<u32>,
     resp: oneshot::Sender<Response>,
 }
 
 struct WorkerChild {
     socket_writer: BufWriter<WriteHalf<TokioUnixStream>>,
     cancel: CancellationToken,
+    // used to SIGKILL the worker when it does not react to a shutdown request
+    pid: Option<u32>,
 }
 
 #[derive(Debug)]
@@ -160,17 +248,27 @@ async fn incoming_on_root_socket(
     stream: TokioUnixStream,
     socket_cmd_sender: mpsc::Sender<SocketCmd>,
 ) -> Option<JoinHandle<()>> {
+    let peer_uid = stream.peer_cred().ok().map(|cred| cred.uid());
     let (socket_reader_half, socket_writer_half) = stream.into_split();
-    let socket_reader = BufReader::new(socket_reader_half);
-    let res_line = socket_reader.lines().next_line().await;
+    let mut socket_reader = BufReader::new(socket_reader_half);
+    let res_line = socket::root::read_message(&mut socket_reader, socket::root::Limits::REQUEST).await;
     match res_line {
-        Ok(Some(line)) => {
+        Ok(line) if line.is_empty() => {
+            tracing::warn!("socket connection closed by peer");
+        }
+        Ok(line) => {
             let res_decode = serde_json::from_str::<LibCommand>(&line);
             match res_decode {
                 Ok(cmd) => {
                     tracing::debug!(command = ?cmd, "received socket command");
+                    // subscribe before the recent lines are taken so none fall in between
+                    let follow = matches!(cmd, LibCommand::Logs { follow: true, .. }).then(log_buffer::subscribe);
                     let (resp_sender, resp_receiver) = oneshot::channel();
-                    let socket_cmd = SocketCmd { cmd, resp: resp_sender };
+                    let socket_cmd = SocketCmd {
+                        cmd,
+                        peer_uid,
+                        resp: resp_sender,
+                    };
                     if let Err(err) = socket_cmd_sender.send(socket_cmd).await {
                         tracing::error!(error = ?err, "failed to send socket command to main loop");
                         return None;
@@ -182,6 +280,8 @@ async fn incoming_on_root_socket(
                                 let mut writer = BufWriter::new(socket_writer_half);
                                 if let Err(err) = send_to_socket(&resp, &mut writer).await {
                                     tracing::error!(error = ?err, "failed to send response to socket");
+                                } else if let Some(lines) = follow {
+                                    follow_logs(lines, &mut writer).await;
                                 }
                             }
                             Err(err) => {
@@ -196,19 +296,101 @@ async fn incoming_on_root_socket(
                 }
             }
         }
-        Ok(None) => {
-            tracing::warn!("socket connection closed by peer");
-        }
         Err(err) => {
-            tracing::error!(error = ?err, "error reading from socket");
+            tracing::error!(error = %err, "error reading from socket");
         }
     };
     None
 }
 
+/// Stream new log lines to a `logs --follow` client until it disconnects.
+///
+/// Lines are sent in batches of whatever arrived meanwhile, an empty batch keeps an idle
+/// connection within the client's read timeout and detects a client that went away.
+async fn follow_logs(mut lines: broadcast::Receiver<String>, writer: &mut BufWriter<OwnedWriteHalf>) {
+    loop {
+        let mut batch = match time::timeout(LOG_FOLLOW_KEEPALIVE, lines.recv()).await {
+            Ok(Ok(line)) => vec![line],
+            Ok(Err(broadcast::error::RecvError::Lagged(skipped))) => vec![format!("... {skipped} lines skipped")],
+            Ok(Err(broadcast::error::RecvError::Closed)) => return,
+            Err(_) => vec![],
+        };
+        while let Ok(line) = lines.try_recv() {
+            batch.push(line);
+        }
+        let Ok(serialized) = serde_json::to_string(&Response::Logs(batch)) else {
+            return;
+        };
+        // not logged at error level like other responses, every written error line would be streamed again
+        if let Err(err) = async {
+            writer.write_all(serialized.as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+            writer.flush().await
+        }
+        .await
+        {
+            tracing::debug!(error = ?err, "log follower disconnected");
+            return;
+        }
+    }
+}
+
 async fn socket_listener(
     socket_path: &Path,
-) -> Result<(CancellationToken, mpsc::Receiver<SocketCmd>), exitcode::ExitCode> {
+    socket_config: &socket::root::Config,
+    sender: mpsc::Sender<SocketCmd>,
+) -> Result<CancellationToken, exitcode::ExitCode> {
+    let listener = match socket::root::abstract_name(socket_path) {
+        Some(name) => {
+            tracing::warn!(%name, "abstract socket ignores group and mode - every process in this network namespace can control the service");
+            socket::root::bind(socket_path).map_err(|e| {
+                if e.kind() == std::io::ErrorKind::AddrInUse {
+                    tracing::error!(%name, "system service is already running - cannot start another instance");
+                    exitcode::TEMPFAIL
+                } else {
+                    tracing::error!(error = ?e, "error binding abstract socket");
+                    exitcode::OSFILE
+                }
+            })?
+        }
+        None => bind_socket_file(socket_path, socket_config).await?,
+    };
+
+    let mut ongoing = JoinSet::new();
+    let cancel = CancellationToken::new();
+    let owned_cancel = cancel.clone();
+    tokio::spawn(async move {
+        loop {
+            let cloned_sender = sender.clone();
+            tokio::select! {
+                Ok((stream, _addr)) = listener.accept() => {
+                    ongoing.spawn(async move {
+                        if let Some(handle) = incoming_on_root_socket(stream, cloned_sender).await {
+                            handle.await.ok();
+                        }
+                    });
+                },
+                _ = cancel.cancelled() => {
+                    tracing::debug!("socket listener received cancellation");
+                    ongoing.shutdown().await;
+                    break;
+                }
+                else => {
+                    tracing::warn!("socket listener streams closed");
+                    break;
+                }
+
+            }
+        }
+    });
+
+    Ok(owned_cancel)
+}
+
+async fn bind_socket_file(
+    socket_path: &Path,
+    socket_config: &socket::root::Config,
+) -> Result<TokioUnixListener, exitcode::ExitCode> {
     match socket_path.try_exists() {
         Ok(true) => {
             tracing::info!("probing for running instance");
@@ -245,44 +427,13 @@ async fn socket_listener(
         exitcode::OSFILE
     })?;
 
-    // update permissions to allow unprivileged access
-    fs::set_permissions(socket_path, std::fs::Permissions::from_mode(0o666))
-        .await
-        .map_err(|e| {
-            tracing::error!(error = ?e, "error setting socket permissions");
-            exitcode::NOPERM
-        })?;
-
-    let mut ongoing = JoinSet::new();
-    let cancel = CancellationToken::new();
-    let owned_cancel = cancel.clone();
-    let (sender, receiver) = mpsc::channel(32);
-    tokio::spawn(async move {
-        loop {
-            let cloned_sender = sender.clone();
-            tokio::select! {
-                Ok((stream, _addr)) = listener.accept() => {
-                    ongoing.spawn(async move {
-                        if let Some(handle) = incoming_on_root_socket(stream, cloned_sender).await {
-                            handle.await.ok();
-                        }
-                    });
-                },
-                _ = cancel.cancelled() => {
-                    tracing::debug!("socket listener received cancellation");
-                    ongoing.shutdown().await;
-                    break;
-                }
-                else => {
-                    tracing::warn!("socket listener streams closed");
-                    break;
-                }
-
-            }
-        }
-    });
+    // grant access to members of the configured group
+    socket::root::restrict(socket_path, socket_config).map_err(|e| {
+        tracing::error!(error = ?e, "error setting socket permissions");
+        exitcode::NOPERM
+    })?;
 
-    Ok((owned_cancel, receiver))
+    Ok(listener)
 }
 
 pub async fn config_watcher(
@@ -426,14 +577,79 @@ async fn keep_alive_timer(
     Ok((owned_cancel, receiver))
 }
 
+/// Compacts the node databases and records the time in `marker`, the worker must not be running.
+async fn compact(
+    state_home: &Path,
+    marker: &Path,
+    log_file: Option<&Path>,
+    paused_worker: bool,
+) -> Result<command::CompactResponse, String> {
+    let res = disk_space::compact(state_home, log_file).await;
+    if let Err(error) = fs::write(marker, b"").await {
+        tracing::warn!(%error, "unable to record compaction time");
+    }
+    match res {
+        Ok(reclaimed) => {
+            tracing::info!(?reclaimed, "compacted node database");
+            Ok(command::CompactResponse {
+                databases: reclaimed.databases,
+                skipped_databases: reclaimed.skipped,
+                rotated_logs: reclaimed.rotated_logs,
+                reclaimed_bytes: reclaimed.bytes,
+                paused_worker,
+            })
+        }
+        Err(error) => {
+            tracing::warn!(%error, "unable to compact node database");
+            Err(error.to_string())
+        }
+    }
+}
+
+/// Pins would silently not apply to the chain client of the HOPR node, it only receives the CA bundle.
+fn install_tls(config: &Config, worker_params: &WorkerParams) -> Result<(), tls::Error> {
+    let chain_url = hopr::blokli_url(worker_params.blokli_url());
+    if let Some(host) = chain_url.host_str() {
+        config.network.tls.refuse_pins_for(host)?;
+    }
+    tls::install(&config.network.tls)
+}
+
+/// Moves state and cache left at their default locations into the configured ones.
+fn migrate_legacy_dirs(worker_params: &WorkerParams, worker_user: &worker::Worker) {
+    let state_home = worker_params.state_home();
+    let cache_home = worker_params.cache_home();
+    let legacy_state_home = PathBuf::from(dirs::DEFAULT_STATE_HOME);
+    let legacy_cache_home = dirs::default_cache_home(&state_home);
+
+    for (from, to) in [(legacy_state_home, state_home), (legacy_cache_home, cache_home)] {
+        // nested locations would be moved into themselves
+        if to.starts_with(&from) || from.starts_with(&to) || !from.is_dir() {
+            continue;
+        }
+        match dirs::migrate(&from, &to, worker_user.uid, worker_user.gid) {
+            Ok(0) => (),
+            Ok(moved) => tracing::info!(from = %from.display(), to = %to.display(), moved, "migrated directory"),
+            Err(error) => {
+                tracing::warn!(%error, from = %from.display(), to = %to.display(), "unable to migrate directory")
+            }
+        }
+    }
+}
+
 async fn daemon(args: cli::Cli) -> Result<(), exitcode::ExitCode> {
+    // logging is not set up yet, unsupported keys are reported once the full configuration is read
+    let configured_dirs = config::read_dirs(&args.config_path).await;
+    let locations = dirs::Locations::resolve(args.dirs(), &configured_dirs, |var| std::env::var(var).ok());
+    let worker_params = args.worker_params(locations);
+
     // ensure worker user exists
-    let worker_params = WorkerParams::from(&args);
     let input = worker::Input::new(
         args.worker_user.clone(),
         args.worker_binary.clone(),
         env!("CARGO_PKG_VERSION"),
         worker_params.state_home(),
+        worker_params.cache_home(),
     );
     let worker_user = worker::Worker::from_system(input).await.map_err(|error| {
         eprintln!("error determining worker user: {:?}", error);
@@ -447,25 +663,53 @@ async fn daemon(args: cli::Cli) -> Result<(), exitcode::ExitCode> {
     tracing::info!(
         version = env!("CARGO_PKG_VERSION"),
         state_home = %worker_params.state_home().display(),
+        cache_home = %worker_params.cache_home().display(),
         "starting {}",
         env!("CARGO_PKG_NAME")
     );
 
+    migrate_legacy_dirs(&worker_params, &worker_user);
+
+    // restore before anything reads the state home, a broken archive must not start an empty node
+    if let Some(ref archive) = args.restore_backup {
+        match backup::restore(archive, &worker_params.state_home(), worker_user.uid, worker_user.gid).await {
+            Ok(restored) => tracing::info!(archive = %restored.display(), "restored state from backup"),
+            Err(error) => {
+                tracing::error!(%error, archive = %archive.display(), "unable to restore backup");
+                return Err(exitcode::DATAERR);
+            }
+        }
+    }
+
+    let root_home = dirs::root_home(&worker_params.state_home());
+    dirs::ensure_private_dir(&root_home).map_err(|error| {
+        tracing::error!(?error, dir = %root_home.display(), "unable to set up root only directory");
+        exitcode::CANTCREAT
+    })?;
+
+    // root keeps its crash reports out of reach of the worker, which writes its own to its cache home
+    let crash_dir = root_home.join(crash::DIR_NAME);
+    if let Err(error) = dirs::ensure_private_dir(&crash_dir) {
+        tracing::warn!(%error, "unable to create crash report directory");
+    }
+    crash::set_dir(crash_dir.clone());
+    let worker_crash_dir = dirs::cache_dir(worker_params.cache_home(), crash::DIR_NAME);
+    if let Err(error) = dirs::ensure_dir(worker_crash_dir.clone(), 0o700, worker_user.uid, worker_user.gid) {
+        tracing::warn!(%error, "unable to create worker crash report directory");
+    }
+    let mut crash_reports = crash::take_reports(&crash_dir);
+    crash_reports.extend(crash::take_reports(&worker_crash_dir));
+    crash_reports.sort_by_key(|(_, report)| report.time);
+
+    // a crash while connected may have left the tunnel servers in place of the host's resolv.conf
+    routing::dns::restore_leftover().await;
+
     let network_info = network_info::NetworkInfo::gather().await;
     tracing::info!(%network_info, "host network info");
 
     // Write root pidfile for the newsyslog service to send signals to
     write_pidfile(&args.pid_file).await?;
 
-    // check wireguard tooling
-    wg_tooling::available()
-        .await
-        .and(wg_tooling::executable().await)
-        .map_err(|err| {
-            tracing::error!(error = ?err, "error checking WireGuard tools");
-            exitcode::UNAVAILABLE
-        })?;
-
     // prepare worker resources
     let config_path = match args.config_path.canonicalize() {
         Ok(path) => path,
@@ -479,13 +723,53 @@ async fn daemon(args: cli::Cli) -> Result<(), exitcode::ExitCode> {
         tracing::error!(error = ?err, "unable to read initial configuration file");
         exitcode::NOINPUT
     })?;
+    install_tls(&config, &worker_params).map_err(|err| {
+        tracing::error!(error = %err, "unusable TLS trust settings");
+        exitcode::CONFIG
+    })?;
+
+    // check wireguard tooling, wg-quick is not needed if the interface is configured over netlink
+    if !wg_tooling::native(&config.wireguard.tooling, config.wireguard.dns_strategy) {
+        wg_tooling::available(&config.wireguard.tooling)
+            .await
+            .and(wg_tooling::executable(&config.wireguard.tooling).await)
+            .map_err(|err| {
+                tracing::error!(error = ?err, "error checking WireGuard tools");
+                exitcode::UNAVAILABLE
+            })?;
+    }
 
     // set up signal handlers
     let (cancel_signal_handlers, signal_receiver) = signal_channel().await?;
 
+    // held until exit, tells `cleanup` of ctl a service is running even while its socket does not answer
+    let _service_lock = match cleanup::lock_service(&root_home) {
+        Ok(Some(lock)) => lock,
+        Ok(None) => {
+            tracing::error!("another service instance is running");
+            return Err(exitcode::TEMPFAIL);
+        }
+        Err(error) => {
+            tracing::error!(?error, "unable to lock service lock file");
+            return Err(exitcode::CANTCREAT);
+        }
+    };
+
     // set up system socket
-    let socket_path = args.socket_path.clone();
-    let (cancel_socket_listener, socket_listener) = socket_listener(&args.socket_path).await?;
+    let socket_path = socket::root::resolve_path(args.socket_path.clone(), config.socket.path.clone());
+    let (socket_cmd_sender, socket_listener) = mpsc::channel(32);
+    let cancel_socket_listener = socket_listener(&socket_path, &config.socket, socket_cmd_sender.clone()).await?;
+
+    // set up optional listener for paired remote clients, its identity is kept out of reach of the worker
+    let remote_dir = root_home.join(socket::remote::DIR_NAME);
+    let paired_clients = Arc::new(Mutex::new(socket::remote::PairedClients::load(
+        remote_dir.join(socket::remote::CLIENTS_FILE),
+    )));
+    remote::discard_legacy(&worker_params.state_home()).await;
+    let cancel_remote_listener = match config.socket.remote_listen {
+        Some(addr) => Some(remote::listen(addr, &remote_dir, paired_clients.clone(), socket_cmd_sender).await?),
+        None => None,
+    };
 
     // set up config file watcher
     let (cancel_config_watcher, config_receiver) = config_watcher(config_path.clone()).await?;
@@ -497,22 +781,47 @@ async fn daemon(args: cli::Cli) -> Result<(), exitcode::ExitCode> {
     let (reconnect_tx, reconnect_rx) = mpsc::channel(1);
 
     let cancel_routing_actor = CancellationToken::new();
-    let (routing_actor_sender, routing_actor_handle) = routing_actor::start(cancel_routing_actor.clone(), reconnect_tx)
-        .map_err(|error| {
-            tracing::error!(?error, "failed to initialize firewall");
-            exitcode::UNAVAILABLE
-        })?;
+    let routing_backend = match args.routing_mode {
+        routing::Mode::Managed => routing::select_backend(args.routing_backend).await.map_err(|error| {
+            tracing::error!(?error, "unusable routing backend");
+            exitcode::CONFIG
+        })?,
+        routing::Mode::Delegated => {
+            tracing::info!("routing is delegated - only the tunnel interface will be managed");
+            args.routing_backend
+        }
+    };
+    let routing_stats = Arc::new(routing::Stats::default());
+    let (routing_actor_sender, routing_actor_handle) = routing_actor::start(
+        cancel_routing_actor.clone(),
+        reconnect_tx,
+        args.routing_mode,
+        routing_backend,
+        routing_stats.clone(),
+    )
+    .map_err(|error| {
+        tracing::error!(?error, "failed to initialize firewall");
+        exitcode::UNAVAILABLE
+    })?;
 
+    let rate_limit = config.connection.egress_rate_limit;
+    let network_online = config.network_online_timeout.is_zero();
+    let disk_space_check = time::interval(config.disk_space.interval);
     let mut state = DaemonState {
-        config,
+        config: config.clone(),
+        local_config: config,
         config_path,
+        socket_path: socket_path.clone(),
         incoming_worker_channel: mpsc::channel(32),
         log_file: args.log_file,
         pending_response_counter: 0,
         pending_responses: HashMap::new(),
         ping_tasks: JoinSet::new(),
+        public_ip_tasks: JoinSet::new(),
+        exit_report_tasks: JoinSet::new(),
         reload_handle,
         shutdown_ongoing: Shutdown::None,
+        shutdown_deadline: None,
         target_dest_id: None,
         worker_child: None,
         worker_exit_channel: mpsc::channel(1),
@@ -520,10 +829,42 @@ async fn daemon(args: cli::Cli) -> Result<(), exitcode::ExitCode> {
         worker_user,
         keep_alive_instruction_sender,
         routing_actor_sender,
+        rate_limit: None,
+        previous_crash: crash_reports.last().map(|(file, report)| report.summary(file.clone())),
+        management: None,
+        management_channel: mpsc::channel(4),
+        socket_requests: 0,
+        core_stalls: 0,
+        routing_stats,
+        pending_backup: None,
+        maintenance_tasks: JoinSet::new(),
+        background_tasks: JoinSet::new(),
+        compaction_task: None,
+        paired_clients,
+        disk_space: None,
+        disk_space_check,
+        disk_space_tasks: JoinSet::new(),
+        pending_compaction: None,
+        compacted: false,
+        routing_mode: args.routing_mode,
+        conflicts: Vec::new(),
+        network_online,
+        waiting_for_network: None,
+        worker_start_pending: false,
+        network_gate_channel: mpsc::channel(1),
     };
+    if let Err(error) = state.set_rate_limit(rate_limit).await {
+        tracing::warn!(%error, "failed to apply configured egress rate limit");
+    }
+    // the cached policy applies right away, before the worker sees any configuration
+    state.start_management();
+    state.config = state.effective_config();
+    state.set_kill_switch().await;
+    state.report_crashes(crash_reports);
+    state.detect_conflicts().await;
     if let Some(keepalive) = args.client_autostart {
         tracing::debug!(?keepalive, "autostarting worker process");
-        state.setup_worker().await?;
+        state.start_worker().await?;
         let _ = state
             .keep_alive_instruction_sender
             .send(KeepAliveInstruction::Ignite(keepalive))
@@ -541,17 +882,26 @@ async fn daemon(args: cli::Cli) -> Result<(), exitcode::ExitCode> {
 
     // cancel running tasks and run teardown logic
     state.teardown().await;
+    if let Some(managed) = &state.management {
+        managed.cancel.cancel();
+    }
+    state.background_tasks.shutdown().await;
     cancel_routing_actor.cancel();
     cancel_socket_listener.cancel();
+    if let Some(cancel) = cancel_remote_listener {
+        cancel.cancel();
+    }
     cancel_signal_handlers.cancel();
     cancel_config_watcher.cancel();
     cancel_keep_alive_timer.cancel();
     let _ = routing_actor_handle.await;
 
-    // remove socket file
-    let _ = fs::remove_file(&socket_path).await.map_err(|err| {
-        tracing::error!(error = ?err, "failed removing socket on shutdown");
-    });
+    // remove socket file - abstract sockets disappear with the listener
+    if socket::root::abstract_name(&socket_path).is_none() {
+        let _ = fs::remove_file(&socket_path).await.map_err(|err| {
+            tracing::error!(error = ?err, "failed removing socket on shutdown");
+        });
+    }
 
     res
 }
@@ -560,23 +910,18 @@ async fn send_to_worker(
     msg: RootToWorker,
     writer: &mut BufWriter<WriteHalf<TokioUnixStream>>,
 ) -> Result<(), exitcode::ExitCode> {
-    let serialized = serde_json::to_string(&msg).map_err(|err| {
-        tracing::error!(msg = ?msg, error = ?err, "failed to serialize message");
-        exitcode::DATAERR
-    })?;
-    writer.write_all(serialized.as_bytes()).await.map_err(|err| {
-        tracing::error!(serialized = ?serialized, error = ?err, "error writing to UnixStream pair write half");
-        exitcode::IOERR
-    })?;
-    writer.write_all(b"\n").await.map_err(|err| {
-        tracing::error!(error = ?err, "error appending newline to UnixStream pair write half");
-        exitcode::IOERR
-    })?;
-    writer.flush().await.map_err(|err| {
-        tracing::error!(error = ?err, "error flushing UnixStream pair write half");
-        exitcode::IOERR
-    })?;
-    Ok(())
+    socket::worker::write_frame(writer, &msg)
+        .await
+        .map_err(|err| match err {
+            socket::worker::Error::IO(err) => {
+                tracing::error!(error = ?err, "error writing to UnixStream pair write half");
+                exitcode::IOERR
+            }
+            err => {
+                tracing::error!(msg = ?msg, error = ?err, "failed to serialize message");
+                exitcode::DATAERR
+            }
+        })
 }
 
 async fn send_to_socket(msg: &Response, writer: &mut BufWriter<OwnedWriteHalf>) -> Result<(), exitcode::ExitCode> {
@@ -608,6 +953,14 @@ async fn spawn_ping(options: ping::Options) -> Result<Duration, String> {
     })
 }
 
+async fn routing_reply(
+    reply: oneshot::Receiver<Result<command::RoutingExplainResponse, String>>,
+) -> Result<command::RoutingExplainResponse, String> {
+    reply
+        .await
+        .unwrap_or_else(|_| Err("routing actor dropped reply channel".to_string()))
+}
+
 fn setup_logging(
     log_file: &Option<std::path::PathBuf>,
     worker: &worker::Worker,
@@ -663,9 +1016,14 @@ async fn write_pidfile(pid_file: &Option<PathBuf>) -> Result<(), exitcode::ExitC
 /// one for handling worker task orchestration
 #[tokio::main(flavor = "multi_thread", worker_threads = 2)]
 async fn main() {
+    crash::install("root", None);
     let args = cli::parse();
 
-    match daemon(args).await {
+    let res = match args.observer.clone() {
+        Some(observer_socket) => observer::run(args, observer_socket).await,
+        None => daemon(args).await,
+    };
+    match res {
         Ok(_) => (),
         Err(exitcode::OK) => (),
         Err(code) => {
@@ -690,14 +1048,40 @@ impl DaemonState {
                 Some(signal) = signal_receiver.recv() => self.incoming_signal(signal).await?,
                 Some(cmd) = socket_listener.recv() => self.incoming_socket_command(cmd).await?,
                 Some(_) = config_receiver.recv() => self.incoming_config_change().await?,
+                Some(res) = self.management_channel.1.recv() => self.incoming_management_policy(res).await?,
                 Some(res) = self.ping_tasks.join_next() =>  match res {
                         Ok((request_id, res)) => self.outgoing_response_from_root(ResponseFromRoot::Ping { request_id, res }).await?,
                         Err(err) => tracing::error!(error = ?err, "ping task join error"),
                 },
-                Some(line) = self.incoming_worker_channel.1.recv() => self.incoming_worker_line(line).await?,
+                Some(res) = self.public_ip_tasks.join_next() =>  match res {
+                        Ok((request_id, res)) => self.outgoing_response_from_root(ResponseFromRoot::PublicIp { request_id, res }).await?,
+                        Err(err) => tracing::error!(error = ?err, "public ip task join error"),
+                },
+                Some(res) = self.exit_report_tasks.join_next() =>  match res {
+                        Ok((request_id, res)) => self.outgoing_response_from_root(ResponseFromRoot::ExitReport { request_id, res }).await?,
+                        Err(err) => tracing::error!(error = ?err, "exit report task join error"),
+                },
+                Some(msg) = self.incoming_worker_channel.1.recv() => self.incoming_worker_message(msg).await?,
                 Some(res) = self.worker_exit_channel.1.recv() => self.incoming_worker_exit(res).await?,
+                Some(res) = self.maintenance_tasks.join_next_with_id() => self.maintenance_finished(res).await?,
+                Some(res) = self.background_tasks.join_next() => if let Err(err) = res {
+                        tracing::error!(error = ?err, "background task join error");
+                },
                 Some(dur) = keep_alive_expired.recv() => self.keep_alive_expired(dur).await?,
+                Some(outcome) = self.network_gate_channel.1.recv() => self.incoming_network_gate(outcome).await?,
                 Some(()) = reconnect_rx.recv() => self.force_reconnect_on_network_change().await,
+                _ = self.disk_space_check.tick() => self.sample_disk_space(),
+                Some(res) = self.disk_space_tasks.join_next() => match res {
+                        Ok(res) => self.incoming_disk_space(res).await?,
+                        Err(err) => tracing::error!(error = ?err, "disk space task join error"),
+                },
+                _ = time::sleep_until(self.shutdown_deadline.unwrap_or_else(time::Instant::now)),
+                    if self.shutdown_deadline.is_some() => {
+                    // returning runs the regular teardown, routing and killswitch are removed regardless
+                    tracing::error!(timeout = ?WORKER_SHUTDOWN_TIMEOUT, "worker did not exit in time - forcing shutdown");
+                    self.kill_worker();
+                    return Err(exitcode::TEMPFAIL);
+                }
                 else => {
                     tracing::error!("unexpected channel closure");
                     return Err(exitcode::IOERR);
@@ -732,6 +1116,7 @@ impl DaemonState {
                     self.shutdown_ongoing = Shutdown::Service;
                     if let Some(ref mut child) = self.worker_child {
                         tracing::debug!("sending shutdown signal to worker process");
+                        self.shutdown_deadline = Some(time::Instant::now() + WORKER_SHUTDOWN_TIMEOUT);
                         send_to_worker(RootToWorker::Shutdown, &mut child.socket_writer).await?;
                         self.cleanup_worker_resources().await;
                         Ok(())
@@ -745,12 +1130,19 @@ impl DaemonState {
                         "received shutdown signal but worker already shutting down - escalate to service shutdown"
                     );
                     self.shutdown_ongoing = Shutdown::Service;
+                    self.shutdown_deadline = Some(time::Instant::now() + WORKER_SHUTDOWN_TIMEOUT);
                     Ok(())
                 }
                 Shutdown::Service => {
                     tracing::warn!(
                         "received shutdown signal but service shutdown already ongoing - forcing immediate exit"
                     );
+                    self.kill_worker();
+                    Err(exitcode::OK)
+                }
+                // paused for maintenance, dropping the task set aborts it
+                Shutdown::RestartWorker if self.worker_child.is_none() => {
+                    tracing::info!("received shutdown signal during maintenance - shutdown immediately");
                     Err(exitcode::OK)
                 }
                 Shutdown::RestartWorker => {
@@ -758,6 +1150,7 @@ impl DaemonState {
                         "received shutdown signal but worker restart already ongoing - escalate to service shutdown"
                     );
                     self.shutdown_ongoing = Shutdown::Service;
+                    self.shutdown_deadline = Some(time::Instant::now() + WORKER_SHUTDOWN_TIMEOUT);
                     Ok(())
                 }
             },
@@ -790,7 +1183,36 @@ impl DaemonState {
     }
 
     async fn incoming_socket_command(&mut self, socket_cmd: SocketCmd) -> Result<(), exitcode::ExitCode> {
-        let SocketCmd { cmd, resp } = socket_cmd;
+        let SocketCmd { cmd, peer_uid, resp } = socket_cmd;
+        self.socket_requests = self.socket_requests.saturating_add(1);
+        if cmd.is_local_only() && peer_uid.is_none() {
+            tracing::info!(command = ?cmd, "refusing local only command from remote client");
+            let response = Response::Refused("only accepted on the local socket".to_string());
+            let _ = resp.send(response).map_err(|error| {
+                tracing::error!(?error, "socket command response channel closed");
+            });
+            return Ok(());
+        }
+        if matches!(
+            cmd,
+            LibCommand::Connect(_) | LibCommand::ConnectAny { .. } | LibCommand::ConnectFailover(_)
+        ) && let Err(reason) = self.policy_allows_connection()
+        {
+            tracing::info!(%reason, "refusing connection");
+            let response = Response::connect(command::ConnectResponse::blocked_by_policy(reason));
+            let _ = resp.send(response).map_err(|error| {
+                tracing::error!(?error, "socket command response channel closed");
+            });
+            return Ok(());
+        }
+        if let LibCommand::Backup(path) = cmd {
+            return self.start_backup(path, peer_uid, resp).await;
+        }
+        if let LibCommand::Compact = cmd
+            && matches!(self.shutdown_ongoing, Shutdown::None)
+        {
+            return self.start_compaction(Compaction::Requested(resp)).await;
+        }
         match WorkerCommand::try_from(cmd.clone()) {
             Ok(w_cmd) => {
                 self.handle_hybrid_cmd(&w_cmd).await;
@@ -810,11 +1232,29 @@ impl DaemonState {
                         .await;
                     Ok(())
                 } else if matches!(w_cmd, WorkerCommand::Status) {
-                    let response = self.status_response_offline();
+                    let response = self.status_response_offline().await;
+                    let _ = resp.send(response).map_err(|error| {
+                        tracing::error!(?error, "socket command response channel closed");
+                    });
+                    Ok(())
+                } else if matches!(w_cmd, WorkerCommand::Info) {
+                    let response = Response::Info(self.info_response(None).await);
                     let _ = resp.send(response).map_err(|error| {
                         tracing::error!(?error, "socket command response channel closed");
                     });
                     Ok(())
+                } else if matches!(w_cmd, WorkerCommand::Diagnose) {
+                    let worker = match self.shutdown_ongoing {
+                        Shutdown::RestartWorker => {
+                            diagnostics::Check::warn("worker", "restarting - run `doctor` again for the core checks")
+                        }
+                        _ => diagnostics::Check::fail(
+                            "worker",
+                            "not running - core checks skipped, start it with `gnosis_vpn-ctl start-client`",
+                        ),
+                    };
+                    self.spawn_diagnose(vec![worker], resp).await;
+                    Ok(())
                 } else {
                     let response = match self.shutdown_ongoing {
                         Shutdown::RestartWorker => Response::WorkerRestarting,
@@ -841,15 +1281,32 @@ impl DaemonState {
 
         match config::read(self.config_path.as_path()).await {
             Ok(new_config) => {
-                self.config = new_config;
-                if matches!(self.shutdown_ongoing, Shutdown::None)
-                    && let Some(ref mut child) = self.worker_child
+                // keep trusting the previous settings rather than falling back to the system trust store
+                if let Err(err) = install_tls(&new_config, &self.worker_params) {
+                    tracing::error!(error = %err, "unusable TLS trust settings - ignoring change");
+                    return Ok(());
+                }
+                let new_rate_limit = new_config.connection.egress_rate_limit;
+                let old_rate_limit = self.config.connection.egress_rate_limit;
+                // the policy watcher keeps the proxy it was started with
+                let management_changed = new_config.management != self.local_config.management
+                    || new_config.network != self.local_config.network;
+                let disk_space_interval_changed = new_config.disk_space.interval != self.config.disk_space.interval;
+                self.local_config = new_config;
+                if management_changed {
+                    self.start_management();
+                }
+                self.config = self.effective_config();
+                self.set_kill_switch().await;
+                if disk_space_interval_changed {
+                    self.disk_space_check = time::interval(self.config.disk_space.interval);
+                }
+                if new_rate_limit != old_rate_limit
+                    && let Err(error) = self.set_rate_limit(new_rate_limit).await
                 {
-                    tracing::debug!("sending shutdown signal to worker process due to config reload");
-                    self.shutdown_ongoing = Shutdown::RestartWorker;
-                    send_to_worker(RootToWorker::Shutdown, &mut child.socket_writer).await?;
-                    self.cleanup_worker_resources().await;
+                    tracing::warn!(%error, "failed to apply updated egress rate limit");
                 }
+                self.restart_worker().await?;
             }
             Err(err) => {
                 tracing::error!(error = ?err, "unable to read updated configuration file - ignoring change");
@@ -858,6 +1315,284 @@ impl DaemonState {
         Ok(())
     }
 
+    /// Samples free space in the data directory off the main loop, `df` may hang on a stale mount.
+    fn sample_disk_space(&mut self) {
+        if !self.disk_space_tasks.is_empty() {
+            tracing::debug!("previous disk space sample still running");
+            return;
+        }
+        let state_home = self.worker_params.state_home();
+        let min_free = self.config.disk_space.min_free;
+        self.disk_space_tasks
+            .spawn(async move { disk_space::sample(&state_home, min_free).await });
+    }
+
+    /// Records a free space sample and compacts the node database once space runs low.
+    async fn incoming_disk_space(
+        &mut self,
+        res: Result<disk_space::Usage, disk_space::Error>,
+    ) -> Result<(), exitcode::ExitCode> {
+        let settings = self.config.disk_space.clone();
+        let usage = match res {
+            Ok(usage) => usage,
+            Err(error) => {
+                tracing::warn!(%error, "unable to determine free disk space");
+                return Ok(());
+            }
+        };
+        let was_low = self.disk_space.as_ref().is_some_and(|u| u.is_low());
+        let is_low = usage.is_low();
+        if is_low && !was_low {
+            tracing::warn!(
+                available = %usage.available,
+                min_free = %settings.min_free,
+                "data directory is running out of disk space"
+            );
+        } else if !is_low && was_low {
+            tracing::info!(available = %usage.available, "data directory disk space recovered");
+            self.compacted = false;
+        }
+        self.disk_space = Some(usage);
+
+        // automatic compaction pauses the worker, never interrupt a connection for it
+        if self.target_dest_id.is_some() || !matches!(self.shutdown_ongoing, Shutdown::None) {
+            return Ok(());
+        }
+        if is_low && settings.compact && !self.compacted {
+            self.compacted = true;
+            self.start_compaction(Compaction::LowDiskSpace).await?;
+        } else if let Some(every) = settings.compact_every
+            && self.compaction_due(every).await
+        {
+            self.start_compaction(Compaction::Scheduled).await?;
+        }
+        Ok(())
+    }
+
+    /// Whether the last compaction is older than `every`, the first check only starts the clock.
+    async fn compaction_due(&self, every: Duration) -> bool {
+        let marker = dirs::cache_dir(self.worker_params.cache_home(), disk_space::LAST_COMPACTION_FILE);
+        match fs::metadata(&marker).await.and_then(|m| m.modified()) {
+            Ok(last) => last.elapsed().is_ok_and(|elapsed| elapsed >= every),
+            Err(_) => {
+                if let Err(error) = fs::write(&marker, b"").await {
+                    tracing::warn!(%error, "unable to record compaction schedule");
+                }
+                false
+            }
+        }
+    }
+
+    /// Compacts right away or once a running worker exited, the node database is only consistent then.
+    async fn start_compaction(&mut self, compaction: Compaction) -> Result<(), exitcode::ExitCode> {
+        if self.compaction_task.is_some() || self.pending_compaction.is_some() {
+            tracing::info!("compaction already ongoing");
+            if let Compaction::Requested(resp) = compaction {
+                let response = Response::Compact(Err("compaction already ongoing".to_string()));
+                let _ = resp.send(response).map_err(|error| {
+                    tracing::error!(?error, "socket command response channel closed");
+                });
+            }
+            return Ok(());
+        }
+        if let Some(ref mut child) = self.worker_child {
+            tracing::info!("pausing worker for compaction");
+            self.shutdown_ongoing = Shutdown::RestartWorker;
+            self.pending_compaction = Some(compaction);
+            send_to_worker(RootToWorker::Shutdown, &mut child.socket_writer).await?;
+            self.cleanup_worker_resources().await;
+        } else {
+            self.spawn_compaction(compaction, false);
+        }
+        Ok(())
+    }
+
+    fn spawn_compaction(&mut self, compaction: Compaction, paused_worker: bool) {
+        let state_home = self.worker_params.state_home();
+        let marker = dirs::cache_dir(self.worker_params.cache_home(), disk_space::LAST_COMPACTION_FILE);
+        let log_file = self.log_file.clone();
+        let task = self.maintenance_tasks.spawn(async move {
+            let res = compact(&state_home, &marker, log_file.as_deref(), paused_worker).await;
+            if let Compaction::Requested(resp) = compaction {
+                let _ = resp.send(Response::Compact(res)).map_err(|error| {
+                    tracing::error!(?error, "socket command response channel closed");
+                });
+            }
+        });
+        self.compaction_task = Some(task.id());
+    }
+
+    async fn restart_worker(&mut self) -> Result<(), exitcode::ExitCode> {
+        if matches!(self.shutdown_ongoing, Shutdown::None)
+            && let Some(ref mut child) = self.worker_child
+        {
+            tracing::debug!("sending shutdown signal to worker process due to config change");
+            self.shutdown_ongoing = Shutdown::RestartWorker;
+            send_to_worker(RootToWorker::Shutdown, &mut child.socket_writer).await?;
+            self.cleanup_worker_resources().await;
+        }
+        Ok(())
+    }
+
+    /// (Re)start fetching the management policy, the cached policy applies until the first fetch.
+    fn start_management(&mut self) {
+        if let Some(managed) = self.management.take() {
+            managed.cancel.cancel();
+        }
+        let Some(management_config) = self.local_config.management.clone() else {
+            return;
+        };
+        let path = self.management_cache_path();
+        let cache = management::Cache::load(&path);
+        // persist a freshly generated installation id
+        if let Err(err) = cache.store(&path) {
+            tracing::warn!(?err, "failed to store management cache");
+        }
+        let policy = cache.verified_policy(&management_config.public_key);
+        let serial = policy.as_ref().map(|p| p.serial);
+        tracing::info!(url = %management_config.url, ?serial, "managed mode enabled");
+        let proxy = self.local_config.network.https_proxy.clone();
+        let cancel = management::watch(
+            management_config,
+            proxy,
+            self.management_channel.0.clone(),
+            &mut self.background_tasks,
+        );
+        self.management = Some(Management { cache, policy, cancel });
+    }
+
+    /// Kept in the root only home, a worker could otherwise roll back the cached policy or swap
+    /// the installation id.
+    fn management_cache_path(&self) -> PathBuf {
+        dirs::root_home(&self.worker_params.state_home()).join(management::CACHE_FILE)
+    }
+
+    /// Local configuration restricted by the current management policy.
+    fn effective_config(&self) -> Config {
+        let mut config = self.local_config.clone();
+        if let Some(policy) = self.management.as_ref().and_then(|m| m.policy.as_ref()) {
+            policy.apply(&mut config);
+        }
+        config
+    }
+
+    fn policy_allows_connection(&self) -> Result<(), String> {
+        match self.management.as_ref().and_then(|m| m.policy.as_ref()) {
+            Some(policy) => policy.connection_allowed(SystemTime::now().into()),
+            None => Ok(()),
+        }
+    }
+
+    async fn incoming_management_policy(&mut self, res: PolicyResult) -> Result<(), exitcode::ExitCode> {
+        let path = self.management_cache_path();
+        let Some(ref mut managed) = self.management else {
+            tracing::debug!("managed mode disabled - ignoring fetched policy");
+            return Ok(());
+        };
+        match res {
+            Ok((_, policy)) if managed.policy.as_ref() == Some(&policy) => {
+                tracing::debug!("management policy unchanged")
+            }
+            Ok((signed, policy)) if policy.supersedes(managed.policy.as_ref()) => {
+                tracing::info!(serial = policy.serial, "applying updated management policy");
+                managed.policy = Some(policy);
+                managed.cache.policy = Some(signed);
+                if let Err(err) = managed.cache.store(&path) {
+                    tracing::warn!(?err, "failed to store management cache");
+                }
+                let config = self.effective_config();
+                if config != self.config {
+                    self.config = config;
+                    self.set_kill_switch().await;
+                    if let Some(id) = self
+                        .target_dest_id
+                        .take_if(|id| !self.config.destinations.contains_key(id))
+                    {
+                        tracing::info!(%id, "target destination no longer allowed by management policy");
+                    }
+                    self.restart_worker().await?;
+                }
+            }
+            Ok((_, policy)) => tracing::warn!(
+                serial = policy.serial,
+                current = ?managed.policy.as_ref().map(|p| p.serial),
+                "refusing management policy with an outdated serial - keeping current policy"
+            ),
+            Err(err) => tracing::warn!(?err, "failed to fetch management policy - keeping previous policy"),
+        }
+        if self.target_dest_id.is_some()
+            && let Err(reason) = self.policy_allows_connection()
+        {
+            tracing::info!(%reason, "disconnecting");
+            self.handle_hybrid_cmd(&WorkerCommand::Disconnect).await;
+            if matches!(self.shutdown_ongoing, Shutdown::None)
+                && let Some(ref mut child) = self.worker_child
+            {
+                let (resp, resp_receiver) = oneshot::channel();
+                self.pending_response_counter += 1;
+                self.pending_responses.insert(self.pending_response_counter, resp);
+                let msg = RootToWorker::WorkerCommand {
+                    cmd: WorkerCommand::Disconnect,
+                    id: self.pending_response_counter,
+                };
+                send_to_worker(msg, &mut child.socket_writer).await?;
+                self.background_tasks.spawn(async move {
+                    if let Ok(resp) = resp_receiver.await {
+                        tracing::debug!(?resp, "worker disconnected as required by management policy");
+                    }
+                });
+            }
+        }
+        self.spawn_management_report();
+        Ok(())
+    }
+
+    /// Upload crash reports of previous runs if the user opted into usage telemetry.
+    fn report_crashes(&mut self, reports: Vec<(PathBuf, crash::Report)>) {
+        if let Some(summary) = &self.previous_crash {
+            tracing::warn!(%summary, "previous run crashed");
+        }
+        let path = dirs::cache_dir(self.worker_params.cache_home(), telemetry::TELEMETRY_FILE);
+        let Some(url) = self.config.telemetry.crash_url.clone() else {
+            return;
+        };
+        if reports.is_empty() || !telemetry::Recorder::load(path, self.config.telemetry.enabled).enabled() {
+            return;
+        }
+        let proxy = self.local_config.network.https_proxy.clone();
+        self.background_tasks.spawn(async move {
+            for (file, report) in reports {
+                if let Err(err) = crash::upload(url.clone(), &report, proxy.as_ref()).await {
+                    tracing::warn!(?err, file = %file.display(), "failed to upload crash report");
+                }
+            }
+        });
+    }
+
+    fn spawn_management_report(&mut self) {
+        let (Some(managed), Some(management_config)) = (&self.management, &self.local_config.management) else {
+            return;
+        };
+        if !management_config.report {
+            return;
+        }
+        let management_config = management_config.clone();
+        let proxy = self.local_config.network.https_proxy.clone();
+        let report = management::Report {
+            client_id: managed.cache.client_id.clone(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            policy_serial: managed.policy.as_ref().map(|p| p.serial),
+            worker_running: self.worker_child.is_some(),
+            connection_requested: self.target_dest_id.is_some(),
+        };
+        self.background_tasks.spawn(async move {
+            if let Err(err) = management::report(&management_config, proxy.as_ref(), &report).await {
+                tracing::warn!(?err, "failed to report to management server");
+            }
+        });
+    }
+
     async fn outgoing_response_from_root(&mut self, resp: ResponseFromRoot) -> Result<(), exitcode::ExitCode> {
         if matches!(self.shutdown_ongoing, Shutdown::None)
             && let Some(ref mut child) = self.worker_child
@@ -873,7 +1608,7 @@ impl DaemonState {
         }
     }
 
-    fn status_response_offline(&self) -> Response {
+    async fn status_response_offline(&self) -> Response {
         let mut vals: Vec<&Destination> = self.config.destinations.values().collect();
         vals.sort_unstable_by(|a, b| a.id.cmp(&b.id));
         let destinations = vals
@@ -883,8 +1618,9 @@ impl DaemonState {
                 route_health: None,
             })
             .collect();
-        let run_mode = match self.shutdown_ongoing {
-            Shutdown::RestartWorker => command::RunMode::Restarting,
+        let run_mode = match (self.shutdown_ongoing, self.waiting_for_network) {
+            (Shutdown::RestartWorker, _) => command::RunMode::Restarting,
+            (Shutdown::None, Some(since)) if self.worker_start_pending => command::RunMode::WaitingForNetwork { since },
             _ => command::RunMode::NotRunning,
         };
         Response::status(command::StatusResponse {
@@ -895,40 +1631,80 @@ impl DaemonState {
             reconnecting: None,
             connected: None,
             disconnecting: vec![],
+            budget: None,
+            manual_channel_funding: self.config.manual_channel_funding(),
+            retrying: vec![],
+            previous_crash: self.previous_crash.clone(),
+            disk_space: self.disk_space.clone(),
+            conflicts: self.conflicts.clone(),
+            kill_switch_error: self.kill_switch_error().await,
+            hints: vec![],
         })
     }
 
     async fn incoming_root_command(&mut self, cmd: LibCommand) -> Result<Response, exitcode::ExitCode> {
         match cmd {
-            LibCommand::Status => Ok(self.status_response_offline()),
+            LibCommand::Status => Ok(self.status_response_offline().await),
             LibCommand::NerdStats
             | LibCommand::Connect(_)
+            | LibCommand::ConnectAny { .. }
+            | LibCommand::ConnectFailover(_)
             | LibCommand::Disconnect
+            | LibCommand::CancelConnect
             | LibCommand::Balance
+            | LibCommand::BalanceHistory { .. }
             | LibCommand::FundingTool(_)
-            | LibCommand::Telemetry => Ok(match self.shutdown_ongoing {
+            | LibCommand::Telemetry
+            | LibCommand::Info
+            | LibCommand::Peers
+            | LibCommand::Sessions
+            | LibCommand::UsageTelemetry(_)
+            | LibCommand::Transactions
+            | LibCommand::ExitReports
+            | LibCommand::ReportIssue { .. }
+            | LibCommand::Diagnose => Ok(match self.shutdown_ongoing {
                 Shutdown::RestartWorker => Response::WorkerRestarting,
                 _ => Response::WorkerOffline,
             }),
             LibCommand::Ping => Ok(Response::Pong),
+            LibCommand::Logs { lines, .. } => Ok(Response::Logs(log_buffer::recent(lines as usize))),
             LibCommand::Destinations => {
                 let mut ids: Vec<String> = self.config.destinations.keys().cloned().collect();
                 ids.sort_unstable();
                 Ok(Response::Destinations(ids))
             }
-            LibCommand::Info => {
-                let package_version = fs::read_to_string("/etc/gnosisvpn/version.txt")
+            LibCommand::ExportDestinations => Ok(Response::ExportDestinations(
+                config::export_destinations(&self.config.destinations).map_err(|e| e.to_string()),
+            )),
+            // the config watcher picks up the new destinations and restarts the worker
+            LibCommand::ImportDestinations(export) => Ok(Response::ImportDestinations(
+                config::import_destinations(&self.config_path, &export)
                     .await
-                    .ok()
-                    .map(|s| s.trim().to_string());
-                let info = command::InfoResponse {
-                    version: env!("CARGO_PKG_VERSION").to_string(),
-                    log_file: self.log_file.clone(),
-                    package_version,
-                };
-                Ok(Response::Info(info))
+                    .map_err(|e| e.to_string()),
+            )),
+            LibCommand::RateLimit => Ok(Response::RateLimit(Ok(command::RateLimitResponse {
+                limit: self.rate_limit,
+            }))),
+            LibCommand::SetRateLimit(limit) => {
+                let res = self
+                    .set_rate_limit(limit)
+                    .await
+                    .map(|_| command::RateLimitResponse { limit: self.rate_limit });
+                Ok(Response::RateLimit(res))
             }
-
+            LibCommand::Gateway => Ok(Response::Gateway(self.gateway_response().await)),
+            LibCommand::RoutingExplain => Ok(Response::RoutingExplain(self.routing_explain_response().await)),
+            // handled by start_backup, it needs the requesting peer
+            LibCommand::Backup(_) => Ok(Response::Backup(Err("backups need a local peer".to_string()))),
+            LibCommand::Unpair(name) => {
+                let removed = self.paired_clients.lock().await.unpair(&name);
+                tracing::info!(%name, removed, "unpaired remote clients");
+                Ok(Response::Unpair(removed))
+            }
+            // started by start_compaction unless the worker is on its way out
+            LibCommand::Compact => Ok(Response::Compact(Err(
+                "worker is shutting down - try again once it stopped".to_string(),
+            ))),
             LibCommand::StartClient(keepalive) => match (self.shutdown_ongoing, &self.worker_child) {
                 (Shutdown::None, Some(_)) => {
                     let _ = self
@@ -938,7 +1714,13 @@ impl DaemonState {
                     Ok(Response::StartClient(command::StartClientResponse::AlreadyRunning))
                 }
                 (Shutdown::None, None) => {
-                    self.setup_worker().await?;
+                    if self.maintenance_tasks.is_empty() {
+                        self.start_worker().await?;
+                    } else {
+                        // a backup or compaction reads the node database, started once it finished
+                        tracing::info!("starting worker once maintenance finished");
+                        self.shutdown_ongoing = Shutdown::RestartWorker;
+                    }
                     let _ = self
                         .keep_alive_instruction_sender
                         .send(KeepAliveInstruction::Ignite(keepalive))
@@ -971,6 +1753,11 @@ impl DaemonState {
             },
 
             LibCommand::StopClient => match (self.shutdown_ongoing, &mut self.worker_child) {
+                (Shutdown::None, None) if self.worker_start_pending => {
+                    tracing::debug!("received stop client command while waiting for network - dropping worker start");
+                    self.worker_start_pending = false;
+                    Ok(Response::StopClient(command::StopClientResponse::Stopped))
+                }
                 (Shutdown::None, None) => Ok(Response::StopClient(command::StopClientResponse::NotRunning)),
                 (Shutdown::None, Some(child)) => {
                     tracing::debug!("sending shutdown signal to worker process due to stop client command");
@@ -1002,23 +1789,132 @@ impl DaemonState {
         }
     }
 
-    async fn incoming_worker_line(&mut self, line: String) -> Result<(), exitcode::ExitCode> {
-        let cmd: WorkerToRoot = serde_json::from_str(&line).map_err(|err| {
-            tracing::error!(error = %err, "failed parsing incoming worker command");
-            exitcode::DATAERR
-        })?;
-        match cmd {
+    async fn incoming_worker_message(&mut self, msg: WorkerToRoot) -> Result<(), exitcode::ExitCode> {
+        match msg {
             WorkerToRoot::Response { id, resp } => self.incoming_worker_response(id, resp).await,
             WorkerToRoot::RequestToRoot(request) => self.incoming_worker_request(request).await,
+            WorkerToRoot::CoreStalled { stalled_for } => self.incoming_core_stalled(stalled_for).await,
+            WorkerToRoot::LogLines(lines) => {
+                // already written to the log file by the worker itself
+                log_buffer::append(lines);
+                Ok(())
+            }
         }
     }
 
-    async fn incoming_worker_response(&mut self, id: u64, resp: Response) -> Result<(), exitcode::ExitCode> {
+    /// The worker aborted its stalled core loop - drop everything tied to it and hand out fresh startup params.
+    async fn incoming_core_stalled(&mut self, stalled_for: Duration) -> Result<(), exitcode::ExitCode> {
+        tracing::warn!(?stalled_for, "worker core loop stalled - re-initializing core");
+        self.core_stalls = self.core_stalls.saturating_add(1);
+        self.cleanup_worker_resources().await;
+        if !matches!(self.shutdown_ongoing, Shutdown::None) {
+            return Ok(());
+        }
+        let Some(ref mut child) = self.worker_child else {
+            return Ok(());
+        };
+        send_to_worker(
+            RootToWorker::StartupParams {
+                config: self.config.clone(),
+                worker_params: self.worker_params.clone(),
+                target_dest_id: self.target_dest_id.clone(),
+            },
+            &mut child.socket_writer,
+        )
+        .await?;
+        let _ = self
+            .keep_alive_instruction_sender
+            .send(KeepAliveInstruction::Restart)
+            .await;
+        Ok(())
+    }
+
+    /// Daemon metrics of the root side, appended to the worker metrics.
+    fn write_metrics(&self, out: &mut String) {
+        metrics::write(
+            out,
+            "gnosis_vpn_socket_requests_total",
+            "Commands received on the local and remote control sockets",
+            metrics::Kind::Counter,
+            self.socket_requests as f64,
+        );
+        metrics::write(
+            out,
+            "gnosis_vpn_pending_worker_responses",
+            "Socket commands awaiting a worker response",
+            metrics::Kind::Gauge,
+            self.pending_responses.len() as f64,
+        );
+        metrics::write(
+            out,
+            "gnosis_vpn_core_stalls_total",
+            "Stalled core loops re-initialized by the worker watchdog",
+            metrics::Kind::Counter,
+            self.core_stalls as f64,
+        );
+        if let Some(usage) = &self.disk_space {
+            metrics::write(
+                out,
+                "gnosis_vpn_data_dir_available_bytes",
+                "Free space on the file system holding the data directory",
+                metrics::Kind::Gauge,
+                usage.available.as_u64() as f64,
+            );
+        }
+        self.routing_stats.write_metrics(out);
+        metrics::ProcessUsage::sample().write_metrics(out, "root");
+    }
+
+    async fn incoming_worker_response(&mut self, id: u64, mut resp: Response) -> Result<(), exitcode::ExitCode> {
         tracing::debug!(?resp, "received worker response");
         // ForceReconnect is fire-and-forget (id=0), no pending response entry
         if matches!(resp, Response::ForceReconnectAcknowledged) {
             return Ok(());
         }
+        // the worker cannot read WireGuard interface state - fill in the handshake on its behalf
+        if let Response::Status(ref mut status) = resp
+            && let Some(ref mut connected) = status.connected
+        {
+            connected.last_handshake = self.latest_handshake().await;
+        }
+        if let Response::Status(ref mut status) = resp {
+            status.previous_crash = self.previous_crash.clone();
+            status.disk_space = self.disk_space.clone();
+            status.conflicts = self.conflicts.clone();
+            status.kill_switch_error = self.kill_switch_error().await;
+            let locale = i18n::Locale::resolve(self.local_config.locale.as_deref());
+            status.hints = status.generate_hints(SystemTime::now(), locale);
+        }
+        if let Response::Telemetry(Some(ref mut out)) = resp {
+            self.write_metrics(out);
+        }
+        // only the worker knows whether an attempt was pending, an established connection stays
+        if let Response::CancelConnect(command::CancelConnectResponse::Cancelled(_)) = resp {
+            tracing::debug!("clearing target destination from cancelled connection attempt");
+            self.clear_target().await;
+        }
+        // `connect --any` leaves picking the destination to the worker
+        if let Response::Connect(command::ConnectResponse::Connecting(ref dest)) = resp
+            && self.target_dest_id.as_ref() != Some(&dest.id)
+        {
+            tracing::debug!(destination = %dest.id, "remembering target destination picked by the worker");
+            self.target_dest_id = Some(dest.id.clone());
+            let _ = self
+                .keep_alive_instruction_sender
+                .send(KeepAliveInstruction::Suspend)
+                .await;
+        }
+        // node identity comes from the worker, versions and paths are known here
+        if let Response::Info(info) = resp {
+            resp = Response::Info(self.info_response(info.node).await);
+        }
+        if let Response::Diagnose(report) = resp {
+            match self.pending_responses.remove(&id) {
+                Some(resp_sender) => self.spawn_diagnose(report.checks, resp_sender).await,
+                None => tracing::warn!(id, "no pending response found for worker response"),
+            }
+            return Ok(());
+        }
         if let Some(resp_sender) = self.pending_responses.remove(&id) {
             if resp_sender.send(resp).is_err() {
                 tracing::error!(id, "unexpected channel closure");
@@ -1046,6 +1942,238 @@ impl DaemonState {
         }
     }
 
+    async fn set_rate_limit(&mut self, limit: Option<Bandwidth>) -> Result<(), String> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let _ = self
+            .routing_actor_sender
+            .send(routing_actor::Msg::SetRateLimit { limit, reply: reply_tx })
+            .await;
+        let res = match reply_rx.await {
+            Ok(res) => res,
+            Err(_) => Err("routing actor dropped reply channel".to_string()),
+        };
+        // the actor keeps the limit for the next routing setup even if applying it failed
+        self.rate_limit = limit;
+        res
+    }
+
+    async fn info_response(&self, node: Option<command::NodeIdentity>) -> command::InfoResponse {
+        let package_version = fs::read_to_string("/etc/gnosisvpn/version.txt")
+            .await
+            .ok()
+            .map(|s| s.trim().to_string());
+        command::InfoResponse {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            log_file: self.log_file.clone(),
+            package_version,
+            config_file: Some(self.config_path.clone()),
+            state_home: Some(self.worker_params.state_home()),
+            cache_home: Some(self.worker_params.cache_home()),
+            node,
+        }
+    }
+
+    /// Root side checks of `doctor` in front of the `core` checks answered by the worker. They run
+    /// off the main loop, the tooling checks spawn processes and the routing actor may be busy.
+    async fn spawn_diagnose(&mut self, core: Vec<diagnostics::Check>, resp: oneshot::Sender<Response>) {
+        let wireguard = self.local_config.wireguard.clone();
+        let permissions = socket::root::check_permissions(&self.socket_path, &self.local_config.socket);
+        let routing = self.request_routing_explain().await;
+        self.background_tasks.spawn(async move {
+            let tooling = &wireguard.tooling;
+            let wg_quick = if wg_tooling::native(tooling, wireguard.dns_strategy) {
+                diagnostics::Check::pass("wg-quick", "not needed, the interface is configured over netlink")
+            } else {
+                match wg_tooling::available(tooling)
+                    .await
+                    .and(wg_tooling::executable(tooling).await)
+                {
+                    Ok(()) => {
+                        diagnostics::Check::pass("wg-quick", format!("{} is available", tooling.wg_quick().display()))
+                    }
+                    Err(e) => diagnostics::Check::fail("wg-quick", e.to_string()),
+                }
+            };
+            let mut checks = vec![
+                wg_quick,
+                permissions,
+                diagnostics::routing(&routing_reply(routing).await),
+            ];
+            checks.extend(core);
+            if resp.send(Response::Diagnose(diagnostics::Report { checks })).is_err() {
+                tracing::error!("socket command response channel closed");
+            }
+        });
+    }
+
+    async fn gateway_response(&self) -> Result<command::GatewayResponse, String> {
+        let lan_interface = self.config.connection.gateway.as_ref().map(|g| g.lan_interface.clone());
+        if lan_interface.is_none() {
+            return Ok(command::GatewayResponse {
+                lan_interface,
+                active: false,
+                clients: Vec::new(),
+            });
+        }
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let _ = self
+            .routing_actor_sender
+            .send(routing_actor::Msg::GatewayClients { reply: reply_tx })
+            .await;
+        let clients = match reply_rx.await {
+            Ok(res) => res?,
+            Err(_) => return Err("routing actor dropped reply channel".to_string()),
+        };
+        Ok(command::GatewayResponse {
+            lan_interface,
+            active: clients.is_some(),
+            clients: clients.unwrap_or_default(),
+        })
+    }
+
+    async fn routing_explain_response(&self) -> Result<command::RoutingExplainResponse, String> {
+        routing_reply(self.request_routing_explain().await).await
+    }
+
+    async fn request_routing_explain(&self) -> oneshot::Receiver<Result<command::RoutingExplainResponse, String>> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let _ = self
+            .routing_actor_sender
+            .send(routing_actor::Msg::ExplainRouting {
+                dscp: self.config.connection.dscp.is_some(),
+                gateway: self.config.connection.gateway.is_some(),
+                block_ipv6: self.config.wireguard.block_ipv6,
+                reply: reply_tx,
+            })
+            .await;
+        reply_rx
+    }
+
+    /// Backs up right away or once a running worker exited, the node database is only consistent then.
+    /// The archive is owned by the requesting user, so only local peers may ask for one.
+    async fn start_backup(
+        &mut self,
+        path: PathBuf,
+        peer_uid: Option<u32>,
+        resp: oneshot::Sender<Response>,
+    ) -> Result<(), exitcode::ExitCode> {
+        let refusal = match (peer_uid, self.shutdown_ongoing) {
+            (None, _) => "backups are only accepted on the local socket",
+            // a worker on its way out may still write to the node database
+            (Some(_), Shutdown::Worker | Shutdown::RestartWorker | Shutdown::Service) => {
+                "worker is shutting down - try again once it stopped"
+            }
+            (Some(owner), Shutdown::None) => {
+                if let Some(ref mut child) = self.worker_child {
+                    tracing::info!(path = %path.display(), "pausing worker for backup");
+                    self.shutdown_ongoing = Shutdown::RestartWorker;
+                    self.pending_backup = Some((path, owner, resp));
+                    send_to_worker(RootToWorker::Shutdown, &mut child.socket_writer).await?;
+                    self.cleanup_worker_resources().await;
+                } else {
+                    self.spawn_backup(path, owner, resp, false);
+                }
+                return Ok(());
+            }
+        };
+        let _ = resp.send(Response::Backup(Err(refusal.to_string()))).map_err(|error| {
+            tracing::error!(?error, "socket command response channel closed");
+        });
+        Ok(())
+    }
+
+    fn spawn_backup(&mut self, path: PathBuf, owner: u32, resp: oneshot::Sender<Response>, paused_worker: bool) {
+        let state_home = self.worker_params.state_home();
+        self.maintenance_tasks.spawn(async move {
+            let res = match backup::create(&state_home, &path, owner).await {
+                Ok(size_bytes) => {
+                    tracing::info!(path = %path.display(), size_bytes, "created backup");
+                    Ok(command::BackupResponse {
+                        path,
+                        size_bytes,
+                        paused_worker,
+                    })
+                }
+                Err(error) => {
+                    tracing::error!(%error, path = %path.display(), "unable to create backup");
+                    Err(error.to_string())
+                }
+            };
+            let _ = resp.send(Response::Backup(res)).map_err(|error| {
+                tracing::error!(?error, "socket command response channel closed");
+            });
+        });
+    }
+
+    /// Restarts a worker paused for maintenance once the last maintenance task finished.
+    async fn maintenance_finished(&mut self, res: Result<(TaskId, ()), JoinError>) -> Result<(), exitcode::ExitCode> {
+        let id = match res {
+            Ok((id, ())) => id,
+            Err(err) => {
+                tracing::error!(error = ?err, "maintenance task join error");
+                err.id()
+            }
+        };
+        if self.compaction_task == Some(id) {
+            self.compaction_task = None;
+        }
+        if self.maintenance_tasks.is_empty()
+            && self.worker_child.is_none()
+            && matches!(self.shutdown_ongoing, Shutdown::RestartWorker)
+        {
+            self.resume_worker().await?;
+        }
+        Ok(())
+    }
+
+    async fn latest_handshake(&self) -> Option<SystemTime> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let _ = self
+            .routing_actor_sender
+            .send(routing_actor::Msg::LatestHandshake { reply: reply_tx })
+            .await;
+        reply_rx.await.ok().flatten()
+    }
+
+    async fn kill_switch_error(&self) -> Option<String> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let _ = self
+            .routing_actor_sender
+            .send(routing_actor::Msg::KillSwitchError { reply: reply_tx })
+            .await;
+        reply_rx.await.ok().flatten()
+    }
+
+    /// Last resort when the worker ignores a shutdown request: it swallows termination signals,
+    /// so only SIGKILL reliably stops it.
+    fn kill_worker(&self) {
+        let Some(pid) = self.worker_child.as_ref().and_then(|child| child.pid) else {
+            return;
+        };
+        tracing::warn!(pid, "killing unresponsive worker process");
+        if unsafe { libc::kill(pid as libc::pid_t, libc::SIGKILL) } != 0 {
+            tracing::error!(
+                error = ?std::io::Error::last_os_error(),
+                pid,
+                "failed to kill worker process"
+            );
+        }
+    }
+
+    /// Kill switch mode follows the configuration, the actor keeps an unchanged block as is.
+    async fn set_kill_switch(&self) {
+        let block = self.config.connection.kill_switch.then(|| routing::firewall::Block {
+            uid: self.worker_user.uid,
+            resolver_uid: routing::firewall::resolver_uid(),
+            interface: wireguard::WG_INTERFACE.to_string(),
+            lan_lockdown: self.config.connection.lan_lockdown,
+        });
+        let _ = self
+            .routing_actor_sender
+            .send(routing_actor::Msg::SetKillSwitch { block })
+            .await;
+    }
+
     async fn disable_killswitch(&self) {
         let _ = self
             .routing_actor_sender
@@ -1079,7 +2207,11 @@ impl DaemonState {
                 wg_data,
                 peer_ips,
             } => {
-                let res = self.setup_static_routing(wg_data, peer_ips).await;
+                self.detect_conflicts().await;
+                let res = self
+                    .setup_static_routing(wg_data, peer_ips)
+                    .await
+                    .map_err(|error| self.blame_conflicts(error));
                 if matches!(self.shutdown_ongoing, Shutdown::None)
                     && let Some(ref mut child) = self.worker_child
                 {
@@ -1100,6 +2232,46 @@ impl DaemonState {
                     .spawn(async move { (request_id, spawn_ping(options).await) });
                 Ok(())
             }
+            RequestToRoot::PublicIp {
+                request_id,
+                endpoint,
+                timeout,
+            } => {
+                self.public_ip_tasks.spawn(async move {
+                    let res = public_ip::lookup(&endpoint, timeout).await.map_err(|e| {
+                        tracing::debug!(error = ?e, %endpoint, "public ip lookup error");
+                        e.to_string()
+                    });
+                    (request_id, res)
+                });
+                Ok(())
+            }
+            RequestToRoot::ExitReport {
+                request_id,
+                url,
+                report,
+            } => {
+                // without a tunnel the report would reveal the address of this host
+                if self.latest_handshake().await.is_none() {
+                    let res = Err("no tunnel to send the report through".to_string());
+                    return self
+                        .outgoing_response_from_root(ResponseFromRoot::ExitReport { request_id, res })
+                        .await;
+                }
+                self.exit_report_tasks.spawn(async move {
+                    let res = exit_reports::upload(url, &report).await.map_err(|e| {
+                        tracing::debug!(error = ?e, "exit report upload error");
+                        e.to_string()
+                    });
+                    (request_id, res)
+                });
+                Ok(())
+            }
+            RequestToRoot::TargetCleared => {
+                tracing::debug!("clearing target destination given up by the worker");
+                self.clear_target().await;
+                Ok(())
+            }
             RequestToRoot::CacheBlokliIps { ips } => {
                 tracing::debug!(?ips, "caching blokli IPs for worker restart");
                 self.worker_params.set_cached_blokli_ips(ips);
@@ -1112,11 +2284,24 @@ impl DaemonState {
                     .await;
                 Ok(())
             }
+            RequestToRoot::KillSwitch { engage } => {
+                let _ = self
+                    .routing_actor_sender
+                    .send(routing_actor::Msg::EngageKillSwitch { engage })
+                    .await;
+                Ok(())
+            }
         }
     }
 
     async fn incoming_worker_exit(&mut self, status: process::ExitStatus) -> Result<(), exitcode::ExitCode> {
         self.worker_child = None;
+        if let Some((path, owner, resp)) = self.pending_backup.take() {
+            self.spawn_backup(path, owner, resp, true);
+        }
+        if let Some(compaction) = self.pending_compaction.take() {
+            self.spawn_compaction(compaction, true);
+        }
         match self.shutdown_ongoing {
             Shutdown::None => {
                 if status.success() {
@@ -1127,6 +2312,10 @@ impl DaemonState {
                         .send(KeepAliveInstruction::Restart)
                         .await;
                     Ok(())
+                } else if status.code() == Some(exitcode::CONFIG) {
+                    // preflight failure of the worker, restarting it would fail the same way
+                    tracing::error!(status = exitcode::CONFIG, "worker process failed its preflight checks");
+                    Err(exitcode::CONFIG)
                 } else {
                     tracing::error!(status = ?status.code(), "worker process exited unexpectedly");
                     Err(exitcode::TEMPFAIL)
@@ -1156,19 +2345,29 @@ impl DaemonState {
                 } else {
                     tracing::warn!(status = ?status.code(), "worker exited with error before restart");
                 }
-                self.shutdown_ongoing = Shutdown::None;
-                self.setup_worker().await?;
-                let _ = self
-                    .keep_alive_instruction_sender
-                    .send(KeepAliveInstruction::Restart)
-                    .await;
-                Ok(())
+                if self.maintenance_tasks.is_empty() {
+                    self.resume_worker().await
+                } else {
+                    tracing::info!("restarting worker once maintenance finished");
+                    Ok(())
+                }
             }
         }
     }
 
+    async fn resume_worker(&mut self) -> Result<(), exitcode::ExitCode> {
+        self.shutdown_ongoing = Shutdown::None;
+        self.setup_worker().await?;
+        let _ = self
+            .keep_alive_instruction_sender
+            .send(KeepAliveInstruction::Restart)
+            .await;
+        Ok(())
+    }
+
     async fn keep_alive_expired(&mut self, duration: Duration) -> Result<(), exitcode::ExitCode> {
         tracing::info!(?duration, "keepalive timer expired - shutting down worker process");
+        self.worker_start_pending = false;
         if matches!(self.shutdown_ongoing, Shutdown::None)
             && let Some(ref mut child) = self.worker_child
         {
@@ -1180,6 +2379,49 @@ impl DaemonState {
         Ok(())
     }
 
+    /// Starts the worker, or holds it back until the network gate passes on its first start.
+    async fn start_worker(&mut self) -> Result<(), exitcode::ExitCode> {
+        if self.network_online {
+            return self.setup_worker().await;
+        }
+        self.worker_start_pending = true;
+        if self.waiting_for_network.is_none() {
+            // the kill switch block drops lookups of root, only the worker is let through
+            let host = (!self.config.connection.kill_switch).then(|| {
+                hopr::blokli_url(self.worker_params.blokli_url())
+                    .host_str()
+                    .unwrap_or_default()
+                    .to_string()
+            });
+            let timeout = self.config.network_online_timeout;
+            tracing::info!(?host, ?timeout, "waiting for network before starting worker process");
+            self.waiting_for_network = Some(SystemTime::now());
+            let sender = self.network_gate_channel.0.clone();
+            self.background_tasks.spawn(async move {
+                let _ = sender.send(network_gate::wait(host, timeout).await).await;
+            });
+        }
+        Ok(())
+    }
+
+    async fn incoming_network_gate(&mut self, outcome: network_gate::Outcome) -> Result<(), exitcode::ExitCode> {
+        match outcome {
+            network_gate::Outcome::Online { waited } => tracing::info!(?waited, "network is usable"),
+            network_gate::Outcome::TimedOut { waited, reason } => {
+                tracing::warn!(?waited, %reason, "network not usable in time - starting worker process anyway")
+            }
+        }
+        self.network_online = true;
+        self.waiting_for_network = None;
+        if std::mem::take(&mut self.worker_start_pending)
+            && matches!(self.shutdown_ongoing, Shutdown::None)
+            && self.worker_child.is_none()
+        {
+            self.setup_worker().await?;
+        }
+        Ok(())
+    }
+
     async fn setup_worker(&mut self) -> Result<(), exitcode::ExitCode> {
         let (parent_socket, child_socket) = UnixStream::pair().map_err(|err| {
             tracing::error!(error = ?err, "unable to create socket pair for worker communication");
@@ -1213,6 +2455,14 @@ impl DaemonState {
         if let Some(ref log_file) = self.log_file {
             worker_command.env(logging::ENV_VAR_LOG_FILE, log_file.to_string_lossy().to_string());
         }
+        // hopr-lib only picks up a proxy from the environment
+        if let Some(ref https_proxy) = self.local_config.network.https_proxy {
+            worker_command.envs(proxy::ENV_VARS.map(|var| (var, https_proxy.as_str())));
+        }
+        // same for a CA bundle, it has to be readable by the worker user
+        if let Some(ref ca_bundle) = self.local_config.network.tls.ca_bundle {
+            worker_command.env(tls::ENV_VAR, ca_bundle);
+        }
         let mut child = worker_command.spawn().map_err(|err| {
             tracing::error!(error = ?err, ?self.worker_user, "unable to spawn worker process");
             exitcode::IOERR
@@ -1231,8 +2481,7 @@ impl DaemonState {
         // root <-> worker communication setup
         tracing::debug!("splitting unix stream into reader and writer halves");
         let (reader_half, writer_half) = io::split(parent_stream);
-        let reader = BufReader::new(reader_half);
-        let mut socket_lines_reader = reader.lines();
+        let mut frame_reader = socket::worker::FrameReader::new(BufReader::new(reader_half));
         let mut socket_writer = BufWriter::new(writer_half);
 
         // send initial configuration and resources to worker
@@ -1246,17 +2495,30 @@ impl DaemonState {
         )
         .await?;
 
+        let pid = child.id();
         let cancel = CancellationToken::new();
         let owned_cancel = cancel.clone();
-        let lines_sender = self.incoming_worker_channel.0.clone();
+        let messages_sender = self.incoming_worker_channel.0.clone();
         let exit_sender = self.worker_exit_channel.0.clone();
         tokio::spawn(async move {
+            // keep waiting for the exit status once the worker closed its end
+            let mut socket_closed = false;
             loop {
                 tokio::select! {
-                    Ok(Some(line)) = socket_lines_reader.next_line() => {
-                        let _ = lines_sender.send(line.clone()).await.map_err(|err| {
-                            tracing::error!(error = ?err, "worker channel receiver dropped");
-                        });
+                    res = frame_reader.next::<WorkerToRoot>(), if !socket_closed => match res {
+                        Ok(Some(msg)) => {
+                            let _ = messages_sender.send(msg).await.map_err(|err| {
+                                tracing::error!(error = ?err, "worker channel receiver dropped");
+                            });
+                        }
+                        Ok(None) => socket_closed = true,
+                        Err(socket::worker::Error::IO(err)) => {
+                            tracing::error!(error = ?err, "error reading from worker socket");
+                            socket_closed = true;
+                        }
+                        Err(err) => {
+                            tracing::error!(error = ?err, "failed decoding incoming worker message - ignoring");
+                        }
                     },
                     Ok(status) = child.wait() => {
                         let _ = exit_sender.send(status).await.map_err(|err| {
@@ -1276,7 +2538,11 @@ impl DaemonState {
             }
         });
 
-        self.worker_child = Some(WorkerChild { cancel, socket_writer });
+        self.worker_child = Some(WorkerChild {
+            cancel,
+            socket_writer,
+            pid,
+        });
         Ok(())
     }
 
@@ -1291,7 +2557,7 @@ impl DaemonState {
         let _ = reply_rx.await;
     }
 
-    /// Remove routing and stop ping tasks
+    /// Remove routing and stop ping and public ip tasks
     async fn teardown(&mut self) {
         self.cleanup_worker_resources().await;
         if let Some(ref mut child) = self.worker_child {
@@ -1301,6 +2567,8 @@ impl DaemonState {
 
     async fn cleanup_worker_resources(&mut self) {
         self.ping_tasks.shutdown().await;
+        self.public_ip_tasks.shutdown().await;
+        self.exit_report_tasks.shutdown().await;
         self.teardown_any_routing().await;
         self.pending_responses.clear();
         let _ = self
@@ -1309,6 +2577,36 @@ impl DaemonState {
             .await;
     }
 
+    /// Looks for other VPN clients, delegated routing leaves the routing table to them anyway.
+    async fn detect_conflicts(&mut self) {
+        if self.routing_mode != routing::Mode::Managed {
+            return;
+        }
+        let found = conflicts::detect().await;
+        for conflict in found.iter().filter(|c| !self.conflicts.contains(c)) {
+            tracing::warn!(%conflict, suggested_mode = %conflict.suggested_mode, "found conflicting VPN client");
+        }
+        self.conflicts = found;
+    }
+
+    /// Names clients holding default routes as the likely cause of a failed route setup.
+    fn blame_conflicts(&self, error: String) -> String {
+        let culprits: Vec<String> = self
+            .conflicts
+            .iter()
+            .filter(|c| c.default_route)
+            .map(|c| c.to_string())
+            .collect();
+        if culprits.is_empty() {
+            return error;
+        }
+        format!(
+            "{error} - likely caused by a conflicting VPN client: {} - stop it or use routing mode {}",
+            culprits.join(", "),
+            conflicts::SuggestedMode::Delegated
+        )
+    }
+
     async fn setup_static_routing(
         &self,
         wg_data: event::WireGuardData,
@@ -1318,9 +2616,16 @@ impl DaemonState {
         let _ = self
             .routing_actor_sender
             .send(routing_actor::Msg::SetupRouting {
-                state_home: self.worker_params.state_home(),
+                cache_home: self.worker_params.cache_home(),
                 wg_data: Box::new(wg_data),
                 peer_ips,
+                dscp: self.config.connection.dscp.map(|dscp| routing::dscp::Marking {
+                    uid: self.worker_user.uid,
+                    dscp,
+                }),
+                gateway_lan: self.config.connection.gateway.as_ref().map(|g| g.lan_interface.clone()),
+                // commands run as root, never taken from a management policy
+                wg_tooling: self.local_config.wireguard.tooling.clone(),
                 reply: reply_tx,
             })
             .await;
@@ -1342,15 +2647,19 @@ impl DaemonState {
             }
             WorkerCommand::Disconnect => {
                 tracing::debug!("clearing target destination from disconnect command");
-                self.target_dest_id = None;
-                self.worker_params.set_cached_blokli_ips(Vec::new());
-                self.disable_killswitch().await;
-                let _ = self
-                    .keep_alive_instruction_sender
-                    .send(KeepAliveInstruction::Resume)
-                    .await;
+                self.clear_target().await;
             }
             _ => (),
         }
     }
+
+    async fn clear_target(&mut self) {
+        self.target_dest_id = None;
+        self.worker_params.set_cached_blokli_ips(Vec::new());
+        self.disable_killswitch().await;
+        let _ = self
+            .keep_alive_instruction_sender
+            .send(KeepAliveInstruction::Resume)
+            .await;
+    }
 }
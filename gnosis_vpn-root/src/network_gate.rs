@@ -0,0 +1,102 @@
+//! Holds back the first worker start until the network is usable.
+//!
+//! Units ordered before network-online start the service while interfaces are still coming up.
+//! The worker would burn its chain endpoint retries and log errors on every boot, so root first
+//! waits for an IPv4 default route and a DNS answer for the blokli host. Once the configured
+//! timeout passes the worker starts regardless and its own retries take over.
+//! Kill switch mode blocks lookups of root itself, only the default route is awaited then.
+
+use tokio::net;
+use tokio::time::{self, Instant};
+
+use std::fmt::{self, Display};
+use std::future::Future;
+use std::time::Duration;
+
+use crate::network_info;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub enum Outcome {
+    Online { waited: Duration },
+    TimedOut { waited: Duration, reason: Unavailable },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Unavailable {
+    NoDefaultRoute,
+    DnsFailing(String),
+}
+
+impl Display for Unavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unavailable::NoDefaultRoute => write!(f, "no IPv4 default route"),
+            Unavailable::DnsFailing(error) => write!(f, "DNS lookup failing: {error}"),
+        }
+    }
+}
+
+/// Polls until `host` resolves via a default route or `timeout` passes, without `host` a default route suffices.
+pub async fn wait(host: Option<String>, timeout: Duration) -> Outcome {
+    poll(timeout, || check(host.as_deref())).await
+}
+
+async fn poll<F, Fut>(timeout: Duration, mut probe: F) -> Outcome
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), Unavailable>>,
+{
+    let started = Instant::now();
+    loop {
+        let reason = match probe().await {
+            Ok(()) => {
+                return Outcome::Online {
+                    waited: started.elapsed(),
+                };
+            }
+            Err(reason) => reason,
+        };
+        if started.elapsed() + POLL_INTERVAL > timeout {
+            return Outcome::TimedOut {
+                waited: started.elapsed(),
+                reason,
+            };
+        }
+        tracing::debug!(%reason, "network not usable yet");
+        time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn check(host: Option<&str>) -> Result<(), Unavailable> {
+    if network_info::gather_ipv4_route().await.is_none() {
+        return Err(Unavailable::NoDefaultRoute);
+    }
+    let Some(host) = host else {
+        return Ok(());
+    };
+    match time::timeout(LOOKUP_TIMEOUT, net::lookup_host((host, 443))).await {
+        Ok(Ok(mut addrs)) if addrs.next().is_some() => Ok(()),
+        Ok(Ok(_)) => Err(Unavailable::DnsFailing(format!("no addresses for {host}"))),
+        Ok(Err(error)) => Err(Unavailable::DnsFailing(error.to_string())),
+        Err(_) => Err(Unavailable::DnsFailing(format!("no answer within {LOOKUP_TIMEOUT:?}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn gives_up_after_timeout() {
+        let probe = || async { Err(Unavailable::DnsFailing("no answer".to_string())) };
+        match poll(Duration::ZERO, probe).await {
+            Outcome::TimedOut { reason, .. } => {
+                assert_eq!(reason, Unavailable::DnsFailing("no answer".to_string()))
+            }
+            Outcome::Online { .. } => panic!("failing probe must not pass the gate"),
+        }
+    }
+}
@@ -71,7 +71,7 @@ fn format_dns(servers: &[String]) -> String {
 // ============================================================================
 
 #[cfg(target_os = "linux")]
-async fn gather_ipv4_route() -> Option<RouteInfo> {
+pub(crate) async fn gather_ipv4_route() -> Option<RouteInfo> {
     let route_output = Command::new("ip")
         .args(["-4", "route", "show", "default"])
         .run_stdout(Logs::Suppress)
@@ -117,7 +117,7 @@ async fn gather_ipv6_route() -> Option<RouteInfo> {
 // ============================================================================
 
 #[cfg(target_os = "macos")]
-async fn gather_ipv4_route() -> Option<RouteInfo> {
+pub(crate) async fn gather_ipv4_route() -> Option<RouteInfo> {
     let route_output = Command::new("route")
         .args(["-n", "get", "0.0.0.0"])
         .run_stdout(Logs::Suppress)
@@ -0,0 +1,184 @@
+//! Read-only observer for monitoring agents running as a separate, less privileged unit.
+//!
+//! The observer listens on its own socket and forwards queries to the service socket, so it only
+//! needs to be a member of the socket group. Its own socket is restricted to that group as well.
+//! Commands changing service or node state are refused, as are queries answered with secrets or
+//! log output. While the service is not reachable status, info and destinations are answered from
+//! the configuration file and the data directory.
+
+use tokio::fs;
+use tokio::sync::mpsc;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use gnosis_vpn_lib::command::{self, Command as LibCommand, Response};
+use gnosis_vpn_lib::config::{self, Config};
+use gnosis_vpn_lib::{dirs, disk_space, i18n, logging, socket};
+
+use crate::{SignalMessage, SocketCmd, cli, signal_channel, socket_listener};
+
+pub const ENV_VAR: &str = "GNOSISVPN_OBSERVER_SOCKET";
+/// Members of the socket group may query the observer, like the service socket it forwards to.
+const SOCKET_MODE: u32 = 0o660;
+
+struct Observer {
+    service_socket: PathBuf,
+    config_path: PathBuf,
+    config: Option<Config>,
+    locations: dirs::Locations,
+}
+
+pub async fn run(args: cli::Cli, observer_socket: PathBuf) -> Result<(), exitcode::ExitCode> {
+    logging::setup_stdout();
+    tracing::info!(
+        version = env!("CARGO_PKG_VERSION"),
+        observer_socket = %observer_socket.display(),
+        "starting {} in observer mode",
+        env!("CARGO_PKG_NAME")
+    );
+
+    // the service may be down, offline answers are simply less detailed without a configuration
+    let config = config::read(&args.config_path)
+        .await
+        .map_err(|error| tracing::warn!(?error, "unable to read configuration file"))
+        .ok();
+    let socket_config = config.as_ref().map(|c| c.socket.clone()).unwrap_or_default();
+    let service_socket = socket::root::resolve_path(args.socket_path.clone(), socket_config.path.clone());
+    if service_socket == observer_socket {
+        tracing::error!(socket_path = %service_socket.display(), "observer socket must differ from the service socket");
+        return Err(exitcode::CONFIG);
+    }
+    let configured_dirs = config.as_ref().map(|c| c.dirs.clone()).unwrap_or_default();
+    let locations = dirs::Locations::resolve(args.dirs(), &configured_dirs, |var| std::env::var(var).ok());
+
+    let (cancel_signal_handlers, mut signal_receiver) = signal_channel().await?;
+    let (sender, mut receiver) = mpsc::channel(32);
+    let listen_config = socket::root::Config {
+        mode: SOCKET_MODE,
+        ..socket_config
+    };
+    let cancel_socket_listener = socket_listener(&observer_socket, &listen_config, sender).await?;
+
+    let observer = Arc::new(Observer {
+        service_socket,
+        config_path: args.config_path,
+        config,
+        locations,
+    });
+    tracing::info!(service_socket = %observer.service_socket.display(), "observing service");
+    loop {
+        tokio::select! {
+            Some(signal) = signal_receiver.recv() => match signal {
+                SignalMessage::Shutdown => break,
+                SignalMessage::RotateLogs => (),
+            },
+            Some(SocketCmd { cmd, resp, .. }) = receiver.recv() => {
+                let observer = observer.clone();
+                tokio::spawn(async move {
+                    let _ = resp.send(observer.respond(cmd).await);
+                });
+            }
+            else => break,
+        }
+    }
+
+    cancel_socket_listener.cancel();
+    cancel_signal_handlers.cancel();
+    if socket::root::abstract_name(&observer_socket).is_none() {
+        let _ = fs::remove_file(&observer_socket).await.map_err(|err| {
+            tracing::error!(error = ?err, "failed removing observer socket on shutdown");
+        });
+    }
+    Ok(())
+}
+
+impl Observer {
+    async fn respond(&self, cmd: LibCommand) -> Response {
+        if !cmd.is_read_only() {
+            tracing::info!(command = ?cmd, "refusing mutating command");
+            return Response::Refused("the observer only answers queries - use the service socket instead".to_string());
+        }
+        if cmd.is_private() {
+            tracing::info!(command = ?cmd, "refusing private query");
+            return Response::Refused(
+                "the observer does not hand out secrets or logs - use the service socket instead".to_string(),
+            );
+        }
+        match socket::root::process_cmd(&self.service_socket, &cmd).await {
+            Ok(resp) => resp,
+            Err(error) => {
+                tracing::debug!(%error, "service unreachable - answering from persisted state");
+                self.offline(cmd).await
+            }
+        }
+    }
+
+    async fn offline(&self, cmd: LibCommand) -> Response {
+        match cmd {
+            LibCommand::Status => Response::status(self.offline_status().await),
+            LibCommand::Info => Response::Info(command::InfoResponse {
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                log_file: None,
+                package_version: None,
+                config_file: Some(self.config_path.clone()),
+                state_home: Some(self.locations.state_home.clone()),
+                cache_home: Some(self.locations.cache_home.clone()),
+                node: None,
+            }),
+            LibCommand::Destinations => {
+                let mut ids: Vec<String> = self
+                    .config
+                    .iter()
+                    .flat_map(|c| c.destinations.keys().cloned())
+                    .collect();
+                ids.sort_unstable();
+                Response::Destinations(ids)
+            }
+            _ => Response::WorkerOffline,
+        }
+    }
+
+    async fn offline_status(&self) -> command::StatusResponse {
+        let mut destinations: Vec<command::DestinationState> = self
+            .config
+            .iter()
+            .flat_map(|c| c.destinations.values())
+            .map(|dest| command::DestinationState {
+                destination: dest.clone(),
+                route_health: None,
+            })
+            .collect();
+        destinations.sort_unstable_by(|a, b| a.destination.id.cmp(&b.destination.id));
+        let min_free = self
+            .config
+            .as_ref()
+            .map(|c| c.disk_space.clone())
+            .unwrap_or_default()
+            .min_free;
+        let disk_space = disk_space::sample(&self.locations.state_home, min_free)
+            .await
+            .map_err(|error| tracing::debug!(%error, "unable to determine free disk space"))
+            .ok();
+        let mut status = command::StatusResponse {
+            run_mode: command::RunMode::NotRunning,
+            destinations,
+            target_destination: None,
+            connecting: None,
+            reconnecting: None,
+            connected: None,
+            disconnecting: vec![],
+            budget: None,
+            manual_channel_funding: self.config.as_ref().is_some_and(Config::manual_channel_funding),
+            retrying: vec![],
+            previous_crash: None,
+            disk_space,
+            conflicts: vec![],
+            kill_switch_error: None,
+            hints: vec![],
+        };
+        let locale = i18n::Locale::resolve(self.config.as_ref().and_then(|c| c.locale.as_deref()));
+        status.hints = status.generate_hints(std::time::SystemTime::now(), locale);
+        status
+    }
+}
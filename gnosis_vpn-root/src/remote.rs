@@ -0,0 +1,308 @@
+//! TLS listener for paired remote gnosis_vpn-ctl clients.
+//!
+//! Pairing is confirmed out of band: the service logs a six digit code which the admin enters
+//! on the client. Only one pairing runs at a time and a failed one delays the next, so codes
+//! cannot be guessed by hammering the listener. Authenticated commands are handed to the main
+//! loop exactly like commands from the local socket.
+
+use gnosis_vpn_lib::command::{Command, Response};
+use gnosis_vpn_lib::socket::remote::{self, Identity, PairedClients, Reply, Request};
+use gnosis_vpn_lib::socket::root::Limits;
+use tokio::fs;
+use tokio::io::{AsyncBufRead, AsyncWrite, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::{Mutex, mpsc, oneshot};
+use tokio::task::JoinSet;
+use tokio::time;
+use tokio_util::sync::CancellationToken;
+
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::SocketCmd;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+const FAILED_PAIRING_DELAY: Duration = Duration::from_secs(5);
+
+struct Shared {
+    clients: Arc<Mutex<PairedClients>>,
+    // held for the whole pairing conversation
+    pairing: Mutex<()>,
+    fingerprint: String,
+    pairing_code: fn() -> String,
+    sender: mpsc::Sender<SocketCmd>,
+}
+
+/// Listen on `addr`, the identity is kept in `dir` next to the file backing `clients`.
+pub async fn listen(
+    addr: SocketAddr,
+    dir: &Path,
+    clients: Arc<Mutex<PairedClients>>,
+    sender: mpsc::Sender<SocketCmd>,
+) -> Result<CancellationToken, exitcode::ExitCode> {
+    let identity = Identity::load_or_create(dir).map_err(|error| {
+        tracing::error!(?error, dir = %dir.display(), "unable to set up remote listener identity");
+        exitcode::CANTCREAT
+    })?;
+    let listener = TcpListener::bind(addr).await.map_err(|error| {
+        tracing::error!(?error, %addr, "error binding remote listener");
+        exitcode::OSERR
+    })?;
+    tracing::info!(%addr, fingerprint = %identity.fingerprint(), "listening for remote clients");
+    serve(listener, &identity, clients, sender, remote::pairing_code)
+}
+
+/// Earlier versions kept identity and paired clients in the state home, where the worker could
+/// replace them. Neither is trusted anymore, paired clients have to pair again.
+pub async fn discard_legacy(state_home: &Path) {
+    let legacy = state_home.join(remote::DIR_NAME);
+    if fs::try_exists(&legacy).await.unwrap_or(false) {
+        tracing::warn!(dir = %legacy.display(), "discarding remote listener identity from the state home - remote clients have to pair again");
+        if let Err(error) = fs::remove_dir_all(&legacy).await {
+            tracing::error!(?error, dir = %legacy.display(), "unable to remove legacy remote listener identity");
+        }
+    }
+}
+
+/// Serve remote clients on `listener` until the returned token is cancelled.
+fn serve(
+    listener: TcpListener,
+    identity: &Identity,
+    clients: Arc<Mutex<PairedClients>>,
+    sender: mpsc::Sender<SocketCmd>,
+    pairing_code: fn() -> String,
+) -> Result<CancellationToken, exitcode::ExitCode> {
+    let acceptor = identity.acceptor().map_err(|error| {
+        tracing::error!(?error, "unable to set up remote listener TLS");
+        exitcode::SOFTWARE
+    })?;
+    let shared = Arc::new(Shared {
+        clients,
+        pairing: Mutex::new(()),
+        fingerprint: identity.fingerprint(),
+        pairing_code,
+        sender,
+    });
+
+    let mut ongoing = JoinSet::new();
+    let cancel = CancellationToken::new();
+    let owned_cancel = cancel.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                Ok((stream, peer)) = listener.accept() => {
+                    let acceptor = acceptor.clone();
+                    let shared = shared.clone();
+                    ongoing.spawn(async move {
+                        match time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                            Ok(Ok(tls)) => incoming(BufReader::new(tls), peer, &shared).await,
+                            Ok(Err(error)) => tracing::warn!(?error, %peer, "remote TLS handshake failed"),
+                            Err(_) => tracing::warn!(%peer, "remote TLS handshake timed out"),
+                        }
+                    });
+                },
+                _ = cancel.cancelled() => {
+                    tracing::debug!("remote listener received cancellation");
+                    ongoing.shutdown().await;
+                    break;
+                }
+                else => {
+                    tracing::warn!("remote listener streams closed");
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(owned_cancel)
+}
+
+async fn incoming<S: AsyncBufRead + AsyncWrite + Unpin>(mut stream: S, peer: SocketAddr, shared: &Shared) {
+    let request = match remote::receive::<_, Request>(&mut stream, Limits::REQUEST).await {
+        Ok(Some(request)) => request,
+        Ok(None) => return,
+        Err(error) => {
+            tracing::warn!(%error, %peer, "error reading remote request");
+            return;
+        }
+    };
+    let reply = match request {
+        Request::Pair { name } => return pair(stream, peer, name, shared).await,
+        Request::Confirm { .. } => Reply::Denied("no pairing in progress".to_string()),
+        Request::Command { token, command } => {
+            let client = shared.clients.lock().await.verify(&token).map(ToString::to_string);
+            match client {
+                // archives are written and the configuration rewritten on this host with root privileges
+                Some(_) if command.is_local_only() => Reply::Denied(
+                    "backups, destination exports and imports are only accepted on the local socket".to_string(),
+                ),
+                Some(client) => {
+                    tracing::debug!(%peer, %client, ?command, "received remote command");
+                    match forward(command, &shared.sender).await {
+                        Some(resp) => Reply::Response(resp),
+                        None => return,
+                    }
+                }
+                None => {
+                    tracing::warn!(%peer, "rejected remote command with unknown token");
+                    Reply::Denied("unknown token - pair this client again".to_string())
+                }
+            }
+        }
+    };
+    if let Err(error) = remote::send(&mut stream, &reply).await {
+        tracing::warn!(%error, %peer, "failed to send remote reply");
+    }
+}
+
+async fn forward(cmd: Command, sender: &mpsc::Sender<SocketCmd>) -> Option<Response> {
+    let (resp_sender, resp_receiver) = oneshot::channel();
+    if let Err(err) = sender
+        .send(SocketCmd {
+            cmd,
+            peer_uid: None,
+            resp: resp_sender,
+        })
+        .await
+    {
+        tracing::error!(error = ?err, "failed to send remote command to main loop");
+        return None;
+    }
+    resp_receiver
+        .await
+        .map_err(|err| tracing::error!(error = ?err, "remote command response channel closed"))
+        .ok()
+}
+
+async fn pair<S: AsyncBufRead + AsyncWrite + Unpin>(mut stream: S, peer: SocketAddr, name: String, shared: &Shared) {
+    let Ok(_guard) = shared.pairing.try_lock() else {
+        let reply = Reply::Denied("another pairing is in progress".to_string());
+        let _ = remote::send(&mut stream, &reply).await;
+        return;
+    };
+    let code = (shared.pairing_code)();
+    tracing::warn!(%peer, %name, %code, fingerprint = %shared.fingerprint, "remote pairing requested - enter this code on the client to confirm");
+    if remote::send(&mut stream, &Reply::PairingStarted).await.is_err() {
+        return;
+    }
+
+    for attempt in 1..=remote::MAX_CODE_ATTEMPTS {
+        let confirmed = match remote::receive::<_, Request>(&mut stream, remote::PAIRING_LIMITS).await {
+            Ok(Some(Request::Confirm { code: entered })) => entered == code,
+            Ok(_) => break,
+            Err(error) => {
+                tracing::warn!(%error, %peer, "remote pairing aborted");
+                break;
+            }
+        };
+        if confirmed {
+            let token = shared.clients.lock().await.pair(name.clone());
+            tracing::info!(%peer, %name, "paired remote client");
+            let _ = remote::send(&mut stream, &Reply::Paired { token }).await;
+            return;
+        }
+        let reason = if attempt < remote::MAX_CODE_ATTEMPTS {
+            "wrong code - try again"
+        } else {
+            "wrong code - pairing cancelled"
+        };
+        if remote::send(&mut stream, &Reply::Denied(reason.to_string()))
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+
+    tracing::warn!(%peer, %name, "remote pairing failed");
+    time::sleep(FAILED_PAIRING_DELAY).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use gnosis_vpn_lib::socket::remote::Credentials;
+    use gnosis_vpn_lib::tls;
+
+    const CODE: &str = "123456";
+
+    type Listener = (String, String, Arc<Mutex<PairedClients>>, CancellationToken);
+
+    /// Listener on a free local port, every forwarded command is answered with `Pong`.
+    async fn start(dir: &Path) -> anyhow::Result<Listener> {
+        let identity = Identity::load_or_create(dir)?;
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?.to_string();
+        let (sender, mut receiver) = mpsc::channel::<SocketCmd>(1);
+        tokio::spawn(async move {
+            while let Some(cmd) = receiver.recv().await {
+                let _ = cmd.resp.send(Response::Pong);
+            }
+        });
+        let clients = Arc::new(Mutex::new(PairedClients::load(dir.join(remote::CLIENTS_FILE))));
+        let cancel = serve(listener, &identity, clients.clone(), sender, || CODE.to_string())
+            .map_err(|code| anyhow::anyhow!("listener failed with exit code {code}"))?;
+        Ok((addr, identity.fingerprint(), clients, cancel))
+    }
+
+    #[tokio::test]
+    async fn paired_client_sends_commands() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (addr, fingerprint, _, cancel) = start(dir.path()).await?;
+
+        let mut codes = ["000000", CODE].into_iter();
+        let mut denials = Vec::new();
+        let credentials = remote::pair(&addr, "laptop", |presented, denied| {
+            assert_eq!(presented, fingerprint);
+            denials.push(denied.map(ToString::to_string));
+            Ok(codes.next().unwrap_or_default().to_string())
+        })
+        .await?;
+        assert_eq!(denials, vec![None, Some("wrong code - try again".to_string())]);
+        assert_eq!(credentials.fingerprint, fingerprint);
+
+        let resp = remote::process_cmd(&addr, &credentials, &Command::Ping).await?;
+        assert!(matches!(resp, Response::Pong));
+        cancel.cancel();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn wrong_fingerprint_or_token_is_rejected() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (addr, _, _, cancel) = start(dir.path()).await?;
+        let credentials = remote::pair(&addr, "laptop", |_, _| Ok(CODE.to_string())).await?;
+
+        let pinned_elsewhere = Credentials {
+            fingerprint: tls::fingerprint(b"another certificate"),
+            ..credentials.clone()
+        };
+        let res = remote::process_cmd(&addr, &pinned_elsewhere, &Command::Ping).await;
+        assert!(matches!(res, Err(remote::Error::IO(_))));
+
+        let unknown_token = Credentials {
+            token: "revoked".to_string(),
+            ..credentials
+        };
+        let res = remote::process_cmd(&addr, &unknown_token, &Command::Ping).await;
+        assert!(matches!(res, Err(remote::Error::Denied(reason)) if reason.starts_with("unknown token")));
+        cancel.cancel();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unpaired_client_is_rejected() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (addr, _, clients, cancel) = start(dir.path()).await?;
+        let credentials = remote::pair(&addr, "laptop", |_, _| Ok(CODE.to_string())).await?;
+        assert_eq!(clients.lock().await.unpair("laptop"), 1);
+
+        let res = remote::process_cmd(&addr, &credentials, &Command::Ping).await;
+        assert!(matches!(res, Err(remote::Error::Denied(reason)) if reason.starts_with("unknown token")));
+        cancel.cancel();
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+//! Routing left to the surrounding environment, e.g. a container or pod network.
+//!
+//! Only the WireGuard interface is brought up, no bypass, split or IPv6 blackhole
+//! routes are installed and WAN changes never trigger a reconnect. Whoever runs the container decides
+//! which traffic enters the tunnel, creating the interface is all that needs `CAP_NET_ADMIN`.
+
+use async_trait::async_trait;
+
+use gnosis_vpn_lib::shell_command_ext::Logs;
+use gnosis_vpn_lib::{event, wireguard};
+
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+
+use super::wg_ops::{self, WgOps};
+use super::{Error, Routing};
+
+pub fn delegated_router(
+    cache_home: PathBuf,
+    wg_data: event::WireGuardData,
+    tooling: wireguard::Tooling,
+) -> Result<impl Routing, Error> {
+    let wg = wg_ops::select(tooling, wg_data.wg.config.dns_strategy)?;
+    Ok(DelegatedRouter {
+        cache_home,
+        wg_data,
+        wg,
+    })
+}
+
+struct DelegatedRouter<W: WgOps> {
+    cache_home: PathBuf,
+    wg_data: event::WireGuardData,
+    wg: W,
+}
+
+#[async_trait]
+impl<W: WgOps> Routing for DelegatedRouter<W> {
+    async fn setup(&mut self) -> Result<String, Error> {
+        let interface_name = self.wg.up(self.cache_home.clone(), &self.wg_data).await?;
+        tracing::info!(%interface_name, "tunnel interface is ready (routing delegated)");
+        Ok(interface_name)
+    }
+
+    async fn teardown(&mut self, logs: Logs) {
+        match self.wg.down(self.cache_home.clone(), logs).await {
+            Ok(_) => tracing::debug!("WireGuard interface down"),
+            Err(error) => tracing::warn!(?error, "WireGuard interface down failed during teardown"),
+        }
+    }
+
+    async fn wan_changed(&mut self) -> Result<bool, Error> {
+        // the environment owns the uplink, its route changes are not ours to react to
+        Ok(false)
+    }
+
+    async fn add_peer_bypass_route(&mut self, _ip: Ipv4Addr) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn remove_peer_bypass_route(&mut self, _ip: Ipv4Addr) -> Result<(), Error> {
+        Ok(())
+    }
+}
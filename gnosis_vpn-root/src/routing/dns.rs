@@ -0,0 +1,398 @@
+//! Tunnel DNS applied by the root process instead of wg-quick.
+//!
+//! wg-quick configures the `DNS =` servers through `resolvconf` on Linux and fails to bring the
+//! interface up on systems without it. Unless the configured [`DnsStrategy`] is `wg-quick` the
+//! generated configuration has no `DNS =` line, the servers are applied here once the interface
+//! exists and reverted on teardown. macOS only supports leaving DNS to wg-quick so far.
+//!
+//! With systemd-resolved the servers and the `~.` routing domain are set on the tunnel link through
+//! the `org.freedesktop.resolve1` D-Bus API (`SetLinkDNS`, `SetLinkDomains`), `RevertLink` restores
+//! the link on disconnect. `/etc/resolv.conf` stays untouched.
+//!
+//! The `file` strategy moves the original `/etc/resolv.conf` aside while connected. A backup left
+//! behind by a crash is restored by [`restore_leftover`] on the next start.
+
+#[cfg(target_os = "linux")]
+use gnosis_vpn_lib::wireguard::RESOLV_CONF_BACKUP;
+use gnosis_vpn_lib::wireguard::{self, DnsStrategy};
+
+#[cfg(any(target_os = "linux", test))]
+use std::net::IpAddr;
+
+use super::Error;
+
+#[cfg(target_os = "linux")]
+use super::{COMMAND_TIMEOUT, QUERY, command};
+#[cfg(target_os = "linux")]
+use gnosis_vpn_lib::shell_command_ext::{self, Logs, ShellCommandExt};
+#[cfg(target_os = "linux")]
+use tokio::fs;
+#[cfg(target_os = "linux")]
+use tokio::io::AsyncWriteExt;
+#[cfg(target_os = "linux")]
+use tokio::process::Command;
+
+#[cfg(target_os = "linux")]
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+/// First line of the generated `/etc/resolv.conf`, tells it apart from one the host had before.
+#[cfg(any(target_os = "linux", test))]
+const GENERATED_HEADER: &str = "# generated by gnosisvpn, the original is restored on disconnect\n";
+/// Destination, object path and interface of the systemd-resolved manager on the system bus.
+#[cfg(target_os = "linux")]
+const RESOLVE1: [&str; 3] = [
+    "org.freedesktop.resolve1",
+    "/org/freedesktop/resolve1",
+    "org.freedesktop.resolve1.Manager",
+];
+/// Routing domain sending every lookup to the link, not only those of its search domains.
+#[cfg(any(target_os = "linux", test))]
+const ROUTE_ALL_DOMAIN: &str = "~.";
+/// Debian's resolvconf ranks interfaces by this file, wg-quick uses the `tun.` prefix if it is listed.
+#[cfg(target_os = "linux")]
+const RESOLVCONF_INTERFACE_ORDER: &str = "/etc/resolvconf/interface-order";
+
+/// DNS servers of the tunnel and how to apply them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dns {
+    pub strategy: DnsStrategy,
+    pub servers: Vec<String>,
+}
+
+impl Dns {
+    /// `None` if wg-quick takes care of DNS or there is nothing to apply.
+    pub fn from_config(config: &wireguard::Config) -> Option<Dns> {
+        if config.dns_strategy.uses_wg_quick() || config.dns_strategy == DnsStrategy::None {
+            return None;
+        }
+        let servers: Vec<String> = config
+            .dns
+            .as_deref()?
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect();
+        if servers.is_empty() {
+            return None;
+        }
+        Some(Dns {
+            strategy: config.dns_strategy,
+            servers,
+        })
+    }
+}
+
+/// DNS applied to an interface with the strategy resolved for this host, reverted by [`remove`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub struct Installed {
+    strategy: DnsStrategy,
+    interface: String,
+}
+
+#[cfg(any(target_os = "linux", test))]
+fn resolv_conf(servers: &[String]) -> String {
+    let mut content = GENERATED_HEADER.to_string();
+    for server in servers {
+        content.push_str(&format!("nameserver {server}\n"));
+    }
+    content
+}
+
+/// `busctl` arguments for `SetLinkDNS`, signature `ia(iay)`: link index, then family and address bytes per server.
+#[cfg(any(target_os = "linux", test))]
+fn link_dns_args(ifindex: u32, servers: &[IpAddr]) -> Vec<String> {
+    let mut args = vec![ifindex.to_string(), servers.len().to_string()];
+    for server in servers {
+        let (family, octets) = match server {
+            IpAddr::V4(ip) => (libc::AF_INET, ip.octets().to_vec()),
+            IpAddr::V6(ip) => (libc::AF_INET6, ip.octets().to_vec()),
+        };
+        args.push(family.to_string());
+        args.push(octets.len().to_string());
+        args.extend(octets.iter().map(u8::to_string));
+    }
+    args
+}
+
+/// `busctl` arguments for `SetLinkDomains`, signature `ia(sb)`: link index, then domain and routing-only flag.
+#[cfg(any(target_os = "linux", test))]
+fn link_domains_args(ifindex: u32) -> Vec<String> {
+    vec![
+        ifindex.to_string(),
+        "1".to_string(),
+        ROUTE_ALL_DOMAIN.to_string(),
+        "true".to_string(),
+    ]
+}
+
+#[cfg(any(target_os = "linux", test))]
+fn resolvconf_name(interface: &str, interface_order: Option<&str>) -> String {
+    let tun_listed = interface_order.is_some_and(|order| order.lines().any(|line| line.trim().starts_with("tun")));
+    if tun_listed {
+        format!("tun.{interface}")
+    } else {
+        interface.to_string()
+    }
+}
+
+/// `auto` prefers systemd-resolved if it manages `/etc/resolv.conf`, then resolvconf, then the file itself.
+#[cfg(target_os = "linux")]
+async fn resolve(strategy: DnsStrategy) -> DnsStrategy {
+    if strategy != DnsStrategy::Auto {
+        return strategy;
+    }
+    let resolved = fs::read_link(RESOLV_CONF)
+        .await
+        .is_ok_and(|target| target.to_string_lossy().contains("systemd/resolve"));
+    if resolved {
+        return DnsStrategy::SystemdResolved;
+    }
+    if Command::new("which").arg("resolvconf").run(QUERY).await.is_ok() {
+        return DnsStrategy::Resolvconf;
+    }
+    DnsStrategy::File
+}
+
+#[cfg(target_os = "linux")]
+pub async fn apply(dns: Dns, interface: &str) -> Result<Installed, Error> {
+    let strategy = resolve(dns.strategy).await;
+    let installed = Installed {
+        strategy,
+        interface: interface.to_string(),
+    };
+    let res = match strategy {
+        DnsStrategy::SystemdResolved => apply_resolved(&dns.servers, interface).await,
+        DnsStrategy::Resolvconf => apply_resolvconf(&dns.servers, interface).await,
+        DnsStrategy::File => apply_file(&dns.servers).await,
+        DnsStrategy::Auto | DnsStrategy::WgQuick | DnsStrategy::None => Ok(()),
+    };
+    match res {
+        Ok(()) => {
+            tracing::info!(?strategy, servers = ?dns.servers, "tunnel DNS applied");
+            Ok(installed)
+        }
+        Err(error) => {
+            remove(installed).await;
+            Err(error)
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub async fn remove(installed: Installed) {
+    match installed.strategy {
+        DnsStrategy::SystemdResolved => {
+            // resolved forgets a link together with the interface, nothing to revert then
+            if let Some(ifindex) = ifindex(&installed.interface)
+                && let Err(error) = resolve1("RevertLink", "i", vec![ifindex.to_string()]).await
+            {
+                tracing::warn!(?error, "failed to revert tunnel link DNS in systemd-resolved");
+            }
+        }
+        DnsStrategy::Resolvconf => {
+            let name = resolvconf_name(&installed.interface, interface_order().await.as_deref());
+            if let Err(error) = Command::new("resolvconf")
+                .args(["-d", &name, "-f"])
+                .run(command(Logs::Print))
+                .await
+            {
+                tracing::warn!(?error, "failed to remove tunnel DNS from resolvconf");
+            }
+        }
+        DnsStrategy::File => match restore_original().await {
+            Ok(true) => tracing::debug!("restored original resolv.conf"),
+            Ok(false) => (),
+            Err(error) => tracing::warn!(?error, "failed to restore original resolv.conf"),
+        },
+        DnsStrategy::Auto | DnsStrategy::WgQuick | DnsStrategy::None => (),
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn apply_resolved(servers: &[String], interface: &str) -> Result<(), Error> {
+    let ifindex = ifindex(interface).ok_or_else(|| Error::General(format!("interface '{interface}' not found")))?;
+    let servers = servers
+        .iter()
+        .map(|s| s.parse::<IpAddr>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| Error::General(format!("invalid DNS server address: {e}")))?;
+    resolve1("SetLinkDNS", "ia(iay)", link_dns_args(ifindex, &servers)).await?;
+    resolve1("SetLinkDomains", "ia(sb)", link_domains_args(ifindex)).await?;
+    // older systemd versions lack this, there the routing domain alone makes the link the default
+    if let Err(error) = resolve1(
+        "SetLinkDefaultRoute",
+        "ib",
+        vec![ifindex.to_string(), "true".to_string()],
+    )
+    .await
+    {
+        tracing::debug!(?error, "unable to mark the tunnel link as default DNS route");
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+async fn resolve1(method: &str, signature: &str, args: Vec<String>) -> Result<(), Error> {
+    Command::new("busctl")
+        .arg("call")
+        .args(RESOLVE1)
+        .args([method, signature])
+        .args(args)
+        .run(command(Logs::Print))
+        .await?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn ifindex(interface: &str) -> Option<u32> {
+    let name = std::ffi::CString::new(interface).ok()?;
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => None,
+        index => Some(index),
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn apply_resolvconf(servers: &[String], interface: &str) -> Result<(), Error> {
+    let name = resolvconf_name(interface, interface_order().await.as_deref());
+    let mut child = Command::new("resolvconf")
+        .args(["-a", &name, "-m", "0", "-x"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(resolv_conf(servers).as_bytes()).await?;
+    }
+    let output = tokio::time::timeout(COMMAND_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| shell_command_ext::Error::TimedOut(COMMAND_TIMEOUT))??;
+    shell_command_ext::stdout_from_output(format!("resolvconf -a {name}"), output, Logs::Print)?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+async fn apply_file(servers: &[String]) -> Result<(), Error> {
+    // a backup left behind by a crash is the original, it must not be replaced by our own file
+    if fs::symlink_metadata(RESOLV_CONF_BACKUP).await.is_err() {
+        match fs::rename(RESOLV_CONF, RESOLV_CONF_BACKUP).await {
+            Ok(()) => (),
+            // nothing to back up, restoring removes the generated file again
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => (),
+            Err(error) => return Err(error.into()),
+        }
+    }
+    fs::write(RESOLV_CONF, resolv_conf(servers)).await?;
+    Ok(())
+}
+
+/// Puts the original `/etc/resolv.conf` back, or removes the generated one if there was none.
+/// `false` if there was nothing to restore.
+#[cfg(target_os = "linux")]
+async fn restore_original() -> std::io::Result<bool> {
+    match fs::rename(RESOLV_CONF_BACKUP, RESOLV_CONF).await {
+        Ok(()) => Ok(true),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            let generated = fs::read_to_string(RESOLV_CONF)
+                .await
+                .is_ok_and(|content| is_generated(&content));
+            if generated {
+                fs::remove_file(RESOLV_CONF).await?;
+            }
+            Ok(generated)
+        }
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(any(target_os = "linux", test))]
+fn is_generated(content: &str) -> bool {
+    content.starts_with(GENERATED_HEADER)
+}
+
+/// Restores `/etc/resolv.conf` if a previous run exited while the `file` strategy was applied.
+#[cfg(target_os = "linux")]
+pub async fn restore_leftover() {
+    match restore_original().await {
+        Ok(true) => tracing::warn!("restored resolv.conf left behind by a previous run"),
+        Ok(false) => (),
+        Err(error) => tracing::warn!(?error, "failed to restore resolv.conf left behind by a previous run"),
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn interface_order() -> Option<String> {
+    fs::read_to_string(RESOLVCONF_INTERFACE_ORDER).await.ok()
+}
+
+#[cfg(target_os = "macos")]
+pub async fn apply(_dns: Dns, _interface: &str) -> Result<Installed, Error> {
+    Err(Error::DnsUnsupported)
+}
+
+#[cfg(target_os = "macos")]
+pub async fn remove(_installed: Installed) {}
+
+#[cfg(target_os = "macos")]
+pub async fn restore_leftover() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(dns: Option<&str>, dns_strategy: DnsStrategy) -> wireguard::Config {
+        wireguard::Config {
+            listen_port: None,
+            force_private_key: None,
+            allowed_ips: None,
+            dns: dns.map(str::to_string),
+            dns_strategy,
+            tooling: wireguard::Tooling::default(),
+            block_ipv6: wireguard::BlockIpv6::default(),
+        }
+    }
+
+    #[test]
+    fn servers_are_split_and_wg_quick_is_left_alone() {
+        let dns = Dns::from_config(&config(Some("1.1.1.1, 8.8.8.8"), DnsStrategy::File)).expect("servers");
+        assert_eq!(dns.servers, vec!["1.1.1.1", "8.8.8.8"]);
+        assert!(Dns::from_config(&config(Some("1.1.1.1"), DnsStrategy::WgQuick)).is_none());
+        assert!(Dns::from_config(&config(Some("1.1.1.1"), DnsStrategy::None)).is_none());
+        assert!(Dns::from_config(&config(None, DnsStrategy::SystemdResolved)).is_none());
+    }
+
+    #[test]
+    fn resolv_conf_lists_servers_in_order() {
+        let content = resolv_conf(&["1.1.1.1".to_string(), "8.8.8.8".to_string()]);
+        let servers: Vec<&str> = content.lines().filter(|l| !l.starts_with('#')).collect();
+        assert_eq!(servers, vec!["nameserver 1.1.1.1", "nameserver 8.8.8.8"]);
+        assert!(is_generated(&content));
+        assert!(!is_generated("nameserver 192.168.1.1\n"));
+    }
+
+    #[test]
+    fn resolve1_link_arguments() {
+        let servers = ["1.1.1.1".parse().unwrap(), "2606:4700::1111".parse().unwrap()];
+        let args = link_dns_args(7, &servers).join(" ");
+        assert_eq!(
+            args,
+            format!(
+                "7 2 {} 4 1 1 1 1 {} 16 38 6 71 0 0 0 0 0 0 0 0 0 0 0 17 17",
+                libc::AF_INET,
+                libc::AF_INET6
+            )
+        );
+        assert_eq!(link_domains_args(7).join(" "), "7 1 ~. true");
+    }
+
+    #[test]
+    fn resolvconf_name_follows_interface_order() {
+        assert_eq!(resolvconf_name("wg0_gnosisvpn", None), "wg0_gnosisvpn");
+        assert_eq!(
+            resolvconf_name("wg0_gnosisvpn", Some("lo.inet6\ntun*\ntap*\n")),
+            "tun.wg0_gnosisvpn"
+        );
+    }
+}
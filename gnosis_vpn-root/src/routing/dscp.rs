@@ -0,0 +1,144 @@
+//! DSCP marking of the encapsulated HOPR transport traffic.
+//!
+//! The WireGuard packets never leave the host directly: they are handed to the worker over
+//! loopback and re-segmented into HOPR packets. Any DSCP value of the inner packets is lost
+//! on that way, so the best we can do is mark everything the worker user sends out.
+//!
+//! On Linux a dedicated nftables table (`gnosis_vpn_qos`) rewrites the DSCP field of all
+//! UDP packets owned by the worker uid. macOS is not supported yet.
+
+use super::Error;
+
+#[cfg(target_os = "linux")]
+use std::ffi::{CStr, CString};
+
+#[cfg(target_os = "linux")]
+use gnosis_vpn_lib::nftables::{self, PayloadLoad, PayloadWrite};
+#[cfg(target_os = "linux")]
+use nftnl::{Batch, Chain, FinalizedBatch, Hook, MsgType, ProtoFamily, Rule, Table, expr, nft_expr};
+
+#[cfg(target_os = "linux")]
+pub(crate) const TABLE_NAME: &CStr = c"gnosis_vpn_qos";
+#[cfg(target_os = "linux")]
+const CHAIN_NAME: &CStr = c"output";
+/// Same as `priority mangle` in nft.
+#[cfg(target_os = "linux")]
+const MANGLE_PRIORITY: i32 = -150;
+/// Offset of the IPv4 header checksum.
+#[cfg(target_os = "linux")]
+const IPV4_CHECKSUM_OFFSET: u32 = 10;
+
+/// DSCP codepoint applied to packets sent by a given user.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Marking {
+    pub uid: u32,
+    pub dscp: u8,
+}
+
+/// Mask and xor setting the DSCP bits of the IPv4 TOS byte, the ECN bits are kept.
+#[cfg(any(target_os = "linux", test))]
+fn ipv4_rewrite(dscp: u8) -> (u8, u8) {
+    (0x03, dscp << 2)
+}
+
+/// Mask and xor setting the DSCP bits of the first two IPv6 header bytes, where the traffic class
+/// sits between the version and the flow label. Version, ECN and flow label are kept.
+#[cfg(any(target_os = "linux", test))]
+fn ipv6_rewrite(dscp: u8) -> ([u8; 2], [u8; 2]) {
+    ([0xf0, 0x3f], [dscp >> 2, (dscp & 0x03) << 6])
+}
+
+/// Batch replacing the marking table, so a changed uid or codepoint never leaves stale rules behind.
+#[cfg(target_os = "linux")]
+fn install_batch(marking: Marking) -> FinalizedBatch {
+    let table = Table::new(TABLE_NAME, ProtoFamily::Inet);
+    let mut batch = Batch::new();
+    // Add/Del/Add atomically replaces any existing table
+    batch.add(&table, MsgType::Add);
+    batch.add(&table, MsgType::Del);
+    batch.add(&table, MsgType::Add);
+
+    let mut chain = Chain::new(CHAIN_NAME, &table);
+    chain.set_hook(Hook::Out, MANGLE_PRIORITY);
+    batch.add(&chain, MsgType::Add);
+
+    let (mask, xor) = ipv4_rewrite(marking.dscp);
+    let mut rule = worker_udp_rule(&chain, marking.uid, libc::NFPROTO_IPV4 as u8);
+    rule.add_expr(&PayloadLoad { offset: 1, len: 1 });
+    rule.add_expr(&nft_expr!(bitwise mask mask, xor xor));
+    rule.add_expr(&PayloadWrite {
+        offset: 1,
+        len: 1,
+        checksum_offset: Some(IPV4_CHECKSUM_OFFSET),
+    });
+    batch.add(&rule, MsgType::Add);
+
+    let (mask, xor) = ipv6_rewrite(marking.dscp);
+    let mut rule = worker_udp_rule(&chain, marking.uid, libc::NFPROTO_IPV6 as u8);
+    rule.add_expr(&PayloadLoad { offset: 0, len: 2 });
+    rule.add_expr(&nft_expr!(bitwise mask &mask[..], xor &xor[..]));
+    rule.add_expr(&PayloadWrite {
+        offset: 0,
+        len: 2,
+        checksum_offset: None,
+    });
+    batch.add(&rule, MsgType::Add);
+
+    batch.finalize()
+}
+
+/// Rule matching UDP packets of `uid` leaving through anything but loopback.
+#[cfg(target_os = "linux")]
+fn worker_udp_rule<'a>(chain: &'a Chain<'_>, uid: u32, nfproto: u8) -> Rule<'a> {
+    let mut rule = Rule::new(chain);
+    rule.add_expr(&nft_expr!(meta skuid));
+    rule.add_expr(&nft_expr!(cmp == uid));
+    rule.add_expr(&nft_expr!(meta l4proto));
+    rule.add_expr(&nft_expr!(cmp == libc::IPPROTO_UDP as u8));
+    rule.add_expr(&nft_expr!(meta oifname));
+    rule.add_expr(&nft_expr!(cmp != expr::InterfaceName::Exact(CString::from(c"lo"))));
+    rule.add_expr(&nft_expr!(meta nfproto));
+    rule.add_expr(&nft_expr!(cmp == nfproto));
+    rule
+}
+
+#[cfg(target_os = "linux")]
+pub async fn apply(marking: Marking) -> Result<(), Error> {
+    nftables::send_batch(&install_batch(marking))?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+pub async fn remove() {
+    if let Err(error) = nftables::delete_table(TABLE_NAME) {
+        tracing::warn!(?error, "failed to remove DSCP marking table");
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub async fn apply(_marking: Marking) -> Result<(), Error> {
+    Err(Error::DscpUnsupported)
+}
+
+#[cfg(target_os = "macos")]
+pub async fn remove() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrites_dscp_and_keeps_surrounding_bits() {
+        // expedited forwarding
+        let dscp = 46;
+        let tos = 0b0000_0001;
+        let (mask, xor) = ipv4_rewrite(dscp);
+        assert_eq!((tos & mask) ^ xor, 0xb9);
+
+        // version 6, traffic class 0x01, flow label starting with 0xa
+        let header = [0x60, 0x1a];
+        let (mask, xor) = ipv6_rewrite(dscp);
+        let rewritten = [(header[0] & mask[0]) ^ xor[0], (header[1] & mask[1]) ^ xor[1]];
+        assert_eq!(rewritten, [0x6b, 0x9a]);
+    }
+}
@@ -0,0 +1,70 @@
+//! Kill switch block while the daemon runs without a tunnel.
+//!
+//! The killswitch of a connection only exists while that connection does. With `kill_switch`
+//! enabled this block covers the time in between: after a dropped tunnel, before the first
+//! connection and after a disconnect nothing but the worker user and the tunnel interface reach
+//! the network. The worker is let through by uid as its HOPR node needs peers and blokli to bring
+//! the tunnel back. Loopback, DHCP, IPv6 neighbor discovery and, unless `lan_lockdown` is set,
+//! the local network stay reachable like they do under the connection killswitch.
+//!
+//! With systemd-resolved the lookups of the worker leave the host as queries of the resolver
+//! user, so that user passes as well. Otherwise an endpoint given by name could not be resolved
+//! and the tunnel would never come back. The price is that DNS queries of other programs still
+//! reach the upstream servers while the block is engaged, their connections do not.
+//!
+//! On Linux the killswitch [`Firewall`] installs the block as a dedicated nftables table
+//! (`gnosis_vpn_block`) in one batch. macOS is not supported yet, the configuration refuses
+//! `kill_switch` there.
+
+use gnosis_vpn_lib::killswitch::Firewall;
+
+use super::Error;
+
+#[cfg(target_os = "linux")]
+const RESOLVER_USER: &str = "systemd-resolve";
+
+/// Traffic let through while the tunnel is down.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    /// Worker user, its HOPR traffic brings the tunnel back up
+    pub uid: u32,
+    /// systemd-resolved user if present, it resolves names on behalf of the worker
+    pub resolver_uid: Option<u32>,
+    /// WireGuard interface, allowed so a tunnel coming up is usable right away
+    pub interface: String,
+    pub lan_lockdown: bool,
+}
+
+/// Install `block`, replacing a block installed before in the same transaction.
+#[cfg(target_os = "linux")]
+pub fn apply(firewall: &mut Firewall, block: &Block) -> Result<(), Error> {
+    let uids: Vec<u32> = std::iter::once(block.uid).chain(block.resolver_uid).collect();
+    firewall.apply_block(&block.interface, &uids, block.lan_lockdown)?;
+    Ok(())
+}
+
+/// User systemd-resolved runs as, `None` if it is not installed.
+#[cfg(target_os = "linux")]
+pub fn resolver_uid() -> Option<u32> {
+    uzers::get_user_by_name(RESOLVER_USER).map(|user| user.uid())
+}
+
+#[cfg(target_os = "macos")]
+pub fn resolver_uid() -> Option<u32> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+pub fn remove(firewall: &mut Firewall) {
+    if let Err(error) = firewall.reset_block() {
+        tracing::warn!(?error, "failed to remove kill switch block");
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn apply(_firewall: &mut Firewall, _block: &Block) -> Result<(), Error> {
+    Err(Error::KillSwitchUnsupported)
+}
+
+#[cfg(target_os = "macos")]
+pub fn remove(_firewall: &mut Firewall) {}
@@ -0,0 +1,278 @@
+//! Local gateway mode: share the tunnel with a LAN.
+//!
+//! While the tunnel is up IPv4 forwarding is enabled and a dedicated nftables table
+//! (`gnosis_vpn_gateway`) accepts traffic from the LAN interface into the WireGuard interface
+//! and masquerades it behind the tunnel address. Replies are only let back in for established
+//! flows. Two dynamic sets keyed by LAN address count the traffic of every client.
+//!
+//! The killswitch already forwards into the tunnel and drops everything else, so LAN clients
+//! never leak to the WAN while it is active. macOS is not supported yet.
+
+use gnosis_vpn_lib::command::GatewayClient;
+
+use super::Error;
+
+#[cfg(target_os = "linux")]
+use super::QUERY;
+#[cfg(target_os = "linux")]
+use gnosis_vpn_lib::nftables::{self, DynsetUpdate};
+#[cfg(target_os = "linux")]
+use gnosis_vpn_lib::shell_command_ext::ShellCommandExt;
+#[cfg(target_os = "linux")]
+use nftnl::expr::{self, Masquerade, Verdict};
+#[cfg(target_os = "linux")]
+use nftnl::{Batch, Chain, ChainType, FinalizedBatch, Hook, MsgType, ProtoFamily, Rule, Table, nft_expr, set::Set};
+#[cfg(target_os = "linux")]
+use std::ffi::{CStr, CString};
+#[cfg(target_os = "linux")]
+use tokio::fs;
+#[cfg(target_os = "linux")]
+use tokio::process::Command;
+
+#[cfg(any(target_os = "linux", test))]
+use std::collections::BTreeMap;
+#[cfg(any(target_os = "linux", test))]
+use std::net::Ipv4Addr;
+
+#[cfg(target_os = "linux")]
+pub(crate) const TABLE_NAME: &CStr = c"gnosis_vpn_gateway";
+#[cfg(target_os = "linux")]
+const TX_SET: &CStr = c"tx";
+#[cfg(target_os = "linux")]
+const RX_SET: &CStr = c"rx";
+/// Ids referring to the sets within the batch creating them.
+#[cfg(target_os = "linux")]
+const TX_SET_ID: u32 = 1;
+#[cfg(target_os = "linux")]
+const RX_SET_ID: u32 = 2;
+/// Same as `priority srcnat` in nft.
+#[cfg(target_os = "linux")]
+const SRCNAT_PRIORITY: i32 = 100;
+#[cfg(target_os = "linux")]
+const IP_FORWARD: &str = "/proc/sys/net/ipv4/ip_forward";
+
+/// Forwarding from `lan_interface` into the tunnel on `wg_interface`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Gateway {
+    pub lan_interface: String,
+    pub wg_interface: String,
+}
+
+/// An applied gateway, remembers the forwarding setting to restore on removal.
+#[derive(Debug)]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub struct Installed {
+    pub gateway: Gateway,
+    forwarding_before: bool,
+}
+
+/// Batch replacing the gateway table, so a changed interface never leaves stale rules behind.
+#[cfg(target_os = "linux")]
+fn install_batch(gateway: &Gateway) -> Result<FinalizedBatch, Error> {
+    let table = Table::new(TABLE_NAME, ProtoFamily::Inet);
+    let mut batch = Batch::new();
+    // Add/Del/Add atomically replaces any existing table
+    batch.add(&table, MsgType::Add);
+    batch.add(&table, MsgType::Del);
+    batch.add(&table, MsgType::Add);
+
+    let tx = Set::<Ipv4Addr>::new(TX_SET, TX_SET_ID, &table, ProtoFamily::Inet);
+    nftables::make_dynamic(&tx);
+    batch.add(&tx, MsgType::Add);
+    let rx = Set::<Ipv4Addr>::new(RX_SET, RX_SET_ID, &table, ProtoFamily::Inet);
+    nftables::make_dynamic(&rx);
+    batch.add(&rx, MsgType::Add);
+
+    let mut forward = Chain::new(c"forward", &table);
+    forward.set_hook(Hook::Forward, 0);
+    batch.add(&forward, MsgType::Add);
+
+    let mut rule = Rule::new(&forward);
+    check_interfaces(&mut rule, &gateway.lan_interface, &gateway.wg_interface)?;
+    rule.add_expr(&nft_expr!(meta nfproto));
+    rule.add_expr(&nft_expr!(cmp == libc::NFPROTO_IPV4 as u8));
+    rule.add_expr(&nft_expr!(payload ipv4 saddr));
+    rule.add_expr(&DynsetUpdate {
+        set: TX_SET,
+        set_id: TX_SET_ID,
+    });
+    rule.add_expr(&Verdict::Accept);
+    batch.add(&rule, MsgType::Add);
+
+    // replies only, the LAN is not reachable from the tunnel
+    let established = (expr::ct::States::ESTABLISHED | expr::ct::States::RELATED).bits();
+    let mut rule = Rule::new(&forward);
+    check_interfaces(&mut rule, &gateway.wg_interface, &gateway.lan_interface)?;
+    rule.add_expr(&nft_expr!(ct state));
+    rule.add_expr(&nft_expr!(bitwise mask established, xor 0u32));
+    rule.add_expr(&nft_expr!(cmp != 0u32));
+    rule.add_expr(&nft_expr!(meta nfproto));
+    rule.add_expr(&nft_expr!(cmp == libc::NFPROTO_IPV4 as u8));
+    rule.add_expr(&nft_expr!(payload ipv4 daddr));
+    rule.add_expr(&DynsetUpdate {
+        set: RX_SET,
+        set_id: RX_SET_ID,
+    });
+    rule.add_expr(&Verdict::Accept);
+    batch.add(&rule, MsgType::Add);
+
+    let mut postrouting = Chain::new(c"postrouting", &table);
+    postrouting.set_hook(Hook::PostRouting, SRCNAT_PRIORITY);
+    postrouting.set_type(ChainType::Nat);
+    batch.add(&postrouting, MsgType::Add);
+
+    let mut rule = Rule::new(&postrouting);
+    check_interfaces(&mut rule, &gateway.lan_interface, &gateway.wg_interface)?;
+    rule.add_expr(&Masquerade);
+    batch.add(&rule, MsgType::Add);
+
+    Ok(batch.finalize())
+}
+
+/// Match packets entering on `iif` and leaving on `oif`.
+#[cfg(target_os = "linux")]
+fn check_interfaces(rule: &mut Rule<'_>, iif: &str, oif: &str) -> Result<(), Error> {
+    for (meta, name) in [(nft_expr!(meta iifname), iif), (nft_expr!(meta oifname), oif)] {
+        let cname = CString::new(name).map_err(|_| Error::InterfaceName(name.to_string()))?;
+        rule.add_expr(&meta);
+        rule.add_expr(&nft_expr!(cmp == expr::InterfaceName::Exact(cname)));
+    }
+    Ok(())
+}
+
+/// Per address counters from `nft -j list set` output.
+#[cfg(any(target_os = "linux", test))]
+fn parse_counters(json: &str) -> Result<BTreeMap<Ipv4Addr, (u64, u64)>, serde_json::Error> {
+    let value: serde_json::Value = serde_json::from_str(json)?;
+    let mut res = BTreeMap::new();
+    let entries = value["nftables"].as_array().cloned().unwrap_or_default();
+    for entry in entries {
+        let elems = entry["set"]["elem"].as_array().cloned().unwrap_or_default();
+        for elem in elems {
+            let ip = elem["elem"]["val"].as_str().and_then(|s| s.parse::<Ipv4Addr>().ok());
+            let counter = &elem["elem"]["counter"];
+            if let Some(ip) = ip {
+                let bytes = counter["bytes"].as_u64().unwrap_or(0);
+                let packets = counter["packets"].as_u64().unwrap_or(0);
+                res.insert(ip, (bytes, packets));
+            }
+        }
+    }
+    Ok(res)
+}
+
+#[cfg(any(target_os = "linux", test))]
+fn merge_counters(tx: BTreeMap<Ipv4Addr, (u64, u64)>, rx: BTreeMap<Ipv4Addr, (u64, u64)>) -> Vec<GatewayClient> {
+    let mut clients: BTreeMap<Ipv4Addr, GatewayClient> = BTreeMap::new();
+    let empty = |ip| GatewayClient {
+        ip,
+        tx_bytes: 0,
+        tx_packets: 0,
+        rx_bytes: 0,
+        rx_packets: 0,
+    };
+    for (ip, (bytes, packets)) in tx {
+        let client = clients.entry(ip).or_insert_with(|| empty(ip));
+        client.tx_bytes = bytes;
+        client.tx_packets = packets;
+    }
+    for (ip, (bytes, packets)) in rx {
+        let client = clients.entry(ip).or_insert_with(|| empty(ip));
+        client.rx_bytes = bytes;
+        client.rx_packets = packets;
+    }
+    clients.into_values().collect()
+}
+
+#[cfg(target_os = "linux")]
+pub async fn apply(gateway: Gateway) -> Result<Installed, Error> {
+    let batch = install_batch(&gateway)?;
+    let forwarding_before = fs::read_to_string(IP_FORWARD).await?.trim() == "1";
+    fs::write(IP_FORWARD, "1").await?;
+    let installed = Installed {
+        gateway,
+        forwarding_before,
+    };
+    if let Err(error) = nftables::send_batch(&batch) {
+        remove(installed).await;
+        return Err(error.into());
+    }
+    Ok(installed)
+}
+
+#[cfg(target_os = "linux")]
+pub async fn remove(installed: Installed) {
+    if let Err(error) = nftables::delete_table(TABLE_NAME) {
+        tracing::warn!(?error, "failed to remove gateway table");
+    }
+    if !installed.forwarding_before
+        && let Err(error) = fs::write(IP_FORWARD, "0").await
+    {
+        tracing::warn!(?error, "failed to restore ip forwarding setting");
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub async fn clients() -> Result<Vec<GatewayClient>, Error> {
+    let mut counters = Vec::with_capacity(2);
+    for set in [TX_SET, RX_SET] {
+        let json = Command::new("nft")
+            .args(["-j", "list", "set", "inet"])
+            .arg(&*TABLE_NAME.to_string_lossy())
+            .arg(&*set.to_string_lossy())
+            .run_stdout(QUERY)
+            .await?;
+        counters.push(parse_counters(&json).map_err(|e| Error::General(e.to_string()))?);
+    }
+    let rx = counters.pop().unwrap_or_default();
+    let tx = counters.pop().unwrap_or_default();
+    Ok(merge_counters(tx, rx))
+}
+
+#[cfg(target_os = "macos")]
+pub async fn apply(_gateway: Gateway) -> Result<Installed, Error> {
+    Err(Error::GatewayUnsupported)
+}
+
+#[cfg(target_os = "macos")]
+pub async fn remove(_installed: Installed) {}
+
+#[cfg(target_os = "macos")]
+pub async fn clients() -> Result<Vec<GatewayClient>, Error> {
+    Err(Error::GatewayUnsupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_are_merged_per_client() {
+        let tx = r#"{"nftables": [{"metainfo": {"json_schema_version": 1}}, {"set": {"family": "inet", "name": "tx",
+            "table": "gnosis_vpn_gateway", "type": "ipv4_addr", "elem": [
+            {"elem": {"val": "192.168.1.20", "counter": {"packets": 10, "bytes": 1200}}},
+            {"elem": {"val": "192.168.1.21", "counter": {"packets": 1, "bytes": 60}}}]}}]}"#;
+        let rx = r#"{"nftables": [{"set": {"family": "inet", "name": "rx", "table": "gnosis_vpn_gateway",
+            "type": "ipv4_addr", "elem": [{"elem": {"val": "192.168.1.20", "counter": {"packets": 8, "bytes": 9000}}}]}}]}"#;
+        let clients = merge_counters(parse_counters(tx).unwrap(), parse_counters(rx).unwrap());
+        assert_eq!(
+            clients,
+            vec![
+                GatewayClient {
+                    ip: "192.168.1.20".parse().unwrap(),
+                    tx_bytes: 1200,
+                    tx_packets: 10,
+                    rx_bytes: 9000,
+                    rx_packets: 8,
+                },
+                GatewayClient {
+                    ip: "192.168.1.21".parse().unwrap(),
+                    tx_bytes: 60,
+                    tx_packets: 1,
+                    rx_bytes: 0,
+                    rx_packets: 0,
+                },
+            ]
+        );
+    }
+}
@@ -1,10 +1,12 @@
 //! Linux routing implementation for split-tunnel VPN behavior.
 //!
 //! Provides a [`StaticRouter`] that:
-//! 1. Adds bypass routes for peer IPs and RFC1918 networks BEFORE bringing up WireGuard
-//! 2. Runs `wg-quick up` with `Table = off` (no automatic routing)
+//! 1. Adds IPv6 blackholes (unless disabled) and bypass routes for peer IPs and RFC1918 networks BEFORE bringing up WireGuard
+//! 2. Brings up the WireGuard interface without routes, over netlink unless `wg-quick` is still needed
 //! 3. Adds VPN split routes (`0.0.0.0/1`, `128.0.0.0/1`) and VPN subnet (`10.128.0.0/9`) via wg0
-//! 4. On teardown: removes VPN routes, brings down WireGuard, removes bypass routes
+//! 4. On teardown: removes VPN routes, brings down WireGuard, removes bypass routes and IPv6 blackholes
+//!
+//! All routes carry the gnosisvpn routing protocol id and only those are ever deleted.
 //!
 //! ## Route Precedence
 //! Route specificity handles all traffic without ip rules or extra routing tables:
@@ -13,35 +15,49 @@
 use async_trait::async_trait;
 
 use gnosis_vpn_lib::shell_command_ext::Logs;
+use gnosis_vpn_lib::wireguard::BlockIpv6;
 use gnosis_vpn_lib::{event, wireguard};
 
+use std::collections::BTreeSet;
 use std::net::Ipv4Addr;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use super::route_ops::{RouteOps, WanRoute};
+use super::route_ops_ip::IpRouteOps;
 use super::route_ops_linux::NetlinkRouteOps;
-use super::wg_ops::{RealWgOps, WgOps};
-use super::{Error, RFC1918_BYPASS_NETS, Routing, VPN_TUNNEL_SUBNET};
-
-/// Public IP used to identify the WAN route and detect DHCP reassignments.
-const PUBLIC_INTERNET_ADDRESS: Ipv4Addr = Ipv4Addr::new(1, 1, 1, 1);
+use super::stats::{Counted, Stats};
+use super::wg_ops::{self, AnyWgOps, WgOps};
+use super::{
+    Backend, Error, Explanation, PUBLIC_INTERNET_ADDRESS, PlannedRoute, RouteKind, Routing, add_ipv6_blackholes,
+    bypass_routes, check_plan, remove_ipv6_blackholes, tunnel_routes,
+};
 
-/// VPN split routes: two /1 halves cover all IPv4 space.
-/// More specific than the WAN /0 default, routing all non-bypass internet traffic into the tunnel.
-const VPN_SPLIT_ROUTES: &[(&str, u8)] = &[("0.0.0.0", 1), ("128.0.0.0", 1)];
+/// The routing table may change between the netlink and the `ip route` read, retry before giving up on netlink.
+const SELF_TEST_ATTEMPTS: u32 = 3;
 
 /// Builds a static Linux router.
 pub fn static_router(
-    state_home: PathBuf,
+    cache_home: PathBuf,
     wg_data: event::WireGuardData,
     peer_ips: Vec<Ipv4Addr>,
+    tooling: wireguard::Tooling,
+    backend: Backend,
+    stats: Arc<Stats>,
 ) -> Result<impl Routing, Error> {
-    let (conn, handle, _) = rtnetlink::new_connection()?;
-    tokio::task::spawn(conn);
-    let route_ops = NetlinkRouteOps::new(handle);
-    let wg = RealWgOps;
+    // `backend` was resolved by `select_backend`, anything but iproute2 is netlink
+    let route_ops = match backend {
+        Backend::Iproute2 => LinuxRouteOps::Iproute2(IpRouteOps),
+        Backend::Auto | Backend::Netlink | Backend::Route => {
+            let (conn, handle, _) = rtnetlink::new_connection()?;
+            tokio::task::spawn(conn);
+            LinuxRouteOps::Netlink(NetlinkRouteOps::new(handle))
+        }
+    };
+    let route_ops = Counted::new(route_ops, stats);
+    let wg = wg_ops::select(tooling, wg_data.wg.config.dns_strategy)?;
     Ok(StaticRouter {
-        state_home: state_home.to_path_buf(),
+        cache_home: cache_home.to_path_buf(),
         wg_data,
         peer_ips,
         route_ops,
@@ -51,55 +67,197 @@ pub fn static_router(
     })
 }
 
+/// Compare what the static router's `setup()` applies for the current WAN route with the routing table.
+pub async fn explain(
+    peer_ips: &[Ipv4Addr],
+    wg_interface: &str,
+    block_ipv6: BlockIpv6,
+    backend: Backend,
+) -> Result<Explanation, Error> {
+    if backend == Backend::Iproute2 {
+        return check_plan(&IpRouteOps, peer_ips, wg_interface, block_ipv6).await;
+    }
+    let (conn, handle, _) = rtnetlink::new_connection()?;
+    let conn = tokio::task::spawn(conn);
+    let res = check_plan(&NetlinkRouteOps::new(handle), peer_ips, wg_interface, block_ipv6).await;
+    conn.abort();
+    res
+}
+
+/// Resolve the `requested` backend once at startup, `auto` runs a compatibility self-test.
+///
+/// `netlink-packet-route` drops replies it cannot parse with a warning, on kernels newer than the
+/// crate routes may silently be missing from a dump and the WAN route would be picked wrongly.
+/// The main table is read via netlink and via `ip route`, on a mismatch the static router uses
+/// `ip route` commands instead. Without a usable `ip` command netlink is kept.
+pub async fn select_backend(requested: Backend) -> Result<Backend, Error> {
+    match requested {
+        Backend::Auto => Ok(self_test().await),
+        Backend::Netlink | Backend::Iproute2 => {
+            tracing::info!(backend = %requested, "using configured routing backend");
+            Ok(requested)
+        }
+        Backend::Route => Err(Error::BackendUnsupported(requested)),
+    }
+}
+
+async fn self_test() -> Backend {
+    for attempt in 1..=SELF_TEST_ATTEMPTS {
+        let shell = match IpRouteOps.main_table().await {
+            Ok(routes) => routes,
+            Err(error) => {
+                tracing::debug!(%error, "ip route unavailable - keeping netlink routing");
+                return Backend::Netlink;
+            }
+        };
+        match netlink_main_table().await {
+            Ok(netlink) if netlink == shell => {
+                tracing::debug!(routes = netlink.len(), "netlink routing self-test passed");
+                return Backend::Netlink;
+            }
+            Ok(netlink) => {
+                // the table may simply have changed between both reads
+                tracing::debug!(
+                    attempt,
+                    ?netlink,
+                    ?shell,
+                    "netlink and ip route disagree on the main table"
+                );
+            }
+            Err(error) => {
+                tracing::debug!(attempt, %error, "netlink route dump failed");
+            }
+        }
+    }
+    tracing::warn!("netlink replies are unreliable on this kernel - falling back to ip route commands");
+    Backend::Iproute2
+}
+
+async fn netlink_main_table() -> Result<BTreeSet<(String, Option<String>)>, Error> {
+    let (conn, handle, _) = rtnetlink::new_connection()?;
+    let conn = tokio::task::spawn(conn);
+    let res = NetlinkRouteOps::new(handle).main_table().await;
+    conn.abort();
+    res
+}
+
+/// Route operations of the [`Backend`] picked at startup.
+enum LinuxRouteOps {
+    Netlink(NetlinkRouteOps),
+    Iproute2(IpRouteOps),
+}
+
+#[async_trait]
+impl RouteOps for LinuxRouteOps {
+    async fn get_wan_route_for(&self, dest: Ipv4Addr, exclude_iface: &str) -> Result<Option<WanRoute>, Error> {
+        match self {
+            LinuxRouteOps::Netlink(ops) => ops.get_wan_route_for(dest, exclude_iface).await,
+            LinuxRouteOps::Iproute2(ops) => ops.get_wan_route_for(dest, exclude_iface).await,
+        }
+    }
+
+    async fn get_route_via_device(&self, dest: Ipv4Addr, device: &str) -> Result<Option<WanRoute>, Error> {
+        match self {
+            LinuxRouteOps::Netlink(ops) => ops.get_route_via_device(dest, device).await,
+            LinuxRouteOps::Iproute2(ops) => ops.get_route_via_device(dest, device).await,
+        }
+    }
+
+    async fn route_add(&self, dest: &str, gateway: Option<&str>, device: &str) -> Result<(), Error> {
+        match self {
+            LinuxRouteOps::Netlink(ops) => ops.route_add(dest, gateway, device).await,
+            LinuxRouteOps::Iproute2(ops) => ops.route_add(dest, gateway, device).await,
+        }
+    }
+
+    async fn route_del(&self, dest: &str, device: &str) -> Result<(), Error> {
+        match self {
+            LinuxRouteOps::Netlink(ops) => ops.route_del(dest, device).await,
+            LinuxRouteOps::Iproute2(ops) => ops.route_del(dest, device).await,
+        }
+    }
+
+    async fn route_exists(&self, dest: &str, device: &str) -> Result<bool, Error> {
+        match self {
+            LinuxRouteOps::Netlink(ops) => ops.route_exists(dest, device).await,
+            LinuxRouteOps::Iproute2(ops) => ops.route_exists(dest, device).await,
+        }
+    }
+
+    async fn ipv6_enabled(&self) -> bool {
+        match self {
+            LinuxRouteOps::Netlink(ops) => ops.ipv6_enabled().await,
+            LinuxRouteOps::Iproute2(ops) => ops.ipv6_enabled().await,
+        }
+    }
+
+    async fn ipv6_blackhole_add(&self, dest: &str) -> Result<(), Error> {
+        match self {
+            LinuxRouteOps::Netlink(ops) => ops.ipv6_blackhole_add(dest).await,
+            LinuxRouteOps::Iproute2(ops) => ops.ipv6_blackhole_add(dest).await,
+        }
+    }
+
+    async fn ipv6_blackhole_del(&self, dest: &str) -> Result<(), Error> {
+        match self {
+            LinuxRouteOps::Netlink(ops) => ops.ipv6_blackhole_del(dest).await,
+            LinuxRouteOps::Iproute2(ops) => ops.ipv6_blackhole_del(dest).await,
+        }
+    }
+
+    async fn ipv6_blackhole_exists(&self, dest: &str) -> Result<bool, Error> {
+        match self {
+            LinuxRouteOps::Netlink(ops) => ops.ipv6_blackhole_exists(dest).await,
+            LinuxRouteOps::Iproute2(ops) => ops.ipv6_blackhole_exists(dest).await,
+        }
+    }
+}
+
 /// Linux static router using route operations via netlink.
 ///
-/// Uses `Table = off` so wg-quick only creates the WireGuard interface.
+/// The WireGuard interface is brought up without routes (`Table = off` for wg-quick).
 /// All routing is owned explicitly by this struct via `RouteOps`:
-/// - bypass routes (peer IPs + RFC1918) via WAN — added before wg-quick up
+/// - bypass routes (peer IPs + RFC1918) via WAN — added before the interface is up
 /// - VPN split routes (`0.0.0.0/1`, `128.0.0.0/1`) + VPN subnet via wg0 — static after setup
 struct StaticRouter {
-    state_home: PathBuf,
+    cache_home: PathBuf,
     wg_data: event::WireGuardData,
     peer_ips: Vec<Ipv4Addr>,
-    route_ops: NetlinkRouteOps,
-    wg: RealWgOps,
+    route_ops: Counted<LinuxRouteOps>,
+    wg: AnyWgOps,
     /// WAN route snapshot captured at setup time.
     /// Used by `wan_changed()` to detect interface switches and DHCP reassignments.
     wan_info: Option<WanRoute>,
     /// Bypass routes currently installed: (dest_cidr, wan_device).
-    /// Tracked for explicit cleanup since the wg-quick config has no PreDown scripts.
+    /// Tracked for explicit cleanup, the interface teardown knows nothing about them.
     active_bypass_routes: Vec<(String, String)>,
 }
 
 impl StaticRouter {
     async fn setup_vpn_routes(&self) -> Result<(), Error> {
-        for (net, prefix) in VPN_SPLIT_ROUTES {
-            let cidr = format!("{}/{}", net, prefix);
-            let _ = self.route_ops.route_del(&cidr, wireguard::WG_INTERFACE).await;
-            self.route_ops.route_add(&cidr, None, wireguard::WG_INTERFACE).await?;
+        for route in tunnel_routes(wireguard::WG_INTERFACE) {
+            self.route_ops.clear_stale(&route.dest, &route.device).await;
+            self.route_ops.route_add(&route.dest, None, &route.device).await?;
         }
-        let (net, prefix) = VPN_TUNNEL_SUBNET;
-        let cidr = format!("{}/{}", net, prefix);
-        let _ = self.route_ops.route_del(&cidr, wireguard::WG_INTERFACE).await;
-        self.route_ops.route_add(&cidr, None, wireguard::WG_INTERFACE).await
+        Ok(())
     }
 
     async fn remove_vpn_routes(&self) {
-        let vpn_routes = [("0.0.0.0", 1u8), ("128.0.0.0", 1u8), VPN_TUNNEL_SUBNET];
-        for (net, prefix) in &vpn_routes {
-            let cidr = format!("{}/{}", net, prefix);
-            if let Err(e) = self.route_ops.route_del(&cidr, wireguard::WG_INTERFACE).await {
-                tracing::warn!(%e, cidr = %cidr, "failed to remove VPN route");
+        for route in tunnel_routes(wireguard::WG_INTERFACE) {
+            if let Err(e) = self.route_ops.route_del(&route.dest, &route.device).await {
+                tracing::warn!(%e, cidr = %route.dest, "failed to remove VPN route");
             }
         }
     }
 
-    async fn rollback_bypass_routes(&mut self) {
+    /// Undo Phase 1: bypass routes and IPv6 blackholes.
+    async fn rollback_wan_routes(&mut self) {
         for (dest, device) in self.active_bypass_routes.drain(..).collect::<Vec<_>>() {
             if let Err(e) = self.route_ops.route_del(&dest, &device).await {
                 tracing::warn!(%e, dest = %dest, "failed to remove bypass route during rollback");
             }
         }
+        remove_ipv6_blackholes(&self.route_ops).await;
     }
 }
 
@@ -107,17 +265,19 @@ impl StaticRouter {
 impl Routing for StaticRouter {
     /// Install split-tunnel routing.
     ///
-    /// Phase 1 (before wg-quick up): add bypass routes via WAN
+    /// Phase 1 (before the interface is up): blackhole IPv6 and add bypass routes via WAN
+    ///   - IPv6 blackholes `::/1` and `8000::/1` as configured by `block_ipv6`
+    ///     (`always`: hard-fail, `auto`: warn and continue, `never`: skipped)
     ///   - Peer IP /32 routes (hard-fail: rollback all on error)
     ///   - RFC1918 bypass routes (soft-fail: warn and continue)
     ///
-    /// Phase 2: wg-quick up with Table = off (no automatic routing)
-    ///   - On failure: rollback Phase 1 bypass routes
+    /// Phase 2: bring up the interface without routes
+    ///   - On failure: rollback Phase 1 routes
     ///
-    /// Phase 3 (after wg-quick up): add VPN routes via wg0
+    /// Phase 3 (after the interface is up): add VPN routes via wg0
     ///   - `0.0.0.0/1` and `128.0.0.0/1` override the WAN default for all internet traffic
     ///   - `10.128.0.0/9` overrides the `10.0.0.0/8` RFC1918 bypass for VPN server traffic
-    ///   - On failure: remove partial VPN routes, wg-quick down, rollback bypass routes
+    ///   - On failure: remove partial VPN routes, interface down, rollback Phase 1 routes
     async fn setup(&mut self) -> Result<String, Error> {
         // Snapshot the WAN route before any VPN routes are installed.
         // Including src_ip lets wan_changed() detect DHCP reassignments on the same
@@ -127,49 +287,44 @@ impl Routing for StaticRouter {
             .get_wan_route_for(PUBLIC_INTERNET_ADDRESS, wireguard::WG_INTERFACE)
             .await?
             .ok_or(Error::NoInterface)?;
-        let device = wan_route.device.clone();
-        let gateway = wan_route.gateway.clone();
-        tracing::debug!(device = %device, gateway = ?gateway, src_ip = ?wan_route.src_ip, "WAN interface for bypass routes");
-
-        // Phase 1: bypass routes before wg-quick up (avoids race with HOPR p2p connections)
-        for ip in &self.peer_ips.clone() {
-            let dest = ip.to_string();
-            let _ = self.route_ops.route_del(&dest, &device).await;
-            if let Err(e) = self.route_ops.route_add(&dest, gateway.as_deref(), &device).await {
-                self.rollback_bypass_routes().await;
-                return Err(e);
-            }
-            self.active_bypass_routes.push((dest, device.clone()));
-        }
-        for (net, prefix) in RFC1918_BYPASS_NETS {
-            let cidr = format!("{}/{}", net, prefix);
-            let _ = self.route_ops.route_del(&cidr, &device).await;
-            match self.route_ops.route_add(&cidr, gateway.as_deref(), &device).await {
-                Ok(_) => self.active_bypass_routes.push((cidr, device.clone())),
-                Err(e) => tracing::warn!(%e, cidr = %cidr, "RFC1918 bypass route failed, continuing"),
+        tracing::debug!(device = %wan_route.device, gateway = ?wan_route.gateway, src_ip = ?wan_route.src_ip, "WAN interface for bypass routes");
+
+        // Phase 1: IPv6 blackholes and bypass routes before the interface is up
+        // (avoids IPv6 leaks and races with HOPR p2p connections)
+        add_ipv6_blackholes(&self.route_ops, self.wg_data.wg.config.block_ipv6).await?;
+        for PlannedRoute {
+            dest,
+            gateway,
+            device,
+            kind,
+        } in bypass_routes(&wan_route, &self.peer_ips)
+        {
+            self.route_ops.clear_stale(&dest, &device).await;
+            match self.route_ops.route_add(&dest, gateway.as_deref(), &device).await {
+                Ok(_) => self.active_bypass_routes.push((dest, device)),
+                Err(e) if kind == RouteKind::PeerBypass => {
+                    self.rollback_wan_routes().await;
+                    return Err(e);
+                }
+                Err(e) => tracing::warn!(%e, cidr = %dest, "RFC1918 bypass route failed, continuing"),
             }
         }
 
-        // Phase 2: wg-quick up with Table = off
-        let wg_content = self.wg_data.wg.to_file_string(
-            &self.wg_data.interface_info,
-            &self.wg_data.peer_info,
-            vec!["Table = off".to_string()],
-        );
-        let interface_name = match self.wg.wg_quick_up(self.state_home.clone(), wg_content).await {
+        // Phase 2: interface without routes
+        let interface_name = match self.wg.up(self.cache_home.clone(), &self.wg_data).await {
             Ok(n) => n,
             Err(e) => {
-                self.rollback_bypass_routes().await;
+                self.rollback_wan_routes().await;
                 return Err(e);
             }
         };
-        tracing::debug!(%interface_name, "wg-quick up");
+        tracing::debug!(%interface_name, "WireGuard interface up");
 
         // Phase 3: VPN routes via wg0 (split defaults + VPN subnet override)
         if let Err(e) = self.setup_vpn_routes().await {
             self.remove_vpn_routes().await;
-            let _ = self.wg.wg_quick_down(self.state_home.clone(), Logs::Suppress).await;
-            self.rollback_bypass_routes().await;
+            let _ = self.wg.down(self.cache_home.clone(), Logs::Suppress).await;
+            self.rollback_wan_routes().await;
             return Err(e);
         }
 
@@ -181,19 +336,20 @@ impl Routing for StaticRouter {
     /// Teardown split-tunnel routing.
     ///
     /// 1. Remove VPN routes (wg0) — warn on error, continue
-    /// 2. wg-quick down
-    /// 3. Remove bypass routes (WAN) — warn on error, continue
+    /// 2. Bring down the interface
+    /// 3. Remove bypass routes (WAN) and IPv6 blackholes — warn on error, continue
     async fn teardown(&mut self, logs: Logs) {
         self.remove_vpn_routes().await;
-        match self.wg.wg_quick_down(self.state_home.clone(), logs).await {
-            Ok(_) => tracing::debug!("wg-quick down"),
-            Err(error) => tracing::warn!(?error, "wg-quick down failed during teardown"),
+        match self.wg.down(self.cache_home.clone(), logs).await {
+            Ok(_) => tracing::debug!("WireGuard interface down"),
+            Err(error) => tracing::warn!(?error, "WireGuard interface down failed during teardown"),
         }
         for (dest, device) in self.active_bypass_routes.drain(..).collect::<Vec<_>>() {
             if let Err(e) = self.route_ops.route_del(&dest, &device).await {
                 tracing::warn!(%e, dest = %dest, device = %device, "failed to remove bypass route");
             }
         }
+        remove_ipv6_blackholes(&self.route_ops).await;
         self.wan_info = None;
         tracing::info!("routing teardown complete");
     }
@@ -223,7 +379,7 @@ impl Routing for StaticRouter {
         let device = wan.device.clone();
         let gateway = wan.gateway.clone();
         let dest = ip.to_string();
-        let _ = self.route_ops.route_del(&dest, &device).await;
+        self.route_ops.clear_stale(&dest, &device).await;
         self.route_ops.route_add(&dest, gateway.as_deref(), &device).await?;
         self.active_bypass_routes.push((dest, device));
         Ok(())
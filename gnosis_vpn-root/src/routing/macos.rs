@@ -1,12 +1,14 @@
 //! macOS routing implementation for split-tunnel VPN behavior.
 //!
 //! Provides a [`StaticRouter`] that:
-//! 1. Adds bypass routes for peer IPs and RFC1918 networks BEFORE bringing up WireGuard
+//! 1. Adds IPv6 blackholes (unless disabled) and bypass routes for peer IPs and RFC1918 networks BEFORE bringing up WireGuard
 //!    (avoids race condition for both HOPR traffic and LAN access)
 //! 2. Runs `wg-quick up` with `Table = off` to prevent automatic routing
 //! 3. Adds VPN split routes (`0.0.0.0/1`, `128.0.0.0/1`) and VPN subnet (`10.128.0.0/9`)
 //!    via the resolved utun interface
-//! 4. On teardown: removes VPN routes, brings down WireGuard, removes bypass routes
+//! 4. On teardown: removes VPN routes, brings down WireGuard, removes bypass routes and IPv6 blackholes
+//!
+//! All routes carry the `RTF_PROTO1` flag and only those are ever deleted.
 //!
 //! ## Route Precedence
 //! Route specificity handles all traffic without extra routing tables:
@@ -15,41 +17,61 @@
 use async_trait::async_trait;
 
 use gnosis_vpn_lib::shell_command_ext::Logs;
+use gnosis_vpn_lib::wireguard::BlockIpv6;
 use gnosis_vpn_lib::{event, wireguard};
 
 use std::net::Ipv4Addr;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use super::route_ops::{RouteOps, WanRoute};
 use super::route_ops_macos::DarwinRouteOps;
-use super::wg_ops::{RealWgOps, WgOps};
-use super::{Error, RFC1918_BYPASS_NETS, Routing, VPN_TUNNEL_SUBNET};
-
-/// Public IP used to identify the WAN route and detect DHCP reassignments.
-const PUBLIC_INTERNET_ADDRESS: Ipv4Addr = Ipv4Addr::new(1, 1, 1, 1);
-
-/// VPN split routes: two /1 halves cover all IPv4 space.
-/// More specific than the WAN /0 default, routing all non-bypass internet traffic into the tunnel.
-const VPN_SPLIT_ROUTES: &[(&str, u8)] = &[("0.0.0.0", 1), ("128.0.0.0", 1)];
+use super::stats::{Counted, Stats};
+use super::wg_ops::{WgOps, WgQuickOps};
+use super::{
+    Backend, Error, Explanation, PUBLIC_INTERNET_ADDRESS, PlannedRoute, RouteKind, Routing, add_ipv6_blackholes,
+    bypass_routes, check_plan, remove_ipv6_blackholes, tunnel_routes,
+};
 
 /// Builds a static macOS router.
 pub fn static_router(
-    state_home: PathBuf,
+    cache_home: PathBuf,
     wg_data: event::WireGuardData,
     peer_ips: Vec<Ipv4Addr>,
+    tooling: wireguard::Tooling,
+    _backend: Backend,
+    stats: Arc<Stats>,
 ) -> Result<impl Routing, Error> {
     Ok(StaticRouter {
-        state_home,
+        cache_home,
         wg_data,
         peer_ips,
-        route_ops: DarwinRouteOps,
-        wg: RealWgOps,
+        route_ops: Counted::new(DarwinRouteOps, stats),
+        wg: WgQuickOps { tooling },
         active_bypass_routes: Vec::new(),
         wg_interface_name: None,
         wan_info: None,
     })
 }
 
+/// Compare what the static router's `setup()` applies for the current WAN route with the routing table.
+pub async fn explain(
+    peer_ips: &[Ipv4Addr],
+    wg_interface: &str,
+    block_ipv6: BlockIpv6,
+    _backend: Backend,
+) -> Result<Explanation, Error> {
+    check_plan(&DarwinRouteOps, peer_ips, wg_interface, block_ipv6).await
+}
+
+/// Only the `route` command is available on macOS.
+pub async fn select_backend(requested: Backend) -> Result<Backend, Error> {
+    match requested {
+        Backend::Auto | Backend::Route => Ok(Backend::Route),
+        Backend::Netlink | Backend::Iproute2 => Err(Error::BackendUnsupported(requested)),
+    }
+}
+
 /// macOS static router using route operations via the `route` command.
 ///
 /// Uses `Table = off` so wg-quick only creates the WireGuard interface.
@@ -57,11 +79,11 @@ pub fn static_router(
 /// - bypass routes (peer IPs + RFC1918) via WAN — added before wg-quick up
 /// - VPN split routes (`0.0.0.0/1`, `128.0.0.0/1`) + VPN subnet via utun — static after setup
 struct StaticRouter {
-    state_home: PathBuf,
+    cache_home: PathBuf,
     wg_data: event::WireGuardData,
     peer_ips: Vec<Ipv4Addr>,
-    route_ops: DarwinRouteOps,
-    wg: RealWgOps,
+    route_ops: Counted<DarwinRouteOps>,
+    wg: WgQuickOps,
     /// Bypass routes currently installed: (dest_cidr, wan_device).
     /// Tracked for explicit cleanup since the wg-quick config has no PreDown scripts.
     active_bypass_routes: Vec<(String, String)>,
@@ -81,34 +103,29 @@ impl StaticRouter {
     }
 
     async fn setup_vpn_routes(&self, iface: &str) -> Result<(), Error> {
-        for (net, prefix) in VPN_SPLIT_ROUTES {
-            let cidr = format!("{}/{}", net, prefix);
-            let _ = self.route_ops.route_del(&cidr, iface).await;
-            self.route_ops.route_add(&cidr, None, iface).await?;
+        for route in tunnel_routes(iface) {
+            self.route_ops.clear_stale(&route.dest, &route.device).await;
+            self.route_ops.route_add(&route.dest, None, &route.device).await?;
         }
-        let (net, prefix) = VPN_TUNNEL_SUBNET;
-        let cidr = format!("{}/{}", net, prefix);
-        let _ = self.route_ops.route_del(&cidr, iface).await;
-        self.route_ops.route_add(&cidr, None, iface).await
+        Ok(())
     }
 
     async fn remove_vpn_routes(&self) {
-        let iface = self.vpn_interface();
-        let vpn_routes = [("0.0.0.0", 1u8), ("128.0.0.0", 1u8), VPN_TUNNEL_SUBNET];
-        for (net, prefix) in &vpn_routes {
-            let cidr = format!("{}/{}", net, prefix);
-            if let Err(e) = self.route_ops.route_del(&cidr, &iface).await {
-                tracing::warn!(%e, cidr = %cidr, "failed to remove VPN route");
+        for route in tunnel_routes(&self.vpn_interface()) {
+            if let Err(e) = self.route_ops.route_del(&route.dest, &route.device).await {
+                tracing::warn!(%e, cidr = %route.dest, "failed to remove VPN route");
             }
         }
     }
 
-    async fn rollback_bypass_routes(&mut self) {
+    /// Undo Phase 1: bypass routes and IPv6 blackholes.
+    async fn rollback_wan_routes(&mut self) {
         for (dest, device) in self.active_bypass_routes.drain(..).collect::<Vec<_>>() {
             if let Err(e) = self.route_ops.route_del(&dest, &device).await {
                 tracing::warn!(%e, dest = %dest, "failed to remove bypass route during rollback");
             }
         }
+        remove_ipv6_blackholes(&self.route_ops).await;
     }
 }
 
@@ -116,17 +133,19 @@ impl StaticRouter {
 impl Routing for StaticRouter {
     /// Install split-tunnel routing.
     ///
-    /// Phase 1 (before wg-quick up): add bypass routes via WAN
+    /// Phase 1 (before wg-quick up): blackhole IPv6 and add bypass routes via WAN
+    ///   - IPv6 blackholes `::/1` and `8000::/1` as configured by `block_ipv6`
+    ///     (`always`: hard-fail, `auto`: warn and continue, `never`: skipped)
     ///   - Peer IP /32 routes (hard-fail: rollback all on error)
     ///   - RFC1918 bypass routes (soft-fail: warn and continue)
     ///
     /// Phase 2: wg-quick up with Table = off (no automatic routing)
-    ///   - On failure: rollback Phase 1 bypass routes
+    ///   - On failure: rollback Phase 1 routes
     ///
     /// Phase 3 (after wg-quick up): add VPN routes via the resolved utun interface
     ///   - `0.0.0.0/1` and `128.0.0.0/1` override the WAN default for all internet traffic
     ///   - `10.128.0.0/9` overrides the `10.0.0.0/8` RFC1918 bypass for VPN server traffic
-    ///   - On failure: remove partial VPN routes, wg-quick down, rollback bypass routes
+    ///   - On failure: remove partial VPN routes, wg-quick down, rollback Phase 1 routes
     async fn setup(&mut self) -> Result<String, Error> {
         // Snapshot the WAN route before any VPN routes are installed.
         // Including src_ip lets wan_changed() detect DHCP reassignments on the same
@@ -136,39 +155,34 @@ impl Routing for StaticRouter {
             .get_wan_route_for(PUBLIC_INTERNET_ADDRESS, wireguard::WG_INTERFACE)
             .await?
             .ok_or(Error::NoInterface)?;
-        let device = wan_route.device.clone();
-        let gateway = wan_route.gateway.clone();
-        tracing::debug!(device = %device, gateway = ?gateway, src_ip = ?wan_route.src_ip, "WAN interface for bypass routes");
-
-        // Phase 1: bypass routes before wg-quick up (avoids race with HOPR p2p connections)
-        for ip in self.peer_ips.clone() {
-            let dest = ip.to_string();
-            let _ = self.route_ops.route_del(&dest, &device).await;
-            if let Err(e) = self.route_ops.route_add(&dest, gateway.as_deref(), &device).await {
-                self.rollback_bypass_routes().await;
-                return Err(e);
-            }
-            self.active_bypass_routes.push((dest, device.clone()));
-        }
-        for (net, prefix) in RFC1918_BYPASS_NETS {
-            let cidr = format!("{}/{}", net, prefix);
-            let _ = self.route_ops.route_del(&cidr, &device).await;
-            match self.route_ops.route_add(&cidr, gateway.as_deref(), &device).await {
-                Ok(_) => self.active_bypass_routes.push((cidr, device.clone())),
-                Err(e) => tracing::warn!(%e, cidr = %cidr, "RFC1918 bypass route failed, continuing"),
+        tracing::debug!(device = %wan_route.device, gateway = ?wan_route.gateway, src_ip = ?wan_route.src_ip, "WAN interface for bypass routes");
+
+        // Phase 1: IPv6 blackholes and bypass routes before wg-quick up
+        // (avoids IPv6 leaks and races with HOPR p2p connections)
+        add_ipv6_blackholes(&self.route_ops, self.wg_data.wg.config.block_ipv6).await?;
+        for PlannedRoute {
+            dest,
+            gateway,
+            device,
+            kind,
+        } in bypass_routes(&wan_route, &self.peer_ips)
+        {
+            self.route_ops.clear_stale(&dest, &device).await;
+            match self.route_ops.route_add(&dest, gateway.as_deref(), &device).await {
+                Ok(_) => self.active_bypass_routes.push((dest, device)),
+                Err(e) if kind == RouteKind::PeerBypass => {
+                    self.rollback_wan_routes().await;
+                    return Err(e);
+                }
+                Err(e) => tracing::warn!(%e, cidr = %dest, "RFC1918 bypass route failed, continuing"),
             }
         }
 
         // Phase 2: wg-quick up with Table = off
-        let wg_content = self.wg_data.wg.to_file_string(
-            &self.wg_data.interface_info,
-            &self.wg_data.peer_info,
-            vec!["Table = off".to_string()],
-        );
-        let interface_name = match self.wg.wg_quick_up(self.state_home.clone(), wg_content).await {
+        let interface_name = match self.wg.up(self.cache_home.clone(), &self.wg_data).await {
             Ok(n) => n,
             Err(e) => {
-                self.rollback_bypass_routes().await;
+                self.rollback_wan_routes().await;
                 return Err(e);
             }
         };
@@ -178,8 +192,8 @@ impl Routing for StaticRouter {
         // Phase 3: VPN routes via utun (split defaults + VPN subnet override)
         if let Err(e) = self.setup_vpn_routes(&interface_name).await {
             self.remove_vpn_routes().await;
-            let _ = self.wg.wg_quick_down(self.state_home.clone(), Logs::Suppress).await;
-            self.rollback_bypass_routes().await;
+            let _ = self.wg.down(self.cache_home.clone(), Logs::Suppress).await;
+            self.rollback_wan_routes().await;
             return Err(e);
         }
 
@@ -192,10 +206,10 @@ impl Routing for StaticRouter {
     ///
     /// 1. Remove VPN routes (utun) — warn on error, continue
     /// 2. wg-quick down
-    /// 3. Remove bypass routes (WAN) — warn on error, continue
+    /// 3. Remove bypass routes (WAN) and IPv6 blackholes — warn on error, continue
     async fn teardown(&mut self, logs: Logs) {
         self.remove_vpn_routes().await;
-        match self.wg.wg_quick_down(self.state_home.clone(), logs).await {
+        match self.wg.down(self.cache_home.clone(), logs).await {
             Ok(_) => tracing::debug!("wg-quick down"),
             Err(error) => tracing::warn!(?error, "wg-quick down failed during teardown"),
         }
@@ -204,6 +218,7 @@ impl Routing for StaticRouter {
                 tracing::warn!(%e, dest = %dest, device = %device, "failed to remove bypass route");
             }
         }
+        remove_ipv6_blackholes(&self.route_ops).await;
         self.wg_interface_name = None;
         self.wan_info = None;
         tracing::info!("routing teardown complete");
@@ -234,7 +249,7 @@ impl Routing for StaticRouter {
         let device = wan.device.clone();
         let gateway = wan.gateway.clone();
         let dest = ip.to_string();
-        let _ = self.route_ops.route_del(&dest, &device).await;
+        self.route_ops.clear_stale(&dest, &device).await;
         self.route_ops.route_add(&dest, gateway.as_deref(), &device).await?;
         self.active_bypass_routes.push((dest, device));
         Ok(())
@@ -4,17 +4,31 @@
 use async_trait::async_trait;
 use thiserror::Error;
 
-use gnosis_vpn_lib::shell_command_ext::{self, Logs};
+use gnosis_vpn_lib::shell_command_ext::{self, Logs, RunOptions};
+use gnosis_vpn_lib::wireguard::BlockIpv6;
 use gnosis_vpn_lib::{dirs, wireguard};
 
+use std::fmt::{self, Display};
 use std::net::Ipv4Addr;
+use std::time::Duration;
 
+use route_ops::{RouteOps, WanRoute};
+
+pub(crate) mod delegated;
+pub(crate) mod dns;
+pub(crate) mod dscp;
+pub(crate) mod firewall;
+pub(crate) mod gateway;
+pub(crate) mod rate_limit;
 pub(crate) mod route_ops;
+pub(crate) mod stats;
 pub(crate) mod wg_ops;
 
 cfg_if::cfg_if! {
     if #[cfg(target_os = "linux")] {
+        pub(crate) mod route_ops_ip;
         pub(crate) mod route_ops_linux;
+        pub(crate) mod wg_ops_linux;
         mod linux;
     } else if #[cfg(target_os = "macos")] {
         pub(crate) mod route_ops_macos;
@@ -27,9 +41,36 @@ cfg_if::cfg_if! {
 // ============================================================================
 
 #[cfg(target_os = "linux")]
-pub use linux::static_router;
+pub use linux::{explain, select_backend, static_router};
 #[cfg(target_os = "macos")]
-pub use macos::static_router;
+pub use macos::{explain, select_backend, static_router};
+
+pub use delegated::delegated_router;
+pub use stats::Stats;
+
+/// Who owns the routes sending traffic into the tunnel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Mode {
+    /// Install split-tunnel and bypass routes for this host
+    #[default]
+    Managed,
+    /// Only bring up the tunnel interface, e.g. in a container whose network is set up externally
+    Delegated,
+}
+
+/// How the static router changes the routing table, resolved once at startup by [`select_backend`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Backend {
+    /// netlink on Linux unless the startup self-test finds it unreliable, `route` on macOS
+    #[default]
+    Auto,
+    /// rtnetlink, Linux only
+    Netlink,
+    /// iproute2 `ip route` commands, Linux only
+    Iproute2,
+    /// BSD `route` command, macOS only
+    Route,
+}
 
 /// RFC1918 + link-local networks that should bypass VPN tunnel.
 /// These are more specific than the VPN default routes (0.0.0.0/1, 128.0.0.0/1)
@@ -52,6 +93,320 @@ pub(crate) const RFC1918_BYPASS_NETS: &[(&str, u8)] = &[
 /// so it takes precedence and ensures VPN server traffic uses the tunnel.
 pub(crate) const VPN_TUNNEL_SUBNET: (&str, u8) = ("10.128.0.0", 9);
 
+/// VPN split routes: two /1 halves cover all IPv4 space.
+/// More specific than the WAN /0 default, routing all non-bypass internet traffic into the tunnel.
+const VPN_SPLIT_ROUTES: &[(&str, u8)] = &[("0.0.0.0", 1), ("128.0.0.0", 1)];
+
+/// IPv6 is not carried by the tunnel yet, so it is blackholed while the tunnel is up.
+/// Two /1 halves are more specific than any default route, including router specific ones.
+const IPV6_BLACKHOLES: &[&str] = &["::/1", "8000::/1"];
+
+/// Public IP used to identify the WAN route and detect DHCP reassignments.
+pub(crate) const PUBLIC_INTERNET_ADDRESS: Ipv4Addr = Ipv4Addr::new(1, 1, 1, 1);
+
+/// Routing helpers only touch local kernel state, one taking longer than this is considered hung.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Run options for commands changing routes, firewall tables or traffic shaping.
+pub(crate) const fn command(logs: Logs) -> RunOptions {
+    RunOptions::new(logs).timeout(COMMAND_TIMEOUT)
+}
+
+/// Run options for read only queries, those are safe to repeat on failure.
+pub(crate) const QUERY: RunOptions = command(Logs::Suppress).retry(3, Duration::from_millis(200));
+
+/// What a route installed by the static router is for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RouteKind {
+    /// Host route to a HOPR peer via the WAN, keeps the transport out of the tunnel
+    PeerBypass,
+    /// Private network via the WAN, keeps the LAN reachable
+    LanBypass,
+    /// Into the tunnel
+    Tunnel,
+    /// Drops IPv6 while the tunnel only carries IPv4
+    Ipv6Blackhole,
+}
+
+/// A route the static router installs, see [`plan`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlannedRoute {
+    pub dest: String,
+    pub gateway: Option<String>,
+    pub device: String,
+    pub kind: RouteKind,
+}
+
+/// What a firewall table installed next to the routes is for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableKind {
+    /// DSCP marking of the worker traffic, see [`dscp`]
+    Dscp,
+    /// Sharing the tunnel with a LAN, see [`gateway`]
+    Gateway,
+    /// Killswitch of the current connection
+    Killswitch,
+    /// Kill switch block while the tunnel is down, see [`firewall`]
+    KillSwitchBlock,
+}
+
+/// A firewall table the routing actor manages and whether it should currently be installed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlannedTable {
+    pub kind: TableKind,
+    pub expected: bool,
+}
+
+/// The static router's [`plan`] for the current WAN route compared against the routing table.
+pub struct Explanation {
+    /// WAN route the bypass routes were planned with
+    pub wan: Option<WanRoute>,
+    /// Planned routes and whether each is in the routing table, `None` if that could not be checked
+    pub routes: Vec<(PlannedRoute, Option<bool>)>,
+}
+
+/// IPv6 blackholes, added before the bypass routes.
+pub(crate) fn ipv6_blackhole_routes() -> Vec<PlannedRoute> {
+    IPV6_BLACKHOLES
+        .iter()
+        .map(|dest| PlannedRoute {
+            dest: dest.to_string(),
+            gateway: None,
+            device: "blackhole".to_string(),
+            kind: RouteKind::Ipv6Blackhole,
+        })
+        .collect()
+}
+
+/// Bypass routes via the WAN, installed before the tunnel comes up.
+pub(crate) fn bypass_routes(wan: &WanRoute, peer_ips: &[Ipv4Addr]) -> Vec<PlannedRoute> {
+    let peers = peer_ips.iter().map(|ip| (ip.to_string(), RouteKind::PeerBypass));
+    let lan = RFC1918_BYPASS_NETS
+        .iter()
+        .map(|(net, prefix)| (format!("{net}/{prefix}"), RouteKind::LanBypass));
+    peers
+        .chain(lan)
+        .map(|(dest, kind)| PlannedRoute {
+            dest,
+            gateway: wan.gateway.clone(),
+            device: wan.device.clone(),
+            kind,
+        })
+        .collect()
+}
+
+/// Routes sending traffic into the tunnel, installed once `wg_interface` exists.
+pub(crate) fn tunnel_routes(wg_interface: &str) -> Vec<PlannedRoute> {
+    VPN_SPLIT_ROUTES
+        .iter()
+        .chain([&VPN_TUNNEL_SUBNET])
+        .map(|(net, prefix)| PlannedRoute {
+            dest: format!("{net}/{prefix}"),
+            gateway: None,
+            device: wg_interface.to_string(),
+            kind: RouteKind::Tunnel,
+        })
+        .collect()
+}
+
+/// Every route the static router's `setup()` applies, in order.
+pub fn plan(wan: &WanRoute, peer_ips: &[Ipv4Addr], wg_interface: &str, ipv6_blackholes: bool) -> Vec<PlannedRoute> {
+    let mut routes = if ipv6_blackholes {
+        ipv6_blackhole_routes()
+    } else {
+        Vec::new()
+    };
+    routes.extend(bypass_routes(wan, peer_ips));
+    routes.extend(tunnel_routes(wg_interface));
+    routes
+}
+
+/// Whether [`add_ipv6_blackholes`] installs blackholes with `mode`.
+async fn blocks_ipv6(route_ops: &impl RouteOps, mode: BlockIpv6) -> bool {
+    match mode {
+        BlockIpv6::Never => false,
+        BlockIpv6::Auto => route_ops.ipv6_enabled().await,
+        BlockIpv6::Always => true,
+    }
+}
+
+/// Blackhole IPv6 as configured by `mode`.
+/// Every route is verified after adding it, with `auto` a failure only warns and IPv6 may leak.
+pub(crate) async fn add_ipv6_blackholes(route_ops: &impl RouteOps, mode: BlockIpv6) -> Result<(), Error> {
+    match mode {
+        BlockIpv6::Never => {
+            tracing::info!("IPv6 blocking disabled, IPv6 traffic bypasses the tunnel");
+            return Ok(());
+        }
+        BlockIpv6::Auto if !route_ops.ipv6_enabled().await => {
+            tracing::debug!("IPv6 disabled on this host, no blackhole routes needed");
+            return Ok(());
+        }
+        BlockIpv6::Auto | BlockIpv6::Always => (),
+    }
+    for dest in IPV6_BLACKHOLES {
+        match add_ipv6_blackhole(route_ops, dest).await {
+            Ok(()) => (),
+            Err(e) if mode == BlockIpv6::Auto => {
+                tracing::warn!(%e, %dest, "failed to blackhole IPv6, IPv6 traffic may bypass the tunnel");
+            }
+            Err(e) => {
+                remove_ipv6_blackholes(route_ops).await;
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn add_ipv6_blackhole(route_ops: &impl RouteOps, dest: &str) -> Result<(), Error> {
+    route_ops.ipv6_blackhole_add(dest).await?;
+    if route_ops.ipv6_blackhole_exists(dest).await? {
+        Ok(())
+    } else {
+        Err(Error::Ipv6BlackholeMissing(dest.to_string()))
+    }
+}
+
+/// Remove owned IPv6 blackholes regardless of the configured mode, so leftovers of earlier runs are cleaned up.
+pub(crate) async fn remove_ipv6_blackholes(route_ops: &impl RouteOps) {
+    if !route_ops.ipv6_enabled().await {
+        return;
+    }
+    for dest in IPV6_BLACKHOLES {
+        if let Err(e) = route_ops.ipv6_blackhole_del(dest).await {
+            tracing::warn!(%e, %dest, "failed to remove IPv6 blackhole route");
+        }
+    }
+}
+
+/// Build the [`Explanation`] using `route_ops`.
+/// Without a WAN default route only the IPv6 blackholes and tunnel routes are planned.
+pub(crate) async fn check_plan(
+    route_ops: &impl RouteOps,
+    peer_ips: &[Ipv4Addr],
+    wg_interface: &str,
+    block_ipv6: BlockIpv6,
+) -> Result<Explanation, Error> {
+    let wan = route_ops
+        .get_wan_route_for(PUBLIC_INTERNET_ADDRESS, wg_interface)
+        .await?;
+    let ipv6_blackholes = blocks_ipv6(route_ops, block_ipv6).await;
+    let planned = match &wan {
+        Some(wan) => plan(wan, peer_ips, wg_interface, ipv6_blackholes),
+        None if ipv6_blackholes => {
+            let mut routes = ipv6_blackhole_routes();
+            routes.extend(tunnel_routes(wg_interface));
+            routes
+        }
+        None => tunnel_routes(wg_interface),
+    };
+    let mut routes = Vec::with_capacity(planned.len());
+    for route in planned {
+        let present = match route.kind {
+            RouteKind::Ipv6Blackhole => route_ops.ipv6_blackhole_exists(&route.dest).await,
+            _ => route_ops.route_exists(&route.dest, &route.device).await,
+        };
+        let present = match present {
+            Ok(present) => Some(present),
+            Err(error) => {
+                tracing::debug!(?error, dest = %route.dest, "unable to check route");
+                None
+            }
+        };
+        routes.push((route, present));
+    }
+    Ok(Explanation { wan, routes })
+}
+
+/// Whether each of the `planned` tables is installed, `None` if that could not be checked.
+#[cfg(target_os = "linux")]
+pub(crate) async fn check_tables(planned: Vec<PlannedTable>) -> Vec<(PlannedTable, Option<bool>)> {
+    use gnosis_vpn_lib::shell_command_ext::ShellCommandExt;
+    use tokio::process::Command;
+
+    let installed = match Command::new("nft")
+        .args(["-j", "list", "tables", "inet"])
+        .run_stdout(QUERY)
+        .await
+    {
+        Ok(json) => parse_tables(&json).map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    let installed = installed
+        .inspect_err(|error| tracing::debug!(%error, "unable to list nftables tables"))
+        .ok();
+    planned
+        .into_iter()
+        .map(|table| {
+            let present = installed.as_ref().map(|names| names.contains(table.kind.table()));
+            (table, present)
+        })
+        .collect()
+}
+
+/// The firewall of macOS is not inspected.
+#[cfg(target_os = "macos")]
+pub(crate) async fn check_tables(planned: Vec<PlannedTable>) -> Vec<(PlannedTable, Option<bool>)> {
+    planned.into_iter().map(|table| (table, None)).collect()
+}
+
+/// Names of the tables in the output of `nft -j list tables`.
+#[cfg(any(target_os = "linux", test))]
+fn parse_tables(json: &str) -> Result<std::collections::HashSet<String>, serde_json::Error> {
+    let value: serde_json::Value = serde_json::from_str(json)?;
+    let entries = value["nftables"].as_array().cloned().unwrap_or_default();
+    Ok(entries
+        .iter()
+        .filter_map(|entry| entry["table"]["name"].as_str().map(str::to_string))
+        .collect())
+}
+
+impl TableKind {
+    /// nftables table holding the rules on Linux.
+    pub fn table(self) -> &'static str {
+        match self {
+            TableKind::Dscp => "gnosis_vpn_qos",
+            TableKind::Gateway => "gnosis_vpn_gateway",
+            TableKind::Killswitch => "gnosis_vpn_ks",
+            TableKind::KillSwitchBlock => "gnosis_vpn_block",
+        }
+    }
+}
+
+impl Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Auto => write!(f, "auto"),
+            Backend::Netlink => write!(f, "netlink"),
+            Backend::Iproute2 => write!(f, "iproute2"),
+            Backend::Route => write!(f, "route"),
+        }
+    }
+}
+
+impl Display for RouteKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteKind::PeerBypass => write!(f, "peer bypass"),
+            RouteKind::LanBypass => write!(f, "LAN bypass"),
+            RouteKind::Tunnel => write!(f, "tunnel"),
+            RouteKind::Ipv6Blackhole => write!(f, "IPv6 blackhole"),
+        }
+    }
+}
+
+impl Display for TableKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableKind::Dscp => write!(f, "DSCP marking"),
+            TableKind::Gateway => write!(f, "local gateway"),
+            TableKind::Killswitch => write!(f, "killswitch"),
+            TableKind::KillSwitchBlock => write!(f, "kill switch block"),
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error(transparent)]
@@ -62,13 +417,49 @@ pub enum Error {
     Dirs(#[from] dirs::Error),
     #[error("IO error: {0}")]
     IO(#[from] std::io::Error),
-    #[error("wg-quick error: {0}")]
+    #[error("WireGuard error: {0}")]
     WgTooling(#[from] wireguard::Error),
+    #[error("IPv6 blackhole route {0} missing after adding it")]
+    Ipv6BlackholeMissing(String),
+    #[error("Routing backend {0} is not available on this platform")]
+    BackendUnsupported(Backend),
 
-    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    #[error("Egress rate limiting is not supported on this platform")]
+    RateLimitUnsupported,
+
+    #[cfg(target_os = "macos")]
+    #[error("DSCP marking is not supported on this platform")]
+    DscpUnsupported,
+
+    #[cfg(target_os = "macos")]
+    #[error("Gateway mode is not supported on this platform")]
+    GatewayUnsupported,
+
+    #[cfg(target_os = "macos")]
+    #[error("Kill switch mode is not supported on this platform")]
+    KillSwitchUnsupported,
+
+    #[cfg(target_os = "macos")]
+    #[error("Only the wg-quick DNS strategy is supported on this platform")]
+    DnsUnsupported,
+
+    #[cfg(any(target_os = "linux", test))]
     #[error("General error: {0}")]
     General(String),
 
+    #[cfg(target_os = "linux")]
+    #[error("Invalid interface name {0:?}")]
+    InterfaceName(String),
+
+    #[cfg(target_os = "linux")]
+    #[error("Kill switch error: {0}")]
+    KillSwitch(#[from] gnosis_vpn_lib::killswitch::Error),
+
+    #[cfg(target_os = "linux")]
+    #[error("nftables error: {0}")]
+    NfTables(#[from] gnosis_vpn_lib::nftables::Error),
+
     #[cfg(target_os = "linux")]
     #[error("rtnetlink error: {0} ")]
     Rtnetlink(#[from] rtnetlink::Error),
@@ -95,3 +486,148 @@ pub trait Routing {
     /// Should be a no-op (return Ok) if routing is not yet set up.
     async fn remove_peer_bypass_route(&mut self, ip: Ipv4Addr) -> Result<(), Error>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plan_bypasses_before_tunnel_routes() {
+        let wan = WanRoute {
+            device: "eth0".to_string(),
+            gateway: Some("192.168.1.1".to_string()),
+            src_ip: None,
+        };
+        let routes = plan(&wan, &[Ipv4Addr::new(1, 2, 3, 4)], "wg0_gnosisvpn", false);
+
+        let kinds: Vec<RouteKind> = routes.iter().map(|r| r.kind).collect();
+        let mut expected = vec![RouteKind::PeerBypass];
+        expected.extend([RouteKind::LanBypass; 4]);
+        expected.extend([RouteKind::Tunnel; 3]);
+        assert_eq!(kinds, expected);
+
+        assert_eq!(routes[0].dest, "1.2.3.4");
+        assert_eq!(routes[0].gateway.as_deref(), Some("192.168.1.1"));
+        assert_eq!(routes[0].device, "eth0");
+        let tunnel: Vec<&str> = routes[5..].iter().map(|r| r.dest.as_str()).collect();
+        assert_eq!(tunnel, ["0.0.0.0/1", "128.0.0.0/1", "10.128.0.0/9"]);
+        assert!(
+            routes[5..]
+                .iter()
+                .all(|r| r.device == "wg0_gnosisvpn" && r.gateway.is_none())
+        );
+    }
+
+    #[test]
+    fn plan_blackholes_ipv6_first() {
+        let wan = WanRoute {
+            device: "eth0".to_string(),
+            gateway: None,
+            src_ip: None,
+        };
+        let routes = plan(&wan, &[], "wg0_gnosisvpn", true);
+        let blackholes: Vec<&str> = routes
+            .iter()
+            .take_while(|r| r.kind == RouteKind::Ipv6Blackhole)
+            .map(|r| r.dest.as_str())
+            .collect();
+        assert_eq!(blackholes, IPV6_BLACKHOLES);
+        assert_eq!(routes.len(), IPV6_BLACKHOLES.len() + 4 + 3);
+    }
+
+    #[test]
+    fn installed_tables_are_parsed_from_nft_json() {
+        let json = r#"{"nftables": [{"metainfo": {"version": "1.0.9", "json_schema_version": 1}},
+            {"table": {"family": "inet", "name": "gnosis_vpn_ks", "handle": 3}},
+            {"table": {"family": "inet", "name": "gnosis_vpn_qos", "handle": 4}}]}"#;
+        let tables = parse_tables(json).unwrap();
+        assert!(tables.contains(TableKind::Killswitch.table()));
+        assert!(tables.contains(TableKind::Dscp.table()));
+        assert!(!tables.contains(TableKind::KillSwitchBlock.table()));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn table_names_match_the_installed_tables() {
+        use gnosis_vpn_lib::killswitch;
+
+        let name = |table: &std::ffi::CStr| table.to_string_lossy().into_owned();
+        assert_eq!(TableKind::Dscp.table(), name(dscp::TABLE_NAME));
+        assert_eq!(TableKind::Gateway.table(), name(gateway::TABLE_NAME));
+        assert_eq!(TableKind::Killswitch.table(), name(killswitch::TABLE_NAME));
+        assert_eq!(TableKind::KillSwitchBlock.table(), name(killswitch::BLOCK_TABLE_NAME));
+    }
+
+    /// Only implements the IPv6 blackhole operations, with a table that silently drops `::/1`.
+    #[derive(Default)]
+    pub(super) struct Blackholes {
+        ipv6_disabled: bool,
+        routes: std::sync::Mutex<Vec<String>>,
+    }
+
+    fn unsupported() -> Error {
+        Error::General("only IPv6 blackholes are supported by this mock".to_string())
+    }
+
+    #[async_trait]
+    impl RouteOps for Blackholes {
+        async fn get_wan_route_for(&self, _: Ipv4Addr, _: &str) -> Result<Option<WanRoute>, Error> {
+            Err(unsupported())
+        }
+        async fn get_route_via_device(&self, _: Ipv4Addr, _: &str) -> Result<Option<WanRoute>, Error> {
+            Err(unsupported())
+        }
+        async fn route_add(&self, _: &str, _: Option<&str>, _: &str) -> Result<(), Error> {
+            Err(unsupported())
+        }
+        async fn route_del(&self, _: &str, _: &str) -> Result<(), Error> {
+            Err(unsupported())
+        }
+        async fn route_exists(&self, _: &str, _: &str) -> Result<bool, Error> {
+            Err(unsupported())
+        }
+        async fn ipv6_enabled(&self) -> bool {
+            !self.ipv6_disabled
+        }
+        async fn ipv6_blackhole_add(&self, dest: &str) -> Result<(), Error> {
+            if dest != "::/1" {
+                self.routes.lock().unwrap().push(dest.to_string());
+            }
+            Ok(())
+        }
+        async fn ipv6_blackhole_del(&self, dest: &str) -> Result<(), Error> {
+            self.routes.lock().unwrap().retain(|r| r != dest);
+            Ok(())
+        }
+        async fn ipv6_blackhole_exists(&self, dest: &str) -> Result<bool, Error> {
+            Ok(self.routes.lock().unwrap().iter().any(|r| r == dest))
+        }
+    }
+
+    #[tokio::test]
+    async fn ipv6_blackholes_follow_mode() {
+        let ops = Blackholes::default();
+        add_ipv6_blackholes(&ops, BlockIpv6::Never)
+            .await
+            .expect("never succeeds");
+        assert!(ops.routes.lock().unwrap().is_empty());
+
+        add_ipv6_blackholes(&ops, BlockIpv6::Auto)
+            .await
+            .expect("auto only warns");
+        assert_eq!(*ops.routes.lock().unwrap(), ["8000::/1"]);
+
+        let res = add_ipv6_blackholes(&ops, BlockIpv6::Always).await;
+        assert!(matches!(res, Err(Error::Ipv6BlackholeMissing(dest)) if dest == "::/1"));
+        assert!(ops.routes.lock().unwrap().is_empty(), "always rolls back on failure");
+
+        let ops = Blackholes {
+            ipv6_disabled: true,
+            ..Default::default()
+        };
+        add_ipv6_blackholes(&ops, BlockIpv6::Auto)
+            .await
+            .expect("nothing to block");
+        assert!(ops.routes.lock().unwrap().is_empty());
+    }
+}
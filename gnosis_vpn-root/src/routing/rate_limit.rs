@@ -0,0 +1,94 @@
+//! Egress rate limiting on the WireGuard interface.
+//!
+//! On Linux a single HTB class is installed as root qdisc of the tunnel device, so every
+//! packet leaving through the tunnel is shaped to the configured rate. macOS would need a
+//! dummynet pipe wired into PF, which is not implemented yet.
+
+use human_bandwidth::re::bandwidth::Bandwidth;
+
+use super::Error;
+
+#[cfg(target_os = "linux")]
+use super::command;
+#[cfg(target_os = "linux")]
+use gnosis_vpn_lib::shell_command_ext::{Logs, ShellCommandExt};
+#[cfg(target_os = "linux")]
+use tokio::process::Command;
+
+/// Arguments for `tc` installing (or replacing) the root HTB qdisc.
+#[cfg(any(target_os = "linux", test))]
+fn qdisc_args(interface: &str) -> Vec<String> {
+    [
+        "qdisc", "replace", "dev", interface, "root", "handle", "1:", "htb", "default", "10",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+/// Arguments for `tc` setting the rate of the default HTB class.
+#[cfg(any(target_os = "linux", test))]
+fn class_args(interface: &str, limit: Bandwidth) -> Vec<String> {
+    let rate = format!("{}bit", limit.as_bps());
+    [
+        "class", "replace", "dev", interface, "parent", "1:", "classid", "1:10", "htb", "rate", &rate, "ceil", &rate,
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+/// Apply `limit` to `interface`, or remove any shaping when `limit` is `None`.
+#[cfg(target_os = "linux")]
+pub async fn apply(interface: &str, limit: Option<Bandwidth>) -> Result<(), Error> {
+    match limit {
+        Some(limit) => {
+            Command::new("tc")
+                .args(qdisc_args(interface))
+                .run(command(Logs::Print))
+                .await?;
+            Command::new("tc")
+                .args(class_args(interface, limit))
+                .run(command(Logs::Print))
+                .await?;
+            Ok(())
+        }
+        None => {
+            // deleting a non existing root qdisc fails - nothing to clean up in that case
+            let _ = Command::new("tc")
+                .args(["qdisc", "del", "dev", interface, "root"])
+                .run(command(Logs::Suppress))
+                .await;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub async fn apply(_interface: &str, limit: Option<Bandwidth>) -> Result<(), Error> {
+    match limit {
+        Some(_) => Err(Error::RateLimitUnsupported),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qdisc_targets_interface_root() {
+        assert_eq!(
+            qdisc_args("wg0_gnosisvpn").join(" "),
+            "qdisc replace dev wg0_gnosisvpn root handle 1: htb default 10"
+        );
+    }
+
+    #[test]
+    fn class_rate_is_expressed_in_bits() {
+        assert_eq!(
+            class_args("wg0_gnosisvpn", Bandwidth::from_mbps(2)).join(" "),
+            "class replace dev wg0_gnosisvpn parent 1: classid 1:10 htb rate 2000000bit ceil 2000000bit"
+        );
+    }
+}
@@ -5,10 +5,15 @@
 //! - the bypass route manager (`bypass::BypassRouteManager`)
 //! - the macOS router (module `routing::macos`)
 //!
-//! **Limitation:** All operations are IPv4-only. IPv6 routing is not supported.
+//! **Limitation:** All operations are IPv4-only, except for the IPv6 blackholes.
+//! IPv6 routing is not supported.
 //!
-//! Platform-specific implementations:
+//! Every route is tagged as owned by gnosisvpn and deletes only ever remove owned routes,
+//! so routes of the user or other software to the same destination are left alone.
+//!
+//! Platform-specific implementations, picked by `--routing-backend`:
 //! - Linux: type `NetlinkRouteOps` in module `routing::route_ops_linux` (via rtnetlink)
+//! - Linux: type `IpRouteOps` in module `routing::route_ops_ip` (via iproute2 `ip route`)
 //! - macOS: type `DarwinRouteOps` in module `routing::route_ops_macos`
 
 use async_trait::async_trait;
@@ -51,6 +56,21 @@ pub trait RouteOps: Send + Sync {
     /// Add a route: destination via optional gateway through device.
     async fn route_add(&self, dest: &str, gateway: Option<&str>, device: &str) -> Result<(), Error>;
 
-    /// Delete a route by destination and device.
+    /// Delete an owned route by destination and device.
     async fn route_del(&self, dest: &str, device: &str) -> Result<(), Error>;
+
+    /// Whether an owned route for exactly `dest` through `device` is in the routing table.
+    async fn route_exists(&self, dest: &str, device: &str) -> Result<bool, Error>;
+
+    /// Whether the host has IPv6 enabled, without it there is nothing to leak.
+    async fn ipv6_enabled(&self) -> bool;
+
+    /// Add an owned IPv6 blackhole route for `dest`, succeeds if it already exists.
+    async fn ipv6_blackhole_add(&self, dest: &str) -> Result<(), Error>;
+
+    /// Delete the owned IPv6 blackhole route for `dest`, succeeds if there is none.
+    async fn ipv6_blackhole_del(&self, dest: &str) -> Result<(), Error>;
+
+    /// Whether an owned IPv6 blackhole route for exactly `dest` is in the routing table.
+    async fn ipv6_blackhole_exists(&self, dest: &str) -> Result<bool, Error>;
 }
@@ -0,0 +1,277 @@
+//! Linux route operations using the iproute2 `ip` command.
+//!
+//! [`IpRouteOps`] implements [`RouteOps`] by running `ip route` and parsing its JSON output.
+//! It is used instead of [`super::route_ops_linux::NetlinkRouteOps`] when the startup self-test
+//! finds that `netlink-packet-route` cannot reliably parse the replies of the running kernel,
+//! see [`super::select_backend`].
+//!
+//! Routes are tagged with the same [`ROUTE_PROTOCOL`] as the netlink implementation, so both
+//! recognize and clean up routes installed by the other.
+
+use async_trait::async_trait;
+use tokio::process::Command;
+
+use gnosis_vpn_lib::shell_command_ext::{Logs, ShellCommandExt};
+
+use std::collections::BTreeSet;
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+
+use super::route_ops::{RouteOps, WanRoute};
+use super::route_ops_linux::{ROUTE_PROTOCOL, covers, ipv6_enabled};
+use super::{Error, QUERY, command};
+
+/// [`ROUTE_PROTOCOL`] as `ip` argument.
+fn protocol() -> String {
+    ROUTE_PROTOCOL.to_string()
+}
+
+/// An IPv4 route as listed by `ip -j route show`.
+#[derive(Debug, Clone, PartialEq)]
+struct IpRoute {
+    dest: Ipv4Addr,
+    prefix_len: u8,
+    device: Option<String>,
+    gateway: Option<String>,
+    src_ip: Option<Ipv4Addr>,
+    metric: u32,
+}
+
+/// Parse `ip -j -4 route show` output, entries with an unexpected destination are skipped.
+fn parse_routes(json: &str) -> Result<Vec<IpRoute>, serde_json::Error> {
+    // older iproute2 versions print nothing at all for an empty table
+    if json.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let value: serde_json::Value = serde_json::from_str(json)?;
+    let entries = value.as_array().cloned().unwrap_or_default();
+    Ok(entries
+        .iter()
+        .filter_map(|entry| {
+            let (dest, prefix_len) = match entry["dst"].as_str()? {
+                "default" => (Ipv4Addr::UNSPECIFIED, 0),
+                dst => match dst.split_once('/') {
+                    Some((addr, len)) => (Ipv4Addr::from_str(addr).ok()?, len.parse().ok()?),
+                    None => (Ipv4Addr::from_str(dst).ok()?, 32),
+                },
+            };
+            Some(IpRoute {
+                dest,
+                prefix_len,
+                device: entry["dev"].as_str().map(str::to_string),
+                gateway: entry["gateway"].as_str().map(str::to_string),
+                src_ip: entry["prefsrc"].as_str().and_then(|s| s.parse().ok()),
+                metric: entry["metric"]
+                    .as_u64()
+                    .and_then(|m| u32::try_from(m).ok())
+                    .unwrap_or(0),
+            })
+        })
+        .collect())
+}
+
+/// The most specific route covering `dest` accepted by `filter`, the lowest metric wins a tie.
+fn best_route<'a>(routes: &'a [IpRoute], dest: Ipv4Addr, filter: impl Fn(&str) -> bool) -> Option<&'a IpRoute> {
+    routes
+        .iter()
+        .filter(|r| r.device.as_deref().is_some_and(&filter))
+        .filter(|r| covers(r.dest, r.prefix_len, dest))
+        .max_by_key(|r| (r.prefix_len, std::cmp::Reverse(r.metric)))
+}
+
+/// Production [`RouteOps`] for Linux backed by the `ip` command.
+pub struct IpRouteOps;
+
+impl IpRouteOps {
+    async fn show(args: &[&str]) -> Result<Vec<IpRoute>, Error> {
+        let json = Command::new("ip")
+            .args(["-j", "-4", "route", "show"])
+            .args(args)
+            .run_stdout(QUERY)
+            .await?;
+        parse_routes(&json).map_err(|e| Error::General(format!("unable to parse ip route output: {e}")))
+    }
+
+    /// Destination and device of every route in the main table, compared with netlink by the self-test.
+    pub(crate) async fn main_table(&self) -> Result<BTreeSet<(String, Option<String>)>, Error> {
+        Ok(Self::show(&["table", "main"])
+            .await?
+            .into_iter()
+            .map(|r| (format!("{}/{}", r.dest, r.prefix_len), r.device))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl RouteOps for IpRouteOps {
+    async fn get_wan_route_for(&self, dest: Ipv4Addr, exclude_iface: &str) -> Result<Option<WanRoute>, Error> {
+        let routes = Self::show(&["table", "main"]).await?;
+        Ok(best_route(&routes, dest, |dev| dev != exclude_iface).map(|r| WanRoute {
+            device: r.device.clone().unwrap_or_default(),
+            gateway: r.gateway.clone(),
+            src_ip: r.src_ip,
+        }))
+    }
+
+    async fn get_route_via_device(&self, dest: Ipv4Addr, device: &str) -> Result<Option<WanRoute>, Error> {
+        // a missing interface simply has no routes
+        let routes = Self::show(&["table", "main"]).await?;
+        Ok(best_route(&routes, dest, |dev| dev == device).map(|r| WanRoute {
+            device: device.to_owned(),
+            gateway: r.gateway.clone(),
+            src_ip: r.src_ip,
+        }))
+    }
+
+    async fn route_add(&self, dest: &str, gateway: Option<&str>, device: &str) -> Result<(), Error> {
+        let mut cmd = Command::new("ip");
+        cmd.args(["-4", "route", "add", dest]);
+        if let Some(gw) = gateway {
+            cmd.args(["via", gw]);
+        }
+        cmd.args(["dev", device, "proto", protocol().as_str()])
+            .run_stdout(command(Logs::Print))
+            .await?;
+        Ok(())
+    }
+
+    async fn route_del(&self, dest: &str, device: &str) -> Result<(), Error> {
+        Command::new("ip")
+            .args(["-4", "route", "del", dest, "dev", device, "proto", protocol().as_str()])
+            .run_stdout(command(Logs::Suppress))
+            .await?;
+        Ok(())
+    }
+
+    async fn route_exists(&self, dest: &str, device: &str) -> Result<bool, Error> {
+        let protocol = protocol();
+        match Self::show(&[
+            "table",
+            "main",
+            "exact",
+            dest,
+            "dev",
+            device,
+            "proto",
+            protocol.as_str(),
+        ])
+        .await
+        {
+            Ok(routes) => Ok(!routes.is_empty()),
+            // `ip` refuses to filter by a device that does not exist
+            Err(Error::ShellCommand(_)) if !device_exists(device) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn ipv6_enabled(&self) -> bool {
+        ipv6_enabled().await
+    }
+
+    async fn ipv6_blackhole_add(&self, dest: &str) -> Result<(), Error> {
+        if self.ipv6_blackhole_exists(dest).await? {
+            return Ok(());
+        }
+        Command::new("ip")
+            .args(["-6", "route", "add", "blackhole", dest, "proto", protocol().as_str()])
+            .run_stdout(command(Logs::Print))
+            .await?;
+        Ok(())
+    }
+
+    async fn ipv6_blackhole_del(&self, dest: &str) -> Result<(), Error> {
+        if !self.ipv6_blackhole_exists(dest).await? {
+            tracing::debug!(%dest, "no owned IPv6 blackhole route to delete");
+            return Ok(());
+        }
+        Command::new("ip")
+            .args(["-6", "route", "del", "blackhole", dest, "proto", protocol().as_str()])
+            .run_stdout(command(Logs::Suppress))
+            .await?;
+        Ok(())
+    }
+
+    async fn ipv6_blackhole_exists(&self, dest: &str) -> Result<bool, Error> {
+        let json = Command::new("ip")
+            .args([
+                "-j",
+                "-6",
+                "route",
+                "show",
+                "table",
+                "main",
+                "exact",
+                dest,
+                "type",
+                "blackhole",
+            ])
+            .args(["proto", protocol().as_str()])
+            .run_stdout(QUERY)
+            .await?;
+        if json.trim().is_empty() {
+            return Ok(false);
+        }
+        let value: serde_json::Value =
+            serde_json::from_str(&json).map_err(|e| Error::General(format!("unable to parse ip route output: {e}")))?;
+        Ok(value.as_array().is_some_and(|routes| !routes.is_empty()))
+    }
+}
+
+fn device_exists(device: &str) -> bool {
+    std::ffi::CString::new(device)
+        .map(|name| unsafe { libc::if_nametoindex(name.as_ptr()) } != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAIN_TABLE: &str = r#"[{"dst":"default","gateway":"192.168.1.1","dev":"wlan0","protocol":"dhcp",
+        "prefsrc":"192.168.1.20","metric":600,"flags":[]},
+        {"dst":"default","gateway":"10.0.0.1","dev":"eth0","protocol":"dhcp","metric":100,"flags":[]},
+        {"dst":"0.0.0.0/1","dev":"wg0_gnosisvpn","protocol":"152","scope":"link","flags":[]},
+        {"dst":"1.1.1.1","gateway":"192.168.1.1","dev":"wlan0","protocol":"152","flags":[]},
+        {"dst":"192.168.1.0/24","dev":"wlan0","protocol":"kernel","scope":"link","prefsrc":"192.168.1.20",
+        "metric":600,"flags":[]},
+        {"dst":"10.1.0.0/16","flags":[],"nexthops":[{"gateway":"10.0.0.1","dev":"eth0"}]}]"#;
+
+    #[test]
+    fn parse_routes_reads_default_host_and_prefix_routes() {
+        let routes = parse_routes(MAIN_TABLE).unwrap();
+        assert_eq!(routes.len(), 6);
+        assert_eq!(
+            routes[0],
+            IpRoute {
+                dest: Ipv4Addr::UNSPECIFIED,
+                prefix_len: 0,
+                device: Some("wlan0".to_string()),
+                gateway: Some("192.168.1.1".to_string()),
+                src_ip: Some(Ipv4Addr::new(192, 168, 1, 20)),
+                metric: 600,
+            }
+        );
+        assert_eq!((routes[3].dest, routes[3].prefix_len), (Ipv4Addr::new(1, 1, 1, 1), 32));
+        assert_eq!(routes[5].device, None);
+        assert!(parse_routes("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn best_route_prefers_specific_then_lowest_metric() {
+        let routes = parse_routes(MAIN_TABLE).unwrap();
+        let dest = Ipv4Addr::new(8, 8, 8, 8);
+        let wan = best_route(&routes, dest, |dev| dev != "wg0_gnosisvpn").unwrap();
+        assert_eq!(wan.device.as_deref(), Some("eth0"));
+        let via_wlan = best_route(&routes, dest, |dev| dev == "wlan0").unwrap();
+        assert_eq!(via_wlan.gateway.as_deref(), Some("192.168.1.1"));
+        let tunnel = best_route(&routes, dest, |_| true).unwrap();
+        assert_eq!(tunnel.device.as_deref(), Some("wg0_gnosisvpn"));
+    }
+
+    #[test]
+    fn best_route_skips_multipath_routes_without_device() {
+        let routes = parse_routes(MAIN_TABLE).unwrap();
+        let route = best_route(&routes, Ipv4Addr::new(10, 1, 2, 3), |dev| dev != "wg0_gnosisvpn").unwrap();
+        assert_eq!(route.prefix_len, 0);
+    }
+}
@@ -1,19 +1,41 @@
 //! Linux route operations using rtnetlink.
 //!
 //! [`NetlinkRouteOps`] implements [`RouteOps`] using typed netlink messages via `rtnetlink::Handle`.
+//!
+//! Routes are tagged with [`ROUTE_PROTOCOL`] (shown by `ip route` as `proto 152`). Deletes carry the
+//! same protocol, which makes the kernel skip routes installed by anyone else.
+//!
+//! Interfaces are resolved with `if_nametoindex` and `if_indextoname` instead of link dumps. Newer
+//! kernels send link attributes (e.g. `IFLA_INET6_CONF`) larger than `netlink-packet-route` knows,
+//! route operations should not depend on parsing those.
 
 use async_trait::async_trait;
 use futures::TryStreamExt;
-use rtnetlink::packet_route::link::LinkAttribute;
-use rtnetlink::packet_route::route::{RouteAddress, RouteAttribute};
-use std::net::Ipv4Addr;
+use rtnetlink::packet_route::route::{RouteAddress, RouteAttribute, RouteProtocol, RouteType};
+use std::collections::BTreeSet;
+use std::ffi::{CStr, CString};
+use std::net::{Ipv4Addr, Ipv6Addr};
 use std::str::FromStr;
 
 use super::Error;
 use super::route_ops::{RouteOps, WanRoute};
 
+pub(crate) use gnosis_vpn_lib::wireguard::ROUTE_PROTOCOL;
+
+/// Set to `1` when IPv6 is disabled at runtime, missing when the kernel has no IPv6 support at all.
+const DISABLE_IPV6: &str = "/proc/sys/net/ipv6/conf/all/disable_ipv6";
+
+fn owned() -> RouteProtocol {
+    RouteProtocol::from(ROUTE_PROTOCOL)
+}
+
+/// Whether the kernel rejected a request with `errno`, e.g. `ESRCH` when deleting a missing route.
+pub(crate) fn is_errno(error: &rtnetlink::Error, errno: i32) -> bool {
+    matches!(error, rtnetlink::Error::NetlinkError(msg) if msg.raw_code() == -errno)
+}
+
 /// Returns true if `prefix/len` covers `dest` (i.e. they share the same leading `len` bits).
-fn covers(prefix: Ipv4Addr, len: u8, dest: Ipv4Addr) -> bool {
+pub(crate) fn covers(prefix: Ipv4Addr, len: u8, dest: Ipv4Addr) -> bool {
     if len == 0 {
         return true;
     }
@@ -21,6 +43,23 @@ fn covers(prefix: Ipv4Addr, len: u8, dest: Ipv4Addr) -> bool {
     (u32::from(prefix) & mask) == (u32::from(dest) & mask)
 }
 
+/// Whether the host has IPv6 enabled, see [`DISABLE_IPV6`].
+pub(crate) async fn ipv6_enabled() -> bool {
+    match tokio::fs::read_to_string(DISABLE_IPV6).await {
+        Ok(value) => value.trim() != "1",
+        Err(_) => false,
+    }
+}
+
+/// Interface index of `device` via `if_nametoindex`.
+pub(crate) fn ifindex(device: &str) -> Result<u32, Error> {
+    let name = CString::new(device).map_err(|e| Error::General(format!("invalid interface name: {e}")))?;
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => Err(Error::General(format!("interface '{device}' not found"))),
+        index => Ok(index),
+    }
+}
+
 /// Production [`RouteOps`] for Linux backed by an `rtnetlink::Handle`.
 pub struct NetlinkRouteOps {
     handle: rtnetlink::Handle,
@@ -48,36 +87,63 @@ impl NetlinkRouteOps {
         }
     }
 
+    /// Parse an IPv6 destination like "8000::/1" into (addr, prefix_len).
+    fn parse_dest6(dest: &str) -> Result<(Ipv6Addr, u8), Error> {
+        let (addr_str, prefix_str) = dest.split_once('/').unwrap_or((dest, "128"));
+        let addr = Ipv6Addr::from_str(addr_str)
+            .map_err(|e| Error::General(format!("invalid IPv6 route destination address: {e}")))?;
+        let prefix_len: u8 = prefix_str
+            .parse()
+            .map_err(|e| Error::General(format!("invalid route prefix length: {e}")))?;
+        Ok((addr, prefix_len))
+    }
+
     /// Resolve a device name to its interface index.
     async fn resolve_ifindex(&self, device: &str) -> Result<u32, Error> {
-        let links: Vec<_> = self
-            .handle
-            .link()
-            .get()
-            .match_name(device.to_string())
-            .execute()
-            .try_collect()
-            .await
-            .map_err(|e| Error::General(format!("failed to resolve interface '{device}': {e}")))?;
-
-        links
-            .first()
-            .map(|l| l.header.index)
-            .ok_or_else(|| Error::General(format!("interface '{device}' not found")))
+        ifindex(device)
     }
 
     async fn resolve_ifname(&self, index: u32) -> Result<String, Error> {
-        let links: Vec<_> = self.handle.link().get().execute().try_collect().await?;
-        links
-            .iter()
-            .find(|l| l.header.index == index)
-            .and_then(|l| {
-                l.attributes.iter().find_map(|a| match a {
-                    LinkAttribute::IfName(n) => Some(n.clone()),
+        let mut buf = [0u8; libc::IF_NAMESIZE];
+        let ptr = unsafe { libc::if_indextoname(index, buf.as_mut_ptr().cast()) };
+        if ptr.is_null() {
+            return Err(Error::General(format!("interface name not found for index {index}")));
+        }
+        CStr::from_bytes_until_nul(&buf)
+            .map(|s| s.to_string_lossy().into_owned())
+            .map_err(|e| Error::General(format!("invalid interface name for index {index}: {e}")))
+    }
+
+    /// Destination and device of every route in the main table, compared with `ip route` by the self-test.
+    pub(crate) async fn main_table(&self) -> Result<BTreeSet<(String, Option<String>)>, Error> {
+        let routes: Vec<_> = self
+            .handle
+            .route()
+            .get(rtnetlink::RouteMessageBuilder::<Ipv4Addr>::default().build())
+            .execute()
+            .try_collect()
+            .await?;
+        let mut res = BTreeSet::new();
+        for route in routes.iter().filter(|r| r.header.table == 254) {
+            let dest = route
+                .attributes
+                .iter()
+                .find_map(|a| match a {
+                    RouteAttribute::Destination(RouteAddress::Inet(ip)) => Some(*ip),
                     _ => None,
                 })
-            })
-            .ok_or_else(|| Error::General(format!("interface name not found for index {index}")))
+                .unwrap_or(Ipv4Addr::UNSPECIFIED);
+            let oif = route.attributes.iter().find_map(|a| match a {
+                RouteAttribute::Oif(idx) => Some(*idx),
+                _ => None,
+            });
+            let device = match oif {
+                Some(index) => Some(self.resolve_ifname(index).await?),
+                None => None,
+            };
+            res.insert((format!("{dest}/{}", route.header.destination_prefix_length), device));
+        }
+        Ok(res)
     }
 }
 
@@ -237,7 +303,8 @@ impl RouteOps for NetlinkRouteOps {
 
         let mut builder = rtnetlink::RouteMessageBuilder::<Ipv4Addr>::default()
             .destination_prefix(addr, prefix_len)
-            .output_interface(if_index);
+            .output_interface(if_index)
+            .protocol(owned());
 
         if let Some(gw_str) = gateway {
             let gw = Ipv4Addr::from_str(gw_str).map_err(|e| Error::General(format!("invalid gateway address: {e}")))?;
@@ -255,11 +322,106 @@ impl RouteOps for NetlinkRouteOps {
         let msg = rtnetlink::RouteMessageBuilder::<Ipv4Addr>::default()
             .destination_prefix(addr, prefix_len)
             .output_interface(if_index)
+            .protocol(owned())
             .build();
 
         self.handle.route().del(msg).execute().await?;
         Ok(())
     }
+
+    async fn route_exists(&self, dest: &str, device: &str) -> Result<bool, Error> {
+        let (addr, prefix_len) = Self::parse_dest(dest)?;
+        // a missing interface cannot carry any route
+        let Ok(device_idx) = self.resolve_ifindex(device).await else {
+            return Ok(false);
+        };
+
+        let routes: Vec<_> = self
+            .handle
+            .route()
+            .get(rtnetlink::RouteMessageBuilder::<Ipv4Addr>::default().build())
+            .execute()
+            .try_collect()
+            .await?;
+
+        Ok(routes
+            .iter()
+            .filter(|r| r.header.table == 254 && r.header.protocol == owned())
+            .filter(|r| r.header.destination_prefix_length == prefix_len)
+            .filter(|r| {
+                r.attributes
+                    .iter()
+                    .any(|a| matches!(a, RouteAttribute::Oif(idx) if *idx == device_idx))
+            })
+            .any(|r| {
+                let prefix_addr = r
+                    .attributes
+                    .iter()
+                    .find_map(|a| match a {
+                        RouteAttribute::Destination(RouteAddress::Inet(ip)) => Some(*ip),
+                        _ => None,
+                    })
+                    .unwrap_or(Ipv4Addr::UNSPECIFIED);
+                prefix_addr == addr
+            }))
+    }
+
+    async fn ipv6_enabled(&self) -> bool {
+        ipv6_enabled().await
+    }
+
+    async fn ipv6_blackhole_add(&self, dest: &str) -> Result<(), Error> {
+        let (addr, prefix_len) = Self::parse_dest6(dest)?;
+        let msg = rtnetlink::RouteMessageBuilder::<Ipv6Addr>::default()
+            .destination_prefix(addr, prefix_len)
+            .kind(RouteType::BlackHole)
+            .protocol(owned())
+            .build();
+        match self.handle.route().add(msg).execute().await {
+            Err(e) if is_errno(&e, libc::EEXIST) => Ok(()),
+            res => Ok(res?),
+        }
+    }
+
+    async fn ipv6_blackhole_del(&self, dest: &str) -> Result<(), Error> {
+        let (addr, prefix_len) = Self::parse_dest6(dest)?;
+        let msg = rtnetlink::RouteMessageBuilder::<Ipv6Addr>::default()
+            .destination_prefix(addr, prefix_len)
+            .kind(RouteType::BlackHole)
+            .protocol(owned())
+            .build();
+        match self.handle.route().del(msg).execute().await {
+            Err(e) if is_errno(&e, libc::ESRCH) => Ok(()),
+            res => Ok(res?),
+        }
+    }
+
+    async fn ipv6_blackhole_exists(&self, dest: &str) -> Result<bool, Error> {
+        let (addr, prefix_len) = Self::parse_dest6(dest)?;
+        let routes: Vec<_> = self
+            .handle
+            .route()
+            .get(rtnetlink::RouteMessageBuilder::<Ipv6Addr>::default().build())
+            .execute()
+            .try_collect()
+            .await?;
+
+        Ok(routes
+            .iter()
+            .filter(|r| r.header.table == 254 && r.header.protocol == owned())
+            .filter(|r| r.header.kind == RouteType::BlackHole && r.header.destination_prefix_length == prefix_len)
+            .any(|r| {
+                let prefix_addr = r
+                    .attributes
+                    .iter()
+                    .find_map(|a| match a {
+                        RouteAttribute::Destination(RouteAddress::Inet6(ip)) => Some(*ip),
+                        _ => None,
+                    })
+                    .unwrap_or(Ipv6Addr::UNSPECIFIED);
+                prefix_addr == addr
+            }))
+    }
 }
 
 #[cfg(test)]
@@ -324,6 +486,14 @@ mod tests {
         assert_eq!(prefix, 32);
     }
 
+    #[test]
+    fn parse_dest6_cidr_notation() {
+        let (addr, prefix) = NetlinkRouteOps::parse_dest6("8000::/1").unwrap();
+        assert_eq!(addr, Ipv6Addr::new(0x8000, 0, 0, 0, 0, 0, 0, 0));
+        assert_eq!(prefix, 1);
+        assert!(NetlinkRouteOps::parse_dest6("10.0.0.0/8").is_err());
+    }
+
     #[test]
     fn parse_dest_rejects_invalid_address() {
         assert!(NetlinkRouteOps::parse_dest("not-an-ip").is_err());
@@ -3,21 +3,27 @@
 //! [`DarwinRouteOps`] implements [`RouteOps`] using macOS-native routing.
 //! Currently wraps the `route` command; a future iteration could use
 //! PF_ROUTE sockets directly for CLI-free operation.
+//!
+//! Routes are added with `-proto1`, setting the `RTF_PROTO1` flag. Deletes first look the route
+//! up and skip it unless the flag is set, so routes installed by anyone else are never removed.
 
 use async_trait::async_trait;
-use std::net::Ipv4Addr;
+use std::net::{Ipv4Addr, Ipv6Addr};
 use tokio::process::Command;
 
-use gnosis_vpn_lib::shell_command_ext::{Logs, ShellCommandExt};
+use gnosis_vpn_lib::shell_command_ext::{self, Logs, ShellCommandExt};
 
-use super::Error;
 use super::route_ops::{RouteOps, WanRoute};
+use super::{Error, QUERY, command};
+
+/// Flag name `route get` reports for routes added with `-proto1`.
+const OWNED_FLAG: &str = "PROTO1";
 
 /// Build the argument list for a `route add` invocation.
 ///
 /// When a gateway is present, `-ifp` pins the route to the named interface.
 /// Without a gateway, `-interface` marks the destination as directly reachable
-/// via the named interface.
+/// via the named interface. `-proto1` marks the route as owned.
 fn route_add_args(dest: &str, gateway: Option<&str>, device: &str) -> Vec<String> {
     let mut args = vec!["-n".into(), "add".into(), "-inet".into(), dest.into()];
     if let Some(gw) = gateway {
@@ -28,6 +34,7 @@ fn route_add_args(dest: &str, gateway: Option<&str>, device: &str) -> Vec<String
         args.push("-interface".into());
         args.push(device.into());
     }
+    args.push("-proto1".into());
     args
 }
 
@@ -41,17 +48,21 @@ impl RouteOps for DarwinRouteOps {
         for arg in route_add_args(dest, gateway, device) {
             cmd.arg(arg);
         }
-        cmd.run_stdout(Logs::Print).await?;
+        cmd.run_stdout(command(Logs::Print)).await?;
         Ok(())
     }
 
-    async fn route_del(&self, dest: &str, _device: &str) -> Result<(), Error> {
+    async fn route_del(&self, dest: &str, device: &str) -> Result<(), Error> {
+        if !self.route_exists(dest, device).await? {
+            tracing::debug!(%dest, %device, "no owned route to delete");
+            return Ok(());
+        }
         Command::new("route")
             .arg("-n")
             .arg("delete")
             .arg("-inet")
             .arg(dest)
-            .run_stdout(Logs::Suppress)
+            .run_stdout(command(Logs::Suppress))
             .await?;
         Ok(())
     }
@@ -65,7 +76,7 @@ impl RouteOps for DarwinRouteOps {
         // the more-specific VPN routes) and is unaffected by route shadowing.
         let output = Command::new("netstat")
             .args(["-rn", "-f", "inet"])
-            .run_stdout(Logs::Suppress)
+            .run_stdout(QUERY)
             .await?;
 
         let Some(gateway) = parse_netstat_default_for_device(&output, device) else {
@@ -86,7 +97,7 @@ impl RouteOps for DarwinRouteOps {
             .arg("-rn")
             .arg("-f")
             .arg("inet")
-            .run_stdout(Logs::Suppress)
+            .run_stdout(QUERY)
             .await?;
 
         let (device, gateway) = match parse_netstat_default_excluding(&output, exclude_iface) {
@@ -102,6 +113,59 @@ impl RouteOps for DarwinRouteOps {
             src_ip,
         }))
     }
+
+    async fn route_exists(&self, dest: &str, device: &str) -> Result<bool, Error> {
+        let kind = if dest.contains('/') { "-net" } else { "-host" };
+        let output = route_get(&["-inet", kind, dest]).await?;
+        Ok(output.is_some_and(|output| route_get_matches(&output, dest, device) && has_flag(&output, OWNED_FLAG)))
+    }
+
+    async fn ipv6_enabled(&self) -> bool {
+        // IPv6 cannot be switched off system wide on macOS
+        true
+    }
+
+    async fn ipv6_blackhole_add(&self, dest: &str) -> Result<(), Error> {
+        if self.ipv6_blackhole_exists(dest).await? {
+            return Ok(());
+        }
+        Command::new("route")
+            .args(["-n", "add", "-blackhole", "-proto1", "-inet6", dest, "::1"])
+            .run_stdout(command(Logs::Print))
+            .await?;
+        Ok(())
+    }
+
+    async fn ipv6_blackhole_del(&self, dest: &str) -> Result<(), Error> {
+        if !self.ipv6_blackhole_exists(dest).await? {
+            tracing::debug!(%dest, "no owned IPv6 blackhole route to delete");
+            return Ok(());
+        }
+        Command::new("route")
+            .args(["-n", "delete", "-inet6", dest, "::1"])
+            .run_stdout(command(Logs::Suppress))
+            .await?;
+        Ok(())
+    }
+
+    async fn ipv6_blackhole_exists(&self, dest: &str) -> Result<bool, Error> {
+        let output = route_get(&["-inet6", dest]).await?;
+        Ok(output.is_some_and(|output| route_get_owns_blackhole(&output, dest)))
+    }
+}
+
+/// Run `route -n get` with `args`, `None` if nothing matches, not even a default route.
+async fn route_get(args: &[&str]) -> Result<Option<String>, Error> {
+    match Command::new("route")
+        .args(["-n", "get"])
+        .args(args)
+        .run_stdout(command(Logs::Suppress))
+        .await
+    {
+        Ok(output) => Ok(Some(output)),
+        Err(shell_command_ext::Error::ExitCode(_)) => Ok(None),
+        Err(error) => Err(error.into()),
+    }
 }
 
 /// Parses `netstat -rn -f inet` output, returning the gateway for entries whose `netif` matches `device`.
@@ -158,13 +222,52 @@ fn parse_netstat_default_excluding(output: &str, exclude_iface: &str) -> Result<
     Err(Error::NoInterface)
 }
 
+/// Whether `route -n get` output describes a route for exactly `dest` through `device`.
+/// `route get` answers with the best matching route, which may merely cover `dest`.
+fn route_get_matches(output: &str, dest: &str, device: &str) -> bool {
+    let (addr, prefix) = dest.split_once('/').unwrap_or((dest, "32"));
+    let (Ok(addr), Ok(prefix)) = (addr.parse::<Ipv4Addr>(), prefix.parse::<u32>()) else {
+        return false;
+    };
+    if prefix > 32 {
+        return false;
+    }
+    let field = |name: &str| route_get_field(output, name);
+    let expected_mask = Ipv4Addr::from(u32::MAX.checked_shl(32 - prefix).unwrap_or(0));
+    // host routes come without a mask
+    let mask = field("mask").map_or(Some(Ipv4Addr::BROADCAST), |m| m.parse().ok());
+    field("destination").and_then(|d| d.parse().ok()) == Some(addr)
+        && mask == Some(expected_mask)
+        && field("interface") == Some(device)
+}
+
+/// Whether `route -n get -inet6` output describes an owned blackhole route for exactly `dest`.
+fn route_get_owns_blackhole(output: &str, dest: &str) -> bool {
+    let addr = dest.split_once('/').map_or(dest, |(addr, _)| addr);
+    let Ok(addr) = addr.parse::<Ipv6Addr>() else {
+        return false;
+    };
+    route_get_field(output, "destination").and_then(|d| d.parse().ok()) == Some(addr)
+        && has_flag(output, "BLACKHOLE")
+        && has_flag(output, OWNED_FLAG)
+}
+
+fn route_get_field<'a>(output: &'a str, name: &str) -> Option<&'a str> {
+    output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix(name)?.strip_prefix(':').map(str::trim))
+}
+
+/// Whether the `flags: <UP,DONE,...>` line of `route -n get` output contains `flag`.
+fn has_flag(output: &str, flag: &str) -> bool {
+    route_get_field(output, "flags")
+        .map(|flags| flags.trim_matches(|c| c == '<' || c == '>'))
+        .is_some_and(|flags| flags.split(',').any(|f| f == flag))
+}
+
 /// Returns the first IPv4 address assigned to `device` via `ifconfig`.
 async fn get_interface_address(device: &str) -> Option<Ipv4Addr> {
-    let output = Command::new("ifconfig")
-        .arg(device)
-        .run_stdout(Logs::Suppress)
-        .await
-        .ok()?;
+    let output = Command::new("ifconfig").arg(device).run_stdout(QUERY).await.ok()?;
     for line in output.lines() {
         let tokens: Vec<&str> = line.split_whitespace().collect();
         if tokens.first() == Some(&"inet") {
@@ -183,14 +286,26 @@ mod tests {
         let args = route_add_args("35.213.7.172", Some("192.168.88.1"), "en0");
         assert_eq!(
             args,
-            vec!["-n", "add", "-inet", "35.213.7.172", "192.168.88.1", "-ifp", "en0"]
+            vec![
+                "-n",
+                "add",
+                "-inet",
+                "35.213.7.172",
+                "192.168.88.1",
+                "-ifp",
+                "en0",
+                "-proto1"
+            ]
         );
     }
 
     #[test]
     fn route_add_args_without_gateway() {
         let args = route_add_args("10.0.0.0/8", None, "utun5");
-        assert_eq!(args, vec!["-n", "add", "-inet", "10.0.0.0/8", "-interface", "utun5"]);
+        assert_eq!(
+            args,
+            vec!["-n", "add", "-inet", "10.0.0.0/8", "-interface", "utun5", "-proto1"]
+        );
     }
 
     // Realistic `netstat -rn -f inet` header + rows used across parser tests.
@@ -276,6 +391,70 @@ default            192.168.1.1        UGScg               en0
         assert_eq!(result, Ok(("en0".to_string(), None)));
     }
 
+    // ── route_get_matches ───────────────────────────────────────────────────
+
+    const ROUTE_GET_SPLIT: &str = "\
+   route to: 0.0.0.0
+destination: 0.0.0.0
+       mask: 128.0.0.0
+  interface: utun5
+      flags: <UP,DONE,STATIC,PROTO1>
+";
+
+    #[test]
+    fn route_get_matches_exact_network_route() {
+        assert!(route_get_matches(ROUTE_GET_SPLIT, "0.0.0.0/1", "utun5"));
+        assert!(!route_get_matches(ROUTE_GET_SPLIT, "0.0.0.0/1", "en0"));
+    }
+
+    #[test]
+    fn route_get_rejects_covering_route() {
+        // without the split route `route get` falls back to the default route
+        let output = "\
+   route to: 0.0.0.0
+destination: default
+       mask: default
+    gateway: 192.168.1.1
+  interface: en0
+";
+        assert!(!route_get_matches(output, "0.0.0.0/1", "en0"));
+        assert!(!route_get_matches(ROUTE_GET_SPLIT, "0.0.0.0/8", "utun5"));
+    }
+
+    #[test]
+    fn route_get_matches_host_route_without_mask() {
+        let output = "\
+   route to: 35.213.7.172
+destination: 35.213.7.172
+    gateway: 192.168.1.1
+  interface: en0
+      flags: <UP,GATEWAY,HOST,DONE,STATIC>
+";
+        assert!(route_get_matches(output, "35.213.7.172", "en0"));
+    }
+
+    #[test]
+    fn ownership_is_read_from_route_flags() {
+        assert!(has_flag(ROUTE_GET_SPLIT, OWNED_FLAG));
+        assert!(!has_flag("      flags: <UP,DONE,STATIC>\n", OWNED_FLAG));
+        assert!(!has_flag("      flags: <UP,DONE,STATIC,PROTO2>\n", OWNED_FLAG));
+    }
+
+    #[test]
+    fn route_get_owns_blackhole_requires_exact_owned_blackhole() {
+        let owned = "\
+   route to: ::
+destination: ::
+       mask: 8000::
+  interface: lo0
+      flags: <UP,DONE,STATIC,BLACKHOLE,PROTO1>
+";
+        assert!(route_get_owns_blackhole(owned, "::/1"));
+        assert!(!route_get_owns_blackhole(owned, "8000::/1"));
+        let foreign = owned.replace(",PROTO1", "");
+        assert!(!route_get_owns_blackhole(&foreign, "::/1"));
+    }
+
     #[test]
     fn netstat_excluding_errors_when_no_default_route_remains() {
         let result = parse_netstat_default_excluding("127  127.0.0.1  UCS  lo0\n", "en0");
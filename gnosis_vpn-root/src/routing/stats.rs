@@ -0,0 +1,190 @@
+//! Counters of the route changes done by the static routers, appended to the metrics output.
+//!
+//! Netlink and `route` invocations fail intermittently on some hosts, the counters show how often
+//! without digging through the logs. Counters are kept for the lifetime of the root process.
+
+use async_trait::async_trait;
+
+use gnosis_vpn_lib::metrics::{self, Kind};
+
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::Error;
+use super::route_ops::{RouteOps, WanRoute};
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    routes_added: AtomicU64,
+    routes_removed: AtomicU64,
+    add_errors: AtomicU64,
+    remove_errors: AtomicU64,
+    query_errors: AtomicU64,
+    peer_bypass_changes: AtomicU64,
+    killswitch_reapplied: AtomicU64,
+}
+
+impl Stats {
+    /// A peer bypass route was added or removed after a peer refresh.
+    pub(crate) fn peer_bypass_changed(&self) {
+        self.peer_bypass_changes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The killswitch was re-applied after a network change.
+    pub(crate) fn killswitch_reapplied(&self) {
+        self.killswitch_reapplied.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn write_metrics(&self, out: &mut String) {
+        let get = |counter: &AtomicU64| counter.load(Ordering::Relaxed) as f64;
+        metrics::write(
+            out,
+            "gnosis_vpn_routes_added_total",
+            "Routes and IPv6 blackholes installed by the static router",
+            Kind::Counter,
+            get(&self.routes_added),
+        );
+        metrics::write(
+            out,
+            "gnosis_vpn_routes_removed_total",
+            "Routes and IPv6 blackholes removed by the static router",
+            Kind::Counter,
+            get(&self.routes_removed),
+        );
+        metrics::write_labeled(
+            out,
+            "gnosis_vpn_routing_errors_total",
+            "Failed route operations by kind",
+            Kind::Counter,
+            "operation",
+            [
+                ("add", get(&self.add_errors)),
+                ("remove", get(&self.remove_errors)),
+                ("query", get(&self.query_errors)),
+            ],
+        );
+        metrics::write(
+            out,
+            "gnosis_vpn_peer_bypass_changes_total",
+            "Peer bypass routes added or removed after the tunnel came up",
+            Kind::Counter,
+            get(&self.peer_bypass_changes),
+        );
+        metrics::write(
+            out,
+            "gnosis_vpn_killswitch_reapplied_total",
+            "Killswitch re-applications after network changes",
+            Kind::Counter,
+            get(&self.killswitch_reapplied),
+        );
+    }
+}
+
+/// Route operations counting their outcome in [`Stats`].
+pub(crate) struct Counted<R> {
+    inner: R,
+    stats: Arc<Stats>,
+}
+
+impl<R: RouteOps> Counted<R> {
+    pub(crate) fn new(inner: R, stats: Arc<Stats>) -> Self {
+        Self { inner, stats }
+    }
+
+    /// Drops an owned leftover of `dest` before adding it again, failing is expected when there is none.
+    pub(crate) async fn clear_stale(&self, dest: &str, device: &str) {
+        let _ = self.inner.route_del(dest, device).await;
+    }
+
+    fn count<T>(&self, res: Result<T, Error>, ok: Option<&AtomicU64>, err: &AtomicU64) -> Result<T, Error> {
+        match &res {
+            Ok(_) => {
+                if let Some(ok) = ok {
+                    ok.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            Err(_) => {
+                err.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        res
+    }
+}
+
+#[async_trait]
+impl<R: RouteOps> RouteOps for Counted<R> {
+    async fn get_wan_route_for(&self, dest: Ipv4Addr, exclude_iface: &str) -> Result<Option<WanRoute>, Error> {
+        let res = self.inner.get_wan_route_for(dest, exclude_iface).await;
+        self.count(res, None, &self.stats.query_errors)
+    }
+
+    async fn get_route_via_device(&self, dest: Ipv4Addr, device: &str) -> Result<Option<WanRoute>, Error> {
+        let res = self.inner.get_route_via_device(dest, device).await;
+        self.count(res, None, &self.stats.query_errors)
+    }
+
+    async fn route_add(&self, dest: &str, gateway: Option<&str>, device: &str) -> Result<(), Error> {
+        let res = self.inner.route_add(dest, gateway, device).await;
+        self.count(res, Some(&self.stats.routes_added), &self.stats.add_errors)
+    }
+
+    async fn route_del(&self, dest: &str, device: &str) -> Result<(), Error> {
+        let res = self.inner.route_del(dest, device).await;
+        self.count(res, Some(&self.stats.routes_removed), &self.stats.remove_errors)
+    }
+
+    async fn route_exists(&self, dest: &str, device: &str) -> Result<bool, Error> {
+        let res = self.inner.route_exists(dest, device).await;
+        self.count(res, None, &self.stats.query_errors)
+    }
+
+    async fn ipv6_enabled(&self) -> bool {
+        self.inner.ipv6_enabled().await
+    }
+
+    async fn ipv6_blackhole_add(&self, dest: &str) -> Result<(), Error> {
+        let res = self.inner.ipv6_blackhole_add(dest).await;
+        self.count(res, Some(&self.stats.routes_added), &self.stats.add_errors)
+    }
+
+    async fn ipv6_blackhole_del(&self, dest: &str) -> Result<(), Error> {
+        let res = self.inner.ipv6_blackhole_del(dest).await;
+        self.count(res, Some(&self.stats.routes_removed), &self.stats.remove_errors)
+    }
+
+    async fn ipv6_blackhole_exists(&self, dest: &str) -> Result<bool, Error> {
+        let res = self.inner.ipv6_blackhole_exists(dest).await;
+        self.count(res, None, &self.stats.query_errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use gnosis_vpn_lib::wireguard::BlockIpv6;
+
+    use crate::routing::add_ipv6_blackholes;
+    use crate::routing::tests::Blackholes;
+
+    #[tokio::test]
+    async fn counts_route_changes() {
+        let stats = Arc::new(Stats::default());
+        let ops = Counted::new(Blackholes::default(), stats.clone());
+        // `::/1` never shows up in the table, so `always` rolls back both blackholes
+        let res = add_ipv6_blackholes(&ops, BlockIpv6::Always).await;
+        assert!(res.is_err());
+        add_ipv6_blackholes(&ops, BlockIpv6::Auto)
+            .await
+            .expect("auto only warns");
+        stats.killswitch_reapplied();
+
+        let mut out = String::new();
+        stats.write_metrics(&mut out);
+        assert!(out.contains("gnosis_vpn_routes_added_total 3\n"));
+        assert!(out.contains("gnosis_vpn_routes_removed_total 2\n"));
+        assert!(out.contains("gnosis_vpn_routing_errors_total{operation=\"add\"} 0\n"));
+        assert!(out.contains("gnosis_vpn_killswitch_reapplied_total 1\n"));
+    }
+}
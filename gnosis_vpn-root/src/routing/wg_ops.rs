@@ -1,42 +1,126 @@
 //! WireGuard interface management abstraction.
 //!
-//! Defines [`WgOps`] trait for wg-quick operations.
-//! This is the only routing abstraction that still uses an external CLI tool
-//! (wg-quick).
+//! Defines [`WgOps`] trait for bringing the tunnel interface up and down without routes.
+//! On Linux the interface is configured over netlink ([`NetlinkWgOps`]), `wg-quick` ([`WgQuickOps`])
+//! is only used on macOS, for a configured replacement and for the wg-quick DNS strategy.
 //!
-//! Production code uses [`RealWgOps`].
+//! Production code uses the [`AnyWgOps`] picked by [`select`].
 
 use async_trait::async_trait;
 use std::path::PathBuf;
 
 use gnosis_vpn_lib::shell_command_ext::Logs;
+use gnosis_vpn_lib::{event, wireguard};
 
 use super::Error;
 
+#[cfg(target_os = "linux")]
+use super::wg_ops_linux::NetlinkWgOps;
+
 use crate::wg_tooling;
 
 /// Abstraction over WireGuard interface management.
 #[async_trait]
 pub trait WgOps: Send + Sync {
-    /// Bring up WireGuard via wg-quick. Returns the resolved interface name.
-    async fn wg_quick_up(&self, state_home: PathBuf, config: String) -> Result<String, Error>;
+    /// Bring up the interface without installing routes. Returns the resolved interface name.
+    async fn up(&self, cache_home: PathBuf, wg_data: &event::WireGuardData) -> Result<String, Error>;
 
-    /// Bring down WireGuard via wg-quick.
-    async fn wg_quick_down(&self, state_home: PathBuf, logs: Logs) -> Result<(), Error>;
+    /// Bring down the interface.
+    async fn down(&self, cache_home: PathBuf, logs: Logs) -> Result<(), Error>;
 }
 
-/// Production [`WgOps`] that delegates to `wg_tooling`.
-pub struct RealWgOps;
+/// [`WgOps`] that delegates to `wg_tooling`, running `wg-quick` with `Table = off`.
+pub struct WgQuickOps {
+    /// Configured `wg-quick` replacement and hooks
+    pub tooling: wireguard::Tooling,
+}
 
 #[async_trait]
-impl WgOps for RealWgOps {
-    async fn wg_quick_up(&self, state_home: PathBuf, config: String) -> Result<String, Error> {
-        let iface = wg_tooling::up(state_home, config).await?;
+impl WgOps for WgQuickOps {
+    async fn up(&self, cache_home: PathBuf, wg_data: &event::WireGuardData) -> Result<String, Error> {
+        let config = wg_data.wg.to_file_string(
+            &wg_data.interface_info,
+            &wg_data.peer_info,
+            vec!["Table = off".to_string()],
+        );
+        let iface = wg_tooling::up(cache_home, config, &self.tooling).await?;
         Ok(iface)
     }
 
-    async fn wg_quick_down(&self, state_home: PathBuf, logs: Logs) -> Result<(), Error> {
-        wg_tooling::down(state_home, logs).await?;
+    async fn down(&self, cache_home: PathBuf, logs: Logs) -> Result<(), Error> {
+        wg_tooling::down(cache_home, &self.tooling, logs).await?;
+        Ok(())
+    }
+}
+
+/// Interface management picked per tunnel, see [`select`].
+pub enum AnyWgOps {
+    WgQuick(WgQuickOps),
+    #[cfg(target_os = "linux")]
+    Netlink(NetlinkWgOps),
+}
+
+/// Netlink unless the tunnel still needs `wg-quick`, see [`crate::wg_tooling::native`].
+#[cfg(target_os = "linux")]
+pub fn select(tooling: wireguard::Tooling, dns_strategy: wireguard::DnsStrategy) -> Result<AnyWgOps, Error> {
+    if wg_tooling::native(&tooling, dns_strategy) {
+        Ok(AnyWgOps::Netlink(NetlinkWgOps::new(tooling)?))
+    } else {
+        Ok(AnyWgOps::WgQuick(WgQuickOps { tooling }))
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn select(tooling: wireguard::Tooling, _dns_strategy: wireguard::DnsStrategy) -> Result<AnyWgOps, Error> {
+    Ok(AnyWgOps::WgQuick(WgQuickOps { tooling }))
+}
+
+#[async_trait]
+impl WgOps for AnyWgOps {
+    async fn up(&self, cache_home: PathBuf, wg_data: &event::WireGuardData) -> Result<String, Error> {
+        match self {
+            AnyWgOps::WgQuick(ops) => ops.up(cache_home, wg_data).await,
+            #[cfg(target_os = "linux")]
+            AnyWgOps::Netlink(ops) => ops.up(cache_home, wg_data).await,
+        }
+    }
+
+    async fn down(&self, cache_home: PathBuf, logs: Logs) -> Result<(), Error> {
+        match self {
+            AnyWgOps::WgQuick(ops) => ops.down(cache_home, logs).await,
+            #[cfg(target_os = "linux")]
+            AnyWgOps::Netlink(ops) => ops.down(cache_home, logs).await,
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    use gnosis_vpn_lib::wireguard::DnsStrategy;
+
+    #[tokio::test]
+    async fn select_keeps_wg_quick_only_where_needed() -> anyhow::Result<()> {
+        let tooling = wireguard::Tooling::default();
+        assert!(matches!(
+            select(tooling.clone(), DnsStrategy::Auto)?,
+            AnyWgOps::Netlink(_)
+        ));
+        assert!(matches!(
+            select(tooling.clone(), DnsStrategy::SystemdResolved)?,
+            AnyWgOps::Netlink(_)
+        ));
+        assert!(matches!(select(tooling, DnsStrategy::WgQuick)?, AnyWgOps::WgQuick(_)));
+
+        let replaced = wireguard::Tooling {
+            wg_quick: Some(PathBuf::from("/opt/wg-quick-wrapper")),
+            ..Default::default()
+        };
+        match select(replaced.clone(), DnsStrategy::Auto)? {
+            AnyWgOps::WgQuick(ops) => assert_eq!(ops.tooling, replaced),
+            AnyWgOps::Netlink(_) => panic!("configured wg-quick replacement ignored"),
+        }
         Ok(())
     }
 }
@@ -0,0 +1,300 @@
+//! Linux WireGuard interface management over netlink.
+//!
+//! [`NetlinkWgOps`] replaces `wg-quick` on Linux: the interface, its address and MTU are set up via
+//! `rtnetlink::Handle`, keys and the peer via the WireGuard generic netlink family. This is what
+//! `wg-quick` with `Table = off` did, minus its shell scripts: no routes are installed, DNS is left to
+//! the root DNS strategies and the private key is never written to disk.
+//!
+//! Without the kernel module the [`Flavor::Userspace`] runs an embedded boringtun device on a TUN
+//! interface of the same name instead, configured over its UAPI socket. [`best_flavor`] picks it
+//! automatically, neither the module nor the `wg` tools are needed then. The device lives as long as
+//! the interface: it is started on `up` and stopped on `down`.
+//!
+//! Hooks run like they do around `wg-quick`, only without `WG_CONFIG_FILE`.
+
+use async_trait::async_trait;
+use boringtun::device::{DeviceConfig, DeviceHandle};
+use tokio::process::Command;
+use wireguard_control::{Backend, DeviceUpdate, InterfaceName, Key, PeerConfigBuilder};
+
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use gnosis_vpn_lib::event;
+use gnosis_vpn_lib::shell_command_ext::{Logs, ShellCommandExt};
+use gnosis_vpn_lib::wireguard::{self, PeerInfo, WireGuard};
+
+use super::Error;
+use super::route_ops_linux::{ifindex, is_errno};
+use super::wg_ops::WgOps;
+
+use crate::wg_tooling;
+
+/// Present once the kernel module is loaded or built in.
+const KERNEL_MODULE: &str = "/sys/module/wireguard";
+
+/// Where the WireGuard protocol runs behind the interface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flavor {
+    /// Kernel module, configured over generic netlink
+    Kernel,
+    /// Embedded boringtun on a TUN device, configured over its UAPI socket
+    Userspace,
+}
+
+impl Flavor {
+    fn backend(self) -> Backend {
+        match self {
+            Flavor::Kernel => Backend::Kernel,
+            Flavor::Userspace => Backend::Userspace,
+        }
+    }
+}
+
+/// The kernel module if it is available or can be loaded, the embedded userspace implementation otherwise.
+pub async fn best_flavor() -> Flavor {
+    let mut modprobe = Command::new("modprobe");
+    modprobe.arg("wireguard");
+    flavor(Path::new(KERNEL_MODULE), modprobe).await
+}
+
+/// Kernel if `module` exists or `modprobe` loads it.
+async fn flavor(module: &Path, mut modprobe: Command) -> Flavor {
+    if module.exists() {
+        return Flavor::Kernel;
+    }
+    match modprobe.run(Logs::Suppress).await {
+        Ok(()) => Flavor::Kernel,
+        Err(error) => {
+            tracing::info!(
+                ?error,
+                "WireGuard kernel module unavailable - using embedded userspace WireGuard"
+            );
+            Flavor::Userspace
+        }
+    }
+}
+
+/// Production [`WgOps`] for Linux backed by an `rtnetlink::Handle`.
+pub struct NetlinkWgOps {
+    /// Hooks, a `wg-quick` replacement never reaches this backend
+    tooling: wireguard::Tooling,
+    handle: rtnetlink::Handle,
+    /// Task driving the netlink connection of `handle`, ends together with the router
+    connection: tokio::task::JoinHandle<()>,
+    /// Running boringtun device of the [`Flavor::Userspace`] interface
+    userspace: Mutex<Option<DeviceHandle>>,
+}
+
+impl NetlinkWgOps {
+    pub fn new(tooling: wireguard::Tooling) -> Result<Self, Error> {
+        let (conn, handle, _) = rtnetlink::new_connection()?;
+        let connection = tokio::task::spawn(conn);
+        Ok(Self {
+            tooling,
+            handle,
+            connection,
+            userspace: Mutex::new(None),
+        })
+    }
+
+    /// Create the interface, for the userspace flavor by starting the boringtun device.
+    async fn create_link(&self, name: &str, flavor: Flavor) -> Result<(), Error> {
+        match flavor {
+            Flavor::Kernel => {
+                self.handle
+                    .link()
+                    .add(rtnetlink::LinkWireguard::new(name).build())
+                    .execute()
+                    .await?;
+            }
+            Flavor::Userspace => {
+                let device = DeviceHandle::new(name, DeviceConfig::default())
+                    .map_err(|e| Error::General(format!("unable to start userspace WireGuard: {e:?}")))?;
+                if let Ok(mut userspace) = self.userspace.lock() {
+                    *userspace = Some(device);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the interface of either flavor, fails if there was none.
+    async fn delete_link(&self, name: &str) -> Result<(), Error> {
+        // dropping the device closes its TUN interface and removes the UAPI socket
+        let device = self.userspace.lock().ok().and_then(|mut userspace| userspace.take());
+        let stopped = device.is_some();
+        drop(device);
+        match ifindex(name) {
+            Ok(index) => match self.handle.link().del(index).execute().await {
+                // the TUN interface of a stopped device can vanish after the lookup
+                Err(e) if is_errno(&e, libc::ENODEV) => Ok(()),
+                res => res.map_err(Error::from),
+            },
+            Err(_) if stopped => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    async fn configure(&self, name: &str, wg_data: &event::WireGuardData, flavor: Flavor) -> Result<(), Error> {
+        let wg = wg_data.wg.clone();
+        let peer = wg_data.peer_info.clone();
+        let iface = name.to_string();
+        // the WireGuard netlink family and the UAPI socket are only spoken blocking
+        tokio::task::spawn_blocking(move || configure_device(&iface, &wg, &peer, flavor.backend()))
+            .await
+            .map_err(|e| Error::General(format!("WireGuard configuration task failed: {e}")))??;
+
+        let index = ifindex(name)?;
+        let (address, prefix_len) = parse_net(&wg_data.interface_info.address)?;
+        self.handle.address().add(index, address, prefix_len).execute().await?;
+        let mtu = wg_data.interface_info.mtu.unwrap_or(wireguard::WG_MTU);
+        self.handle
+            .link()
+            .set(rtnetlink::LinkUnspec::new_with_index(index).mtu(mtu).up().build())
+            .execute()
+            .await?;
+        Ok(())
+    }
+}
+
+impl Drop for NetlinkWgOps {
+    fn drop(&mut self) {
+        self.connection.abort();
+    }
+}
+
+#[async_trait]
+impl WgOps for NetlinkWgOps {
+    async fn up(&self, _cache_home: PathBuf, wg_data: &event::WireGuardData) -> Result<String, Error> {
+        let name = wireguard::WG_INTERFACE;
+        wg_tooling::run_hook("pre_up", self.tooling.pre_up.as_deref(), name, None).await?;
+        // an interface left behind by a crashed service would still carry its old peer
+        if self.delete_link(name).await.is_ok() {
+            tracing::debug!(interface = %name, "removed stale WireGuard interface");
+        }
+        // a UAPI socket left by a crash would make the handshake lookup ask a device that is gone
+        remove_stale_socket(&wg_tooling::userspace_socket(name));
+        let flavor = best_flavor().await;
+        self.create_link(name, flavor).await?;
+        if let Err(error) = self.configure(name, wg_data, flavor).await {
+            let _ = self.delete_link(name).await;
+            return Err(error);
+        }
+        // same as wg-quick: a failing post up hook takes the interface down again
+        if let Err(error) = wg_tooling::run_hook("post_up", self.tooling.post_up.as_deref(), name, None).await {
+            let _ = self.delete_link(name).await;
+            return Err(error.into());
+        }
+        tracing::debug!(interface = %name, ?flavor, "WireGuard interface configured");
+        Ok(name.to_string())
+    }
+
+    async fn down(&self, _cache_home: PathBuf, _logs: Logs) -> Result<(), Error> {
+        let name = wireguard::WG_INTERFACE;
+        if let Err(error) = wg_tooling::run_hook("pre_down", self.tooling.pre_down.as_deref(), name, None).await {
+            tracing::warn!(?error, "pre down hook failed");
+        }
+        // a missing interface is already down
+        let userspace = self.userspace.lock().is_ok_and(|userspace| userspace.is_some());
+        if userspace || ifindex(name).is_ok() {
+            self.delete_link(name).await?;
+        }
+        if let Err(error) = wg_tooling::run_hook("post_down", self.tooling.post_down.as_deref(), name, None).await {
+            tracing::warn!(?error, "post down hook failed");
+        }
+        Ok(())
+    }
+}
+
+/// Private key, listen port and the single peer, replacing any previous peers.
+fn configure_device(name: &str, wg: &WireGuard, peer: &PeerInfo, backend: Backend) -> Result<(), Error> {
+    let iface = InterfaceName::from_str(name).map_err(|e| Error::General(format!("invalid interface name: {e}")))?;
+    let endpoint = SocketAddr::from_str(&peer.endpoint)
+        .map_err(|e| Error::General(format!("invalid WireGuard endpoint {}: {e}", peer.endpoint)))?;
+    let mut peer_config = PeerConfigBuilder::new(&key(&peer.public_key)?)
+        .set_preshared_key(key(&peer.preshared_key)?)
+        .set_endpoint(endpoint)
+        .replace_allowed_ips();
+    for allowed_ip in wg.config.allowed_ips.as_deref().unwrap_or("0.0.0.0/0").split(',') {
+        let (address, cidr) = parse_net(allowed_ip)?;
+        peer_config = peer_config.add_allowed_ip(address, cidr);
+    }
+    let mut update = DeviceUpdate::new()
+        .set_private_key(key(&wg.key_pair.priv_key)?)
+        .replace_peers()
+        .add_peer(peer_config);
+    if let Some(listen_port) = wg.config.listen_port {
+        update = update.set_listen_port(listen_port);
+    }
+    update.apply(&iface, backend)?;
+    Ok(())
+}
+
+fn remove_stale_socket(path: &Path) {
+    match std::fs::remove_file(path) {
+        Ok(()) => tracing::debug!(path = %path.display(), "removed stale userspace WireGuard socket"),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => (),
+        Err(error) => {
+            tracing::warn!(?error, path = %path.display(), "failed to remove stale userspace WireGuard socket")
+        }
+    }
+}
+
+fn key(base64: &str) -> Result<Key, Error> {
+    // never echo key material into errors
+    Key::from_base64(base64.trim()).map_err(|_| Error::General("invalid WireGuard key".to_string()))
+}
+
+/// Parse a network like "10.128.0.2/32", a plain address is a host network.
+fn parse_net(net: &str) -> Result<(IpAddr, u8), Error> {
+    let net = net.trim();
+    let (address, prefix_len) = net.split_once('/').map_or((net, None), |(a, p)| (a, Some(p)));
+    let address =
+        IpAddr::from_str(address).map_err(|e| Error::General(format!("invalid network address {net}: {e}")))?;
+    let max_len = if address.is_ipv4() { 32 } else { 128 };
+    let prefix_len = match prefix_len {
+        Some(len) => len
+            .parse::<u8>()
+            .ok()
+            .filter(|len| *len <= max_len)
+            .ok_or_else(|| Error::General(format!("invalid network prefix length {net}")))?,
+        None => max_len,
+    };
+    Ok((address, prefix_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn networks_parse_like_wg_quick_allowed_ips() -> anyhow::Result<()> {
+        assert_eq!(parse_net(" 0.0.0.0/0")?, (IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0));
+        assert_eq!(
+            parse_net("10.128.0.2/32")?,
+            (IpAddr::V4(Ipv4Addr::new(10, 128, 0, 2)), 32)
+        );
+        assert_eq!(parse_net("10.128.0.2")?.1, 32);
+        assert_eq!(parse_net("fd00::/8")?.1, 8);
+        assert!(parse_net("10.0.0.0/33").is_err());
+        assert!(parse_net("wg0").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_userspace_without_kernel_module() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let module = dir.path().join("wireguard");
+
+        assert_eq!(flavor(&module, Command::new("false")).await, Flavor::Userspace);
+        assert_eq!(flavor(&module, Command::new("true")).await, Flavor::Kernel);
+        std::fs::create_dir(&module)?;
+        assert_eq!(flavor(&module, Command::new("false")).await, Flavor::Kernel);
+        Ok(())
+    }
+}
@@ -12,31 +12,44 @@
 //!   connection. Added and removed by `update_peer_ips`; reset to empty on routing
 //!   teardown. The firewall sees `floor ∪ delta`, so the floor is always allowed even
 //!   when the delta shrinks to zero.
+//!
+//! With kill switch mode enabled a separate block (see `routing::firewall`) covers the time without
+//! a tunnel. It is engaged on every routing teardown and lifted by the worker once connected.
 
 use std::collections::HashSet;
 use std::net::{IpAddr, Ipv4Addr};
 use std::path::PathBuf;
-use std::time::{Duration, Instant};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
+use gnosis_vpn_lib::command::{GatewayClient, RouteExplanation, RoutingExplainResponse, TableExplanation};
 use gnosis_vpn_lib::event;
 use gnosis_vpn_lib::killswitch::Firewall;
 use gnosis_vpn_lib::shell_command_ext::Logs;
-use gnosis_vpn_lib::wireguard;
+use gnosis_vpn_lib::wireguard::{self, BlockIpv6};
+use human_bandwidth::re::bandwidth::Bandwidth;
 use tokio::sync::{mpsc, oneshot};
 use tokio::time;
 use tokio_util::sync::CancellationToken;
 
 use crate::device_monitor::{self, NetworkEvent};
 use crate::routing::{self, Routing};
+use crate::wg_tooling;
 
 const DEBOUNCE_SETTLE: Duration = Duration::from_millis(250);
 const DEBOUNCE_MAX: Duration = Duration::from_secs(1);
 
 pub enum Msg {
     SetupRouting {
-        state_home: PathBuf,
+        cache_home: PathBuf,
         wg_data: Box<event::WireGuardData>,
         peer_ips: Vec<Ipv4Addr>,
+        /// DSCP marking for the worker's transport traffic while the tunnel is up.
+        dscp: Option<routing::dscp::Marking>,
+        /// LAN interface to share the tunnel with while it is up.
+        gateway_lan: Option<String>,
+        /// `wg-quick` replacement and hooks used for this tunnel's setup and teardown.
+        wg_tooling: wireguard::Tooling,
         reply: oneshot::Sender<Result<String, String>>,
     },
     TeardownRouting {
@@ -55,6 +68,41 @@ pub enum Msg {
     UpdatePeerIps {
         peer_ips: Vec<Ipv4Addr>,
     },
+    /// Set or clear the egress rate limit. Remembered across routing setups and applied
+    /// immediately if the tunnel is up.
+    SetRateLimit {
+        limit: Option<Bandwidth>,
+        reply: oneshot::Sender<Result<(), String>>,
+    },
+    /// Query the most recent WireGuard handshake; replies `None` if routing is not set up.
+    LatestHandshake {
+        reply: oneshot::Sender<Option<SystemTime>>,
+    },
+    /// Per client traffic of the local gateway; replies `None` if the gateway is not installed.
+    GatewayClients {
+        reply: oneshot::Sender<Result<Option<Vec<GatewayClient>>, String>>,
+    },
+    /// Routes and firewall tables a managed setup applies next to whether they are installed.
+    /// The DSCP, gateway and IPv6 settings are those the next setup would be given.
+    ExplainRouting {
+        dscp: bool,
+        gateway: bool,
+        block_ipv6: BlockIpv6,
+        reply: oneshot::Sender<Result<RoutingExplainResponse, String>>,
+    },
+    /// Fire-and-forget: enable kill switch mode with the traffic to let through, `None` disables it.
+    /// Engaged right away unless a tunnel is set up.
+    SetKillSwitch {
+        block: Option<routing::firewall::Block>,
+    },
+    /// Fire-and-forget: engage or lift the kill switch block, ignored while kill switch mode is disabled.
+    EngageKillSwitch {
+        engage: bool,
+    },
+    /// Why the kill switch block could not be engaged, `None` unless its last attempt failed.
+    KillSwitchError {
+        reply: oneshot::Sender<Option<String>>,
+    },
 }
 
 /// Returned by `Actor::handle` to tell `run` whether to start or stop the device monitor.
@@ -78,28 +126,60 @@ struct AppliedPolicy {
 /// message queue, so setup, teardown and policy changes cannot interleave.
 struct Actor {
     firewall: Firewall,
+    mode: routing::Mode,
+    /// Route operations the static router uses, resolved at startup.
+    backend: routing::Backend,
     router: Option<Box<dyn Routing + Send>>,
     applied_policy: Option<AppliedPolicy>,
     /// Timestamp of the last `update_peer_ips` observation per IP.
     /// An IP is retained in the allowlist for `PEER_IP_HYSTERESIS_SECS` after last observation.
     peer_ip_last_seen: std::collections::HashMap<Ipv4Addr, Instant>,
+    /// Peer IPs the current routing setup installed bypass routes for; cleared on teardown.
+    setup_peer_ips: Vec<Ipv4Addr>,
     /// Dynamic delta above the static floor: peers discovered after initial connection.
     /// Diffed and reconciled by `update_peer_ips`; reset to empty on routing teardown.
     active_bypass: HashSet<Ipv4Addr>,
     /// Resolved WireGuard interface name (e.g. "utun8" on macOS, "wg0_gnosisvpn" on Linux).
     /// Populated after a successful routing setup; cleared on teardown.
     wg_interface_name: Option<String>,
+    /// Egress rate limit to install on the WireGuard interface whenever routing is set up.
+    rate_limit: Option<Bandwidth>,
+    /// DSCP marking installed alongside the current routing setup; removed on teardown.
+    dscp: Option<routing::dscp::Marking>,
+    /// Local gateway installed alongside the current routing setup; removed on teardown.
+    gateway: Option<routing::gateway::Installed>,
+    /// Tunnel DNS applied alongside the current routing setup; reverted on teardown.
+    dns: Option<routing::dns::Installed>,
+    /// Traffic let through while the tunnel is down, `None` while kill switch mode is disabled.
+    kill_switch: Option<routing::firewall::Block>,
+    /// Kill switch block is installed.
+    block_engaged: bool,
+    /// Failure of the last attempt to engage the kill switch block, reported in status.
+    kill_switch_error: Option<String>,
+    /// Route change counters shared with the metrics output.
+    stats: Arc<routing::Stats>,
 }
 
 impl Actor {
-    fn new() -> Result<Self, String> {
+    fn new(mode: routing::Mode, backend: routing::Backend, stats: Arc<routing::Stats>) -> Result<Self, String> {
         Ok(Actor {
             firewall: Firewall::new().map_err(|e| e.to_string())?,
+            mode,
+            backend,
             router: None,
             applied_policy: None,
             peer_ip_last_seen: std::collections::HashMap::new(),
+            setup_peer_ips: Vec::new(),
             active_bypass: HashSet::new(),
             wg_interface_name: None,
+            rate_limit: None,
+            dscp: None,
+            gateway: None,
+            dns: None,
+            kill_switch: None,
+            block_engaged: false,
+            kill_switch_error: None,
+            stats,
         })
     }
 
@@ -110,12 +190,17 @@ impl Actor {
     async fn handle(&mut self, msg: Msg) -> Option<MonitorAction> {
         match msg {
             Msg::SetupRouting {
-                state_home,
+                cache_home,
                 wg_data,
                 peer_ips,
+                dscp,
+                gateway_lan,
+                wg_tooling,
                 reply,
             } => {
-                let result = self.setup_routing(state_home, *wg_data, peer_ips).await;
+                let result = self
+                    .setup_routing(cache_home, *wg_data, peer_ips, dscp, gateway_lan, wg_tooling)
+                    .await;
                 let _ = reply.send(result);
                 None
             }
@@ -150,6 +235,44 @@ impl Actor {
                 self.update_peer_ips(peer_ips).await;
                 None
             }
+            Msg::SetRateLimit { limit, reply } => {
+                let result = self.set_rate_limit(limit).await;
+                let _ = reply.send(result);
+                None
+            }
+            Msg::LatestHandshake { reply } => {
+                let _ = reply.send(self.latest_handshake().await);
+                None
+            }
+            Msg::GatewayClients { reply } => {
+                let result = match self.gateway {
+                    Some(_) => routing::gateway::clients().await.map(Some).map_err(|e| e.to_string()),
+                    None => Ok(None),
+                };
+                let _ = reply.send(result);
+                None
+            }
+            Msg::ExplainRouting {
+                dscp,
+                gateway,
+                block_ipv6,
+                reply,
+            } => {
+                let _ = reply.send(self.explain_routing(dscp, gateway, block_ipv6).await);
+                None
+            }
+            Msg::SetKillSwitch { block } => {
+                self.set_kill_switch(block);
+                None
+            }
+            Msg::EngageKillSwitch { engage } => {
+                self.engage_kill_switch(engage);
+                None
+            }
+            Msg::KillSwitchError { reply } => {
+                let _ = reply.send(self.kill_switch_error.clone());
+                None
+            }
         }
     }
 
@@ -206,27 +329,83 @@ impl Actor {
 
     async fn setup_routing(
         &mut self,
-        state_home: PathBuf,
+        cache_home: PathBuf,
         wg_data: event::WireGuardData,
         peer_ips: Vec<Ipv4Addr>,
+        dscp: Option<routing::dscp::Marking>,
+        gateway_lan: Option<String>,
+        wg_tooling: wireguard::Tooling,
     ) -> Result<String, String> {
         // ensure clean slate
         self.teardown_routing().await;
-
-        let mut router = match routing::static_router(state_home, wg_data, peer_ips) {
-            Ok(router) => router,
-            Err(error) => {
-                tracing::error!(?error, "failed to build static router");
-                return Err(error.to_string());
+        self.setup_peer_ips = peer_ips.clone();
+        let dns = routing::dns::Dns::from_config(&wg_data.wg.config);
+
+        let mut router: Box<dyn Routing + Send> = match self.mode {
+            routing::Mode::Managed => {
+                match routing::static_router(
+                    cache_home,
+                    wg_data,
+                    peer_ips,
+                    wg_tooling,
+                    self.backend,
+                    self.stats.clone(),
+                ) {
+                    Ok(router) => Box::new(router),
+                    Err(error) => {
+                        tracing::error!(?error, "failed to build static router");
+                        return Err(error.to_string());
+                    }
+                }
             }
+            routing::Mode::Delegated => match routing::delegated_router(cache_home, wg_data, wg_tooling) {
+                Ok(router) => Box::new(router),
+                Err(error) => {
+                    tracing::error!(?error, "failed to build delegated router");
+                    return Err(error.to_string());
+                }
+            },
         };
         let res_setup = router.setup().await;
         // store the router even on setup error so partial state can be torn down
-        self.router = Some(Box::new(router));
+        self.router = Some(router);
         match res_setup {
             Ok(interface_name) => {
                 self.wg_interface_name = Some(interface_name.clone());
                 tracing::info!("static routing setup successfully");
+                if let Some(limit) = self.rate_limit
+                    && let Err(error) = routing::rate_limit::apply(&interface_name, Some(limit)).await
+                {
+                    tracing::warn!(?error, ?limit, "failed to apply egress rate limit after routing setup");
+                }
+                if let Some(marking) = dscp {
+                    // best effort: a missing QoS marking must not prevent the tunnel from coming up
+                    match routing::dscp::apply(marking).await {
+                        Ok(()) => self.dscp = Some(marking),
+                        Err(error) => tracing::warn!(?error, ?marking, "failed to apply DSCP marking"),
+                    }
+                }
+                if let Some(lan_interface) = gateway_lan {
+                    let gateway = routing::gateway::Gateway {
+                        lan_interface,
+                        wg_interface: interface_name.clone(),
+                    };
+                    // the tunnel is still usable by this host if sharing it fails
+                    match routing::gateway::apply(gateway.clone()).await {
+                        Ok(installed) => {
+                            tracing::info!(lan_interface = %gateway.lan_interface, "local gateway installed");
+                            self.gateway = Some(installed);
+                        }
+                        Err(error) => tracing::warn!(?error, ?gateway, "failed to install local gateway"),
+                    }
+                }
+                if let Some(dns) = dns {
+                    // lookups still work through the previous resolver, which is reached via the tunnel
+                    match routing::dns::apply(dns.clone(), &interface_name).await {
+                        Ok(installed) => self.dns = Some(installed),
+                        Err(error) => tracing::warn!(?error, ?dns, "failed to apply tunnel DNS"),
+                    }
+                }
                 Ok(interface_name)
             }
             Err(error) => {
@@ -238,6 +417,10 @@ impl Actor {
     }
 
     async fn teardown_routing(&mut self) {
+        // revert while the interface still exists
+        if let Some(installed) = self.dns.take() {
+            routing::dns::remove(installed).await;
+        }
         if let Some(ref mut router) = self.router {
             for ip in self.active_bypass.drain().collect::<Vec<_>>() {
                 if let Err(e) = router.remove_peer_bypass_route(ip).await {
@@ -246,10 +429,173 @@ impl Actor {
             }
             router.teardown(Logs::Print).await;
         }
+        if self.dscp.take().is_some() {
+            routing::dscp::remove().await;
+        }
+        if let Some(installed) = self.gateway.take() {
+            routing::gateway::remove(installed).await;
+        }
         self.router = None;
         self.wg_interface_name = None;
         self.peer_ip_last_seen.clear();
+        self.setup_peer_ips.clear();
         self.active_bypass.clear();
+        // no tunnel left to carry traffic
+        self.engage_kill_switch(true);
+    }
+
+    fn set_kill_switch(&mut self, block: Option<routing::firewall::Block>) {
+        if block == self.kill_switch {
+            return;
+        }
+        if block.is_none() {
+            self.engage_kill_switch(false);
+            self.kill_switch = None;
+            return;
+        }
+        self.kill_switch = block;
+        // an engaged block is replaced in place, lifting it first would open a gap
+        if self.block_engaged || self.router.is_none() {
+            self.install_block();
+        }
+    }
+
+    fn engage_kill_switch(&mut self, engage: bool) {
+        if self.kill_switch.is_none() || engage == self.block_engaged {
+            // nothing to lift after a failed attempt, but its error is outdated all the same
+            if !engage {
+                self.kill_switch_error = None;
+            }
+            return;
+        }
+        if engage {
+            self.install_block();
+        } else {
+            routing::firewall::remove(&mut self.firewall);
+            tracing::info!("kill switch lifted");
+            self.block_engaged = false;
+            self.kill_switch_error = None;
+        }
+    }
+
+    fn install_block(&mut self) {
+        let Some(block) = &self.kill_switch else {
+            return;
+        };
+        match routing::firewall::apply(&mut self.firewall, block) {
+            Ok(()) => {
+                tracing::info!("kill switch engaged - blocking traffic outside the tunnel");
+                self.block_engaged = true;
+                self.kill_switch_error = None;
+            }
+            Err(error) => {
+                tracing::error!(?error, "failed to engage kill switch");
+                self.kill_switch_error = Some(error.to_string());
+            }
+        }
+    }
+
+    /// Without a tunnel the peers of the next setup are unknown, so only fixed routes are listed.
+    async fn explain_routing(
+        &self,
+        dscp: bool,
+        gateway: bool,
+        block_ipv6: BlockIpv6,
+    ) -> Result<RoutingExplainResponse, String> {
+        let active = self.router.is_some();
+        if self.mode == routing::Mode::Delegated {
+            return Ok(RoutingExplainResponse {
+                managed: false,
+                active,
+                wan_device: None,
+                routes: Vec::new(),
+                tables: Vec::new(),
+            });
+        }
+        let mut peer_ips = self.setup_peer_ips.clone();
+        peer_ips.extend(self.active_bypass.iter().filter(|ip| !self.setup_peer_ips.contains(ip)));
+        let wg_interface = self.wg_interface_name.as_deref().unwrap_or(wireguard::WG_INTERFACE);
+        let explanation = routing::explain(&peer_ips, wg_interface, block_ipv6, self.backend)
+            .await
+            .map_err(|e| e.to_string())?;
+        let tables = routing::check_tables(self.planned_tables(active, dscp, gateway)).await;
+        Ok(RoutingExplainResponse {
+            managed: true,
+            active,
+            wan_device: explanation.wan.map(|wan| wan.device),
+            routes: explanation
+                .routes
+                .into_iter()
+                .map(|(route, present)| RouteExplanation {
+                    destination: route.dest,
+                    gateway: route.gateway,
+                    device: route.device,
+                    purpose: route.kind.to_string(),
+                    present,
+                })
+                .collect(),
+            tables: tables
+                .into_iter()
+                .map(|(table, present)| TableExplanation {
+                    table: table.kind.table().to_string(),
+                    purpose: table.kind.to_string(),
+                    expected: table.expected,
+                    present,
+                })
+                .collect(),
+        })
+    }
+
+    /// Firewall tables in use, those for DSCP marking and the local gateway come and go with the tunnel.
+    fn planned_tables(&self, active: bool, dscp: bool, gateway: bool) -> Vec<routing::PlannedTable> {
+        let planned = [
+            (routing::TableKind::Dscp, dscp, active),
+            (routing::TableKind::Gateway, gateway, active),
+            (
+                routing::TableKind::Killswitch,
+                self.applied_policy.is_some(),
+                self.applied_policy.is_some(),
+            ),
+            (
+                routing::TableKind::KillSwitchBlock,
+                self.kill_switch.is_some(),
+                self.block_engaged,
+            ),
+        ];
+        planned
+            .into_iter()
+            .filter(|(_, in_use, _)| *in_use)
+            .map(|(kind, _, expected)| routing::PlannedTable { kind, expected })
+            .collect()
+    }
+
+    async fn set_rate_limit(&mut self, limit: Option<Bandwidth>) -> Result<(), String> {
+        self.rate_limit = limit;
+        let Some(interface) = self.wg_interface_name.as_deref() else {
+            tracing::debug!("routing not set up - egress rate limit will be applied on next setup");
+            return Ok(());
+        };
+        match routing::rate_limit::apply(interface, limit).await {
+            Ok(()) => {
+                tracing::info!(?limit, %interface, "egress rate limit applied");
+                Ok(())
+            }
+            Err(error) => {
+                tracing::error!(?error, "failed to apply egress rate limit");
+                Err(error.to_string())
+            }
+        }
+    }
+
+    async fn latest_handshake(&self) -> Option<SystemTime> {
+        let interface = self.wg_interface_name.as_deref()?;
+        match wg_tooling::latest_handshake(interface).await {
+            Ok(handshake) => handshake,
+            Err(error) => {
+                tracing::debug!(?error, "failed to query latest WireGuard handshake");
+                None
+            }
+        }
     }
 
     async fn update_peer_ips(&mut self, peer_ips: Vec<Ipv4Addr>) {
@@ -268,13 +614,15 @@ impl Actor {
 
         if let Some(ref mut router) = self.router {
             for ip in alive.difference(&self.active_bypass).copied().collect::<Vec<_>>() {
-                if let Err(e) = router.add_peer_bypass_route(ip).await {
-                    tracing::warn!(error = %e, peer_ip = %ip, "failed to add dynamic peer bypass route");
+                match router.add_peer_bypass_route(ip).await {
+                    Ok(()) => self.stats.peer_bypass_changed(),
+                    Err(e) => tracing::warn!(error = %e, peer_ip = %ip, "failed to add dynamic peer bypass route"),
                 }
             }
             for ip in self.active_bypass.difference(&alive).copied().collect::<Vec<_>>() {
-                if let Err(e) = router.remove_peer_bypass_route(ip).await {
-                    tracing::warn!(error = %e, peer_ip = %ip, "failed to remove dynamic peer bypass route");
+                match router.remove_peer_bypass_route(ip).await {
+                    Ok(()) => self.stats.peer_bypass_changed(),
+                    Err(e) => tracing::warn!(error = %e, peer_ip = %ip, "failed to remove dynamic peer bypass route"),
                 }
             }
         }
@@ -342,15 +690,21 @@ impl Actor {
             .collect::<std::collections::BTreeSet<_>>()
             .into_iter()
             .collect();
-        if let Err(error) = self
+        match self
             .firewall
             .reapply_policy(&policy.interface, &combined, policy.lan_lockdown)
         {
-            tracing::warn!(?error, "failed to re-apply killswitch after network change");
+            Ok(()) => self.stats.killswitch_reapplied(),
+            Err(error) => tracing::warn!(?error, "failed to re-apply killswitch after network change"),
         }
     }
 
     async fn teardown(&mut self) {
+        // the daemon is going away, nothing left to guard
+        if self.block_engaged {
+            self.engage_kill_switch(false);
+        }
+        self.kill_switch = None;
         self.teardown_routing().await;
         if let Err(error) = self.firewall.reset_policy() {
             tracing::warn!(?error, "failed to reset killswitch policy on shutdown");
@@ -361,8 +715,11 @@ impl Actor {
 pub fn start(
     cancel: CancellationToken,
     reconnect_tx: mpsc::Sender<()>,
+    mode: routing::Mode,
+    backend: routing::Backend,
+    stats: Arc<routing::Stats>,
 ) -> Result<(mpsc::Sender<Msg>, tokio::task::JoinHandle<()>), String> {
-    let actor = Actor::new()?;
+    let actor = Actor::new(mode, backend, stats)?;
     let (sender, receiver) = mpsc::channel(32);
     let handle = tokio::spawn(run(actor, receiver, cancel, reconnect_tx));
     Ok((sender, handle))
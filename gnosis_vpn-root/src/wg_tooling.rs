@@ -1,15 +1,38 @@
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
+#[cfg(target_os = "linux")]
+use wireguard_control::{Backend, Device, InterfaceName};
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, SystemTime};
+#[cfg(target_os = "linux")]
+use std::{io, str::FromStr};
 
-use gnosis_vpn_lib::shell_command_ext::{Logs, ShellCommandExt};
+use gnosis_vpn_lib::shell_command_ext::{Logs, RunOptions, ShellCommandExt};
 use gnosis_vpn_lib::{dirs, wireguard};
 
-pub async fn available() -> Result<(), wireguard::Error> {
+/// UAPI sockets of userspace WireGuard implementations, boringtun and wireguard-go alike.
+#[cfg(target_os = "linux")]
+const USERSPACE_SOCKET_DIR: &str = "/var/run/wireguard";
+/// Hooks run as root, so they only get a fixed `PATH` and the variables set here.
+const HOOK_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+/// `wg-quick` runs `resolvconf` and the hooks of its config, give it more room than a plain command.
+const WG_QUICK_TIMEOUT: Duration = Duration::from_secs(60);
+/// `wg show` only queries the kernel and is polled, a slow answer is treated as a failed check.
+#[cfg(target_os = "macos")]
+const WG_SHOW_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Whether the interface is configured over netlink. wg-quick is only needed on macOS, for a
+/// configured replacement and for its own DNS handling.
+pub fn native(tooling: &wireguard::Tooling, dns_strategy: wireguard::DnsStrategy) -> bool {
+    cfg!(target_os = "linux") && tooling.wg_quick.is_none() && !dns_strategy.uses_wg_quick()
+}
+
+pub async fn available(tooling: &wireguard::Tooling) -> Result<(), wireguard::Error> {
     let out = Command::new("which")
-        .arg("wg-quick")
+        .arg(tooling.wg_quick())
         .run_stdout(Logs::Print)
         .await
         .map_err(wireguard::Error::from)?;
@@ -17,18 +40,23 @@ pub async fn available() -> Result<(), wireguard::Error> {
     Ok(())
 }
 
-pub async fn executable() -> Result<(), wireguard::Error> {
-    Command::new("wg-quick")
+pub async fn executable(tooling: &wireguard::Tooling) -> Result<(), wireguard::Error> {
+    Command::new(tooling.wg_quick())
         .arg("-h")
         .spawn_no_capture()
         .await
         .map_err(wireguard::Error::from)
 }
 
-/// Write the WireGuard config to a file and bring up the interface using `wg-quick`.
+/// Write the WireGuard config to a file and bring up the interface using `wg-quick` or the
+/// configured replacement, surrounded by the `pre_up` and `post_up` hooks.
 /// Returns created interface name on success.
-pub async fn up(state_home: PathBuf, config_content: String) -> Result<String, wireguard::Error> {
-    let conf_file = dirs::cache_dir(state_home, wireguard::WG_CONFIG_FILE);
+pub async fn up(
+    cache_home: PathBuf,
+    config_content: String,
+    tooling: &wireguard::Tooling,
+) -> Result<String, wireguard::Error> {
+    let conf_file = dirs::cache_dir(cache_home, wireguard::WG_CONFIG_FILE);
     let content = config_content.as_bytes();
 
     // Remove stale config so mode() applies to a fresh file (O_CREAT only sets mode on creation)
@@ -44,16 +72,58 @@ pub async fn up(state_home: PathBuf, config_content: String) -> Result<String, w
     file.write_all(content).await?;
     file.flush().await?;
 
-    Command::new("wg-quick")
+    run_hook(
+        "pre_up",
+        tooling.pre_up.as_deref(),
+        wireguard::WG_INTERFACE,
+        Some(&conf_file),
+    )
+    .await?;
+    Command::new(tooling.wg_quick())
         .arg("up")
-        .arg(conf_file)
-        .run(Logs::Print)
+        .arg(&conf_file)
+        .run(RunOptions::new(Logs::Print).timeout(WG_QUICK_TIMEOUT))
         .await?;
 
     let iface_name = resolve_interface_name().await;
+    // same as wg-quick: a failing post up hook takes the interface down again
+    if let Err(error) = run_hook("post_up", tooling.post_up.as_deref(), &iface_name, Some(&conf_file)).await {
+        let _ = Command::new(tooling.wg_quick())
+            .arg("down")
+            .arg(&conf_file)
+            .run(RunOptions::new(Logs::Suppress).timeout(WG_QUICK_TIMEOUT))
+            .await;
+        return Err(error);
+    }
     Ok(iface_name)
 }
 
+/// Run a hook command through `sh` with a sanitized environment, `WG_CONFIG_FILE` is only set for wg-quick.
+pub async fn run_hook(
+    name: &str,
+    hook: Option<&str>,
+    interface: &str,
+    conf_file: Option<&Path>,
+) -> Result<(), wireguard::Error> {
+    let Some(hook) = hook else {
+        return Ok(());
+    };
+    tracing::debug!(%name, %hook, %interface, "running WireGuard hook");
+    let mut command = Command::new("/bin/sh");
+    command
+        .arg("-c")
+        .arg(hook)
+        .env_clear()
+        .env("PATH", HOOK_PATH)
+        .env("WG_INTERFACE", interface)
+        .stdin(Stdio::null());
+    if let Some(conf_file) = conf_file {
+        command.env("WG_CONFIG_FILE", conf_file);
+    }
+    command.run(Logs::Print).await?;
+    Ok(())
+}
+
 /// Resolve the real WireGuard interface name.
 ///
 /// On macOS, `wg-quick` creates `utunN` interfaces and stores the mapping in
@@ -80,8 +150,111 @@ pub async fn resolve_interface_name() -> String {
     wireguard::WG_INTERFACE.to_string()
 }
 
-pub async fn down(state_home: PathBuf, logs: Logs) -> Result<(), wireguard::Error> {
-    let conf_file = dirs::cache_dir(state_home, wireguard::WG_CONFIG_FILE);
-    Command::new("wg-quick").arg("down").arg(conf_file).run(logs).await?;
+/// UAPI socket a userspace implementation running `interface` listens on.
+#[cfg(target_os = "linux")]
+pub fn userspace_socket(interface: &str) -> PathBuf {
+    Path::new(USERSPACE_SOCKET_DIR).join(format!("{interface}.sock"))
+}
+
+/// Most recent handshake of any peer on `interface`, `None` if no handshake happened yet.
+///
+/// Queried over netlink, or the UAPI socket of the embedded userspace implementation if it runs the interface.
+#[cfg(target_os = "linux")]
+pub async fn latest_handshake(interface: &str) -> Result<Option<SystemTime>, wireguard::Error> {
+    let userspace = userspace_socket(interface).exists();
+    let backend = if userspace { Backend::Userspace } else { Backend::Kernel };
+    let name =
+        InterfaceName::from_str(interface).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    let device = tokio::task::spawn_blocking(move || Device::get(&name, backend))
+        .await
+        .map_err(io::Error::other)??;
+    Ok(device
+        .peers
+        .iter()
+        .filter_map(|peer| peer.stats.last_handshake_time)
+        .max())
+}
+
+/// Most recent handshake of any peer on `interface`, `None` if no handshake happened yet.
+#[cfg(target_os = "macos")]
+pub async fn latest_handshake(interface: &str) -> Result<Option<SystemTime>, wireguard::Error> {
+    let out = Command::new("wg")
+        .args(["show", interface, "latest-handshakes"])
+        .run_stdout(RunOptions::new(Logs::Print).timeout(WG_SHOW_TIMEOUT))
+        .await?;
+    Ok(parse_latest_handshakes(&out))
+}
+
+/// Parse `wg show <if> latest-handshakes` output: one `<public key>\t<unix seconds>` line per peer,
+/// where `0` means no handshake yet.
+#[cfg(any(target_os = "macos", test))]
+fn parse_latest_handshakes(output: &str) -> Option<SystemTime> {
+    output
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .filter_map(|secs| secs.parse::<u64>().ok())
+        .filter(|secs| *secs > 0)
+        .max()
+        .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Bring down the interface, down hooks only warn on failure so teardown always completes.
+pub async fn down(cache_home: PathBuf, tooling: &wireguard::Tooling, logs: Logs) -> Result<(), wireguard::Error> {
+    let conf_file = dirs::cache_dir(cache_home, wireguard::WG_CONFIG_FILE);
+    let iface_name = resolve_interface_name().await;
+    if let Err(error) = run_hook("pre_down", tooling.pre_down.as_deref(), &iface_name, Some(&conf_file)).await {
+        tracing::warn!(?error, "pre down hook failed");
+    }
+    Command::new(tooling.wg_quick())
+        .arg("down")
+        .arg(&conf_file)
+        .run(RunOptions::new(logs).timeout(WG_QUICK_TIMEOUT))
+        .await?;
+    if let Err(error) = run_hook("post_down", tooling.post_down.as_deref(), &iface_name, Some(&conf_file)).await {
+        tracing::warn!(?error, "post down hook failed");
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn native_unless_wg_quick_is_needed() {
+        let tooling = wireguard::Tooling::default();
+        assert_eq!(
+            native(&tooling, wireguard::DnsStrategy::Auto),
+            cfg!(target_os = "linux")
+        );
+        assert_eq!(
+            native(&tooling, wireguard::DnsStrategy::File),
+            cfg!(target_os = "linux")
+        );
+        assert!(!native(&tooling, wireguard::DnsStrategy::WgQuick));
+
+        let replaced = wireguard::Tooling {
+            wg_quick: Some(PathBuf::from("/opt/wg-quick-wrapper")),
+            ..Default::default()
+        };
+        assert!(!native(&replaced, wireguard::DnsStrategy::Auto));
+        assert!(!native(&replaced, wireguard::DnsStrategy::None));
+    }
+
+    #[test]
+    fn latest_handshake_picks_most_recent_peer() {
+        let out = "peerA=\t1700000000\npeerB=\t1700000042\n";
+        assert_eq!(
+            parse_latest_handshakes(out),
+            Some(UNIX_EPOCH + Duration::from_secs(1_700_000_042))
+        );
+    }
+
+    #[test]
+    fn latest_handshake_ignores_peers_without_handshake() {
+        assert_eq!(parse_latest_handshakes("peerA=\t0\n"), None);
+        assert_eq!(parse_latest_handshakes(""), None);
+    }
+}
@@ -1,6 +1,10 @@
 use clap::{Args, Parser, Subcommand};
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use url::Url;
 
+use crate::fixtures::netsim::Scenario;
+
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
@@ -13,6 +17,12 @@ pub struct Cli {
 #[derive(Debug, Subcommand)]
 pub enum Command {
     Download(DownloadArgs),
+    /// Restart the service while connected and verify connectivity through and outside the tunnel afterwards.
+    Restart(RestartArgs),
+    /// Run the service in a network namespace under restricted network conditions (Linux, requires root).
+    NetSim(NetSimArgs),
+    /// Cycle connections, destination switches and config reloads for hours while watching for resource leaks.
+    Soak(SoakArgs),
 }
 
 #[derive(Debug, Clone, Args)]
@@ -86,3 +96,98 @@ pub struct DownloadArgs {
     )]
     pub repetitions: usize,
 }
+
+#[derive(Debug, Clone, Copy, Args)]
+pub struct RestartArgs {
+    /// Seconds to wait for the service to shut down gracefully before it is killed.
+    #[arg(
+        long = "stopTimeout",
+        env = "SYSTEM_TEST_RESTART_STOP_TIMEOUT",
+        value_name = "SECONDS",
+        default_value = "30"
+    )]
+    pub stop_timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct NetSimArgs {
+    /// Scenarios to run, all of them if omitted.
+    #[arg(
+        long = "scenario",
+        env = "SYSTEM_TEST_NETSIM_SCENARIOS",
+        value_name = "SCENARIO",
+        value_delimiter = ','
+    )]
+    pub scenarios: Vec<Scenario>,
+
+    /// HTTP proxy reachable from the namespace, the only egress in the proxied-only scenario.
+    #[arg(long = "netsimProxy", env = "SYSTEM_TEST_NETSIM_PROXY", value_name = "IP:PORT")]
+    pub proxy: Option<SocketAddr>,
+
+    /// Minutes to wait for the expected outcome of each scenario.
+    #[arg(
+        long = "scenarioTimeout",
+        env = "SYSTEM_TEST_NETSIM_SCENARIO_TIMEOUT",
+        value_name = "MINUTES",
+        default_value = "15"
+    )]
+    pub scenario_timeout_mins: u64,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct SoakArgs {
+    /// How long to keep cycling.
+    #[arg(
+        long = "hours",
+        env = "SYSTEM_TEST_SOAK_HOURS",
+        value_name = "HOURS",
+        default_value = "24"
+    )]
+    pub hours: u64,
+
+    /// Pause between two cycles.
+    #[arg(
+        long = "pauseSecs",
+        env = "SYSTEM_TEST_SOAK_PAUSE_SECS",
+        value_name = "SECONDS",
+        default_value = "30"
+    )]
+    pub pause_secs: u64,
+
+    /// Config file rewritten once per cycle to trigger a reload.
+    #[arg(
+        long = "configPath",
+        env = "GNOSISVPN_CONFIG_PATH",
+        value_name = "PATH",
+        default_value = gnosis_vpn_lib::config::DEFAULT_PATH
+    )]
+    pub config_path: PathBuf,
+
+    /// Open file descriptors the service may gain over the baseline.
+    #[arg(long = "maxFdGrowth", env = "SYSTEM_TEST_SOAK_MAX_FD_GROWTH", default_value = "64")]
+    pub max_fd_growth: u64,
+
+    /// Threads the service may gain over the baseline.
+    #[arg(
+        long = "maxThreadGrowth",
+        env = "SYSTEM_TEST_SOAK_MAX_THREAD_GROWTH",
+        default_value = "16"
+    )]
+    pub max_thread_growth: u64,
+
+    /// Tokio tasks the root and worker runtimes may gain over the baseline.
+    #[arg(
+        long = "maxTaskGrowth",
+        env = "SYSTEM_TEST_SOAK_MAX_TASK_GROWTH",
+        default_value = "32"
+    )]
+    pub max_task_growth: u64,
+
+    /// Resident memory in MiB the service may gain over the baseline.
+    #[arg(
+        long = "maxRssGrowthMib",
+        env = "SYSTEM_TEST_SOAK_MAX_RSS_GROWTH_MIB",
+        default_value = "256"
+    )]
+    pub max_rss_growth_mib: u64,
+}
@@ -1,61 +1,10 @@
 use anyhow::Context;
 use std::{path::PathBuf, time::Duration};
-use tokio::time::Instant;
 use tracing::{debug, warn};
 use url::{Url, form_urlencoded::Serializer};
 
 const BASE_DOWNLOAD_URL: &str = "https://speed.cloudflare.com/__down";
 
-/// Result of a single condition evaluation.
-#[derive(Debug)]
-pub enum ConditionCheck<T> {
-    /// Condition not satisfied yet and no partial result available.
-    Pending,
-    /// Condition not satisfied yet but we captured a partial result.
-    PendingWithValue(T),
-    /// Condition satisfied with the provided value.
-    Ready(T),
-}
-
-/// Repeatedly evaluates `check` until it yields a value or the timeout expires.
-pub async fn wait_for_condition<T, F, Fut>(
-    label: &str,
-    timeout: Duration,
-    interval: Duration,
-    mut check: F,
-) -> anyhow::Result<T>
-where
-    T: std::fmt::Debug,
-    F: FnMut() -> Fut,
-    Fut: std::future::Future<Output = anyhow::Result<ConditionCheck<T>>>,
-{
-    let start = Instant::now();
-    let mut last_progress: Option<T> = None;
-
-    loop {
-        debug!("checking condition for {label}");
-        match check().await? {
-            ConditionCheck::Ready(result) => return Ok(result),
-            ConditionCheck::Pending => {}
-            ConditionCheck::PendingWithValue(result) => {
-                last_progress = Some(result);
-            }
-        }
-
-        let elapsed = start.elapsed();
-        if elapsed > timeout {
-            if let Some(result) = last_progress {
-                warn!(%label, "timeout expired, returning last known progress");
-                return Ok(result);
-            }
-            Err(anyhow::anyhow!("timeout on {label}"))?;
-        }
-
-        let sleep_duration = interval.min(timeout.saturating_sub(elapsed));
-        tokio::time::sleep(sleep_duration).await;
-    }
-}
-
 /// Downloads a file of the provided size, optionally routing traffic through a proxy.
 pub async fn download_file(size_bytes: u64, proxy: Option<&Url>) -> anyhow::Result<()> {
     let mut download_url = Url::parse(BASE_DOWNLOAD_URL)?;
@@ -1,3 +1,5 @@
-pub mod control_client;
 pub mod lib;
+pub mod netsim;
+pub mod network;
+pub mod proc_stats;
 pub mod service;
@@ -0,0 +1,338 @@
+use std::fmt::{self, Display};
+use std::fs;
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use anyhow::Context;
+use gnosis_vpn_lib::wireguard::WG_INTERFACE;
+use tracing::{info, warn};
+
+const NAMESPACE: &str = "gnosisvpn-netsim";
+const HOST_VETH: &str = "gvpn-netsim0";
+const NS_VETH: &str = "gvpn-netsim1";
+const HOST_ADDR: &str = "10.203.0.1";
+const NS_ADDR: &str = "10.203.0.2";
+const SUBNET: &str = "10.203.0.0/30";
+const NAT_TABLE: &str = "gnosisvpn_netsim_nat";
+const FILTER_TABLE: &str = "gnosisvpn_netsim";
+const IP_FORWARD: &str = "/proc/sys/net/ipv4/ip_forward";
+
+/// Network conditions the service is exposed to inside the namespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Scenario {
+    /// Masqueraded egress without further restrictions, the baseline
+    Open,
+    /// No unsolicited inbound traffic, only replies to outgoing connections get through
+    RestrictiveNat,
+    /// All outgoing UDP except DNS is dropped
+    BlockedUdp,
+    /// No direct egress, only the configured HTTP proxy is reachable
+    ProxiedOnly,
+}
+
+/// What the client is expected to do under a scenario.
+#[derive(Debug, Clone, Copy)]
+pub enum Expectation {
+    /// The connection is established and no network trouble is hinted at
+    Connects,
+    /// Like [`Expectation::Connects`], after the preferred UDP transport was tried, blocked and
+    /// given up in favour of TCP
+    FallsBackToTcp,
+    /// A hint containing one of these markers is reported
+    Hints(&'static [&'static str]),
+}
+
+impl Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expectation::Connects => write!(f, "connects"),
+            Expectation::FallsBackToTcp => write!(f, "connects over TCP"),
+            Expectation::Hints(markers) => write!(f, "hint: {}", markers.join(" | ")),
+        }
+    }
+}
+
+/// Markers of hints blaming the local network, none of them may show up on a working network.
+pub const NETWORK_HINTS: &[&str] = &["check the internet connection", "UDP traffic may be blocked"];
+
+impl Scenario {
+    pub const ALL: &[Scenario] = &[
+        Scenario::Open,
+        Scenario::RestrictiveNat,
+        Scenario::BlockedUdp,
+        Scenario::ProxiedOnly,
+    ];
+
+    pub fn expectation(&self) -> Expectation {
+        match self {
+            // HOPR only dials out and relays inbound traffic, so a NAT without port mapping is fine
+            Scenario::Open | Scenario::RestrictiveNat => Expectation::Connects,
+            // HOPR falls back to TCP transports when QUIC cannot get through
+            Scenario::BlockedUdp => Expectation::FallsBackToTcp,
+            // the chain provider may be reachable through the proxy, p2p transports never are
+            Scenario::ProxiedOnly => {
+                Expectation::Hints(&["check the internet connection", "Node startup keeps failing"])
+            }
+        }
+    }
+
+    /// nftables ruleset loaded inside the namespace.
+    fn ruleset(&self, proxy: Option<SocketAddr>) -> String {
+        let allow_local = format!("oif \"lo\" accept\n        oifname \"{WG_INTERFACE}\" accept");
+        let (input, output) = match self {
+            Scenario::Open => (String::new(), String::new()),
+            Scenario::RestrictiveNat => (
+                "iif \"lo\" accept\n        ct state established,related accept\n        drop".to_string(),
+                String::new(),
+            ),
+            Scenario::BlockedUdp => (
+                String::new(),
+                format!("{allow_local}\n        udp dport 53 accept\n        meta l4proto udp counter drop"),
+            ),
+            Scenario::ProxiedOnly => {
+                let proxy = proxy
+                    .map(|p| format!("\n        ip daddr {} tcp dport {} accept", p.ip(), p.port()))
+                    .unwrap_or_default();
+                (
+                    String::new(),
+                    format!("{allow_local}\n        udp dport 53 accept{proxy}\n        drop"),
+                )
+            }
+        };
+        format!(
+            "table inet {FILTER_TABLE} {{
+    chain input {{
+        type filter hook input priority 0; policy accept;
+        {input}
+    }}
+    chain output {{
+        type filter hook output priority 0; policy accept;
+        {output}
+    }}
+}}
+"
+        )
+    }
+}
+
+impl Display for Scenario {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Scenario::Open => "open",
+            Scenario::RestrictiveNat => "restrictive NAT",
+            Scenario::BlockedUdp => "blocked UDP",
+            Scenario::ProxiedOnly => "proxied only",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Network namespace reaching the internet through a masqueraded veth pair, torn down on drop.
+pub struct NetNamespace {
+    scenario: Scenario,
+    proxy: Option<SocketAddr>,
+    ip_forward: Option<String>,
+}
+
+impl NetNamespace {
+    /// Creates the namespace and applies the restrictions of `scenario`.
+    /// `proxy` is the only egress allowed in [`Scenario::ProxiedOnly`].
+    pub fn create(scenario: Scenario, proxy: Option<SocketAddr>) -> anyhow::Result<Self> {
+        info!(%scenario, "creating network namespace {NAMESPACE}");
+        // anything left over from an aborted run would make the setup below fail
+        cleanup();
+
+        let namespace = Self {
+            scenario,
+            proxy,
+            ip_forward: fs::read_to_string(IP_FORWARD).ok(),
+        };
+
+        run("ip", &["netns", "add", NAMESPACE])?;
+        run(
+            "ip",
+            &["link", "add", HOST_VETH, "type", "veth", "peer", "name", NS_VETH],
+        )?;
+        run("ip", &["link", "set", NS_VETH, "netns", NAMESPACE])?;
+        run("ip", &["addr", "add", &format!("{HOST_ADDR}/30"), "dev", HOST_VETH])?;
+        run("ip", &["link", "set", HOST_VETH, "up"])?;
+        run_in_ns(&["ip", "link", "set", "lo", "up"])?;
+        run_in_ns(&["ip", "addr", "add", &format!("{NS_ADDR}/30"), "dev", NS_VETH])?;
+        run_in_ns(&["ip", "link", "set", NS_VETH, "up"])?;
+        run_in_ns(&["ip", "route", "add", "default", "via", HOST_ADDR])?;
+
+        fs::write(IP_FORWARD, "1").context("enable IPv4 forwarding")?;
+        run_with_input(
+            "nft",
+            &["-f", "-"],
+            &format!(
+                "table ip {NAT_TABLE} {{
+    chain postrouting {{
+        type nat hook postrouting priority 100; policy accept;
+        ip saddr {SUBNET} masquerade
+    }}
+}}
+"
+            ),
+        )?;
+
+        let resolv_dir = resolv_dir();
+        fs::create_dir_all(&resolv_dir).context("create namespace resolv.conf directory")?;
+        fs::write(
+            resolv_dir.join("resolv.conf"),
+            "nameserver 1.1.1.1\nnameserver 8.8.8.8\n",
+        )
+        .context("write namespace resolv.conf")?;
+
+        run_with_input(
+            "ip",
+            &["netns", "exec", NAMESPACE, "nft", "-f", "-"],
+            &scenario.ruleset(proxy),
+        )?;
+        Ok(namespace)
+    }
+
+    pub fn name(&self) -> &str {
+        NAMESPACE
+    }
+
+    /// Established TCP connections leaving the namespace, loopback and the proxy excluded.
+    pub fn external_tcp_flows(&self) -> anyhow::Result<usize> {
+        let output = Command::new("ip")
+            .args([
+                "netns",
+                "exec",
+                NAMESPACE,
+                "ss",
+                "-H",
+                "-n",
+                "-t",
+                "state",
+                "established",
+            ])
+            .output()
+            .context("run ss in namespace")?;
+        anyhow::ensure!(output.status.success(), "ss failed with {}", output.status);
+        Ok(parse_peer_addrs(&String::from_utf8_lossy(&output.stdout))
+            .filter(|peer| !peer.ip().is_loopback() && Some(*peer) != self.proxy)
+            .count())
+    }
+
+    /// Outgoing UDP packets dropped by the scenario ruleset so far.
+    pub fn dropped_udp_packets(&self) -> anyhow::Result<u64> {
+        let output = Command::new("ip")
+            .args([
+                "netns",
+                "exec",
+                NAMESPACE,
+                "nft",
+                "list",
+                "chain",
+                "inet",
+                FILTER_TABLE,
+                "output",
+            ])
+            .output()
+            .context("list namespace output chain")?;
+        anyhow::ensure!(output.status.success(), "nft failed with {}", output.status);
+        Ok(parse_counter_packets(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Environment for processes started inside the namespace.
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        match (self.scenario, self.proxy) {
+            (Scenario::ProxiedOnly, Some(proxy)) => vec![
+                ("HTTP_PROXY", format!("http://{proxy}")),
+                ("HTTPS_PROXY", format!("http://{proxy}")),
+            ],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Drop for NetNamespace {
+    fn drop(&mut self) {
+        info!(scenario = %self.scenario, "removing network namespace {NAMESPACE}");
+        cleanup();
+        if let Some(previous) = &self.ip_forward
+            && let Err(error) = fs::write(IP_FORWARD, previous)
+        {
+            warn!(?error, "failed to restore IPv4 forwarding setting");
+        }
+    }
+}
+
+/// Peer addresses of `ss -H -n state established` output, where the state column is left out.
+fn parse_peer_addrs(output: &str) -> impl Iterator<Item = SocketAddr> + '_ {
+    output.lines().filter_map(|line| {
+        let (ip, port) = line.split_whitespace().nth(3)?.rsplit_once(':')?;
+        let ip: IpAddr = ip.trim_start_matches('[').trim_end_matches(']').parse().ok()?;
+        Some(SocketAddr::new(ip.to_canonical(), port.parse().ok()?))
+    })
+}
+
+/// Sums the packets of all counters in an nft chain listing.
+fn parse_counter_packets(listing: &str) -> u64 {
+    listing
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .windows(2)
+        .filter(|pair| pair[0] == "packets")
+        .filter_map(|pair| pair[1].parse::<u64>().ok())
+        .sum()
+}
+
+/// Removes everything [`NetNamespace::create`] sets up, ignoring what does not exist.
+fn cleanup() {
+    // deleting the namespace also removes the veth pair
+    let _ = run_quiet("ip", &["netns", "del", NAMESPACE]);
+    let _ = run_quiet("ip", &["link", "del", HOST_VETH]);
+    let _ = run_quiet("nft", &["delete", "table", "ip", NAT_TABLE]);
+    let _ = fs::remove_dir_all(resolv_dir());
+}
+
+/// `ip netns exec` bind mounts files from here over `/etc`.
+fn resolv_dir() -> PathBuf {
+    PathBuf::from("/etc/netns").join(NAMESPACE)
+}
+
+fn run(program: &str, args: &[&str]) -> anyhow::Result<()> {
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .with_context(|| format!("run {program} {}", args.join(" ")))?;
+    anyhow::ensure!(status.success(), "{program} {} failed with {status}", args.join(" "));
+    Ok(())
+}
+
+fn run_in_ns(args: &[&str]) -> anyhow::Result<()> {
+    let mut full = vec!["netns", "exec", NAMESPACE];
+    full.extend_from_slice(args);
+    run("ip", &full)
+}
+
+fn run_quiet(program: &str, args: &[&str]) -> anyhow::Result<()> {
+    let status = Command::new(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    anyhow::ensure!(status.success(), "{program} failed with {status}");
+    Ok(())
+}
+
+fn run_with_input(program: &str, args: &[&str], input: &str) -> anyhow::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("run {program} {}", args.join(" ")))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())?;
+    }
+    let status = child.wait()?;
+    anyhow::ensure!(status.success(), "{program} {} failed with {status}", args.join(" "));
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use std::net::IpAddr;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::debug;
+use url::Url;
+
+const TRACE_URL: &str = "https://speed.cloudflare.com/cdn-cgi/trace";
+
+/// Fetches the public IP seen by an external host, optionally routing the request through a proxy.
+pub async fn public_ip(proxy: Option<&Url>) -> anyhow::Result<IpAddr> {
+    let mut client = reqwest::Client::builder().timeout(Duration::from_secs(30));
+    if let Some(proxy_url) = proxy {
+        client = client.proxy(reqwest::Proxy::all(proxy_url.as_str())?);
+    } else {
+        client = client.no_proxy();
+    }
+
+    let body = client
+        .build()?
+        .get(TRACE_URL)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let ip = parse_trace_ip(&body).ok_or_else(|| anyhow::anyhow!("no ip in trace response from {TRACE_URL}"))?;
+    debug!(%ip, proxied = proxy.is_some(), "resolved public ip");
+    Ok(ip)
+}
+
+/// Extracts the `ip=` line of a Cloudflare trace response.
+fn parse_trace_ip(body: &str) -> Option<IpAddr> {
+    body.lines()
+        .find_map(|line| line.strip_prefix("ip="))
+        .and_then(|ip| ip.trim().parse().ok())
+}
+
+/// Dumps the host routing tables for failure reports, command errors are included in the output.
+pub async fn route_snapshot() -> String {
+    let commands: &[&[&str]] = if cfg!(target_os = "macos") {
+        &[&["netstat", "-rn"]]
+    } else {
+        &[&["ip", "route", "show", "table", "all"], &["ip", "-6", "route", "show"]]
+    };
+
+    let mut snapshot = String::new();
+    for args in commands {
+        snapshot.push_str(&format!("$ {}\n", args.join(" ")));
+        match Command::new(args[0]).args(&args[1..]).output().await {
+            Ok(output) => {
+                snapshot.push_str(&String::from_utf8_lossy(&output.stdout));
+                snapshot.push_str(&String::from_utf8_lossy(&output.stderr));
+            }
+            Err(error) => snapshot.push_str(&format!("failed to run: {error}\n")),
+        }
+    }
+    snapshot
+}
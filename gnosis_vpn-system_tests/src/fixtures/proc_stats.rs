@@ -0,0 +1,99 @@
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::Path;
+
+/// Resource usage of a process and all its descendants, read from `/proc`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcessStats {
+    pub processes: u64,
+    pub fds: u64,
+    pub threads: u64,
+    pub rss_kib: u64,
+    /// Tokio tasks alive in the service runtimes, `/proc` does not know about them so the
+    /// caller fills them in from the service metrics.
+    pub tasks: u64,
+}
+
+impl ProcessStats {
+    /// Collects the stats of `pid` and every process below it, e.g. the worker spawned by the root service.
+    pub fn collect(pid: u32) -> anyhow::Result<Self> {
+        let mut stats = ProcessStats::default();
+        for pid in descendants(pid)? {
+            // processes may exit while we look at them, they no longer hold anything
+            let Ok(status) = fs::read_to_string(format!("/proc/{pid}/status")) else {
+                continue;
+            };
+            let fds = fs::read_dir(format!("/proc/{pid}/fd")).map(|d| d.count()).unwrap_or(0);
+            stats.processes += 1;
+            stats.fds += fds as u64;
+            stats.threads += status_field(&status, "Threads:").unwrap_or(0);
+            stats.rss_kib += status_field(&status, "VmRSS:").unwrap_or(0);
+        }
+        anyhow::ensure!(stats.processes > 0, "process {pid} is not running");
+        Ok(stats)
+    }
+
+    /// Growth relative to `baseline`, negative values are clamped to zero.
+    pub fn growth_since(&self, baseline: &ProcessStats) -> ProcessStats {
+        ProcessStats {
+            processes: self.processes.saturating_sub(baseline.processes),
+            fds: self.fds.saturating_sub(baseline.fds),
+            threads: self.threads.saturating_sub(baseline.threads),
+            rss_kib: self.rss_kib.saturating_sub(baseline.rss_kib),
+            tasks: self.tasks.saturating_sub(baseline.tasks),
+        }
+    }
+}
+
+impl Display for ProcessStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} processes, {} fds, {} threads, {} tasks, {} MiB rss",
+            self.processes,
+            self.fds,
+            self.threads,
+            self.tasks,
+            self.rss_kib / 1024
+        )
+    }
+}
+
+/// `pid` followed by all its descendants, found by walking the parent pids of every process.
+fn descendants(pid: u32) -> anyhow::Result<Vec<u32>> {
+    let mut parents = Vec::new();
+    for entry in fs::read_dir("/proc")? {
+        let entry = entry?;
+        let Some(child) = entry.file_name().to_str().and_then(|n| n.parse::<u32>().ok()) else {
+            continue;
+        };
+        if let Some(parent) = parent_pid(&entry.path()) {
+            parents.push((child, parent));
+        }
+    }
+
+    let mut tree = vec![pid];
+    let mut idx = 0;
+    while idx < tree.len() {
+        let current = tree[idx];
+        tree.extend(parents.iter().filter(|(_, p)| *p == current).map(|(c, _)| *c));
+        idx += 1;
+    }
+    Ok(tree)
+}
+
+fn parent_pid(proc_dir: &Path) -> Option<u32> {
+    let stat = fs::read_to_string(proc_dir.join("stat")).ok()?;
+    // the command name may contain spaces and parentheses, the fields after it do not
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Leading number of a `/proc/<pid>/status` line, e.g. `VmRSS:   1234 kB`.
+fn status_field(status: &str, key: &str) -> Option<u64> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix(key))
+        .and_then(|value| value.split_whitespace().next())
+        .and_then(|value| value.parse().ok())
+}
@@ -1,21 +1,39 @@
 use std::fs;
 use std::path::Path;
 use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
 
 use crate::cli::SharedArgs;
+use crate::fixtures::netsim::NetNamespace;
 use anyhow::Context;
-use tracing::info;
+use tracing::{info, warn};
 
 pub struct Service;
 
 impl Service {
     /// Spawns the binary with the configuration required for system tests.
     pub fn spawn(binary: &Path, cfg: &SharedArgs, socket_path: &Path) -> anyhow::Result<ServiceGuard> {
+        Self::spawn_with(Command::new(binary), cfg, socket_path)
+    }
+
+    /// Spawns the binary inside `namespace`, with the environment its scenario requires.
+    pub fn spawn_in_namespace(
+        binary: &Path,
+        cfg: &SharedArgs,
+        socket_path: &Path,
+        namespace: &NetNamespace,
+    ) -> anyhow::Result<ServiceGuard> {
+        let mut cmd = Command::new("ip");
+        cmd.arg("netns").arg("exec").arg(namespace.name()).arg(binary);
+        cmd.envs(namespace.env());
+        Self::spawn_with(cmd, cfg, socket_path)
+    }
+
+    fn spawn_with(mut cmd: Command, cfg: &SharedArgs, socket_path: &Path) -> anyhow::Result<ServiceGuard> {
         if let Some(parent) = socket_path.parent() {
             fs::create_dir_all(parent).context("create socket directory")?;
         }
 
-        let mut cmd = Command::new(binary);
         cmd.arg("--hopr-blokli-url")
             .arg(cfg.blokli_url.as_str())
             .arg("--socket-path")
@@ -45,6 +63,38 @@ impl Service {
 
 pub struct ServiceGuard(Child);
 
+impl ServiceGuard {
+    /// Process id of the root service.
+    pub fn id(&self) -> u32 {
+        self.0.id()
+    }
+
+    /// Asks the service to shut down like a service manager would, killing it if it is still running after `timeout`.
+    pub async fn stop(&mut self, timeout: Duration) -> anyhow::Result<()> {
+        let pid = self.0.id().to_string();
+        info!(%pid, "Stopping gnosis-vpn service");
+        Command::new("kill")
+            .arg("-TERM")
+            .arg(&pid)
+            .status()
+            .context("send SIGTERM to service")?;
+
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if let Some(status) = self.0.try_wait()? {
+                info!(%status, "gnosis-vpn service stopped");
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+
+        warn!(?timeout, "service did not stop gracefully, killing it");
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+        Err(anyhow::anyhow!("service did not stop within {timeout:?}"))
+    }
+}
+
 impl Drop for ServiceGuard {
     fn drop(&mut self) {
         let _ = self.0.kill();
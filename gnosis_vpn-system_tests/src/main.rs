@@ -2,6 +2,7 @@ mod cli;
 mod download;
 mod fixtures;
 mod report;
+mod soak;
 mod workflow;
 
 use anyhow::Result;
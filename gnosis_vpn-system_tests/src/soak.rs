@@ -0,0 +1,168 @@
+use anyhow::{Result, anyhow};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use gnosis_vpn_lib::connection::destination::Destination;
+use gnosis_vpn_lib::test_util::ControlClient;
+
+use crate::{
+    cli::SoakArgs,
+    fixtures::proc_stats::ProcessStats,
+    report::{ReportTable, RowStatus},
+};
+
+const CONNECTION_TIMEOUT: Duration = Duration::from_mins(10);
+const DISCONNECTION_TIMEOUT: Duration = Duration::from_secs(30);
+const RELOAD_SETTLE: Duration = Duration::from_secs(5);
+/// Cycles run before the baseline is taken, so caches and connection pools are filled.
+const WARMUP_CYCLES: u64 = 2;
+/// Share of cycles allowed to fail, single failures are expected on public networks.
+const MAX_FAILED_RATIO: f64 = 0.1;
+
+/// Cycles connect, destination switch, config reload and disconnect until `args.hours` passed,
+/// failing as soon as the service grows beyond the allowed fds, threads, tasks or memory.
+pub async fn run_soak(
+    client: &ControlClient,
+    service_pid: u32,
+    destinations: &[Destination],
+    args: &SoakArgs,
+) -> Result<()> {
+    if destinations.is_empty() {
+        return Err(anyhow!("no destinations available to soak"));
+    }
+
+    let deadline = Instant::now() + Duration::from_hours(args.hours);
+    let mut report = ReportTable::new(
+        "sample",
+        &["cycle", "processes", "fds", "threads", "tasks", "rss (MiB)"],
+    );
+    let mut baseline: Option<ProcessStats> = None;
+    let mut peak = ProcessStats::default();
+    let mut cycles: u64 = 0;
+    let mut failed_cycles: u64 = 0;
+    let mut leak = None;
+
+    while Instant::now() < deadline {
+        let first = &destinations[cycles as usize % destinations.len()];
+        let second = &destinations[(cycles as usize + 1) % destinations.len()];
+        cycles += 1;
+
+        if let Err(error) = run_cycle(client, first, second, args).await {
+            error!(cycle = cycles, ?error, "soak cycle failed");
+            failed_cycles += 1;
+        }
+        // leave the service idle before sampling, whatever state the cycle ended in
+        if let Err(error) = client.wait_for_disconnection(DISCONNECTION_TIMEOUT).await {
+            warn!(cycle = cycles, ?error, "service did not disconnect after cycle");
+        }
+        tokio::time::sleep(Duration::from_secs(args.pause_secs)).await;
+
+        let stats = sample(client, service_pid).await?;
+        info!(cycle = cycles, %stats, "soak sample");
+        peak = ProcessStats {
+            processes: peak.processes.max(stats.processes),
+            fds: peak.fds.max(stats.fds),
+            threads: peak.threads.max(stats.threads),
+            rss_kib: peak.rss_kib.max(stats.rss_kib),
+            tasks: peak.tasks.max(stats.tasks),
+        };
+
+        match baseline {
+            None if cycles >= WARMUP_CYCLES => {
+                add_sample(&mut report, "baseline", cycles, &stats, RowStatus::Success);
+                baseline = Some(stats);
+            }
+            None => (),
+            Some(baseline) => {
+                if let Some(reason) = exceeded(&stats.growth_since(&baseline), args) {
+                    add_sample(&mut report, "leak", cycles, &stats, RowStatus::Failure(reason.clone()));
+                    leak = Some(reason);
+                    break;
+                }
+            }
+        }
+    }
+
+    let last = sample(client, service_pid).await?;
+    add_sample(&mut report, "peak", cycles, &peak, RowStatus::Success);
+    add_sample(&mut report, "final", cycles, &last, RowStatus::Success);
+    info!(cycles, failed_cycles, "\n\nSoak resource usage:\n{}", report.render());
+
+    if let Some(reason) = leak {
+        return Err(anyhow!("service resources kept growing: {reason}"));
+    }
+    if failed_cycles as f64 > cycles as f64 * MAX_FAILED_RATIO {
+        return Err(anyhow!("{failed_cycles} of {cycles} soak cycles failed"));
+    }
+    Ok(())
+}
+
+/// Connect to `first`, switch to `second`, reload the config while connected and disconnect.
+async fn run_cycle(client: &ControlClient, first: &Destination, second: &Destination, args: &SoakArgs) -> Result<()> {
+    client.connect(first.id.clone()).await?;
+    client
+        .wait_for_connection_established(first, CONNECTION_TIMEOUT)
+        .await?;
+
+    if second.id != first.id {
+        info!(from = %first, to = %second, "switching destination");
+        client.connect(second.id.clone()).await?;
+        client
+            .wait_for_connection_established(second, CONNECTION_TIMEOUT)
+            .await?;
+    }
+
+    // rewriting the unchanged config triggers the file watcher and a full reload
+    let content = tokio::fs::read(&args.config_path).await?;
+    tokio::fs::write(&args.config_path, content).await?;
+    tokio::time::sleep(RELOAD_SETTLE).await;
+    client
+        .wait_for_connection_established(second, CONNECTION_TIMEOUT)
+        .await?;
+
+    client.wait_for_disconnection(DISCONNECTION_TIMEOUT).await
+}
+
+/// Resource usage of the service process tree including the tasks alive in its runtimes.
+async fn sample(client: &ControlClient, service_pid: u32) -> Result<ProcessStats> {
+    let mut stats = ProcessStats::collect(service_pid)?;
+    stats.tasks = client.alive_tasks().await?;
+    Ok(stats)
+}
+
+/// Describes the first resource growing past its limit.
+fn exceeded(growth: &ProcessStats, args: &SoakArgs) -> Option<String> {
+    if growth.fds > args.max_fd_growth {
+        Some(format!("{} more fds (max {})", growth.fds, args.max_fd_growth))
+    } else if growth.threads > args.max_thread_growth {
+        Some(format!(
+            "{} more threads (max {})",
+            growth.threads, args.max_thread_growth
+        ))
+    } else if growth.tasks > args.max_task_growth {
+        Some(format!("{} more tasks (max {})", growth.tasks, args.max_task_growth))
+    } else if growth.rss_kib / 1024 > args.max_rss_growth_mib {
+        Some(format!(
+            "{} MiB more rss (max {})",
+            growth.rss_kib / 1024,
+            args.max_rss_growth_mib
+        ))
+    } else {
+        None
+    }
+}
+
+fn add_sample(report: &mut ReportTable, label: &str, cycle: u64, stats: &ProcessStats, status: RowStatus) {
+    report.add_row(
+        label,
+        status,
+        vec![
+            cycle.to_string(),
+            stats.processes.to_string(),
+            stats.fds.to_string(),
+            stats.threads.to_string(),
+            stats.tasks.to_string(),
+            (stats.rss_kib / 1024).to_string(),
+        ],
+    );
+}
@@ -1,19 +1,23 @@
 use anyhow::{Result, anyhow};
 use rand::seq::IndexedRandom;
+use std::path::PathBuf;
 use std::time::Duration;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::{
-    cli::{Cli, Command},
+    cli::{Cli, Command, NetSimArgs, RestartArgs},
     download,
     fixtures::{
-        control_client::ControlClient,
         lib,
+        netsim::{Expectation, NETWORK_HINTS, NetNamespace, Scenario},
+        network,
         service::{Service, ServiceGuard},
     },
     report::{ReportTable, RowStatus},
+    soak,
 };
 use gnosis_vpn_lib::connection::destination::Destination;
+use gnosis_vpn_lib::test_util::ControlClient;
 
 const SERVICE_TIMEOUT: Duration = Duration::from_secs(30);
 const SAFE_TIMEOUT: Duration = Duration::from_mins(1);
@@ -23,11 +27,14 @@ const READY_DESTINATIONS_TIMEOUT: Duration = Duration::from_mins(10);
 const CONNECTION_TIMEOUT: Duration = Duration::from_mins(10);
 const FINAL_CONNECTION_TIMEOUT: Duration = Duration::from_mins(10);
 const DISCONNECTION_TIMEOUT: Duration = Duration::from_secs(15);
+const SERVICE_STOP_TIMEOUT: Duration = Duration::from_secs(30);
 
 pub struct SystemTestWorkflow {
     cli: Cli,
     client: ControlClient,
-    _service: ServiceGuard,
+    service: ServiceGuard,
+    gnosis_bin_root: PathBuf,
+    socket_path: PathBuf,
 }
 
 impl SystemTestWorkflow {
@@ -40,11 +47,18 @@ impl SystemTestWorkflow {
         Ok(Self {
             cli,
             client,
-            _service: service,
+            service,
+            gnosis_bin_root,
+            socket_path,
         })
     }
 
-    pub async fn run(self) -> Result<()> {
+    pub async fn run(mut self) -> Result<()> {
+        if let Some(Command::NetSim(args)) = &self.cli.command {
+            let args = args.clone();
+            return self.run_netsim(&args).await;
+        }
+
         self.ensure_daemon_ready().await?;
 
         let readiness = self
@@ -88,6 +102,14 @@ impl SystemTestWorkflow {
 
         match &self.cli.command {
             Some(Command::Download(args)) => download::run_downloads(&self.cli.shared, args).await?,
+            Some(Command::Restart(args)) => {
+                let args = *args;
+                self.verify_restart(&destination, args).await?
+            }
+            Some(Command::Soak(args)) => {
+                soak::run_soak(&self.client, self.service.id(), &successful_destinations, args).await?
+            }
+            Some(Command::NetSim(_)) => unreachable!("network simulation runs on its own"),
             None => info!("no additional commands to run"),
         };
 
@@ -166,4 +188,215 @@ impl SystemTestWorkflow {
         info!("closing connection");
         self.client.wait_for_disconnection(timeout).await
     }
+
+    /// Restarts the service while connected to `destination`, then verifies the tunnel comes back
+    /// with an exit address other than the host's own and the host reaches the internet directly again once
+    /// disconnected.
+    async fn verify_restart(&mut self, destination: &Destination, args: RestartArgs) -> Result<()> {
+        let mut report = ReportTable::new("check", &["public ip"]);
+        let mut snapshots = Vec::new();
+        let proxy = self.cli.shared.proxy.clone();
+
+        let tunnel_before = network::public_ip(proxy.as_ref()).await;
+        let mut tunnel_ips = Vec::new();
+        tunnel_ips.extend(tunnel_before.as_ref().ok().copied());
+        record_check(
+            &mut report,
+            &mut snapshots,
+            "tunnel before restart",
+            tunnel_before.map(|ip| ip.to_string()),
+        )
+        .await;
+
+        info!(dest = %destination, "restarting service while connected");
+        self.service.stop(Duration::from_secs(args.stop_timeout_secs)).await?;
+        self.service = Service::spawn(&self.gnosis_bin_root, &self.cli.shared, &self.socket_path)?;
+
+        let reconnect = async {
+            self.ensure_daemon_ready().await?;
+            self.establish_connection(destination, CONNECTION_TIMEOUT).await
+        }
+        .await
+        .map(|_| "-".to_string());
+        if record_check(&mut report, &mut snapshots, "reconnect after restart", reconnect).await {
+            let tunnel_after = network::public_ip(proxy.as_ref()).await;
+            tunnel_ips.extend(tunnel_after.as_ref().ok().copied());
+            record_check(
+                &mut report,
+                &mut snapshots,
+                "tunnel after restart",
+                tunnel_after.map(|ip| ip.to_string()),
+            )
+            .await;
+        }
+
+        self.close_connection(DISCONNECTION_TIMEOUT).await?;
+        let outside_after = network::public_ip(None).await;
+        let direct_ip = outside_after.as_ref().ok().copied();
+        record_check(
+            &mut report,
+            &mut snapshots,
+            "outside tunnel after restart",
+            outside_after.map(|ip| ip.to_string()),
+        )
+        .await;
+
+        // an exit that shows the host's own address means traffic bypassed the tunnel
+        if let Some(direct_ip) = direct_ip {
+            let leaked = tunnel_ips.iter().any(|ip| *ip == direct_ip);
+            let isolation = if leaked {
+                Err(anyhow!("tunnel traffic left with the direct ip {direct_ip}"))
+            } else {
+                Ok(format!("{direct_ip} not seen through tunnel"))
+            };
+            record_check(
+                &mut report,
+                &mut snapshots,
+                "tunnel ip differs from direct ip",
+                isolation,
+            )
+            .await;
+        }
+
+        info!("\n\nRestart connectivity:\n{}", report.render());
+        if snapshots.is_empty() {
+            return Ok(());
+        }
+        for snapshot in snapshots {
+            error!("\n\n{snapshot}");
+        }
+        Err(anyhow!("connectivity checks after restart failed"))
+    }
+
+    /// Runs the requested scenarios, each against a fresh service inside a restricted network namespace.
+    async fn run_netsim(&mut self, args: &NetSimArgs) -> Result<()> {
+        // scenarios share state and socket with the host service, so it has to go first
+        self.service.stop(SERVICE_STOP_TIMEOUT).await?;
+
+        let scenarios = if args.scenarios.is_empty() {
+            Scenario::ALL.to_vec()
+        } else {
+            args.scenarios.clone()
+        };
+        let timeout = Duration::from_mins(args.scenario_timeout_mins);
+        let mut report = ReportTable::new("scenario", &["expected", "observed"]);
+        let mut failed = false;
+
+        for scenario in scenarios {
+            let expectation = scenario.expectation();
+            let namespace = NetNamespace::create(scenario, args.proxy)?;
+            self.service =
+                Service::spawn_in_namespace(&self.gnosis_bin_root, &self.cli.shared, &self.socket_path, &namespace)?;
+
+            let res = self.verify_scenario(expectation, &namespace, timeout).await;
+            let _ = self.client.stop().await;
+            if let Err(error) = self.service.stop(SERVICE_STOP_TIMEOUT).await {
+                warn!(%scenario, ?error, "service did not stop cleanly");
+            }
+            drop(namespace);
+
+            match res {
+                Ok(observed) => {
+                    info!(%scenario, %observed, "scenario passed");
+                    report.add_row(
+                        scenario.to_string(),
+                        RowStatus::Success,
+                        vec![expectation.to_string(), observed],
+                    );
+                }
+                Err(error) => {
+                    error!(%scenario, ?error, "scenario failed");
+                    failed = true;
+                    report.add_row(
+                        scenario.to_string(),
+                        RowStatus::Failure(format!("{error:?}")),
+                        vec![expectation.to_string(), "-".to_string()],
+                    );
+                }
+            }
+        }
+
+        info!("\n\nNetwork simulation:\n{}", report.render());
+        if failed {
+            Err(anyhow!("one or more network scenarios failed"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Drives the freshly spawned service until `expectation` is met, returning what was observed.
+    async fn verify_scenario(
+        &self,
+        expectation: Expectation,
+        namespace: &NetNamespace,
+        timeout: Duration,
+    ) -> Result<String> {
+        self.client.wait_for_service_running(SERVICE_TIMEOUT).await?;
+        self.client.start().await?;
+
+        match expectation {
+            Expectation::Connects | Expectation::FallsBackToTcp => {
+                self.client.wait_for_node_running(timeout).await?;
+                let readiness = self.client.wait_for_ready_destinations(timeout).await?;
+                let destination = self.select_destination(readiness.ready())?;
+                self.try_connect(&destination, timeout).await?;
+
+                // sampled while connected, the node holds its peer connections at this point
+                let tcp_flows = namespace.external_tcp_flows();
+                let dropped_udp = namespace.dropped_udp_packets();
+                let hints = self.client.status().await?.map(|s| s.hints).unwrap_or_default();
+                self.close_connection(DISCONNECTION_TIMEOUT).await?;
+                let misleading: Vec<String> = hints
+                    .into_iter()
+                    .filter(|hint| NETWORK_HINTS.iter().any(|marker| hint.contains(marker)))
+                    .collect();
+                if !misleading.is_empty() {
+                    return Err(anyhow!(
+                        "connected but hinted at network trouble: {}",
+                        misleading.join("; ")
+                    ));
+                }
+                let location = destination.get_meta("location").unwrap_or("<unknown>".to_string());
+                if matches!(expectation, Expectation::Connects) {
+                    return Ok(format!("connected to {location}"));
+                }
+
+                let (tcp_flows, dropped_udp) = (tcp_flows?, dropped_udp?);
+                if dropped_udp == 0 {
+                    return Err(anyhow!("no UDP was attempted, the fallback to TCP was not exercised"));
+                }
+                if tcp_flows == 0 {
+                    return Err(anyhow!("connected without any TCP connection to a peer"));
+                }
+                Ok(format!(
+                    "connected to {location} over {tcp_flows} TCP connections, {dropped_udp} UDP packets dropped"
+                ))
+            }
+            Expectation::Hints(markers) => self.client.wait_for_hint(markers, timeout).await,
+        }
+    }
+}
+
+/// Adds the outcome of a connectivity check to `report`, capturing the routing tables on failure.
+/// Returns whether the check succeeded.
+async fn record_check(
+    report: &mut ReportTable,
+    snapshots: &mut Vec<String>,
+    label: &str,
+    result: Result<String>,
+) -> bool {
+    match result {
+        Ok(value) => {
+            info!(check = label, %value, "connectivity check succeeded");
+            report.add_row(label, RowStatus::Success, vec![value]);
+            true
+        }
+        Err(error) => {
+            error!(check = label, ?error, "connectivity check failed");
+            report.add_row(label, RowStatus::Failure(format!("{error:?}")), vec!["-".to_string()]);
+            let routes = network::route_snapshot().await;
+            snapshots.push(format!("Routing tables at failed check '{label}':\n{routes}"));
+            false
+        }
+    }
 }
@@ -1,19 +1,21 @@
-use tokio::io::{self, AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter, WriteHalf};
+use tokio::io::{self, BufReader, BufWriter, WriteHalf};
 use tokio::net::UnixStream as TokioUnixStream;
 use tokio::signal::unix::{SignalKind, signal};
-use tokio::sync::{mpsc, oneshot};
-use tokio::task::JoinSet;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time;
 use tokio_util::sync::CancellationToken;
 
 use std::env;
 use std::os::unix::io::FromRawFd;
 use std::os::unix::net::UnixStream;
 use std::process;
+use std::time::Duration;
 
-use gnosis_vpn_lib::core::Core;
+use gnosis_vpn_lib::core::{Core, Error as CoreError, NodeHandle};
 use gnosis_vpn_lib::event::{CoreToWorker, ResponseFromRoot, RootToWorker, WorkerToCore, WorkerToRoot};
 use gnosis_vpn_lib::hopr::hopr_lib;
-use gnosis_vpn_lib::{command, config, logging, socket, worker_params};
+use gnosis_vpn_lib::{command, config, crash, dirs, log_buffer, logging, socket, tls, watchdog, worker_params};
 
 mod cli;
 // Avoid musl's default allocator due to degraded performance
@@ -22,6 +24,9 @@ mod cli;
 #[global_allocator]
 static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
+/// How often the latest log lines are handed to root.
+const LOG_FORWARD_INTERVAL: Duration = Duration::from_secs(1);
+
 struct LoggingHandle {
     reload_handle: logging::LogReloadHandle,
     log_path: std::path::PathBuf,
@@ -31,6 +36,10 @@ struct State {
     log_handle: Option<LoggingHandle>,
     core_task: JoinSet<()>,
     core_cancel: CancellationToken,
+    core_heartbeat: Option<watchdog::Heartbeat>,
+    core_node: Option<NodeHandle>,
+    // hands back the worker-to-core receiver once cancelled
+    core_forwarding: Option<JoinHandle<mpsc::Receiver<WorkerToCore>>>,
     root_socket_writer: BufWriter<WriteHalf<TokioUnixStream>>,
 }
 
@@ -117,27 +126,29 @@ async fn incoming_socket() -> Result<
 
     // splitting unix stream into reader and writer halves
     let (reader_half, writer_half) = io::split(child_stream);
-    let reader = BufReader::new(reader_half);
-    let mut lines_reader = reader.lines();
+    let mut frame_reader = socket::worker::FrameReader::new(BufReader::new(reader_half));
 
     let cancel = CancellationToken::new();
     let owned_cancel = cancel.clone();
     let (sender, receiver) = mpsc::channel(32);
     tokio::spawn(async move {
+        let mut socket_closed = false;
         loop {
             tokio::select! {
-                Ok(Some(line)) = lines_reader.next_line() => {
-                    tracing::debug!(line = %line, "incoming from root service");
-                    let res_cmd = serde_json::from_str::<RootToWorker>(&line);
-                    match res_cmd {
-                        Ok(cmd) => {
-                            let _ = sender.send(cmd).await;
-                        }
-                        Err(err) => {
-                            tracing::error!(error = %err, "failed parsing incoming worker command - ignoring");
-                        }
+                res = frame_reader.next::<RootToWorker>(), if !socket_closed => match res {
+                    Ok(Some(cmd)) => {
+                        tracing::debug!(?cmd, "incoming from root service");
+                        let _ = sender.send(cmd).await;
                     }
-                }
+                    Ok(None) => socket_closed = true,
+                    Err(socket::worker::Error::IO(err)) => {
+                        tracing::error!(error = %err, "error reading from root socket");
+                        socket_closed = true;
+                    }
+                    Err(err) => {
+                        tracing::error!(error = %err, "failed decoding incoming worker command - ignoring");
+                    }
+                },
                 _ = cancel.cancelled() => {
                     tracing::debug!("socket reader received cancellation");
                     break;
@@ -181,27 +192,36 @@ async fn daemon(args: cli::Cli) -> Result<(), exitcode::ExitCode> {
     res
 }
 
+/// Log lines since the last call, root merges them into its buffer for `gnosis_vpn-ctl logs`.
+fn drain_log_lines(receiver: &mut broadcast::Receiver<String>) -> Vec<String> {
+    let mut lines = Vec::new();
+    loop {
+        match receiver.try_recv() {
+            Ok(line) => lines.push(line),
+            Err(broadcast::error::TryRecvError::Lagged(skipped)) => {
+                lines.push(format!("... {skipped} worker lines skipped"));
+            }
+            Err(_) => return lines,
+        }
+    }
+}
+
 async fn send_to_root(
     resp: Box<WorkerToRoot>,
     writer: &mut BufWriter<WriteHalf<TokioUnixStream>>,
 ) -> Result<(), exitcode::ExitCode> {
-    let serialized = serde_json::to_string(&resp).map_err(|err| {
-        tracing::error!(error = ?err, "failed to serialize response");
-        exitcode::DATAERR
-    })?;
-    writer.write_all(serialized.as_bytes()).await.map_err(|err| {
-        tracing::error!(error = ?err, "error writing to stdout");
-        exitcode::IOERR
-    })?;
-    writer.write_all(b"\n").await.map_err(|err| {
-        tracing::error!(error = ?err, "error appending newline to stdout");
-        exitcode::IOERR
-    })?;
-    writer.flush().await.map_err(|err| {
-        tracing::error!(error = ?err, "error flushing stdout");
-        exitcode::IOERR
-    })?;
-    Ok(())
+    socket::worker::write_frame(writer, &resp)
+        .await
+        .map_err(|err| match err {
+            socket::worker::Error::IO(err) => {
+                tracing::error!(error = ?err, "error writing to root socket");
+                exitcode::IOERR
+            }
+            err => {
+                tracing::error!(error = ?err, "failed to serialize response");
+                exitcode::DATAERR
+            }
+        })
 }
 
 fn main() {
@@ -217,6 +237,7 @@ fn main() {
 }
 
 async fn main_inner() {
+    crash::install("worker", None);
     let args = cli::parse();
 
     let _app_nap_token = gnosis_vpn_lib::app_nap::disable("Gnosis VPN session monitoring requires timely execution");
@@ -261,6 +282,9 @@ impl State {
             log_handle,
             core_task: JoinSet::new(),
             core_cancel: CancellationToken::new(),
+            core_heartbeat: None,
+            core_node: None,
+            core_forwarding: None,
             root_socket_writer,
         }
     }
@@ -288,6 +312,14 @@ impl State {
                 )
                 .await
             }
+            RootToWorker::WorkerCommand { cmd, id } if self.core_task.is_empty() => {
+                tracing::warn!(
+                    ?cmd,
+                    id,
+                    "received command from root while core loop is not running - dropping"
+                );
+                IncomingResolution::SustainLoop
+            }
             RootToWorker::WorkerCommand { cmd, id } => {
                 tracing::debug!(?cmd, id, "received command from root");
                 IncomingResolution::RoundtripViaCore(Box::new((cmd, id)))
@@ -345,14 +377,34 @@ impl State {
             return IncomingResolution::SustainLoop;
         }
         tracing::debug!(?config, ?worker_params, "received startup params from root");
+        crash::set_dir(dirs::cache_dir(worker_params.cache_home(), crash::DIR_NAME));
+        if let Err(err) = tls::install(&config.network.tls) {
+            tracing::error!(error = %err, "unusable TLS trust settings");
+            return IncomingResolution::Shutdown(exitcode::CONFIG);
+        }
         let (sender, mut core_to_worker_receiver) = mpsc::channel(32);
         let res_core = Core::init(config, worker_params, target_dest_id, sender).await;
         match (res_core, worker_to_core_receiver_wrapper.take()) {
             (Ok((core, worker_to_core_sender)), Some(mut worker_to_core_receiver)) => {
-                self.core_task.spawn(async move { core.start().await });
+                let heartbeat = core.heartbeat();
+                self.core_node = Some(core.node());
+                let core_abort = self.core_task.spawn(async move { core.start().await });
+                self.core_heartbeat = Some(heartbeat.clone());
+                let watchdog_cancel = self.core_cancel.clone();
+                tokio::spawn(async move {
+                    tokio::select! {
+                        since = watchdog::stalled(&heartbeat) => {
+                            tracing::error!(?since, "core loop stalled - aborting it");
+                            core_abort.abort();
+                        }
+                        _ = watchdog_cancel.cancelled() => {
+                            tracing::debug!("core watchdog received cancellation");
+                        }
+                    }
+                });
                 let owned_cancel = self.core_cancel.clone();
                 // set up message forwarding to work around lifetime ownership of receivers
-                tokio::spawn(async move {
+                self.core_forwarding = Some(tokio::spawn(async move {
                     loop {
                         tokio::select! {
                             Some(cmd) = worker_to_core_receiver.recv() => {
@@ -371,7 +423,8 @@ impl State {
                             }
                         }
                     }
-                });
+                    worker_to_core_receiver
+                }));
                 tracing::info!("core logic initialized and started");
                 IncomingResolution::SustainLoop
             }
@@ -379,6 +432,11 @@ impl State {
                 tracing::error!("failed to initialize core logic - exhausted worker-to-core channel");
                 IncomingResolution::Shutdown(exitcode::SOFTWARE)
             }
+            (Err(CoreError::Preflight(err)), _) => {
+                // a distinct exit code so root can tell a misconfigured node from a crashing worker
+                tracing::error!(error = %err, "core preflight check failed");
+                IncomingResolution::Shutdown(exitcode::CONFIG)
+            }
             (Err(err), _) => {
                 tracing::error!(error = %err, "failed to initialize core logic");
                 IncomingResolution::Shutdown(exitcode::OSERR)
@@ -394,6 +452,8 @@ impl State {
         let (worker_to_core_sender, worker_to_core_receiver) = mpsc::channel::<WorkerToCore>(32);
         let (core_to_worker_sender, mut core_to_worker_receiver) = mpsc::channel::<CoreToWorker>(32);
         let mut worker_to_core_receiver_wrapper = Some(worker_to_core_receiver);
+        let mut log_lines = log_buffer::subscribe();
+        let mut log_forwarding = time::interval(LOG_FORWARD_INTERVAL);
         loop {
             tokio::select! {
                 Some(cmd) = socket_receiver.recv() => match self.incoming_command(cmd, &mut worker_to_core_receiver_wrapper, core_to_worker_sender.clone()).await {
@@ -429,9 +489,21 @@ impl State {
                         send_to_root(Box::new(WorkerToRoot::RequestToRoot(req)), &mut self.root_socket_writer).await?;
                     }
                 },
-                Some(_) = self.core_task.join_next() => {
-                    tracing::info!("shutting down worker daemon after core loop completion");
-                    return Ok(());
+                _ = log_forwarding.tick() => {
+                    let lines = drain_log_lines(&mut log_lines);
+                    if !lines.is_empty() {
+                        send_to_root(Box::new(WorkerToRoot::LogLines(lines)), &mut self.root_socket_writer).await?;
+                    }
+                },
+                Some(res) = self.core_task.join_next() => {
+                    if res.is_err_and(|err| err.is_cancelled()) {
+                        let stalled_for = self.reset_core(&mut worker_to_core_receiver_wrapper).await;
+                        tracing::warn!(?stalled_for, "core loop aborted by watchdog - awaiting re-initialization from root");
+                        send_to_root(Box::new(WorkerToRoot::CoreStalled { stalled_for }), &mut self.root_socket_writer).await?;
+                    } else {
+                        tracing::info!("shutting down worker daemon after core loop completion");
+                        return Ok(());
+                    }
                 },
                 else => {
                     tracing::error!("unexpected channel closure");
@@ -441,6 +513,37 @@ impl State {
         }
     }
 
+    /// Tear down what is left of an aborted core loop so fresh startup params can initialize a new one.
+    async fn reset_core(
+        &mut self,
+        worker_to_core_receiver_wrapper: &mut Option<mpsc::Receiver<WorkerToCore>>,
+    ) -> Duration {
+        let stalled_for = self
+            .core_heartbeat
+            .take()
+            .map(|heartbeat| heartbeat.since_last_beat())
+            .unwrap_or_default();
+        self.core_cancel.cancel();
+        self.core_cancel = CancellationToken::new();
+        if let Some(forwarding) = self.core_forwarding.take() {
+            match forwarding.await {
+                Ok(mut receiver) => {
+                    // drop whatever was meant for the aborted core, closing pending responders
+                    while receiver.try_recv().is_ok() {}
+                    *worker_to_core_receiver_wrapper = Some(receiver);
+                }
+                Err(err) => {
+                    tracing::error!(error = ?err, "worker-core channel forwarding task failed");
+                }
+            }
+        }
+        // the aborted loop never got to stop its node, which still holds the database
+        if let Some(node) = self.core_node.take() {
+            node.shutdown().await;
+        }
+        stalled_for
+    }
+
     async fn teardown(&mut self) {
         // should be already empty from main loop drainage
         self.core_task.shutdown().await;